@@ -0,0 +1,174 @@
+//! Client dry run against a built-in mock server
+//!
+//! Starts a tiny in-process HTTP server that speaks the same API as the
+//! ESP32 firmware, then drives it the way `boid-client` does: a synthetic
+//! fingertip path is sent to `/api/position`, followed by a settings update
+//! and a status query. No camera, OpenCV window or hardware is needed.
+//!
+//! Run with: `cargo run -p boid-client --example dry_run`
+
+use anyhow::Result;
+use boid_shared::{BoidSettings, Position, SettingsUpdate, StatusResponse, TargetPositionUpdate};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// State the mock server keeps, mirroring the firmware's SimulationState
+#[derive(Default)]
+struct MockState {
+    target: Option<Position>,
+    settings: BoidSettings,
+    position_updates: usize,
+}
+
+fn start_mock_server(state: Arc<Mutex<MockState>>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle_request(stream, &state) {
+                eprintln!("[mock] error: {}", e);
+            }
+        }
+    });
+
+    Ok(addr)
+}
+
+fn handle_request(mut stream: TcpStream, state: &Mutex<MockState>) -> Result<()> {
+    let mut buffer = [0u8; 2048];
+    let bytes_read = stream.read(&mut buffer)?;
+    let request = &buffer[..bytes_read];
+
+    let head_end = request
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|pos| pos + 4)
+        .unwrap_or(request.len());
+    let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+    let mut body = request[head_end..].to_vec();
+
+    // reqwest may send the body in a separate packet
+    let content_length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        })
+        .unwrap_or(0);
+    while body.len() < content_length {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&buffer[..n]);
+    }
+
+    let request_line = head.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, response) = {
+        let mut state = state.lock().unwrap();
+        match (method, path) {
+            ("POST", "/api/position") => {
+                let update: TargetPositionUpdate = serde_json::from_slice(&body)?;
+                state.target = update.position;
+                state.position_updates += 1;
+                (200, r#"{"status":"ok"}"#.to_string())
+            }
+            ("POST", "/api/settings") => {
+                let update: SettingsUpdate = serde_json::from_slice(&body)?;
+                state.settings = update.settings;
+                (200, r#"{"status":"ok"}"#.to_string())
+            }
+            ("GET", "/api/status") => {
+                let status = StatusResponse {
+                    boid_count: 20,
+                    fps: 30,
+                    target_active: state.target.is_some(),
+                    controllers: Vec::new(),
+                };
+                (200, serde_json::to_string(&status)?)
+            }
+            _ => (404, r#"{"error":"Not found"}"#.to_string()),
+        }
+    };
+
+    println!("[mock] {} {} -> {}", method, path, status);
+
+    write!(
+        stream,
+        "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let addr = start_mock_server(state.clone())?;
+    let server_url = format!("http://{}", addr);
+    println!("Mock ESP32 listening on {}", server_url);
+
+    let http_client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(1))
+        .build()?;
+
+    // A fingertip circling the middle of a 320x240 camera frame
+    for step in 0..12 {
+        let angle = step as f32 / 12.0 * std::f32::consts::TAU;
+        let position = Position::new(160.0 + 60.0 * angle.cos(), 120.0 + 60.0 * angle.sin());
+        let update = TargetPositionUpdate {
+            position: Some(position),
+        };
+        http_client
+            .post(format!("{}/api/position", server_url))
+            .json(&update)
+            .send()?
+            .error_for_status()?;
+    }
+
+    let settings = SettingsUpdate {
+        settings: BoidSettings {
+            max_speed: 3.0,
+            ..BoidSettings::default()
+        },
+        boid_count: None,
+    };
+    http_client
+        .post(format!("{}/api/settings", server_url))
+        .json(&settings)
+        .send()?
+        .error_for_status()?;
+
+    let status: StatusResponse = http_client
+        .get(format!("{}/api/status", server_url))
+        .send()?
+        .json()?;
+    println!("Status: {:?}", status);
+
+    // Hand leaves the frame: clear the target
+    http_client
+        .post(format!("{}/api/position", server_url))
+        .json(&TargetPositionUpdate { position: None })
+        .send()?
+        .error_for_status()?;
+
+    let state = state.lock().unwrap();
+    println!(
+        "Dry run complete: {} position updates, max_speed now {}, target active: {}",
+        state.position_updates,
+        state.settings.max_speed,
+        state.target.is_some()
+    );
+
+    Ok(())
+}
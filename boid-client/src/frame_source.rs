@@ -0,0 +1,331 @@
+//! Where the client's frames come from
+//!
+//! The capture loop only sees a [`FrameSource`]. Besides a local camera and
+//! the ESP32's stream, a source can be any MJPEG URL or recorded footage: a
+//! directory of images or a video file, both replayed in a loop. Recorded
+//! footage gives the same frames in the same order on every run, so
+//! integration tests and offline development can drive the whole pipeline
+//! without a camera or a device.
+
+use crate::mjpeg::{MjpegStream, RgbFrame};
+use anyhow::{Context, Result};
+use boid_shared::ErrorCode;
+use opencv::{
+    core::{Mat, Scalar, CV_8UC3},
+    imgcodecs,
+    prelude::*,
+    videoio::{self, VideoCapture, VideoCaptureAPIs},
+};
+use std::path::{Path, PathBuf};
+
+/// File extensions an image directory is read for, lowercase
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp"];
+
+/// A `--video-source` value
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceSpec {
+    /// The ESP32 camera's `/stream`
+    Esp32,
+    /// A local camera, by OpenCV device id
+    Device(i32),
+    /// Any MJPEG-over-HTTP stream
+    Mjpeg(String),
+    /// The images in a directory, in file name order
+    Directory(PathBuf),
+    /// A video file OpenCV can decode
+    Video(PathBuf),
+}
+
+impl SourceSpec {
+    /// `esp32`, a camera device id, an `http://` or `https://` MJPEG URL,
+    /// or the path of an image directory or a video file
+    pub fn parse(source: &str) -> Result<Self> {
+        if source == "esp32" {
+            return Ok(Self::Esp32);
+        }
+        if let Ok(id) = source.parse() {
+            return Ok(Self::Device(id));
+        }
+        if source.starts_with("http://") || source.starts_with("https://") {
+            return Ok(Self::Mjpeg(source.to_string()));
+        }
+        let path = PathBuf::from(source);
+        if path.is_dir() {
+            Ok(Self::Directory(path))
+        } else if path.is_file() {
+            Ok(Self::Video(path))
+        } else {
+            anyhow::bail!(
+                "Video source must be 'esp32', a camera device ID (e.g. '0'), an MJPEG URL, \
+                 or an existing image directory or video file, not '{}'",
+                source
+            )
+        }
+    }
+
+    /// Whether frames come from the ESP32's own camera
+    pub fn is_esp32(&self) -> bool {
+        *self == Self::Esp32
+    }
+
+    /// Open the source; the ESP32's stream is read from `server_url`
+    pub fn open(&self, server_url: &str) -> Result<Box<dyn FrameSource>> {
+        let source: Box<dyn FrameSource> = match self {
+            Self::Esp32 => Box::new(MjpegSource::connect(&format!("{}/stream", server_url))?),
+            Self::Device(id) => Box::new(DeviceSource::open(*id)?),
+            Self::Mjpeg(url) => Box::new(MjpegSource::connect(url)?),
+            Self::Directory(dir) => Box::new(ImageDirectory::open(dir)?),
+            Self::Video(path) => Box::new(VideoFile::open(path)?),
+        };
+        Ok(source)
+    }
+}
+
+/// Something that delivers BGR frames
+pub trait FrameSource {
+    /// Block until the next frame and write it to `frame`
+    fn read(&mut self, frame: &mut Mat) -> Result<()>;
+
+    /// Stop capturing, e.g. to let the ESP32 serve requests again
+    fn release(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A local camera, read through OpenCV
+pub struct DeviceSource(VideoCapture);
+
+impl DeviceSource {
+    pub fn open(id: i32) -> Result<Self> {
+        log::info!("Opening local camera device {}...", id);
+        let mut capture = VideoCapture::new(id, VideoCaptureAPIs::CAP_ANY as i32)?;
+        if !capture.is_opened()? {
+            anyhow::bail!(
+                "[{}] Failed to open camera device {}",
+                ErrorCode::CameraInitFailed,
+                id
+            );
+        }
+        // Smaller frames keep hand tracking fast
+        capture.set(videoio::CAP_PROP_FRAME_WIDTH, 640.0)?;
+        capture.set(videoio::CAP_PROP_FRAME_HEIGHT, 480.0)?;
+        log::info!("Successfully opened local camera");
+        Ok(Self(capture))
+    }
+}
+
+impl FrameSource for DeviceSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<()> {
+        self.0.read(frame)?;
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<()> {
+        self.0.release()?;
+        Ok(())
+    }
+}
+
+/// An MJPEG stream, decoded natively
+pub struct MjpegSource(MjpegStream);
+
+impl MjpegSource {
+    pub fn connect(url: &str) -> Result<Self> {
+        log::info!("Opening MJPEG stream from {}...", url);
+        let stream = MjpegStream::connect(url)?;
+        log::info!("Successfully connected to {}", url);
+        Ok(Self(stream))
+    }
+}
+
+impl FrameSource for MjpegSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<()> {
+        rgb_to_mat(&self.0.next_frame()?, frame)
+    }
+
+    fn release(&mut self) -> Result<()> {
+        self.0.close();
+        Ok(())
+    }
+}
+
+/// Copy a decoded frame into `mat` as BGR, reusing its buffer when the size
+/// matches
+pub fn rgb_to_mat(frame: &RgbFrame, mat: &mut Mat) -> Result<()> {
+    let (rows, cols) = (frame.height as i32, frame.width as i32);
+    if mat.rows() != rows || mat.cols() != cols || mat.typ() != CV_8UC3 {
+        *mat = Mat::new_rows_cols_with_default(rows, cols, CV_8UC3, Scalar::all(0.0))?;
+    }
+    let pixels = mat.data_bytes_mut()?;
+    for (bgr, rgb) in pixels.chunks_exact_mut(3).zip(frame.data.chunks_exact(3)) {
+        bgr.copy_from_slice(&[rgb[2], rgb[1], rgb[0]]);
+    }
+    Ok(())
+}
+
+/// Whether `path` has one of the [`IMAGE_EXTENSIONS`], in any case
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+/// The images in a directory, one per frame, starting over after the last
+pub struct ImageDirectory {
+    paths: Vec<PathBuf>,
+    next: usize,
+}
+
+impl ImageDirectory {
+    /// Find the images in `dir`, sorted by file name
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut paths = Vec::new();
+        for entry in
+            std::fs::read_dir(dir).with_context(|| format!("Cannot read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_file() && is_image(&path) {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            anyhow::bail!("{} holds no images", dir.display());
+        }
+        paths.sort();
+        log::info!("Replaying {} images from {}", paths.len(), dir.display());
+        Ok(Self { paths, next: 0 })
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+impl FrameSource for ImageDirectory {
+    fn read(&mut self, frame: &mut Mat) -> Result<()> {
+        let path = &self.paths[self.next];
+        self.next = (self.next + 1) % self.paths.len();
+        *frame = imgcodecs::imread(&path.to_string_lossy(), imgcodecs::IMREAD_COLOR)?;
+        if frame.empty() {
+            anyhow::bail!(
+                "[{}] Cannot decode {}",
+                ErrorCode::CameraCaptureFailed,
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A video file, rewound to the start when it ends
+pub struct VideoFile {
+    capture: VideoCapture,
+    path: PathBuf,
+}
+
+impl VideoFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        let capture =
+            VideoCapture::from_file(&path.to_string_lossy(), VideoCaptureAPIs::CAP_ANY as i32)?;
+        if !capture.is_opened()? {
+            anyhow::bail!(
+                "[{}] Cannot open video {}",
+                ErrorCode::CameraInitFailed,
+                path.display()
+            );
+        }
+        log::info!("Replaying {}", path.display());
+        Ok(Self {
+            capture,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl FrameSource for VideoFile {
+    fn read(&mut self, frame: &mut Mat) -> Result<()> {
+        if self.capture.read(frame)? && !frame.empty() {
+            return Ok(());
+        }
+        self.capture.set(videoio::CAP_PROP_POS_FRAMES, 0.0)?;
+        if self.capture.read(frame)? && !frame.empty() {
+            return Ok(());
+        }
+        anyhow::bail!(
+            "[{}] {} has no frames",
+            ErrorCode::CameraCaptureFailed,
+            self.path.display()
+        )
+    }
+
+    fn release(&mut self) -> Result<()> {
+        self.capture.release()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::Vector;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("boid-frames-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_source_spec() {
+        let dir = temp_dir("parse");
+        let video = dir.join("hand.mp4");
+        std::fs::write(&video, b"").unwrap();
+
+        assert_eq!(SourceSpec::parse("esp32").unwrap(), SourceSpec::Esp32);
+        assert_eq!(SourceSpec::parse("1").unwrap(), SourceSpec::Device(1));
+        assert_eq!(
+            SourceSpec::parse("http://10.0.0.2:81/stream").unwrap(),
+            SourceSpec::Mjpeg("http://10.0.0.2:81/stream".to_string())
+        );
+        assert_eq!(
+            SourceSpec::parse(&dir.to_string_lossy()).unwrap(),
+            SourceSpec::Directory(dir.clone())
+        );
+        assert_eq!(
+            SourceSpec::parse(&video.to_string_lossy()).unwrap(),
+            SourceSpec::Video(video)
+        );
+        assert!(SourceSpec::parse("/no/such/footage").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_image_directory_loops_in_name_order() {
+        let dir = temp_dir("images");
+        for (name, width) in [("b.png", 20), ("a.PNG", 10), ("c.png", 30)] {
+            let image =
+                Mat::new_rows_cols_with_default(8, width, CV_8UC3, Scalar::all(128.0)).unwrap();
+            imgcodecs::imwrite(&dir.join(name).to_string_lossy(), &image, &Vector::new()).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), b"not a frame").unwrap();
+
+        let mut source = ImageDirectory::open(&dir).unwrap();
+        assert_eq!(source.paths().len(), 3);
+        let mut frame = Mat::default();
+        let widths: Vec<i32> = (0..4)
+            .map(|_| {
+                source.read(&mut frame).unwrap();
+                frame.cols()
+            })
+            .collect();
+        assert_eq!(widths, [10, 20, 30, 10]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(ImageDirectory::open(&dir).is_err());
+    }
+}
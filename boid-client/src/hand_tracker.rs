@@ -1,23 +1,117 @@
-use anyhow::Result;
-use boid_shared::{HandLandmarks, Position};
+use anyhow::{Context, Result};
+use boid_hand_detector::artifacts::Candidate;
+use boid_hand_detector::{
+    ArtifactLayer, BlobRejection, BlobShape, FrameArtifacts, HandDetector, Point as PixelPoint,
+};
+use boid_shared::{Frame, HandDetectorBackend, HandLandmarks, PixelFormat, Position};
 use opencv::{
-    core::{self, Mat, Point, Scalar, Size, Vector, BORDER_DEFAULT},
-    imgproc,
-    prelude::MatTraitConst,
+    core::{self, Mat, Point, Scalar, Size, Vector, BORDER_DEFAULT, CV_8UC3, CV_8UC4},
+    imgcodecs, imgproc,
+    prelude::{MatTraitConst, MatTraitConstManual, MatTraitManual},
 };
+use std::path::Path;
+
+/// Hand detectors the client can run on its camera frames, picked with
+/// `--detector`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DetectorKind {
+    /// Skin contours found with OpenCV
+    #[default]
+    Opencv,
+    /// `boid-hand-detector`'s skin detector, the one the ESP32 and the
+    /// browser run
+    Heuristic,
+}
+
+impl DetectorKind {
+    pub fn build(self) -> Result<Box<dyn Detector>> {
+        Ok(match self {
+            DetectorKind::Opencv => Box::new(HandTracker::new()?),
+            DetectorKind::Heuristic => Box::new(HandDetector::new()),
+        })
+    }
+}
+
+/// A [`HandDetectorBackend`] that can record what it saw, for
+/// `--dump-artifacts`
+pub trait Detector: HandDetectorBackend {
+    /// Keep each frame's [`FrameArtifacts`]; stopping drops the last ones
+    fn set_artifacts(&mut self, enabled: bool);
+
+    /// What the last frame's detection saw, while recording
+    fn artifacts(&self) -> Option<&FrameArtifacts>;
+}
+
+impl Detector for HandDetector {
+    fn set_artifacts(&mut self, enabled: bool) {
+        HandDetector::set_artifacts(self, enabled);
+    }
+
+    fn artifacts(&self) -> Option<&FrameArtifacts> {
+        HandDetector::artifacts(self)
+    }
+}
+
+/// An OpenCV camera frame as handed to a detector
+pub fn frame_of(mat: &Mat) -> Result<Frame<'_>> {
+    if mat.typ() != CV_8UC3 {
+        anyhow::bail!("Expected an 8-bit BGR frame");
+    }
+    Frame::new(
+        mat.cols() as usize,
+        mat.rows() as usize,
+        mat.data_bytes()?,
+        PixelFormat::Bgr,
+    )
+    .context("Frame has padded rows")
+}
 
 pub struct HandTracker {
     // Store previous frame for motion detection if needed
     min_contour_area: f64,
+    record_artifacts: bool,
+    /// The last frame's, while recording
+    artifacts: Option<FrameArtifacts>,
 }
 
 impl HandTracker {
     pub fn new() -> Result<Self> {
         Ok(Self {
             min_contour_area: 5000.0, // Minimum area to consider as a hand
+            record_artifacts: false,
+            artifacts: None,
         })
     }
 
+    /// Keep each frame's skin mask, contours and fingertips for
+    /// [`artifacts`](Self::artifacts), in the same form as the heuristic
+    /// detector's; stopping drops the last ones
+    pub fn set_artifacts(&mut self, enabled: bool) {
+        self.record_artifacts = enabled;
+        if !enabled {
+            self.artifacts = None;
+        }
+    }
+
+    /// What the last frame's detection saw, while recording
+    pub fn artifacts(&self) -> Option<&FrameArtifacts> {
+        self.artifacts.as_ref()
+    }
+
+    /// Copy `frame` into a BGR [`Mat`] for [`process_frame`](Self::process_frame)
+    fn to_mat(frame: &Frame<'_>) -> Result<Mat> {
+        let (rows, cols) = (frame.height as i32, frame.width as i32);
+        let mut mat = Mat::new_rows_cols_with_default(rows, cols, CV_8UC3, Scalar::all(0.0))?;
+        let pixels = frame.data.chunks_exact(frame.format.bytes_per_pixel());
+        for (bgr, pixel) in mat.data_bytes_mut()?.chunks_exact_mut(3).zip(pixels) {
+            match frame.format {
+                PixelFormat::Bgr => bgr.copy_from_slice(pixel),
+                PixelFormat::Rgba => bgr.copy_from_slice(&[pixel[2], pixel[1], pixel[0]]),
+            }
+        }
+        Ok(mat)
+    }
+
     /// Process a frame and detect hand landmarks
     /// Returns HandLandmarks if a hand is detected
     pub fn process_frame(&mut self, frame: &Mat) -> Result<Option<HandLandmarks>> {
@@ -96,14 +190,62 @@ impl HandTracker {
         }
 
         // If we found a large enough contour, extract hand landmarks
-        if let Some(idx) = max_contour_idx {
-            if max_area > self.min_contour_area {
-                let contour = &contours.get(idx)?;
-                return self.extract_hand_landmarks(contour, frame);
-            }
+        let chosen = max_contour_idx.filter(|_| max_area > self.min_contour_area);
+        let hand = match chosen {
+            Some(idx) => self.extract_hand_landmarks(&contours.get(idx)?, frame)?,
+            None => None,
+        };
+
+        if self.record_artifacts {
+            self.artifacts = Some(self.record(&mask, &contours, chosen, hand.clone())?);
+        }
+        Ok(hand)
+    }
+
+    /// The mask and contours of a frame as [`FrameArtifacts`]; contours
+    /// too small to be the hand count as rejected for their area
+    fn record(
+        &self,
+        mask: &Mat,
+        contours: &Vector<Vector<Point>>,
+        chosen: Option<usize>,
+        hand: Option<HandLandmarks>,
+    ) -> Result<FrameArtifacts> {
+        let (width, height) = (mask.cols() as usize, mask.rows() as usize);
+        let mut artifacts = FrameArtifacts::new(width, height);
+        for (skin, &value) in artifacts.skin_mask.iter_mut().zip(mask.data_bytes()?) {
+            *skin = value > 127;
         }
 
-        Ok(None)
+        let frame_area = (width * height) as f64;
+        for contour in contours.iter() {
+            let area = imgproc::contour_area(&contour, false)?;
+            let rect = imgproc::bounding_rect(&contour)?;
+            let mut hull = Vector::<Point>::new();
+            imgproc::convex_hull(&contour, &mut hull, false, true)?;
+            let hull_area = imgproc::contour_area(&hull, false)?;
+            let (w, h) = (rect.width.max(1), rect.height.max(1));
+            let corner = |x: i32, y: i32| PixelPoint::new(x as usize, y as usize);
+            let shape = BlobShape {
+                pixel_count: area as usize,
+                min: corner(rect.x, rect.y),
+                max: corner(rect.x + w - 1, rect.y + h - 1),
+                aspect_ratio: w.min(h) as f32 / w.max(h) as f32,
+                solidity: if hull_area > 0.0 {
+                    (area / hull_area) as f32
+                } else {
+                    0.0
+                },
+                area_fraction: (area / frame_area) as f32,
+            };
+            artifacts.candidates.push(Candidate {
+                shape,
+                rejection: (area <= self.min_contour_area).then_some(BlobRejection::AreaFraction),
+            });
+        }
+        artifacts.chosen = chosen;
+        artifacts.hand = hand;
+        Ok(artifacts)
     }
 
     /// Extract thumb and index finger positions from hand contour
@@ -206,3 +348,45 @@ impl HandTracker {
         Ok(Some(HandLandmarks::new(thumb_tip, index_tip)))
     }
 }
+
+impl HandDetectorBackend for HandTracker {
+    /// No hand when OpenCV fails on the frame
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        Self::to_mat(frame)
+            .and_then(|mat| self.process_frame(&mat))
+            .unwrap_or_else(|e| {
+                log::warn!("Hand detection failed: {}", e);
+                None
+            })
+    }
+}
+
+impl Detector for HandTracker {
+    fn set_artifacts(&mut self, enabled: bool) {
+        HandTracker::set_artifacts(self, enabled);
+    }
+
+    fn artifacts(&self) -> Option<&FrameArtifacts> {
+        HandTracker::artifacts(self)
+    }
+}
+
+/// Write every [`ArtifactLayer`] of a frame to `dir` as
+/// `<frame>_<layer>.png`, with the frame number zero-padded so the files
+/// sort in order
+pub fn write_artifacts(artifacts: &FrameArtifacts, dir: &Path, frame: u64) -> Result<()> {
+    let (rows, cols) = (artifacts.height as i32, artifacts.width as i32);
+    let mut rgba = Mat::new_rows_cols_with_default(rows, cols, CV_8UC4, Scalar::all(0.0))?;
+    let mut bgra = Mat::default();
+    for layer in ArtifactLayer::ALL {
+        rgba.data_bytes_mut()?
+            .copy_from_slice(&artifacts.render(layer));
+        imgproc::cvt_color(&rgba, &mut bgra, imgproc::COLOR_RGBA2BGRA, 0)?;
+        let path = dir.join(format!("{:06}_{}.png", frame, layer.name()));
+        let written = imgcodecs::imwrite(&path.to_string_lossy(), &bgra, &Vector::new())?;
+        if !written {
+            anyhow::bail!("Cannot write {}", path.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! The ESP32's HTTP API as a [`Transport`]
+//!
+//! The socket transports live in `boid_shared::transport`; this one is here
+//! because it sits on reqwest, like the client's other requests.
+
+use boid_shared::transport::{Transport, TransportError, DEFAULT_TIMEOUT};
+use boid_shared::{
+    DepthUpdate, ErrorBody, ParameterScalesUpdate, SettingsUpdate, StatusResponse,
+    TargetPositionUpdate,
+};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::Serialize;
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+
+/// One HTTP request per message to the ESP32's API
+///
+/// Requests block, so send from outside the tokio runtime's async tasks,
+/// e.g. on its blocking pool. Clones share one connection pool.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: Client,
+    server_url: String,
+    timeout: Duration,
+}
+
+impl HttpTransport {
+    /// Talk to `server_url`, e.g. `http://192.168.1.100`
+    pub fn new(server_url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            server_url: server_url.trim_end_matches('/').to_string(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn post(&self, path: &str, body: &impl Serialize) -> Result<(), TransportError> {
+        let url = format!("{}{}", self.server_url, path);
+        self.request(self.client.post(url).json(body)).map(drop)
+    }
+
+    /// Send `request` and return a 2xx response
+    fn request(&self, request: RequestBuilder) -> Result<Response, TransportError> {
+        let response = request.timeout(self.timeout).send().map_err(unreachable)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let error = response.json::<ErrorBody>().ok();
+        Err(TransportError::Rejected {
+            status: status.as_u16(),
+            code: error.as_ref().map(|error| error.code),
+            message: error.map_or_else(String::new, |error| error.error),
+        })
+    }
+}
+
+/// A request that got no answer, as the I/O error it comes down to
+fn unreachable(e: reqwest::Error) -> TransportError {
+    let kind = if e.is_timeout() {
+        ErrorKind::TimedOut
+    } else {
+        ErrorKind::Other
+    };
+    TransportError::Io(io::Error::new(kind, e))
+}
+
+impl Transport for HttpTransport {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn send_target(&mut self, update: &TargetPositionUpdate) -> Result<(), TransportError> {
+        self.post("/api/position", update)
+    }
+
+    fn send_settings(&mut self, update: &SettingsUpdate) -> Result<(), TransportError> {
+        self.post("/api/settings", update)
+    }
+
+    fn send_depth(&mut self, update: &DepthUpdate) -> Result<(), TransportError> {
+        self.post("/api/depth", update)
+    }
+
+    fn send_scales(&mut self, update: &ParameterScalesUpdate) -> Result<(), TransportError> {
+        self.post("/api/scales", update)
+    }
+
+    fn fetch_status(&mut self) -> Result<StatusResponse, TransportError> {
+        let url = format!("{}/api/status", self.server_url);
+        self.request(self.client.get(url))?
+            .json()
+            .map_err(|e| TransportError::Protocol(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boid_shared::{BoidSettings, ErrorCode, Position};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    /// Serve one canned response per connection, handing each request out
+    fn serve(responses: Vec<String>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::channel();
+        thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let n = stream.read(&mut request).unwrap();
+                requests
+                    .send(String::from_utf8_lossy(&request[..n]).into_owned())
+                    .unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    fn response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn test_http_transport_posts_and_reports_error_codes() {
+        let (url, requests) = serve(vec![
+            response("200 OK", r#"{"success":true}"#),
+            response(
+                "400 Bad Request",
+                r#"{"code":"E-CFG-01","error":"max_speed is not finite"}"#,
+            ),
+            response(
+                "200 OK",
+                r#"{"boid_count":20,"fps":30,"target_active":true}"#,
+            ),
+        ]);
+        let mut transport = HttpTransport::new(&url);
+
+        let update = TargetPositionUpdate {
+            position: Some(Position::new(1.0, 2.0)),
+        };
+        transport.send_target(&update).unwrap();
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /api/position HTTP/1.1\r\n"));
+        assert!(request.ends_with(r#"{"position":{"x":1.0,"y":2.0}}"#));
+
+        let error = transport
+            .send_settings(&SettingsUpdate {
+                settings: BoidSettings::default(),
+                boid_count: None,
+            })
+            .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidSetting);
+        assert_eq!(error.to_string(), "[E-CFG-01] max_speed is not finite");
+        assert!(requests.recv().unwrap().starts_with("POST /api/settings "));
+
+        let status = transport.fetch_status().unwrap();
+        assert_eq!((status.boid_count, status.fps), (20, 30));
+        assert!(requests.recv().unwrap().starts_with("GET /api/status "));
+    }
+}
@@ -1,2 +1,12 @@
 // Library exports for testing
+#[cfg(feature = "opencv")]
+pub mod frame_source;
+#[cfg(feature = "opencv")]
 pub mod hand_tracker;
+pub mod http;
+pub mod mjpeg;
+pub mod profile;
+pub mod scene;
+pub mod sender;
+pub mod status;
+pub mod stereo;
@@ -1,24 +1,61 @@
 use anyhow::{Context, Result};
-use boid_shared::{Position, TargetPositionUpdate};
+use boid_core::mapping::{InputSignals, MappingTable};
+use boid_core::{ConfigParameter, ParameterScales, Vector2D};
+use boid_shared::transport::{Transport as _, UdpTransport, WebSocketTransport};
+use boid_shared::{
+    stereo, udp, ControlMessage, DepthUpdate, ErrorBody, ErrorCode, HandDetectorBackend,
+    HandLandmarks, LandmarkSmoother, LensCalibration, ParameterScalesUpdate, Position,
+    PresetUpdate, StreamPowerResponse, TargetPositionUpdate,
+};
 use clap::Parser;
 use opencv::{
     core::{Mat, Point, Scalar},
     highgui, imgproc,
     prelude::*,
-    videoio::{self, VideoCapture, VideoCaptureAPIs},
 };
-use std::time::Instant;
-
-use boid_client::hand_tracker::HandTracker;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+use boid_client::frame_source::{FrameSource, SourceSpec};
+use boid_client::hand_tracker::{self, Detector, DetectorKind};
+use boid_client::http::HttpTransport;
+use boid_client::profile::{Profile, ProfileStore};
+use boid_client::scene::{self, ScenePreset, SceneWatcher};
+use boid_client::sender::{self, Outbox};
+use boid_client::status::StatusPoller;
+use boid_client::stereo::{Pose, StereoDepth};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Boid client with hand tracking", long_about = None)]
 struct Args {
-    /// ESP32 server URL (e.g., http://192.168.1.100)
+    /// ESP32 server URL (e.g., http://192.168.1.100); required unless the
+    /// profile sets it
     #[arg(short, long)]
-    server: String,
+    server: Option<String>,
+
+    /// Load server, calibration, smoothing, scene and preset from a saved
+    /// profile; flags given as well override it
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Save the server, calibration, smoothing, scene and preset flags given
+    /// into this profile, keeping its other settings, then exit
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["profile", "list_profiles", "delete_profile"])]
+    save_profile: Option<String>,
 
-    /// Video stream source: 'esp32' to stream from ESP32 camera, or camera device ID (e.g., '0' for local camera)
+    /// List the saved profiles and exit
+    #[arg(long)]
+    list_profiles: bool,
+
+    /// Delete a saved profile and exit
+    #[arg(long, value_name = "NAME")]
+    delete_profile: Option<String>,
+
+    /// Video source: 'esp32' to stream from the ESP32 camera, a camera
+    /// device ID (e.g. '0' for a local camera), any MJPEG URL, or recorded
+    /// footage to replay in a loop: a directory of images or a video file
     #[arg(short = 'v', long, default_value = "esp32")]
     video_source: String,
 
@@ -29,108 +66,405 @@ struct Args {
     /// Show camera window
     #[arg(short = 'w', long, default_value = "true")]
     show_window: bool,
+
+    /// Stream updates over a persistent WebSocket instead of one HTTP request each
+    #[arg(long)]
+    websocket: bool,
+
+    /// Send positions as UDP datagrams for the lowest latency on a LAN; the
+    /// ESP32 must be built with its `udp` feature
+    #[arg(long, conflicts_with = "websocket")]
+    udp: bool,
+
+    /// Port of the ESP32's UDP target channel
+    #[arg(long, default_value_t = udp::DEFAULT_PORT)]
+    udp_port: u16,
+
+    /// Radial lens distortion coefficient k1 (negative for barrel
+    /// distortion) [default: 0]
+    #[arg(long, allow_negative_numbers = true)]
+    k1: Option<f32>,
+
+    /// Radial lens distortion coefficient k2 [default: 0]
+    #[arg(long, allow_negative_numbers = true)]
+    k2: Option<f32>,
+
+    /// Landmark smoothing: weight of each new detection, from 1 (off) down
+    /// towards 0 (smoothest, but laggiest) [default: 0.5]
+    #[arg(long)]
+    smoothing: Option<f32>,
+
+    /// Scene file (TOML) whose settings are sent to the ESP32 at startup and
+    /// again whenever the file is saved
+    #[arg(long)]
+    scene: Option<PathBuf>,
+
+    /// Scene file (TOML) whose settings are sent to the ESP32 once at
+    /// startup, before `--scene`; a saved profile keeps the settings
+    /// themselves as the device's preferred preset
+    #[arg(long, value_name = "FILE")]
+    preset: Option<PathBuf>,
+
+    /// Second camera, placed to the right of the first, for stereo hand
+    /// depth: any `--video-source`. Depth scales the seek radius.
+    #[arg(long)]
+    stereo: Option<String>,
+
+    /// Stereo calibration file; press 'n' and 'f' in the camera window with
+    /// the hand near and far to write it
+    #[arg(long, default_value = "stereo.toml")]
+    stereo_calibration: PathBuf,
+
+    /// How often to poll the ESP32's /api/status for the overlay, in
+    /// milliseconds (0 disables)
+    #[arg(long, default_value_t = 1000)]
+    status_interval_ms: u64,
+
+    /// Warn when the ESP32 reports a frame rate below this
+    #[arg(long, default_value_t = 20)]
+    min_server_fps: u32,
+
+    /// Hand detector to run on the camera frames
+    #[arg(long, value_enum, default_value_t = DetectorKind::Opencv)]
+    detector: DetectorKind,
+
+    /// Write the detector's skin mask, candidate blobs and their composite
+    /// for every frame into this directory as PNGs
+    #[arg(long, value_name = "DIR")]
+    dump_artifacts: Option<PathBuf>,
+}
+
+impl Args {
+    /// The flags a profile can hold, as given on the command line
+    fn profile_flags(&self) -> Result<Profile> {
+        let preset = match self.preset {
+            Some(ref path) => Some(
+                scene::load_preset(path)
+                    .with_context(|| format!("Failed to load preset {}", path.display()))?,
+            ),
+            None => None,
+        };
+        Ok(Profile {
+            server: self.server.clone(),
+            k1: self.k1,
+            k2: self.k2,
+            smoothing: self.smoothing,
+            scene: self.scene.clone(),
+            preset,
+        })
+    }
+}
+
+/// Where frames come from, and what looks for the hand in them
+struct CameraSources {
+    primary: SourceSpec,
+    detector: DetectorKind,
+    /// Second camera of a stereo pair
+    stereo: Option<SourceSpec>,
+    stereo_calibration: PathBuf,
+}
+
+/// The second camera of a stereo pair and its depth calibration
+struct StereoInput {
+    camera: Box<dyn FrameSource>,
+    tracker: Box<dyn Detector>,
+    frame: Mat,
+    depth: StereoDepth,
+    /// Disparity of the last frame pair where both cameras saw the hand
+    last_disparity: Option<f32>,
+}
+
+impl StereoInput {
+    /// Read the second camera and estimate the depth of `hand`, as seen by
+    /// the first camera. The frame is read even without a hand so the
+    /// camera's buffer does not fall behind.
+    fn measure(&mut self, hand: Option<&HandLandmarks>, width: f32) -> Result<Option<f32>> {
+        self.camera.read(&mut self.frame)?;
+        self.last_disparity = None;
+        let Some(hand) = hand else {
+            return Ok(None);
+        };
+        if self.frame.empty() {
+            return Ok(None);
+        }
+        let Some(other) = self.tracker.detect(&hand_tracker::frame_of(&self.frame)?) else {
+            return Ok(None);
+        };
+        let disparity = stereo::disparity(hand, width, &other, self.frame.cols() as f32);
+        self.last_disparity = Some(disparity);
+        Ok(self.depth.depth(disparity))
+    }
+
+    /// Take the current disparity as the calibration sample for `pose`
+    fn record(&mut self, pose: Pose) {
+        let Some(disparity) = self.last_disparity else {
+            log::warn!("Stereo calibration needs the hand in view of both cameras");
+            return;
+        };
+        match self.depth.record(pose, disparity) {
+            Ok(Some(calibration)) => log::info!(
+                "Stereo calibration saved to {} ({:?})",
+                self.depth.path().display(),
+                calibration
+            ),
+            Ok(None) => log::info!("Recorded {:?} pose (disparity {:.3})", pose, disparity),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+}
+
+/// How position updates reach the ESP32
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transport {
+    /// One `POST /api/position` per update
+    Http,
+    WebSocket,
+    /// Datagrams to the given port
+    Udp(u16),
 }
 
 struct BoidClient {
     server_url: String,
-    camera: VideoCapture,
-    hand_tracker: HandTracker,
+    camera: Box<dyn FrameSource>,
+    detector: Box<dyn Detector>,
+    /// Damps detector jitter before positions are sent
+    smoother: LandmarkSmoother,
     http_client: reqwest::blocking::Client,
     last_position: Option<Position>,
     show_window: bool,
+    /// Lens correction applied locally; the ESP32 corrects its own camera
+    calibration: LensCalibration,
+    /// WebSocket or UDP link for updates, until `run` hands it to the
+    /// network task; HTTP carries what it cannot, and everything once it
+    /// fails
+    link: Option<sender::Link>,
+    /// Whether frames come from the ESP32 stream, whose camera is powered
+    /// down again on exit
+    remote_camera: bool,
+    /// Scene file being watched for edits
+    scene: Option<SceneWatcher>,
+    /// The scene's input mappings, and the multipliers last sent from them
+    mappings: MappingTable,
+    last_scales: ParameterScales,
+    /// Fingertip midpoint on the last frame with a hand, for its speed
+    last_hand: Option<(Vector2D, Instant)>,
+    /// Second camera, when running in stereo mode
+    stereo: Option<StereoInput>,
+    last_depth: Option<f32>,
+    /// Server telemetry shown in the camera window
+    status: Option<Arc<StatusPoller>>,
+    /// Directory detector artifacts are written to, and frames written so far
+    artifact_dump: Option<(PathBuf, u64)>,
 }
 
 impl BoidClient {
-    fn new(server_url: String, video_source: &str, show_window: bool) -> Result<Self> {
-        let camera = if video_source == "esp32" {
-            // Stream from ESP32 camera via MJPEG endpoint
-            let stream_url = format!("{}/stream", server_url);
-            log::info!("Opening ESP32 camera stream from {}...", stream_url);
-
-            let cam = VideoCapture::from_file(&stream_url, VideoCaptureAPIs::CAP_ANY as i32)?;
-
-            if !cam.is_opened()? {
-                anyhow::bail!(
-                    "Failed to open ESP32 camera stream at {}. \
-                    Make sure the ESP32 is running and camera streaming is enabled.",
-                    stream_url
-                );
-            }
+    fn new(
+        server_url: String,
+        sources: CameraSources,
+        show_window: bool,
+        calibration: LensCalibration,
+        transport: Transport,
+        smoother: LandmarkSmoother,
+    ) -> Result<Self> {
+        let http_client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(1))
+            .build()?;
 
-            log::info!("Successfully connected to ESP32 camera stream");
-            cam
-        } else {
-            // Use local camera device
-            let camera_id: i32 = video_source
-                .parse()
-                .context("Video source must be 'esp32' or a camera device ID (e.g., '0')")?;
+        let remote_camera = sources.primary.is_esp32();
+        let camera = open_camera(&sources.primary, &http_client, &server_url)?;
 
-            log::info!("Opening local camera device {}...", camera_id);
-            let mut cam = VideoCapture::new(camera_id, VideoCaptureAPIs::CAP_ANY as i32)?;
+        log::info!("Initializing {:?} hand detector...", sources.detector);
+        let detector = sources.detector.build()?;
 
-            if !cam.is_opened()? {
-                anyhow::bail!("Failed to open camera device {}", camera_id);
+        let stereo = match sources.stereo {
+            Some(ref source) => {
+                let depth = StereoDepth::load(&sources.stereo_calibration)?;
+                if depth.calibration().is_none() {
+                    log::warn!(
+                        "{} not found; press 'n' with the hand near the cameras and 'f' with it far away to calibrate",
+                        depth.path().display()
+                    );
+                }
+                Some(StereoInput {
+                    camera: open_camera(source, &http_client, &server_url)?,
+                    tracker: sources.detector.build()?,
+                    frame: Mat::default(),
+                    depth,
+                    last_disparity: None,
+                })
             }
-
-            // Set camera properties for better performance
-            cam.set(videoio::CAP_PROP_FRAME_WIDTH, 640.0)?;
-            cam.set(videoio::CAP_PROP_FRAME_HEIGHT, 480.0)?;
-
-            log::info!("Successfully opened local camera");
-            cam
+            None => None,
+        };
+        let remote_camera =
+            remote_camera || sources.stereo.as_ref().is_some_and(SourceSpec::is_esp32);
+
+        // Positions from the ESP32 stream are corrected on the device, so hand
+        // the coefficients over instead of applying them twice
+        let calibration = if remote_camera {
+            if !calibration.is_identity() {
+                let url = format!("{}/api/calibration", server_url);
+                http_client
+                    .post(&url)
+                    .json(&calibration)
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .context("Failed to send lens calibration to ESP32")?;
+                log::info!(
+                    "Lens calibration sent to ESP32 (k1={}, k2={})",
+                    calibration.k1,
+                    calibration.k2
+                );
+            }
+            LensCalibration::default()
+        } else {
+            calibration
         };
 
-        log::info!("Initializing hand tracker...");
-        let hand_tracker = HandTracker::new()?;
-
-        let http_client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(1))
-            .build()?;
+        let link: Option<sender::Link> = match transport {
+            Transport::Http => None,
+            Transport::WebSocket => {
+                let mut channel = WebSocketTransport::connect(&server_url)
+                    .context("Failed to open WebSocket control channel")?;
+                channel.subscribe_events()?;
+                log::info!("WebSocket control channel connected");
+                Some(Box::new(channel))
+            }
+            Transport::Udp(port) => {
+                let sender = UdpTransport::connect(&server_url, port)
+                    .context("Failed to open UDP target channel")?;
+                log::info!("Sending positions over UDP port {}", port);
+                Some(Box::new(sender))
+            }
+        };
 
         Ok(Self {
             server_url,
             camera,
-            hand_tracker,
+            detector,
+            smoother,
             http_client,
             last_position: None,
             show_window,
+            calibration,
+            link,
+            remote_camera,
+            scene: None,
+            mappings: MappingTable::default(),
+            last_scales: ParameterScales::IDENTITY,
+            last_hand: None,
+            stereo,
+            last_depth: None,
+            status: None,
+            artifact_dump: None,
         })
     }
 
-    fn send_position_update(&mut self, position: Option<Position>) -> Result<()> {
+    /// Send the preferred preset, then the scene at `scene_path`, and watch
+    /// that file. A broken preset or scene is reported, not fatal: fixing
+    /// and saving the scene applies it without restarting.
+    fn load_scenes(
+        &mut self,
+        preset: Option<ScenePreset>,
+        scene_path: Option<PathBuf>,
+    ) -> Result<()> {
+        if let Some(preset) = preset {
+            match preset.resolve() {
+                Ok(preset) => send_preset(&self.http_client, &self.server_url, &preset),
+                Err(e) => log::warn!("Preferred preset: {}", e),
+            }
+        }
+        let Some(path) = scene_path else {
+            return Ok(());
+        };
+        match scene::load_scene(&path) {
+            Ok(scene) => {
+                send_preset(&self.http_client, &self.server_url, &scene.preset);
+                self.mappings = scene.mappings;
+            }
+            Err(e) => log::warn!("{}: {}", path.display(), e),
+        }
+        let watcher = SceneWatcher::new(&path)
+            .with_context(|| format!("Failed to watch {}", path.display()))?;
+        log::info!("Watching {} for changes", path.display());
+        self.scene = Some(watcher);
+        Ok(())
+    }
+
+    fn send_depth_update(&mut self, outbox: &Outbox, depth: Option<f32>) {
+        let changed = match (depth, self.last_depth) {
+            (Some(depth), Some(last)) => (depth - last).abs() > 0.02,
+            (depth, last) => depth.is_some() != last.is_some(),
+        };
+        // A full outbox leaves `last_depth` alone, so the next frame tries again
+        if changed && outbox.try_send(ControlMessage::Depth(DepthUpdate { depth })) {
+            self.last_depth = depth;
+        }
+    }
+
+    fn send_position_update(&mut self, outbox: &Outbox, position: Option<Position>) {
         // Only send if position changed significantly (reduce network traffic)
         if let Some(pos) = position {
             if let Some(last) = self.last_position {
                 let distance = ((pos.x - last.x).powi(2) + (pos.y - last.y).powi(2)).sqrt();
                 if distance < 5.0 {
                     // Skip update if movement is too small
-                    return Ok(());
+                    return;
                 }
             }
         }
 
         let update = TargetPositionUpdate { position };
-        let url = format!("{}/api/position", self.server_url);
-
-        match self.http_client.post(&url).json(&update).send() {
-            Ok(response) => {
-                if response.status().is_success() {
-                    self.last_position = position;
-                    log::debug!("Position update sent: {:?}", position);
-                } else {
-                    log::warn!("Server returned error: {}", response.status());
-                }
+        if outbox.try_send(ControlMessage::Position(update)) {
+            self.last_position = position;
+        }
+    }
+
+    /// Advance the scene's mappings by one frame of `hand` and send the
+    /// multipliers they make when these changed
+    fn send_scales_update(&mut self, outbox: &Outbox, hand: Option<&HandLandmarks>, height: f32) {
+        if self.mappings.mappings().is_empty() && self.last_scales.is_identity() {
+            return;
+        }
+        let now = Instant::now();
+        let signals = match hand {
+            Some(hand) => {
+                let thumb = Vector2D::new(hand.thumb_tip.x, hand.thumb_tip.y);
+                let index = Vector2D::new(hand.index_tip.x, hand.index_tip.y);
+                let midpoint = (thumb + index) * 0.5;
+                let speed = self.last_hand.and_then(|(last, at)| {
+                    let secs = now.duration_since(at).as_secs_f32();
+                    (secs > 0.0).then(|| (midpoint - last).magnitude() / secs)
+                });
+                self.last_hand = Some((midpoint, now));
+                InputSignals::of_hand(midpoint, index.distance(&thumb), height, speed)
             }
-            Err(e) => {
-                log::warn!("Failed to send position update: {}", e);
+            None => {
+                self.last_hand = None;
+                InputSignals::default()
             }
+        };
+        let mut scales = ParameterScales::IDENTITY;
+        self.mappings.update(&signals, &mut scales);
+
+        let changed = ConfigParameter::ALL.into_iter().any(|parameter| {
+            (scales.get(parameter) - self.last_scales.get(parameter)).abs() > 0.01
+        });
+        // A full outbox leaves `last_scales` alone, so the next frame tries again
+        if changed && outbox.try_send(ControlMessage::Scales(scales_update(&scales))) {
+            self.last_scales = scales;
         }
-
-        Ok(())
     }
 
-    fn run(&mut self) -> Result<()> {
+    /// Capture and detect on this thread, which owns the window, while
+    /// `runtime` sends the updates
+    fn run(&mut self, runtime: &Runtime) -> Result<()> {
         log::info!("Starting main loop...");
+        let outbox = Outbox::spawn(
+            runtime.handle(),
+            self.link.take(),
+            HttpTransport::new(&self.server_url),
+            self.status.clone(),
+        );
 
         if self.show_window {
             highgui::named_window("Boid Hand Tracker", highgui::WINDOW_AUTOSIZE)?;
@@ -149,19 +483,60 @@ impl BoidClient {
                 continue;
             }
 
+            if let Some(ref mut watcher) = self.scene {
+                match watcher.poll() {
+                    Some(Ok(scene)) => {
+                        log::info!("{} changed, applying", watcher.path().display());
+                        send_preset(&self.http_client, &self.server_url, &scene.preset);
+                        self.mappings = scene.mappings;
+                    }
+                    Some(Err(e)) => log::warn!("{}: {}", watcher.path().display(), e),
+                    None => {}
+                }
+            }
+
             // Process hand tracking
-            let hand_result = self.hand_tracker.process_frame(&frame)?;
+            let detection = self.detector.detect(&hand_tracker::frame_of(&frame)?);
+            if let Some((ref dir, ref mut written)) = self.artifact_dump {
+                if let Some(artifacts) = self.detector.artifacts() {
+                    hand_tracker::write_artifacts(artifacts, dir, *written)?;
+                    *written += 1;
+                }
+            }
+            let depth = match self.stereo {
+                Some(ref mut stereo) => stereo.measure(detection.as_ref(), frame.cols() as f32)?,
+                None => None,
+            };
+            let hand_result = match detection {
+                Some(landmarks) => Some(self.smoother.smooth(&landmarks.with_depth(depth))),
+                None => {
+                    self.smoother.reset();
+                    None
+                }
+            };
+
+            let hand = hand_result.as_ref().map(|hand_data| {
+                self.calibration.undistort_landmarks(
+                    hand_data,
+                    frame.cols() as f32,
+                    frame.rows() as f32,
+                )
+            });
 
             // Send position update to ESP32
-            if let Some(ref hand_data) = hand_result {
+            if let Some(ref hand_data) = hand {
                 let position = Position::new(hand_data.index_tip.x, hand_data.index_tip.y);
-                self.send_position_update(Some(position))?;
+                self.send_position_update(&outbox, Some(position));
             } else {
                 // No hand detected, clear target
                 if self.last_position.is_some() {
-                    self.send_position_update(None)?;
+                    self.send_position_update(&outbox, None);
                 }
             }
+            self.send_scales_update(&outbox, hand.as_ref(), frame.rows() as f32);
+            if self.stereo.is_some() {
+                self.send_depth_update(&outbox, depth);
+            }
 
             // Draw visualization
             if self.show_window {
@@ -214,6 +589,20 @@ impl BoidClient {
                         imgproc::LINE_8,
                         false,
                     )?;
+
+                    if let Some(depth) = hand_data.depth {
+                        imgproc::put_text(
+                            &mut display_frame,
+                            &format!("Depth: {:.2}", depth),
+                            Point::new(10, 90),
+                            imgproc::FONT_HERSHEY_SIMPLEX,
+                            1.0,
+                            Scalar::new(0.0, 255.0, 255.0, 0.0),
+                            2,
+                            imgproc::LINE_8,
+                            false,
+                        )?;
+                    }
                 }
 
                 // Display FPS
@@ -237,20 +626,205 @@ impl BoidClient {
                     false,
                 )?;
 
+                if let Some(ref poller) = self.status {
+                    let (text, color) = match poller.current() {
+                        Some(status) => (
+                            format!(
+                                "ESP32: {} boids, {} fps, target {}",
+                                status.boid_count,
+                                status.fps,
+                                if status.target_active { "on" } else { "off" }
+                            ),
+                            Scalar::new(255.0, 255.0, 255.0, 0.0),
+                        ),
+                        None => (
+                            "ESP32: no status".to_string(),
+                            Scalar::new(0.0, 0.0, 255.0, 0.0),
+                        ),
+                    };
+                    imgproc::put_text(
+                        &mut display_frame,
+                        &text,
+                        Point::new(10, display_frame.rows() - 15),
+                        imgproc::FONT_HERSHEY_SIMPLEX,
+                        0.7,
+                        color,
+                        2,
+                        imgproc::LINE_8,
+                        false,
+                    )?;
+                }
+
                 highgui::imshow("Boid Hand Tracker", &display_frame)?;
             }
 
-            // Check for 'q' key to quit
-            if highgui::wait_key(1)? == b'q' as i32 {
+            // 'q' quits; 'n' and 'f' record the stereo calibration poses
+            let key = highgui::wait_key(1)?;
+            if key == b'q' as i32 {
                 log::info!("Quit requested");
                 break;
             }
+            if let Some(ref mut stereo) = self.stereo {
+                if key == b'n' as i32 {
+                    stereo.record(Pose::Near);
+                } else if key == b'f' as i32 {
+                    stereo.record(Pose::Far);
+                }
+            }
+        }
+        // Leave the flock at its settings, not wherever the hand left it
+        if !self.last_scales.is_identity() {
+            outbox.try_send(ControlMessage::Scales(ParameterScalesUpdate::default()));
+        }
+        outbox.close(runtime, SHUTDOWN_TIMEOUT);
+
+        if self.remote_camera {
+            // The server is busy while the stream is open, so close it first
+            self.camera.release()?;
+            if let Some(ref mut stereo) = self.stereo {
+                stereo.camera.release()?;
+            }
+            if let Err(e) = set_stream_power(&self.http_client, &self.server_url, false) {
+                log::warn!(
+                    "[{}] Failed to power down the ESP32 camera: {}",
+                    ErrorCode::CameraPowerFailed,
+                    e
+                );
+            }
         }
 
         Ok(())
     }
 }
 
+/// Open `source`, powering the ESP32's camera up first if it is the source
+fn open_camera(
+    source: &SourceSpec,
+    http_client: &reqwest::blocking::Client,
+    server_url: &str,
+) -> Result<Box<dyn FrameSource>> {
+    if !source.is_esp32() {
+        return source.open(server_url);
+    }
+    // The ESP32 keeps its camera in standby until asked; opening the
+    // stream would wake it too, but failing early here is clearer
+    set_stream_power(http_client, server_url, true)
+        .context("Failed to power up the ESP32 camera")?;
+    source.open(server_url).with_context(|| {
+        format!(
+            "Failed to open ESP32 camera stream at {}/stream. \
+            Make sure the ESP32 is running and camera streaming is enabled.",
+            server_url
+        )
+    })
+}
+
+/// `"[E-..] message"` from an ESP32 error response, or the bare status for
+/// firmware that predates error codes
+fn error_response_message(response: reqwest::blocking::Response) -> String {
+    let status = response.status();
+    match response.json::<ErrorBody>() {
+        Ok(body) => format!("[{}] {}", body.code, body.error),
+        Err(_) => format!("HTTP {}", status),
+    }
+}
+
+/// Apply a scene preset on the ESP32; failures are logged so a running
+/// client survives a bad edit or a busy server
+/// The parameters `scales` changes, by name
+fn scales_update(scales: &ParameterScales) -> ParameterScalesUpdate {
+    ParameterScalesUpdate {
+        scales: ConfigParameter::ALL
+            .into_iter()
+            .filter(|&parameter| scales.get(parameter) != 1.0)
+            .map(|parameter| (parameter.as_str().to_string(), scales.get(parameter)))
+            .collect(),
+    }
+}
+
+fn send_preset(http_client: &reqwest::blocking::Client, server_url: &str, preset: &PresetUpdate) {
+    let url = format!("{}/api/preset", server_url);
+    match http_client.post(&url).json(preset).send() {
+        Ok(response) if response.status().is_success() => log::info!(
+            "Scene applied over {} ticks: {:?}",
+            preset.duration_ticks,
+            preset.settings
+        ),
+        Ok(response) => log::warn!(
+            "Server rejected scene: {}",
+            error_response_message(response)
+        ),
+        Err(e) => log::warn!(
+            "[{}] Failed to send scene: {}",
+            ErrorCode::ServerUnreachable,
+            e
+        ),
+    }
+}
+
+/// Ask the ESP32 to wake its camera or put it back into standby
+fn set_stream_power(
+    http_client: &reqwest::blocking::Client,
+    server_url: &str,
+    power: bool,
+) -> Result<()> {
+    let action = if power { "start" } else { "stop" };
+    let response: StreamPowerResponse = http_client
+        .post(format!("{}/api/stream/{}", server_url, action))
+        .send()
+        .and_then(|response| response.error_for_status())?
+        .json()?;
+    let state = if response.powered {
+        "powered up"
+    } else {
+        "in standby"
+    };
+    log::info!(
+        "ESP32 camera {} (idle timeout {}s)",
+        state,
+        response.idle_timeout_secs
+    );
+    Ok(())
+}
+
+/// Landmark smoothing without `--smoothing` or a profile
+const DEFAULT_SMOOTHING: f32 = 0.5;
+
+/// How long quitting waits for the last updates to reach the ESP32
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Handle `--list-profiles`, `--delete-profile` and `--save-profile`
+fn manage_profiles(args: &Args) -> Result<()> {
+    let store = ProfileStore::open_default()?;
+    if let Some(ref name) = args.save_profile {
+        let mut flags = args.profile_flags()?;
+        // The profile is used from other directories
+        if let Some(scene) = flags.scene.take() {
+            flags.scene = Some(std::path::absolute(&scene)?);
+        }
+        let profile = store.update(name, &flags)?;
+        println!("Saved profile {} to {}", name, store.dir().display());
+        print!("{}", toml::to_string(&profile)?);
+    }
+    if let Some(ref name) = args.delete_profile {
+        if store.delete(name)? {
+            println!("Deleted profile {}", name);
+        } else {
+            println!("No profile named {}", name);
+        }
+    }
+    if args.list_profiles {
+        let names = store.list()?;
+        if names.is_empty() {
+            println!("No profiles in {}", store.dir().display());
+        }
+        for name in names {
+            println!("{}", name);
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
@@ -265,14 +839,91 @@ fn main() -> Result<()> {
             .init();
     }
 
+    if args.list_profiles || args.delete_profile.is_some() || args.save_profile.is_some() {
+        return manage_profiles(&args);
+    }
+
+    // Flags override the profile, defaults fill in the rest
+    let settings = match args.profile {
+        Some(ref name) => {
+            let profile = ProfileStore::open_default()?.load(name)?;
+            log::info!("Using profile {}", name);
+            profile.merged(&args.profile_flags()?)
+        }
+        None => args.profile_flags()?,
+    };
+    let Some(server) = settings.server else {
+        anyhow::bail!("--server is required unless the profile sets a server");
+    };
+    let smoothing = settings.smoothing.unwrap_or(DEFAULT_SMOOTHING);
+
     log::info!("Boid client starting...");
-    log::info!("Server: {}", args.server);
+    log::info!("Server: {}", server);
     log::info!("Video source: {}", args.video_source);
+    if let Some(ref stereo) = args.stereo {
+        log::info!("Stereo source: {}", stereo);
+    }
+
+    let calibration = LensCalibration::new(settings.k1.unwrap_or(0.0), settings.k2.unwrap_or(0.0));
+    if !calibration.is_valid() {
+        anyhow::bail!(
+            "[{}] Lens calibration coefficients must be finite",
+            ErrorCode::InvalidCalibration
+        );
+    }
 
-    let mut client = BoidClient::new(args.server, &args.video_source, args.show_window)
-        .context("Failed to initialize client")?;
+    if !(smoothing > 0.0 && smoothing <= 1.0) {
+        anyhow::bail!(
+            "[{}] --smoothing must be in (0, 1]",
+            ErrorCode::InvalidSetting
+        );
+    }
+
+    let transport = if args.websocket {
+        Transport::WebSocket
+    } else if args.udp {
+        Transport::Udp(args.udp_port)
+    } else {
+        Transport::Http
+    };
+
+    // Network sends and status polls run here, never on the capture loop
+    let runtime = Runtime::new().context("Failed to start the async runtime")?;
+    let status = (args.status_interval_ms > 0).then(|| {
+        Arc::new(StatusPoller::spawn(
+            runtime.handle(),
+            &server,
+            Duration::from_millis(args.status_interval_ms),
+            args.min_server_fps,
+        ))
+    });
+
+    let sources = CameraSources {
+        primary: SourceSpec::parse(&args.video_source)?,
+        detector: args.detector,
+        stereo: args.stereo.as_deref().map(SourceSpec::parse).transpose()?,
+        stereo_calibration: args.stereo_calibration,
+    };
+    let mut client = BoidClient::new(
+        server,
+        sources,
+        args.show_window,
+        calibration,
+        transport,
+        LandmarkSmoother::new(smoothing),
+    )
+    .context("Failed to initialize client")?;
+    client.load_scenes(settings.preset, settings.scene)?;
+    client.status = status;
+    if let Some(dir) = args.dump_artifacts {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        log::info!("Writing detector artifacts to {}", dir.display());
+        client.detector.set_artifacts(true);
+        client.artifact_dump = Some((dir, 0));
+    }
 
-    client.run().context("Client error")?;
+    client.run(&runtime).context("Client error")?;
 
     Ok(())
 }
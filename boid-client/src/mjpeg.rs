@@ -0,0 +1,399 @@
+use anyhow::{Context, Result};
+use boid_shared::ErrorCode;
+use std::io::{self, BufRead, BufReader, Read};
+use std::time::Duration;
+
+/// Largest part accepted from a stream; anything bigger is not a camera frame
+pub const MAX_PART_LEN: usize = 4 * 1024 * 1024;
+
+/// A stream that sends nothing for this long is treated as dropped
+const STALL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A decoded frame: row-major RGB, 3 bytes per pixel
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// The boundary of a `multipart/x-mixed-replace; boundary=...` content type
+pub fn boundary_from_content_type(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime
+        .trim()
+        .eq_ignore_ascii_case("multipart/x-mixed-replace")
+    {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("boundary") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        // Some servers repeat the leading dashes in the header
+        let value = value.strip_prefix("--").unwrap_or(value);
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Splits a `multipart/x-mixed-replace` body into its parts
+///
+/// Parts with a `Content-Length` header are read in one go; parts without
+/// one run until the next boundary line.
+pub struct MultipartReader<R> {
+    reader: BufReader<R>,
+    /// `--` followed by the boundary
+    delimiter: Vec<u8>,
+    /// The previous part ended on a boundary line that was already consumed
+    at_part_start: bool,
+}
+
+impl<R: Read> MultipartReader<R> {
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            delimiter: format!("--{}", boundary).into_bytes(),
+            at_part_start: false,
+        }
+    }
+
+    /// Body of the next part, or `None` once the stream has ended
+    pub fn next_part(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if !std::mem::take(&mut self.at_part_start) {
+            // Skip the preamble, or the blank line after the previous body
+            loop {
+                let mut line = Vec::new();
+                if self.reader.read_until(b'\n', &mut line)? == 0 {
+                    return Ok(None);
+                }
+                match self.boundary_line(&line) {
+                    Some(true) => return Ok(None),
+                    Some(false) => break,
+                    None => {}
+                }
+            }
+        }
+
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        match content_length {
+            Some(len) if len > MAX_PART_LEN => Err(too_large()),
+            Some(len) => {
+                let mut body = vec![0; len];
+                self.reader.read_exact(&mut body)?;
+                Ok(Some(body))
+            }
+            None => self.read_until_boundary().map(Some),
+        }
+    }
+
+    fn read_until_boundary(&mut self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                // The last part of a stream that closed without a final boundary
+                return Ok(body);
+            }
+            if let Some(last) = self.boundary_line(&line) {
+                self.at_part_start = !last;
+                // The line break before the boundary belongs to it
+                if body.ends_with(b"\r\n") {
+                    body.truncate(body.len() - 2);
+                } else if body.ends_with(b"\n") {
+                    body.truncate(body.len() - 1);
+                }
+                return Ok(body);
+            }
+            body.extend_from_slice(&line);
+            if body.len() > MAX_PART_LEN {
+                return Err(too_large());
+            }
+        }
+    }
+
+    /// `Some(is_last)` if `line` is a boundary line
+    fn boundary_line(&self, line: &[u8]) -> Option<bool> {
+        let rest = line.strip_prefix(self.delimiter.as_slice())?;
+        let rest = rest.strip_suffix(b"\n").unwrap_or(rest);
+        let rest = rest.strip_suffix(b"\r").unwrap_or(rest);
+        match rest {
+            b"" => Some(false),
+            b"--" => Some(true),
+            _ => None,
+        }
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("multipart part larger than {} bytes", MAX_PART_LEN),
+    )
+}
+
+pub fn decode_jpeg(jpeg: &[u8]) -> Result<RgbFrame> {
+    let mut decoder = jpeg_decoder::Decoder::new(jpeg);
+    let pixels = decoder.decode().context("Invalid JPEG frame")?;
+    let info = decoder.info().context("JPEG frame has no header")?;
+    let data = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => pixels,
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l]).collect(),
+        // Big-endian samples; keep the high byte
+        jpeg_decoder::PixelFormat::L16 => pixels
+            .chunks_exact(2)
+            .flat_map(|l| [l[0], l[0], l[0]])
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => anyhow::bail!("CMYK JPEG frames are not supported"),
+    };
+    Ok(RgbFrame {
+        width: info.width as u32,
+        height: info.height as u32,
+        data,
+    })
+}
+
+/// Delays between reconnection attempts: doubling from `initial` up to `max`
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Delay before the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Start over after a success
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+/// Frames from an MJPEG-over-HTTP stream such as the ESP32's `/stream`
+///
+/// Frames come in the order the server sent them; nothing is buffered
+/// beyond what the socket holds, so a slow caller gets frames up to a
+/// socket's worth behind and then holds the server back rather than
+/// skipping ahead. When the stream drops or stalls, it is reopened with
+/// backoff for as long as the caller keeps asking for frames.
+pub struct MjpegStream {
+    url: String,
+    client: reqwest::blocking::Client,
+    reader: Option<MultipartReader<reqwest::blocking::Response>>,
+    backoff: Backoff,
+}
+
+impl MjpegStream {
+    /// Open `url`, failing right away if it is unreachable or not a stream
+    pub fn connect(url: &str) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .connect_timeout(STALL_TIMEOUT)
+            // Applies to every read of the body, not to the stream as a whole
+            .timeout(STALL_TIMEOUT)
+            .build()?;
+        let mut stream = Self {
+            url: url.to_string(),
+            client,
+            reader: None,
+            backoff: Backoff::new(Duration::from_millis(250), Duration::from_secs(5)),
+        };
+        stream.reader = Some(stream.open()?);
+        Ok(stream)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Drop the connection; the next `next_frame` opens a new one
+    pub fn close(&mut self) {
+        self.reader = None;
+    }
+
+    fn open(&self) -> Result<MultipartReader<reqwest::blocking::Response>> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .with_context(|| {
+                format!(
+                    "[{}] Cannot open {}",
+                    ErrorCode::ServerUnreachable,
+                    self.url
+                )
+            })?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let boundary = boundary_from_content_type(content_type)
+            .with_context(|| format!("{} is not an MJPEG stream ({})", self.url, content_type))?;
+        Ok(MultipartReader::new(response, &boundary))
+    }
+
+    /// Block until the next frame in the stream arrives, which is not
+    /// necessarily the newest one sent, reconnecting as needed.
+    /// Frames that fail to decode are skipped.
+    pub fn next_frame(&mut self) -> Result<RgbFrame> {
+        loop {
+            let Some(reader) = self.reader.as_mut() else {
+                let delay = self.backoff.next_delay();
+                std::thread::sleep(delay);
+                match self.open() {
+                    Ok(reader) => {
+                        log::info!("Reconnected to {}", self.url);
+                        self.reader = Some(reader);
+                    }
+                    Err(e) => log::warn!("{:#}; retrying", e),
+                }
+                continue;
+            };
+
+            match reader.next_part() {
+                Ok(Some(jpeg)) => match decode_jpeg(&jpeg) {
+                    Ok(frame) => {
+                        self.backoff.reset();
+                        return Ok(frame);
+                    }
+                    Err(e) => log::debug!("Skipping frame: {:#}", e),
+                },
+                Ok(None) => {
+                    log::warn!(
+                        "[{}] {} ended; reconnecting",
+                        ErrorCode::CameraCaptureFailed,
+                        self.url
+                    );
+                    self.reader = None;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "[{}] {} dropped ({}); reconnecting",
+                        ErrorCode::CameraCaptureFailed,
+                        self.url,
+                        e
+                    );
+                    self.reader = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_reader_splits_parts() {
+        assert_eq!(
+            boundary_from_content_type("multipart/x-mixed-replace; boundary=BOUNDARY").as_deref(),
+            Some("BOUNDARY")
+        );
+        assert_eq!(
+            boundary_from_content_type(r#"multipart/x-mixed-replace;boundary="--frame""#)
+                .as_deref(),
+            Some("frame")
+        );
+        assert_eq!(boundary_from_content_type("image/jpeg"), None);
+
+        // One part with a length, one with a line break inside and none
+        let body = b"preamble\r\n\
+            --BOUNDARY\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\n\r\nab\r\n\r\n\
+            --BOUNDARY\r\nContent-Type: image/jpeg\r\n\r\nxy\nz\r\n\
+            --BOUNDARY--\r\n";
+        let mut reader = MultipartReader::new(&body[..], "BOUNDARY");
+        assert_eq!(reader.next_part().unwrap().unwrap(), b"ab\r\n");
+        assert_eq!(reader.next_part().unwrap().unwrap(), b"xy\nz");
+        assert_eq!(reader.next_part().unwrap(), None);
+
+        let truncated = b"--BOUNDARY\r\nContent-Length: 10\r\n\r\nabc";
+        let mut reader = MultipartReader::new(&truncated[..], "BOUNDARY");
+        assert!(reader.next_part().is_err());
+    }
+
+    /// Serves `frames` connections, each sending one JPEG and closing
+    fn serve_frames(jpeg: Vec<u8>, frames: usize) -> String {
+        use std::io::Write;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(frames) {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\n\
+                     Content-Type: multipart/x-mixed-replace; boundary=BOUNDARY\r\n\r\n\
+                     --BOUNDARY\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                    jpeg.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&jpeg);
+                let _ = stream.write_all(b"\r\n");
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_stream_decodes_frames_and_reconnects() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/test_images/IMG_8522.jpeg"
+        );
+        let jpeg = std::fs::read(path).unwrap();
+        let url = serve_frames(jpeg, 2);
+
+        let mut stream = MjpegStream::connect(&url).unwrap();
+        let frame = stream.next_frame().unwrap();
+        assert!(frame.width > 0 && frame.height > 0);
+        assert_eq!(frame.data.len(), (frame.width * frame.height * 3) as usize);
+
+        // The first connection closed after its frame; this one comes from the second
+        assert_eq!(stream.next_frame().unwrap(), frame);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_resets() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(300));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(300));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert!(decode_jpeg(b"not a jpeg").is_err());
+    }
+}
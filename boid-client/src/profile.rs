@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use boid_shared::ErrorCode;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::scene::ScenePreset;
+
+/// Settings for one device, saved under a name such as `living-room`
+///
+/// ```toml
+/// server = "http://192.168.1.100"
+/// k1 = -0.12
+/// smoothing = 0.4
+/// scene = "/home/me/scenes/calm.toml"
+///
+/// [preset]
+/// duration_ticks = 30
+///
+/// [preset.settings]
+/// max_speed = 3.0
+/// ```
+///
+/// Every field is optional; command-line flags override the profile and
+/// built-in defaults fill whatever neither sets. There is no auth token:
+/// the ESP32 API does not authenticate clients, so it would have nothing to
+/// check one against.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// ESP32 server URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Radial lens distortion coefficients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k1: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k2: Option<f32>,
+    /// Landmark smoothing weight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<f32>,
+    /// Scene file applied at startup and again whenever it is saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene: Option<PathBuf>,
+    /// The device's preferred preset, kept in the profile itself and
+    /// applied at startup before `scene`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<ScenePreset>,
+}
+
+impl Profile {
+    /// `self` with the fields set in `changes` replaced
+    pub fn merged(&self, changes: &Profile) -> Profile {
+        Profile {
+            server: changes.server.clone().or_else(|| self.server.clone()),
+            k1: changes.k1.or(self.k1),
+            k2: changes.k2.or(self.k2),
+            smoothing: changes.smoothing.or(self.smoothing),
+            scene: changes.scene.clone().or_else(|| self.scene.clone()),
+            preset: changes.preset.clone().or_else(|| self.preset.clone()),
+        }
+    }
+}
+
+/// A directory of `<name>.toml` profiles
+pub struct ProfileStore {
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `boid-client/profiles` in the user's config directory (e.g.
+    /// `~/.config` on Linux), or `$BOID_CLIENT_CONFIG_DIR/profiles`
+    pub fn open_default() -> Result<Self> {
+        let base = match std::env::var_os("BOID_CLIENT_CONFIG_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::config_dir()
+                .context("No config directory; set BOID_CLIENT_CONFIG_DIR")?
+                .join("boid-client"),
+        };
+        Ok(Self::new(base.join("profiles")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            anyhow::bail!(
+                "[{}] Profile names may only use letters, digits, '-' and '_': {:?}",
+                ErrorCode::InvalidProfile,
+                name
+            );
+        }
+        Ok(self.dir.join(format!("{}.toml", name)))
+    }
+
+    pub fn load(&self, name: &str) -> Result<Profile> {
+        let path = self.path(name)?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+                "[{}] No profile named {:?} in {}",
+                ErrorCode::InvalidProfile,
+                name,
+                self.dir.display()
+            ),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        toml::from_str(&text).with_context(|| {
+            format!(
+                "[{}] Invalid profile {}",
+                ErrorCode::InvalidProfile,
+                path.display()
+            )
+        })
+    }
+
+    /// Merge `changes` into the profile `name`, creating it if needed, and
+    /// return the saved profile. Fields `changes` leaves unset are kept.
+    pub fn update(&self, name: &str, changes: &Profile) -> Result<Profile> {
+        let current = if self.path(name)?.exists() {
+            self.load(name)?
+        } else {
+            Profile::default()
+        };
+        let profile = current.merged(changes);
+        self.save(name, &profile)?;
+        Ok(profile)
+    }
+
+    /// Write the profile `name`, replacing it whole
+    pub fn save(&self, name: &str, profile: &Profile) -> Result<()> {
+        let path = self.path(name)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        // Written aside and renamed, so a crash never leaves half a profile
+        let partial = path.with_extension("toml.partial");
+        std::fs::write(&partial, toml::to_string(profile)?)
+            .and_then(|()| std::fs::rename(&partial, &path))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Names of the saved profiles, sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.dir.display()))
+            }
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns false if there was no such profile
+    pub fn delete(&self, name: &str) -> Result<bool> {
+        let path = self.path(name)?;
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to delete {}", path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(test: &str) -> ProfileStore {
+        let dir =
+            std::env::temp_dir().join(format!("boid-profiles-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        ProfileStore::new(dir)
+    }
+
+    #[test]
+    fn test_update_keeps_unset_fields() {
+        let store = store("update");
+        store
+            .update(
+                "living-room",
+                &Profile {
+                    server: Some("http://10.0.0.2".to_string()),
+                    k1: Some(-0.1),
+                    ..Profile::default()
+                },
+            )
+            .unwrap();
+        let profile = store
+            .update(
+                "living-room",
+                &Profile {
+                    smoothing: Some(0.3),
+                    k1: Some(-0.2),
+                    ..Profile::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(store.load("living-room").unwrap(), profile);
+        assert_eq!(profile.server.as_deref(), Some("http://10.0.0.2"));
+        assert_eq!((profile.k1, profile.k2), (Some(-0.2), None));
+        assert_eq!(profile.smoothing, Some(0.3));
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_preset_round_trips() {
+        let store = store("preset");
+        let mut preset = ScenePreset {
+            duration_ticks: 30,
+            ..ScenePreset::default()
+        };
+        preset.settings.max_speed = Some(3.0);
+        let profile = Profile {
+            server: Some("http://10.0.0.2".to_string()),
+            preset: Some(preset),
+            ..Profile::default()
+        };
+        store.save("studio", &profile).unwrap();
+        assert_eq!(store.load("studio").unwrap(), profile);
+
+        let resolved = profile.preset.unwrap().resolve().unwrap();
+        assert_eq!(resolved.settings.max_speed, 3.0);
+        assert_eq!(resolved.duration_ticks, 30);
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_delete() {
+        let store = store("list");
+        assert!(store.list().unwrap().is_empty());
+        store.save("studio", &Profile::default()).unwrap();
+        store.save("desk", &Profile::default()).unwrap();
+        assert_eq!(store.list().unwrap(), ["desk", "studio"]);
+
+        assert!(store.delete("desk").unwrap());
+        assert!(!store.delete("desk").unwrap());
+        assert!(store.load("desk").is_err());
+        assert!(store.save("../escape", &Profile::default()).is_err());
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+}
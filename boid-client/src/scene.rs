@@ -0,0 +1,348 @@
+use boid_core::mapping::{InputMapping, InvalidMapping, MappingTable};
+use boid_shared::{BoidSettings, ErrorCode, PresetUpdate};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// A `scene.toml` file: settings plus an optional crossfade, and the
+/// input mappings the hand drives
+///
+/// ```toml
+/// duration_ticks = 60
+///
+/// [settings]
+/// separation_weight = 1.5
+/// max_speed = 3.0
+///
+/// [[mapping]]
+/// input = "hand_height"
+/// parameter = "cohesion_weight"
+/// input_min = 0.2
+/// input_max = 0.8
+/// low = 0.5
+/// high = 2.0
+/// ```
+///
+/// Settings left out keep their defaults. Each `[[mapping]]` is an
+/// `InputMapping`; without any, the hand only steers the target.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    duration_ticks: u32,
+    #[serde(default)]
+    settings: SceneSettings,
+    #[serde(default, rename = "mapping")]
+    mappings: Vec<InputMapping>,
+}
+
+/// The settings and crossfade of a scene, without its mappings; a profile
+/// keeps one as its preferred preset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenePreset {
+    #[serde(default)]
+    pub duration_ticks: u32,
+    #[serde(default)]
+    pub settings: SceneSettings,
+}
+
+impl ScenePreset {
+    /// The preset for `POST /api/preset`
+    ///
+    /// Out-of-range values are clamped like the server would; each
+    /// adjustment is logged as a warning.
+    pub fn resolve(&self) -> Result<PresetUpdate, SceneError> {
+        let mut settings = self.settings.over(BoidSettings::default());
+        settings
+            .clamp_to_limits(|field, requested, applied| {
+                log::warn!(
+                    "Scene value {} = {} is out of range, using {}",
+                    field,
+                    requested,
+                    applied
+                );
+            })
+            .map_err(|invalid| SceneError::InvalidSetting(invalid.field))?;
+        Ok(PresetUpdate {
+            settings,
+            duration_ticks: self.duration_ticks,
+        })
+    }
+}
+
+/// A parsed scene file
+#[derive(Debug, Clone)]
+pub struct Scene {
+    /// Settings and crossfade for `POST /api/preset`
+    pub preset: PresetUpdate,
+    /// Mappings whose multipliers go to `POST /api/scales`
+    pub mappings: MappingTable,
+}
+
+/// The `[settings]` of a scene; `None` keeps the default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separation_weight: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alignment_weight: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohesion_weight: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_speed: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_force: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seek_weight: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seek_radius: Option<f32>,
+}
+
+impl SceneSettings {
+    fn over(&self, base: BoidSettings) -> BoidSettings {
+        BoidSettings {
+            separation_weight: self.separation_weight.unwrap_or(base.separation_weight),
+            alignment_weight: self.alignment_weight.unwrap_or(base.alignment_weight),
+            cohesion_weight: self.cohesion_weight.unwrap_or(base.cohesion_weight),
+            max_speed: self.max_speed.unwrap_or(base.max_speed),
+            max_force: self.max_force.unwrap_or(base.max_force),
+            seek_weight: self.seek_weight.unwrap_or(base.seek_weight),
+            seek_radius: self.seek_radius.or(base.seek_radius),
+        }
+    }
+}
+
+/// Why a scene file could not be applied
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    /// Not valid TOML, or an unknown field
+    Parse(String),
+    /// A setting is NaN or infinite
+    InvalidSetting(&'static str),
+    InvalidMapping(InvalidMapping),
+}
+
+impl SceneError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            SceneError::Io(_) | SceneError::Parse(_) => ErrorCode::InvalidPreset,
+            SceneError::InvalidSetting(_) => ErrorCode::InvalidSetting,
+            SceneError::InvalidMapping(_) => ErrorCode::InvalidMapping,
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "[{}] Cannot read scene file: {}", self.code(), e),
+            SceneError::Parse(e) => write!(f, "[{}] Invalid scene file: {}", self.code(), e),
+            SceneError::InvalidSetting(field) => {
+                write!(f, "[{}] {} must be a finite number", self.code(), field)
+            }
+            SceneError::InvalidMapping(e) => write!(f, "[{}] {}", self.code(), e),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Parse a scene file into a preset and its mappings, see
+/// [`ScenePreset::resolve`]
+pub fn parse_scene(text: &str) -> Result<Scene, SceneError> {
+    let scene: SceneFile = toml::from_str(text).map_err(|e| SceneError::Parse(e.to_string()))?;
+    let preset = ScenePreset {
+        duration_ticks: scene.duration_ticks,
+        settings: scene.settings,
+    };
+    Ok(Scene {
+        preset: preset.resolve()?,
+        mappings: MappingTable::new(scene.mappings).map_err(SceneError::InvalidMapping)?,
+    })
+}
+
+pub fn load_scene(path: &Path) -> Result<Scene, SceneError> {
+    let text = std::fs::read_to_string(path).map_err(SceneError::Io)?;
+    parse_scene(&text)
+}
+
+/// The settings and crossfade of the scene file at `path`, as given, for
+/// a profile to keep; its mappings are checked but left out
+pub fn load_preset(path: &Path) -> Result<ScenePreset, SceneError> {
+    let text = std::fs::read_to_string(path).map_err(SceneError::Io)?;
+    parse_scene(&text)?;
+    let scene: SceneFile = toml::from_str(&text).map_err(|e| SceneError::Parse(e.to_string()))?;
+    Ok(ScenePreset {
+        duration_ticks: scene.duration_ticks,
+        settings: scene.settings,
+    })
+}
+
+/// Watches one scene file for edits
+///
+/// The parent directory is watched rather than the file itself, because many
+/// editors save by writing a new file and renaming it over the old one.
+pub struct SceneWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+    /// Contents last handed out, to skip saves that changed nothing
+    last_text: Option<String>,
+}
+
+impl SceneWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> notify::Result<Self> {
+        let path = path.into();
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            events,
+            _watcher: watcher,
+            last_text: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Drain pending file events and reload the scene if it changed
+    ///
+    /// Never blocks. Returns `None` when there is nothing new, including
+    /// saves that left the contents as they were.
+    pub fn poll(&mut self) -> Option<Result<Scene, SceneError>> {
+        let file_name = self.path.file_name()?;
+        let mut touched = false;
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) => {
+                    touched |= event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == Some(file_name));
+                }
+                Err(e) => log::debug!("Scene watcher error: {}", e),
+            }
+        }
+        if !touched {
+            return None;
+        }
+
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            // Deleted, or caught between an editor's delete and rename
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => return Some(Err(SceneError::Io(e))),
+        };
+        if self.last_text.as_deref() == Some(text.as_str()) {
+            return None;
+        }
+        let result = parse_scene(&text);
+        self.last_text = Some(text);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boid_core::mapping::InputSignal;
+    use boid_core::ConfigParameter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_parse_scene_fills_defaults_and_validates() {
+        let scene = parse_scene(
+            "duration_ticks = 30\n\
+             [settings]\n\
+             max_speed = 3.5\n\
+             seek_weight = 1000.0\n",
+        )
+        .unwrap();
+        assert!(scene.mappings.mappings().is_empty());
+        let preset = scene.preset;
+        assert_eq!(preset.duration_ticks, 30);
+        assert_eq!(preset.settings.max_speed, 3.5);
+        assert_eq!(
+            preset.settings.cohesion_weight,
+            BoidSettings::default().cohesion_weight
+        );
+        assert!(preset.settings.seek_weight < 1000.0);
+
+        let error = parse_scene("[settings]\nmax_sped = 3.0\n").unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidPreset);
+        let error = parse_scene("[settings]\nmax_speed = nan\n").unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidSetting);
+    }
+
+    #[test]
+    fn test_parse_scene_mappings() {
+        let scene = parse_scene(
+            "[[mapping]]\n\
+             input = \"hand_height\"\n\
+             parameter = \"cohesion_weight\"\n\
+             input_min = 0.2\n\
+             input_max = 0.8\n\
+             low = 0.5\n\
+             high = 2.0\n",
+        )
+        .unwrap();
+        let mapping = scene.mappings.mappings()[0];
+        assert_eq!(mapping.input, InputSignal::HandHeight);
+        assert_eq!(mapping.parameter, ConfigParameter::CohesionWeight);
+        assert_eq!(mapping.exponent, 1.0);
+
+        let error = parse_scene(
+            "[[mapping]]\n\
+             input = \"hand_height\"\n\
+             parameter = \"cohesion_weight\"\n\
+             input_min = 0.5\n\
+             input_max = 0.5\n\
+             low = 0.5\n\
+             high = 2.0\n",
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidMapping);
+        let error = parse_scene("[[mapping]]\ninput = \"elbow\"\n").unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidPreset);
+    }
+
+    #[test]
+    fn test_watcher_reloads_edited_scene() {
+        let directory = std::env::temp_dir().join(format!("boid-scene-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("scene.toml");
+        std::fs::write(&path, "[settings]\nmax_speed = 2.0\n").unwrap();
+
+        let mut watcher = SceneWatcher::new(&path).unwrap();
+        assert!(watcher.poll().is_none());
+
+        std::fs::write(&path, "[settings]\nmax_speed = 4.0\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let reloaded = loop {
+            if let Some(result) = watcher.poll() {
+                break result;
+            }
+            assert!(Instant::now() < deadline, "edit was not noticed");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(reloaded.unwrap().preset.settings.max_speed, 4.0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
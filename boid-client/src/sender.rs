@@ -0,0 +1,257 @@
+//! Network sends on a tokio task, off the capture loop
+//!
+//! A slow or unreachable ESP32 used to stall the camera: every update was
+//! sent from the capture loop, which waited for the answer. Now the loop
+//! only queues updates in a small bounded [`Outbox`] and moves on. A task
+//! on the tokio runtime takes them out and sends them. When it falls
+//! behind, only the newest target, depth and scales are sent; the updates
+//! they superseded are stale by then and dropped.
+//!
+//! The transports block, so each round of sends runs on tokio's blocking
+//! pool. The same task takes the statuses the WebSocket link pushes and
+//! hands them to the [`StatusPoller`].
+
+use boid_shared::transport::{ServerEvent, Transport, TransportError};
+use boid_shared::{ControlMessage, ErrorCode};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+
+use crate::http::HttpTransport;
+use crate::status::StatusPoller;
+
+/// Updates queued before the capture loop has to try again on a later frame
+pub const OUTBOX_CAPACITY: usize = 8;
+/// How often pushed statuses are collected while no updates are sent
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A link to the ESP32 that can be handed to the blocking pool
+pub type Link = Box<dyn Transport + Send>;
+
+/// The capture loop's end of the network task
+pub struct Outbox {
+    queue: mpsc::Sender<ControlMessage>,
+    task: JoinHandle<()>,
+}
+
+impl Outbox {
+    /// Start sending on `runtime`, over `link` when there is one, and over
+    /// `http` for what it cannot carry and once it fails. Statuses the link
+    /// pushes go to `status`.
+    pub fn spawn(
+        runtime: &Handle,
+        link: Option<Link>,
+        http: HttpTransport,
+        status: Option<Arc<StatusPoller>>,
+    ) -> Self {
+        let (queue, queued) = mpsc::channel(OUTBOX_CAPACITY);
+        let sender = Sender { link, http, status };
+        let task = runtime.spawn(sender.run(queued));
+        Self { queue, task }
+    }
+
+    /// Queue `message` without waiting. False when the queue is full, or
+    /// the task is gone; the caller keeps its newer state and tries again.
+    pub fn try_send(&self, message: ControlMessage) -> bool {
+        match self.queue.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                log::debug!("Network is behind, holding the update back");
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Stop taking updates and wait up to `timeout` for the queued ones to
+    /// go out, so the last target is cleared on exit
+    pub fn close(self, runtime: &Runtime, timeout: Duration) {
+        let Outbox { queue, task } = self;
+        drop(queue);
+        if runtime
+            .block_on(async { tokio::time::timeout(timeout, task).await })
+            .is_err()
+        {
+            log::warn!("Gave up on sending the last updates");
+        }
+    }
+}
+
+/// Keep the newest position, depth and scales update of `queued`, in
+/// order, with every other message
+pub fn coalesce(queued: Vec<ControlMessage>) -> Vec<ControlMessage> {
+    let last_position = queued
+        .iter()
+        .rposition(|message| matches!(message, ControlMessage::Position(_)));
+    let last_depth = queued
+        .iter()
+        .rposition(|message| matches!(message, ControlMessage::Depth(_)));
+    let last_scales = queued
+        .iter()
+        .rposition(|message| matches!(message, ControlMessage::Scales(_)));
+    queued
+        .into_iter()
+        .enumerate()
+        .filter(|(i, message)| match message {
+            ControlMessage::Position(_) => Some(*i) == last_position,
+            ControlMessage::Depth(_) => Some(*i) == last_depth,
+            ControlMessage::Scales(_) => Some(*i) == last_scales,
+            ControlMessage::Settings(_) => true,
+        })
+        .map(|(_, message)| message)
+        .collect()
+}
+
+/// The network task's state, moved onto the blocking pool for each round
+struct Sender {
+    link: Option<Link>,
+    http: HttpTransport,
+    status: Option<Arc<StatusPoller>>,
+}
+
+impl Sender {
+    async fn run(mut self, mut queued: mpsc::Receiver<ControlMessage>) {
+        let mut ticks = tokio::time::interval(EVENT_POLL_INTERVAL);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let mut batch = Vec::new();
+            tokio::select! {
+                message = queued.recv() => match message {
+                    Some(message) => batch.push(message),
+                    None => break,
+                },
+                _ = ticks.tick(), if self.link.is_some() => {}
+            }
+            while let Ok(message) = queued.try_recv() {
+                batch.push(message);
+            }
+            let queued_count = batch.len();
+            let batch = coalesce(batch);
+            if batch.len() < queued_count {
+                log::debug!("Dropped {} stale updates", queued_count - batch.len());
+            }
+            self = match tokio::task::spawn_blocking(move || self.round(batch)).await {
+                Ok(sender) => sender,
+                Err(e) => {
+                    log::error!("Network task failed: {}", e);
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Send `batch` and collect what the link pushed meanwhile
+    fn round(mut self, batch: Vec<ControlMessage>) -> Self {
+        for message in &batch {
+            let kind = match message {
+                ControlMessage::Position(_) => "position",
+                ControlMessage::Settings(_) => "settings",
+                ControlMessage::Depth(_) => "depth",
+                ControlMessage::Scales(_) => "scales",
+            };
+            match self.send(message) {
+                Ok(()) => log::debug!("Sent {:?}", message),
+                Err(e @ TransportError::Rejected { .. }) => {
+                    log::warn!("Server rejected {} update: {}", kind, e)
+                }
+                Err(e) => log::warn!("[{}] Failed to send {} update: {}", e.code(), kind, e),
+            }
+        }
+        self.receive();
+        self
+    }
+
+    /// Send over the link, falling back to HTTP for messages it cannot
+    /// carry, and for good once it fails
+    fn send(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        if let Some(ref mut link) = self.link {
+            match link.send(message) {
+                Ok(()) => return Ok(()),
+                Err(TransportError::Unsupported(_)) => {}
+                Err(e) => self.drop_link(e),
+            }
+        }
+        self.http.send(message)
+    }
+
+    fn receive(&mut self) {
+        while let Some(ref mut link) = self.link {
+            match link.next_event() {
+                Ok(Some(ServerEvent::Status(status))) => {
+                    log::debug!("Server status: {:?}", status);
+                    if let Some(ref poller) = self.status {
+                        poller.record(status);
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => self.drop_link(e),
+            }
+        }
+    }
+
+    fn drop_link(&mut self, error: TransportError) {
+        if let Some(link) = self.link.take() {
+            log::warn!(
+                "[{}] {} link failed, falling back to HTTP: {}",
+                ErrorCode::ServerUnreachable,
+                link.name(),
+                error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boid_shared::{DepthUpdate, Position, TargetPositionUpdate};
+
+    fn position(x: f32) -> ControlMessage {
+        ControlMessage::Position(TargetPositionUpdate {
+            position: Some(Position::new(x, 0.0)),
+        })
+    }
+
+    #[test]
+    fn test_coalesce_keeps_the_newest_target_and_depth() {
+        let cleared = ControlMessage::Position(TargetPositionUpdate { position: None });
+        let depth = |depth| ControlMessage::Depth(DepthUpdate { depth: Some(depth) });
+        let sent = coalesce(vec![
+            position(1.0),
+            depth(0.2),
+            position(2.0),
+            depth(0.4),
+            cleared,
+        ]);
+        assert_eq!(sent.len(), 2);
+        assert!(matches!(
+            sent[0],
+            ControlMessage::Depth(DepthUpdate { depth: Some(d) }) if d == 0.4
+        ));
+        assert!(matches!(
+            sent[1],
+            ControlMessage::Position(TargetPositionUpdate { position: None })
+        ));
+        assert_eq!(coalesce(vec![position(3.0)]).len(), 1);
+    }
+
+    #[test]
+    fn test_outbox_is_bounded_and_drains() {
+        // The task only runs inside `block_on` here, so nothing is taken
+        // out while queueing
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // Nothing listens here, so every send fails fast
+        let http = HttpTransport::new("http://127.0.0.1:9").with_timeout(Duration::from_millis(50));
+        let outbox = Outbox::spawn(runtime.handle(), None, http, None);
+        let accepted = (0..20)
+            .filter(|&i| outbox.try_send(position(i as f32)))
+            .count();
+        assert_eq!(accepted, OUTBOX_CAPACITY);
+        outbox.close(&runtime, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,197 @@
+use boid_shared::transport::Transport;
+use boid_shared::StatusResponse;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use crate::http::HttpTransport;
+
+/// A status report and when it arrived
+#[derive(Debug, Clone)]
+pub struct StatusSample {
+    pub status: StatusResponse,
+    pub received: Instant,
+}
+
+/// Warns once when the server's frame rate drops below `threshold`, and
+/// again when it recovers, instead of on every report
+#[derive(Debug, Clone)]
+pub struct FpsAlarm {
+    threshold: u32,
+    low: bool,
+}
+
+impl FpsAlarm {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            low: false,
+        }
+    }
+
+    /// `Some(true)` when `fps` just fell below the threshold, `Some(false)`
+    /// when it just got back to it, `None` otherwise
+    pub fn check(&mut self, fps: u32) -> Option<bool> {
+        let low = fps < self.threshold;
+        if low == self.low {
+            return None;
+        }
+        self.low = low;
+        Some(low)
+    }
+}
+
+struct Shared {
+    latest: Option<StatusSample>,
+    alarm: FpsAlarm,
+}
+
+impl Shared {
+    fn record(&mut self, status: StatusResponse) {
+        match self.alarm.check(status.fps) {
+            Some(true) => log::warn!(
+                "Server running at {} FPS (below {}): the lag is on the device, not the network",
+                status.fps,
+                self.alarm.threshold
+            ),
+            Some(false) => log::info!("Server back at {} FPS", status.fps),
+            None => {}
+        }
+        self.latest = Some(StatusSample {
+            status,
+            received: Instant::now(),
+        });
+    }
+}
+
+/// Polls `GET /api/status` on a tokio task
+///
+/// Statuses pushed over the WebSocket control channel can be fed in with
+/// [`record`](Self::record), so both sources share one view. The task
+/// stops when the poller is dropped.
+pub struct StatusPoller {
+    shared: Arc<Mutex<Shared>>,
+    interval: Duration,
+    task: JoinHandle<()>,
+}
+
+impl StatusPoller {
+    /// Poll `server_url` every `interval` on `runtime`, warning when the
+    /// reported FPS drops below `min_fps`
+    pub fn spawn(runtime: &Handle, server_url: &str, interval: Duration, min_fps: u32) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            latest: None,
+            alarm: FpsAlarm::new(min_fps),
+        }));
+        let transport =
+            HttpTransport::new(server_url).with_timeout(interval.max(Duration::from_secs(1)));
+
+        let task_shared = shared.clone();
+        let task = runtime.spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                // Each poll makes its own connection, and blocks while at it
+                let mut poll = transport.clone();
+                match tokio::task::spawn_blocking(move || poll.fetch_status()).await {
+                    Ok(Ok(status)) => task_shared.lock().unwrap().record(status),
+                    // The ESP32 answers one request at a time, so misses are expected
+                    Ok(Err(e)) => log::debug!("[{}] Status poll failed: {}", e.code(), e),
+                    // The runtime is shutting down
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Self {
+            shared,
+            interval,
+            task,
+        }
+    }
+
+    /// Take a status that arrived some other way
+    pub fn record(&self, status: StatusResponse) {
+        self.shared.lock().unwrap().record(status);
+    }
+
+    pub fn latest(&self) -> Option<StatusSample> {
+        self.shared.lock().unwrap().latest.clone()
+    }
+
+    /// The latest status, unless three polls in a row have missed
+    pub fn current(&self) -> Option<StatusResponse> {
+        self.latest()
+            .filter(|sample| sample.received.elapsed() < self.interval * 3)
+            .map(|sample| sample.status)
+    }
+}
+
+impl Drop for StatusPoller {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_fps_alarm_fires_on_transitions_only() {
+        let mut alarm = FpsAlarm::new(20);
+        assert_eq!(alarm.check(30), None);
+        assert_eq!(alarm.check(12), Some(true));
+        assert_eq!(alarm.check(10), None);
+        assert_eq!(alarm.check(20), Some(false));
+        assert_eq!(alarm.check(25), None);
+    }
+
+    #[test]
+    fn test_poller_fetches_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let body = r#"{"boid_count":20,"fps":12,"target_active":true}"#;
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let poller = StatusPoller::spawn(runtime.handle(), &url, Duration::from_millis(20), 20);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let status = loop {
+            if let Some(status) = poller.current() {
+                break status;
+            }
+            assert!(Instant::now() < deadline, "no status received");
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(status.boid_count, 20);
+        assert_eq!(status.fps, 12);
+        assert!(status.target_active);
+
+        poller.record(StatusResponse {
+            boid_count: 5,
+            fps: 30,
+            target_active: false,
+            controllers: Vec::new(),
+        });
+        assert!(poller.latest().is_some());
+    }
+}
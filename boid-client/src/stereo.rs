@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use boid_shared::stereo::StereoCalibration;
+use boid_shared::ErrorCode;
+use std::path::{Path, PathBuf};
+
+/// Calibration pose the user is holding the hand at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pose {
+    Near,
+    Far,
+}
+
+/// Depth estimation for a stereo camera pair, with its calibration file
+///
+/// Calibrating takes one disparity sample at each pose. Once both are
+/// recorded the calibration is written to the file, so later runs start
+/// calibrated.
+pub struct StereoDepth {
+    path: PathBuf,
+    calibration: Option<StereoCalibration>,
+    near: Option<f32>,
+    far: Option<f32>,
+}
+
+impl StereoDepth {
+    /// Load the calibration from `path`; a missing file starts uncalibrated
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let calibration = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let calibration: StereoCalibration = toml::from_str(&text)
+                    .with_context(|| format!("Invalid stereo calibration {}", path.display()))?;
+                if !calibration.is_valid() {
+                    anyhow::bail!(
+                        "[{}] {} has near and far disparities too close together",
+                        ErrorCode::InvalidCalibration,
+                        path.display()
+                    );
+                }
+                Some(calibration)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path,
+            calibration,
+            near: None,
+            far: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn calibration(&self) -> Option<StereoCalibration> {
+        self.calibration
+    }
+
+    /// Record the disparity measured at `pose`. Returns the new calibration
+    /// once both poses are recorded, after saving it.
+    pub fn record(&mut self, pose: Pose, disparity: f32) -> Result<Option<StereoCalibration>> {
+        match pose {
+            Pose::Near => self.near = Some(disparity),
+            Pose::Far => self.far = Some(disparity),
+        }
+        let (Some(near), Some(far)) = (self.near, self.far) else {
+            return Ok(None);
+        };
+        let calibration = StereoCalibration::new(near, far);
+        if !calibration.is_valid() {
+            // Drop the sample just taken so that pose can be retaken
+            match pose {
+                Pose::Near => self.near = None,
+                Pose::Far => self.far = None,
+            }
+            anyhow::bail!(
+                "[{}] Near and far poses look the same to the cameras; move the hand further",
+                ErrorCode::InvalidCalibration
+            );
+        }
+
+        let text = toml::to_string(&calibration)?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("Failed to save {}", self.path.display()))?;
+        self.calibration = Some(calibration);
+        self.near = None;
+        self.far = None;
+        Ok(Some(calibration))
+    }
+
+    /// Depth from 0 (near pose) to 1 (far pose), once calibrated
+    pub fn depth(&self, disparity: f32) -> Option<f32> {
+        self.calibration?.depth(disparity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_is_recorded_and_reloaded() {
+        let path = std::env::temp_dir().join(format!("boid-stereo-{}.toml", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut stereo = StereoDepth::load(&path).unwrap();
+        assert_eq!(stereo.depth(0.2), None);
+        assert_eq!(stereo.record(Pose::Near, 0.3).unwrap(), None);
+        assert!(stereo.record(Pose::Far, 0.3).is_err());
+        let calibration = stereo.record(Pose::Far, 0.1).unwrap().unwrap();
+        assert_eq!(calibration, StereoCalibration::new(0.3, 0.1));
+        assert_eq!(stereo.depth(0.2), Some(0.5));
+
+        let reloaded = StereoDepth::load(&path).unwrap();
+        assert_eq!(reloaded.calibration(), Some(calibration));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -419,6 +419,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_detector_backends_share_one_interface() -> Result<()> {
+        use boid_client::hand_tracker::{self, DetectorKind, HandTracker};
+        use boid_shared::HandDetectorBackend;
+
+        let pinch = create_pinch_gesture_image(640, 480, 50.0)?;
+        let no_hand = create_no_hand_image(640, 480)?;
+
+        // Through the trait, the OpenCV tracker sees what it sees directly
+        let mut tracker = HandTracker::new()?;
+        let direct = tracker.process_frame(&pinch)?;
+        let mut backend = DetectorKind::Opencv.build()?;
+        assert_eq!(backend.detect(&hand_tracker::frame_of(&pinch)?), direct);
+
+        for kind in [DetectorKind::Opencv, DetectorKind::Heuristic] {
+            let mut detector = kind.build()?;
+            let frame = hand_tracker::frame_of(&no_hand)?;
+            assert_eq!(detector.detect(&frame), None, "{:?}", kind);
+
+            detector.set_artifacts(true);
+            detector.detect(&frame);
+            let artifacts = detector.artifacts().expect("artifacts while recording");
+            assert_eq!((artifacts.width, artifacts.height), (640, 480));
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_hand_tracker_with_real_pinch_images() -> Result<()> {
         use boid_client::hand_tracker::HandTracker;
@@ -852,4 +879,110 @@ mod tests {
         println!("\n[SUCCESS] All real image integration tests passed with assertions!");
         Ok(())
     }
+
+    #[test]
+    fn test_recorded_footage_replays_deterministically() -> Result<()> {
+        use boid_client::frame_source::{FrameSource, ImageDirectory, SourceSpec};
+        use boid_client::hand_tracker::HandTracker;
+
+        let test_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test_images");
+        assert_eq!(
+            SourceSpec::parse(test_dir)?,
+            SourceSpec::Directory(test_dir.into())
+        );
+        // The images are listed once, so ones other tests save meanwhile
+        // are not picked up
+        let mut source = ImageDirectory::open(std::path::Path::new(test_dir))?;
+        let frames = source.paths().len();
+        let mut tracker = HandTracker::new()?;
+        let mut frame = Mat::default();
+        let mut detections = Vec::new();
+        for _ in 0..2 * frames {
+            source.read(&mut frame)?;
+            let hand = tracker.process_frame(&frame)?;
+            detections.push(hand.map(|hand| (hand.index_tip.x, hand.index_tip.y)));
+        }
+
+        // The second pass over the footage sees exactly what the first did
+        let (first, second) = detections.split_at(frames);
+        assert_eq!(first, second);
+        assert!(first.iter().any(|hand| hand.is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_channel_streams_updates() -> Result<()> {
+        use boid_shared::transport::{Transport, WebSocketTransport};
+        use boid_shared::websocket::{self, Opcode};
+        use boid_shared::{ControlMessage, StatusResponse};
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        println!("\n[TEST] Starting control channel test");
+
+        // Minimal /ws server: handshake, read one message, push one status
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let server = std::thread::spawn(move || -> Result<Vec<u8>> {
+            let (mut stream, _) = listener.accept()?;
+            let mut data = Vec::new();
+            let mut chunk = [0u8; 512];
+            while !data.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut chunk)?;
+                data.extend_from_slice(&chunk[..n]);
+            }
+            let request = String::from_utf8(data)?;
+            let key = request
+                .lines()
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+                .expect("handshake should include a key");
+            write!(
+                stream,
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\r\n",
+                websocket::accept_key(key)
+            )?;
+
+            let mut pending = Vec::new();
+            let frame = loop {
+                if let Some((frame, _)) = websocket::decode_frame(&pending).unwrap() {
+                    break frame;
+                }
+                let n = stream.read(&mut chunk)?;
+                pending.extend_from_slice(&chunk[..n]);
+            };
+            assert_eq!(frame.opcode, Opcode::Text);
+
+            let status = serde_json::to_vec(&StatusResponse {
+                boid_count: 20,
+                fps: 30,
+                target_active: true,
+                controllers: Vec::new(),
+            })?;
+            stream.write_all(&websocket::encode_frame(Opcode::Text, &status, None))?;
+            Ok(frame.payload)
+        });
+
+        let mut channel = WebSocketTransport::connect(&format!("http://127.0.0.1:{}", port))?;
+        channel.send_target(&TargetPositionUpdate {
+            position: Some(Position::new(12.0, 34.0)),
+        })?;
+
+        let received = server.join().unwrap()?;
+        let message: ControlMessage = serde_json::from_slice(&received)?;
+        match message {
+            ControlMessage::Position(update) => {
+                assert_eq!(update.position, Some(Position::new(12.0, 34.0)))
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let status = channel.fetch_status()?;
+        assert!(status.target_active);
+
+        println!("[SUCCESS] Control channel test passed");
+        Ok(())
+    }
 }
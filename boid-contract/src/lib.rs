@@ -0,0 +1,187 @@
+//! Wire-format contract between the firmware and its clients
+//!
+//! Clients and firmware share the message types in `boid-shared`, but not
+//! the code that encodes them: the browser, the desktop client and the
+//! ESP32 use `serde_json`, a `no_std` build would use `serde-json-core`,
+//! and the UDP target channel is postcard. A field added to a type, or an
+//! attribute only one encoder honors, passes every unit test of the crate
+//! that changed it and breaks the other side in the field.
+//!
+//! So every message has a sample value and a fixture under `fixtures/`
+//! holding its exact bytes on the wire. [`check`] has each [`Codec`] that
+//! carries the type encode the sample to the fixture and decode the fixture
+//! back. [`check_compat`] covers the other forms a message takes in the
+//! field: sent by older peers without the fields added since, or by newer
+//! ones with fields this side does not know. Changing a message means
+//! changing its fixture, which shows up in review.
+//!
+//! A new encoding is one more [`Codec`] variant.
+
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Largest message the fixed-buffer encoders are given room for
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// An encoding messages travel in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// `serde_json`, used by clients and the ESP32 firmware
+    SerdeJson,
+    /// `serde-json-core`, for `no_std` firmware without an allocator
+    SerdeJsonCore,
+    /// postcard, used by the UDP target channel (see `boid_shared::udp`)
+    Postcard,
+}
+
+impl Codec {
+    /// Both JSON encoders, for types `boid-shared` builds without `std`
+    pub const JSON: &'static [Codec] = &[Codec::SerdeJson, Codec::SerdeJsonCore];
+    /// `serde_json` alone, for types that need `std`
+    pub const STD_JSON: &'static [Codec] = &[Codec::SerdeJson];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::SerdeJson => "serde_json",
+            Codec::SerdeJsonCore => "serde-json-core",
+            Codec::Postcard => "postcard",
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0; MAX_MESSAGE_LEN];
+        let len = match self {
+            Codec::SerdeJson => return serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Codec::SerdeJsonCore => {
+                serde_json_core::to_slice(value, &mut buf).map_err(|e| e.to_string())?
+            }
+            Codec::Postcard => postcard::to_slice(value, &mut buf)
+                .map_err(|e| e.to_string())?
+                .len(),
+        };
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Codec::SerdeJson => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Codec::SerdeJsonCore => match serde_json_core::from_slice(bytes) {
+                Ok((value, len)) if len == bytes.len() => Ok(value),
+                Ok((_, len)) => Err(format!("{} trailing bytes", bytes.len() - len)),
+                Err(e) => Err(e.to_string()),
+            },
+            Codec::Postcard => match postcard::take_from_bytes(bytes) {
+                Ok((value, [])) => Ok(value),
+                Ok((_, rest)) => Err(format!("{} trailing bytes", rest.len())),
+                Err(e) => Err(e.to_string()),
+            },
+        }
+    }
+
+    /// JSON fixtures are shared by both JSON encoders; binary ones are
+    /// stored as hex
+    fn fixture_path(self, dir: &str, name: &str) -> PathBuf {
+        let file = match self {
+            Codec::SerdeJson | Codec::SerdeJsonCore => format!("json/{dir}{name}.json"),
+            Codec::Postcard => format!("postcard/{dir}{name}.hex"),
+        };
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(file)
+    }
+}
+
+/// The bytes of fixture `name` for `codec`. A trailing newline in JSON and
+/// whitespace in hex are not part of the message.
+///
+/// # Panics
+///
+/// If the fixture is missing or is not valid hex.
+pub fn fixture(codec: Codec, name: &str) -> Vec<u8> {
+    read_fixture(codec, "", name)
+}
+
+fn read_fixture(codec: Codec, dir: &str, name: &str) -> Vec<u8> {
+    let path = codec.fixture_path(dir, name);
+    let text =
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    match codec {
+        Codec::SerdeJson | Codec::SerdeJsonCore => text.trim_end().as_bytes().to_vec(),
+        Codec::Postcard => {
+            decode_hex(&text).unwrap_or_else(|| panic!("{}: not hex", path.display()))
+        }
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Show bytes as text when they are, for readable failures
+fn show(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|b| format!("{b:02x}")).collect(),
+    }
+}
+
+/// Every codec in `codecs` encodes `sample` to fixture `name`, and decodes
+/// the fixture to a value that encodes to it again
+///
+/// # Panics
+///
+/// On the first codec that disagrees with the fixture.
+pub fn check<T: Serialize + DeserializeOwned>(name: &str, sample: &T, codecs: &[Codec]) {
+    for &codec in codecs {
+        let expected = fixture(codec, name);
+        let encoded = codec
+            .encode(sample)
+            .unwrap_or_else(|e| panic!("{name}: {} can't encode: {e}", codec.name()));
+        assert_eq!(
+            show(&encoded),
+            show(&expected),
+            "{name}: {} encodes differently from the fixture",
+            codec.name()
+        );
+        let decoded: T = codec
+            .decode(&expected)
+            .unwrap_or_else(|e| panic!("{name}: {} can't decode: {e}", codec.name()));
+        assert_eq!(
+            show(&codec.encode(&decoded).unwrap()),
+            show(&expected),
+            "{name}: {} decodes to a different value",
+            codec.name()
+        );
+    }
+}
+
+/// Every codec in `codecs` decodes fixture `compat/<name>`, a form of the
+/// message other than the one [`check`] pins, to `expected`
+///
+/// # Panics
+///
+/// On the first codec that can't decode the fixture or decodes it to
+/// something else.
+pub fn check_compat<T: Serialize + DeserializeOwned>(name: &str, expected: &T, codecs: &[Codec]) {
+    for &codec in codecs {
+        let bytes = read_fixture(codec, "compat/", name);
+        let decoded: T = codec
+            .decode(&bytes)
+            .unwrap_or_else(|e| panic!("compat/{name}: {} can't decode: {e}", codec.name()));
+        assert_eq!(
+            show(&codec.encode(&decoded).unwrap()),
+            show(&codec.encode(expected).unwrap()),
+            "compat/{name}: {} decodes to a different value",
+            codec.name()
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Every message of the boid protocol against its fixture
+//!
+//! Types `boid-shared` builds without `std` are checked with both JSON
+//! encoders, since `no_std` firmware reads and writes them with
+//! `serde-json-core`; the rest only ever travel through `serde_json`.
+//! Samples set every optional field, so a field one encoder drops shows;
+//! the `compat` fixtures leave them out.
+
+use boid_contract::{check, check_compat, fixture, Codec};
+use boid_shared::arbitration::{ArbitrationConfig, ArbitrationPolicy};
+use boid_shared::demo::{DemoScene, DemoSchedule, ScriptedTarget, TargetPath, Theme};
+use boid_shared::mirror::{BoidState, FlockFrame};
+use boid_shared::stereo::StereoCalibration;
+use boid_shared::style::BoidShape;
+use boid_shared::{
+    udp, BatchCommand, BatchResponse, BatchResult, BoidCountResponse, BoidCountUpdate,
+    BoidSettings, ColorScheme, ColorSchemeUpdate, ConfigChangeEntry, ConfigHistoryResponse,
+    ControlMessage, DepthUpdate, ErrorBody, ErrorCode, ErrorCodeInfo, FullHandLandmarks,
+    GustSettings, HandGesture, HandLandmarks, Handedness, HealthResponse, LensCalibration,
+    MultiHandLandmarks, ParameterScalesUpdate, Position, PresetUpdate, RenderStyle,
+    SettingAdjustment, SettingsUpdate, SettingsUpdateResponse, Smoothing, SpellRequest,
+    StatusResponse, StreamPowerResponse, TargetPositionUpdate, TrackedHand, WindUpdate,
+    HAND_LANDMARK_COUNT,
+};
+
+fn hand() -> HandLandmarks {
+    HandLandmarks::new(Position::new(120.5, 80.0), Position::new(140.0, 75.25))
+}
+
+fn settings() -> BoidSettings {
+    BoidSettings {
+        seek_radius: Some(150.0),
+        ..BoidSettings::default()
+    }
+}
+
+fn settings_update() -> SettingsUpdate {
+    SettingsUpdate {
+        settings: settings(),
+        boid_count: Some(24),
+    }
+}
+
+fn target() -> TargetPositionUpdate {
+    TargetPositionUpdate {
+        position: Some(Position::new(64.0, 32.5)),
+    }
+}
+
+fn full_hand() -> FullHandLandmarks {
+    let points = core::array::from_fn(|i| Position::new(i as f32 * 4.0, 200.0 - i as f32 * 8.0));
+    let mut hand = FullHandLandmarks::new(points);
+    hand.visibility = Some([0.75; HAND_LANDMARK_COUNT]);
+    hand
+}
+
+fn adjustment() -> SettingAdjustment {
+    SettingAdjustment {
+        field: "max_speed".to_string(),
+        requested: 40.0,
+        applied: 20.0,
+    }
+}
+
+fn noise_wind() -> WindUpdate {
+    WindUpdate {
+        force_x: 0.05,
+        force_y: -0.025,
+        gust: GustSettings::Noise {
+            amplitude_x: 0.1,
+            amplitude_y: 0.0,
+            period: 4.0,
+            seed: 7,
+        },
+    }
+}
+
+#[test]
+fn test_hand_tracking_messages() {
+    check("position", &Position::new(12.5, -3.0), Codec::JSON);
+    check(
+        "hand_landmarks",
+        &hand().with_depth(Some(0.25)),
+        Codec::JSON,
+    );
+    let tracked = TrackedHand {
+        handedness: Handedness::Left,
+        landmarks: hand(),
+    };
+    check("tracked_hand", &tracked, Codec::JSON);
+    check("full_hand_landmarks", &full_hand(), Codec::JSON);
+    check("hand_gesture", &HandGesture::OpenPalm, Codec::JSON);
+    check(
+        "depth_update",
+        &DepthUpdate { depth: Some(0.5) },
+        Codec::JSON,
+    );
+    check(
+        "lens_calibration",
+        &LensCalibration::new(-0.25, 0.0625),
+        Codec::JSON,
+    );
+    check(
+        "stereo_calibration",
+        &StereoCalibration::new(0.125, 0.03125),
+        Codec::JSON,
+    );
+
+    let mut hands = MultiHandLandmarks::new();
+    hands.insert(tracked);
+    hands.insert(TrackedHand {
+        handedness: Handedness::Right,
+        landmarks: hand().with_depth(Some(0.75)),
+    });
+    check("multi_hand_landmarks", &hands, Codec::STD_JSON);
+    let exponential = Smoothing::Exponential { alpha: 0.5 };
+    check("smoothing_exponential", &exponential, Codec::STD_JSON);
+    let kalman = Smoothing::Kalman {
+        process_noise: 0.5,
+        measurement_noise: 8.0,
+    };
+    check("smoothing_kalman", &kalman, Codec::STD_JSON);
+}
+
+#[test]
+fn test_control_messages() {
+    check("target_position_update", &target(), Codec::JSON);
+    let cleared = TargetPositionUpdate { position: None };
+    check("target_position_cleared", &cleared, Codec::JSON);
+    check("boid_settings", &settings(), Codec::JSON);
+    check("settings_update", &settings_update(), Codec::JSON);
+    check(
+        "boid_count_update",
+        &BoidCountUpdate { count: 12 },
+        Codec::JSON,
+    );
+    let scheme = ColorSchemeUpdate {
+        scheme: ColorScheme::OkabeIto,
+    };
+    check("color_scheme_update", &scheme, Codec::JSON);
+    let preset = PresetUpdate {
+        settings: settings(),
+        duration_ticks: 90,
+    };
+    check("preset_update", &preset, Codec::JSON);
+    let style = RenderStyle {
+        size: 1.5,
+        palette: ColorScheme::HighContrast,
+        shape: BoidShape::Arrow,
+    };
+    check("render_style", &style, Codec::JSON);
+
+    check("wind_update", &noise_wind(), Codec::STD_JSON);
+    let spell = SpellRequest {
+        text: "HI".to_string(),
+        hold_secs: 5,
+    };
+    check("spell_request", &spell, Codec::STD_JSON);
+    let scales = ParameterScalesUpdate {
+        scales: [
+            ("max_speed".to_string(), 1.5),
+            ("separation_weight".to_string(), 2.25),
+        ]
+        .into(),
+    };
+    check("parameter_scales_update", &scales, Codec::STD_JSON);
+    let control = [
+        ControlMessage::Position(target()),
+        ControlMessage::Settings(settings_update()),
+        ControlMessage::Depth(DepthUpdate { depth: None }),
+        ControlMessage::Scales(scales),
+    ];
+    check("control_messages", &control, Codec::STD_JSON);
+    let batch = [
+        BatchCommand::Position(target()),
+        BatchCommand::Settings(settings_update()),
+        BatchCommand::Preset(preset),
+        BatchCommand::Boids(BoidCountUpdate { count: 12 }),
+        BatchCommand::Wind(WindUpdate {
+            force_x: 0.0,
+            force_y: 0.125,
+            gust: GustSettings::Sine {
+                amplitude_x: 0.25,
+                amplitude_y: 0.0,
+                period: 2.0,
+            },
+        }),
+        BatchCommand::Colors(scheme),
+    ];
+    check("batch_commands", &batch, Codec::STD_JSON);
+    let arbitration = ArbitrationConfig {
+        policy: ArbitrationPolicy::Priority,
+        lease_ms: 1500,
+        priorities: [("kiosk".to_string(), 10), ("phone".to_string(), -1)].into(),
+    };
+    check("arbitration_config", &arbitration, Codec::STD_JSON);
+    let schedule = DemoSchedule {
+        scenes: vec![
+            DemoScene {
+                duration_ticks: 300,
+                settings: Some(settings()),
+                spell: None,
+                target: Some(ScriptedTarget {
+                    path: TargetPath::FigureEight,
+                    period_ticks: 240,
+                }),
+                theme: Some(Theme::Ember),
+            },
+            DemoScene {
+                duration_ticks: 150,
+                settings: None,
+                spell: Some("BOID".to_string()),
+                target: None,
+                theme: None,
+            },
+        ],
+        transition_ticks: 45,
+    };
+    check("demo_schedule", &schedule, Codec::STD_JSON);
+}
+
+#[test]
+fn test_device_replies() {
+    let counts = BoidCountResponse {
+        boid_count: 12,
+        capacity: 20,
+    };
+    check("boid_count_response", &counts, Codec::JSON);
+    let power = StreamPowerResponse {
+        powered: true,
+        idle_timeout_secs: 30,
+    };
+    check("stream_power_response", &power, Codec::JSON);
+    let health = HealthResponse {
+        display_ok: true,
+        camera_ok: false,
+        wifi_rssi: Some(-61),
+        psram_present: true,
+        nvs_ok: true,
+    };
+    check("health_response", &health, Codec::JSON);
+
+    let status = StatusResponse {
+        boid_count: 20,
+        fps: 30,
+        target_active: true,
+        controllers: vec!["kiosk".to_string()],
+    };
+    check("status_response", &status, Codec::STD_JSON);
+    let updated = SettingsUpdateResponse {
+        status: "ok".to_string(),
+        adjusted: vec![adjustment()],
+    };
+    check("settings_update_response", &updated, Codec::STD_JSON);
+    let history = ConfigHistoryResponse {
+        changes: vec![ConfigChangeEntry {
+            field: "seek_weight".to_string(),
+            source: "api".to_string(),
+            timestamp_ms: 125_000,
+        }],
+    };
+    check("config_history_response", &history, Codec::STD_JSON);
+    let error = ErrorBody::new(ErrorCode::InvalidSetting);
+    check("error_body", &error, Codec::STD_JSON);
+    let info = ErrorCodeInfo::from(ErrorCode::InvalidSetting);
+    check("error_code_info", &info, Codec::STD_JSON);
+    let rejected = BatchResponse {
+        applied: false,
+        results: vec![
+            BatchResult {
+                error: None,
+                adjusted: vec![adjustment()],
+            },
+            BatchResult {
+                error: Some(error.clone()),
+                adjusted: Vec::new(),
+            },
+        ],
+        error: Some(error),
+    };
+    check("batch_response", &rejected, Codec::STD_JSON);
+    let frame = FlockFrame {
+        frame: 42,
+        width: 240.0,
+        height: 240.0,
+        boids: vec![
+            BoidState {
+                x: 10.5,
+                y: 20.0,
+                vx: -1.25,
+                vy: 0.5,
+                group: 0,
+            },
+            BoidState {
+                x: 200.0,
+                y: 3.0,
+                vx: 0.0,
+                vy: 2.0,
+                group: 2,
+            },
+        ],
+    };
+    check("flock_frame", &frame, Codec::STD_JSON);
+}
+
+/// The UDP channel and the binary mirror frame have encoders of their own,
+/// which must write what the fixtures hold
+#[test]
+fn test_binary_messages() {
+    for (name, update) in [
+        ("target_position_update", target()),
+        (
+            "target_position_cleared",
+            TargetPositionUpdate { position: None },
+        ),
+    ] {
+        check(name, &update, &[Codec::Postcard]);
+        let datagram = fixture(Codec::Postcard, name);
+        let mut buf = [0; udp::MAX_DATAGRAM_LEN];
+        assert_eq!(udp::encode_target(&update, &mut buf).unwrap(), datagram);
+        let decoded = udp::decode_target(&datagram).unwrap();
+        assert_eq!(decoded.position, update.position);
+    }
+
+    let json = fixture(Codec::SerdeJson, "flock_frame");
+    let frame: FlockFrame = serde_json::from_slice(&json).unwrap();
+    let binary = fixture(Codec::Postcard, "flock_frame_binary");
+    assert_eq!(frame.to_bytes(), binary);
+    assert_eq!(FlockFrame::from_bytes(&binary), Some(frame));
+}
+
+/// Older peers leave out fields added since; newer ones send fields this
+/// side doesn't know yet. Both must still be understood.
+#[test]
+fn test_other_forms_are_accepted() {
+    check_compat("hand_landmarks_without_depth", &hand(), Codec::JSON);
+    let no_visibility = FullHandLandmarks::new(full_hand().points);
+    check_compat(
+        "full_hand_landmarks_without_visibility",
+        &no_visibility,
+        Codec::JSON,
+    );
+    check_compat(
+        "boid_settings_without_seek_radius",
+        &BoidSettings::default(),
+        Codec::JSON,
+    );
+    let no_count = SettingsUpdate {
+        settings: BoidSettings {
+            seek_radius: Some(0.0),
+            ..BoidSettings::default()
+        },
+        boid_count: None,
+    };
+    check_compat("settings_update_without_boid_count", &no_count, Codec::JSON);
+    let immediate = PresetUpdate {
+        settings: settings(),
+        duration_ticks: 0,
+    };
+    check_compat("preset_update_without_duration", &immediate, Codec::JSON);
+    check_compat("target_position_update_newer", &target(), Codec::JSON);
+    let dots = RenderStyle {
+        shape: BoidShape::Dot,
+        ..RenderStyle::default()
+    };
+    check_compat("render_style_shape_only", &dots, Codec::JSON);
+    check_compat(
+        "health_response_newer",
+        &HealthResponse::default(),
+        Codec::JSON,
+    );
+
+    let status = StatusResponse {
+        boid_count: 20,
+        fps: 30,
+        target_active: false,
+        controllers: Vec::new(),
+    };
+    check_compat(
+        "status_response_without_controllers",
+        &status,
+        Codec::STD_JSON,
+    );
+    let spell = SpellRequest {
+        text: "HI".to_string(),
+        hold_secs: 0,
+    };
+    check_compat("spell_request_without_hold", &spell, Codec::STD_JSON);
+    let calm = WindUpdate {
+        force_x: 0.05,
+        force_y: 0.0,
+        gust: GustSettings::Calm,
+    };
+    check_compat("wind_update_without_gust", &calm, Codec::STD_JSON);
+    let unseeded = WindUpdate {
+        gust: GustSettings::Noise {
+            amplitude_x: 0.1,
+            amplitude_y: 0.0,
+            period: 4.0,
+            seed: 0,
+        },
+        ..noise_wind()
+    };
+    check_compat("wind_update_without_seed", &unseeded, Codec::STD_JSON);
+}
@@ -0,0 +1,82 @@
+//! Embedded flock configuration walk-through
+//!
+//! Mirrors how the ESP32 firmware sets up its simulation: a fixed-capacity
+//! `Flock<N>` with no heap allocation, seeded from a deterministic LCG and
+//! configured from the `BoidSettings` that clients send to `/api/settings`.
+//! The result is printed as a coarse ASCII frame instead of being drawn on
+//! the LCD.
+//!
+//! Run with: `cargo run -p boid-core --example embedded_flock`
+
+use boid_core::{Boid, BoidConfig, Flock, Vector2D};
+use boid_shared::BoidSettings;
+
+// Same values as boid-esp32/src/main.rs
+const DISPLAY_WIDTH: f32 = 240.0;
+const DISPLAY_HEIGHT: f32 = 240.0;
+const NUM_BOIDS: usize = 20;
+
+const ASCII_COLS: usize = 48;
+const ASCII_ROWS: usize = 24;
+
+/// Linear congruential generator, as used by the firmware's `SimpleRng`
+struct Lcg(u32);
+
+impl Lcg {
+    fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1664525).wrapping_add(1013904223);
+        self.0 as f32 / u32::MAX as f32
+    }
+}
+
+fn print_frame(flock: &Flock<NUM_BOIDS>) {
+    let mut grid = [[b'.'; ASCII_COLS]; ASCII_ROWS];
+    for boid in flock.boids.iter() {
+        let col = (boid.position.x / flock.width * ASCII_COLS as f32) as usize;
+        let row = (boid.position.y / flock.height * ASCII_ROWS as f32) as usize;
+        grid[row.min(ASCII_ROWS - 1)][col.min(ASCII_COLS - 1)] = b'>';
+    }
+    for row in grid.iter() {
+        println!("{}", String::from_utf8_lossy(row));
+    }
+}
+
+fn main() {
+    // Step 1: start from the defaults shared with every frontend
+    let defaults = BoidConfig::default();
+
+    // Step 2: apply the fields a client can change over HTTP
+    let settings = BoidSettings {
+        separation_weight: 2.0,
+        cohesion_weight: 1.2,
+        max_speed: 2.5,
+        seek_radius: Some(80.0),
+        ..BoidSettings::default()
+    };
+    let config = settings.apply_to(defaults);
+    println!("Config: {:#?}", config);
+
+    // Step 3: fill the fixed-capacity flock; add_boid hands the boid back when full
+    let mut flock = Flock::<NUM_BOIDS>::new(DISPLAY_WIDTH, DISPLAY_HEIGHT, config);
+    let mut rng = Lcg(12345);
+    loop {
+        let position = Vector2D::new(
+            rng.next_f32() * DISPLAY_WIDTH,
+            rng.next_f32() * DISPLAY_HEIGHT,
+        );
+        let velocity = Vector2D::new((rng.next_f32() - 0.5) * 4.0, (rng.next_f32() - 0.5) * 4.0);
+        if flock.add_boid(Boid::new(position, velocity)).is_err() {
+            break;
+        }
+    }
+    println!("Flock filled to capacity: {} boids", flock.boids.len());
+
+    // Step 4: run the main loop for ~3 seconds at 30 FPS, with a hand
+    // target in one corner for the last second; only boids within
+    // `seek_radius` of it are pulled in
+    let target = Vector2D::new(DISPLAY_WIDTH * 0.25, DISPLAY_HEIGHT * 0.25);
+    for frame in 0..90 {
+        flock.update_with_target((frame >= 60).then_some(target));
+    }
+    print_frame(&flock);
+}
@@ -0,0 +1,70 @@
+//! Minimal native flock
+//!
+//! Runs a `FlockStd` for a few hundred ticks, first flying freely and then
+//! seeking a target, and inspects the individual steering forces of one boid
+//! with the `behavior` module.
+//!
+//! Run with: `cargo run -p boid-core --example minimal_flock`
+
+use boid_core::{behavior, BoidConfig, FlockStd, Vector2D};
+
+const WIDTH: f32 = 800.0;
+const HEIGHT: f32 = 600.0;
+
+fn center_of_mass(flock: &FlockStd) -> Vector2D {
+    let sum = flock
+        .boids
+        .iter()
+        .fold(Vector2D::zero(), |acc, boid| acc + boid.position);
+    sum / flock.boids.len() as f32
+}
+
+fn main() {
+    let config = BoidConfig {
+        max_speed: 3.0,
+        ..BoidConfig::default()
+    };
+    let mut flock = FlockStd::new_with_config(WIDTH, HEIGHT, 50, config);
+
+    println!("Free flight");
+    for tick in 0..200 {
+        flock.update();
+        if tick % 50 == 0 {
+            let com = center_of_mass(&flock);
+            println!(
+                "  tick {:3}: center of mass ({:.1}, {:.1})",
+                tick, com.x, com.y
+            );
+        }
+    }
+
+    let target = Vector2D::new(WIDTH / 2.0, HEIGHT / 2.0);
+    println!("Seeking ({:.0}, {:.0})", target.x, target.y);
+    for tick in 0..200 {
+        flock.update_with_target(Some(target));
+        if tick % 50 == 0 {
+            let com = center_of_mass(&flock);
+            println!(
+                "  tick {:3}: center of mass ({:.1}, {:.1}), distance to target {:.1}",
+                tick,
+                com.x,
+                com.y,
+                com.distance(&target)
+            );
+        }
+    }
+
+    // The behavior functions can also be evaluated on their own
+    let boid = &flock.boids[0];
+    let others = flock.boids.iter();
+    let separation = behavior::separation(boid, others.clone(), &flock.config);
+    let alignment = behavior::alignment(boid, others.clone(), &flock.config);
+    let cohesion = behavior::cohesion(boid, others, &flock.config);
+    let seek = behavior::seek(boid, target, &flock.config);
+
+    println!("Forces on boid 0:");
+    println!("  separation {:?}", separation);
+    println!("  alignment  {:?}", alignment);
+    println!("  cohesion   {:?}", cohesion);
+    println!("  seek       {:?}", seek);
+}
@@ -0,0 +1,64 @@
+//! Per-phase tick timings
+//!
+//! Runs `FlockStd::tick_instrumented` over a range of flock sizes, with and
+//! without the spatial grid, and prints the average time spent in neighbor
+//! search, force evaluation and integration. These are the same numbers the
+//! WASM HUD shows live.
+//!
+//! Run with: `cargo run --release -p boid-core --example phase_timing`
+
+use boid_core::{BoidConfig, FlockStd, TickTiming};
+
+const WIDTH: f32 = 1920.0;
+const HEIGHT: f32 = 1080.0;
+const WARMUP_TICKS: usize = 20;
+const MEASURED_TICKS: usize = 100;
+
+fn average_timing(count: usize, spatial_grid: bool) -> TickTiming {
+    let config = BoidConfig {
+        spatial_grid,
+        ..BoidConfig::default()
+    };
+    let mut flock = FlockStd::new_with_config(WIDTH, HEIGHT, count, config);
+
+    for _ in 0..WARMUP_TICKS {
+        flock.update();
+    }
+
+    let mut sum = TickTiming::default();
+    for _ in 0..MEASURED_TICKS {
+        let timing = flock.tick_instrumented(None);
+        sum.neighbor_search_ms += timing.neighbor_search_ms;
+        sum.forces_ms += timing.forces_ms;
+        sum.integration_ms += timing.integration_ms;
+    }
+
+    let ticks = MEASURED_TICKS as f64;
+    TickTiming {
+        neighbor_search_ms: sum.neighbor_search_ms / ticks,
+        forces_ms: sum.forces_ms / ticks,
+        integration_ms: sum.integration_ms / ticks,
+    }
+}
+
+fn main() {
+    println!(
+        "{:>6}  {:>5}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "boids", "grid", "search ms", "forces ms", "integ. ms", "total ms"
+    );
+
+    for count in [100, 500, 1000, 2000, 5000] {
+        for spatial_grid in [false, true] {
+            let timing = average_timing(count, spatial_grid);
+            println!(
+                "{:>6}  {:>5}  {:>10.3}  {:>10.3}  {:>10.3}  {:>10.3}",
+                count,
+                spatial_grid,
+                timing.neighbor_search_ms,
+                timing.forces_ms,
+                timing.integration_ms,
+                timing.total_ms()
+            );
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Record a run to an animated GIF or a PNG sequence
+//!
+//! Seeds a flock, lets it settle, then circles a target around the area.
+//! The same arguments always produce the same file, so CI keeps the output
+//! as a visual regression artifact.
+//!
+//! Run with: `cargo run --release -p boid-core --features record --example record -- flock.gif`
+//!
+//! A path without a `.gif` extension is treated as a directory of PNG
+//! frames. An optional second argument sets the number of frames.
+
+use boid_core::{record::Recorder, FlockStd, Vector2D};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+const WIDTH: f32 = 320.0;
+const HEIGHT: f32 = 240.0;
+const BOIDS: usize = 80;
+const SEED: u64 = 42;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let output = PathBuf::from(args.next().unwrap_or_else(|| "flock.gif".to_string()));
+    let frames = match args.next() {
+        Some(frames) => frames.parse()?,
+        None => 150,
+    };
+
+    let mut flock = FlockStd::new_with_seed(WIDTH, HEIGHT, BOIDS, SEED);
+    for (i, boid) in flock.boids.iter_mut().enumerate() {
+        boid.group = (i % 2) as u8;
+    }
+    let recorder = Recorder::new(frames).with_target(|tick| {
+        let angle = tick as f32 * 0.03;
+        (tick >= 60).then(|| {
+            Vector2D::new(
+                WIDTH / 2.0 + 90.0 * angle.cos(),
+                HEIGHT / 2.0 + 70.0 * angle.sin(),
+            )
+        })
+    });
+
+    if output.extension().is_some_and(|ext| ext == "gif") {
+        recorder.write_gif(&mut flock, BufWriter::new(File::create(&output)?))?;
+        println!("Wrote {} frames to {}", frames, output.display());
+    } else {
+        let paths = recorder.write_png_sequence(&mut flock, &output)?;
+        println!("Wrote {} frames to {}/", paths.len(), output.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,201 @@
+//! Packed copies of boid vectors for batch math
+//!
+//! `Boid` keeps position and velocity side by side, which suits per-boid
+//! rules but not scans over the whole flock. [`Vectors`] holds the same
+//! values as separate x and y arrays, so a scan reads contiguous lanes. With
+//! the `simd` feature the scans use SSE2 on x86_64 and NEON on aarch64, four
+//! boids at a time; elsewhere, and for the leftover boids, they run one boid
+//! at a time. Both paths round the same way, so results match bit for bit.
+//!
+//! The packed arrays are a snapshot: `FlockStd::boids` stays the source of
+//! truth and a copy is reloaded whenever it is needed.
+
+use crate::Vector2D;
+
+/// Vectors stored as separate x and y arrays
+#[derive(Debug, Clone, Default)]
+pub struct Vectors {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+}
+
+impl Vectors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the contents with `values`, keeping the allocation
+    pub fn load(&mut self, values: impl IntoIterator<Item = Vector2D>) {
+        self.xs.clear();
+        self.ys.clear();
+        for value in values {
+            self.xs.push(value.x);
+            self.ys.push(value.y);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    /// Vectors the buffers hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.xs.capacity().min(self.ys.capacity())
+    }
+
+    pub fn get(&self, index: usize) -> Option<Vector2D> {
+        Some(Vector2D::new(*self.xs.get(index)?, self.ys[index]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Vector2D> + '_ {
+        self.xs
+            .iter()
+            .zip(&self.ys)
+            .map(|(&x, &y)| Vector2D::new(x, y))
+    }
+
+    /// Append the indices of the vectors closer than `radius` to `center`
+    /// to `out`, in order. Distances are measured as in
+    /// [`Vector2D::distance`], so this keeps exactly the indices a filter
+    /// over `distance(..) < radius` would.
+    pub fn within(&self, center: Vector2D, radius: f32, out: &mut Vec<usize>) {
+        let packed = simd::within(&self.xs, &self.ys, center, radius, out);
+        for index in packed..self.len() {
+            let dx = self.xs[index] - center.x;
+            let dy = self.ys[index] - center.y;
+            if (dx * dx + dy * dy).sqrt() < radius {
+                out.push(index);
+            }
+        }
+    }
+}
+
+/// Four-lane kernels. Each handles the longest prefix that fills whole
+/// lanes and returns its length; the caller finishes the rest.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd {
+    use crate::Vector2D;
+    use core::arch::x86_64::*;
+
+    pub fn within(
+        xs: &[f32],
+        ys: &[f32],
+        center: Vector2D,
+        radius: f32,
+        out: &mut Vec<usize>,
+    ) -> usize {
+        let packed = xs.len().min(ys.len()) / 4 * 4;
+        // SAFETY: SSE2 is part of the x86_64 baseline, and every load reads
+        // four floats below `packed`
+        unsafe {
+            let cx = _mm_set1_ps(center.x);
+            let cy = _mm_set1_ps(center.y);
+            let r = _mm_set1_ps(radius);
+            for i in (0..packed).step_by(4) {
+                let dx = _mm_sub_ps(_mm_loadu_ps(xs.as_ptr().add(i)), cx);
+                let dy = _mm_sub_ps(_mm_loadu_ps(ys.as_ptr().add(i)), cy);
+                let distance = _mm_sqrt_ps(_mm_add_ps(_mm_mul_ps(dx, dx), _mm_mul_ps(dy, dy)));
+                push_lanes(_mm_movemask_ps(_mm_cmplt_ps(distance, r)) as u32, i, out);
+            }
+        }
+        packed
+    }
+
+    fn push_lanes(mut mask: u32, base: usize, out: &mut Vec<usize>) {
+        while mask != 0 {
+            out.push(base + mask.trailing_zeros() as usize);
+            mask &= mask - 1;
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod simd {
+    use crate::Vector2D;
+    use core::arch::aarch64::*;
+
+    pub fn within(
+        xs: &[f32],
+        ys: &[f32],
+        center: Vector2D,
+        radius: f32,
+        out: &mut Vec<usize>,
+    ) -> usize {
+        let packed = xs.len().min(ys.len()) / 4 * 4;
+        // SAFETY: NEON is part of the aarch64 baseline, and every load reads
+        // four floats below `packed`
+        unsafe {
+            let cx = vdupq_n_f32(center.x);
+            let cy = vdupq_n_f32(center.y);
+            let r = vdupq_n_f32(radius);
+            let mut lanes = [0u32; 4];
+            for i in (0..packed).step_by(4) {
+                let dx = vsubq_f32(vld1q_f32(xs.as_ptr().add(i)), cx);
+                let dy = vsubq_f32(vld1q_f32(ys.as_ptr().add(i)), cy);
+                let distance = vsqrtq_f32(vaddq_f32(vmulq_f32(dx, dx), vmulq_f32(dy, dy)));
+                vst1q_u32(lanes.as_mut_ptr(), vcltq_f32(distance, r));
+                for (lane, &hit) in lanes.iter().enumerate() {
+                    if hit != 0 {
+                        out.push(i + lane);
+                    }
+                }
+            }
+        }
+        packed
+    }
+}
+
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+mod simd {
+    use crate::Vector2D;
+
+    pub fn within(_: &[f32], _: &[f32], _: Vector2D, _: f32, _: &mut Vec<usize>) -> usize {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_within_matches_distance_filter() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let points: Vec<Vector2D> = (0..103)
+            .map(|_| Vector2D::new(rng.gen_range(0.0..200.0), rng.gen_range(0.0..200.0)))
+            .collect();
+        let mut packed = Vectors::new();
+        packed.load(points.iter().copied());
+        assert_eq!(packed.len(), points.len());
+        assert!(packed.iter().eq(points.iter().copied()));
+
+        for center in points.iter().take(10) {
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|&i| points[i].distance(center) < 50.0)
+                .collect();
+            let mut found = Vec::new();
+            packed.within(*center, 50.0, &mut found);
+            assert_eq!(found, expected);
+        }
+
+        // A point exactly on the radius is outside, and NaN never matches
+        packed.load([
+            Vector2D::new(3.0, 4.0),
+            Vector2D::new(f32::NAN, 0.0),
+            Vector2D::new(0.0, 4.9),
+            Vector2D::new(5.0, 0.0),
+            Vector2D::new(-5.0, 0.0),
+        ]);
+        let mut found = Vec::new();
+        packed.within(Vector2D::zero(), 5.0, &mut found);
+        assert_eq!(found, [2]);
+        assert_eq!(packed.get(0), Some(Vector2D::new(3.0, 4.0)));
+        assert_eq!(packed.get(5), None);
+    }
+}
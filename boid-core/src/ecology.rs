@@ -0,0 +1,309 @@
+//! Population dynamics for long-running displays: boids age, breed and die
+//!
+//! With an [`EcologyConfig`] set on a `FlockStd`, every boid carries a
+//! [`Life`]: its age and its energy. Every boid slowly regains energy and
+//! spends it flying, more the faster it flies; the defaults let boids at
+//! the base `max_speed` just about keep up, so a flock sped up by modifiers
+//! tires. A boid with energy to spare now and then hands half of it to an
+//! offspring spawned next to it, less often the closer the flock is to
+//! its carrying capacity. A boid dies when it reaches its lifespan, which
+//! varies a little from boid to boid, or when it runs out of energy.
+//!
+//! Births and deaths go through the flock like any other change, so they
+//! show up as `StepEvent::Born` and `StepEvent::Died` and, to watchers, as
+//! `FlockEvent::Spawned` and `FlockEvent::Removed`.
+//!
+//! The flock keeps to its population limit: no boid is born while it is
+//! full. `min_population` still respawns boids at random when too many die.
+
+use crate::{Boid, Vector2D};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Birth and death rates for the ecological mode. Times are in seconds of
+/// simulation, see `StepInput::dt`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EcologyConfig {
+    /// Average lifespan; each boid gets one within `lifespan_spread` of it
+    pub lifespan: f32,
+    /// Share of `lifespan` a boid's own lifespan may differ by, 0 to 1
+    pub lifespan_spread: f32,
+    /// Offspring per second of a boid with at least `birth_energy`
+    pub birth_rate: f32,
+    /// Energy, 0 to 1, a boid needs before it can breed
+    pub birth_energy: f32,
+    /// Population at which births stop; they slow down on the way
+    pub carrying_capacity: usize,
+    /// Energy per second spent flying at the base config's `max_speed`;
+    /// other speeds cost proportionally
+    pub energy_drain: f32,
+    /// Energy per second every boid regains
+    pub energy_gain: f32,
+    /// Offspring appear within this distance of the parent
+    pub spawn_radius: f32,
+}
+
+impl Default for EcologyConfig {
+    fn default() -> Self {
+        Self {
+            lifespan: 120.0,
+            lifespan_spread: 0.25,
+            birth_rate: 0.05,
+            birth_energy: 0.8,
+            carrying_capacity: 200,
+            energy_drain: 0.04,
+            energy_gain: 0.06,
+            spawn_radius: 15.0,
+        }
+    }
+}
+
+/// Age and energy of one boid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Life {
+    /// Seconds since the boid was born
+    pub age: f32,
+    /// Seconds the boid lives at most
+    pub lifespan: f32,
+    /// 0 (exhausted) to 1
+    pub energy: f32,
+}
+
+/// Births and deaths so far, and the flock as it is now
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PopulationStats {
+    pub population: usize,
+    pub births: u64,
+    /// Boids that reached their lifespan
+    pub deaths_of_age: u64,
+    /// Boids that ran out of energy
+    pub deaths_of_exhaustion: u64,
+    /// Mean over the boids alive, 0 without any
+    pub mean_age: f32,
+    pub mean_energy: f32,
+}
+
+/// Lives of a flock's boids, by id; owned by `FlockStd`
+#[derive(Debug, Clone)]
+pub(crate) struct Ecology {
+    pub(crate) config: EcologyConfig,
+    lives: HashMap<u32, (Life, u32)>,
+    /// Steps taken, to forget boids that left the flock some other way
+    steps: u32,
+    births: u64,
+    deaths_of_age: u64,
+    deaths_of_exhaustion: u64,
+    newborn: Vec<(Boid, f32)>,
+}
+
+impl Ecology {
+    /// Start with `boids` at random ages, so they do not all die at once
+    pub(crate) fn new(config: EcologyConfig, boids: &[Boid], rng: &mut impl Rng) -> Self {
+        let mut ecology = Self {
+            config,
+            lives: HashMap::new(),
+            steps: 0,
+            births: 0,
+            deaths_of_age: 0,
+            deaths_of_exhaustion: 0,
+            newborn: Vec::new(),
+        };
+        for boid in boids {
+            let mut life = ecology.birth(1.0, rng);
+            life.age = rng.gen_range(0.0..=life.lifespan);
+            ecology.lives.insert(boid.id, (life, 0));
+        }
+        ecology
+    }
+
+    pub(crate) fn life(&self, id: u32) -> Option<Life> {
+        self.lives.get(&id).map(|&(life, _)| life)
+    }
+
+    pub(crate) fn stats(&self, boids: &[Boid]) -> PopulationStats {
+        let mut stats = PopulationStats {
+            population: boids.len(),
+            births: self.births,
+            deaths_of_age: self.deaths_of_age,
+            deaths_of_exhaustion: self.deaths_of_exhaustion,
+            ..PopulationStats::default()
+        };
+        let lives: Vec<Life> = boids.iter().filter_map(|boid| self.life(boid.id)).collect();
+        if !lives.is_empty() {
+            let count = lives.len() as f32;
+            stats.mean_age = lives.iter().map(|life| life.age).sum::<f32>() / count;
+            stats.mean_energy = lives.iter().map(|life| life.energy).sum::<f32>() / count;
+        }
+        stats
+    }
+
+    /// Advance every boid's life by `secs`, remove the dead and add the
+    /// newborn, at most `room` of them, with ids from `next_id`. `max_speed`
+    /// is the speed that costs `energy_drain`. Puppets neither age nor
+    /// breed. Returns how many were born and died.
+    pub(crate) fn step(
+        &mut self,
+        boids: &mut Vec<Boid>,
+        secs: f32,
+        max_speed: f32,
+        room: usize,
+        next_id: &mut u32,
+        rng: &mut impl Rng,
+    ) -> (usize, usize) {
+        self.steps = self.steps.wrapping_add(1);
+        let config = self.config;
+        let mut newborn = core::mem::take(&mut self.newborn);
+        newborn.clear();
+        let (mut old, mut exhausted) = (0, 0);
+        let crowding = if config.carrying_capacity > 0 {
+            (1.0 - boids.len() as f32 / config.carrying_capacity as f32).max(0.0)
+        } else {
+            0.0
+        };
+
+        boids.retain(|boid| {
+            if boid.puppet {
+                return true;
+            }
+            let mut life = match self.lives.get(&boid.id) {
+                Some(&(life, _)) => life,
+                None => self.birth(1.0, rng),
+            };
+            let effort = if max_speed > 0.0 {
+                boid.velocity.magnitude() / max_speed
+            } else {
+                0.0
+            };
+            life.age += secs;
+            life.energy = (life.energy
+                + (config.energy_gain - config.energy_drain * effort) * secs)
+                .clamp(0.0, 1.0);
+            if life.age >= life.lifespan {
+                self.lives.remove(&boid.id);
+                old += 1;
+                return false;
+            }
+            if life.energy <= 0.0 {
+                self.lives.remove(&boid.id);
+                exhausted += 1;
+                return false;
+            }
+            if life.energy >= config.birth_energy
+                && newborn.len() < room
+                && rng.gen::<f32>() < config.birth_rate * crowding * secs
+            {
+                life.energy /= 2.0;
+                let offset = Vector2D::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0))
+                    * config.spawn_radius;
+                let child = Boid {
+                    group: boid.group,
+                    ..Boid::new(boid.position + offset, boid.velocity)
+                };
+                newborn.push((child, life.energy));
+            }
+            self.lives.insert(boid.id, (life, self.steps));
+            true
+        });
+
+        for (child, energy) in newborn.iter() {
+            let id = *next_id;
+            *next_id += 1;
+            boids.push(Boid { id, ..*child });
+            let life = self.birth(*energy, rng);
+            self.lives.insert(id, (life, self.steps));
+        }
+        let steps = self.steps;
+        let puppets = boids.iter().filter(|boid| boid.puppet).count();
+        if self.lives.len() > boids.len() - puppets {
+            self.lives.retain(|_, &mut (_, seen)| seen == steps);
+        }
+
+        self.births += newborn.len() as u64;
+        self.deaths_of_age += old;
+        self.deaths_of_exhaustion += exhausted;
+        let born = newborn.len();
+        self.newborn = newborn;
+        (born, (old + exhausted) as usize)
+    }
+
+    /// A newborn's life
+    fn birth(&self, energy: f32, rng: &mut impl Rng) -> Life {
+        let spread = self.config.lifespan_spread.clamp(0.0, 1.0);
+        Life {
+            age: 0.0,
+            lifespan: self.config.lifespan * (1.0 + rng.gen_range(-spread..=spread)),
+            energy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn boids(count: u32, speed: f32) -> Vec<Boid> {
+        (0..count)
+            .map(|id| Boid {
+                id,
+                ..Boid::new(Vector2D::new(50.0, 50.0), Vector2D::new(speed, 0.0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_boids_breed_and_die() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let config = EcologyConfig {
+            lifespan: 10.0,
+            birth_rate: 1.0,
+            ..EcologyConfig::default()
+        };
+        let mut flock = boids(20, 0.0);
+        let mut ecology = Ecology::new(config, &flock, &mut rng);
+        let mut next_id = 20;
+
+        // Resting boids stay full of energy, so they breed until the flock
+        // is full, and the oldest start dying within a lifespan
+        let (mut born, mut died) = (0, 0);
+        for _ in 0..300 {
+            let room = 40 - flock.len();
+            let (b, d) = ecology.step(&mut flock, 0.1, 4.0, room, &mut next_id, &mut rng);
+            born += b;
+            died += d;
+            assert!(flock.len() <= 40);
+        }
+        let stats = ecology.stats(&flock);
+        assert!(born > 20 && died > 20, "{} {}", born, died);
+        assert_eq!(stats.births, born as u64);
+        assert_eq!(stats.deaths_of_age, died as u64);
+        assert_eq!(stats.population, flock.len());
+        assert_eq!(flock.len(), 20 + born - died);
+        assert!(flock
+            .iter()
+            .all(|boid| ecology.life(boid.id).unwrap().age < 12.5));
+        assert_eq!(next_id, 20 + born as u32);
+    }
+
+    #[test]
+    fn test_fast_boids_die_of_exhaustion() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let config = EcologyConfig {
+            birth_rate: 0.0,
+            ..EcologyConfig::default()
+        };
+        // Twice the speed the boids can keep up
+        let mut flock = boids(5, 8.0);
+        let mut ecology = Ecology::new(config, &[], &mut rng);
+        let mut next_id = 5;
+        for _ in 0..600 {
+            ecology.step(&mut flock, 0.1, 4.0, 0, &mut next_id, &mut rng);
+        }
+        assert!(flock.is_empty());
+        let stats = ecology.stats(&flock);
+        assert_eq!(stats.deaths_of_exhaustion, 5);
+        assert_eq!(stats.mean_energy, 0.0);
+    }
+}
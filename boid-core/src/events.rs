@@ -0,0 +1,231 @@
+//! Simulation events for embedders: sounds, analytics, game logic
+//!
+//! A `FlockStd` that is watched compares each step with the one before and
+//! reports what changed as [`FlockEvent`]s: boids that appeared or
+//! disappeared, however that happened, boids the boundary caught, the
+//! flock's centroid crossing into or out of a [`Region`], and targets
+//! appearing or disappearing. Events reach listeners registered with
+//! `FlockStd::on_event` right after the step, and, once
+//! `FlockStd::watch_events` was called, wait in a queue for
+//! `FlockStd::drain_events`, for embedders that poll instead.
+//!
+//! Nothing is tracked until the flock is watched one of these ways, so
+//! unwatched flocks pay nothing.
+
+use crate::{Boid, Vector2D};
+use std::collections::vec_deque::{Drain, VecDeque};
+
+/// Something that happened to the flock during a step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlockEvent {
+    /// A boid joined the flock, by `spawn`, `add_boid`, a respawn or by
+    /// being pushed onto `boids` directly
+    Spawned {
+        id: u32,
+        position: Vector2D,
+    },
+    /// A boid left the flock
+    Removed {
+        id: u32,
+    },
+    /// The boundary bounced a boid back or wrapped it around; steering
+    /// away from the edges never counts as a hit
+    HitBoundary {
+        id: u32,
+        position: Vector2D,
+    },
+    /// The flock's centroid moved into region `region`, numbered in the
+    /// order the regions were added
+    RegionEntered {
+        region: usize,
+        centroid: Vector2D,
+    },
+    RegionLeft {
+        region: usize,
+        centroid: Vector2D,
+    },
+    /// A step had a target after one without; `position` is the first
+    TargetAcquired {
+        position: Vector2D,
+    },
+    /// A step had no target after one with one
+    TargetLost,
+}
+
+/// Axis-aligned rectangle the flock's centroid is watched against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub min: Vector2D,
+    pub max: Vector2D,
+}
+
+impl Region {
+    /// The rectangle spanned by two opposite corners
+    pub fn new(a: Vector2D, b: Vector2D) -> Self {
+        Self {
+            min: Vector2D::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Vector2D::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    /// Edges count as inside
+    pub fn contains(&self, point: Vector2D) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+}
+
+type Listener = Box<dyn FnMut(&FlockEvent) + Send + Sync>;
+
+/// Most events kept for `drain_events`; the oldest are dropped first
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// Turns consecutive flock states into [`FlockEvent`]s; owned by `FlockStd`
+#[derive(Default)]
+pub(crate) struct EventTracker {
+    listeners: Vec<Listener>,
+    queued: bool,
+    queue: VecDeque<FlockEvent>,
+    dropped: u64,
+    /// Each region, and whether the centroid was inside it after the last
+    /// step
+    regions: Vec<(Region, bool)>,
+    /// Sorted ids of the boids seen at the last step
+    known: Vec<u32>,
+    current: Vec<u32>,
+    had_target: bool,
+}
+
+impl EventTracker {
+    /// Whether anyone listens or polls
+    pub(crate) fn is_active(&self) -> bool {
+        self.queued || !self.listeners.is_empty()
+    }
+
+    pub(crate) fn listen(&mut self, listener: Listener, boids: &[Boid]) {
+        self.start(boids);
+        self.listeners.push(listener);
+    }
+
+    pub(crate) fn queue(&mut self, boids: &[Boid]) {
+        self.start(boids);
+        self.queued = true;
+    }
+
+    /// Take the queued events, oldest first
+    pub(crate) fn drain(&mut self) -> Drain<'_, FlockEvent> {
+        self.queue.drain(..)
+    }
+
+    /// Events lost to `capacity` since the queue was started
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Watch the centroid against `region`; returns its number
+    pub(crate) fn add_region(&mut self, region: Region) -> usize {
+        self.regions.push((region, false));
+        self.regions.len() - 1
+    }
+
+    pub(crate) fn clear_regions(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Take the boids as they are now as the starting point, once
+    fn start(&mut self, boids: &[Boid]) {
+        if self.is_active() {
+            return;
+        }
+        self.known.clear();
+        self.known.extend(boids.iter().map(|boid| boid.id));
+        self.known.sort_unstable();
+    }
+
+    /// Report what changed since the last call; `hits` are the boids the
+    /// boundary caught this step, by id and where they ended up
+    pub(crate) fn observe(
+        &mut self,
+        boids: &[Boid],
+        targets: &[Vector2D],
+        hits: &[(u32, Vector2D)],
+    ) {
+        let mut events = core::mem::take(&mut self.queue);
+        let from = events.len();
+
+        self.current.clear();
+        self.current.extend(boids.iter().map(|boid| boid.id));
+        self.current.sort_unstable();
+        let (mut old, mut new) = (0, 0);
+        loop {
+            match (self.known.get(old).copied(), self.current.get(new).copied()) {
+                (Some(a), Some(b)) if a == b => {
+                    old += 1;
+                    new += 1;
+                }
+                (Some(id), Some(b)) if id < b => {
+                    events.push_back(FlockEvent::Removed { id });
+                    old += 1;
+                }
+                (Some(id), None) => {
+                    events.push_back(FlockEvent::Removed { id });
+                    old += 1;
+                }
+                (_, Some(id)) => {
+                    let position = boids
+                        .iter()
+                        .find(|boid| boid.id == id)
+                        .map_or(Vector2D::zero(), |boid| boid.position);
+                    events.push_back(FlockEvent::Spawned { id, position });
+                    new += 1;
+                }
+                (None, None) => break,
+            }
+        }
+        core::mem::swap(&mut self.known, &mut self.current);
+
+        events.extend(
+            hits.iter()
+                .map(|&(id, position)| FlockEvent::HitBoundary { id, position }),
+        );
+
+        if !boids.is_empty() {
+            let sum = boids
+                .iter()
+                .fold(Vector2D::zero(), |sum, boid| sum + boid.position);
+            let centroid = sum / boids.len() as f32;
+            for (region, (area, inside)) in self.regions.iter_mut().enumerate() {
+                let now = area.contains(centroid);
+                if now != *inside {
+                    events.push_back(if now {
+                        FlockEvent::RegionEntered { region, centroid }
+                    } else {
+                        FlockEvent::RegionLeft { region, centroid }
+                    });
+                    *inside = now;
+                }
+            }
+        }
+
+        match (self.had_target, targets.first()) {
+            (false, Some(&position)) => events.push_back(FlockEvent::TargetAcquired { position }),
+            (true, None) => events.push_back(FlockEvent::TargetLost),
+            _ => {}
+        }
+        self.had_target = !targets.is_empty();
+
+        for event in events.range(from..) {
+            for listener in self.listeners.iter_mut() {
+                listener(event);
+            }
+        }
+        if self.queued {
+            while events.len() > QUEUE_CAPACITY {
+                events.pop_front();
+                self.dropped += 1;
+            }
+        } else {
+            events.clear();
+        }
+        self.queue = events;
+    }
+}
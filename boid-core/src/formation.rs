@@ -0,0 +1,512 @@
+//! Formations: boids taking up fixed slots, e.g. to spell out text
+//!
+//! A [`Raster`] (text in the built-in 5x7 font, or a caller's bitmap) is
+//! sampled into slot positions with [`fit`], and [`assign`] hands each boid
+//! a slot through [`TargetOverride::Formation`]. Everything but the
+//! [`FormationController`] works without `std`, so the fixed-capacity
+//! `Flock` can spell too.
+//!
+//! `assign` is quick but greedy: early slots take the nearest boids, and
+//! the boids left for the last slots may have to cross the whole shape. A
+//! [`FormationController`] gives every boid its own slot so that the boids
+//! travel as little as possible in total, exactly for flocks up to
+//! `optimal_limit` boids, and keeps the shape up as boids come and go.
+
+use crate::{Boid, TargetOverride, Vector2D};
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+/// Slots `Flock::spell` can hold; enough for about a dozen letters
+pub const MAX_SLOTS: usize = 512;
+
+/// Blank columns between letters
+const LETTER_SPACING: usize = 1;
+
+/// Rows of the built-in glyph for `c`, top first, with bit 4 the leftmost
+/// column. Letters are case-insensitive; `None` for characters the font
+/// lacks.
+pub fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match c.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0x00; GLYPH_HEIGHT],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '?' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// Monochrome image whose set pixels become formation slots
+#[derive(Debug, Clone, Copy)]
+pub enum Raster<'a> {
+    /// One line of text in the built-in font; characters it lacks are
+    /// drawn as `?`
+    Text(&'a str),
+    /// Row-major pixels, `width` per row; nonzero bytes are set
+    Bitmap { width: usize, pixels: &'a [u8] },
+}
+
+impl<'a> Raster<'a> {
+    /// `(columns, rows)` in pixels
+    pub fn size(&self) -> (usize, usize) {
+        match *self {
+            Raster::Text(text) => {
+                let letters = text.chars().count();
+                let columns = (letters * (GLYPH_WIDTH + LETTER_SPACING)).saturating_sub(1);
+                (columns, if letters == 0 { 0 } else { GLYPH_HEIGHT })
+            }
+            Raster::Bitmap { width, pixels } if width > 0 => (width, pixels.len() / width),
+            Raster::Bitmap { .. } => (0, 0),
+        }
+    }
+
+    pub fn is_set(&self, column: usize, row: usize) -> bool {
+        match *self {
+            Raster::Text(text) => {
+                let cell = GLYPH_WIDTH + LETTER_SPACING;
+                let within = column % cell;
+                if within >= GLYPH_WIDTH || row >= GLYPH_HEIGHT {
+                    return false;
+                }
+                let Some(c) = text.chars().nth(column / cell) else {
+                    return false;
+                };
+                let rows = glyph(c).or_else(|| glyph('?')).unwrap_or_default();
+                rows[row] & (0x10 >> within) != 0
+            }
+            Raster::Bitmap { width, pixels } => {
+                column < width && pixels.get(row * width + column).is_some_and(|&p| p != 0)
+            }
+        }
+    }
+
+    /// Set pixels as `(column, row)`, row by row
+    pub fn pixels(self) -> impl Iterator<Item = (usize, usize)> + Clone + 'a {
+        let (columns, rows) = self.size();
+        (0..rows)
+            .flat_map(move |row| (0..columns).map(move |column| (column, row)))
+            .filter(move |&(column, row)| self.is_set(column, row))
+    }
+}
+
+/// Slot positions for the set pixels of `raster`, scaled to fit a
+/// `width` x `height` box centered on `center`. Square pixels, so the
+/// raster keeps its aspect ratio.
+pub fn fit(
+    raster: Raster<'_>,
+    center: Vector2D,
+    width: f32,
+    height: f32,
+) -> impl Iterator<Item = Vector2D> + Clone + '_ {
+    let (columns, rows) = raster.size();
+    let pitch = if columns == 0 || rows == 0 {
+        0.0
+    } else {
+        (width / columns as f32).min(height / rows as f32)
+    };
+    let origin = Vector2D::new(
+        center.x - (columns as f32 - 1.0) * pitch / 2.0,
+        center.y - (rows as f32 - 1.0) * pitch / 2.0,
+    );
+    raster.pixels().map(move |(column, row)| {
+        Vector2D::new(
+            origin.x + column as f32 * pitch,
+            origin.y + row as f32 * pitch,
+        )
+    })
+}
+
+/// `fit` into the middle of a `width` x `height` area, spanning at most
+/// 90% of its width and half its height
+pub fn fit_centered(
+    raster: Raster<'_>,
+    width: f32,
+    height: f32,
+) -> impl Iterator<Item = Vector2D> + Clone + '_ {
+    fit(
+        raster,
+        Vector2D::new(width / 2.0, height / 2.0),
+        width * 0.9,
+        height * 0.5,
+    )
+}
+
+/// Send every boid to a formation slot
+///
+/// With more slots than boids, the slots are sampled evenly so the whole
+/// shape stays recognizable; with fewer, slots take several boids each.
+/// Slots are handed out in order, each to the nearest boid still free.
+/// Any earlier target override is replaced. Empty `slots` releases the
+/// formation instead.
+pub fn assign(boids: &mut [Boid], slots: &[Vector2D]) {
+    release(boids);
+    if slots.is_empty() || boids.is_empty() {
+        return;
+    }
+
+    let used = slots.len().min(boids.len());
+    let mut remaining = boids.len();
+    'passes: loop {
+        for i in 0..used {
+            if remaining == 0 {
+                break 'passes;
+            }
+            let slot = slots[i * slots.len() / used];
+            let nearest = boids
+                .iter_mut()
+                .filter(|boid| !boid.target_override.is_formation())
+                .min_by(|a, b| {
+                    a.position
+                        .distance(&slot)
+                        .total_cmp(&b.position.distance(&slot))
+                });
+            if let Some(boid) = nearest {
+                boid.target_override = TargetOverride::Formation(slot);
+                remaining -= 1;
+            }
+        }
+    }
+}
+
+/// Let boids in formation rejoin the flock
+pub fn release(boids: &mut [Boid]) {
+    for boid in boids.iter_mut() {
+        if boid.target_override.is_formation() {
+            boid.target_override = TargetOverride::Inherit;
+        }
+    }
+}
+
+/// Holds a flock in a shape given as points, e.g. sampled from text the
+/// caller rasterized, see the [module docs](self)
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct FormationController {
+    points: Vec<Vector2D>,
+    /// Flocks up to this size get the assignment with the least total
+    /// travel, larger ones the greedy one of [`assign`]; the optimal one
+    /// takes time cubic in the flock size
+    pub optimal_limit: usize,
+    /// Ids of the boids at the last assignment, in flock order
+    ids: Vec<u32>,
+}
+
+#[cfg(feature = "std")]
+impl FormationController {
+    pub fn new(points: &[Vector2D]) -> Self {
+        Self {
+            points: points.to_vec(),
+            optimal_limit: 150,
+            ids: Vec::new(),
+        }
+    }
+
+    pub fn points(&self) -> &[Vector2D] {
+        &self.points
+    }
+
+    /// Send every boid to its own slot. With more points than boids, the
+    /// points are sampled evenly; with fewer, points take several boids
+    /// each. Without points, the boids are released.
+    pub fn assign(&mut self, boids: &mut [Boid]) {
+        self.ids.clear();
+        self.ids.extend(boids.iter().map(|boid| boid.id));
+        let n = boids.len();
+        if n > self.optimal_limit || self.points.is_empty() {
+            // The greedy assignment, as for plain slots
+            assign(boids, &self.points);
+            return;
+        }
+
+        release(boids);
+        let points = self.points.len();
+        let slots: Vec<Vector2D> = if points >= n {
+            (0..n).map(|i| self.points[i * points / n]).collect()
+        } else {
+            (0..n).map(|i| self.points[i % points]).collect()
+        };
+        let order = optimal_assignment(boids, &slots);
+        for (boid, slot) in boids.iter_mut().zip(order) {
+            boid.target_override = TargetOverride::Formation(slots[slot]);
+        }
+    }
+
+    /// Reassign the slots if boids joined or left the flock since the
+    /// last assignment; returns whether it did
+    pub fn maintain(&mut self, boids: &mut [Boid]) -> bool {
+        let unchanged = boids.len() == self.ids.len()
+            && boids.iter().zip(&self.ids).all(|(boid, &id)| boid.id == id);
+        if !unchanged {
+            self.assign(boids);
+        }
+        !unchanged
+    }
+
+    /// Share of the flock within `tolerance` of its slot, 0 to 1
+    pub fn settled(&self, boids: &[Boid], tolerance: f32) -> f32 {
+        if boids.is_empty() {
+            return 0.0;
+        }
+        let settled = boids
+            .iter()
+            .filter(|boid| match boid.target_override {
+                TargetOverride::Formation(slot) => boid.position.distance(&slot) <= tolerance,
+                _ => false,
+            })
+            .count();
+        settled as f32 / boids.len() as f32
+    }
+
+    /// Release the boids, sending each off at `speed` away from the
+    /// middle of the shape
+    pub fn disperse(&mut self, boids: &mut [Boid], speed: f32) {
+        self.ids.clear();
+        release(boids);
+        if self.points.is_empty() {
+            return;
+        }
+        let center = self
+            .points
+            .iter()
+            .fold(Vector2D::zero(), |sum, &point| sum + point)
+            / self.points.len() as f32;
+        for boid in boids.iter_mut() {
+            let away = boid.position - center;
+            if away != Vector2D::zero() {
+                boid.velocity = away.normalize() * speed;
+            }
+        }
+    }
+}
+
+/// Slot index for each boid, with the least total distance, by the
+/// Hungarian method; boids and slots are equally many
+#[cfg(feature = "std")]
+fn optimal_assignment(boids: &[Boid], slots: &[Vector2D]) -> Vec<usize> {
+    let n = boids.len();
+    let cost = |boid: usize, slot: usize| boids[boid].position.distance(&slots[slot]) as f64;
+    // Potentials and matches are 1-based, with 0 as the sentinel; `owner`
+    // is the boid holding each slot
+    let mut boid_potential = vec![0.0; n + 1];
+    let mut slot_potential = vec![0.0; n + 1];
+    let mut owner = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+    let mut min_to = vec![0.0f64; n + 1];
+    let mut used = vec![false; n + 1];
+    for boid in 1..=n {
+        owner[0] = boid;
+        let mut slot = 0;
+        min_to.fill(f64::INFINITY);
+        used.fill(false);
+        loop {
+            used[slot] = true;
+            let current = owner[slot];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let reduced =
+                    cost(current - 1, j - 1) - boid_potential[current] - slot_potential[j];
+                if reduced < min_to[j] {
+                    min_to[j] = reduced;
+                    way[j] = slot;
+                }
+                if min_to[j] < delta {
+                    delta = min_to[j];
+                    next = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    boid_potential[owner[j]] += delta;
+                    slot_potential[j] -= delta;
+                } else {
+                    min_to[j] -= delta;
+                }
+            }
+            slot = next;
+            if owner[slot] == 0 {
+                break;
+            }
+        }
+        while slot != 0 {
+            let previous = way[slot];
+            owner[slot] = owner[previous];
+            slot = previous;
+        }
+    }
+    let mut order = vec![0; n];
+    for slot in 1..=n {
+        order[owner[slot] - 1] = slot - 1;
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_raster() {
+        let raster = Raster::Text("Hi");
+        assert_eq!(raster.size(), (11, GLYPH_HEIGHT));
+        // The H's crossbar, the gap between letters, the I's stem
+        assert!((0..GLYPH_WIDTH).all(|column| raster.is_set(column, 3)));
+        assert!((0..GLYPH_HEIGHT).all(|row| !raster.is_set(5, row)));
+        assert!((0..GLYPH_HEIGHT).all(|row| raster.is_set(8, row)));
+        assert_eq!(raster.pixels().count(), 17 + 11);
+
+        // Unknown characters fall back to `?`
+        assert_eq!(
+            Raster::Text("~").pixels().count(),
+            Raster::Text("?").pixels().count()
+        );
+        assert_eq!(Raster::Text("").size(), (0, 0));
+    }
+
+    #[test]
+    fn test_fit_and_assign() {
+        let pixels = [1, 0, 1, 0, 1, 0];
+        let raster = Raster::Bitmap {
+            width: 3,
+            pixels: &pixels,
+        };
+        let mut slots = [Vector2D::zero(); 3];
+        for (slot, point) in
+            slots
+                .iter_mut()
+                .zip(fit(raster, Vector2D::new(50.0, 50.0), 60.0, 60.0))
+        {
+            *slot = point;
+        }
+        // Pitch 20: three columns span 40, two rows span 20
+        assert_eq!(slots[0], Vector2D::new(30.0, 40.0));
+        assert_eq!(slots[1], Vector2D::new(70.0, 40.0));
+        assert_eq!(slots[2], Vector2D::new(50.0, 60.0));
+
+        let mut boids = [
+            Boid::new(Vector2D::new(80.0, 40.0), Vector2D::zero()),
+            Boid::new(Vector2D::new(10.0, 10.0), Vector2D::zero()),
+            Boid::new(Vector2D::new(50.0, 90.0), Vector2D::zero()),
+            Boid::new(Vector2D::new(55.0, 90.0), Vector2D::zero()),
+        ];
+        assign(&mut boids, &slots);
+        assert_eq!(
+            boids[1].target_override,
+            TargetOverride::Formation(slots[0])
+        );
+        assert_eq!(
+            boids[0].target_override,
+            TargetOverride::Formation(slots[1])
+        );
+        assert_eq!(
+            boids[2].target_override,
+            TargetOverride::Formation(slots[2])
+        );
+        // The spare boid doubles up on the first slot
+        assert_eq!(
+            boids[3].target_override,
+            TargetOverride::Formation(slots[0])
+        );
+
+        release(&mut boids);
+        assert!(boids
+            .iter()
+            .all(|boid| boid.target_override == TargetOverride::Inherit));
+    }
+
+    #[test]
+    fn test_controller_minimizes_total_travel() {
+        // Greedy hands the first slot the boid right next to it, leaving
+        // the far boid to cross over to the second slot
+        let slots = [Vector2D::new(10.0, 0.0), Vector2D::new(0.0, 0.0)];
+        let boids = || {
+            [
+                Boid::new(Vector2D::new(5.0, 0.0), Vector2D::zero()),
+                Boid::new(Vector2D::new(16.0, 0.0), Vector2D::zero()),
+            ]
+        };
+        let travel = |boids: &[Boid]| -> f32 {
+            boids
+                .iter()
+                .map(|boid| match boid.target_override {
+                    TargetOverride::Formation(slot) => boid.position.distance(&slot),
+                    _ => f32::INFINITY,
+                })
+                .sum()
+        };
+        let mut greedy = boids();
+        let mut controller = FormationController::new(&slots);
+        controller.optimal_limit = 0;
+        controller.assign(&mut greedy);
+        assert_eq!(travel(&greedy), 5.0 + 16.0);
+
+        let mut optimal = boids();
+        controller.optimal_limit = 150;
+        controller.assign(&mut optimal);
+        assert_eq!(travel(&optimal), 5.0 + 6.0);
+        assert!(!controller.maintain(&mut optimal));
+
+        // A newcomer gets a slot, doubling up as there are only two
+        let mut grown = [
+            optimal[0].clone(),
+            optimal[1].clone(),
+            Boid {
+                id: 7,
+                ..Boid::new(Vector2D::new(1.0, 1.0), Vector2D::zero())
+            },
+        ];
+        assert!(controller.maintain(&mut grown));
+        assert!(grown.iter().all(|boid| boid.target_override.is_formation()));
+        assert_eq!(controller.settled(&grown, 2.0), 1.0 / 3.0);
+
+        controller.disperse(&mut grown, 3.0);
+        assert!(grown
+            .iter()
+            .all(|boid| !boid.target_override.is_formation()));
+        assert_eq!(grown[1].velocity, Vector2D::new(3.0, 0.0));
+    }
+}
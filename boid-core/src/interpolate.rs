@@ -0,0 +1,188 @@
+//! Smooth targets between network updates
+//!
+//! Clients send the hand position 10 to 30 times a second, while the flock
+//! steps at its own rate. Reaching for each update as it arrives makes the
+//! target jump. A [`TargetInterpolator`] keeps the last few timestamped
+//! samples and runs a natural cubic spline through them. It is evaluated
+//! one update interval behind the newest sample, so the target glides
+//! through every sample instead of guessing where the hand goes next.
+
+use crate::Vector2D;
+
+/// Samples the spline runs through
+pub const MAX_SAMPLES: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TargetInterpolator {
+    /// Seconds and position, oldest first
+    samples: heapless::Vec<(f64, Vector2D), MAX_SAMPLES>,
+    /// A sample arriving this many seconds after the previous one starts
+    /// over from it, rather than sweeping in from a stale position
+    pub gap: f32,
+}
+
+impl Default for TargetInterpolator {
+    fn default() -> Self {
+        Self {
+            samples: heapless::Vec::new(),
+            gap: 0.5,
+        }
+    }
+}
+
+impl TargetInterpolator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a target that arrived `time` seconds into the run. A sample no
+    /// later than the newest one replaces its position.
+    pub fn push(&mut self, time: f64, position: Vector2D) {
+        if let Some(newest) = self.samples.last_mut() {
+            if time <= newest.0 {
+                newest.1 = position;
+                return;
+            }
+            if time - newest.0 > self.gap as f64 {
+                self.samples.clear();
+            }
+        }
+        if self.samples.is_full() {
+            self.samples.remove(0);
+        }
+        let _ = self.samples.push((time, position));
+    }
+
+    /// Forget all samples, e.g. when the hand is lost
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Position of the newest sample
+    pub fn newest(&self) -> Option<Vector2D> {
+        self.samples.last().map(|&(_, position)| position)
+    }
+
+    /// The target `time` seconds into the run; `None` without samples. Once
+    /// updates stop, the target settles on the newest sample.
+    pub fn sample(&self, time: f64) -> Option<Vector2D> {
+        let (&(first, _), &(newest, position)) = (self.samples.first()?, self.samples.last()?);
+        let count = self.samples.len();
+        if count == 1 {
+            return Some(position);
+        }
+        let interval = newest - self.samples[count - 2].0;
+        let at = (time - interval).clamp(first, newest);
+
+        let mut knots = [(0.0, Vector2D::zero()); MAX_SAMPLES];
+        for (knot, &(time, position)) in knots.iter_mut().zip(self.samples.iter()) {
+            *knot = ((time - first) as f32, position);
+        }
+        Some(natural_spline(&knots[..count], (at - first) as f32))
+    }
+}
+
+/// Natural cubic spline through at least two `knots` with increasing times,
+/// evaluated at `at`
+fn natural_spline(knots: &[(f32, Vector2D)], at: f32) -> Vector2D {
+    let count = knots.len();
+
+    // Second derivatives at the knots, zero at both ends; the tridiagonal
+    // system for the inner ones is solved by forward elimination and back
+    // substitution
+    let mut curvature = [Vector2D::zero(); MAX_SAMPLES];
+    let mut upper = [0.0f32; MAX_SAMPLES];
+    let mut rhs = [Vector2D::zero(); MAX_SAMPLES];
+    for i in 1..count - 1 {
+        let before = knots[i].0 - knots[i - 1].0;
+        let after = knots[i + 1].0 - knots[i].0;
+        let slopes = (knots[i + 1].1 - knots[i].1) / after - (knots[i].1 - knots[i - 1].1) / before;
+        let pivot = 2.0 * (before + after) - before * upper[i - 1];
+        upper[i] = after / pivot;
+        rhs[i] = (slopes * 6.0 - rhs[i - 1] * before) / pivot;
+    }
+    for i in (1..count - 1).rev() {
+        curvature[i] = rhs[i] - curvature[i + 1] * upper[i];
+    }
+
+    let i = (0..count - 1)
+        .find(|&i| at <= knots[i + 1].0)
+        .unwrap_or(count - 2);
+    let ((start, from), (end, to)) = (knots[i], knots[i + 1]);
+    let h = end - start;
+    let (left, right) = (end - at, at - start);
+    curvature[i] * (left * left * left / (6.0 * h))
+        + curvature[i + 1] * (right * right * right / (6.0 * h))
+        + (from / h - curvature[i] * (h / 6.0)) * left
+        + (to / h - curvature[i + 1] * (h / 6.0)) * right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector2D, b: Vector2D) -> bool {
+        a.distance(&b) < 1e-3
+    }
+
+    #[test]
+    fn test_passes_through_samples_one_interval_behind() {
+        let mut interpolator = TargetInterpolator::new();
+        assert_eq!(interpolator.sample(0.0), None);
+
+        let points = [
+            Vector2D::new(0.0, 0.0),
+            Vector2D::new(10.0, 5.0),
+            Vector2D::new(15.0, 20.0),
+            Vector2D::new(5.0, 30.0),
+        ];
+        for (i, &point) in points.iter().enumerate() {
+            interpolator.push(i as f64 * 0.1, point);
+        }
+        // Evaluated an interval late, the newest sample is reached one
+        // interval after it arrived, and the target stays there
+        assert!(close(interpolator.sample(0.3).unwrap(), points[2]));
+        assert!(close(interpolator.sample(0.4).unwrap(), points[3]));
+        assert!(close(interpolator.sample(2.0).unwrap(), points[3]));
+
+        // In between, the path bends smoothly rather than cutting corners
+        let mid = interpolator.sample(0.35).unwrap();
+        assert!(!close(mid, (points[2] + points[3]) * 0.5));
+        assert!(mid.distance(&points[2]) < points[2].distance(&points[3]));
+    }
+
+    #[test]
+    fn test_straight_evenly_spaced_samples_move_evenly() {
+        let mut interpolator = TargetInterpolator::new();
+        for i in 0..6 {
+            interpolator.push(i as f64 * 0.05, Vector2D::new(i as f32 * 4.0, 0.0));
+        }
+        // 0.25 s is the newest sample; 0.0125 s later the target is a
+        // quarter of the way from the one before
+        let target = interpolator.sample(0.2625).unwrap();
+        assert!(close(target, Vector2D::new(17.0, 0.0)), "{:?}", target);
+    }
+
+    #[test]
+    fn test_gap_and_late_samples() {
+        let mut interpolator = TargetInterpolator::new();
+        interpolator.push(1.0, Vector2D::new(0.0, 0.0));
+        interpolator.push(1.1, Vector2D::new(10.0, 0.0));
+        // Arriving at the same time as the newest, it takes its place
+        interpolator.push(1.1, Vector2D::new(20.0, 0.0));
+        assert_eq!(interpolator.sample(1.2), Some(Vector2D::new(20.0, 0.0)));
+
+        // After a pause the target jumps instead of sweeping across
+        interpolator.push(5.0, Vector2D::new(100.0, 100.0));
+        assert_eq!(interpolator.sample(5.0), Some(Vector2D::new(100.0, 100.0)));
+
+        interpolator.clear();
+        assert!(interpolator.is_empty());
+        assert_eq!(interpolator.sample(5.0), None);
+    }
+}
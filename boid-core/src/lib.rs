@@ -1,10 +1,68 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(feature = "std")]
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "std")]
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod ecology;
+#[cfg(feature = "std")]
+pub mod events;
+pub mod formation;
+pub mod interpolate;
+#[cfg(feature = "std")]
+pub mod mapping;
+#[cfg(feature = "std")]
+pub mod neighbors;
+#[cfg(feature = "std")]
+pub mod obstacles;
+#[cfg(feature = "std")]
+pub mod particles;
+pub mod path;
+#[cfg(feature = "std")]
+pub mod perching;
+pub mod pointer;
+pub mod profile;
+#[cfg(feature = "record")]
+pub mod record;
+pub mod steering;
+pub mod step;
+pub mod three_d;
+#[cfg(feature = "std")]
+pub mod timeline;
+pub mod wind;
+
+#[cfg(feature = "std")]
+pub use ecology::{EcologyConfig, Life, PopulationStats};
+#[cfg(feature = "std")]
+pub use events::{FlockEvent, Region};
+pub use formation::Raster;
+pub use interpolate::TargetInterpolator;
+#[cfg(feature = "std")]
+pub use neighbors::{NeighborGraph, NeighborRadius};
+pub use path::Path;
+#[cfg(feature = "std")]
+pub use perching::{PerchConfig, PerchState};
+pub use pointer::PointerMode;
+pub use profile::FlockProfile;
+#[cfg(feature = "std")]
+pub use steering::BehaviorPipeline;
+pub use steering::{BehaviorList, FlockContext, SteeringBehavior};
+use step::{StepEvent, Stimulus};
+pub use step::{StepInput, StepOutput};
+pub use three_d::{Boid3D, BoundsMode, Flock3D, Vector3D};
+#[cfg(feature = "std")]
+pub use timeline::{Marker, Timeline, TimelineConfig, TimelineEntry};
+pub use wind::Gust;
 
 /// A 2D vector used for position and velocity
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2D {
     pub x: f32,
     pub y: f32,
@@ -67,6 +125,10 @@ impl Vector2D {
             libm::sqrtf(dx * dx + dy * dy)
         }
     }
+
+    pub fn dot(&self, other: &Vector2D) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
 }
 
 impl core::ops::Add for Vector2D {
@@ -122,29 +184,187 @@ impl core::ops::AddAssign for Vector2D {
 
 /// A single boid entity
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Boid {
+    /// Stable identifier, assigned by `FlockStd` when the boid is added
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: u32,
     pub position: Vector2D,
     pub velocity: Vector2D,
     pub acceleration: Vector2D,
     pub wander_angle: f32,
+    /// Puppet boids are driven externally: they act as neighbors for the
+    /// rest of the flock but are skipped during force integration
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub puppet: bool,
+    /// Replaces the flock-wide target for this boid in `FlockStd`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target_override: TargetOverride,
+    /// Sub-flock: alignment and cohesion only count boids of the same
+    /// group, separation counts everyone. Group 0 is the default.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub group: u8,
+}
+
+/// Per-boid replacement for the target passed to `FlockStd::update_with_target`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetOverride {
+    /// Follow the flock-wide target
+    #[default]
+    Inherit,
+    /// Steer toward this position instead
+    Target(Vector2D),
+    /// Ignore any target and flock freely
+    FreeFloat,
+    /// Hold a formation slot: arrive at this position and stay there,
+    /// ignoring the flock, the flock-wide target and global forces
+    Formation(Vector2D),
+}
+
+impl TargetOverride {
+    /// Target in effect for a boid given the flock-wide `target`
+    pub fn resolve(self, target: Option<Vector2D>) -> Option<Vector2D> {
+        match self {
+            TargetOverride::Inherit => target,
+            TargetOverride::Target(position) | TargetOverride::Formation(position) => {
+                Some(position)
+            }
+            TargetOverride::FreeFloat => None,
+        }
+    }
+
+    pub fn is_formation(self) -> bool {
+        matches!(self, TargetOverride::Formation(_))
+    }
+}
+
+/// Cap on the number of boids in a flock
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PopulationLimit {
+    pub max: usize,
+    /// Spawning at the cap respawns the oldest boid at the new spot
+    /// instead of being refused
+    pub recycle_oldest: bool,
+}
+
+impl PopulationLimit {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            recycle_oldest: false,
+        }
+    }
+
+    pub fn recycling(max: usize) -> Self {
+        Self {
+            max,
+            recycle_oldest: true,
+        }
+    }
+}
+
+/// A boid that did not fit into a `Flock<N>`, handed back so the caller
+/// can drop it or make room
+#[derive(Debug, Clone)]
+pub struct CapacityError {
+    pub boid: Boid,
+    /// Most boids the flock could hold when the boid was refused
+    pub capacity: usize,
+}
+
+impl core::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "flock is full ({} boids)", self.capacity)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+/// Capacity events reported by `Flock::try_update`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpdateDiagnostics {
+    /// Boids simulated in this update
+    pub boids: usize,
+    /// Boids the flock can hold
+    pub capacity: usize,
+    /// Boids dropped, oldest first, because the flock was over its
+    /// population limit
+    pub trimmed: usize,
+    /// Boids refused by `add_boid` or `spawn` since the previous update
+    pub rejected: usize,
+    /// Boids respawned by `spawn` to make room since the previous update
+    pub recycled: usize,
+}
+
+impl UpdateDiagnostics {
+    pub fn is_full(&self) -> bool {
+        self.boids >= self.capacity
+    }
+
+    /// Whether any boid was refused, recycled or dropped
+    pub fn has_events(&self) -> bool {
+        self.trimmed + self.rejected + self.recycled > 0
+    }
+}
+
+/// Index of the boid closest to `position`
+fn nearest_boid(boids: &[Boid], position: Vector2D) -> Option<usize> {
+    boids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            a.position
+                .distance(&position)
+                .total_cmp(&b.position.distance(&position))
+        })
+        .map(|(index, _)| index)
 }
 
 impl Boid {
     pub fn new(position: Vector2D, velocity: Vector2D) -> Self {
         Self {
+            id: 0,
             position,
             velocity,
             acceleration: Vector2D::zero(),
             wander_angle: 0.0,
+            puppet: false,
+            target_override: TargetOverride::Inherit,
+            group: 0,
+        }
+    }
+
+    /// Whether `other` counts for this boid's alignment and cohesion
+    pub fn is_flockmate(&self, other: &Boid) -> bool {
+        self.group == other.group
+    }
+
+    /// Create an externally driven boid, see [`Boid::puppet`]
+    pub fn new_puppet(position: Vector2D, velocity: Vector2D) -> Self {
+        Self {
+            puppet: true,
+            ..Self::new(position, velocity)
         }
     }
 
     #[cfg(feature = "std")]
     pub fn random(width: f32, height: f32) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::random_with_rng(width, height, &mut rand::thread_rng())
+    }
+
+    /// Random boid drawn from `rng`, for reproducible setups
+    #[cfg(feature = "std")]
+    pub fn random_with_rng<R: Rng + ?Sized>(width: f32, height: f32, rng: &mut R) -> Self {
         let position = Vector2D::new(rng.gen_range(0.0..width), rng.gen_range(0.0..height));
         let velocity = Vector2D::new(rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
-        Self::new(position, velocity)
+        Self {
+            // Each boid wanders off its own way
+            wander_angle: rng.gen_range(0.0..core::f32::consts::TAU),
+            ..Self::new(position, velocity)
+        }
     }
 
     pub fn apply_force(&mut self, force: Vector2D) {
@@ -152,9 +372,15 @@ impl Boid {
     }
 
     pub fn update(&mut self, max_speed: f32, _max_force: f32) {
-        self.velocity += self.acceleration;
+        self.integrate(max_speed, 1.0);
+    }
+
+    /// `update` advancing `scale` updates' worth of motion, for steps
+    /// longer or shorter than one tick
+    pub fn integrate(&mut self, max_speed: f32, scale: f32) {
+        self.velocity += self.acceleration * scale;
         self.velocity = self.velocity.limit(max_speed);
-        self.position += self.velocity;
+        self.position += self.velocity * scale;
         self.acceleration = Vector2D::zero();
     }
 
@@ -194,8 +420,84 @@ impl Boid {
     }
 }
 
+/// What happens to boids at the edges of the area
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundaryBehavior {
+    /// Leave one edge and reappear at the opposite one
+    Wrap,
+    /// Clamp inside a 10 px margin and reflect the velocity off the edge
+    Bounce,
+    /// Steer back toward the interior once within `margin` of an edge,
+    /// before reaching it. The push grows with depth into the margin and
+    /// keeps growing past the edge; `weight` scales it in units of
+    /// `max_force`. Nothing is clamped, so a fast boid may overshoot.
+    SteerAway { margin: f32, weight: f32 },
+    /// No edges; boids fly on past the area
+    Unbounded,
+}
+
+#[deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
+impl BoundaryBehavior {
+    /// Steering force pushing `boid` away from the edges; zero except for
+    /// `SteerAway`
+    pub fn steering(&self, boid: &Boid, width: f32, height: f32, config: &BoidConfig) -> Vector2D {
+        let BoundaryBehavior::SteerAway { margin, weight } = *self else {
+            return Vector2D::zero();
+        };
+        if margin <= 0.0 {
+            return Vector2D::zero();
+        }
+        // Depth into the margin, 0 at its inner side and 1 at the edge
+        let push = |position: f32, size: f32| {
+            if position < margin {
+                (margin - position) / margin
+            } else if position > size - margin {
+                -(position - (size - margin)) / margin
+            } else {
+                0.0
+            }
+        };
+        Vector2D::new(push(boid.position.x, width), push(boid.position.y, height))
+            * (weight * config.max_force)
+    }
+
+    /// Move or redirect `boid` after it has moved this tick
+    pub fn constrain(&self, boid: &mut Boid, width: f32, height: f32) {
+        match self {
+            BoundaryBehavior::Wrap => boid.wrap_edges(width, height),
+            BoundaryBehavior::Bounce => boid.contain_within_bounds(width, height),
+            BoundaryBehavior::SteerAway { .. } | BoundaryBehavior::Unbounded => {}
+        }
+    }
+}
+
+/// How an active target influences the flock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetMode {
+    /// Steering-limited seek toward the target
+    #[default]
+    Seek,
+    /// Inverse-square attraction applied directly to velocity, producing
+    /// orbits around the target instead of a beeline
+    GravityWell,
+    /// Steer away from the target, scattering boids within `flee_radius`
+    Flee,
+    /// Seek that brakes within `arrive_radius` of the target, so boids
+    /// settle on it instead of overshooting and circling back
+    Arrive,
+}
+
 /// Configuration for the boid simulation
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// Snapshots taken before a field existed load with its default
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct BoidConfig {
     pub max_speed: f32,
     pub max_force: f32,
@@ -206,8 +508,37 @@ pub struct BoidConfig {
     pub alignment_weight: f32,
     pub cohesion_weight: f32,
     pub seek_weight: f32,
+    /// Only boids closer than this to the target seek it, with a smooth
+    /// falloff toward the edge. `0.0` means every boid responds.
+    pub seek_radius: f32,
     pub wander_radius: f32,
     pub wander_enabled: bool,
+    pub target_mode: TargetMode,
+    /// Gravitational constant of the well (force = strength / distance²)
+    pub gravity_strength: f32,
+    /// Softening length that keeps the pull finite near the well center
+    pub gravity_softening: f32,
+    /// Fraction of velocity removed per tick while inside the well
+    pub gravity_damping: f32,
+    /// Boids farther than this from the well are released from it
+    pub gravity_escape_radius: f32,
+    /// Reach of the target in `TargetMode::Flee`; the push fades to zero here
+    pub flee_radius: f32,
+    /// Distance from the target where `TargetMode::Arrive` starts braking.
+    /// `0.0` brakes where `max_force` can just stop a boid at `max_speed`.
+    pub arrive_radius: f32,
+    /// Strength of `behavior::follow_path`
+    pub path_weight: f32,
+    /// Half the width of a path; boids within it follow the path's
+    /// direction, boids farther out steer back onto it
+    pub path_radius: f32,
+    /// Use a uniform grid for neighbor lookups in `FlockStd` instead of
+    /// checking every pair of boids. Ignored by the fixed-capacity `Flock`.
+    pub spatial_grid: bool,
+    /// Edge handling, the same for `FlockStd` and `Flock<N>`. `None` keeps
+    /// each flock's own: `Flock<N>` wraps and `FlockStd` bounces, as they
+    /// did before this field existed.
+    pub boundary: Option<BoundaryBehavior>,
 }
 
 impl Default for BoidConfig {
@@ -222,8 +553,300 @@ impl Default for BoidConfig {
             alignment_weight: 1.0,
             cohesion_weight: 1.0,
             seek_weight: 8.0,
+            seek_radius: 0.0,
             wander_radius: 0.1,
             wander_enabled: false,
+            target_mode: TargetMode::Seek,
+            gravity_strength: 200.0,
+            gravity_softening: 10.0,
+            gravity_damping: 0.01,
+            gravity_escape_radius: 300.0,
+            flee_radius: 120.0,
+            arrive_radius: 0.0,
+            path_weight: 1.0,
+            path_radius: 20.0,
+            spatial_grid: false,
+            boundary: None,
+        }
+    }
+}
+
+/// Transient multipliers layered on top of a base `BoidConfig`.
+///
+/// Interactive input (e.g. hand gestures) writes modifiers instead of the
+/// config itself, so the user-set values are never overwritten and dropping
+/// the modifiers always returns to them exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigModifiers {
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub max_speed: f32,
+    pub max_force: f32,
+    /// Replaces the base target mode, e.g. to repel from a pointer
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target_mode: Option<TargetMode>,
+    /// Further multipliers for any numeric parameter, on top of the ones
+    /// above; written by `mapping::MappingTable`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scales: ParameterScales,
+}
+
+impl ConfigModifiers {
+    /// Modifiers that leave the base config unchanged
+    pub const IDENTITY: Self = Self {
+        separation_weight: 1.0,
+        alignment_weight: 1.0,
+        cohesion_weight: 1.0,
+        max_speed: 1.0,
+        max_force: 1.0,
+        target_mode: None,
+        scales: ParameterScales::IDENTITY,
+    };
+
+    /// Resolve the config used for a tick from the persisted `base`
+    pub fn apply(&self, base: &BoidConfig) -> BoidConfig {
+        let mut config = BoidConfig {
+            separation_weight: base.separation_weight * self.separation_weight,
+            alignment_weight: base.alignment_weight * self.alignment_weight,
+            cohesion_weight: base.cohesion_weight * self.cohesion_weight,
+            max_speed: base.max_speed * self.max_speed,
+            max_force: base.max_force * self.max_force,
+            target_mode: self.target_mode.unwrap_or(base.target_mode),
+            ..*base
+        };
+        self.scales.apply(&mut config);
+        config
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+}
+
+impl Default for ConfigModifiers {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// A numeric [`BoidConfig`] field, to address it by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ConfigParameter {
+    MaxSpeed,
+    MaxForce,
+    SeparationDistance,
+    AlignmentDistance,
+    CohesionDistance,
+    SeparationWeight,
+    AlignmentWeight,
+    CohesionWeight,
+    SeekWeight,
+    SeekRadius,
+    WanderRadius,
+    GravityStrength,
+    GravitySoftening,
+    GravityDamping,
+    GravityEscapeRadius,
+    FleeRadius,
+    ArriveRadius,
+    PathWeight,
+    PathRadius,
+}
+
+impl ConfigParameter {
+    pub const COUNT: usize = 19;
+
+    pub const ALL: [ConfigParameter; Self::COUNT] = [
+        ConfigParameter::MaxSpeed,
+        ConfigParameter::MaxForce,
+        ConfigParameter::SeparationDistance,
+        ConfigParameter::AlignmentDistance,
+        ConfigParameter::CohesionDistance,
+        ConfigParameter::SeparationWeight,
+        ConfigParameter::AlignmentWeight,
+        ConfigParameter::CohesionWeight,
+        ConfigParameter::SeekWeight,
+        ConfigParameter::SeekRadius,
+        ConfigParameter::WanderRadius,
+        ConfigParameter::GravityStrength,
+        ConfigParameter::GravitySoftening,
+        ConfigParameter::GravityDamping,
+        ConfigParameter::GravityEscapeRadius,
+        ConfigParameter::FleeRadius,
+        ConfigParameter::ArriveRadius,
+        ConfigParameter::PathWeight,
+        ConfigParameter::PathRadius,
+    ];
+
+    /// The field's name in `BoidConfig`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MaxSpeed => "max_speed",
+            Self::MaxForce => "max_force",
+            Self::SeparationDistance => "separation_distance",
+            Self::AlignmentDistance => "alignment_distance",
+            Self::CohesionDistance => "cohesion_distance",
+            Self::SeparationWeight => "separation_weight",
+            Self::AlignmentWeight => "alignment_weight",
+            Self::CohesionWeight => "cohesion_weight",
+            Self::SeekWeight => "seek_weight",
+            Self::SeekRadius => "seek_radius",
+            Self::WanderRadius => "wander_radius",
+            Self::GravityStrength => "gravity_strength",
+            Self::GravitySoftening => "gravity_softening",
+            Self::GravityDamping => "gravity_damping",
+            Self::GravityEscapeRadius => "gravity_escape_radius",
+            Self::FleeRadius => "flee_radius",
+            Self::ArriveRadius => "arrive_radius",
+            Self::PathWeight => "path_weight",
+            Self::PathRadius => "path_radius",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|parameter| parameter.as_str() == name)
+    }
+
+    pub fn get(self, config: &BoidConfig) -> f32 {
+        let mut config = *config;
+        *self.get_mut(&mut config)
+    }
+
+    pub fn get_mut(self, config: &mut BoidConfig) -> &mut f32 {
+        match self {
+            Self::MaxSpeed => &mut config.max_speed,
+            Self::MaxForce => &mut config.max_force,
+            Self::SeparationDistance => &mut config.separation_distance,
+            Self::AlignmentDistance => &mut config.alignment_distance,
+            Self::CohesionDistance => &mut config.cohesion_distance,
+            Self::SeparationWeight => &mut config.separation_weight,
+            Self::AlignmentWeight => &mut config.alignment_weight,
+            Self::CohesionWeight => &mut config.cohesion_weight,
+            Self::SeekWeight => &mut config.seek_weight,
+            Self::SeekRadius => &mut config.seek_radius,
+            Self::WanderRadius => &mut config.wander_radius,
+            Self::GravityStrength => &mut config.gravity_strength,
+            Self::GravitySoftening => &mut config.gravity_softening,
+            Self::GravityDamping => &mut config.gravity_damping,
+            Self::GravityEscapeRadius => &mut config.gravity_escape_radius,
+            Self::FleeRadius => &mut config.flee_radius,
+            Self::ArriveRadius => &mut config.arrive_radius,
+            Self::PathWeight => &mut config.path_weight,
+            Self::PathRadius => &mut config.path_radius,
+        }
+    }
+}
+
+/// A multiplier for every [`ConfigParameter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParameterScales([f32; ConfigParameter::COUNT]);
+
+impl ParameterScales {
+    /// Every parameter at its base value
+    pub const IDENTITY: Self = Self([1.0; ConfigParameter::COUNT]);
+
+    pub fn get(&self, parameter: ConfigParameter) -> f32 {
+        self.0[parameter as usize]
+    }
+
+    /// Multiply the scale of `parameter` by `factor`
+    pub fn scale(&mut self, parameter: ConfigParameter, factor: f32) {
+        self.0[parameter as usize] *= factor;
+    }
+
+    /// Multiply every field of `config` by its scale
+    pub fn apply(&self, config: &mut BoidConfig) {
+        for parameter in ConfigParameter::ALL {
+            *parameter.get_mut(config) *= self.get(parameter);
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+}
+
+impl Default for ParameterScales {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Gradual change from one config to another over a number of ticks.
+///
+/// Numeric fields ease in and out (smoothstep) so switching presets does not
+/// jolt the flock; switches such as `wander_enabled` or `target_mode` take
+/// the target value on the first tick.
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigTransition {
+    from: BoidConfig,
+    to: BoidConfig,
+    ticks: u32,
+    elapsed: u32,
+}
+
+impl ConfigTransition {
+    pub fn new(from: BoidConfig, to: BoidConfig, ticks: u32) -> Self {
+        Self {
+            from,
+            to,
+            ticks,
+            elapsed: 0,
+        }
+    }
+
+    /// Config the transition ends on
+    pub fn target(&self) -> &BoidConfig {
+        &self.to
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.ticks
+    }
+
+    /// Advance one tick and return the config to use for it
+    pub fn step(&mut self) -> BoidConfig {
+        self.elapsed = (self.elapsed + 1).min(self.ticks);
+        if self.is_finished() {
+            return self.to;
+        }
+        let t = self.elapsed as f32 / self.ticks as f32;
+        Self::blend(&self.from, &self.to, t * t * (3.0 - 2.0 * t))
+    }
+
+    fn blend(from: &BoidConfig, to: &BoidConfig, t: f32) -> BoidConfig {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        BoidConfig {
+            max_speed: lerp(from.max_speed, to.max_speed),
+            max_force: lerp(from.max_force, to.max_force),
+            separation_distance: lerp(from.separation_distance, to.separation_distance),
+            alignment_distance: lerp(from.alignment_distance, to.alignment_distance),
+            cohesion_distance: lerp(from.cohesion_distance, to.cohesion_distance),
+            separation_weight: lerp(from.separation_weight, to.separation_weight),
+            alignment_weight: lerp(from.alignment_weight, to.alignment_weight),
+            cohesion_weight: lerp(from.cohesion_weight, to.cohesion_weight),
+            seek_weight: lerp(from.seek_weight, to.seek_weight),
+            seek_radius: lerp(from.seek_radius, to.seek_radius),
+            wander_radius: lerp(from.wander_radius, to.wander_radius),
+            gravity_strength: lerp(from.gravity_strength, to.gravity_strength),
+            gravity_softening: lerp(from.gravity_softening, to.gravity_softening),
+            gravity_damping: lerp(from.gravity_damping, to.gravity_damping),
+            gravity_escape_radius: lerp(from.gravity_escape_radius, to.gravity_escape_radius),
+            flee_radius: lerp(from.flee_radius, to.flee_radius),
+            arrive_radius: lerp(from.arrive_radius, to.arrive_radius),
+            path_weight: lerp(from.path_weight, to.path_weight),
+            path_radius: lerp(from.path_radius, to.path_radius),
+            ..*to
         }
     }
 }
@@ -236,7 +859,24 @@ pub trait FlockBehavior {
     fn seek(&self, boid: &Boid, target: Vector2D, config: &BoidConfig) -> Vector2D;
 }
 
+/// Pipeline entry of `FlockStd::follow_path`
+#[cfg(feature = "std")]
+const PATH_BEHAVIOR: &str = "path";
+
+/// Ticks at `max_speed` that `behavior::follow_path` looks ahead
+const PATH_LOOKAHEAD_TICKS: f32 = 10.0;
+
+/// How fast wander angles drift, in radians per square root of a second:
+/// after `t` seconds a boid's angle is typically `WANDER_JITTER * sqrt(t)`
+/// off where it started
+pub const WANDER_JITTER: f32 = 0.2236;
+
 /// Helper functions for boid behavior
+#[deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
 pub mod behavior {
     use super::*;
 
@@ -329,291 +969,3123 @@ pub mod behavior {
         steering.limit(config.max_force)
     }
 
-    #[cfg(feature = "std")]
-    pub fn wander(boid: &mut Boid, config: &BoidConfig) -> Vector2D {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    /// Scale for the seek force on a boid `distance` from the target:
+    /// 1 at the target, easing to 0 at `seek_radius` (smoothstep)
+    pub fn seek_falloff(distance: f32, config: &BoidConfig) -> f32 {
+        if config.seek_radius <= 0.0 {
+            return 1.0;
+        }
+        let t = (1.0 - distance / config.seek_radius).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Seek force scaled by `seek_weight` and the seek radius falloff
+    pub fn weighted_seek(boid: &Boid, target: Vector2D, config: &BoidConfig) -> Vector2D {
+        let falloff = seek_falloff(boid.position.distance(&target), config);
+        if falloff == 0.0 {
+            return Vector2D::zero();
+        }
+        seek(boid, target, config) * (config.seek_weight * falloff)
+    }
+
+    /// Steering away from `threat`, scaled by `seek_weight`: strongest at
+    /// the threat, fading linearly to zero at `flee_radius`
+    pub fn flee(boid: &Boid, threat: Vector2D, config: &BoidConfig) -> Vector2D {
+        let offset = boid.position - threat;
+        let distance = offset.magnitude();
+        if distance >= config.flee_radius {
+            return Vector2D::zero();
+        }
+        let desired = offset.normalize() * config.max_speed;
+        let strength = 1.0 - distance / config.flee_radius;
+        (desired - boid.velocity).limit(config.max_force) * (config.seek_weight * strength)
+    }
+
+    /// Seek that brakes on approach so the boid comes to rest on `target`,
+    /// steering with up to `max_force`. Braking starts where that force
+    /// can just stop a boid moving at `max_speed`.
+    pub fn arrive(boid: &Boid, target: Vector2D, max_speed: f32, max_force: f32) -> Vector2D {
+        let braking_distance = max_speed * max_speed / max_force.max(f32::EPSILON);
+        arrive_within(boid, target, braking_distance, max_speed, max_force)
+    }
+
+    /// `arrive` braking from `slowing_radius` in
+    fn arrive_within(
+        boid: &Boid,
+        target: Vector2D,
+        slowing_radius: f32,
+        max_speed: f32,
+        max_force: f32,
+    ) -> Vector2D {
+        let offset = target - boid.position;
+        let speed = max_speed * (offset.magnitude() / slowing_radius.max(f32::EPSILON)).min(1.0);
+        let desired = offset.normalize() * speed;
+        (desired - boid.velocity).limit(max_force)
+    }
+
+    /// Arrive at the target in `TargetMode::Arrive`, braking from
+    /// `arrive_radius`. It steers as hard as `weighted_seek` would, but
+    /// the weight raises the force limit instead of scaling the force, so
+    /// the boid does not oscillate around its resting point.
+    pub fn weighted_arrive(boid: &Boid, target: Vector2D, config: &BoidConfig) -> Vector2D {
+        let falloff = seek_falloff(boid.position.distance(&target), config);
+        if falloff == 0.0 {
+            return Vector2D::zero();
+        }
+        let max_force = config.max_force * config.seek_weight * falloff;
+        if config.arrive_radius > 0.0 {
+            arrive_within(
+                boid,
+                target,
+                config.arrive_radius,
+                config.max_speed,
+                max_force,
+            )
+        } else {
+            arrive(boid, target, config.max_speed, max_force)
+        }
+    }
+
+    /// Steering along `path`, scaled by `path_weight`. The boid looks
+    /// ahead along its heading: if that point is within `path_radius` of
+    /// the path it turns to the path's direction there, otherwise it seeks
+    /// a point on the path a little further on.
+    pub fn follow_path(boid: &Boid, path: &Path, config: &BoidConfig) -> Vector2D {
+        let lookahead = config.max_speed * PATH_LOOKAHEAD_TICKS;
+        let ahead = boid.position + boid.velocity.normalize() * lookahead;
+        let Some((nearest, direction)) = path.closest(ahead) else {
+            return Vector2D::zero();
+        };
+        let force = if nearest.distance(&ahead) <= config.path_radius {
+            (direction * config.max_speed - boid.velocity).limit(config.max_force)
+        } else {
+            seek(boid, nearest + direction * lookahead, config)
+        };
+        force * config.path_weight
+    }
+
+    /// Steering toward a formation slot; as strong as a full-weight seek
+    pub fn hold_slot(boid: &Boid, slot: Vector2D, config: &BoidConfig) -> Vector2D {
+        let max_force = config.max_force * config.seek_weight.max(1.0);
+        arrive(boid, slot, config.max_speed, max_force)
+    }
+
+    /// Velocity change caused by a gravity well at `target`.
+    /// Unlike the steering behaviors this is not limited by `max_force`.
+    pub fn gravity_well(boid: &Boid, target: Vector2D, config: &BoidConfig) -> Vector2D {
+        let offset = target - boid.position;
+        let distance = offset.magnitude();
 
-        // Update wander angle with small random change
-        boid.wander_angle += rng.gen_range(-0.05..0.05);
+        if distance == 0.0 || distance > config.gravity_escape_radius {
+            return Vector2D::zero();
+        }
+
+        let softened_sq = distance * distance + config.gravity_softening * config.gravity_softening;
+        offset.normalize() * (config.gravity_strength / softened_sq)
+    }
 
-        // Convert angle to vector
+    /// Push along the boid's wander angle, `wander_radius` strong
+    pub fn wander(boid: &Boid, config: &BoidConfig) -> Vector2D {
         #[cfg(feature = "std")]
         let (sin, cos) = (boid.wander_angle.sin(), boid.wander_angle.cos());
 
         #[cfg(not(feature = "std"))]
         let (sin, cos) = (libm::sinf(boid.wander_angle), libm::cosf(boid.wander_angle));
 
-        let mut wander_force = Vector2D::new(cos, sin);
-        wander_force = wander_force.normalize();
-        wander_force = wander_force * config.wander_radius;
+        Vector2D::new(cos, sin) * config.wander_radius
+    }
 
-        wander_force
+    /// Advance the boid's wander angle by `dt` seconds of a random walk
+    /// drawn from `rng`. The steps' variance grows with `dt`, so the angle
+    /// spreads by about `WANDER_JITTER * sqrt(t)` after `t` seconds at any
+    /// frame rate.
+    #[cfg(feature = "std")]
+    pub fn evolve_wander<R: Rng + ?Sized>(boid: &mut Boid, dt: f32, rng: &mut R) {
+        if dt.is_nan() || dt <= 0.0 {
+            return;
+        }
+        // A uniform step of this half-width has variance JITTER^2 * dt
+        let half_width = WANDER_JITTER * (3.0 * dt).sqrt();
+        boid.wander_angle += rng.gen_range(-half_width..half_width);
     }
 }
 
-/// A collection of boids for embedded (no_std) environments
-pub struct Flock<const N: usize> {
-    pub boids: heapless::Vec<Boid, N>,
-    pub config: BoidConfig,
-    pub width: f32,
-    pub height: f32,
+/// Where a configuration change came from. Hand gestures are not one:
+/// they modulate `ConfigModifiers` and leave the config itself alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// A setter called from the UI (e.g. a WASM slider)
+    Setter,
+    /// A remote API request
+    Api,
 }
 
-impl<const N: usize> Flock<N> {
-    pub fn new(width: f32, height: f32, config: BoidConfig) -> Self {
-        Self {
-            boids: heapless::Vec::new(),
-            config,
-            width,
-            height,
+impl ChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeSource::Setter => "setter",
+            ChangeSource::Api => "api",
         }
     }
+}
 
-    pub fn add_boid(&mut self, boid: Boid) -> Result<(), Boid> {
-        self.boids.push(boid)
-    }
+/// A recorded configuration change, holding the config as it was before
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigChange {
+    /// Name of the changed field, or a group name for bulk updates
+    pub field: &'static str,
+    pub source: ChangeSource,
+    /// Caller-supplied timestamp in milliseconds
+    pub timestamp_ms: u64,
+    pub previous: BoidConfig,
+}
 
-    pub fn update(&mut self) {
-        // Calculate forces for all boids
-        let mut forces = heapless::Vec::<Vector2D, N>::new();
+/// Ring buffer of the last `N` configuration changes, for undo
+pub struct ConfigHistory<const N: usize> {
+    changes: heapless::Deque<ConfigChange, N>,
+}
 
-        for boid in self.boids.iter() {
-            let sep = behavior::separation(boid, self.boids.iter(), &self.config)
-                * self.config.separation_weight;
-            let ali = behavior::alignment(boid, self.boids.iter(), &self.config)
-                * self.config.alignment_weight;
-            let coh = behavior::cohesion(boid, self.boids.iter(), &self.config)
-                * self.config.cohesion_weight;
-            let _ = forces.push(sep + ali + coh);
+impl<const N: usize> ConfigHistory<N> {
+    pub fn new() -> Self {
+        Self {
+            changes: heapless::Deque::new(),
         }
+    }
 
-        // Apply forces and update boids
-        for (boid, force) in self.boids.iter_mut().zip(forces.iter()) {
-            boid.apply_force(*force);
-            boid.update(self.config.max_speed, self.config.max_force);
-            boid.wrap_edges(self.width, self.height);
+    /// Record a change; the oldest entry is dropped once the ring is full
+    pub fn record(&mut self, change: ConfigChange) {
+        if self.changes.is_full() {
+            self.changes.pop_front();
         }
+        let _ = self.changes.push_back(change);
     }
 
-    pub fn resize(&mut self, width: f32, height: f32) {
-        self.width = width;
-        self.height = height;
+    /// Remove the most recent change and return it, so the caller can
+    /// restore `previous`
+    pub fn undo(&mut self) -> Option<ConfigChange> {
+        self.changes.pop_back()
+    }
+
+    /// Iterate over the recorded changes, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &ConfigChange> {
+        self.changes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
     }
 }
 
-/// A collection of boids for std environments
-#[cfg(feature = "std")]
-pub struct FlockStd {
-    pub boids: Vec<Boid>,
+impl<const N: usize> Default for ConfigHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A collection of boids for embedded (no_std) environments; the
+/// [`FlockProfile`] `P` picks the behaviors compiled in
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flock<const N: usize, P: FlockProfile = profile::Standard> {
+    pub boids: heapless::Vec<Boid, N>,
     pub config: BoidConfig,
     pub width: f32,
     pub height: f32,
+    /// Force added to every boid each update (e.g. device tilt as gravity)
+    pub global_force: Vector2D,
+    /// Wind varying over time on top of `global_force`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gust: Gust,
+    /// Seconds `gust` has been blowing
+    #[cfg_attr(feature = "serde", serde(default))]
+    gust_time: f64,
+    /// Lowers the cap below the capacity `N`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub population: Option<PopulationLimit>,
+    /// Capacity events since the last `try_update`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pending: UpdateDiagnostics,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    profile: core::marker::PhantomData<P>,
 }
 
-#[cfg(feature = "std")]
-impl FlockStd {
-    pub fn new(width: f32, height: f32, count: usize) -> Self {
-        let boids = (0..count).map(|_| Boid::random(width, height)).collect();
-
+impl<const N: usize, P: FlockProfile> Flock<N, P> {
+    pub fn new(width: f32, height: f32, config: BoidConfig) -> Self {
         Self {
-            boids,
-            config: BoidConfig::default(),
+            boids: heapless::Vec::new(),
+            config,
             width,
             height,
+            global_force: Vector2D::zero(),
+            gust: Gust::Calm,
+            gust_time: 0.0,
+            population: None,
+            pending: UpdateDiagnostics::default(),
+            profile: core::marker::PhantomData,
         }
     }
 
-    pub fn new_with_config(width: f32, height: f32, count: usize, config: BoidConfig) -> Self {
-        let boids = (0..count).map(|_| Boid::random(width, height)).collect();
+    /// A flock starting from the profile's tuned config
+    pub fn with_profile(width: f32, height: f32) -> Self {
+        Self::new(width, height, P::config())
+    }
 
-        Self {
-            boids,
-            config,
-            width,
-            height,
+    /// Add a boid, or hand it back if the flock is at its capacity or
+    /// population limit
+    pub fn add_boid(&mut self, boid: Boid) -> Result<(), CapacityError> {
+        let capacity = self.max_population();
+        if self.boids.len() >= capacity {
+            self.pending.rejected += 1;
+            return Err(CapacityError { boid, capacity });
         }
+        self.boids
+            .push(boid)
+            .map_err(|boid| CapacityError { boid, capacity })
     }
 
-    pub fn update(&mut self) {
-        self.update_with_target(None);
+    /// Storage size `N`
+    pub fn capacity(&self) -> usize {
+        N
     }
 
-    pub fn update_with_target(&mut self, target: Option<Vector2D>) {
-        // Update wander angles if wander is enabled or if seeking
-        if self.config.wander_enabled || target.is_some() {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
-            for boid in self.boids.iter_mut() {
-                boid.wander_angle += rng.gen_range(-0.05..0.05);
+    /// Whether `add_boid` and non-recycling `spawn` would be refused
+    pub fn is_full(&self) -> bool {
+        self.boids.len() >= self.max_population()
+    }
+
+    /// Most boids the flock holds: the population limit, at most `N`
+    pub fn max_population(&self) -> usize {
+        self.population.map_or(N, |limit| limit.max.min(N))
+    }
+
+    /// Grow or shrink the flock to `count` boids, at most
+    /// `max_population()`; returns the new size. The newest boids are
+    /// removed first and new ones come from `make`.
+    pub fn set_boid_count(&mut self, count: usize, mut make: impl FnMut() -> Boid) -> usize {
+        let count = count.min(self.max_population());
+        self.boids.truncate(count);
+        while self.boids.len() < count && self.add_boid(make()).is_ok() {}
+        self.boids.len()
+    }
+
+    /// Add a boid at `position` moving with `velocity` and return its index.
+    /// At the cap the oldest boid is respawned there if the limit recycles,
+    /// otherwise nothing is spawned.
+    pub fn spawn(&mut self, position: Vector2D, velocity: Vector2D) -> Option<usize> {
+        self.trim_population();
+        let max = self.max_population();
+        if max == 0 {
+            self.pending.rejected += 1;
+            return None;
+        }
+        if self.boids.len() >= max {
+            if !self.population.is_some_and(|limit| limit.recycle_oldest) {
+                self.pending.rejected += 1;
+                return None;
             }
+            self.boids.remove(0);
+            self.pending.recycled += 1;
         }
+        self.add_boid(Boid::new(position, velocity)).ok()?;
+        Some(self.boids.len() - 1)
+    }
 
-        // Calculate forces for all boids
-        let forces: Vec<Vector2D> = self
-            .boids
-            .iter()
-            .map(|boid| {
-                let sep = behavior::separation(boid, self.boids.iter(), &self.config)
-                    * self.config.separation_weight;
-                let ali = behavior::alignment(boid, self.boids.iter(), &self.config)
-                    * self.config.alignment_weight;
-                let coh = behavior::cohesion(boid, self.boids.iter(), &self.config)
-                    * self.config.cohesion_weight;
-
-                // Add seek behavior if target is present
-                let seek_force = if let Some(target_pos) = target {
-                    behavior::seek(boid, target_pos, &self.config) * self.config.seek_weight
-                } else {
-                    Vector2D::zero()
-                };
-
-                // Add wander behavior if enabled
-                let wander_force = if self.config.wander_enabled || target.is_some() {
-                    // Calculate wander using the updated angle
-                    let (sin, cos) = (boid.wander_angle.sin(), boid.wander_angle.cos());
-                    let mut wander = Vector2D::new(cos, sin);
-                    wander = wander.normalize();
-                    wander * self.config.wander_radius
-                } else {
-                    Vector2D::zero()
-                };
-
-                sep + ali + coh + seek_force + wander_force
-            })
+    /// Remove the boid at `index`, keeping the order of the others
+    pub fn remove_at(&mut self, index: usize) -> Option<Boid> {
+        (index < self.boids.len()).then(|| self.boids.remove(index))
+    }
+
+    /// Remove every boid matching `predicate`; returns how many were removed
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&Boid) -> bool) -> usize {
+        let before = self.boids.len();
+        self.boids.retain(|boid| !predicate(boid));
+        before - self.boids.len()
+    }
+
+    /// Index of the boid closest to `position`
+    pub fn nearest(&self, position: Vector2D) -> Option<usize> {
+        nearest_boid(&self.boids, position)
+    }
+
+    /// Remove the boid closest to `position` if it is within `max_distance`
+    pub fn remove_nearest(&mut self, position: Vector2D, max_distance: f32) -> Option<Boid> {
+        let index = self.nearest(position)?;
+        if self.boids[index].position.distance(&position) > max_distance {
+            return None;
+        }
+        self.remove_at(index)
+    }
+
+    /// Drop the oldest boids while the flock is over its limit
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    fn trim_population(&mut self) {
+        let excess = self.boids.len().saturating_sub(self.max_population());
+        if excess > 0 {
+            let keep = self.boids.len() - excess;
+            self.boids.rotate_left(excess);
+            self.boids.truncate(keep);
+            self.pending.trimmed += excess;
+        }
+    }
+
+    /// Advance the flock as `input` says; see [`step`]
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    pub fn step(&mut self, input: StepInput<'_>) -> StepOutput {
+        let scale = input.scale();
+        self.advance(input.targets, input.stimuli, scale);
+        let pending = core::mem::take(&mut self.pending);
+        let mut output = StepOutput::default();
+        output.push(StepEvent::Trimmed(pending.trimmed));
+        output.push(StepEvent::Rejected(pending.rejected));
+        output.push(StepEvent::Recycled(pending.recycled));
+        output.with_stats(&self.boids, Some(self.max_population()), scale)
+    }
+
+    /// Set a force applied uniformly to all boids, bypassing `max_force`.
+    /// Pass `Vector2D::zero()` to disable it.
+    pub fn set_global_force(&mut self, force: Vector2D) {
+        self.global_force = force;
+    }
+
+    /// Blow `gust` on top of the global force, from the start of its
+    /// pattern. `Gust::Calm` stops it.
+    pub fn set_gust(&mut self, gust: Gust) {
+        self.gust = gust;
+        self.gust_time = 0.0;
+    }
+
+    /// Global force plus the current gust, as added to every boid
+    pub fn wind(&self) -> Vector2D {
+        self.global_force + self.gust.at(self.gust_time)
+    }
+
+    /// Edge handling in effect: `config.boundary`, wrapping when unset
+    pub fn boundary(&self) -> BoundaryBehavior {
+        self.config.boundary.unwrap_or(BoundaryBehavior::Wrap)
+    }
+
+    pub fn update(&mut self) {
+        self.update_with_target(None);
+    }
+
+    /// `update_with_target`, reporting the capacity events since the
+    /// previous `try_update` so firmware can decide whether to drop or
+    /// recycle boids
+    #[must_use]
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    pub fn try_update(&mut self, target: Option<Vector2D>) -> UpdateDiagnostics {
+        self.update_with_target(target);
+        let diagnostics = UpdateDiagnostics {
+            boids: self.boids.len(),
+            capacity: self.max_population(),
+            ..self.pending
+        };
+        self.pending = UpdateDiagnostics::default();
+        diagnostics
+    }
+
+    /// Update with boids within `seek_radius` seeking `target`
+    pub fn update_with_target(&mut self, target: Option<Vector2D>) {
+        self.advance(target.as_slice(), &[], 1.0);
+    }
+
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    fn advance(&mut self, targets: &[Vector2D], stimuli: &[Stimulus], scale: f32) {
+        self.trim_population();
+        let wind = self.wind();
+
+        // Calculate forces for all boids; `boids` never holds more than N
+        let mut forces = [Vector2D::zero(); N];
+
+        for (force, boid) in forces.iter_mut().zip(self.boids.iter()) {
+            if let TargetOverride::Formation(slot) = boid.target_override {
+                *force = behavior::hold_slot(boid, slot, &self.config);
+                continue;
+            }
+            // Behaviors the profile leaves out are constant-folded away
+            let sep = if P::SEPARATION {
+                behavior::separation(boid, self.boids.iter(), &self.config)
+                    * self.config.separation_weight
+            } else {
+                Vector2D::zero()
+            };
+            let flockmates = || self.boids.iter().filter(|other| boid.is_flockmate(other));
+            let ali = if P::ALIGNMENT {
+                behavior::alignment(boid, flockmates(), &self.config) * self.config.alignment_weight
+            } else {
+                Vector2D::zero()
+            };
+            let coh = if P::COHESION {
+                behavior::cohesion(boid, flockmates(), &self.config) * self.config.cohesion_weight
+            } else {
+                Vector2D::zero()
+            };
+            let target = nearest_target(targets, boid.position);
+            let seek = match boid.target_override.resolve(target).filter(|_| P::TARGET) {
+                Some(target) if self.config.target_mode == TargetMode::Flee => {
+                    behavior::flee(boid, target, &self.config)
+                }
+                Some(target) if self.config.target_mode == TargetMode::Arrive => {
+                    behavior::weighted_arrive(boid, target, &self.config)
+                }
+                Some(target) => behavior::weighted_seek(boid, target, &self.config),
+                None => Vector2D::zero(),
+            };
+            *force =
+                sep + ali + coh + seek + wind + step::stimulus_force(boid, stimuli, &self.config);
+        }
+
+        // Apply forces and update boids
+        let boundary = self.boundary();
+        for (boid, force) in self.boids.iter_mut().zip(forces.iter()) {
+            if boid.puppet {
+                continue;
+            }
+            boid.apply_force(
+                *force + boundary.steering(boid, self.width, self.height, &self.config),
+            );
+            boid.integrate(self.config.max_speed, scale);
+            boundary.constrain(boid, self.width, self.height);
+        }
+        self.gust_time += (scale * step::REFERENCE_DT) as f64;
+    }
+
+    /// Update with custom steering: each boid outside a formation is pushed
+    /// by the weighted sum of `behaviors` plus the global force.
+    /// `update_with_target` is the same with separation, alignment,
+    /// cohesion and seek at weight 1.
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    pub fn update_with_behaviors<const M: usize>(
+        &mut self,
+        target: Option<Vector2D>,
+        behaviors: &BehaviorList<'_, M>,
+    ) {
+        self.trim_population();
+        let wind = self.wind();
+        let mut forces = [Vector2D::zero(); N];
+
+        for (force, boid) in forces.iter_mut().zip(self.boids.iter()) {
+            *force = match boid.target_override {
+                TargetOverride::Formation(slot) => behavior::hold_slot(boid, slot, &self.config),
+                target_override => {
+                    let ctx = FlockContext::new(
+                        &self.boids,
+                        &self.config,
+                        target_override.resolve(target),
+                        self.width,
+                        self.height,
+                    );
+                    behaviors.steer(boid, &ctx) + wind
+                }
+            };
+        }
+
+        let boundary = self.boundary();
+        for (boid, force) in self.boids.iter_mut().zip(forces.iter()) {
+            if boid.puppet {
+                continue;
+            }
+            boid.apply_force(
+                *force + boundary.steering(boid, self.width, self.height, &self.config),
+            );
+            boid.update(self.config.max_speed, self.config.max_force);
+            boundary.constrain(boid, self.width, self.height);
+        }
+        self.gust_time += step::REFERENCE_DT as f64;
+    }
+
+    /// Send the boids to formation slots, see [`formation::assign`]
+    pub fn form(&mut self, slots: &[Vector2D]) {
+        formation::assign(&mut self.boids, slots);
+    }
+
+    /// Arrange the flock into `text`, centered on the area; returns the
+    /// number of slots. Slots beyond [`formation::MAX_SLOTS`] are dropped.
+    pub fn spell(&mut self, text: &str) -> usize {
+        let slots: heapless::Vec<Vector2D, { formation::MAX_SLOTS }> =
+            formation::fit_centered(Raster::Text(text), self.width, self.height)
+                .take(formation::MAX_SLOTS)
+                .collect();
+        self.form(&slots);
+        slots.len()
+    }
+
+    pub fn release_formation(&mut self) {
+        formation::release(&mut self.boids);
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+/// Uniform grid that buckets boids by position for neighbor queries.
+///
+/// Cells are at least as large as the query radius, so every neighbor of a
+/// boid lies in the 3x3 block of cells around it. Boids outside the bounds
+/// are clamped into the edge cells.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    /// Start offset of each cell in `indices`, with a trailing end offset
+    cell_start: Vec<usize>,
+    /// Boid indices sorted by cell
+    indices: Vec<usize>,
+    /// Next free slot of each cell while scattering, kept between rebuilds
+    next: Vec<usize>,
+}
+
+#[cfg(feature = "std")]
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the grid for `boids` in a `width` x `height` area
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    pub fn rebuild(&mut self, boids: &[Boid], width: f32, height: f32, cell_size: f32) {
+        self.cell_size = cell_size.max(1.0);
+        self.cols = ((width / self.cell_size).ceil() as usize).max(1);
+        self.rows = ((height / self.cell_size).ceil() as usize).max(1);
+
+        // Counting sort: histogram, prefix sum, scatter
+        let cell_count = self.cols * self.rows;
+        self.cell_start.clear();
+        self.cell_start.resize(cell_count + 1, 0);
+        for boid in boids {
+            let cell = self.cell_of(boid.position);
+            self.cell_start[cell + 1] += 1;
+        }
+        for cell in 0..cell_count {
+            self.cell_start[cell + 1] += self.cell_start[cell];
+        }
+
+        self.indices.clear();
+        self.indices.resize(boids.len(), 0);
+        self.next.clear();
+        self.next.extend_from_slice(&self.cell_start);
+        for (index, boid) in boids.iter().enumerate() {
+            let cell = self.cell_of(boid.position);
+            self.indices[self.next[cell]] = index;
+            self.next[cell] += 1;
+        }
+    }
+
+    /// Indices of all boids that may lie within `radius` of `position`
+    pub fn query(
+        &self,
+        position: Vector2D,
+        radius: f32,
+    ) -> impl Iterator<Item = usize> + Clone + '_ {
+        let (min_col, min_row) = self.cell_coords(position - Vector2D::new(radius, radius));
+        let (max_col, max_row) = self.cell_coords(position + Vector2D::new(radius, radius));
+
+        (min_row..=max_row).flat_map(move |row| {
+            let start = self.cell_start[row * self.cols + min_col];
+            let end = self.cell_start[row * self.cols + max_col + 1];
+            self.indices[start..end].iter().copied()
+        })
+    }
+
+    fn cell_coords(&self, position: Vector2D) -> (usize, usize) {
+        let col = (position.x / self.cell_size).max(0.0) as usize;
+        let row = (position.y / self.cell_size).max(0.0) as usize;
+        (col.min(self.cols - 1), row.min(self.rows - 1))
+    }
+
+    fn cell_of(&self, position: Vector2D) -> usize {
+        let (col, row) = self.cell_coords(position);
+        row * self.cols + col
+    }
+}
+
+/// Buffers reused across `FlockStd` ticks, so that a steady-state update
+/// does not touch the allocator.
+///
+/// Capacity only grows, in powers of two, and is kept when the flock
+/// shrinks; `growths` counts how often that happened.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct ScratchArena {
+    forces: Vec<Vector2D>,
+    /// Neighbor lists of the current tick: boid `i` sees
+    /// `neighbor_indices[neighbor_start[i]..neighbor_start[i + 1]]`
+    neighbor_start: Vec<usize>,
+    neighbor_indices: Vec<usize>,
+    /// The boids as the neighbor lists were built, by index, kept for
+    /// [`FlockStd::neighbor_graph`]
+    searched: Vec<neighbors::SearchedBoid>,
+    /// Packed boid positions for the pairwise scan
+    positions: batch::Vectors,
+    /// Group configs of the current tick with modifiers applied, by group
+    group_configs: Vec<(u8, BoidConfig)>,
+    /// Boids the boundary caught this tick, by id and position, while the
+    /// flock's events are watched
+    boundary_hits: Vec<(u32, Vector2D)>,
+    growths: u32,
+}
+
+#[cfg(feature = "std")]
+impl ScratchArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of times a buffer had to grow since the arena was created
+    pub fn growths(&self) -> u32 {
+        self.growths
+    }
+
+    /// Bytes currently reserved by all buffers
+    pub fn capacity_bytes(&self) -> usize {
+        self.forces.capacity() * core::mem::size_of::<Vector2D>()
+            + (self.neighbor_start.capacity() + self.neighbor_indices.capacity())
+                * core::mem::size_of::<usize>()
+            + self.searched.capacity() * core::mem::size_of::<neighbors::SearchedBoid>()
+            + self.positions.capacity() * core::mem::size_of::<Vector2D>()
+            + self.group_configs.capacity() * core::mem::size_of::<(u8, BoidConfig)>()
+            + self.boundary_hits.capacity() * core::mem::size_of::<(u32, Vector2D)>()
+    }
+
+    /// Empty all buffers, making sure the per-boid ones fit `boid_count`
+    fn reset(&mut self, boid_count: usize) {
+        self.forces.clear();
+        self.boundary_hits.clear();
+        self.neighbor_start.clear();
+        self.neighbor_indices.clear();
+        self.searched.clear();
+        let grew = reserve_geometric(&mut self.forces, boid_count)
+            | reserve_geometric(&mut self.neighbor_start, boid_count + 1)
+            | reserve_geometric(&mut self.searched, boid_count);
+        self.growths += grew as u32;
+    }
+
+    /// Make room for every boid to hit the boundary in one tick
+    fn reserve_boundary_hits(&mut self, boid_count: usize) {
+        self.growths += reserve_geometric(&mut self.boundary_hits, boid_count) as u32;
+    }
+}
+
+/// Grow `buffer` to the next power of two holding `needed` elements.
+/// Returns whether it had to reallocate.
+#[cfg(feature = "std")]
+fn reserve_geometric<T>(buffer: &mut Vec<T>, needed: usize) -> bool {
+    if buffer.capacity() >= needed {
+        return false;
+    }
+    buffer.reserve_exact(needed.next_power_of_two() - buffer.len());
+    true
+}
+
+/// Time spent in each phase of one `FlockStd` tick, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickTiming {
+    /// Building the neighbor lists (grid rebuild and queries, or the pairwise scan)
+    pub neighbor_search_ms: f64,
+    /// Wander updates and steering force evaluation
+    pub forces_ms: f64,
+    /// Applying forces, moving boids and boundary handling
+    pub integration_ms: f64,
+}
+
+impl TickTiming {
+    pub fn total_ms(&self) -> f64 {
+        self.neighbor_search_ms + self.forces_ms + self.integration_ms
+    }
+}
+
+/// A collection of boids for std environments
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "FlockStdState"))]
+pub struct FlockStd {
+    pub boids: Vec<Boid>,
+    /// Persisted base configuration
+    pub config: BoidConfig,
+    /// Configuration replacing `config` for the boids of a group; groups
+    /// without one use `config`. Modifiers apply to both.
+    pub group_configs: BTreeMap<u8, BoidConfig>,
+    /// Transient modifiers resolved against `config` every tick
+    pub modifiers: ConfigModifiers,
+    /// Crossfade toward a preset, advancing `config` once per tick
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub transition: Option<ConfigTransition>,
+    pub width: f32,
+    pub height: f32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    grid: SpatialGrid,
+    next_id: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scratch: ScratchArena,
+    /// Forces summed for every boid outside a formation; snapshots do not
+    /// keep custom behaviors, a restored flock starts with the standard ones
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub behaviors: BehaviorPipeline,
+    /// Caps `spawn`; extra boids are dropped oldest first on update
+    pub population: Option<PopulationLimit>,
+    /// Killed boids are respawned at random positions on the next update
+    /// while fewer than this many remain
+    pub min_population: usize,
+    /// Force added to every boid outside a formation each update,
+    /// bypassing `max_force`
+    pub global_force: Vector2D,
+    /// Wind varying over time on top of `global_force`
+    pub gust: Gust,
+    /// Seconds `gust` has been blowing
+    gust_time: f64,
+    /// Source of all randomness (initial boids and wander), saved in
+    /// snapshots so a restored run continues identically
+    rng: ChaCha8Rng,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    events: events::EventTracker,
+    /// Ages, breeds and kills boids when set, see [`ecology`]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ecology: Option<ecology::Ecology>,
+    /// Lands boids on the bottom edge to rest when set, see [`perching`]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    perching: Option<perching::Perching>,
+    /// Notable moments of the run when set, see [`timeline`]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    timeline: Option<Timeline>,
+    /// Set by `form_shape`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    shape: Option<formation::FormationController>,
+}
+
+/// Serialized form of `FlockStd`. The fields missing from a `Flock<N>`
+/// snapshot are optional, so embedded snapshots load as well.
+#[cfg(all(feature = "std", feature = "serde"))]
+#[derive(serde::Deserialize)]
+struct FlockStdState {
+    boids: Vec<Boid>,
+    #[serde(default)]
+    config: BoidConfig,
+    #[serde(default)]
+    group_configs: BTreeMap<u8, BoidConfig>,
+    #[serde(default)]
+    modifiers: ConfigModifiers,
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    next_id: u32,
+    #[serde(default)]
+    population: Option<PopulationLimit>,
+    #[serde(default)]
+    min_population: usize,
+    #[serde(default)]
+    global_force: Vector2D,
+    #[serde(default)]
+    gust: Gust,
+    #[serde(default)]
+    gust_time: f64,
+    #[serde(default)]
+    rng: Option<ChaCha8Rng>,
+}
+
+#[cfg(all(feature = "std", feature = "serde"))]
+impl From<FlockStdState> for FlockStd {
+    fn from(state: FlockStdState) -> Self {
+        let mut boids = state.boids;
+
+        // `Flock<N>` does not assign ids, so its boids all arrive as 0
+        let mut ids: Vec<u32> = boids.iter().map(|boid| boid.id).collect();
+        ids.sort_unstable();
+        if ids.windows(2).any(|pair| pair[0] == pair[1]) {
+            for (id, boid) in boids.iter_mut().enumerate() {
+                boid.id = id as u32;
+            }
+        }
+        let next_id = boids
+            .iter()
+            .map(|boid| boid.id + 1)
+            .max()
+            .unwrap_or(0)
+            .max(state.next_id);
+
+        Self {
+            boids,
+            config: state.config,
+            group_configs: state.group_configs,
+            modifiers: state.modifiers,
+            transition: None,
+            width: state.width,
+            height: state.height,
+            grid: SpatialGrid::new(),
+            next_id,
+            scratch: ScratchArena::new(),
+            behaviors: BehaviorPipeline::standard(),
+            population: state.population,
+            min_population: state.min_population,
+            global_force: state.global_force,
+            gust: state.gust,
+            gust_time: state.gust_time,
+            rng: state.rng.unwrap_or_else(entropy_rng),
+            events: events::EventTracker::default(),
+            ecology: None,
+            perching: None,
+            timeline: None,
+            shape: None,
+        }
+    }
+}
+
+/// The target closest to `position`, if there is any
+fn nearest_target(targets: &[Vector2D], position: Vector2D) -> Option<Vector2D> {
+    targets
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance(&position).total_cmp(&b.distance(&position)))
+}
+
+#[cfg(feature = "std")]
+fn entropy_rng() -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(rand::thread_rng().gen())
+}
+
+#[cfg(feature = "std")]
+impl FlockStd {
+    pub fn new(width: f32, height: f32, count: usize) -> Self {
+        Self::new_with_config(width, height, count, BoidConfig::default())
+    }
+
+    pub fn new_with_config(width: f32, height: f32, count: usize, config: BoidConfig) -> Self {
+        Self::with_rng(width, height, count, config, entropy_rng())
+    }
+
+    /// Reproducible flock: the same seed gives the same boids and the same
+    /// wander on every platform, including wasm32
+    pub fn new_with_seed(width: f32, height: f32, count: usize, seed: u64) -> Self {
+        Self::new_with_config_and_seed(width, height, count, BoidConfig::default(), seed)
+    }
+
+    pub fn new_with_config_and_seed(
+        width: f32,
+        height: f32,
+        count: usize,
+        config: BoidConfig,
+        seed: u64,
+    ) -> Self {
+        Self::with_rng(
+            width,
+            height,
+            count,
+            config,
+            ChaCha8Rng::seed_from_u64(seed),
+        )
+    }
+
+    fn with_rng(
+        width: f32,
+        height: f32,
+        count: usize,
+        config: BoidConfig,
+        mut rng: ChaCha8Rng,
+    ) -> Self {
+        let boids = (0..count as u32)
+            .map(|id| Boid {
+                id,
+                ..Boid::random_with_rng(width, height, &mut rng)
+            })
+            .collect();
+
+        Self {
+            boids,
+            config,
+            group_configs: BTreeMap::new(),
+            modifiers: ConfigModifiers::IDENTITY,
+            transition: None,
+            width,
+            height,
+            grid: SpatialGrid::new(),
+            next_id: count as u32,
+            scratch: ScratchArena::new(),
+            behaviors: BehaviorPipeline::standard(),
+            population: None,
+            min_population: 0,
+            global_force: Vector2D::zero(),
+            gust: Gust::Calm,
+            gust_time: 0.0,
+            rng,
+            events: events::EventTracker::default(),
+            ecology: None,
+            perching: None,
+            timeline: None,
+            shape: None,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.update_with_target(None);
+    }
+
+    /// Set a force applied uniformly to all boids, bypassing `max_force`.
+    /// Pass `Vector2D::zero()` to disable it.
+    pub fn set_global_force(&mut self, force: Vector2D) {
+        self.global_force = force;
+    }
+
+    /// Blow `gust` on top of the global force, from the start of its
+    /// pattern. `Gust::Calm` stops it.
+    pub fn set_gust(&mut self, gust: Gust) {
+        self.gust = gust;
+        self.gust_time = 0.0;
+    }
+
+    /// Global force plus the current gust, as added to every boid
+    pub fn wind(&self) -> Vector2D {
+        self.global_force + self.gust.at(self.gust_time)
+    }
+
+    /// Edge handling in effect: `config.boundary`, bouncing when unset
+    pub fn boundary(&self) -> BoundaryBehavior {
+        self.config.boundary.unwrap_or(BoundaryBehavior::Bounce)
+    }
+
+    /// Base config with the current modifiers applied, as used by `update`
+    pub fn effective_config(&self) -> BoidConfig {
+        self.modifiers.apply(&self.config)
+    }
+
+    /// Effective configuration for the boids of `group`
+    pub fn config_for_group(&self, group: u8) -> BoidConfig {
+        let base = self.group_configs.get(&group).unwrap_or(&self.config);
+        self.modifiers.apply(base)
+    }
+
+    /// Give `group` its own configuration instead of the flock's
+    pub fn set_group_config(&mut self, group: u8, config: BoidConfig) {
+        self.group_configs.insert(group, config);
+    }
+
+    /// Let `group` use the flock's configuration again
+    pub fn clear_group_config(&mut self, group: u8) -> Option<BoidConfig> {
+        self.group_configs.remove(&group)
+    }
+
+    /// Move the boid with the given id into `group`; false if there is none
+    pub fn set_group(&mut self, id: u32, group: u8) -> bool {
+        match self.get_mut(id) {
+            Some(boid) => {
+                boid.group = group;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ease `config` toward `preset` over the next `ticks` updates.
+    /// Replaces any crossfade in progress; `0` applies it immediately.
+    pub fn apply_preset_over(&mut self, preset: BoidConfig, ticks: u32) {
+        if ticks == 0 {
+            self.config = preset;
+            self.transition = None;
+        } else {
+            self.transition = Some(ConfigTransition::new(self.config, preset, ticks));
+        }
+    }
+
+    pub fn update_with_target(&mut self, target: Option<Vector2D>) {
+        self.step(StepInput::new().with_targets(target.as_slice()));
+    }
+
+    /// Update with several independent targets (e.g. one per tracked hand).
+    /// Each boid seeks whichever target is nearest to it, so the flock
+    /// splits into one herd per target.
+    pub fn update_with_targets(&mut self, targets: &[Vector2D]) {
+        self.step(StepInput::new().with_targets(targets));
+    }
+
+    /// Same as `update_with_target`, additionally measuring each phase
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tick_instrumented(&mut self, target: Option<Vector2D>) -> TickTiming {
+        let start = std::time::Instant::now();
+        let mut clock = || start.elapsed().as_secs_f64() * 1000.0;
+        self.tick_instrumented_with_clock(target.as_slice(), &mut clock)
+    }
+
+    /// `update_with_targets` measuring each phase with a caller-supplied
+    /// millisecond clock, for targets without `std::time::Instant` (e.g.
+    /// `performance.now()` in WASM)
+    pub fn tick_instrumented_with_clock(
+        &mut self,
+        targets: &[Vector2D],
+        mut clock: impl FnMut() -> f64,
+    ) -> TickTiming {
+        let input = StepInput::new()
+            .with_targets(targets)
+            .with_clock(&mut clock);
+        self.step(input).debug.unwrap_or_default()
+    }
+
+    /// Advance the flock as `input` says; see [`step`]
+    pub fn step(&mut self, input: StepInput<'_>) -> StepOutput {
+        let scale = input.scale();
+        let targets = input.targets;
+        let mut output = StepOutput::default();
+        if let Some(ref mut shape) = self.shape {
+            shape.maintain(&mut self.boids);
+        }
+        let timing = match input.clock {
+            Some(clock) => Some(self.tick(input.targets, input.stimuli, scale, clock, &mut output)),
+            None => {
+                self.tick(
+                    input.targets,
+                    input.stimuli,
+                    scale,
+                    &mut || 0.0,
+                    &mut output,
+                );
+                None
+            }
+        };
+        output.debug = timing;
+        if self.events.is_active() {
+            self.events
+                .observe(&self.boids, targets, &self.scratch.boundary_hits);
+        }
+        if let Some(ref mut timeline) = self.timeline {
+            let crossfade = self.transition.as_ref().map(ConfigTransition::target);
+            timeline.observe(&self.boids, &self.config, crossfade, targets);
+        }
+        let capacity = self.max_population();
+        output.with_stats(&self.boids, capacity, scale)
+    }
+
+    /// Call `listener` with every [`FlockEvent`] from now on, right after
+    /// the step it happened in
+    pub fn on_event(&mut self, listener: impl FnMut(&FlockEvent) + Send + Sync + 'static) {
+        self.events.listen(Box::new(listener), &self.boids);
+    }
+
+    /// Queue every [`FlockEvent`] from now on for `drain_events`, for
+    /// embedders that poll instead of taking callbacks. The queue keeps the
+    /// latest `events::QUEUE_CAPACITY` events.
+    pub fn watch_events(&mut self) {
+        self.events.queue(&self.boids);
+    }
+
+    /// Take the queued events, oldest first
+    pub fn drain_events(&mut self) -> std::collections::vec_deque::Drain<'_, FlockEvent> {
+        self.events.drain()
+    }
+
+    /// Events the queue lost to its capacity
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped()
+    }
+
+    /// Report the flock's centroid entering and leaving `region`; returns
+    /// the number its events carry
+    pub fn add_event_region(&mut self, region: Region) -> usize {
+        self.events.add_region(region)
+    }
+
+    pub fn clear_event_regions(&mut self) {
+        self.events.clear_regions();
+    }
+
+    /// Let the boids age, breed and die, see [`ecology`]. Off with `None`,
+    /// which forgets every boid's age and energy; a new config keeps them.
+    pub fn set_ecology(&mut self, config: Option<EcologyConfig>) {
+        match (config, self.ecology.as_mut()) {
+            (Some(config), Some(ecology)) => ecology.config = config,
+            (Some(config), None) => {
+                self.ecology = Some(ecology::Ecology::new(config, &self.boids, &mut self.rng));
+            }
+            (None, _) => self.ecology = None,
+        }
+    }
+
+    pub fn ecology(&self) -> Option<EcologyConfig> {
+        self.ecology.as_ref().map(|ecology| ecology.config)
+    }
+
+    /// Keep a [`Timeline`] of notable moments from the next step on. Off
+    /// with `None`, which discards it; a new config keeps its markers.
+    pub fn set_timeline(&mut self, config: Option<TimelineConfig>) {
+        match (config, self.timeline.as_mut()) {
+            (Some(config), Some(timeline)) => timeline.config = config,
+            (Some(config), None) => self.timeline = Some(Timeline::new(config)),
+            (None, _) => self.timeline = None,
+        }
+    }
+
+    pub fn timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    /// Age and energy of the boid with the given id, in ecological mode
+    pub fn life(&self, id: u32) -> Option<Life> {
+        self.ecology.as_ref()?.life(id)
+    }
+
+    /// Births, deaths and the population's age and energy, in ecological
+    /// mode
+    pub fn population_stats(&self) -> Option<PopulationStats> {
+        Some(self.ecology.as_ref()?.stats(&self.boids))
+    }
+
+    /// Let boids land on the bottom edge and rest now and then, see
+    /// [`perching`]. Off with `None`, which sends perched and landing boids
+    /// straight back into the air; a new config keeps them where they are.
+    pub fn set_perching(&mut self, config: Option<PerchConfig>) {
+        match (config, self.perching.as_mut()) {
+            (Some(config), Some(perching)) => perching.config = config,
+            (Some(config), None) => self.perching = Some(perching::Perching::new(config)),
+            (None, _) => self.perching = None,
+        }
+    }
+
+    pub fn perching(&self) -> Option<PerchConfig> {
+        self.perching.as_ref().map(|perching| perching.config)
+    }
+
+    /// Landing cycle of the boid with the given id, `Flying` unless
+    /// perching is on, for renderers to draw resting boids apart
+    pub fn perch_state(&self, id: u32) -> PerchState {
+        self.perching
+            .as_ref()
+            .map_or(PerchState::Flying, |perching| perching.state(id))
+    }
+
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    fn tick(
+        &mut self,
+        targets: &[Vector2D],
+        stimuli: &[Stimulus],
+        scale: f32,
+        clock: &mut dyn FnMut() -> f64,
+        output: &mut StepOutput,
+    ) -> TickTiming {
+        let (trimmed, respawned) = self.maintain_population();
+        output.push(StepEvent::Trimmed(trimmed));
+        output.push(StepEvent::Respawned(respawned));
+        if let Some(ref mut transition) = self.transition {
+            self.config = transition.step();
+            if transition.is_finished() {
+                self.transition = None;
+                output.push(StepEvent::TransitionFinished);
+            }
+        }
+        let config = self.effective_config();
+        // Taken out of the arena for the tick, so it can be read while the
+        // other buffers are written
+        let mut group_configs = core::mem::take(&mut self.scratch.group_configs);
+        group_configs.clear();
+        group_configs.extend(
+            self.group_configs
+                .iter()
+                .map(|(&group, base)| (group, self.modifiers.apply(base))),
+        );
+        // Sorted, since the map iterates in key order
+        let config_of = |boid: &Boid| match group_configs
+            .binary_search_by_key(&boid.group, |&(group, _)| group)
+        {
+            Ok(index) => &group_configs[index].1,
+            Err(_) => &config,
+        };
+        let start = clock();
+        let radius = group_configs
+            .iter()
+            .map(|(_, config)| config)
+            .chain([&config])
+            .map(|config| {
+                config
+                    .separation_distance
+                    .max(config.alignment_distance)
+                    .max(config.cohesion_distance)
+            })
+            .fold(0.0, f32::max);
+        self.find_neighbors(radius, config.spatial_grid);
+        let watched = self.events.is_active();
+        if watched {
+            self.scratch.reserve_boundary_hits(self.boids.len());
+        }
+        let searched = clock();
+
+        // Wander angles drift wherever wander is on, with or without a target
+        let dt = scale * step::REFERENCE_DT;
+        for boid in self.boids.iter_mut() {
+            if config_of(boid).wander_enabled {
+                behavior::evolve_wander(boid, dt, &mut self.rng);
+            }
+        }
+
+        // Calculate forces for all boids
+        let ScratchArena {
+            forces,
+            neighbor_start,
+            neighbor_indices,
+            ..
+        } = &mut self.scratch;
+        forces.extend(self.boids.iter().enumerate().map(|(i, boid)| {
+            let config = config_of(boid);
+            if let TargetOverride::Formation(slot) = boid.target_override {
+                return behavior::hold_slot(boid, slot, config);
+            }
+            if let Some(force) = self
+                .perching
+                .as_ref()
+                .and_then(|perching| perching.steer(boid, self.height, config))
+            {
+                return force;
+            }
+            let target = boid
+                .target_override
+                .resolve(nearest_target(targets, boid.position));
+            let ctx = FlockContext::new(&self.boids, config, target, self.width, self.height)
+                .with_neighbors(&neighbor_indices[neighbor_start[i]..neighbor_start[i + 1]]);
+            self.behaviors.steer(boid, &ctx)
+        }));
+        let forced = clock();
+
+        // Apply forces and update boids
+        let wind = self.wind();
+        for (boid, force) in self.boids.iter_mut().zip(self.scratch.forces.iter()) {
+            if boid.puppet {
+                continue;
+            }
+            let config = config_of(boid);
+            let perch = self
+                .perching
+                .as_ref()
+                .map_or(PerchState::Flying, |perching| perching.state(boid.id));
+            match perch {
+                PerchState::Flying => {}
+                // Sits still until it takes off
+                PerchState::Perched { .. } => continue,
+                // Glides straight down, past wind and the boundary
+                PerchState::Landing => {
+                    boid.apply_force(*force);
+                    boid.integrate(config.max_speed, scale);
+                    boid.position.y = boid.position.y.min(perching::ground(self.height));
+                    let boundary = config.boundary.unwrap_or(BoundaryBehavior::Bounce);
+                    boundary.constrain(boid, self.width, self.height);
+                    continue;
+                }
+            }
+
+            // Gravity acts on velocity directly, bypassing steering limits
+            let target = nearest_target(targets, boid.position);
+            if let Some(target_pos) = boid.target_override.resolve(target) {
+                if config.target_mode == TargetMode::GravityWell
+                    && !boid.target_override.is_formation()
+                {
+                    let pull = behavior::gravity_well(boid, target_pos, config);
+                    if pull != Vector2D::zero() {
+                        boid.velocity += pull * scale;
+                        boid.velocity = boid.velocity * (1.0 - config.gravity_damping);
+                    }
+                }
+            }
+
+            if !boid.target_override.is_formation() {
+                boid.apply_force(wind + step::stimulus_force(boid, stimuli, config));
+            }
+            let boundary = config.boundary.unwrap_or(BoundaryBehavior::Bounce);
+            boid.apply_force(*force + boundary.steering(boid, self.width, self.height, config));
+            boid.integrate(config.max_speed, scale);
+            let unconstrained = boid.position;
+            boundary.constrain(boid, self.width, self.height);
+            if watched && boid.position != unconstrained {
+                self.scratch.boundary_hits.push((boid.id, boid.position));
+            }
+        }
+        if let Some(ref mut perching) = self.perching {
+            perching.step(
+                &mut self.boids,
+                self.height,
+                scale * step::REFERENCE_DT,
+                &mut self.rng,
+            );
+        }
+        let (born, died) = self.live(scale);
+        output.push(StepEvent::Born(born));
+        output.push(StepEvent::Died(died));
+        let end = clock();
+        self.scratch.group_configs = group_configs;
+        self.gust_time += (scale * step::REFERENCE_DT) as f64;
+
+        TickTiming {
+            neighbor_search_ms: searched - start,
+            forces_ms: forced - searched,
+            integration_ms: end - forced,
+        }
+    }
+
+    /// Collect, for every boid, the boids within `radius`, the largest
+    /// behavior radius of any group
+    #[deny(
+        clippy::disallowed_types,
+        clippy::disallowed_macros,
+        clippy::disallowed_methods
+    )]
+    fn find_neighbors(&mut self, radius: f32, spatial_grid: bool) {
+        self.scratch.reset(self.boids.len());
+
+        if spatial_grid {
+            self.grid
+                .rebuild(&self.boids, self.width, self.height, radius);
+        } else {
+            let positions = &mut self.scratch.positions;
+            let capacity = positions.capacity();
+            positions.load(self.boids.iter().map(|boid| boid.position));
+            self.scratch.growths += (positions.capacity() != capacity) as u32;
+        }
+
+        // The total neighbor count is only known afterwards; `Vec` already
+        // grows this one geometrically
+        let scratch = &mut self.scratch;
+        let capacity = scratch.neighbor_indices.capacity();
+        for boid in &self.boids {
+            scratch.neighbor_start.push(scratch.neighbor_indices.len());
+            scratch.searched.push(neighbors::SearchedBoid {
+                id: boid.id,
+                group: boid.group,
+                position: boid.position,
+            });
+            let within = |&j: &usize| self.boids[j].position.distance(&boid.position) < radius;
+            if spatial_grid {
+                let start = scratch.neighbor_indices.len();
+                scratch
+                    .neighbor_indices
+                    .extend(self.grid.query(boid.position, radius).filter(within));
+                // Visit neighbors in flock order, as without the grid, so
+                // forces sum identically and the grid never changes a run
+                scratch.neighbor_indices[start..].sort_unstable();
+            } else {
+                scratch
+                    .positions
+                    .within(boid.position, radius, &mut scratch.neighbor_indices);
+            }
+        }
+        scratch.neighbor_start.push(scratch.neighbor_indices.len());
+        scratch.growths += (scratch.neighbor_indices.capacity() != capacity) as u32;
+    }
+
+    /// Send the boids to formation slots, see [`formation::assign`]
+    pub fn form(&mut self, slots: &[Vector2D]) {
+        self.shape = None;
+        formation::assign(&mut self.boids, slots);
+    }
+
+    /// Arrange the flock into `text`, centered on the area; returns the
+    /// number of slots
+    pub fn spell(&mut self, text: &str) -> usize {
+        let slots: Vec<Vector2D> =
+            formation::fit_centered(Raster::Text(text), self.width, self.height).collect();
+        self.form(&slots);
+        slots.len()
+    }
+
+    pub fn release_formation(&mut self) {
+        self.shape = None;
+        formation::release(&mut self.boids);
+    }
+
+    /// Hold the flock in the shape of `points`, one boid per point where
+    /// there are enough, with the least total travel. Boids that join or
+    /// leave later are accounted for on the next step; see
+    /// [`formation::FormationController`]. Replaces any formation before.
+    pub fn form_shape(&mut self, points: &[Vector2D]) {
+        let mut controller = formation::FormationController::new(points);
+        controller.assign(&mut self.boids);
+        self.shape = Some(controller);
+    }
+
+    /// The shape `form_shape` holds the flock in
+    pub fn shape(&self) -> Option<&formation::FormationController> {
+        self.shape.as_ref()
+    }
+
+    /// Break up the formation, scattering the boids outward from the middle
+    /// of the shape at `max_speed`
+    pub fn disperse(&mut self) {
+        match self.shape.take() {
+            Some(mut controller) => controller.disperse(&mut self.boids, self.config.max_speed),
+            None => formation::release(&mut self.boids),
+        }
+    }
+
+    /// Steer the boids along `path` on top of their other behaviors, as the
+    /// `"path"` behavior; replaces any path followed before
+    pub fn follow_path(&mut self, path: Path) {
+        self.behaviors
+            .add(PATH_BEHAVIOR, steering::FollowPath(path), 1.0);
+    }
+
+    /// Stop following the path; false if there was none
+    pub fn stop_following_path(&mut self) -> bool {
+        self.behaviors.remove(PATH_BEHAVIOR)
+    }
+
+    /// Who counted as whose neighbor in the last update, under one
+    /// behavior's radius; see [`neighbors`]
+    pub fn neighbor_graph(&self, radius: NeighborRadius) -> NeighborGraph<'_> {
+        NeighborGraph::new(self, radius)
+    }
+
+    /// Ids of the boids `id` steered by under `radius` in the last update
+    pub fn neighbors_of(&self, id: u32, radius: NeighborRadius) -> Vec<u32> {
+        self.neighbor_graph(radius).neighbors_of(id).collect()
+    }
+
+    /// Per-tick buffers, for monitoring allocator pressure
+    pub fn scratch(&self) -> &ScratchArena {
+        &self.scratch
+    }
+
+    /// Add a boid and return the stable id assigned to it
+    pub fn add_boid(&mut self, boid: Boid) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.boids.push(Boid { id, ..boid });
+        id
+    }
+
+    /// Current index of the boid with the given id
+    pub fn index_of(&self, id: u32) -> Option<usize> {
+        self.boids.iter().position(|boid| boid.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Boid> {
+        self.boids.iter_mut().find(|boid| boid.id == id)
+    }
+
+    /// Remove the boid with the given id; other ids are unaffected
+    pub fn remove_boid(&mut self, id: u32) -> Option<Boid> {
+        let index = self.index_of(id)?;
+        Some(self.boids.remove(index))
+    }
+
+    /// Most boids the flock holds, if it is capped
+    pub fn max_population(&self) -> Option<usize> {
+        self.population.map(|limit| limit.max)
+    }
+
+    /// Grow or shrink the flock to `count` boids, at most the population
+    /// limit. New boids start at random; the newest are removed first and
+    /// returned.
+    pub fn set_boid_count(&mut self, count: usize) -> Vec<Boid> {
+        let count = self.max_population().map_or(count, |max| count.min(max));
+        let removed = self.boids.split_off(count.min(self.boids.len()));
+        while self.boids.len() < count {
+            let boid = Boid::random_with_rng(self.width, self.height, &mut self.rng);
+            self.add_boid(boid);
+        }
+        removed
+    }
+
+    /// Add a boid at `position` moving with `velocity` and return its id.
+    /// At the cap the oldest boid is respawned there (with a new id) if the
+    /// limit recycles, otherwise nothing is spawned.
+    pub fn spawn(&mut self, position: Vector2D, velocity: Vector2D) -> Option<u32> {
+        self.trim_population();
+        if let Some(limit) = self.population {
+            if limit.max == 0 {
+                return None;
+            }
+            if self.boids.len() >= limit.max {
+                if !limit.recycle_oldest {
+                    return None;
+                }
+                self.boids.remove(0);
+            }
+        }
+        Some(self.add_boid(Boid::new(position, velocity)))
+    }
+
+    /// Remove the boid at `index`, keeping the order of the others
+    pub fn remove_at(&mut self, index: usize) -> Option<Boid> {
+        (index < self.boids.len()).then(|| self.boids.remove(index))
+    }
+
+    /// Remove every boid matching `predicate`; returns how many were removed
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&Boid) -> bool) -> usize {
+        let before = self.boids.len();
+        self.boids.retain(|boid| !predicate(boid));
+        before - self.boids.len()
+    }
+
+    /// Index of the boid closest to `position`
+    pub fn nearest(&self, position: Vector2D) -> Option<usize> {
+        nearest_boid(&self.boids, position)
+    }
+
+    /// Remove the boid closest to `position` if it is within `max_distance`
+    pub fn remove_nearest(&mut self, position: Vector2D, max_distance: f32) -> Option<Boid> {
+        let index = self.nearest(position)?;
+        if self.boids[index].position.distance(&position) > max_distance {
+            return None;
+        }
+        self.remove_at(index)
+    }
+
+    /// Drop the oldest boids while the flock is over its limit; returns how
+    /// many were dropped
+    fn trim_population(&mut self) -> usize {
+        let Some(max) = self.max_population() else {
+            return 0;
+        };
+        let excess = self.boids.len().saturating_sub(max);
+        self.boids.drain(..excess);
+        excess
+    }
+
+    /// Enforce the population limit, then respawn up to `min_population`;
+    /// returns how many boids were dropped and respawned
+    fn maintain_population(&mut self) -> (usize, usize) {
+        let trimmed = self.trim_population();
+        let floor = self
+            .max_population()
+            .map_or(self.min_population, |max| max.min(self.min_population));
+        let respawned = floor.saturating_sub(self.boids.len());
+        while self.boids.len() < floor {
+            let boid = Boid::random_with_rng(self.width, self.height, &mut self.rng);
+            self.add_boid(boid);
+        }
+        (trimmed, respawned)
+    }
+
+    /// Advance the boids' lives by a step of `scale`; returns how many
+    /// were born and died
+    fn live(&mut self, scale: f32) -> (usize, usize) {
+        let Some(ref mut ecology) = self.ecology else {
+            return (0, 0);
+        };
+        let room = self.population.map_or(usize::MAX, |limit| {
+            limit.max.saturating_sub(self.boids.len())
+        });
+        ecology.step(
+            &mut self.boids,
+            scale * step::REFERENCE_DT,
+            self.config.max_speed,
+            room,
+            &mut self.next_id,
+            &mut self.rng,
+        )
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector2d_new() {
+        let v = Vector2D::new(3.0, 4.0);
+        assert_eq!(v.x, 3.0);
+        assert_eq!(v.y, 4.0);
+    }
+
+    #[test]
+    fn test_vector2d_magnitude() {
+        let v = Vector2D::new(3.0, 4.0);
+        assert_eq!(v.magnitude(), 5.0);
+    }
+
+    #[test]
+    fn test_vector2d_normalize() {
+        let v = Vector2D::new(3.0, 4.0);
+        let normalized = v.normalize();
+        assert!((normalized.magnitude() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_vector2d_operations() {
+        let v1 = Vector2D::new(1.0, 2.0);
+        let v2 = Vector2D::new(3.0, 4.0);
+
+        let sum = v1 + v2;
+        assert_eq!(sum.x, 4.0);
+        assert_eq!(sum.y, 6.0);
+
+        let diff = v2 - v1;
+        assert_eq!(diff.x, 2.0);
+        assert_eq!(diff.y, 2.0);
+
+        let scaled = v1 * 2.0;
+        assert_eq!(scaled.x, 2.0);
+        assert_eq!(scaled.y, 4.0);
+    }
+
+    #[test]
+    fn test_boid_creation() {
+        let pos = Vector2D::new(10.0, 20.0);
+        let vel = Vector2D::new(1.0, 1.0);
+        let boid = Boid::new(pos, vel);
+
+        assert_eq!(boid.position.x, 10.0);
+        assert_eq!(boid.position.y, 20.0);
+        assert_eq!(boid.velocity.x, 1.0);
+        assert_eq!(boid.velocity.y, 1.0);
+    }
+
+    #[test]
+    fn test_boid_update() {
+        let pos = Vector2D::new(0.0, 0.0);
+        let vel = Vector2D::new(1.0, 1.0);
+        let mut boid = Boid::new(pos, vel);
+
+        boid.update(10.0, 1.0);
+
+        assert_eq!(boid.position.x, 1.0);
+        assert_eq!(boid.position.y, 1.0);
+    }
+
+    #[test]
+    fn test_boid_wrap_edges() {
+        let pos = Vector2D::new(-1.0, -1.0);
+        let vel = Vector2D::new(0.0, 0.0);
+        let mut boid = Boid::new(pos, vel);
+
+        boid.wrap_edges(100.0, 100.0);
+
+        assert_eq!(boid.position.x, 100.0);
+        assert_eq!(boid.position.y, 100.0);
+    }
+
+    #[test]
+    fn test_steer_away_turns_before_the_wall() {
+        let config = BoidConfig {
+            boundary: Some(BoundaryBehavior::SteerAway {
+                margin: 40.0,
+                weight: 4.0,
+            }),
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            ..BoidConfig::default()
+        };
+        let heading_right = || Boid::new(Vector2D::new(100.0, 100.0), Vector2D::new(2.0, 0.0));
+
+        let mut flock = FlockStd::new_with_config(200.0, 200.0, 0, config);
+        flock.boids.push(heading_right());
+        let mut fixed = Flock::<1>::new(200.0, 200.0, config);
+        fixed.add_boid(heading_right()).unwrap();
+
+        let mut furthest: f32 = 0.0;
+        for _ in 0..300 {
+            flock.update();
+            fixed.update();
+            furthest = furthest.max(flock.boids[0].position.x);
+        }
+        assert!(furthest < 200.0, "reached x = {}", furthest);
+        assert!(flock.boids[0].velocity.x < 0.0);
+        assert_eq!(flock.boids[0].position, fixed.boids[0].position);
+    }
+
+    #[test]
+    fn test_unbounded_boids_leave_the_area() {
+        let config = BoidConfig {
+            boundary: Some(BoundaryBehavior::Unbounded),
+            ..BoidConfig::default()
+        };
+        let mut flock = Flock::<1>::new(100.0, 100.0, config);
+        flock
+            .add_boid(Boid::new(
+                Vector2D::new(95.0, 50.0),
+                Vector2D::new(2.0, 0.0),
+            ))
+            .unwrap();
+        for _ in 0..10 {
+            flock.update();
+        }
+        assert!(flock.boids[0].position.x > 110.0);
+    }
+
+    #[test]
+    fn test_unset_boundary_keeps_each_flocks_edges() {
+        let leaving = || Boid::new(Vector2D::new(99.0, 50.0), Vector2D::new(2.0, 0.0));
+        let mut fixed = Flock::<1>::new(100.0, 100.0, BoidConfig::default());
+        fixed.add_boid(leaving()).unwrap();
+        fixed.update();
+        assert_eq!(fixed.boundary(), BoundaryBehavior::Wrap);
+        assert!(fixed.boids[0].position.x < 10.0);
+
+        let mut flock = FlockStd::new(100.0, 100.0, 0);
+        flock.boids.push(leaving());
+        flock.update();
+        assert_eq!(flock.boundary(), BoundaryBehavior::Bounce);
+        assert_eq!(flock.boids[0].position.x, 90.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_without_boundary_keeps_wrapping() {
+        let flock = Flock::<1>::new(100.0, 100.0, BoidConfig::default());
+        let mut json = serde_json::to_value(&flock).unwrap();
+        json["config"].as_object_mut().unwrap().remove("boundary");
+        let restored: Flock<1> = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.config.boundary, None);
+        assert_eq!(restored.boundary(), BoundaryBehavior::Wrap);
+    }
+
+    #[test]
+    fn test_flock_creation() {
+        let flock = FlockStd::new(800.0, 600.0, 50);
+        assert_eq!(flock.boids.len(), 50);
+        assert_eq!(flock.width, 800.0);
+        assert_eq!(flock.height, 600.0);
+    }
+
+    #[test]
+    fn test_flock_update() {
+        let mut flock = FlockStd::new(800.0, 600.0, 10);
+        let initial_positions: Vec<_> = flock.boids.iter().map(|b| b.position).collect();
+
+        flock.update();
+
+        // Positions should change after update
+        let changed = flock
+            .boids
+            .iter()
+            .zip(initial_positions.iter())
+            .any(|(b, &initial)| b.position.x != initial.x || b.position.y != initial.y);
+
+        assert!(changed);
+    }
+
+    #[test]
+    fn test_flock_add_boid() {
+        let mut flock = FlockStd::new(800.0, 600.0, 10);
+        let initial_count = flock.boids.len();
+
+        let boid = Boid::random(800.0, 600.0);
+        flock.add_boid(boid);
+
+        assert_eq!(flock.boids.len(), initial_count + 1);
+    }
+
+    #[test]
+    fn test_gravity_well_pulls_toward_target() {
+        let config = BoidConfig::default();
+        let boid = Boid::new(Vector2D::new(100.0, 100.0), Vector2D::zero());
+
+        let pull = behavior::gravity_well(&boid, Vector2D::new(150.0, 100.0), &config);
+        assert!(pull.x > 0.0);
+        assert_eq!(pull.y, 0.0);
+
+        // Inverse-square: twice the distance gives roughly a quarter of the pull
+        let far_pull = behavior::gravity_well(&boid, Vector2D::new(200.0, 100.0), &config);
+        assert!(far_pull.x < pull.x / 3.0);
+    }
+
+    #[test]
+    fn test_gravity_well_releases_escaped_boids() {
+        let config = BoidConfig {
+            gravity_escape_radius: 50.0,
+            ..BoidConfig::default()
+        };
+        let boid = Boid::new(Vector2D::new(0.0, 0.0), Vector2D::zero());
+
+        let pull = behavior::gravity_well(&boid, Vector2D::new(100.0, 0.0), &config);
+        assert_eq!(pull, Vector2D::zero());
+    }
+
+    #[test]
+    fn test_gravity_well_mode_replaces_seek() {
+        let config = BoidConfig {
+            target_mode: TargetMode::GravityWell,
+            gravity_damping: 0.0,
+            ..BoidConfig::default()
+        };
+        let mut flock = FlockStd::new_with_config(800.0, 600.0, 0, config);
+        // Moving tangentially around the target at (400, 300)
+        flock.add_boid(Boid::new(
+            Vector2D::new(400.0, 250.0),
+            Vector2D::new(2.0, 0.0),
+        ));
+
+        flock.update_with_target(Some(Vector2D::new(400.0, 300.0)));
+
+        // Pulled toward the target while keeping tangential momentum
+        let boid = &flock.boids[0];
+        assert!(boid.velocity.y > 0.0);
+        assert!(boid.velocity.x > 1.5);
+    }
+
+    #[test]
+    fn test_puppet_boid_not_integrated_but_seen_by_neighbors() {
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        flock.add_boid(Boid::new_puppet(
+            Vector2D::new(400.0, 300.0),
+            Vector2D::new(1.0, 0.0),
+        ));
+        flock.add_boid(Boid::new(Vector2D::new(410.0, 300.0), Vector2D::zero()));
+
+        flock.update();
+
+        // Puppet stays exactly where it was put
+        assert_eq!(flock.boids[0].position, Vector2D::new(400.0, 300.0));
+        assert_eq!(flock.boids[0].velocity, Vector2D::new(1.0, 0.0));
+
+        // Regular boid is pushed away from the puppet by separation
+        assert!(flock.boids[1].velocity.x > 0.0);
+    }
+
+    #[test]
+    fn test_config_history_undo_restores_previous() {
+        let mut history = ConfigHistory::<4>::new();
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+
+        history.record(ConfigChange {
+            field: "max_speed",
+            source: ChangeSource::Setter,
+            timestamp_ms: 1,
+            previous: flock.config,
+        });
+        flock.config.max_speed = 9.0;
+
+        let change = history.undo().unwrap();
+        assert_eq!(change.field, "max_speed");
+        flock.config = change.previous;
+        assert_eq!(flock.config.max_speed, BoidConfig::default().max_speed);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn test_config_history_drops_oldest_when_full() {
+        let mut history = ConfigHistory::<2>::new();
+        for timestamp_ms in 0..3 {
+            history.record(ConfigChange {
+                field: "seek_weight",
+                source: ChangeSource::Api,
+                timestamp_ms,
+                previous: BoidConfig::default(),
+            });
+        }
+
+        assert_eq!(history.len(), 2);
+        let timestamps: Vec<u64> = history.iter().map(|c| c.timestamp_ms).collect();
+        assert_eq!(timestamps, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_flock_global_force() {
+        let mut flock = Flock::<4>::new(240.0, 240.0, BoidConfig::default());
+        flock
+            .add_boid(Boid::new(Vector2D::new(120.0, 120.0), Vector2D::zero()))
+            .unwrap();
+
+        flock.set_global_force(Vector2D::new(0.0, 0.5));
+        flock.update();
+
+        assert!(flock.boids[0].velocity.y > 0.0);
+        assert!(flock.boids[0].position.y > 120.0);
+    }
+
+    #[test]
+    fn test_wind_gusts_push_both_flocks_alike() {
+        let config = BoidConfig {
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            ..BoidConfig::default()
+        };
+        let boid = Boid::new(Vector2D::new(120.0, 120.0), Vector2D::zero());
+        let mut fixed = Flock::<4>::new(240.0, 240.0, config);
+        fixed.add_boid(boid.clone()).unwrap();
+        let mut flock = FlockStd::new_with_config(240.0, 240.0, 0, config);
+        flock.add_boid(boid);
+
+        let gust = Gust::Sine {
+            amplitude: Vector2D::new(0.1, 0.0),
+            period: 1.0,
+        };
+        fixed.set_global_force(Vector2D::new(0.0, 0.05));
+        fixed.set_gust(gust);
+        flock.set_global_force(Vector2D::new(0.0, 0.05));
+        flock.set_gust(gust);
+        assert_eq!(flock.wind(), Vector2D::new(0.0, 0.05));
+
+        for _ in 0..15 {
+            fixed.update();
+            flock.update();
+        }
+        // A quarter period in, the gust blows at full strength
+        assert!((flock.wind().x - 0.1).abs() < 1e-4);
+        assert_eq!(flock.wind(), fixed.wind());
+        assert_eq!(flock.boids[0].velocity, fixed.boids[0].velocity);
+        assert!(flock.boids[0].velocity.x > 0.0 && flock.boids[0].velocity.y > 0.0);
+    }
+
+    #[test]
+    fn test_spatial_grid_query_covers_radius() {
+        let boids: Vec<Boid> = (0..200).map(|_| Boid::random(400.0, 300.0)).collect();
+        let mut grid = SpatialGrid::new();
+        grid.rebuild(&boids, 400.0, 300.0, 25.0);
+
+        let center = Vector2D::new(200.0, 150.0);
+        let found: Vec<usize> = grid.query(center, 25.0).collect();
+        for (index, boid) in boids.iter().enumerate() {
+            if boid.position.distance(&center) < 25.0 {
+                assert!(found.contains(&index));
+            }
+        }
+
+        // Out-of-bounds positions land in the edge cells
+        let outside = [Boid::new(Vector2D::new(-50.0, 500.0), Vector2D::zero())];
+        grid.rebuild(&outside, 400.0, 300.0, 25.0);
+        assert_eq!(grid.query(Vector2D::new(0.0, 300.0), 25.0).count(), 1);
+    }
+
+    #[test]
+    fn test_spatial_grid_matches_brute_force() {
+        let mut brute = FlockStd::new(400.0, 300.0, 150);
+        let mut gridded = FlockStd::new_with_config(
+            400.0,
+            300.0,
+            0,
+            BoidConfig {
+                spatial_grid: true,
+                ..BoidConfig::default()
+            },
+        );
+        gridded.boids = brute.boids.clone();
+
+        brute.update();
+        gridded.update();
+
+        for (a, b) in brute.boids.iter().zip(gridded.boids.iter()) {
+            assert!(a.position.distance(&b.position) < 1e-4);
+            assert!((a.velocity - b.velocity).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_flock_std_ids_stable_across_removal() {
+        let mut flock = FlockStd::new(800.0, 600.0, 3);
+        let ids: Vec<u32> = flock.boids.iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+
+        let added = flock.add_boid(Boid::new(Vector2D::zero(), Vector2D::zero()));
+        assert_eq!(added, 3);
+
+        assert!(flock.remove_boid(1).is_some());
+        assert!(flock.remove_boid(1).is_none());
+        assert_eq!(flock.index_of(3), Some(2));
+        assert_eq!(
+            flock.add_boid(Boid::new(Vector2D::zero(), Vector2D::zero())),
+            4
+        );
+    }
+
+    #[test]
+    fn test_target_override() {
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        let inherit = flock.add_boid(Boid::new(Vector2D::new(400.0, 300.0), Vector2D::zero()));
+        let free = flock.add_boid(Boid::new(Vector2D::new(100.0, 100.0), Vector2D::zero()));
+        let redirected = flock.add_boid(Boid::new(Vector2D::new(700.0, 300.0), Vector2D::zero()));
+        flock.get_mut(free).unwrap().target_override = TargetOverride::FreeFloat;
+        flock.get_mut(redirected).unwrap().target_override =
+            TargetOverride::Target(Vector2D::new(700.0, 500.0));
+
+        flock.update_with_target(Some(Vector2D::new(600.0, 300.0)));
+
+        let velocity = |id| flock.boids[flock.index_of(id).unwrap()].velocity;
+        assert!(velocity(inherit).x > 0.0);
+        assert!(velocity(free).magnitude() < 0.2);
+        assert!(velocity(redirected).y > velocity(redirected).x.abs());
+    }
+
+    #[test]
+    fn test_update_with_targets_splits_flock() {
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        let left = flock.add_boid(Boid::new(Vector2D::new(200.0, 300.0), Vector2D::zero()));
+        let right = flock.add_boid(Boid::new(Vector2D::new(600.0, 300.0), Vector2D::zero()));
+
+        // Each boid heads for the target on its own side
+        flock.update_with_targets(&[Vector2D::new(100.0, 300.0), Vector2D::new(700.0, 300.0)]);
+
+        let velocity = |id| flock.boids[flock.index_of(id).unwrap()].velocity;
+        assert!(velocity(left).x < 0.0);
+        assert!(velocity(right).x > 0.0);
+    }
+
+    #[test]
+    fn test_form_shape_keeps_up_with_the_flock() {
+        let mut flock = FlockStd::new_with_seed(400.0, 400.0, 30, 7);
+        let ring: Vec<Vector2D> = (0..40)
+            .map(|i| {
+                let angle = i as f32 * core::f32::consts::TAU / 40.0;
+                Vector2D::new(200.0 + 100.0 * angle.cos(), 200.0 + 100.0 * angle.sin())
+            })
+            .collect();
+        flock.form_shape(&ring);
+        let slots: Vec<Option<Vector2D>> = flock
+            .boids
+            .iter()
+            .map(|boid| boid.target_override.resolve(None))
             .collect();
+        assert!(slots.iter().all(|slot| slot.is_some()));
+        // One boid per slot
+        for (i, slot) in slots.iter().enumerate() {
+            assert!(!slots[i + 1..].contains(slot));
+        }
 
-        // Apply forces and update boids
-        for (boid, force) in self.boids.iter_mut().zip(forces.iter()) {
-            boid.apply_force(*force);
-            boid.update(self.config.max_speed, self.config.max_force);
+        let id = flock.add_boid(Boid::new(Vector2D::new(10.0, 10.0), Vector2D::zero()));
+        for _ in 0..600 {
+            flock.update();
+        }
+        assert!(flock.get_mut(id).unwrap().target_override.is_formation());
+        assert_eq!(flock.shape().unwrap().settled(&flock.boids, 1.0), 1.0);
+
+        flock.disperse();
+        assert!(flock.shape().is_none());
+        let max_speed = flock.config.max_speed;
+        assert!(flock.boids.iter().all(|boid| {
+            boid.target_override == TargetOverride::Inherit
+                && (boid.velocity.magnitude() - max_speed).abs() < 1e-4
+        }));
+    }
 
-            // Keep boids within canvas bounds
-            boid.contain_within_bounds(self.width, self.height);
+    #[test]
+    fn test_flocks_spell_text() {
+        let mut flock = FlockStd::new_with_seed(800.0, 600.0, 40, 7);
+        let slots = flock.spell("HI");
+        assert_eq!(slots, 28);
+        for _ in 0..600 {
+            flock.update_with_target(Some(Vector2D::new(0.0, 0.0)));
+        }
+        // Every boid rests on its slot despite the target and its neighbors
+        for boid in &flock.boids {
+            let TargetOverride::Formation(slot) = boid.target_override else {
+                panic!("boid {} has no slot", boid.id);
+            };
+            assert!(boid.position.distance(&slot) < 1.0);
+            assert!(boid.velocity.magnitude() < 0.1);
+        }
+        flock.release_formation();
+        assert!(flock
+            .boids
+            .iter()
+            .all(|boid| boid.target_override == TargetOverride::Inherit));
+
+        // More slots than boids: the fixed flock samples the whole word
+        let mut small = Flock::<8>::new(240.0, 240.0, BoidConfig::default());
+        for i in 0..8 {
+            let position = Vector2D::new(30.0 * i as f32, 10.0);
+            small
+                .add_boid(Boid::new(position, Vector2D::zero()))
+                .unwrap();
+        }
+        assert_eq!(small.spell("OK"), 16 + 14);
+        let rightmost = small
+            .boids
+            .iter()
+            .filter_map(|boid| boid.target_override.resolve(None))
+            .map(|slot| slot.x)
+            .fold(0.0, f32::max);
+        assert!(rightmost > 120.0);
+        for _ in 0..600 {
+            small.update();
         }
+        assert!(small.boids.iter().all(|boid| {
+            let slot = boid.target_override.resolve(None).unwrap();
+            boid.position.distance(&slot) < 1.0
+        }));
     }
 
-    pub fn add_boid(&mut self, boid: Boid) {
-        self.boids.push(boid);
+    #[test]
+    fn test_custom_steering_behaviors() {
+        struct Orbit {
+            center: Vector2D,
+        }
+        impl SteeringBehavior for Orbit {
+            fn steer(&self, boid: &Boid, _ctx: &FlockContext<'_>) -> Vector2D {
+                let offset = boid.position - self.center;
+                Vector2D::new(-offset.y, offset.x).normalize() * 0.1
+            }
+        }
+
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        let id = flock.add_boid(Boid::new(Vector2D::new(500.0, 300.0), Vector2D::zero()));
+        flock.behaviors = BehaviorPipeline::empty();
+        flock.behaviors.add(
+            "orbit",
+            Orbit {
+                center: Vector2D::new(400.0, 300.0),
+            },
+            2.0,
+        );
+        // Closures are behaviors too; same name replaces in place
+        flock.behaviors.add(
+            "drift",
+            |_: &Boid, _: &FlockContext<'_>| Vector2D::new(0.0, 0.0),
+            1.0,
+        );
+        flock.behaviors.add(
+            "drift",
+            |_: &Boid, ctx: &FlockContext<'_>| Vector2D::new(ctx.width, 0.0),
+            0.0,
+        );
+        assert_eq!(
+            flock.behaviors.names().collect::<Vec<_>>(),
+            ["orbit", "drift"]
+        );
+
+        flock.update();
+        let velocity = flock.boids[flock.index_of(id).unwrap()].velocity;
+        assert!((velocity.y - 0.2).abs() < 1e-6 && velocity.x.abs() < 1e-6);
+
+        assert!(flock.behaviors.set_weight("drift", 1e-4));
+        assert!(flock.behaviors.remove("orbit"));
+        assert!(!flock.behaviors.remove("orbit"));
+        assert_eq!(flock.behaviors.weight("drift"), Some(1e-4));
     }
 
-    pub fn resize(&mut self, width: f32, height: f32) {
-        self.width = width;
-        self.height = height;
+    #[test]
+    fn test_behavior_list_matches_fixed_pipeline() {
+        use steering::{Alignment, Cohesion, Seek, Separation};
+
+        let mut fixed = Flock::<8>::new(200.0, 200.0, BoidConfig::default());
+        for i in 0..8 {
+            let position = Vector2D::new(90.0 + 4.0 * i as f32, 100.0 + (i % 3) as f32);
+            let velocity = Vector2D::new(1.0, 0.2 * i as f32);
+            fixed.add_boid(Boid::new(position, velocity)).unwrap();
+        }
+        let mut custom = Flock::<8>::new(200.0, 200.0, BoidConfig::default());
+        custom.boids = fixed.boids.clone();
+
+        let mut behaviors = BehaviorList::<4>::new();
+        for behavior in [
+            &Separation as &dyn SteeringBehavior,
+            &Alignment,
+            &Cohesion,
+            &Seek,
+        ] {
+            behaviors.push(behavior, 1.0).ok().unwrap();
+        }
+        assert!(behaviors.push(&Seek, 1.0).is_err());
+
+        let target = Some(Vector2D::new(150.0, 50.0));
+        for _ in 0..5 {
+            fixed.update_with_target(target);
+            custom.update_with_behaviors(target, &behaviors);
+        }
+        for (a, b) in fixed.boids.iter().zip(custom.boids.iter()) {
+            assert_eq!(a.position, b.position);
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_tick_instrumented_reports_phases() {
+        let mut flock = FlockStd::new(800.0, 600.0, 50);
+        let timing = flock.tick_instrumented(None);
+        assert!(timing.neighbor_search_ms >= 0.0);
+        assert!(timing.forces_ms >= 0.0);
+        assert!(timing.integration_ms >= 0.0);
+
+        // A fake clock advancing 1ms per reading gives 1ms per phase
+        let mut now = 0.0;
+        let timing = flock.tick_instrumented_with_clock(&[], || {
+            now += 1.0;
+            now
+        });
+        assert_eq!(timing.total_ms(), 3.0);
+        assert_eq!(timing.forces_ms, 1.0);
+    }
 
     #[test]
-    fn test_vector2d_new() {
-        let v = Vector2D::new(3.0, 4.0);
-        assert_eq!(v.x, 3.0);
-        assert_eq!(v.y, 4.0);
+    fn test_config_modifiers_leave_base_untouched() {
+        // A tight cluster heading the same way, so alignment reliably
+        // speeds the boids up
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        flock.boids = (0..10)
+            .map(|i| {
+                let position =
+                    Vector2D::new(400.0 + (i % 5) as f32 * 10.0, 300.0 + (i / 5) as f32 * 10.0);
+                Boid::new(position, Vector2D::new(1.0, 0.5))
+            })
+            .collect();
+        flock.modifiers = ConfigModifiers {
+            max_speed: 2.5,
+            separation_weight: 3.0,
+            ..ConfigModifiers::IDENTITY
+        };
+
+        let effective = flock.effective_config();
+        assert_eq!(effective.max_speed, 5.0);
+        assert_eq!(effective.separation_weight, 4.5);
+        assert_eq!(effective.cohesion_weight, 1.0);
+
+        // Boids move at the modified speed while the base stays as set
+        for _ in 0..200 {
+            flock.update();
+        }
+        assert!(flock.boids.iter().any(|b| b.velocity.magnitude() > 2.0));
+        assert_eq!(flock.config.max_speed, 2.0);
+
+        flock.modifiers = ConfigModifiers::default();
+        assert!(flock.modifiers.is_identity());
+        assert_eq!(flock.effective_config().max_speed, 2.0);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn test_vector2d_magnitude() {
-        let v = Vector2D::new(3.0, 4.0);
-        assert_eq!(v.magnitude(), 5.0);
+    fn test_flock_std_json_roundtrip() {
+        let mut flock = FlockStd::new(800.0, 600.0, 25);
+        for _ in 0..10 {
+            flock.update();
+        }
+        flock.remove_boid(3);
+
+        let json = serde_json::to_string(&flock).unwrap();
+        let mut restored: FlockStd = serde_json::from_str(&json).unwrap();
+
+        for (a, b) in flock.boids.iter().zip(restored.boids.iter()) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.velocity, b.velocity);
+            assert_eq!(a.wander_angle, b.wander_angle);
+        }
+        assert_eq!(restored.config.max_speed, flock.config.max_speed);
+        let id = restored.add_boid(Boid::new(Vector2D::zero(), Vector2D::zero()));
+        assert_eq!(id, 25);
     }
 
     #[test]
-    fn test_vector2d_normalize() {
-        let v = Vector2D::new(3.0, 4.0);
-        let normalized = v.normalize();
-        assert!((normalized.magnitude() - 1.0).abs() < 0.0001);
+    fn test_groups_only_flock_with_their_own() {
+        let boid = Boid::new(Vector2D::new(100.0, 100.0), Vector2D::new(1.0, 0.0));
+        let other = Boid {
+            group: 1,
+            ..Boid::new(Vector2D::new(120.0, 100.0), Vector2D::new(0.0, 1.0))
+        };
+        let crowding = Boid {
+            group: 1,
+            ..Boid::new(Vector2D::new(105.0, 100.0), Vector2D::zero())
+        };
+        let config = BoidConfig::default();
+
+        let boids = [boid.clone(), other];
+        let ctx = FlockContext::new(&boids, &config, None, 800.0, 600.0);
+        assert_eq!(steering::Cohesion.steer(&boid, &ctx), Vector2D::zero());
+        assert_eq!(steering::Alignment.steer(&boid, &ctx), Vector2D::zero());
+
+        // Separation still keeps other groups at a distance
+        let boids = [boid.clone(), crowding];
+        let ctx = FlockContext::new(&boids, &config, None, 800.0, 600.0);
+        assert!(steering::Separation.steer(&boid, &ctx).x < 0.0);
     }
 
     #[test]
-    fn test_vector2d_operations() {
-        let v1 = Vector2D::new(1.0, 2.0);
-        let v2 = Vector2D::new(3.0, 4.0);
+    fn test_group_config_overrides_flock_config() {
+        let mut flock = FlockStd::new_with_seed(800.0, 600.0, 20, 7);
+        let ids: Vec<u32> = flock.boids.iter().map(|boid| boid.id).collect();
+        for &id in ids.iter().step_by(2) {
+            assert!(flock.set_group(id, 1));
+        }
+        assert!(!flock.set_group(1000, 1));
+        flock.set_group_config(
+            1,
+            BoidConfig {
+                max_speed: 0.5,
+                ..BoidConfig::default()
+            },
+        );
+        flock.modifiers = ConfigModifiers {
+            max_speed: 2.0,
+            ..ConfigModifiers::default()
+        };
+        assert_eq!(flock.config_for_group(1).max_speed, 1.0);
+        assert_eq!(flock.config_for_group(0).max_speed, 4.0);
 
-        let sum = v1 + v2;
-        assert_eq!(sum.x, 4.0);
-        assert_eq!(sum.y, 6.0);
+        for _ in 0..50 {
+            flock.update();
+        }
+        for boid in &flock.boids {
+            let limit = if boid.group == 1 { 1.0 } else { 4.0 };
+            assert!(boid.velocity.magnitude() <= limit + 1e-4);
+        }
+        assert!(flock
+            .boids
+            .iter()
+            .any(|boid| boid.group == 0 && boid.velocity.magnitude() > 1.0));
 
-        let diff = v2 - v1;
-        assert_eq!(diff.x, 2.0);
-        assert_eq!(diff.y, 2.0);
+        assert!(flock.clear_group_config(1).is_some());
+        assert_eq!(flock.config_for_group(1).max_speed, 4.0);
+    }
 
-        let scaled = v1 * 2.0;
-        assert_eq!(scaled.x, 2.0);
-        assert_eq!(scaled.y, 4.0);
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_groups_survive_json_roundtrip() {
+        let mut flock = FlockStd::new(800.0, 600.0, 4);
+        flock.set_group(2, 3);
+        flock.set_group_config(
+            3,
+            BoidConfig {
+                cohesion_weight: 2.5,
+                ..BoidConfig::default()
+            },
+        );
+
+        let json = serde_json::to_string(&flock).unwrap();
+        let restored: FlockStd = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.boids[2].group, 3);
+        assert_eq!(restored.config_for_group(3).cohesion_weight, 2.5);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn test_boid_creation() {
-        let pos = Vector2D::new(10.0, 20.0);
-        let vel = Vector2D::new(1.0, 1.0);
-        let boid = Boid::new(pos, vel);
+    fn test_embedded_snapshot_replays_in_flock_std() {
+        let mut flock = Flock::<8>::new(240.0, 240.0, BoidConfig::default());
+        for i in 0..5 {
+            let position = Vector2D::new(100.0 + i as f32 * 7.3, 120.0 - i as f32 * 3.1);
+            let velocity = Vector2D::new(0.5 * i as f32, -0.25);
+            flock.add_boid(Boid::new(position, velocity)).unwrap();
+        }
+        flock.update();
 
-        assert_eq!(boid.position.x, 10.0);
-        assert_eq!(boid.position.y, 20.0);
-        assert_eq!(boid.velocity.x, 1.0);
-        assert_eq!(boid.velocity.y, 1.0);
+        let bytes = serde_cbor::to_vec(&flock).unwrap();
+        let embedded: Flock<8> = serde_cbor::from_slice(&bytes).unwrap();
+        let replayed: FlockStd = serde_cbor::from_slice(&bytes).unwrap();
+
+        for ((a, b), c) in flock
+            .boids
+            .iter()
+            .zip(embedded.boids.iter())
+            .zip(replayed.boids.iter())
+        {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.velocity, c.velocity);
+            assert_eq!(a.position, c.position);
+        }
+
+        // Ids are made unique when loading an embedded snapshot
+        let ids: Vec<u32> = replayed.boids.iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+
+        // Too many boids for the capacity is an error, not a truncation
+        assert!(serde_cbor::from_slice::<Flock<4>>(&bytes).is_err());
     }
 
     #[test]
-    fn test_boid_update() {
-        let pos = Vector2D::new(0.0, 0.0);
-        let vel = Vector2D::new(1.0, 1.0);
-        let mut boid = Boid::new(pos, vel);
+    fn test_seeded_flock_is_reproducible() {
+        let config = BoidConfig {
+            wander_enabled: true,
+            ..BoidConfig::default()
+        };
+        let run = |seed| {
+            let mut flock = FlockStd::new_with_config_and_seed(800.0, 600.0, 30, config, seed);
+            for _ in 0..50 {
+                flock.update();
+            }
+            flock.boids
+        };
 
-        boid.update(10.0, 1.0);
+        let a = run(42);
+        let b = run(42);
+        let c = run(43);
+        assert!(a
+            .iter()
+            .zip(b.iter())
+            .all(|(x, y)| x.position == y.position && x.velocity == y.velocity));
+        assert!(a
+            .iter()
+            .zip(c.iter())
+            .any(|(x, y)| x.position != y.position));
+    }
 
-        assert_eq!(boid.position.x, 1.0);
-        assert_eq!(boid.position.y, 1.0);
+    #[test]
+    fn test_seeded_flock_known_start() {
+        // Pinned values guard against the RNG or the order of draws changing
+        // silently, which would break recorded demos
+        let flock = FlockStd::new_with_seed(800.0, 600.0, 2, 7);
+        assert_eq!(flock.boids[0].position, Vector2D::new(112.002655, 94.67761));
+        assert_eq!(flock.boids[0].velocity, Vector2D::new(-1.27178, -1.328043));
+        assert_ne!(flock.boids[0].position, flock.boids[1].position);
+    }
+
+    /// Mean and variance of how far each boid's wander angle moved
+    fn wander_drift(before: &[Boid], after: &[Boid]) -> (f32, f32) {
+        let drift: Vec<f32> = before
+            .iter()
+            .zip(after)
+            .map(|(a, b)| b.wander_angle - a.wander_angle)
+            .collect();
+        let n = drift.len() as f32;
+        let mean = drift.iter().sum::<f32>() / n;
+        let variance = drift.iter().map(|d| (d - mean) * (d - mean)).sum::<f32>() / n;
+        (mean, variance)
     }
 
     #[test]
-    fn test_boid_wrap_edges() {
-        let pos = Vector2D::new(-1.0, -1.0);
-        let vel = Vector2D::new(0.0, 0.0);
-        let mut boid = Boid::new(pos, vel);
+    fn test_wander_spreads_alike_at_any_frame_rate() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let start = vec![Boid::new(Vector2D::zero(), Vector2D::zero()); 4000];
+        for fps in [20, 60, 240] {
+            let mut boids = start.clone();
+            // Two seconds
+            for _ in 0..2 * fps {
+                for boid in boids.iter_mut() {
+                    behavior::evolve_wander(boid, 1.0 / fps as f32, &mut rng);
+                }
+            }
+            let (mean, variance) = wander_drift(&start, &boids);
+            let expected = WANDER_JITTER * WANDER_JITTER * 2.0;
+            assert!(mean.abs() < 0.03, "{} fps drifts by {}", fps, mean);
+            assert!(
+                (variance / expected - 1.0).abs() < 0.1,
+                "{} fps spreads by {} instead of {}",
+                fps,
+                variance,
+                expected
+            );
+        }
+        let mut boid = start[0].clone();
+        behavior::evolve_wander(&mut boid, 0.0, &mut rng);
+        behavior::evolve_wander(&mut boid, f32::NAN, &mut rng);
+        assert_eq!(boid.wander_angle, 0.0);
+    }
 
-        boid.wrap_edges(100.0, 100.0);
+    #[test]
+    fn test_wander_follows_wander_enabled_not_the_target() {
+        let run = |wander_enabled, target: Option<Vector2D>, fps: u32| {
+            let config = BoidConfig {
+                wander_enabled,
+                boundary: Some(BoundaryBehavior::Unbounded),
+                ..BoidConfig::default()
+            };
+            let mut flock = FlockStd::new_with_config_and_seed(800.0, 600.0, 200, config, 11);
+            let before = flock.boids.clone();
+            for _ in 0..2 * fps {
+                flock.step(
+                    StepInput::new()
+                        .with_dt(1.0 / fps as f32)
+                        .with_targets(target.as_slice()),
+                );
+            }
+            wander_drift(&before, &flock.boids)
+        };
+
+        // Seeking no longer stirs the angles, and wander needs no target
+        let target = Some(Vector2D::new(400.0, 300.0));
+        assert_eq!(run(false, target, 60), (0.0, 0.0));
+        let expected = WANDER_JITTER * WANDER_JITTER * 2.0;
+        for fps in [30, 120] {
+            let (_, variance) = run(true, None, fps);
+            assert!(
+                (variance / expected - 1.0).abs() < 0.35,
+                "{} fps spreads by {} instead of {}",
+                fps,
+                variance,
+                expected
+            );
+        }
 
-        assert_eq!(boid.position.x, 100.0);
-        assert_eq!(boid.position.y, 100.0);
+        // Only boids whose group wanders drift
+        let mut flock = FlockStd::new_with_seed(800.0, 600.0, 2, 3);
+        flock.boids[1].group = 1;
+        flock.set_group_config(
+            1,
+            BoidConfig {
+                wander_enabled: true,
+                ..BoidConfig::default()
+            },
+        );
+        let before = flock.boids.clone();
+        flock.update();
+        assert_eq!(flock.boids[0].wander_angle, before[0].wander_angle);
+        assert_ne!(flock.boids[1].wander_angle, before[1].wander_angle);
     }
 
     #[test]
-    fn test_flock_creation() {
-        let flock = FlockStd::new(800.0, 600.0, 50);
-        assert_eq!(flock.boids.len(), 50);
-        assert_eq!(flock.width, 800.0);
-        assert_eq!(flock.height, 600.0);
+    fn test_random_boids_wander_their_own_way() {
+        let flock = FlockStd::new_with_seed(800.0, 600.0, 50, 8);
+        let first = flock.boids[0].wander_angle;
+        assert!((0.0..core::f32::consts::TAU).contains(&first));
+        assert!(flock.boids.iter().any(|boid| boid.wander_angle != first));
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn test_flock_update() {
-        let mut flock = FlockStd::new(800.0, 600.0, 10);
-        let initial_positions: Vec<_> = flock.boids.iter().map(|b| b.position).collect();
+    fn test_restored_snapshot_continues_identically() {
+        let mut flock = FlockStd::new_with_seed(800.0, 600.0, 20, 99);
+        let json = serde_json::to_string(&flock).unwrap();
+        let mut restored: FlockStd = serde_json::from_str(&json).unwrap();
+
+        // Wander draws from the saved RNG state, so both runs stay in lockstep
+        let target = Some(Vector2D::new(400.0, 300.0));
+        for _ in 0..20 {
+            flock.update_with_target(target);
+            restored.update_with_target(target);
+        }
+        for (a, b) in flock.boids.iter().zip(restored.boids.iter()) {
+            assert_eq!(a.position, b.position);
+        }
+    }
+
+    #[test]
+    fn test_seek_falloff() {
+        let mut config = BoidConfig::default();
+        assert_eq!(behavior::seek_falloff(1000.0, &config), 1.0);
+
+        config.seek_radius = 100.0;
+        assert_eq!(behavior::seek_falloff(0.0, &config), 1.0);
+        assert_eq!(behavior::seek_falloff(50.0, &config), 0.5);
+        assert_eq!(behavior::seek_falloff(100.0, &config), 0.0);
+        assert_eq!(behavior::seek_falloff(250.0, &config), 0.0);
+        assert!(behavior::seek_falloff(20.0, &config) > behavior::seek_falloff(40.0, &config));
+    }
+
+    #[test]
+    fn test_seek_radius_limits_which_boids_respond() {
+        let config = BoidConfig {
+            seek_radius: 50.0,
+            ..BoidConfig::default()
+        };
+        let mut flock = Flock::<2>::new(400.0, 400.0, config);
+        flock
+            .add_boid(Boid::new(Vector2D::new(120.0, 100.0), Vector2D::zero()))
+            .unwrap();
+        flock
+            .add_boid(Boid::new(Vector2D::new(300.0, 300.0), Vector2D::zero()))
+            .unwrap();
+
+        flock.update_with_target(Some(Vector2D::new(100.0, 100.0)));
+
+        // The near boid moves toward the target, the far one is left alone
+        assert!(flock.boids[0].velocity.x < 0.0);
+        assert_eq!(flock.boids[1].velocity, Vector2D::zero());
+    }
+
+    #[test]
+    fn test_profile_compiles_out_behaviors() {
+        fn pair<P: FlockProfile>() -> Flock<2, P> {
+            let mut flock = Flock::<2, P>::new(400.0, 400.0, BoidConfig::default());
+            for x in [100.0, 120.0] {
+                flock
+                    .add_boid(Boid::new(Vector2D::new(x, 100.0), Vector2D::zero()))
+                    .unwrap();
+            }
+            flock
+        }
+
+        // 20 apart, the boids only feel each other's cohesion
+        let mut standard = pair::<profile::Standard>();
+        standard.update();
+        assert!(standard.boids[0].velocity.x > 0.0);
+
+        let mut low_cpu = pair::<profile::LowCpu>();
+        low_cpu.update();
+        assert_eq!(low_cpu.boids[0].velocity, Vector2D::zero());
+        assert!(profile::LowCpu::ignores("cohesion_weight"));
+        assert!(!profile::LowCpu::ignores("max_speed"));
+        assert!(!profile::Standard::ignores("cohesion_weight"));
+
+        let pretty = Flock::<2, profile::Pretty>::with_profile(400.0, 400.0);
+        assert_eq!(pretty.config.cohesion_distance, 40.0);
+    }
 
+    #[test]
+    fn test_config_transition_eases_to_target() {
+        let from = BoidConfig::default();
+        let to = BoidConfig {
+            separation_weight: 3.5,
+            max_speed: 6.0,
+            wander_enabled: true,
+            ..BoidConfig::default()
+        };
+        let mut transition = ConfigTransition::new(from, to, 4);
+
+        let first = transition.step();
+        assert!(first.wander_enabled);
+        assert!(first.separation_weight > from.separation_weight);
+        let second = transition.step();
+        assert_eq!(second.separation_weight, 2.5);
+        let third = transition.step();
+        // Smoothstep: the middle step moves farther than the first
+        assert!(third.max_speed - second.max_speed > first.max_speed - from.max_speed);
+
+        assert!(!transition.is_finished());
+        assert_eq!(transition.step().max_speed, 6.0);
+        assert!(transition.is_finished());
+        assert_eq!(transition.step().max_speed, 6.0);
+    }
+
+    #[test]
+    fn test_flock_apply_preset_over() {
+        let mut flock = FlockStd::new_with_seed(200.0, 200.0, 5, 1);
+        let preset = BoidConfig {
+            cohesion_weight: 3.0,
+            ..BoidConfig::default()
+        };
+
+        flock.apply_preset_over(preset, 10);
         flock.update();
+        assert!(flock.config.cohesion_weight > 1.0 && flock.config.cohesion_weight < 3.0);
+        for _ in 0..9 {
+            flock.update();
+        }
+        assert_eq!(flock.config.cohesion_weight, 3.0);
+        assert!(flock.transition.is_none());
 
-        // Positions should change after update
-        let changed = flock
-            .boids
+        flock.apply_preset_over(BoidConfig::default(), 0);
+        assert_eq!(flock.config.cohesion_weight, 1.0);
+    }
+
+    #[test]
+    fn test_scratch_arena_reused_across_ticks() {
+        // Stationary boids too far apart to interact keep the tick identical
+        let mut flock = FlockStd::new_with_seed(1000.0, 1000.0, 0, 1);
+        for i in 0..100 {
+            let position = Vector2D::new(
+                50.0 + 100.0 * (i % 10) as f32,
+                50.0 + 100.0 * (i / 10) as f32,
+            );
+            flock.add_boid(Boid::new(position, Vector2D::zero()));
+        }
+
+        flock.update();
+        let growths = flock.scratch().growths();
+        let bytes = flock.scratch().capacity_bytes();
+        assert!(growths > 0);
+        assert!(bytes >= 128 * core::mem::size_of::<Vector2D>());
+
+        for _ in 0..50 {
+            flock.update();
+        }
+        flock.boids.truncate(40);
+        flock.update();
+        assert_eq!(flock.scratch().growths(), growths);
+        assert_eq!(flock.scratch().capacity_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_ecology_renews_the_population() {
+        let mut flock = FlockStd::new_with_seed(400.0, 300.0, 30, 5);
+        assert_eq!(flock.population_stats(), None);
+        flock.set_ecology(Some(EcologyConfig {
+            lifespan: 5.0,
+            birth_rate: 1.0,
+            carrying_capacity: 60,
+            energy_gain: 0.5,
+            ..EcologyConfig::default()
+        }));
+        flock.watch_events();
+
+        let (mut born, mut died) = (0, 0);
+        for _ in 0..1200 {
+            for event in flock.step(StepInput::new()).events {
+                match event {
+                    StepEvent::Born(count) => born += count,
+                    StepEvent::Died(count) => died += count,
+                    _ => {}
+                }
+            }
+        }
+        let spawned = flock
+            .drain_events()
+            .filter(|event| matches!(event, FlockEvent::Spawned { .. }))
+            .count();
+        let stats = flock.population_stats().unwrap();
+        assert_eq!(
+            (stats.births, stats.deaths_of_age),
+            (born as u64, died as u64)
+        );
+        assert_eq!(spawned, born);
+        assert_eq!(flock.boids.len(), 30 + born - died);
+        // Twenty seconds outlive every founding boid
+        assert!(flock.boids.iter().all(|boid| boid.id >= 30));
+        assert!(flock.boids.len() < 60);
+        let id = flock.boids[0].id;
+        assert!(flock.life(id).unwrap().age < 6.25);
+
+        flock.set_ecology(None);
+        assert_eq!(flock.life(id), None);
+    }
+
+    #[test]
+    fn test_timeline_marks_targets_presets_and_spawns() {
+        let mut flock = FlockStd::new_with_seed(400.0, 300.0, 10, 2);
+        assert!(flock.timeline().is_none());
+        flock.set_timeline(Some(TimelineConfig::default()));
+
+        let target = Vector2D::new(200.0, 150.0);
+        flock.update();
+        flock.update_with_target(Some(target));
+        flock.update();
+        flock.apply_preset_over(BoidConfig::default(), 20);
+        flock.update();
+        for _ in 0..8 {
+            flock.spawn(target, Vector2D::zero());
+        }
+        for _ in 0..40 {
+            flock.update();
+        }
+
+        let timeline = flock.timeline().unwrap();
+        let markers: Vec<_> = timeline
+            .entries()
+            .filter(|entry| {
+                !matches!(
+                    entry.marker,
+                    Marker::ClusterSplit { .. } | Marker::ClusterMerge { .. }
+                )
+            })
+            .map(|entry| (entry.tick, entry.marker))
+            .collect();
+        assert_eq!(
+            markers,
+            [
+                (1, Marker::TargetAcquired { position: target }),
+                (2, Marker::TargetLost),
+                (3, Marker::PresetApplied { crossfade: true }),
+                (4, Marker::SpawnBurst { count: 8 }),
+            ]
+        );
+        assert_eq!(timeline.ticks(), 44);
+
+        flock.set_timeline(None);
+        assert!(flock.timeline().is_none());
+    }
+
+    #[test]
+    fn test_flock_events_reach_listeners_and_queue() {
+        use std::sync::{Arc, Mutex};
+
+        let mut flock = FlockStd::new_with_seed(1000.0, 1000.0, 0, 1);
+        let first = flock.add_boid(Boid::new(Vector2D::new(500.0, 500.0), Vector2D::zero()));
+        let heard = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&heard);
+        flock.on_event(move |event| sink.lock().unwrap().push(*event));
+        flock.watch_events();
+        let region = flock.add_event_region(Region::new(
+            Vector2D::new(900.0, 0.0),
+            Vector2D::new(1000.0, 1000.0),
+        ));
+
+        // Unchanged flocks are quiet
+        flock.update();
+        assert_eq!(flock.drain_events().count(), 0);
+
+        let second = flock.add_boid(Boid::new(
+            Vector2D::new(998.0, 500.0),
+            Vector2D::new(3.0, 0.0),
+        ));
+        flock.remove_boid(first);
+        let target = Vector2D::new(10.0, 10.0);
+        flock.update_with_target(Some(target));
+        let events: Vec<FlockEvent> = flock.drain_events().collect();
+        assert_eq!(events[0], FlockEvent::Removed { id: first });
+        assert!(matches!(events[1], FlockEvent::Spawned { id, .. } if id == second));
+        assert!(matches!(events[2], FlockEvent::HitBoundary { id, position }
+            if id == second && position.x < 1000.0));
+        assert!(matches!(events[3], FlockEvent::RegionEntered { region: r, .. } if r == region));
+        assert_eq!(events[4], FlockEvent::TargetAcquired { position: target });
+        assert_eq!(events.len(), 5);
+        assert_eq!(*heard.lock().unwrap(), events);
+
+        flock.remove_boid(second);
+        flock.add_boid(Boid::new(Vector2D::new(100.0, 100.0), Vector2D::zero()));
+        flock.update();
+        let events: Vec<FlockEvent> = flock.drain_events().collect();
+        assert!(events.contains(&FlockEvent::TargetLost));
+        assert!(events
             .iter()
-            .zip(initial_positions.iter())
-            .any(|(b, &initial)| b.position.x != initial.x || b.position.y != initial.y);
+            .any(|event| matches!(event, FlockEvent::RegionLeft { .. })));
+        assert_eq!(heard.lock().unwrap().len(), 5 + events.len());
+        assert_eq!(flock.dropped_events(), 0);
+    }
 
-        assert!(changed);
+    #[test]
+    fn test_spawn_kill_and_population_cap() {
+        let mut flock = FlockStd::new_with_seed(200.0, 200.0, 0, 3);
+        let first = flock
+            .spawn(Vector2D::new(10.0, 10.0), Vector2D::new(1.0, 0.0))
+            .unwrap();
+        flock.spawn(Vector2D::new(100.0, 100.0), Vector2D::zero());
+        flock.spawn(Vector2D::new(190.0, 190.0), Vector2D::zero());
+
+        flock.population = Some(PopulationLimit::new(3));
+        assert_eq!(
+            flock.spawn(Vector2D::new(50.0, 50.0), Vector2D::zero()),
+            None
+        );
+        flock.population = Some(PopulationLimit::recycling(3));
+        let newest = flock
+            .spawn(Vector2D::new(50.0, 50.0), Vector2D::zero())
+            .unwrap();
+        assert_eq!(flock.boids.len(), 3);
+        assert_eq!(flock.index_of(first), None);
+        assert_eq!(flock.boids[2].id, newest);
+
+        let removed = flock
+            .remove_nearest(Vector2D::new(55.0, 55.0), 20.0)
+            .unwrap();
+        assert_eq!(removed.id, newest);
+        assert!(flock
+            .remove_nearest(Vector2D::new(55.0, 55.0), 20.0)
+            .is_none());
+        assert_eq!(flock.remove_where(|boid| boid.position.x > 150.0), 1);
+        assert_eq!(flock.boids.len(), 1);
+
+        // Killed boids come back up to the floor, never past the cap
+        flock.min_population = 5;
+        flock.update();
+        assert_eq!(flock.boids.len(), 3);
+        flock.population = Some(PopulationLimit::new(2));
+        flock.update();
+        assert_eq!(flock.boids.len(), 2);
+
+        let mut fixed = Flock::<4>::new(200.0, 200.0, BoidConfig::default());
+        for i in 0..4 {
+            assert_eq!(
+                fixed.spawn(Vector2D::new(40.0 * i as f32, 0.0), Vector2D::zero()),
+                Some(i)
+            );
+        }
+        assert_eq!(fixed.spawn(Vector2D::zero(), Vector2D::zero()), None);
+        fixed.population = Some(PopulationLimit::recycling(3));
+        assert_eq!(
+            fixed.spawn(Vector2D::new(0.0, 100.0), Vector2D::zero()),
+            Some(2)
+        );
+        assert_eq!(fixed.boids[0].position.x, 80.0);
+        assert_eq!(fixed.nearest(Vector2D::new(0.0, 90.0)), Some(2));
+        assert!(fixed.remove_at(2).is_some());
+        assert!(fixed.remove_at(2).is_none());
     }
 
     #[test]
-    fn test_flock_add_boid() {
-        let mut flock = FlockStd::new(800.0, 600.0, 10);
-        let initial_count = flock.boids.len();
+    fn test_set_boid_count() {
+        let mut flock = FlockStd::new_with_seed(200.0, 200.0, 5, 3);
+        let newest = flock.boids[4].id;
+        let removed = flock.set_boid_count(3);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].id, newest);
+        assert!(flock.set_boid_count(8).is_empty());
+        assert_eq!(flock.boids.len(), 8);
+        flock.population = Some(PopulationLimit::new(6));
+        assert_eq!(flock.set_boid_count(10).len(), 2);
+        assert_eq!(flock.boids.len(), 6);
+
+        let mut fixed = Flock::<4>::new(200.0, 200.0, BoidConfig::default());
+        assert_eq!(fixed.capacity(), 4);
+        let make = || Boid::new(Vector2D::new(10.0, 10.0), Vector2D::zero());
+        assert_eq!(fixed.set_boid_count(3, make), 3);
+        assert_eq!(fixed.set_boid_count(9, make), 4);
+        assert_eq!(fixed.set_boid_count(1, make), 1);
+    }
 
-        let boid = Boid::random(800.0, 600.0);
-        flock.add_boid(boid);
+    #[test]
+    fn test_fixed_flock_reports_capacity_events() {
+        let mut flock = Flock::<3>::new(100.0, 100.0, BoidConfig::default());
+        assert_eq!(flock.capacity(), 3);
+        while !flock.is_full() {
+            flock
+                .add_boid(Boid::new(Vector2D::new(50.0, 50.0), Vector2D::zero()))
+                .unwrap();
+        }
+        let error = flock
+            .add_boid(Boid::new(Vector2D::new(1.0, 2.0), Vector2D::zero()))
+            .unwrap_err();
+        assert_eq!(error.boid.position, Vector2D::new(1.0, 2.0));
+        assert_eq!(error.capacity, 3);
+        assert_eq!(error.to_string(), "flock is full (3 boids)");
+
+        let diagnostics = flock.try_update(None);
+        assert_eq!(diagnostics.boids, 3);
+        assert!(diagnostics.is_full());
+        assert_eq!(diagnostics.rejected, 1);
+        assert!(!flock.try_update(None).has_events());
+
+        flock.population = Some(PopulationLimit::recycling(2));
+        flock.spawn(Vector2D::zero(), Vector2D::zero()).unwrap();
+        let diagnostics = flock.try_update(None);
+        assert_eq!(
+            (
+                diagnostics.trimmed,
+                diagnostics.recycled,
+                diagnostics.capacity
+            ),
+            (1, 1, 2)
+        );
+    }
 
-        assert_eq!(flock.boids.len(), initial_count + 1);
+    #[test]
+    fn test_flee_scatters_from_target() {
+        let threat = Vector2D::new(100.0, 100.0);
+        let config = BoidConfig {
+            target_mode: TargetMode::Flee,
+            ..BoidConfig::default()
+        };
+        let near = Boid::new(Vector2D::new(110.0, 100.0), Vector2D::zero());
+        let far = Boid::new(Vector2D::new(300.0, 100.0), Vector2D::zero());
+        assert!(behavior::flee(&near, threat, &config).x > 0.0);
+        assert_eq!(behavior::flee(&far, threat, &config), Vector2D::zero());
+
+        let mut fixed = Flock::<1>::new(400.0, 400.0, config);
+        fixed.add_boid(near.clone()).unwrap();
+        fixed.update_with_target(Some(threat));
+        assert!(fixed.boids[0].position.x > 110.0);
+
+        // A modifier turns the base seek into flee without touching the config
+        let mut flock = FlockStd::new_with_seed(400.0, 400.0, 0, 5);
+        flock.add_boid(near);
+        flock.modifiers.target_mode = Some(TargetMode::Flee);
+        flock.update_with_target(Some(threat));
+        assert!(flock.boids[0].position.x > 110.0);
+        assert_eq!(flock.config.target_mode, TargetMode::Seek);
+    }
+
+    #[test]
+    fn test_arrive_settles_where_seek_overshoots() {
+        let target = Vector2D::new(300.0, 200.0);
+        // Furthest past the target a lone boid gets, and its final speed
+        let run = |target_mode| {
+            let config = BoidConfig {
+                target_mode,
+                arrive_radius: 60.0,
+                wander_radius: 0.0,
+                ..BoidConfig::default()
+            };
+            let mut flock = FlockStd::new_with_config_and_seed(400.0, 400.0, 0, config, 1);
+            flock.add_boid(Boid::new(Vector2D::new(100.0, 200.0), Vector2D::zero()));
+            let mut overshoot: f32 = 0.0;
+            for _ in 0..600 {
+                flock.update_with_target(Some(target));
+                overshoot = overshoot.max(flock.boids[0].position.x - target.x);
+            }
+            (overshoot, flock.boids[0].velocity.magnitude())
+        };
+
+        let (overshoot, speed) = run(TargetMode::Arrive);
+        assert!(overshoot < 1.0 && speed < 0.05, "{} {}", overshoot, speed);
+        let (overshoot, _) = run(TargetMode::Seek);
+        assert!(overshoot > 2.0, "{}", overshoot);
+    }
+
+    #[test]
+    fn test_follow_path_keeps_boids_on_a_loop() {
+        let corners = [
+            Vector2D::new(100.0, 100.0),
+            Vector2D::new(300.0, 100.0),
+            Vector2D::new(300.0, 300.0),
+            Vector2D::new(100.0, 300.0),
+        ];
+        let config = BoidConfig {
+            path_weight: 4.0,
+            ..BoidConfig::default()
+        };
+        let mut flock = FlockStd::new_with_config_and_seed(400.0, 400.0, 0, config, 1);
+        flock.add_boid(Boid::new(
+            Vector2D::new(200.0, 200.0),
+            Vector2D::new(1.0, 0.0),
+        ));
+        let path = Path::new(&corners, true);
+        flock.follow_path(path.clone());
+
+        // Every corner is rounded, and the boid stays near the line
+        let mut visited = [false; 4];
+        for tick in 0..3000 {
+            flock.update();
+            let position = flock.boids[0].position;
+            for (seen, corner) in visited.iter_mut().zip(corners) {
+                *seen |= position.distance(&corner) < 40.0;
+            }
+            if tick > 300 {
+                let (nearest, _) = path.closest(position).unwrap();
+                assert!(nearest.distance(&position) < 2.0 * config.path_radius);
+            }
+        }
+        assert_eq!(visited, [true; 4]);
+
+        assert!(flock.stop_following_path());
+        assert!(!flock.stop_following_path());
+    }
+
+    #[test]
+    fn test_boids_perch_rest_and_take_off() {
+        let mut flock = FlockStd::new_with_seed(400.0, 300.0, 20, 8);
+        flock.set_perching(Some(PerchConfig {
+            perch_probability: 0.5,
+            rest_duration: 1.0,
+            rest_spread: 0.0,
+            ..PerchConfig::default()
+        }));
+        let perched = |flock: &FlockStd| {
+            flock
+                .boids
+                .iter()
+                .find(|boid| flock.perch_state(boid.id).is_perched())
+                .cloned()
+        };
+        let mut sitter = None;
+        for _ in 0..1200 {
+            flock.step(StepInput::new());
+            sitter = perched(&flock);
+            if sitter.is_some() {
+                break;
+            }
+        }
+        let sitter = sitter.expect("no boid perched");
+        assert_eq!(sitter.position.y, 300.0 - perching::GROUND_CLEARANCE);
+        assert_eq!(sitter.velocity, Vector2D::zero());
+
+        // It rests for a second, then takes off upward
+        let mut rested = 0;
+        loop {
+            flock.step(StepInput::new());
+            let boid = flock
+                .boids
+                .iter()
+                .find(|boid| boid.id == sitter.id)
+                .unwrap();
+            if !flock.perch_state(boid.id).is_perched() {
+                assert!(boid.velocity.y < 0.0);
+                break;
+            }
+            assert_eq!(boid.position, sitter.position);
+            rested += 1;
+        }
+        assert!((55..=60).contains(&rested), "rested {rested} steps");
+
+        flock.set_perching(None);
+        assert!(flock
+            .boids
+            .iter()
+            .all(|boid| flock.perch_state(boid.id) == PerchState::Flying));
     }
 }
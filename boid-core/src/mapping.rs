@@ -0,0 +1,396 @@
+//! Interactive input mapped onto config parameters
+//!
+//! A [`MappingTable`] links measured input, such as the distance between
+//! two fingers, to any numeric [`ConfigParameter`]. Each [`InputMapping`]
+//! reads one [`InputSignal`], places it within an input range, shapes it
+//! with an exponent, turns it into a multiplier between `low` and `high`
+//! and smooths that over ticks. The table is plain data, so frontends can
+//! load and edit it instead of hard-coding each interaction.
+//!
+//! Multipliers go into [`ParameterScales`] on the flock's modifiers, so the
+//! user-set config is never written and a lost input returns to it exactly.
+//! Two mappings onto one parameter multiply.
+
+use crate::{ConfigParameter, ParameterScales, Vector2D};
+
+/// Finger distance, in pixels, that the built-in mappings read as fully open
+pub const MAX_FINGER_DISTANCE: f32 = 300.0;
+
+/// Something measured every tick that a mapping can read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum InputSignal {
+    /// Pixels between thumb and index tip of an open hand
+    FingerDistance,
+    /// Height of the hand, 0 at the bottom of the view to 1 at the top
+    HandHeight,
+    /// Pixels per second the hand moves
+    HandSpeed,
+    /// How closely the fingers pinch, 0 fully open to 1 touching
+    PinchStrength,
+    /// Loudness of the music, 0 silent to 1 loud
+    AudioLevel,
+}
+
+impl InputSignal {
+    pub const ALL: [InputSignal; 5] = [
+        InputSignal::FingerDistance,
+        InputSignal::HandHeight,
+        InputSignal::HandSpeed,
+        InputSignal::PinchStrength,
+        InputSignal::AudioLevel,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FingerDistance => "finger_distance",
+            Self::HandHeight => "hand_height",
+            Self::HandSpeed => "hand_speed",
+            Self::PinchStrength => "pinch_strength",
+            Self::AudioLevel => "audio_level",
+        }
+    }
+}
+
+/// The signals of one tick; `None` for what is not measured right now
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct InputSignals {
+    pub finger_distance: Option<f32>,
+    pub hand_height: Option<f32>,
+    pub hand_speed: Option<f32>,
+    pub pinch_strength: Option<f32>,
+    pub audio_level: Option<f32>,
+}
+
+impl InputSignals {
+    /// Signals of a hand whose thumb and index tip are `distance` pixels
+    /// apart around `midpoint`, in a view `view_height` pixels tall; `speed`
+    /// is how fast the midpoint moves, when known
+    pub fn of_hand(
+        midpoint: Vector2D,
+        distance: f32,
+        view_height: f32,
+        speed: Option<f32>,
+    ) -> Self {
+        Self {
+            finger_distance: Some(distance),
+            hand_height: Some((1.0 - midpoint.y / view_height).clamp(0.0, 1.0)),
+            hand_speed: speed,
+            pinch_strength: Some((1.0 - distance / MAX_FINGER_DISTANCE).clamp(0.0, 1.0)),
+            audio_level: None,
+        }
+    }
+
+    pub fn get(&self, signal: InputSignal) -> Option<f32> {
+        match signal {
+            InputSignal::FingerDistance => self.finger_distance,
+            InputSignal::HandHeight => self.hand_height,
+            InputSignal::HandSpeed => self.hand_speed,
+            InputSignal::PinchStrength => self.pinch_strength,
+            InputSignal::AudioLevel => self.audio_level,
+        }
+    }
+}
+
+/// One row of a [`MappingTable`], e.g. in JSON:
+///
+/// ```json
+/// {"input": "hand_height", "parameter": "cohesion_weight",
+///  "input_min": 0.2, "input_max": 0.8, "low": 0.5, "high": 2.0,
+///  "exponent": 2.0, "smoothing": 0.8}
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct InputMapping {
+    pub input: InputSignal,
+    pub parameter: ConfigParameter,
+    /// Input read as the low end; values outside the range are clamped,
+    /// and `input_min` above `input_max` inverts the mapping
+    pub input_min: f32,
+    /// Input read as the high end
+    pub input_max: f32,
+    /// Multiplier at the low end of the input
+    pub low: f32,
+    /// Multiplier at the high end of the input
+    pub high: f32,
+    /// Shape of the way from `low` to `high`, as `t^exponent`: 1 is
+    /// linear, above 1 reacts mostly near the high end
+    #[cfg_attr(feature = "serde", serde(default = "default_exponent"))]
+    pub exponent: f32,
+    /// Share of the last multiplier kept each tick, from 0 (follow the
+    /// input at once) to below 1
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub smoothing: f32,
+}
+
+#[cfg(feature = "serde")]
+fn default_exponent() -> f32 {
+    1.0
+}
+
+impl InputMapping {
+    /// Scale `parameter` linearly from `low` at `input_min` to `high` at
+    /// `input_max`, without smoothing
+    pub fn new(
+        input: InputSignal,
+        parameter: ConfigParameter,
+        (input_min, input_max): (f32, f32),
+        (low, high): (f32, f32),
+    ) -> Self {
+        Self {
+            input,
+            parameter,
+            input_min,
+            input_max,
+            low,
+            high,
+            exponent: 1.0,
+            smoothing: 0.0,
+        }
+    }
+
+    pub fn with_exponent(mut self, exponent: f32) -> Self {
+        self.exponent = exponent;
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Finite numbers, a range that is not empty, a positive exponent and
+    /// smoothing in `[0, 1)`
+    pub fn is_valid(&self) -> bool {
+        let values = [
+            self.input_min,
+            self.input_max,
+            self.low,
+            self.high,
+            self.exponent,
+        ];
+        values.iter().all(|value| value.is_finite())
+            && self.input_min != self.input_max
+            && self.exponent > 0.0
+            && (0.0..1.0).contains(&self.smoothing)
+    }
+
+    /// The multiplier for an input of `value`, before smoothing
+    pub fn scale_at(&self, value: f32) -> f32 {
+        let t = ((value - self.input_min) / (self.input_max - self.input_min)).clamp(0.0, 1.0);
+        self.low + (self.high - self.low) * libm::powf(t, self.exponent)
+    }
+}
+
+/// A mapping of a table that failed [`InputMapping::is_valid`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidMapping {
+    /// Position of the mapping in the table
+    pub index: usize,
+}
+
+impl core::fmt::Display for InvalidMapping {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "mapping {} needs finite numbers, input_min != input_max, exponent > 0 and smoothing in [0, 1)",
+            self.index
+        )
+    }
+}
+
+impl std::error::Error for InvalidMapping {}
+
+/// Mappings and the smoothed multiplier of each
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MappingTable {
+    mappings: Vec<InputMapping>,
+    /// Last multiplier per mapping; `None` while its input is missing
+    levels: Vec<Option<f32>>,
+}
+
+impl MappingTable {
+    pub fn new(mappings: Vec<InputMapping>) -> Result<Self, InvalidMapping> {
+        if let Some(index) = mappings.iter().position(|mapping| !mapping.is_valid()) {
+            return Err(InvalidMapping { index });
+        }
+        Ok(Self {
+            levels: vec![None; mappings.len()],
+            mappings,
+        })
+    }
+
+    /// Open fingers spread the flock out and speed it up: finger distance
+    /// scales separation by 1 to 3 and max speed by 1 to 2.5
+    pub fn hands() -> Self {
+        let open = (0.0, MAX_FINGER_DISTANCE);
+        Self::new(vec![
+            InputMapping::new(
+                InputSignal::FingerDistance,
+                ConfigParameter::SeparationWeight,
+                open,
+                (1.0, 3.0),
+            ),
+            InputMapping::new(
+                InputSignal::FingerDistance,
+                ConfigParameter::MaxSpeed,
+                open,
+                (1.0, 2.5),
+            ),
+        ])
+        .expect("built-in mappings are valid")
+    }
+
+    pub fn mappings(&self) -> &[InputMapping] {
+        &self.mappings
+    }
+
+    /// Advance the smoothing by one tick of `signals` and multiply `scales`
+    /// by the result. A mapping whose input is missing scales nothing and
+    /// starts afresh when the input returns.
+    pub fn update(&mut self, signals: &InputSignals, scales: &mut ParameterScales) {
+        for (mapping, level) in self.mappings.iter().zip(self.levels.iter_mut()) {
+            let Some(value) = signals.get(mapping.input) else {
+                *level = None;
+                continue;
+            };
+            let target = mapping.scale_at(value);
+            let next = match *level {
+                Some(last) => last + (target - last) * (1.0 - mapping.smoothing),
+                None => target,
+            };
+            *level = Some(next);
+            scales.scale(mapping.parameter, next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoidConfig, ConfigModifiers};
+
+    fn scaled(table: &mut MappingTable, signals: &InputSignals) -> ParameterScales {
+        let mut scales = ParameterScales::IDENTITY;
+        table.update(signals, &mut scales);
+        scales
+    }
+
+    #[test]
+    fn test_hands_spread_the_flock_with_open_fingers() {
+        let mut table = MappingTable::hands();
+        let half_open = InputSignals {
+            finger_distance: Some(150.0),
+            ..InputSignals::default()
+        };
+        let scales = scaled(&mut table, &half_open);
+        assert_eq!(scales.get(ConfigParameter::SeparationWeight), 2.0);
+        assert_eq!(scales.get(ConfigParameter::MaxSpeed), 1.75);
+        assert_eq!(scales.get(ConfigParameter::CohesionWeight), 1.0);
+
+        let modifiers = ConfigModifiers {
+            scales,
+            ..ConfigModifiers::IDENTITY
+        };
+        let base = BoidConfig::default();
+        let config = modifiers.apply(&base);
+        assert_eq!(config.separation_weight, base.separation_weight * 2.0);
+        assert_eq!(config.max_speed, base.max_speed * 1.75);
+
+        // Wider than the range counts as fully open; no hand, no change
+        let wide = InputSignals {
+            finger_distance: Some(900.0),
+            ..InputSignals::default()
+        };
+        assert_eq!(
+            scaled(&mut table, &wide).get(ConfigParameter::SeparationWeight),
+            3.0
+        );
+        assert!(scaled(&mut table, &InputSignals::default()).is_identity());
+    }
+
+    #[test]
+    fn test_mapping_shapes_inverts_and_smooths() {
+        let mapping = InputMapping::new(
+            InputSignal::HandHeight,
+            ConfigParameter::GravityStrength,
+            (1.0, 0.0),
+            (0.0, 4.0),
+        )
+        .with_exponent(2.0);
+        assert_eq!(mapping.scale_at(1.0), 0.0);
+        assert_eq!(mapping.scale_at(0.5), 1.0);
+        assert_eq!(mapping.scale_at(0.0), 4.0);
+
+        let mut table = MappingTable::new(vec![mapping.with_smoothing(0.5)]).unwrap();
+        let at = |height| InputSignals {
+            hand_height: Some(height),
+            ..InputSignals::default()
+        };
+        let gravity = |scales: ParameterScales| scales.get(ConfigParameter::GravityStrength);
+        assert_eq!(gravity(scaled(&mut table, &at(1.0))), 0.0);
+        assert_eq!(gravity(scaled(&mut table, &at(0.0))), 2.0);
+        assert_eq!(gravity(scaled(&mut table, &at(0.0))), 3.0);
+        // A lost hand starts over instead of easing in from before
+        scaled(&mut table, &InputSignals::default());
+        assert_eq!(gravity(scaled(&mut table, &at(0.0))), 4.0);
+    }
+
+    #[test]
+    fn test_hand_signals() {
+        let signals = InputSignals::of_hand(Vector2D::new(50.0, 150.0), 75.0, 200.0, Some(30.0));
+        assert_eq!(signals.finger_distance, Some(75.0));
+        assert_eq!(signals.hand_height, Some(0.25));
+        assert_eq!(signals.hand_speed, Some(30.0));
+        assert_eq!(signals.pinch_strength, Some(0.75));
+        assert_eq!(signals.audio_level, None);
+    }
+
+    #[test]
+    fn test_invalid_mappings_are_rejected() {
+        let valid = InputMapping::new(
+            InputSignal::AudioLevel,
+            ConfigParameter::CohesionWeight,
+            (0.0, 1.0),
+            (1.0, 2.0),
+        );
+        assert!(MappingTable::new(vec![valid]).is_ok());
+        for invalid in [
+            InputMapping {
+                input_max: 0.0,
+                ..valid
+            },
+            InputMapping {
+                high: f32::NAN,
+                ..valid
+            },
+            valid.with_exponent(0.0),
+            valid.with_smoothing(1.0),
+        ] {
+            assert_eq!(
+                MappingTable::new(vec![valid, invalid]),
+                Err(InvalidMapping { index: 1 })
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_parameter_round_trips_by_name() {
+        let mut config = BoidConfig::default();
+        for parameter in ConfigParameter::ALL {
+            assert_eq!(ConfigParameter::parse(parameter.as_str()), Some(parameter));
+            *parameter.get_mut(&mut config) = 7.0;
+            assert_eq!(parameter.get(&config), 7.0);
+        }
+        assert_eq!(config.path_radius, 7.0);
+        assert_eq!(ConfigParameter::parse("wander_enabled"), None);
+    }
+}
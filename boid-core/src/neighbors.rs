@@ -0,0 +1,217 @@
+//! The neighbor graph of the last update, for drawing and analysis
+//!
+//! Every `FlockStd` update collects, for each boid, the boids within the
+//! largest behavior radius, and steers by them. The lists are kept until
+//! the next update, along with where each boid was when they were built,
+//! so a [`NeighborGraph`] can answer who influenced whom without another
+//! pairwise search: the boids one separates from, or the cohesion links
+//! between flockmates to draw as lines.
+//!
+//! Radii are those of each boid's group in the configuration now in
+//! effect. Boids added since the update have no neighbors yet, and boids
+//! removed since still appear in it.
+
+use crate::{BoidConfig, FlockStd, ScratchArena, Vector2D};
+use std::vec::Vec;
+
+/// Which behavior's neighborhood to look at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborRadius {
+    /// Boids of any group within `separation_distance`
+    Separation,
+    /// Flockmates within `alignment_distance`
+    Alignment,
+    /// Flockmates within `cohesion_distance`
+    Cohesion,
+}
+
+impl NeighborRadius {
+    pub const ALL: [NeighborRadius; 3] = [
+        NeighborRadius::Separation,
+        NeighborRadius::Alignment,
+        NeighborRadius::Cohesion,
+    ];
+
+    /// `"separation"`, `"alignment"` or `"cohesion"`
+    pub fn name(self) -> &'static str {
+        match self {
+            NeighborRadius::Separation => "separation",
+            NeighborRadius::Alignment => "alignment",
+            NeighborRadius::Cohesion => "cohesion",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|radius| radius.name() == name)
+    }
+
+    pub fn distance(self, config: &BoidConfig) -> f32 {
+        match self {
+            NeighborRadius::Separation => config.separation_distance,
+            NeighborRadius::Alignment => config.alignment_distance,
+            NeighborRadius::Cohesion => config.cohesion_distance,
+        }
+    }
+
+    /// Whether only boids of the same group count
+    pub fn flockmates_only(self) -> bool {
+        self != NeighborRadius::Separation
+    }
+}
+
+/// A boid as the last neighbor search saw it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SearchedBoid {
+    pub(crate) id: u32,
+    pub(crate) group: u8,
+    pub(crate) position: Vector2D,
+}
+
+/// One behavior's view of the neighbor lists of the last update, see
+/// [`FlockStd::neighbor_graph`]
+#[derive(Debug, Clone)]
+pub struct NeighborGraph<'a> {
+    scratch: &'a ScratchArena,
+    kind: NeighborRadius,
+    /// The radius per group present in the last search, sorted by group
+    radii: Vec<(u8, f32)>,
+}
+
+impl<'a> NeighborGraph<'a> {
+    pub(crate) fn new(flock: &'a FlockStd, kind: NeighborRadius) -> Self {
+        let mut radii: Vec<(u8, f32)> = Vec::new();
+        for boid in &flock.scratch.searched {
+            if let Err(at) = radii.binary_search_by_key(&boid.group, |&(group, _)| group) {
+                let radius = kind.distance(&flock.config_for_group(boid.group));
+                radii.insert(at, (boid.group, radius));
+            }
+        }
+        Self {
+            scratch: &flock.scratch,
+            kind,
+            radii,
+        }
+    }
+
+    pub fn kind(&self) -> NeighborRadius {
+        self.kind
+    }
+
+    /// Ids of the boids `id` counted as neighbors, in flock order; none
+    /// for boids the last update did not see
+    pub fn neighbors_of(&self, id: u32) -> impl Iterator<Item = u32> + '_ {
+        let searched = &self.scratch.searched;
+        let index = searched.iter().position(|boid| boid.id == id);
+        index
+            .into_iter()
+            .flat_map(move |i| self.candidates(i).filter(move |&j| self.sees(i, j)))
+            .map(move |j| searched[j].id)
+    }
+
+    /// Every pair of neighbors once, as ids with the earlier boid in flock
+    /// order first. A pair counts when either boid sees the other, which
+    /// differs only between groups with different radii.
+    pub fn edges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let searched = &self.scratch.searched;
+        (0..searched.len()).flat_map(move |i| {
+            self.candidates(i)
+                .filter(move |&j| j > i && (self.sees(i, j) || self.sees(j, i)))
+                .map(move |j| (searched[i].id, searched[j].id))
+        })
+    }
+
+    /// Indices of the boids within the search radius of boid `i`; the
+    /// search radius is the same for every boid, so these lists are
+    /// symmetric
+    fn candidates(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let scratch = self.scratch;
+        scratch.neighbor_indices[scratch.neighbor_start[i]..scratch.neighbor_start[i + 1]]
+            .iter()
+            .copied()
+    }
+
+    /// Whether boid `i` counts boid `j` under its group's radius
+    fn sees(&self, i: usize, j: usize) -> bool {
+        let (boid, other) = (&self.scratch.searched[i], &self.scratch.searched[j]);
+        if self.kind.flockmates_only() && boid.group != other.group {
+            return false;
+        }
+        let radius = match self
+            .radii
+            .binary_search_by_key(&boid.group, |&(group, _)| group)
+        {
+            Ok(at) => self.radii[at].1,
+            Err(_) => return false,
+        };
+        // Like the behaviors, which skip boids at distance 0, the boid
+        // itself among them
+        let distance = boid.position.distance(&other.position);
+        distance > 0.0 && distance < radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Boid;
+
+    /// Four boids on a line and one of group 1 between the first two
+    fn flock(spatial_grid: bool) -> (FlockStd, [u32; 5]) {
+        let mut flock = FlockStd::new(800.0, 600.0, 0);
+        flock.config.separation_distance = 15.0;
+        flock.config.cohesion_distance = 40.0;
+        flock.config.spatial_grid = spatial_grid;
+        let mut ids = [0; 5];
+        for (id, x) in ids.iter_mut().zip([100.0, 110.0, 130.0, 500.0]) {
+            *id = flock.add_boid(Boid::new(Vector2D::new(x, 100.0), Vector2D::zero()));
+        }
+        ids[4] = flock.add_boid(Boid {
+            group: 1,
+            ..Boid::new(Vector2D::new(105.0, 100.0), Vector2D::zero())
+        });
+        // Group 1 keeps its distance from further away
+        let wary = BoidConfig {
+            separation_distance: 30.0,
+            ..flock.config
+        };
+        flock.set_group_config(1, wary);
+        (flock, ids)
+    }
+
+    #[test]
+    fn test_neighbors_by_radius() {
+        let (mut flock, [a, b, c, d, e]) = flock(false);
+        assert!(flock.neighbors_of(a, NeighborRadius::Cohesion).is_empty());
+
+        flock.update();
+        assert_eq!(flock.neighbors_of(a, NeighborRadius::Separation), [b, e]);
+        assert_eq!(flock.neighbors_of(a, NeighborRadius::Cohesion), [b, c]);
+        assert!(flock.neighbors_of(d, NeighborRadius::Cohesion).is_empty());
+        // Group 1 has no flockmates, and separates from further away
+        assert!(flock.neighbors_of(e, NeighborRadius::Alignment).is_empty());
+        assert_eq!(flock.neighbors_of(e, NeighborRadius::Separation), [a, b, c]);
+        assert!(flock.neighbors_of(c, NeighborRadius::Separation).is_empty());
+        assert!(flock
+            .neighbors_of(99, NeighborRadius::Separation)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_edges_with_and_without_the_grid() {
+        for spatial_grid in [false, true] {
+            let (mut flock, [a, b, c, _, e]) = flock(spatial_grid);
+            flock.update();
+            let cohesion: Vec<_> = flock
+                .neighbor_graph(NeighborRadius::Cohesion)
+                .edges()
+                .collect();
+            assert_eq!(cohesion, [(a, b), (a, c), (b, c)]);
+            // One sided, since only `e` is close enough to see `c`
+            let separation: Vec<_> = flock
+                .neighbor_graph(NeighborRadius::Separation)
+                .edges()
+                .collect();
+            assert_eq!(separation, [(a, b), (a, e), (b, e), (c, e)]);
+        }
+    }
+}
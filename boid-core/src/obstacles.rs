@@ -0,0 +1,485 @@
+//! Obstacle maps: a black-and-white mask, such as a logo silhouette or a
+//! floor plan, stretched over the flock's area for boids to flow around
+//!
+//! White pixels are open and black ones are avoided. Boids near a black
+//! region turn away from it, and boids that end up inside one head for the
+//! nearest open space. [`ObstacleMap`] is a [`SteeringBehavior`], so it
+//! joins a flock like any other:
+//!
+//! ```
+//! use boid_core::{obstacles::ObstacleMap, FlockStd};
+//!
+//! // 2x2 mask with the top right quarter blocked
+//! let map = ObstacleMap::from_luma(&[255, 0, 255, 255], 2, 2).unwrap();
+//! let mut flock = FlockStd::new(800.0, 600.0, 100);
+//! flock.behaviors.add("obstacles", map, 3.0);
+//! ```
+//!
+//! [`ExclusionRects`] does the same for a few rectangles given in flock
+//! units, such as the headlines and buttons of a page laid over the flock,
+//! without drawing them to a mask first.
+
+use crate::steering::{FlockContext, SteeringBehavior};
+use crate::{behavior, Boid, Vector2D};
+use std::collections::VecDeque;
+
+/// Larger masks are sampled down to this many cells a side
+pub const MAX_CELLS_PER_SIDE: usize = 256;
+
+/// Default distance from a blocked region at which boids start turning
+pub const DEFAULT_REACH: f32 = 30.0;
+
+/// Why a mask could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaskError {
+    /// The pixel data does not hold `width` x `height` pixels, or the
+    /// mask is empty
+    Size {
+        width: usize,
+        height: usize,
+        len: usize,
+    },
+}
+
+impl core::fmt::Display for MaskError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Size { width, height, len } => {
+                write!(f, "{} bytes do not make a {}x{} mask", len, width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+/// Open and blocked cells of a mask, with the nearest cell of the other
+/// kind precomputed for each, so steering is a lookup per boid
+#[derive(Debug, Clone)]
+pub struct ObstacleMap {
+    cols: usize,
+    rows: usize,
+    blocked: Vec<bool>,
+    /// Column and row of the nearest cell of the other kind; `None` when
+    /// the mask is all open or all blocked
+    nearest: Vec<Option<(u16, u16)>>,
+    /// Distance from a blocked region, in flock units, within which boids
+    /// turn away; the push grows from 0 at `reach` to full at the edge
+    pub reach: f32,
+}
+
+impl ObstacleMap {
+    /// Mask with one byte per pixel, row by row; 128 and above is open
+    pub fn from_luma(pixels: &[u8], width: usize, height: usize) -> Result<Self, MaskError> {
+        check_size(pixels, width, height, 1)?;
+        Ok(Self::sample(width, height, |x, y| {
+            pixels[y * width + x] >= 128
+        }))
+    }
+
+    /// Mask as RGBA bytes, e.g. a canvas `ImageData`. Light pixels are
+    /// open, and so are transparent ones, so a dark logo on a transparent
+    /// background works as is.
+    pub fn from_rgba(pixels: &[u8], width: usize, height: usize) -> Result<Self, MaskError> {
+        check_size(pixels, width, height, 4)?;
+        Ok(Self::sample(width, height, |x, y| {
+            let [r, g, b, a] = [0, 1, 2, 3].map(|c| pixels[(y * width + x) * 4 + c] as u32);
+            a < 128 || (r * 299 + g * 587 + b * 114) / 1000 >= 128
+        }))
+    }
+
+    fn sample(width: usize, height: usize, open: impl Fn(usize, usize) -> bool) -> Self {
+        let cols = width.min(MAX_CELLS_PER_SIDE);
+        let rows = height.min(MAX_CELLS_PER_SIDE);
+        // Each cell takes the pixel at its center
+        let blocked: Vec<bool> = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let x = (2 * col + 1) * width / (2 * cols);
+                let y = (2 * row + 1) * height / (2 * rows);
+                !open(x, y)
+            })
+            .collect();
+
+        let nearest_blocked = nearest_cells(cols, rows, &blocked, true);
+        let nearest_open = nearest_cells(cols, rows, &blocked, false);
+        let nearest = blocked
+            .iter()
+            .zip(nearest_blocked.into_iter().zip(nearest_open))
+            .map(|(&blocked, (to_blocked, to_open))| if blocked { to_open } else { to_blocked })
+            .collect();
+
+        Self {
+            cols,
+            rows,
+            blocked,
+            nearest,
+            reach: DEFAULT_REACH,
+        }
+    }
+
+    /// Columns and rows after sampling
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Whether `position` lies on a blocked cell of the map stretched over
+    /// a `width` x `height` area
+    pub fn is_blocked(&self, position: Vector2D, width: f32, height: f32) -> bool {
+        self.blocked[self.cell_of(position, width, height)]
+    }
+
+    fn cell_of(&self, position: Vector2D, width: f32, height: f32) -> usize {
+        let col = (position.x / width * self.cols as f32).max(0.0) as usize;
+        let row = (position.y / height * self.rows as f32).max(0.0) as usize;
+        row.min(self.rows - 1) * self.cols + col.min(self.cols - 1)
+    }
+}
+
+#[deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
+impl SteeringBehavior for ObstacleMap {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        let cell = self.cell_of(boid.position, ctx.width, ctx.height);
+        let Some((col, row)) = self.nearest[cell] else {
+            return Vector2D::zero();
+        };
+        let cell_width = ctx.width / self.cols as f32;
+        let cell_height = ctx.height / self.rows as f32;
+        let nearest = Vector2D::new(
+            (col as f32 + 0.5) * cell_width,
+            (row as f32 + 0.5) * cell_height,
+        );
+
+        if self.blocked[cell] {
+            // Inside an obstacle: head for open space at full strength
+            return behavior::seek(boid, nearest, ctx.config);
+        }
+        // The edge lies about half a cell before the blocked cell's center
+        let distance = boid.position.distance(&nearest) - 0.5 * cell_width.min(cell_height);
+        if distance >= self.reach {
+            return Vector2D::zero();
+        }
+        let strength = (1.0 - distance / self.reach).min(1.0);
+        let away = boid.position + (boid.position - nearest);
+        behavior::seek(boid, away, ctx.config) * strength
+    }
+}
+
+/// An axis-aligned rectangle in flock units, `x` and `y` being its top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, point: Vector2D) -> bool {
+        point.x >= self.x
+            && point.x <= self.x + self.width
+            && point.y >= self.y
+            && point.y <= self.y + self.height
+    }
+
+    /// The point of the rectangle nearest to `point`, which is `point`
+    /// itself when inside
+    fn nearest(&self, point: Vector2D) -> Vector2D {
+        Vector2D::new(
+            point.x.clamp(self.x, self.x + self.width),
+            point.y.clamp(self.y, self.y + self.height),
+        )
+    }
+
+    /// A point just outside the side nearest to `point`, which is inside
+    fn exit(&self, point: Vector2D) -> Vector2D {
+        let sides = [
+            (point.x - self.x, Vector2D::new(self.x - 1.0, point.y)),
+            (
+                self.x + self.width - point.x,
+                Vector2D::new(self.x + self.width + 1.0, point.y),
+            ),
+            (point.y - self.y, Vector2D::new(point.x, self.y - 1.0)),
+            (
+                self.y + self.height - point.y,
+                Vector2D::new(point.x, self.y + self.height + 1.0),
+            ),
+        ];
+        sides
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(point, |(_, exit)| exit)
+    }
+}
+
+/// Rectangles boids flow around as soft obstacles: each pushes boids within
+/// `reach` of it away, harder the closer they are, and boids that end up
+/// inside leave through the nearest side. Pushes from neighboring rectangles
+/// add up. Unlike an [`ObstacleMap`], the rectangles are not stretched over
+/// the flock's area.
+#[derive(Debug, Clone)]
+pub struct ExclusionRects {
+    rects: Vec<Rect>,
+    /// Distance from a rectangle, in flock units, within which boids turn
+    /// away; the push grows from 0 at `reach` to full at the edge
+    pub reach: f32,
+}
+
+impl ExclusionRects {
+    /// Rectangles that are empty or not finite are left out
+    pub fn new(rects: impl IntoIterator<Item = Rect>) -> Self {
+        let rects = rects
+            .into_iter()
+            .filter(|rect| {
+                [rect.x, rect.y, rect.width, rect.height]
+                    .iter()
+                    .all(|v| v.is_finite())
+                    && rect.width > 0.0
+                    && rect.height > 0.0
+            })
+            .collect();
+        Self {
+            rects,
+            reach: DEFAULT_REACH,
+        }
+    }
+
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Whether `position` lies inside any of the rectangles
+    pub fn is_blocked(&self, position: Vector2D) -> bool {
+        self.rects.iter().any(|rect| rect.contains(position))
+    }
+}
+
+#[deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
+impl SteeringBehavior for ExclusionRects {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        self.rects.iter().fold(Vector2D::zero(), |force, rect| {
+            if rect.contains(boid.position) {
+                return force + behavior::seek(boid, rect.exit(boid.position), ctx.config);
+            }
+            let edge = rect.nearest(boid.position);
+            let distance = boid.position.distance(&edge);
+            if distance >= self.reach {
+                return force;
+            }
+            let strength = 1.0 - distance / self.reach;
+            let away = boid.position + (boid.position - edge);
+            force + behavior::seek(boid, away, ctx.config) * strength
+        })
+    }
+}
+
+fn check_size(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+) -> Result<(), MaskError> {
+    if width == 0 || height == 0 || pixels.len() != width * height * bytes_per_pixel {
+        return Err(MaskError::Size {
+            width,
+            height,
+            len: pixels.len(),
+        });
+    }
+    Ok(())
+}
+
+/// For every cell, the nearest cell whose `blocked` flag equals `kind`.
+/// Seeds spread outward breadth first, each cell keeping the closest seed
+/// it has been offered, which is exact or off by a fraction of a cell.
+fn nearest_cells(
+    cols: usize,
+    rows: usize,
+    blocked: &[bool],
+    kind: bool,
+) -> Vec<Option<(u16, u16)>> {
+    let mut nearest = vec![None; blocked.len()];
+    let mut queue = VecDeque::new();
+    for (index, _) in blocked.iter().enumerate().filter(|(_, &b)| b == kind) {
+        nearest[index] = Some(((index % cols) as u16, (index / cols) as u16));
+        queue.push_back(index);
+    }
+    let distance2 = |index: usize, (col, row): (u16, u16)| {
+        let dx = (index % cols) as i32 - col as i32;
+        let dy = (index / cols) as i32 - row as i32;
+        dx * dx + dy * dy
+    };
+
+    while let Some(index) = queue.pop_front() {
+        let Some(seed) = nearest[index] else {
+            continue;
+        };
+        let (col, row) = ((index % cols) as i32, (index / cols) as i32);
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            let (c, r) = (col + dx, row + dy);
+            if c < 0 || r < 0 || c >= cols as i32 || r >= rows as i32 {
+                continue;
+            }
+            let neighbor = r as usize * cols + c as usize;
+            let closer = match nearest[neighbor] {
+                Some(current) => distance2(neighbor, seed) < distance2(neighbor, current),
+                None => true,
+            };
+            if closer {
+                nearest[neighbor] = Some(seed);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoidConfig, FlockStd};
+
+    /// 40x40 mask, open but for a black square in the middle
+    fn square_mask() -> Vec<u8> {
+        (0..40 * 40)
+            .map(|i| {
+                let (x, y) = (i % 40, i / 40);
+                if (15..25).contains(&x) && (15..25).contains(&y) {
+                    0
+                } else {
+                    255
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mask_size_is_checked() {
+        assert_eq!(
+            ObstacleMap::from_luma(&[255; 5], 2, 2).unwrap_err(),
+            MaskError::Size {
+                width: 2,
+                height: 2,
+                len: 5
+            }
+        );
+        assert!(ObstacleMap::from_rgba(&[], 0, 0).is_err());
+
+        let map = ObstacleMap::from_rgba(&[0, 0, 0, 255, 0, 0, 0, 0], 2, 1).unwrap();
+        assert!(map.is_blocked(Vector2D::new(10.0, 5.0), 40.0, 10.0));
+        // Transparent pixels are open
+        assert!(!map.is_blocked(Vector2D::new(30.0, 5.0), 40.0, 10.0));
+    }
+
+    #[test]
+    fn test_boids_flow_around_obstacles() {
+        let map = ObstacleMap::from_luma(&square_mask(), 40, 40).unwrap();
+        let config = BoidConfig {
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            max_force: 0.2,
+            ..BoidConfig::default()
+        };
+        let mut flock = FlockStd::new_with_config(400.0, 400.0, 0, config);
+        flock.behaviors.add("obstacles", map.clone(), 3.0);
+        // Heading straight for the square, just off its center line
+        for i in 0..5 {
+            flock.add_boid(Boid::new(
+                Vector2D::new(20.0, 190.0 + i as f32 * 5.0),
+                Vector2D::new(2.0, 0.0),
+            ));
+        }
+        // One boid starts inside it
+        flock.add_boid(Boid::new(Vector2D::new(200.0, 200.0), Vector2D::zero()));
+
+        for tick in 0..300 {
+            flock.update();
+            if tick >= 60 {
+                for boid in &flock.boids {
+                    assert!(
+                        !map.is_blocked(boid.position, 400.0, 400.0),
+                        "tick {}: boid {} at {:?}",
+                        tick,
+                        boid.id,
+                        boid.position
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_boids_flow_around_exclusion_rects() {
+        // A headline-shaped bar across the middle, and rectangles that are
+        // left out
+        let rects = ExclusionRects::new([
+            Rect::new(100.0, 180.0, 200.0, 40.0),
+            Rect::new(0.0, 0.0, 0.0, 10.0),
+            Rect::new(f32::NAN, 0.0, 10.0, 10.0),
+        ]);
+        assert_eq!(rects.rects().len(), 1);
+        assert!(rects.is_blocked(Vector2D::new(200.0, 200.0)));
+        assert!(!rects.is_blocked(Vector2D::new(200.0, 240.0)));
+
+        let config = BoidConfig {
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            max_force: 0.2,
+            ..BoidConfig::default()
+        };
+        let mut flock = FlockStd::new_with_config(400.0, 400.0, 0, config);
+        flock.behaviors.add("exclusions", rects.clone(), 3.0);
+        // Falling straight onto the bar
+        for i in 0..5 {
+            flock.add_boid(Boid::new(
+                Vector2D::new(150.0 + i as f32 * 25.0, 20.0),
+                Vector2D::new(0.0, 2.0),
+            ));
+        }
+        // One boid starts under it, off center
+        flock.add_boid(Boid::new(Vector2D::new(120.0, 195.0), Vector2D::zero()));
+
+        for tick in 0..300 {
+            flock.update();
+            if tick >= 30 {
+                for boid in &flock.boids {
+                    assert!(
+                        !rects.is_blocked(boid.position),
+                        "tick {}: boid {} at {:?}",
+                        tick,
+                        boid.id,
+                        boid.position
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Particle spawn events derived from boid motion, so an external particle
+//! system (sparks, smoke trails) can be layered on top of a flock without
+//! simulating it a second time
+//!
+//! Call [`ParticleEmitter::observe`] once after every tick. Each boid that
+//! changed velocity sharply enough during the tick queues a
+//! [`ParticleEvent`], and the particle system drains the queue whenever it
+//! spawns, at its own pace.
+
+use crate::{Boid, Vector2D};
+use std::collections::vec_deque::{Drain, VecDeque};
+
+/// One particle to spawn where a boid is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleEvent {
+    /// Id of the emitting boid (always 0 for `Flock<N>` boids)
+    pub boid_id: u32,
+    pub position: Vector2D,
+    pub velocity: Vector2D,
+    /// The boid's acceleration over the tick relative to
+    /// [`ParticleEmitter::full_scale`], from `threshold` up to 1
+    pub intensity: f32,
+}
+
+/// Turns boid accelerations into a queue of [`ParticleEvent`]s
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    /// Velocity change per tick that gives intensity 1; the default
+    /// matches the default `max_force`
+    pub full_scale: f32,
+    /// Boids below this intensity emit nothing
+    pub threshold: f32,
+    /// Most events kept between drains; the oldest are dropped first
+    pub capacity: usize,
+    /// Id and velocity of each boid at the last `observe`, by index
+    previous: Vec<(u32, Vector2D)>,
+    queue: VecDeque<ParticleEvent>,
+    dropped: u64,
+}
+
+impl ParticleEmitter {
+    pub fn new() -> Self {
+        Self {
+            full_scale: 0.05,
+            threshold: 0.5,
+            capacity: 4096,
+            previous: Vec::new(),
+            queue: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Queue an event for every boid whose velocity changed enough since
+    /// the last call. A boid is compared with the one at the same index, and
+    /// only if the ids match, so boids added or removed in between emit
+    /// nothing on their first tick.
+    pub fn observe(&mut self, boids: &[Boid]) {
+        for (boid, &(id, before)) in boids.iter().zip(&self.previous) {
+            if id != boid.id || self.full_scale <= 0.0 {
+                continue;
+            }
+            let acceleration = (boid.velocity - before).magnitude();
+            let intensity = (acceleration / self.full_scale).min(1.0);
+            if intensity <= 0.0 || intensity < self.threshold {
+                continue;
+            }
+            self.queue.push_back(ParticleEvent {
+                boid_id: boid.id,
+                position: boid.position,
+                velocity: boid.velocity,
+                intensity,
+            });
+        }
+        while self.queue.len() > self.capacity {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+
+        self.previous.clear();
+        self.previous
+            .extend(boids.iter().map(|boid| (boid.id, boid.velocity)));
+    }
+
+    /// Take the queued events, oldest first
+    pub fn drain(&mut self) -> Drain<'_, ParticleEvent> {
+        self.queue.drain(..)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Events lost to `capacity` since the emitter was created
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Forget the queue and the last velocities, e.g. after the flock was
+    /// replaced or emission was paused
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.previous.clear();
+    }
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sharp_turns_emit() {
+        let mut boids = [
+            Boid::new(Vector2D::new(10.0, 10.0), Vector2D::new(1.0, 0.0)),
+            Boid::new(Vector2D::new(50.0, 50.0), Vector2D::new(1.0, 0.0)),
+        ];
+        boids[1].id = 1;
+        let mut emitter = ParticleEmitter::new();
+        emitter.observe(&boids);
+        assert!(emitter.is_empty(), "first sight has no acceleration");
+
+        boids[0].velocity = Vector2D::new(1.01, 0.0);
+        boids[1].velocity = Vector2D::new(1.0, 0.04);
+        emitter.observe(&boids);
+        let events: Vec<_> = emitter.drain().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].boid_id, 1);
+        assert!((events[0].intensity - 0.8).abs() < 1e-4);
+        assert!(emitter.is_empty());
+
+        // A different boid at the same index is not compared
+        boids[1].id = 7;
+        boids[1].velocity = Vector2D::new(-1.0, 0.0);
+        emitter.observe(&boids);
+        assert!(emitter.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let mut emitter = ParticleEmitter {
+            capacity: 2,
+            ..ParticleEmitter::new()
+        };
+        let mut boid = Boid::new(Vector2D::zero(), Vector2D::zero());
+        emitter.observe(core::slice::from_ref(&boid));
+        for step in 1..=3 {
+            boid.velocity = Vector2D::new(step as f32, 0.0);
+            boid.position = Vector2D::new(step as f32, 0.0);
+            emitter.observe(core::slice::from_ref(&boid));
+        }
+        assert_eq!(emitter.dropped(), 1);
+        let positions: Vec<_> = emitter.drain().map(|e| e.position.x).collect();
+        assert_eq!(positions, [2.0, 3.0]);
+        assert_eq!(emitter.drain().count(), 0);
+    }
+}
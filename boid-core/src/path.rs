@@ -0,0 +1,110 @@
+//! Polylines for boids to fly along
+//!
+//! A [`Path`] is a fixed-capacity list of points, open or closed into a
+//! loop. [`behavior::follow_path`](crate::behavior::follow_path) steers a
+//! boid along it: boids within `path_radius` of the line keep to its
+//! direction, boids farther out steer back onto it a little ahead of where
+//! they meet it. Scripted paths, e.g. the outline of a letter, let the
+//! flock trace shapes while still flocking.
+
+use crate::Vector2D;
+
+/// Most points a path holds
+pub const MAX_POINTS: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Path {
+    points: heapless::Vec<Vector2D, MAX_POINTS>,
+    /// Join the last point back to the first, so boids go round and round.
+    /// Boids reaching the end of an open path fly on past it.
+    pub closed: bool,
+}
+
+impl Path {
+    /// The path through `points`, dropping any beyond [`MAX_POINTS`]
+    pub fn new(points: &[Vector2D], closed: bool) -> Self {
+        let mut path = Self {
+            closed,
+            ..Self::default()
+        };
+        for &point in points.iter().take(MAX_POINTS) {
+            let _ = path.points.push(point);
+        }
+        path
+    }
+
+    /// Extend the path; hands `point` back if it is full
+    pub fn push(&mut self, point: Vector2D) -> Result<(), Vector2D> {
+        self.points.push(point)
+    }
+
+    pub fn points(&self) -> &[Vector2D] {
+        &self.points
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The point on the path nearest to `point`, and the direction the path
+    /// runs there; `None` for paths without a segment of any length
+    pub fn closest(&self, point: Vector2D) -> Option<(Vector2D, Vector2D)> {
+        let closing = match (self.closed, self.points.first(), self.points.last()) {
+            (true, Some(&first), Some(&last)) if self.points.len() > 2 => Some((last, first)),
+            _ => None,
+        };
+        self.points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+            .filter_map(|(start, end)| {
+                let segment = end - start;
+                let length_sq = segment.dot(&segment);
+                if length_sq == 0.0 {
+                    return None;
+                }
+                let t = ((point - start).dot(&segment) / length_sq).clamp(0.0, 1.0);
+                let nearest = start + segment * t;
+                Some((nearest.distance(&point), nearest, segment.normalize()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, nearest, direction)| (nearest, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_point_and_direction() {
+        let square = [
+            Vector2D::new(0.0, 0.0),
+            Vector2D::new(100.0, 0.0),
+            Vector2D::new(100.0, 100.0),
+            Vector2D::new(0.0, 100.0),
+        ];
+        let open = Path::new(&square, false);
+        let (point, direction) = open.closest(Vector2D::new(40.0, -10.0)).unwrap();
+        assert_eq!(point, Vector2D::new(40.0, 0.0));
+        assert_eq!(direction, Vector2D::new(1.0, 0.0));
+
+        // Only the closed path has the left edge, running back up
+        let left = Vector2D::new(-5.0, 50.0);
+        assert_eq!(open.closest(left).unwrap().0, Vector2D::new(0.0, 0.0));
+        let closed = Path::new(&square, true);
+        assert_eq!(
+            closed.closest(left).unwrap(),
+            (Vector2D::new(0.0, 50.0), Vector2D::new(0.0, -1.0))
+        );
+
+        assert_eq!(Path::new(&square[..1], true).closest(left), None);
+        let long: Vec<Vector2D> = (0..200).map(|i| Vector2D::new(i as f32, 0.0)).collect();
+        assert_eq!(Path::new(&long, false).len(), MAX_POINTS);
+    }
+}
@@ -0,0 +1,154 @@
+//! Boids that now and then land on the bottom edge and rest, like birds on
+//! a wire
+//!
+//! With a [`PerchConfig`] set on a `FlockStd`, every flying boid may set off
+//! to land, on average `perch_probability` times a second. A
+//! [`PerchState::Landing`] boid leaves the flock and glides down to the
+//! ground, a little above the bottom edge, ignoring wind and the boundary.
+//! Once there it is [`PerchState::Perched`]: it stops and sits still until
+//! its rest, which varies a little from boid to boid, runs out, then takes
+//! off upward and rejoins the flock. Flying boids still see perched ones as
+//! neighbors, so a flock passing low may pick up a few of them.
+//!
+//! Puppets and boids holding a formation slot never perch; a boid called
+//! into a formation takes off at once.
+
+use crate::{behavior, Boid, BoidConfig, Vector2D};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Distance above the bottom edge at which boids perch: the margin bouncing
+/// boids keep, so that they are drawn whole
+pub const GROUND_CLEARANCE: f32 = 10.0;
+
+/// How often boids land and how long they rest. Times are in seconds of
+/// simulation, see `StepInput::dt`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerchConfig {
+    /// Chance per second that a flying boid sets off to land
+    pub perch_probability: f32,
+    /// Average rest on the ground; each landing gets one within
+    /// `rest_spread` of it
+    pub rest_duration: f32,
+    /// Share of `rest_duration` a rest may differ by, 0 to 1
+    pub rest_spread: f32,
+    /// Speed a boid takes off with, mostly upward
+    pub takeoff_speed: f32,
+}
+
+impl Default for PerchConfig {
+    fn default() -> Self {
+        Self {
+            perch_probability: 0.02,
+            rest_duration: 4.0,
+            rest_spread: 0.5,
+            takeoff_speed: 3.0,
+        }
+    }
+}
+
+/// Where a boid is in its landing cycle
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PerchState {
+    #[default]
+    Flying,
+    /// On its way down to the ground
+    Landing,
+    /// Resting on the ground
+    Perched {
+        /// Seconds until it takes off
+        remaining: f32,
+    },
+}
+
+impl PerchState {
+    pub fn is_perched(&self) -> bool {
+        matches!(self, Self::Perched { .. })
+    }
+}
+
+/// The perching state of every boid that is not simply flying
+#[derive(Debug, Clone)]
+pub(crate) struct Perching {
+    pub(crate) config: PerchConfig,
+    /// State and the step it was last seen in, to forget removed boids
+    states: HashMap<u32, (PerchState, u64)>,
+    steps: u64,
+}
+
+impl Perching {
+    pub(crate) fn new(config: PerchConfig) -> Self {
+        Self {
+            config,
+            states: HashMap::new(),
+            steps: 0,
+        }
+    }
+
+    pub(crate) fn state(&self, id: u32) -> PerchState {
+        self.states
+            .get(&id)
+            .map_or(PerchState::Flying, |&(state, _)| state)
+    }
+
+    /// The force on a landing or perched boid, which replaces the flock's;
+    /// `None` for a flying boid
+    pub(crate) fn steer(&self, boid: &Boid, height: f32, config: &BoidConfig) -> Option<Vector2D> {
+        match self.state(boid.id) {
+            PerchState::Flying => None,
+            PerchState::Landing => {
+                let ground = Vector2D::new(boid.position.x, ground(height));
+                Some(behavior::seek(boid, ground, config))
+            }
+            PerchState::Perched { .. } => Some(Vector2D::zero()),
+        }
+    }
+
+    /// Advance every boid's landing cycle by `secs`: set some flying boids
+    /// off to land, perch the landing ones that reached the ground and
+    /// launch the rested ones
+    pub(crate) fn step(&mut self, boids: &mut [Boid], height: f32, secs: f32, rng: &mut impl Rng) {
+        self.steps = self.steps.wrapping_add(1);
+        let config = self.config;
+        let ground = ground(height);
+        for boid in boids.iter_mut() {
+            if boid.puppet || boid.target_override.is_formation() {
+                self.states.remove(&boid.id);
+                continue;
+            }
+            let state = match self.state(boid.id) {
+                PerchState::Flying if rng.gen::<f32>() < config.perch_probability * secs => {
+                    PerchState::Landing
+                }
+                PerchState::Flying => continue,
+                PerchState::Landing if boid.position.y >= ground => {
+                    boid.position.y = ground;
+                    boid.velocity = Vector2D::zero();
+                    let spread = config.rest_spread.clamp(0.0, 1.0);
+                    PerchState::Perched {
+                        remaining: config.rest_duration * (1.0 + rng.gen_range(-spread..=spread)),
+                    }
+                }
+                PerchState::Landing => PerchState::Landing,
+                PerchState::Perched { remaining } if remaining > secs => PerchState::Perched {
+                    remaining: remaining - secs,
+                },
+                PerchState::Perched { .. } => {
+                    boid.velocity = Vector2D::new(rng.gen_range(-0.5..=0.5), -1.0).normalize()
+                        * config.takeoff_speed;
+                    self.states.remove(&boid.id);
+                    continue;
+                }
+            };
+            self.states.insert(boid.id, (state, self.steps));
+        }
+        let steps = self.steps;
+        self.states.retain(|_, &mut (_, seen)| seen == steps);
+    }
+}
+
+/// Height at which boids perch
+pub(crate) fn ground(height: f32) -> f32 {
+    (height - GROUND_CLEARANCE).max(0.0)
+}
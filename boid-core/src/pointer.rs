@@ -0,0 +1,60 @@
+//! What holding the mouse or a finger on the canvas does to the flock
+//!
+//! Shared by the web and native frontends. Hand tracking always attracts;
+//! the pointer mode only applies while no hand is tracked.
+
+/// Effect of a pressed pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerMode {
+    /// Boids seek the pointer
+    #[default]
+    Attract,
+    /// Boids flee the pointer, see `TargetMode::Flee`
+    Repel,
+    /// The pointer does not affect the flock
+    None,
+}
+
+impl PointerMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "attract" => Some(Self::Attract),
+            "repel" => Some(Self::Repel),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Attract => "attract",
+            Self::Repel => "repel",
+            Self::None => "none",
+        }
+    }
+
+    /// The mode after this one, for a key that cycles through them
+    pub fn next(self) -> Self {
+        match self {
+            Self::Attract => Self::Repel,
+            Self::Repel => Self::None,
+            Self::None => Self::Attract,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_mode_names_and_cycle() {
+        let mut mode = PointerMode::default();
+        for _ in 0..3 {
+            assert_eq!(PointerMode::parse(mode.as_str()), Some(mode));
+            mode = mode.next();
+        }
+        assert_eq!(mode, PointerMode::Attract);
+        assert_eq!(PointerMode::parse("push"), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! Compile-time tuning profiles for the fixed-capacity flock
+//!
+//! A `Flock<N, P>` takes its profile `P` as a type parameter. The profile
+//! decides which neighbor behaviors are compiled in: the switches are
+//! associated constants, so the optimizer drops the code of a disabled
+//! behavior along with its pass over the flock. This saves flash and CPU
+//! on the ESP32. [`FlockProfile::config`] supplies the tuned distances and
+//! weights that [`Flock::with_profile`](crate::Flock::with_profile) starts
+//! from.
+//!
+//! Every field of `flock.config` stays tunable at runtime, e.g. from
+//! `/api/settings`. The weights and distances of a compiled-out behavior
+//! are ignored, and [`FlockProfile::ignores`] tells which settings those
+//! are.
+
+use crate::BoidConfig;
+
+/// Behaviors and tuning chosen at compile time
+pub trait FlockProfile {
+    const SEPARATION: bool = true;
+    const ALIGNMENT: bool = true;
+    const COHESION: bool = true;
+    /// Seek, flee or arrive toward the target
+    const TARGET: bool = true;
+
+    /// Starting config tuned for the profile
+    fn config() -> BoidConfig {
+        BoidConfig::default()
+    }
+
+    /// Whether the `BoidConfig` field `name` has no effect under this
+    /// profile, so a settings endpoint can warn about it
+    fn ignores(name: &str) -> bool {
+        match name {
+            "separation_distance" | "separation_weight" => !Self::SEPARATION,
+            "alignment_distance" | "alignment_weight" => !Self::ALIGNMENT,
+            "cohesion_distance" | "cohesion_weight" => !Self::COHESION,
+            "seek_weight" | "seek_radius" | "target_mode" | "flee_radius" | "arrive_radius" => {
+                !Self::TARGET
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Every behavior with the default config; what `Flock<N>` uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Standard;
+
+impl FlockProfile for Standard {}
+
+/// For slow cores and large flocks: cohesion is compiled out, which saves
+/// one of the three passes over the flock per boid. The target gathers the
+/// boids instead, and shorter neighbor distances keep the remaining passes
+/// cheap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LowCpu;
+
+impl FlockProfile for LowCpu {
+    const COHESION: bool = false;
+
+    fn config() -> BoidConfig {
+        BoidConfig {
+            separation_distance: 12.0,
+            alignment_distance: 20.0,
+            ..BoidConfig::default()
+        }
+    }
+}
+
+/// Every behavior, with wider neighborhoods and gentler turns for larger,
+/// smoother flocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pretty;
+
+impl FlockProfile for Pretty {
+    fn config() -> BoidConfig {
+        BoidConfig {
+            max_speed: 2.5,
+            max_force: 0.04,
+            separation_distance: 18.0,
+            alignment_distance: 40.0,
+            cohesion_distance: 40.0,
+            alignment_weight: 1.2,
+            ..BoidConfig::default()
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Offline recording of simulation runs
+//!
+//! [`Recorder`] steps a [`FlockStd`] and renders every frame in software,
+//! exporting the run as an animated GIF or a PNG sequence. A seeded flock
+//! records the same bytes on every run, so the files double as visual
+//! regression artifacts. [`Frame`] is the rasterizer, shared with the
+//! desktop renderer.
+//!
+//! A [`Timeline`](crate::timeline::Timeline) turned on right before
+//! recording counts the same ticks as the recorder, so a marker at `tick`
+//! shows up in frame `tick / ticks_per_frame`.
+
+use crate::{Boid, FlockStd, Vector2D};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub type Rgb = [u8; 3];
+
+pub const BACKGROUND: Rgb = [10, 14, 39];
+/// Externally driven boids, as in the web demo
+pub const PUPPET: Rgb = [230, 140, 60];
+const TARGET: Rgb = [255, 255, 255];
+/// Length of a boid from nose to tail, in pixels
+pub const BOID_SIZE: f32 = 8.0;
+/// Boid colors by sub-flock, cycled when there are more groups
+const GROUP_COLORS: [Rgb; 6] = [
+    [80, 200, 255],
+    [255, 110, 150],
+    [140, 230, 110],
+    [250, 210, 90],
+    [180, 140, 255],
+    [90, 230, 200],
+];
+
+/// An RGBA frame, `width * height * 4` bytes
+pub struct Frame<'a> {
+    pixels: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Frame<'a> {
+    pub fn new(pixels: &'a mut [u8], width: usize, height: usize) -> Self {
+        assert_eq!(pixels.len(), width * height * 4, "frame size mismatch");
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    pub fn clear(&mut self, color: Rgb) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color[0], color[1], color[2], 0xff]);
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, color: Rgb) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let offset = (y as usize * self.width + x as usize) * 4;
+        self.pixels[offset..offset + 4].copy_from_slice(&[color[0], color[1], color[2], 0xff]);
+    }
+
+    /// Fill the triangle `a b c`, clipped to the frame
+    pub fn fill_triangle(&mut self, a: Vector2D, b: Vector2D, c: Vector2D, color: Rgb) {
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as i32;
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(self.width as f32 - 1.0) as i32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as i32;
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(self.height as f32 - 1.0) as i32;
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let p = Vector2D::new(x as f32 + 0.5, y as f32 + 0.5);
+                // Inside when all edge functions share the winding's sign
+                let (w0, w1, w2) = (edge(b, c, p), edge(c, a, p), edge(a, b, p));
+                let inside = if area > 0.0 {
+                    w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0
+                } else {
+                    w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0
+                };
+                if inside {
+                    self.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fill the convex polygon through `corners`, as a fan of triangles
+    pub fn fill_polygon(&mut self, corners: &[Vector2D], color: Rgb) {
+        let Some((&first, rest)) = corners.split_first() else {
+            return;
+        };
+        for pair in rest.windows(2) {
+            self.fill_triangle(first, pair[0], pair[1], color);
+        }
+    }
+
+    /// One-pixel circle outline
+    pub fn circle(&mut self, center: Vector2D, radius: f32, color: Rgb) {
+        let steps = (radius * 8.0).max(8.0) as u32;
+        for step in 0..steps {
+            let angle = step as f32 / steps as f32 * std::f32::consts::TAU;
+            let x = center.x + radius * angle.cos();
+            let y = center.y + radius * angle.sin();
+            self.set(x.floor() as i32, y.floor() as i32, color);
+        }
+    }
+
+    /// A triangle pointing along the boid's heading
+    pub fn boid(&mut self, boid: &Boid, color: Rgb) {
+        let heading = if boid.velocity.magnitude() > 0.0 {
+            boid.velocity.normalize()
+        } else {
+            Vector2D::new(1.0, 0.0)
+        };
+        let side = Vector2D::new(-heading.y, heading.x);
+        let nose = boid.position + heading * BOID_SIZE;
+        let tail = boid.position - heading * (BOID_SIZE / 2.0);
+        self.fill_triangle(
+            nose,
+            tail + side * (BOID_SIZE / 2.0),
+            tail - side * (BOID_SIZE / 2.0),
+            color,
+        );
+    }
+
+    /// Crosshair ring where the flock is being steered
+    pub fn target(&mut self, target: Vector2D) {
+        self.circle(target, 12.0, TARGET);
+        self.circle(target, 3.0, TARGET);
+    }
+}
+
+/// Twice the signed area of `a b p`
+fn edge(a: Vector2D, b: Vector2D, p: Vector2D) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Why a recording failed
+#[derive(Debug)]
+pub enum RecordError {
+    /// GIF frames are limited to 65535 pixels a side, and empty frames
+    /// cannot be encoded at all
+    Size {
+        width: usize,
+        height: usize,
+    },
+    Io(io::Error),
+    Gif(gif::EncodingError),
+    Png(png::EncodingError),
+}
+
+impl core::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Size { width, height } => {
+                write!(f, "cannot record a {}x{} frame", width, height)
+            }
+            Self::Io(e) => write!(f, "recording failed: {}", e),
+            Self::Gif(e) => write!(f, "GIF encoding failed: {}", e),
+            Self::Png(e) => write!(f, "PNG encoding failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<gif::EncodingError> for RecordError {
+    fn from(e: gif::EncodingError) -> Self {
+        Self::Gif(e)
+    }
+}
+
+impl From<png::EncodingError> for RecordError {
+    fn from(e: png::EncodingError) -> Self {
+        Self::Png(e)
+    }
+}
+
+/// Steps a flock and captures its frames
+///
+/// ```no_run
+/// use boid_core::{record::Recorder, FlockStd, Vector2D};
+///
+/// let mut flock = FlockStd::new_with_seed(320.0, 240.0, 60, 42);
+/// let recorder = Recorder::new(120).with_target(|tick| {
+///     (tick > 60).then(|| Vector2D::new(160.0, 120.0))
+/// });
+/// let file = std::fs::File::create("flock.gif").unwrap();
+/// recorder.write_gif(&mut flock, file).unwrap();
+/// ```
+pub struct Recorder {
+    /// Frames to capture
+    pub frames: u32,
+    /// Simulation ticks per captured frame
+    pub ticks_per_frame: u32,
+    /// GIF delay between frames, in hundredths of a second
+    pub frame_delay: u16,
+    /// Target on each tick, counted from the start of the recording
+    target: Box<dyn Fn(u32) -> Option<Vector2D>>,
+}
+
+impl Recorder {
+    /// Record `frames` frames, one per tick, at about 30 frames per second
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames,
+            ticks_per_frame: 1,
+            frame_delay: 3,
+            target: Box::new(|_| None),
+        }
+    }
+
+    /// Steer the flock toward `target(tick)` while recording
+    pub fn with_target(mut self, target: impl Fn(u32) -> Option<Vector2D> + 'static) -> Self {
+        self.target = Box::new(target);
+        self
+    }
+
+    /// Draw the flock into an RGBA buffer of its size, rounded up
+    pub fn render(flock: &FlockStd, target: Option<Vector2D>, rgba: &mut [u8]) {
+        let (width, height) = frame_size(flock);
+        let mut frame = Frame::new(rgba, width, height);
+        frame.clear(BACKGROUND);
+        for boid in &flock.boids {
+            let color = if boid.puppet {
+                PUPPET
+            } else {
+                GROUP_COLORS[boid.group as usize % GROUP_COLORS.len()]
+            };
+            frame.boid(boid, color);
+        }
+        if let Some(target) = target {
+            frame.target(target);
+        }
+    }
+
+    /// Step `flock` and pass every captured RGBA frame to `sink`, with its
+    /// index
+    pub fn record(
+        &self,
+        flock: &mut FlockStd,
+        mut sink: impl FnMut(u32, &[u8]) -> Result<(), RecordError>,
+    ) -> Result<(), RecordError> {
+        let (width, height) = frame_size(flock);
+        if width == 0 || height == 0 {
+            return Err(RecordError::Size { width, height });
+        }
+        let mut rgba = vec![0; width * height * 4];
+        let mut tick = 0;
+        for index in 0..self.frames {
+            let mut target = None;
+            for _ in 0..self.ticks_per_frame.max(1) {
+                target = (self.target)(tick);
+                flock.update_with_target(target);
+                tick += 1;
+            }
+            Self::render(flock, target, &mut rgba);
+            sink(index, &rgba)?;
+        }
+        Ok(())
+    }
+
+    /// Record an animated GIF that loops forever
+    pub fn write_gif<W: Write>(&self, flock: &mut FlockStd, writer: W) -> Result<(), RecordError> {
+        let (width, height) = frame_size(flock);
+        let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(RecordError::Size { width, height });
+        };
+        // Every color the recorder draws, so frames index it exactly
+        let colors: Vec<Rgb> = [BACKGROUND, PUPPET, TARGET]
+            .into_iter()
+            .chain(GROUP_COLORS)
+            .collect();
+        let palette: Vec<u8> = colors.iter().flatten().copied().collect();
+
+        let mut encoder = gif::Encoder::new(writer, gif_width, gif_height, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        self.record(flock, |_, rgba| {
+            let indices: Vec<u8> = rgba
+                .chunks_exact(4)
+                .map(|pixel| {
+                    let index = colors.iter().position(|color| color[..] == pixel[..3]);
+                    index.unwrap_or(0) as u8
+                })
+                .collect();
+            let mut frame = gif::Frame::from_indexed_pixels(gif_width, gif_height, indices, None);
+            frame.delay = self.frame_delay;
+            encoder.write_frame(&frame)?;
+            Ok(())
+        })
+    }
+
+    /// Record one PNG per frame into `dir`, named `frame_0000.png` and so
+    /// on; returns the paths in order
+    pub fn write_png_sequence(
+        &self,
+        flock: &mut FlockStd,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>, RecordError> {
+        let (width, height) = frame_size(flock);
+        std::fs::create_dir_all(dir)?;
+        let mut paths = Vec::with_capacity(self.frames as usize);
+        self.record(flock, |index, rgba| {
+            let path = dir.join(format!("frame_{:04}.png", index));
+            let mut encoder = png::Encoder::new(
+                BufWriter::new(File::create(&path)?),
+                width as u32,
+                height as u32,
+            );
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(rgba)?;
+            paths.push(path);
+            Ok(())
+        })?;
+        Ok(paths)
+    }
+}
+
+/// Frame size for a flock: its area in whole pixels
+fn frame_size(flock: &FlockStd) -> (usize, usize) {
+    (
+        flock.width.max(0.0).ceil() as usize,
+        flock.height.max(0.0).ceil() as usize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(buffer: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * width + x) * 4;
+        buffer[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_boid_is_drawn_along_its_heading() {
+        let mut buffer = vec![0; 40 * 40 * 4];
+        let mut frame = Frame::new(&mut buffer, 40, 40);
+        frame.clear(BACKGROUND);
+        let boid = Boid::new(Vector2D::new(20.0, 20.0), Vector2D::new(0.0, 1.0));
+        frame.boid(&boid, [255, 0, 0]);
+
+        // Nose points down: filled below the center, empty above the tail
+        assert_eq!(pixel(&buffer, 40, 20, 24), [255, 0, 0, 255]);
+        assert_eq!(pixel(&buffer, 40, 20, 12), [10, 14, 39, 255]);
+    }
+
+    #[test]
+    fn test_shapes_are_clipped_to_the_frame() {
+        let mut buffer = vec![0; 10 * 10 * 4];
+        let mut frame = Frame::new(&mut buffer, 10, 10);
+        frame.fill_triangle(
+            Vector2D::new(-20.0, -20.0),
+            Vector2D::new(30.0, -20.0),
+            Vector2D::new(-20.0, 30.0),
+            [1, 2, 3],
+        );
+        // The outer ring lies entirely outside
+        frame.target(Vector2D::new(5.0, 5.0));
+        assert_eq!(pixel(&buffer, 10, 0, 0), [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn test_seeded_recordings_are_identical() {
+        let record = || {
+            let mut flock = FlockStd::new_with_seed(64.0, 48.0, 12, 3);
+            let mut gif = Vec::new();
+            Recorder::new(10)
+                .with_target(|tick| (tick > 4).then(|| Vector2D::new(32.0, 24.0)))
+                .write_gif(&mut flock, &mut gif)
+                .unwrap();
+            gif
+        };
+        let gif = record();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif, record());
+
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..]).unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!((decoder.width(), decoder.height(), frames), (64, 48, 10));
+    }
+
+    #[test]
+    fn test_timeline_ticks_match_the_recording() {
+        use crate::timeline::{Marker, TimelineConfig};
+
+        let mut flock = FlockStd::new_with_seed(64.0, 48.0, 12, 3);
+        flock.set_timeline(Some(TimelineConfig::default()));
+        let mut recorder = Recorder::new(5)
+            .with_target(|tick| (4..7).contains(&tick).then(|| Vector2D::new(32.0, 24.0)));
+        recorder.ticks_per_frame = 2;
+        recorder.write_gif(&mut flock, io::sink()).unwrap();
+
+        let timeline = flock.timeline().unwrap();
+        let ticks: Vec<_> = timeline
+            .entries()
+            .filter(|entry| {
+                !matches!(
+                    entry.marker,
+                    Marker::ClusterSplit { .. } | Marker::ClusterMerge { .. }
+                )
+            })
+            .map(|entry| entry.tick)
+            .collect();
+        assert_eq!(ticks, [4, 7]);
+        assert_eq!(timeline.ticks(), 10);
+    }
+
+    #[test]
+    fn test_png_sequence() {
+        let dir = std::env::temp_dir().join(format!("boid-record-{}", std::process::id()));
+        let mut flock = FlockStd::new_with_seed(30.5, 20.0, 5, 1);
+        let mut recorder = Recorder::new(3);
+        recorder.ticks_per_frame = 2;
+        let paths = recorder.write_png_sequence(&mut flock, &dir).unwrap();
+
+        assert_eq!(paths.len(), 3);
+        assert!(paths[2].ends_with("frame_0002.png"));
+        let decoder = png::Decoder::new(File::open(&paths[0]).unwrap());
+        let info = decoder.read_info().unwrap().info().clone();
+        assert_eq!((info.width, info.height), (31, 20));
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let mut empty = FlockStd::new(0.0, 10.0, 0);
+        assert!(matches!(
+            recorder.write_gif(&mut empty, io::sink()),
+            Err(RecordError::Size { .. })
+        ));
+    }
+}
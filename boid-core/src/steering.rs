@@ -0,0 +1,386 @@
+//! Pluggable steering behaviors
+//!
+//! Every force acting on a boid comes from a [`SteeringBehavior`]. `FlockStd`
+//! sums the behaviors in its [`BehaviorPipeline`], which starts out as the
+//! classic separation, alignment, cohesion, seek and wander, plus flee and
+//! arrive for the other target modes; register your own (orbit, patrol,
+//! [`FollowPath`], ...) next to them or instead of them.
+//! Without `std`, pass a fixed-capacity [`BehaviorList`] to
+//! `Flock::update_with_behaviors`.
+//!
+//! Behaviors run on every tick, so this module must not allocate; only
+//! building a `BehaviorPipeline` may.
+#![deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
+
+use crate::{behavior, Boid, BoidConfig, Path, TargetMode, Vector2D};
+
+/// A force acting on one boid
+pub trait SteeringBehavior {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D;
+}
+
+/// Closures work as behaviors
+impl<F> SteeringBehavior for F
+where
+    F: Fn(&Boid, &FlockContext<'_>) -> Vector2D,
+{
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        self(boid, ctx)
+    }
+}
+
+/// What a behavior sees of the flock while steering one boid
+#[derive(Debug, Clone, Copy)]
+pub struct FlockContext<'a> {
+    /// Configuration in effect this tick, modifiers included
+    pub config: &'a BoidConfig,
+    /// Target in effect for this boid, after its target override
+    pub target: Option<Vector2D>,
+    pub width: f32,
+    pub height: f32,
+    boids: &'a [Boid],
+    /// Indices into `boids`; `None` means every boid counts
+    neighbor_indices: Option<&'a [usize]>,
+}
+
+impl<'a> FlockContext<'a> {
+    /// Context in which every boid is a neighbor
+    pub fn new(
+        boids: &'a [Boid],
+        config: &'a BoidConfig,
+        target: Option<Vector2D>,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        Self {
+            config,
+            target,
+            width,
+            height,
+            boids,
+            neighbor_indices: None,
+        }
+    }
+
+    /// Restrict the neighbors to these indices into the flock
+    pub fn with_neighbors(self, indices: &'a [usize]) -> Self {
+        Self {
+            neighbor_indices: Some(indices),
+            ..self
+        }
+    }
+
+    /// The whole flock
+    pub fn boids(&self) -> &'a [Boid] {
+        self.boids
+    }
+
+    /// Boids within the largest behavior radius, possibly including the
+    /// steered boid itself (at distance 0)
+    pub fn neighbors(&self) -> Neighbors<'a> {
+        Neighbors {
+            boids: self.boids,
+            indices: self.neighbor_indices.map(|indices| indices.iter()),
+            next: 0,
+        }
+    }
+
+    /// Neighbors in the same group as `boid`, see [`Boid::group`]
+    pub fn flockmates<'b>(&self, boid: &'b Boid) -> impl Iterator<Item = &'a Boid> + 'b
+    where
+        'a: 'b,
+    {
+        self.neighbors()
+            .filter(move |other| boid.is_flockmate(other))
+    }
+}
+
+/// Iterator over the neighbors in a [`FlockContext`]
+#[derive(Debug, Clone)]
+pub struct Neighbors<'a> {
+    boids: &'a [Boid],
+    indices: Option<core::slice::Iter<'a, usize>>,
+    next: usize,
+}
+
+impl<'a> Iterator for Neighbors<'a> {
+    type Item = &'a Boid;
+
+    fn next(&mut self) -> Option<&'a Boid> {
+        match self.indices {
+            Some(ref mut indices) => indices.next().map(|&i| &self.boids[i]),
+            None => {
+                let boid = self.boids.get(self.next);
+                self.next += 1;
+                boid
+            }
+        }
+    }
+}
+
+/// Steer away from crowded neighbors, scaled by `separation_weight`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Separation;
+
+impl SteeringBehavior for Separation {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        behavior::separation(boid, ctx.neighbors(), ctx.config) * ctx.config.separation_weight
+    }
+}
+
+/// Match the flockmates' heading, scaled by `alignment_weight`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Alignment;
+
+impl SteeringBehavior for Alignment {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        behavior::alignment(boid, ctx.flockmates(boid), ctx.config) * ctx.config.alignment_weight
+    }
+}
+
+/// Steer toward the flockmates' center, scaled by `cohesion_weight`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cohesion;
+
+impl SteeringBehavior for Cohesion {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        behavior::cohesion(boid, ctx.flockmates(boid), ctx.config) * ctx.config.cohesion_weight
+    }
+}
+
+/// Seek the target in `TargetMode::Seek`, see [`behavior::weighted_seek`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Seek;
+
+impl SteeringBehavior for Seek {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        match ctx.target {
+            Some(target) if ctx.config.target_mode == TargetMode::Seek => {
+                behavior::weighted_seek(boid, target, ctx.config)
+            }
+            _ => Vector2D::zero(),
+        }
+    }
+}
+
+/// Steer away from the target in `TargetMode::Flee`, see [`behavior::flee`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Flee;
+
+impl SteeringBehavior for Flee {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        match ctx.target {
+            Some(threat) if ctx.config.target_mode == TargetMode::Flee => {
+                behavior::flee(boid, threat, ctx.config)
+            }
+            _ => Vector2D::zero(),
+        }
+    }
+}
+
+/// Arrive at the target in `TargetMode::Arrive`, see
+/// [`behavior::weighted_arrive`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Arrive;
+
+impl SteeringBehavior for Arrive {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        match ctx.target {
+            Some(target) if ctx.config.target_mode == TargetMode::Arrive => {
+                behavior::weighted_arrive(boid, target, ctx.config)
+            }
+            _ => Vector2D::zero(),
+        }
+    }
+}
+
+/// Fly along a path, see [`behavior::follow_path`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FollowPath(pub Path);
+
+impl SteeringBehavior for FollowPath {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        behavior::follow_path(boid, &self.0, ctx.config)
+    }
+}
+
+/// Push along the boid's wander angle while wander is enabled, see
+/// [`behavior::wander`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wander;
+
+impl SteeringBehavior for Wander {
+    fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        if !ctx.config.wander_enabled {
+            return Vector2D::zero();
+        }
+        behavior::wander(boid, ctx.config)
+    }
+}
+
+/// Fixed-capacity weighted behavior list for `Flock::update_with_behaviors`
+pub struct BehaviorList<'a, const N: usize> {
+    entries: heapless::Vec<(&'a dyn SteeringBehavior, f32), N>,
+}
+
+impl<'a, const N: usize> BehaviorList<'a, N> {
+    // Built once by the caller and passed to every tick
+    #[allow(clippy::disallowed_methods)]
+    pub fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    /// Add a behavior; hands it back if the list is full
+    pub fn push(
+        &mut self,
+        behavior: &'a dyn SteeringBehavior,
+        weight: f32,
+    ) -> Result<(), &'a dyn SteeringBehavior> {
+        self.entries
+            .push((behavior, weight))
+            .map_err(|(behavior, _)| behavior)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Weighted sum of all behaviors
+    pub fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        self.entries
+            .iter()
+            .fold(Vector2D::zero(), |force, (behavior, weight)| {
+                force + behavior.steer(boid, ctx) * *weight
+            })
+    }
+}
+
+impl<const N: usize> Default for BehaviorList<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::disallowed_types, clippy::disallowed_macros)]
+struct WeightedBehavior {
+    name: String,
+    behavior: Box<dyn SteeringBehavior + Send + Sync>,
+    weight: f32,
+}
+
+/// Named, weighted behaviors summed into each boid's steering force
+#[cfg(feature = "std")]
+#[allow(clippy::disallowed_types, clippy::disallowed_macros)]
+pub struct BehaviorPipeline {
+    entries: Vec<WeightedBehavior>,
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::disallowed_types, clippy::disallowed_macros)]
+impl BehaviorPipeline {
+    pub fn empty() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// `"separation"`, `"alignment"`, `"cohesion"`, `"seek"`, `"flee"`,
+    /// `"arrive"` and `"wander"`, each at weight 1; the config's own weights
+    /// still apply on top
+    pub fn standard() -> Self {
+        let mut pipeline = Self::empty();
+        pipeline.add("separation", Separation, 1.0);
+        pipeline.add("alignment", Alignment, 1.0);
+        pipeline.add("cohesion", Cohesion, 1.0);
+        pipeline.add("seek", Seek, 1.0);
+        pipeline.add("flee", Flee, 1.0);
+        pipeline.add("arrive", Arrive, 1.0);
+        pipeline.add("wander", Wander, 1.0);
+        pipeline
+    }
+
+    /// Append a behavior, or replace the one already registered as `name`
+    /// in place
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        behavior: impl SteeringBehavior + Send + Sync + 'static,
+        weight: f32,
+    ) {
+        let entry = WeightedBehavior {
+            name: name.into(),
+            behavior: Box::new(behavior),
+            weight,
+        };
+        match self.entries.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.len() != before
+    }
+
+    pub fn weight(&self, name: &str) -> Option<f32> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.weight)
+    }
+
+    /// Returns false if no behavior is registered as `name`
+    pub fn set_weight(&mut self, name: &str, weight: f32) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.weight = weight;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registered names, in evaluation order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Weighted sum of all behaviors
+    #[deny(clippy::disallowed_types, clippy::disallowed_macros)]
+    pub fn steer(&self, boid: &Boid, ctx: &FlockContext<'_>) -> Vector2D {
+        self.entries.iter().fold(Vector2D::zero(), |force, entry| {
+            force + entry.behavior.steer(boid, ctx) * entry.weight
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::disallowed_types, clippy::disallowed_macros)]
+impl Default for BehaviorPipeline {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::disallowed_types, clippy::disallowed_macros)]
+impl core::fmt::Debug for BehaviorPipeline {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|e| (&e.name, e.weight)))
+            .finish()
+    }
+}
@@ -0,0 +1,296 @@
+//! One entry point for advancing a flock: [`FlockStd::step`](crate::FlockStd::step)
+//! and [`Flock::step`](crate::Flock::step) take a [`StepInput`] and return
+//! a [`StepOutput`]
+//!
+//! The input carries everything that drives a tick from outside: elapsed
+//! time, targets and one-off stimuli. The output reports what happened:
+//! population events, summary stats and, when asked for, phase timings.
+//! The `update*` methods are shorthands for common inputs.
+//!
+//! ```
+//! use boid_core::step::{StepInput, Stimulus};
+//! use boid_core::{FlockStd, Vector2D};
+//!
+//! let mut flock = FlockStd::new(800.0, 600.0, 50);
+//! let targets = [Vector2D::new(400.0, 300.0)];
+//! let stimuli = [Stimulus::Force(Vector2D::new(0.0, 0.02))];
+//! let output = flock.step(
+//!     StepInput::new()
+//!         .with_dt(1.0 / 30.0)
+//!         .with_targets(&targets)
+//!         .with_stimuli(&stimuli),
+//! );
+//! assert_eq!(output.stats.boids, 50);
+//! ```
+
+use crate::{behavior, Boid, BoidConfig, TickTiming, Vector2D};
+
+/// Time one plain `update` advances, in seconds
+pub const REFERENCE_DT: f32 = 1.0 / 60.0;
+
+/// Longest step, in `REFERENCE_DT`s. Longer gaps, such as a backgrounded
+/// browser tab, are cut short so boids do not jump across the area.
+pub const MAX_DT_SCALE: f32 = 4.0;
+
+/// Most events one step reports
+pub const MAX_EVENTS: usize = 8;
+
+/// Outside influence on the flock for a single step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stimulus {
+    /// Push every boid outside a formation, bypassing `max_force`, e.g.
+    /// wind or a tilted device
+    Force(Vector2D),
+    /// Boids within `radius` of `position` flee from it, harder the closer
+    /// they are, e.g. a tap or a passing predator
+    Repel { position: Vector2D, radius: f32 },
+}
+
+/// Something that happened to the population during a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepEvent {
+    /// Boids dropped, oldest first, to get under the population limit
+    Trimmed(usize),
+    /// Boids `add_boid` or `spawn` refused since the previous step (or
+    /// `try_update`)
+    Rejected(usize),
+    /// Boids `spawn` respawned to make room since the previous step
+    Recycled(usize),
+    /// Boids respawned at random to keep `min_population`
+    Respawned(usize),
+    /// A preset crossfade reached its target config
+    TransitionFinished,
+    /// Offspring born in ecological mode
+    Born(usize),
+    /// Boids that died of age or exhaustion in ecological mode
+    Died(usize),
+}
+
+/// What drives one step
+pub struct StepInput<'a> {
+    /// Seconds to advance; motion scales with `dt / REFERENCE_DT`, at most
+    /// `MAX_DT_SCALE`
+    pub dt: f32,
+    /// Each boid seeks (or flees, see `TargetMode`) the nearest of these
+    pub targets: &'a [Vector2D],
+    pub stimuli: &'a [Stimulus],
+    /// Millisecond clock; when set, `FlockStd` times each phase of the
+    /// step into `StepOutput::debug`
+    pub clock: Option<&'a mut dyn FnMut() -> f64>,
+}
+
+impl<'a> StepInput<'a> {
+    /// One `REFERENCE_DT` with no targets or stimuli
+    pub fn new() -> Self {
+        Self {
+            dt: REFERENCE_DT,
+            targets: &[],
+            stimuli: &[],
+            clock: None,
+        }
+    }
+
+    pub fn with_dt(mut self, dt: f32) -> Self {
+        self.dt = dt;
+        self
+    }
+
+    pub fn with_targets(mut self, targets: &'a [Vector2D]) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    pub fn with_stimuli(mut self, stimuli: &'a [Stimulus]) -> Self {
+        self.stimuli = stimuli;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: &'a mut dyn FnMut() -> f64) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Motion this step, in plain updates; 0 for a negative or NaN `dt`
+    pub fn scale(&self) -> f32 {
+        if self.dt > 0.0 {
+            (self.dt / REFERENCE_DT).min(MAX_DT_SCALE)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for StepInput<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summary of the flock after a step
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StepStats {
+    pub boids: usize,
+    /// Most boids the flock holds, if it is capped
+    pub capacity: Option<usize>,
+    pub mean_speed: f32,
+    /// Plain updates' worth of motion applied, see `StepInput::scale`
+    pub scale: f32,
+}
+
+/// What one step did
+#[derive(Debug, Clone, Default)]
+pub struct StepOutput {
+    pub events: heapless::Vec<StepEvent, MAX_EVENTS>,
+    pub stats: StepStats,
+    /// Phase timings, when the input had a clock and the flock measures them
+    pub debug: Option<TickTiming>,
+}
+
+impl StepOutput {
+    /// Record `event` unless it is an empty count
+    pub(crate) fn push(&mut self, event: StepEvent) {
+        let empty = matches!(
+            event,
+            StepEvent::Trimmed(0)
+                | StepEvent::Rejected(0)
+                | StepEvent::Recycled(0)
+                | StepEvent::Respawned(0)
+                | StepEvent::Born(0)
+                | StepEvent::Died(0)
+        );
+        if !empty {
+            // There are fewer kinds of event than slots
+            let _ = self.events.push(event);
+        }
+    }
+
+    pub(crate) fn with_stats(
+        mut self,
+        boids: &[Boid],
+        capacity: Option<usize>,
+        scale: f32,
+    ) -> Self {
+        let total: f32 = boids.iter().map(|boid| boid.velocity.magnitude()).sum();
+        self.stats = StepStats {
+            boids: boids.len(),
+            capacity,
+            mean_speed: if boids.is_empty() {
+                0.0
+            } else {
+                total / boids.len() as f32
+            },
+            scale,
+        };
+        self
+    }
+}
+
+/// Sum of the pushes `stimuli` give `boid`
+#[deny(
+    clippy::disallowed_types,
+    clippy::disallowed_macros,
+    clippy::disallowed_methods
+)]
+pub(crate) fn stimulus_force(boid: &Boid, stimuli: &[Stimulus], config: &BoidConfig) -> Vector2D {
+    stimuli
+        .iter()
+        .fold(Vector2D::zero(), |sum, stimulus| match *stimulus {
+            Stimulus::Force(force) => sum + force,
+            Stimulus::Repel { position, radius } => {
+                let distance = boid.position.distance(&position);
+                if distance >= radius {
+                    return sum;
+                }
+                let away = boid.position + (boid.position - position);
+                sum + behavior::seek(boid, away, config) * (1.0 - distance / radius)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Flock, FlockStd, PopulationLimit};
+
+    fn still_config() -> BoidConfig {
+        BoidConfig {
+            separation_weight: 0.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+            ..BoidConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_dt_scales_motion() {
+        let boid = Boid::new(Vector2D::new(100.0, 100.0), Vector2D::new(1.0, 0.0));
+        let mut flock = Flock::<4>::new(400.0, 400.0, still_config());
+        flock.add_boid(boid.clone()).unwrap();
+        let mut twice = Flock::<4>::new(400.0, 400.0, still_config());
+        twice.add_boid(boid).unwrap();
+
+        flock.update();
+        twice.step(StepInput::new().with_dt(2.0 * REFERENCE_DT));
+        assert_eq!(flock.boids[0].position, Vector2D::new(101.0, 100.0));
+        assert!((twice.boids[0].position.x - 102.0).abs() < 1e-4);
+
+        // A long pause does not teleport the flock
+        let output = twice.step(StepInput::new().with_dt(10.0));
+        assert_eq!(output.stats.scale, MAX_DT_SCALE);
+        assert_eq!(StepInput::new().with_dt(f32::NAN).scale(), 0.0);
+    }
+
+    #[test]
+    fn test_step_reports_events_and_stats() {
+        let mut flock = FlockStd::new_with_seed(400.0, 400.0, 6, 7);
+        flock.population = Some(PopulationLimit::new(4));
+        flock.apply_preset_over(still_config(), 1);
+        let output = flock.step(StepInput::new());
+        assert_eq!(
+            output.events.as_slice(),
+            [StepEvent::Trimmed(2), StepEvent::TransitionFinished]
+        );
+        assert_eq!(output.stats.boids, 4);
+        assert_eq!(output.stats.capacity, Some(4));
+        assert!(output.stats.mean_speed > 0.0);
+        assert!(output.debug.is_none());
+
+        flock.boids.clear();
+        flock.min_population = 3;
+        let mut ticks = 0.0;
+        let mut clock = || {
+            ticks += 1.0;
+            ticks
+        };
+        let output = flock.step(StepInput::new().with_clock(&mut clock));
+        assert_eq!(output.events.as_slice(), [StepEvent::Respawned(3)]);
+        assert_eq!(output.debug.unwrap().total_ms(), 3.0);
+
+        let mut fixed = Flock::<2>::new(400.0, 400.0, still_config());
+        for _ in 0..3 {
+            let _ = fixed.add_boid(Boid::new(Vector2D::zero(), Vector2D::zero()));
+        }
+        let output = fixed.step(StepInput::new());
+        assert_eq!(output.events.as_slice(), [StepEvent::Rejected(1)]);
+        assert!(fixed.step(StepInput::new()).events.is_empty());
+    }
+
+    #[test]
+    fn test_stimuli_push_boids() {
+        let mut flock = FlockStd::new_with_config(400.0, 400.0, 0, still_config());
+        flock.add_boid(Boid::new(Vector2D::new(200.0, 200.0), Vector2D::zero()));
+        flock.add_boid(Boid::new(Vector2D::new(50.0, 50.0), Vector2D::zero()));
+
+        let stimuli = [Stimulus::Repel {
+            position: Vector2D::new(190.0, 200.0),
+            radius: 40.0,
+        }];
+        flock.step(StepInput::new().with_stimuli(&stimuli));
+        assert!(flock.boids[0].velocity.x > 0.0);
+        assert_eq!(flock.boids[1].velocity, Vector2D::zero());
+
+        let wind = [Stimulus::Force(Vector2D::new(0.0, 0.5))];
+        flock.step(StepInput::new().with_stimuli(&wind));
+        assert!(flock.boids[1].velocity.y > 0.4);
+    }
+}
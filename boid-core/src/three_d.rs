@@ -0,0 +1,427 @@
+//! Three-dimensional counterparts of the core types
+//!
+//! `Vector3D`, `Boid3D` and `Flock3D` mirror their 2D versions and share
+//! `BoidConfig`, so the same tuning works for a 3D visualization. Like
+//! `Flock`, `Flock3D` has a fixed capacity and works without `std`.
+
+use crate::BoidConfig;
+
+#[cfg(feature = "std")]
+use rand::Rng;
+
+fn sqrt(value: f32) -> f32 {
+    #[cfg(feature = "std")]
+    {
+        value.sqrt()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        libm::sqrtf(value)
+    }
+}
+
+/// A 3D vector used for position and velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vector3D {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vector3D {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
+    }
+
+    pub fn normalize(&self) -> Self {
+        let mag = self.magnitude();
+        if mag > 0.0 {
+            *self / mag
+        } else {
+            Self::zero()
+        }
+    }
+
+    pub fn limit(&self, max: f32) -> Self {
+        if self.magnitude() > max {
+            self.normalize() * max
+        } else {
+            *self
+        }
+    }
+
+    pub fn distance(&self, other: &Vector3D) -> f32 {
+        (*self - *other).magnitude()
+    }
+}
+
+impl core::ops::Add for Vector3D {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl core::ops::Sub for Vector3D {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl core::ops::Mul<f32> for Vector3D {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl core::ops::Div<f32> for Vector3D {
+    type Output = Self;
+
+    fn div(self, scalar: f32) -> Self {
+        Self::new(self.x / scalar, self.y / scalar, self.z / scalar)
+    }
+}
+
+impl core::ops::AddAssign for Vector3D {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+        self.z += other.z;
+    }
+}
+
+/// What happens when a boid reaches the edge of the simulation volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundsMode {
+    /// Reappear on the opposite face
+    Wrap,
+    /// Bounce back inside, keeping a small margin from the faces
+    #[default]
+    Contain,
+}
+
+/// A single boid in three dimensions
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Boid3D {
+    pub position: Vector3D,
+    pub velocity: Vector3D,
+    pub acceleration: Vector3D,
+}
+
+impl Boid3D {
+    pub fn new(position: Vector3D, velocity: Vector3D) -> Self {
+        Self {
+            position,
+            velocity,
+            acceleration: Vector3D::zero(),
+        }
+    }
+
+    /// Random boid inside the box from the origin to `bounds`
+    #[cfg(feature = "std")]
+    pub fn random(bounds: Vector3D) -> Self {
+        let mut rng = rand::thread_rng();
+        let position = Vector3D::new(
+            rng.gen_range(0.0..bounds.x),
+            rng.gen_range(0.0..bounds.y),
+            rng.gen_range(0.0..bounds.z),
+        );
+        let velocity = Vector3D::new(
+            rng.gen_range(-2.0..2.0),
+            rng.gen_range(-2.0..2.0),
+            rng.gen_range(-2.0..2.0),
+        );
+        Self::new(position, velocity)
+    }
+
+    pub fn apply_force(&mut self, force: Vector3D) {
+        self.acceleration += force;
+    }
+
+    pub fn update(&mut self, max_speed: f32) {
+        self.velocity += self.acceleration;
+        self.velocity = self.velocity.limit(max_speed);
+        self.position += self.velocity;
+        self.acceleration = Vector3D::zero();
+    }
+
+    pub fn wrap_edges(&mut self, bounds: Vector3D) {
+        fn wrap(value: &mut f32, max: f32) {
+            if *value < 0.0 {
+                *value = max;
+            } else if *value > max {
+                *value = 0.0;
+            }
+        }
+
+        wrap(&mut self.position.x, bounds.x);
+        wrap(&mut self.position.y, bounds.y);
+        wrap(&mut self.position.z, bounds.z);
+    }
+
+    pub fn contain_within_bounds(&mut self, bounds: Vector3D) {
+        let margin = 10.0;
+
+        // Bounce off faces by reversing the velocity component
+        fn contain(position: &mut f32, velocity: &mut f32, max: f32, margin: f32) {
+            if *position < margin {
+                *position = margin;
+                *velocity = velocity.abs();
+            } else if *position > max - margin {
+                *position = max - margin;
+                *velocity = -velocity.abs();
+            }
+        }
+
+        contain(&mut self.position.x, &mut self.velocity.x, bounds.x, margin);
+        contain(&mut self.position.y, &mut self.velocity.y, bounds.y, margin);
+        contain(&mut self.position.z, &mut self.velocity.z, bounds.z, margin);
+    }
+}
+
+/// Flocking behaviors in three dimensions, see [`crate::behavior`]
+pub mod behavior {
+    use super::*;
+
+    pub fn separation<'a, I>(boid: &Boid3D, others: I, config: &BoidConfig) -> Vector3D
+    where
+        I: Iterator<Item = &'a Boid3D>,
+    {
+        let mut steering = Vector3D::zero();
+        let mut count = 0;
+
+        for other in others {
+            let distance = boid.position.distance(&other.position);
+            if distance > 0.0 && distance < config.separation_distance {
+                let diff = (boid.position - other.position).normalize() / distance;
+                steering += diff;
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            steering = steering / count as f32;
+        }
+
+        if steering.magnitude() > 0.0 {
+            steering = steering.normalize() * config.max_speed - boid.velocity;
+            steering = steering.limit(config.max_force);
+        }
+
+        steering
+    }
+
+    pub fn alignment<'a, I>(boid: &Boid3D, others: I, config: &BoidConfig) -> Vector3D
+    where
+        I: Iterator<Item = &'a Boid3D>,
+    {
+        let mut sum = Vector3D::zero();
+        let mut count = 0;
+
+        for other in others {
+            let distance = boid.position.distance(&other.position);
+            if distance > 0.0 && distance < config.alignment_distance {
+                sum += other.velocity;
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            let desired = (sum / count as f32).normalize() * config.max_speed;
+            (desired - boid.velocity).limit(config.max_force)
+        } else {
+            Vector3D::zero()
+        }
+    }
+
+    pub fn cohesion<'a, I>(boid: &Boid3D, others: I, config: &BoidConfig) -> Vector3D
+    where
+        I: Iterator<Item = &'a Boid3D>,
+    {
+        let mut sum = Vector3D::zero();
+        let mut count = 0;
+
+        for other in others {
+            let distance = boid.position.distance(&other.position);
+            if distance > 0.0 && distance < config.cohesion_distance {
+                sum += other.position;
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            seek(boid, sum / count as f32, config)
+        } else {
+            Vector3D::zero()
+        }
+    }
+
+    pub fn seek(boid: &Boid3D, target: Vector3D, config: &BoidConfig) -> Vector3D {
+        let desired = (target - boid.position).normalize() * config.max_speed;
+        (desired - boid.velocity).limit(config.max_force)
+    }
+}
+
+/// A fixed-capacity flock in a box from the origin to `bounds`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flock3D<const N: usize> {
+    pub boids: heapless::Vec<Boid3D, N>,
+    pub config: BoidConfig,
+    pub bounds: Vector3D,
+    pub bounds_mode: BoundsMode,
+}
+
+impl<const N: usize> Flock3D<N> {
+    pub fn new(bounds: Vector3D, config: BoidConfig) -> Self {
+        Self {
+            boids: heapless::Vec::new(),
+            config,
+            bounds,
+            bounds_mode: BoundsMode::default(),
+        }
+    }
+
+    /// Fill the flock with up to `count` random boids
+    #[cfg(feature = "std")]
+    pub fn new_random(bounds: Vector3D, count: usize, config: BoidConfig) -> Self {
+        let mut flock = Self::new(bounds, config);
+        for _ in 0..count.min(N) {
+            let _ = flock.add_boid(Boid3D::random(bounds));
+        }
+        flock
+    }
+
+    pub fn add_boid(&mut self, boid: Boid3D) -> Result<(), Boid3D> {
+        self.boids.push(boid)
+    }
+
+    pub fn update(&mut self) {
+        self.update_with_target(None);
+    }
+
+    pub fn update_with_target(&mut self, target: Option<Vector3D>) {
+        // Calculate forces for all boids; `boids` never holds more than N
+        let mut forces = [Vector3D::zero(); N];
+
+        for (force, boid) in forces.iter_mut().zip(self.boids.iter()) {
+            let sep = behavior::separation(boid, self.boids.iter(), &self.config)
+                * self.config.separation_weight;
+            let ali = behavior::alignment(boid, self.boids.iter(), &self.config)
+                * self.config.alignment_weight;
+            let coh = behavior::cohesion(boid, self.boids.iter(), &self.config)
+                * self.config.cohesion_weight;
+            let seek = match target {
+                Some(target) => {
+                    let falloff = crate::behavior::seek_falloff(
+                        boid.position.distance(&target),
+                        &self.config,
+                    );
+                    behavior::seek(boid, target, &self.config) * (self.config.seek_weight * falloff)
+                }
+                None => Vector3D::zero(),
+            };
+            *force = sep + ali + coh + seek;
+        }
+
+        // Apply forces and update boids
+        for (boid, force) in self.boids.iter_mut().zip(forces.iter()) {
+            boid.apply_force(*force);
+            boid.update(self.config.max_speed);
+            match self.bounds_mode {
+                BoundsMode::Wrap => boid.wrap_edges(self.bounds),
+                BoundsMode::Contain => boid.contain_within_bounds(self.bounds),
+            }
+        }
+    }
+
+    pub fn resize(&mut self, bounds: Vector3D) {
+        self.bounds = bounds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector3d_operations() {
+        let v = Vector3D::new(2.0, 3.0, 6.0);
+        assert_eq!(v.magnitude(), 7.0);
+        assert!((v.normalize().magnitude() - 1.0).abs() < 1e-6);
+        assert!((v.limit(3.5).magnitude() - 3.5).abs() < 1e-5);
+        assert_eq!(v - v, Vector3D::zero());
+        assert_eq!(v.distance(&Vector3D::zero()), 7.0);
+    }
+
+    #[test]
+    fn test_boid3d_bounds() {
+        let bounds = Vector3D::new(100.0, 100.0, 100.0);
+
+        let mut boid = Boid3D::new(Vector3D::new(50.0, 50.0, 101.0), Vector3D::zero());
+        boid.wrap_edges(bounds);
+        assert_eq!(boid.position.z, 0.0);
+
+        let mut boid = Boid3D::new(
+            Vector3D::new(50.0, 50.0, -5.0),
+            Vector3D::new(0.0, 0.0, -1.0),
+        );
+        boid.contain_within_bounds(bounds);
+        assert_eq!(boid.position.z, 10.0);
+        assert_eq!(boid.velocity.z, 1.0);
+    }
+
+    #[test]
+    fn test_separation_in_depth() {
+        let config = BoidConfig::default();
+        let boid = Boid3D::new(Vector3D::new(50.0, 50.0, 50.0), Vector3D::zero());
+        let above = Boid3D::new(Vector3D::new(50.0, 50.0, 55.0), Vector3D::zero());
+
+        // Neighbor directly along z pushes only along -z
+        let force = behavior::separation(&boid, [above].iter(), &config);
+        assert!(force.z < 0.0);
+        assert_eq!(force.x, 0.0);
+        assert_eq!(force.y, 0.0);
+    }
+
+    #[test]
+    fn test_flock3d_seeks_target() {
+        let bounds = Vector3D::new(200.0, 200.0, 200.0);
+        let mut flock = Flock3D::<4>::new(bounds, BoidConfig::default());
+        flock
+            .add_boid(Boid3D::new(
+                Vector3D::new(50.0, 50.0, 50.0),
+                Vector3D::zero(),
+            ))
+            .unwrap();
+
+        let target = Vector3D::new(150.0, 150.0, 150.0);
+        let start = flock.boids[0].position.distance(&target);
+        for _ in 0..50 {
+            flock.update_with_target(Some(target));
+        }
+        assert!(flock.boids[0].position.distance(&target) < start);
+    }
+
+    #[test]
+    fn test_flock3d_capacity() {
+        let bounds = Vector3D::new(200.0, 200.0, 200.0);
+        let flock = Flock3D::<8>::new_random(bounds, 20, BoidConfig::default());
+        assert_eq!(flock.boids.len(), 8);
+    }
+}
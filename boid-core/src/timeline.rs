@@ -0,0 +1,398 @@
+//! A timeline of notable moments in a run, for annotating replays
+//!
+//! Trajectories show where every boid went; a [`Timeline`] says what
+//! happened along the way. Once `FlockStd::set_timeline` turns it on, every
+//! step is compared with the one before, and moments worth a marker are
+//! kept with the index of the step they happened in:
+//!
+//! - a target appearing or disappearing,
+//! - a preset being applied,
+//! - the flock splitting into more clusters, or clusters merging,
+//! - many boids appearing or vanishing within a short time.
+//!
+//! The [`Recorder`](crate::record::Recorder) steps the flock once per tick,
+//! so with a timeline on, the markers' ticks line up with its frames.
+//! Tick 0 is the first step after the timeline was turned on.
+
+use crate::{Boid, BoidConfig, Vector2D};
+use std::collections::VecDeque;
+
+/// What makes a moment worth a marker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineConfig {
+    /// Steps between cluster counts, which compare every pair of boids
+    pub cluster_interval: u32,
+    /// Clusters with fewer boids are stragglers and not counted
+    pub min_cluster_size: usize,
+    /// Boids that must appear, or vanish, within `burst_window` steps of
+    /// the first for a burst
+    pub burst_size: usize,
+    pub burst_window: u32,
+    /// Most markers kept; the oldest are dropped first
+    pub capacity: usize,
+}
+
+impl Default for TimelineConfig {
+    fn default() -> Self {
+        Self {
+            cluster_interval: 10,
+            min_cluster_size: 3,
+            burst_size: 5,
+            burst_window: 30,
+            capacity: 4096,
+        }
+    }
+}
+
+/// Something notable that happened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    /// A step had a target after one without; `position` is the first
+    TargetAcquired {
+        position: Vector2D,
+    },
+    TargetLost,
+    /// A crossfade toward a new preset started, or `config` was replaced
+    /// at once, by a preset without crossfade or a settings change
+    PresetApplied {
+        crossfade: bool,
+    },
+    /// The flock split into `clusters` clusters, more than before
+    ClusterSplit {
+        clusters: usize,
+    },
+    /// Clusters merged, leaving `clusters`
+    ClusterMerge {
+        clusters: usize,
+    },
+    /// The flock grew by `count` boids within the burst window
+    SpawnBurst {
+        count: usize,
+    },
+    /// The flock shrank by `count` boids within the burst window
+    DespawnBurst {
+        count: usize,
+    },
+}
+
+impl Marker {
+    /// `"target_acquired"`, `"cluster_split"` and so on
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TargetAcquired { .. } => "target_acquired",
+            Self::TargetLost => "target_lost",
+            Self::PresetApplied { .. } => "preset_applied",
+            Self::ClusterSplit { .. } => "cluster_split",
+            Self::ClusterMerge { .. } => "cluster_merge",
+            Self::SpawnBurst { .. } => "spawn_burst",
+            Self::DespawnBurst { .. } => "despawn_burst",
+        }
+    }
+}
+
+/// A marker and the step it belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineEntry {
+    pub tick: u64,
+    pub marker: Marker,
+}
+
+/// Boids that appeared or vanished since `start`
+#[derive(Debug, Clone, Copy)]
+struct Burst {
+    start: u64,
+    count: usize,
+}
+
+/// Markers of a run, ordered by tick; see the [module docs](self)
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub config: TimelineConfig,
+    entries: VecDeque<TimelineEntry>,
+    dropped: u64,
+    /// Index of the next step
+    tick: u64,
+    had_target: bool,
+    /// Config and crossfade target after the last step; `None` before the
+    /// first
+    last_config: Option<BoidConfig>,
+    last_crossfade: Option<BoidConfig>,
+    clusters: Option<usize>,
+    boids: Option<usize>,
+    spawned: Option<Burst>,
+    despawned: Option<Burst>,
+    visited: Vec<bool>,
+    stack: Vec<usize>,
+}
+
+impl Timeline {
+    pub fn new(config: TimelineConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            dropped: 0,
+            tick: 0,
+            had_target: false,
+            last_config: None,
+            last_crossfade: None,
+            clusters: None,
+            boids: None,
+            spawned: None,
+            despawned: None,
+            visited: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Every marker kept, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &TimelineEntry> {
+        self.entries.iter()
+    }
+
+    /// Markers of the steps `from..to`
+    pub fn between(&self, from: u64, to: u64) -> impl Iterator<Item = &TimelineEntry> {
+        let start = self.entries.partition_point(|entry| entry.tick < from);
+        let end = self.entries.partition_point(|entry| entry.tick < to);
+        self.entries.range(start..end.max(start))
+    }
+
+    /// Steps observed so far
+    pub fn ticks(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Markers lost to `capacity`
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Note what changed in the step that left `boids` and `config`, with
+    /// the crossfade still running toward `crossfade`
+    pub(crate) fn observe(
+        &mut self,
+        boids: &[Boid],
+        config: &BoidConfig,
+        crossfade: Option<&BoidConfig>,
+        targets: &[Vector2D],
+    ) {
+        let tick = self.tick;
+        self.tick += 1;
+
+        match (self.had_target, targets.first()) {
+            (false, Some(&position)) => self.push(tick, Marker::TargetAcquired { position }),
+            (true, None) => self.push(tick, Marker::TargetLost),
+            _ => {}
+        }
+        self.had_target = !targets.is_empty();
+
+        let crossfade = crossfade.copied();
+        let started = crossfade.is_some() && crossfade != self.last_crossfade;
+        // A finished crossfade leaves the config at its target, which is
+        // not a change of its own
+        let replaced = crossfade.is_none()
+            && self.last_crossfade.is_none()
+            && self.last_config.is_some_and(|last| last != *config);
+        if started || replaced {
+            self.push(tick, Marker::PresetApplied { crossfade: started });
+        }
+        self.last_config = Some(*config);
+        self.last_crossfade = crossfade;
+
+        if tick.is_multiple_of(self.config.cluster_interval.max(1) as u64) {
+            let count = self.count_clusters(boids, config.cohesion_distance);
+            match self.clusters {
+                Some(last) if count > last => {
+                    self.push(tick, Marker::ClusterSplit { clusters: count })
+                }
+                Some(last) if count < last => {
+                    self.push(tick, Marker::ClusterMerge { clusters: count })
+                }
+                _ => {}
+            }
+            self.clusters = Some(count);
+        }
+
+        let (last, now) = (self.boids.unwrap_or(boids.len()), boids.len());
+        self.boids = Some(now);
+        let (grown, shrunk) = (now.saturating_sub(last), last.saturating_sub(now));
+        let spawned = self.track_burst(self.spawned, grown, tick);
+        self.spawned = self.close_burst(spawned, tick, |count| Marker::SpawnBurst { count });
+        let despawned = self.track_burst(self.despawned, shrunk, tick);
+        self.despawned = self.close_burst(despawned, tick, |count| Marker::DespawnBurst { count });
+    }
+
+    /// Add `count` boids to the open window, opening one if needed
+    fn track_burst(&self, burst: Option<Burst>, count: usize, tick: u64) -> Option<Burst> {
+        match burst {
+            Some(burst) => Some(Burst {
+                count: burst.count + count,
+                ..burst
+            }),
+            None if count > 0 => Some(Burst { start: tick, count }),
+            None => None,
+        }
+    }
+
+    /// Report a window once it is over, at the step it opened in
+    fn close_burst(
+        &mut self,
+        burst: Option<Burst>,
+        tick: u64,
+        marker: impl Fn(usize) -> Marker,
+    ) -> Option<Burst> {
+        let burst = burst?;
+        if tick + 1 < burst.start + self.config.burst_window.max(1) as u64 {
+            return Some(burst);
+        }
+        if burst.count >= self.config.burst_size {
+            self.push(burst.start, marker(burst.count));
+        }
+        None
+    }
+
+    /// Insert in tick order; bursts arrive after later markers
+    fn push(&mut self, tick: u64, marker: Marker) {
+        let at = self.entries.partition_point(|entry| entry.tick <= tick);
+        self.entries.insert(at, TimelineEntry { tick, marker });
+        while self.entries.len() > self.config.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// Clusters of at least `min_cluster_size` boids, each a chain of
+    /// neighbors closer than `link_distance`
+    fn count_clusters(&mut self, boids: &[Boid], link_distance: f32) -> usize {
+        self.visited.clear();
+        self.visited.resize(boids.len(), false);
+        let mut count = 0;
+        for start in 0..boids.len() {
+            if self.visited[start] {
+                continue;
+            }
+            self.visited[start] = true;
+            self.stack.push(start);
+            let mut size = 0;
+            while let Some(index) = self.stack.pop() {
+                size += 1;
+                let position = boids[index].position;
+                for (other, boid) in boids.iter().enumerate() {
+                    if !self.visited[other] && boid.position.distance(&position) < link_distance {
+                        self.visited[other] = true;
+                        self.stack.push(other);
+                    }
+                }
+            }
+            if size >= self.config.min_cluster_size {
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(TimelineConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boids_at(xs: &[f32]) -> Vec<Boid> {
+        xs.iter()
+            .map(|&x| Boid::new(Vector2D::new(x, 0.0), Vector2D::zero()))
+            .collect()
+    }
+
+    #[test]
+    fn test_targets_presets_and_clusters() {
+        let mut timeline = Timeline::new(TimelineConfig {
+            cluster_interval: 1,
+            ..TimelineConfig::default()
+        });
+        let config = BoidConfig::default();
+        let together = boids_at(&[0.0, 10.0, 20.0, 30.0, 40.0, 50.0]);
+        let apart = boids_at(&[0.0, 10.0, 20.0, 200.0, 210.0, 220.0]);
+        let target = [Vector2D::new(5.0, 5.0)];
+
+        timeline.observe(&together, &config, None, &[]);
+        timeline.observe(&together, &config, None, &target);
+        timeline.observe(&apart, &config, None, &target);
+        let faster = BoidConfig {
+            max_speed: 4.0,
+            ..config
+        };
+        timeline.observe(&apart, &faster, None, &[]);
+        timeline.observe(&together, &faster, Some(&config), &[]);
+        // The crossfade going on is not a new preset
+        timeline.observe(&together, &faster, Some(&config), &[]);
+
+        let markers: Vec<_> = timeline.entries().map(|e| (e.tick, e.marker)).collect();
+        assert_eq!(
+            markers,
+            [
+                (
+                    1,
+                    Marker::TargetAcquired {
+                        position: target[0]
+                    }
+                ),
+                (2, Marker::ClusterSplit { clusters: 2 }),
+                (3, Marker::TargetLost),
+                (3, Marker::PresetApplied { crossfade: false }),
+                (4, Marker::PresetApplied { crossfade: true }),
+                (4, Marker::ClusterMerge { clusters: 1 }),
+            ]
+        );
+        assert_eq!(timeline.between(2, 4).count(), 3);
+        assert_eq!(timeline.ticks(), 6);
+    }
+
+    #[test]
+    fn test_bursts_are_reported_when_their_window_closes() {
+        let mut timeline = Timeline::new(TimelineConfig {
+            burst_size: 4,
+            burst_window: 3,
+            ..TimelineConfig::default()
+        });
+        let config = BoidConfig::default();
+        let target = [Vector2D::zero()];
+        for count in [2, 4, 7, 7, 7, 6, 7] {
+            timeline.observe(&boids_at(&vec![0.0; count]), &config, None, &target);
+        }
+        // Growing by 5 over ticks 1-3 is a burst; the one boid lost and
+        // regained at ticks 5 and 6 are not
+        let markers: Vec<_> = timeline.entries().map(|e| (e.tick, e.marker)).collect();
+        assert_eq!(
+            markers,
+            [
+                (
+                    0,
+                    Marker::TargetAcquired {
+                        position: target[0]
+                    }
+                ),
+                (1, Marker::SpawnBurst { count: 5 }),
+            ]
+        );
+
+        // Losing all seven closes the window the lost boid opened at tick
+        // 5, a burst of eight that sorts before the target loss
+        timeline.config.capacity = 1;
+        timeline.observe(&[], &config, None, &[]);
+        let kept: Vec<_> = timeline.entries().map(|e| (e.tick, e.marker)).collect();
+        assert_eq!(kept, [(7, Marker::TargetLost)]);
+        assert_eq!(timeline.dropped(), 3);
+    }
+}
@@ -0,0 +1,119 @@
+//! Wind: a steady global force plus gusts that vary over time
+//!
+//! Both flocks add their `wind()` to every boid outside
+//! a formation each step, bypassing `max_force`. The gust clock advances
+//! with the step's `dt`, so gusts keep their timing at any frame rate.
+
+use crate::Vector2D;
+
+/// Time-varying part of the wind
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gust {
+    #[default]
+    Calm,
+    /// `amplitude * sin(2π t / period)`: wind swelling and turning back
+    /// every `period` seconds
+    Sine { amplitude: Vector2D, period: f32 },
+    /// Smooth noise within `±amplitude` on each axis, changing course about
+    /// every `period` seconds. Each `seed` gives a different but repeatable
+    /// pattern.
+    Noise {
+        amplitude: Vector2D,
+        period: f32,
+        seed: u32,
+    },
+}
+
+impl Gust {
+    /// Gust force `time` seconds into the run; zero for a period that is
+    /// not positive
+    pub fn at(&self, time: f64) -> Vector2D {
+        match *self {
+            Gust::Calm => Vector2D::zero(),
+            Gust::Sine { amplitude, period } => {
+                if period <= 0.0 {
+                    return Vector2D::zero();
+                }
+                let turns = time / period as f64;
+                let phase = (turns - libm::floor(turns)) * core::f64::consts::TAU;
+                amplitude * libm::sin(phase) as f32
+            }
+            Gust::Noise {
+                amplitude,
+                period,
+                seed,
+            } => {
+                if period <= 0.0 {
+                    return Vector2D::zero();
+                }
+                let t = time / period as f64;
+                Vector2D::new(
+                    amplitude.x * value_noise(seed, t),
+                    amplitude.y * value_noise(seed ^ 0x9e37_79b9, t),
+                )
+            }
+        }
+    }
+}
+
+/// Smoothstep between random values in [-1, 1] at whole `t`
+fn value_noise(seed: u32, t: f64) -> f32 {
+    let floor = libm::floor(t);
+    let i = floor as i64 as u32;
+    let f = (t - floor) as f32;
+    let a = lattice(seed, i);
+    let b = lattice(seed, i.wrapping_add(1));
+    a + (b - a) * f * f * (3.0 - 2.0 * f)
+}
+
+fn lattice(seed: u32, i: u32) -> f32 {
+    // lowbias32 integer hash
+    let mut x = i ^ seed.wrapping_mul(0x2c1b_3c6d);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gusts_vary_over_time() {
+        let amplitude = Vector2D::new(0.2, 0.0);
+        let sine = Gust::Sine {
+            amplitude,
+            period: 4.0,
+        };
+        assert_eq!(sine.at(0.0), Vector2D::zero());
+        assert!((sine.at(1.0).x - 0.2).abs() < 1e-6);
+        assert!((sine.at(3.0).x + 0.2).abs() < 1e-6);
+        // Long runs keep the same phase
+        assert!((sine.at(4.0e6 + 1.0).x - 0.2).abs() < 1e-6);
+
+        let noise = Gust::Noise {
+            amplitude,
+            period: 2.0,
+            seed: 7,
+        };
+        let samples: Vec<f32> = (0..400).map(|i| noise.at(i as f64 * 0.05).x).collect();
+        assert!(samples.iter().all(|x| x.abs() <= 0.2));
+        assert!(samples
+            .windows(2)
+            .all(|pair| (pair[1] - pair[0]).abs() < 0.02));
+        assert!(samples.iter().any(|&x| x > 0.02) && samples.iter().any(|&x| x < -0.02));
+        assert_eq!(noise.at(3.3), noise.at(3.3));
+        assert_eq!(
+            Gust::Sine {
+                amplitude,
+                period: 0.0
+            }
+            .at(1.0),
+            Vector2D::zero()
+        );
+    }
+}
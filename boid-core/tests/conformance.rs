@@ -0,0 +1,318 @@
+//! Conformance suite: every flock implementation must move the same boids
+//! along the same trajectories, given the same config and inputs.
+//!
+//! Each scenario runs once per boundary behavior on every backend, and each run
+//! is compared with the `FlockStd` reference after every tick. A new
+//! backend implements [`Backend`] and is added to [`backends`].
+//!
+//! Features only one implementation has are left out of the scenarios:
+//! - wander, because `Flock<N>` has no RNG.
+//! - `TargetMode::GravityWell`, which `Flock<N>` treats as a seek.
+//! - group configs, modifiers and preset transitions (`FlockStd` only).
+//! - global forces (`Flock<N>` only).
+
+use boid_core::{
+    steering::{Alignment, Arrive, Cohesion, Flee, Seek, Separation},
+    BehaviorList, Boid, BoidConfig, BoundaryBehavior, Flock, FlockStd, TargetMode, TargetOverride,
+    Vector2D,
+};
+
+const WIDTH: f32 = 200.0;
+const HEIGHT: f32 = 160.0;
+const BOIDS: usize = 40;
+const CAPACITY: usize = 64;
+const TICKS: usize = 300;
+/// Largest position or velocity difference allowed between backends. The
+/// current ones agree bit for bit; this leaves room for backends that round
+/// differently, while a real rule difference grows far past it.
+const TOLERANCE: f32 = 1e-3;
+
+/// A flock implementation under test
+trait Backend {
+    fn run_tick(&mut self, target: Option<Vector2D>);
+    fn boids(&self) -> &[Boid];
+}
+
+impl Backend for FlockStd {
+    fn run_tick(&mut self, target: Option<Vector2D>) {
+        self.update_with_target(target);
+    }
+
+    fn boids(&self) -> &[Boid] {
+        &self.boids
+    }
+}
+
+impl<const N: usize> Backend for Flock<N> {
+    fn run_tick(&mut self, target: Option<Vector2D>) {
+        self.update_with_target(target);
+    }
+
+    fn boids(&self) -> &[Boid] {
+        &self.boids
+    }
+}
+
+/// `Flock<N>` driven through `update_with_behaviors` with the standard
+/// behaviors, which must match its built-in rules
+struct FlockWithBehaviors(Flock<CAPACITY>);
+
+impl Backend for FlockWithBehaviors {
+    fn run_tick(&mut self, target: Option<Vector2D>) {
+        let mut behaviors = BehaviorList::<6>::new();
+        for behavior in [
+            &Separation as &dyn boid_core::SteeringBehavior,
+            &Alignment,
+            &Cohesion,
+            &Seek,
+            &Flee,
+            &Arrive,
+        ] {
+            behaviors.push(behavior, 1.0).ok().unwrap();
+        }
+        self.0.update_with_behaviors(target, &behaviors);
+    }
+
+    fn boids(&self) -> &[Boid] {
+        &self.0.boids
+    }
+}
+
+/// Every backend but the reference, named for failure messages
+fn backends(config: BoidConfig, boids: &[Boid]) -> Vec<(&'static str, Box<dyn Backend>)> {
+    let mut grid = reference(config, boids);
+    grid.config.spatial_grid = true;
+
+    let fixed = || {
+        let mut flock = Flock::<CAPACITY>::new(WIDTH, HEIGHT, config);
+        for boid in boids {
+            flock.add_boid(boid.clone()).unwrap();
+        }
+        flock
+    };
+
+    vec![
+        ("FlockStd (spatial grid)", Box::new(grid)),
+        ("Flock<N>", Box::new(fixed())),
+        (
+            "Flock<N> (behavior list)",
+            Box::new(FlockWithBehaviors(fixed())),
+        ),
+    ]
+}
+
+fn reference(config: BoidConfig, boids: &[Boid]) -> FlockStd {
+    let mut flock = FlockStd::new_with_config(WIDTH, HEIGHT, 0, config);
+    flock.boids = boids.to_vec();
+    flock
+}
+
+/// Where a run first left the reference
+#[derive(Debug)]
+struct Divergence {
+    tick: usize,
+    boid: usize,
+    field: &'static str,
+    expected: Vector2D,
+    actual: Vector2D,
+}
+
+/// The worst position or velocity difference between two flock states,
+/// if it exceeds the tolerance
+fn diff(tick: usize, expected: &[Boid], actual: &[Boid]) -> Option<Divergence> {
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "tick {}: boid counts differ",
+        tick
+    );
+    expected
+        .iter()
+        .zip(actual)
+        .enumerate()
+        .flat_map(|(boid, (e, a))| {
+            [
+                ("position", e.position, a.position),
+                ("velocity", e.velocity, a.velocity),
+            ]
+            .map(|(field, expected, actual)| Divergence {
+                tick,
+                boid,
+                field,
+                expected,
+                actual,
+            })
+        })
+        .filter(|d| d.expected.distance(&d.actual) > TOLERANCE)
+        .max_by(|a, b| {
+            let error = |d: &Divergence| d.expected.distance(&d.actual);
+            error(a).total_cmp(&error(b))
+        })
+}
+
+struct Scenario {
+    name: &'static str,
+    config: BoidConfig,
+    /// Applied to the seeded boids before the run
+    setup: fn(&mut [Boid]),
+    /// Target on each tick
+    target: fn(usize) -> Option<Vector2D>,
+}
+
+impl Scenario {
+    fn run(&self, boundary: BoundaryBehavior) {
+        let mut boids = FlockStd::new_with_seed(WIDTH, HEIGHT, BOIDS, 7).boids;
+        (self.setup)(&mut boids);
+
+        let config = BoidConfig {
+            boundary: Some(boundary),
+            ..self.config
+        };
+        let mut reference = reference(config, &boids);
+        let mut backends = backends(config, &boids);
+        for tick in 0..TICKS {
+            let target = (self.target)(tick);
+            reference.run_tick(target);
+            for (name, backend) in backends.iter_mut() {
+                backend.run_tick(target);
+                if let Some(d) = diff(tick, reference.boids(), backend.boids()) {
+                    panic!(
+                        "{} ({:?}): {} diverges at tick {}, boid {} {}: \
+                         expected {:?}, got {:?}",
+                        self.name, boundary, name, d.tick, d.boid, d.field, d.expected, d.actual
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn no_setup(_: &mut [Boid]) {}
+
+fn no_target(_: usize) -> Option<Vector2D> {
+    None
+}
+
+/// Circles the area, switching off every 100 ticks
+fn circling_target(tick: usize) -> Option<Vector2D> {
+    let angle = tick as f32 * 0.05;
+    ((tick / 100) % 3 != 2).then(|| {
+        Vector2D::new(
+            WIDTH / 2.0 + 60.0 * angle.cos(),
+            HEIGHT / 2.0 + 50.0 * angle.sin(),
+        )
+    })
+}
+
+fn scenarios() -> Vec<Scenario> {
+    vec![
+        Scenario {
+            name: "free flocking",
+            config: BoidConfig::default(),
+            setup: no_setup,
+            target: no_target,
+        },
+        Scenario {
+            name: "fast and tight",
+            config: BoidConfig {
+                max_speed: 5.0,
+                max_force: 0.2,
+                separation_weight: 3.0,
+                cohesion_distance: 60.0,
+                ..BoidConfig::default()
+            },
+            setup: no_setup,
+            target: no_target,
+        },
+        Scenario {
+            name: "seek",
+            config: BoidConfig::default(),
+            setup: no_setup,
+            target: circling_target,
+        },
+        Scenario {
+            name: "seek within a radius",
+            config: BoidConfig {
+                seek_radius: 80.0,
+                ..BoidConfig::default()
+            },
+            setup: no_setup,
+            target: circling_target,
+        },
+        Scenario {
+            name: "flee",
+            config: BoidConfig {
+                target_mode: TargetMode::Flee,
+                ..BoidConfig::default()
+            },
+            setup: no_setup,
+            target: circling_target,
+        },
+        Scenario {
+            name: "arrive",
+            config: BoidConfig {
+                target_mode: TargetMode::Arrive,
+                arrive_radius: 40.0,
+                ..BoidConfig::default()
+            },
+            setup: no_setup,
+            target: circling_target,
+        },
+        Scenario {
+            name: "sub-flocks and puppets",
+            config: BoidConfig::default(),
+            setup: |boids| {
+                for (i, boid) in boids.iter_mut().enumerate() {
+                    boid.group = (i % 3) as u8;
+                    boid.puppet = i % 10 == 0;
+                }
+            },
+            target: no_target,
+        },
+        Scenario {
+            name: "target overrides and formation slots",
+            config: BoidConfig::default(),
+            setup: |boids| {
+                for (i, boid) in boids.iter_mut().enumerate() {
+                    boid.target_override = match i % 4 {
+                        0 => TargetOverride::Target(Vector2D::new(20.0, 20.0)),
+                        1 => TargetOverride::FreeFloat,
+                        2 => TargetOverride::Formation(Vector2D::new(i as f32 * 4.0, 80.0)),
+                        _ => TargetOverride::Inherit,
+                    };
+                }
+            },
+            target: circling_target,
+        },
+    ]
+}
+
+#[test]
+fn test_backends_follow_the_reference() {
+    for scenario in scenarios() {
+        for boundary in [
+            BoundaryBehavior::Wrap,
+            BoundaryBehavior::Bounce,
+            BoundaryBehavior::SteerAway {
+                margin: 30.0,
+                weight: 2.0,
+            },
+            BoundaryBehavior::Unbounded,
+        ] {
+            scenario.run(boundary);
+        }
+    }
+}
+
+#[test]
+fn test_diff_reports_the_worst_divergence() {
+    let boids = FlockStd::new_with_seed(WIDTH, HEIGHT, 3, 1).boids;
+    assert!(diff(0, &boids, &boids).is_none());
+
+    let mut moved = boids.clone();
+    moved[1].position.x += TOLERANCE / 2.0;
+    moved[2].velocity.y += 0.5;
+    moved[0].position.y += 0.1;
+    let d = diff(4, &boids, &moved).unwrap();
+    assert_eq!((d.tick, d.boid, d.field), (4, 2, "velocity"));
+}
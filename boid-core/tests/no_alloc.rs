@@ -0,0 +1,155 @@
+//! Per-tick allocation checks: a tick of `Flock<N>` must never touch the
+//! heap, and a `FlockStd` tick only when its scratch arena grows, which
+//! the arena reports. Long-running embedded deployments fragment their
+//! heap otherwise.
+//!
+//! A counting global allocator tallies the allocations of the current
+//! thread, so tests running in parallel do not see each other's.
+
+use boid_core::{
+    steering::{Alignment, Cohesion, Flee, Seek, Separation},
+    BehaviorList, Boid, BoidConfig, BoundaryBehavior, Flock, FlockStd, PopulationLimit, TargetMode,
+    TargetOverride, Vector2D,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations `f` made on this thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const TICKS: usize = 200;
+
+fn target(tick: usize) -> Option<Vector2D> {
+    (tick % 50 < 40).then(|| Vector2D::new(60.0 + tick as f32 * 0.3, 80.0))
+}
+
+/// Boids in sub-flocks, with puppets, overrides and formation slots
+fn varied_boids() -> Vec<Boid> {
+    let mut boids = FlockStd::new_with_seed(200.0, 160.0, 48, 3).boids;
+    for (i, boid) in boids.iter_mut().enumerate() {
+        boid.group = (i % 3) as u8;
+        boid.puppet = i % 16 == 0;
+        boid.target_override = match i % 5 {
+            0 => TargetOverride::Target(Vector2D::new(20.0, 20.0)),
+            1 => TargetOverride::FreeFloat,
+            2 => TargetOverride::Formation(Vector2D::new(i as f32 * 3.0, 60.0)),
+            _ => TargetOverride::Inherit,
+        };
+    }
+    boids
+}
+
+fn configs() -> [BoidConfig; 3] {
+    [
+        BoidConfig::default(),
+        BoidConfig {
+            target_mode: TargetMode::Flee,
+            boundary: Some(BoundaryBehavior::SteerAway {
+                margin: 20.0,
+                weight: 2.0,
+            }),
+            ..BoidConfig::default()
+        },
+        BoidConfig {
+            target_mode: TargetMode::GravityWell,
+            boundary: Some(BoundaryBehavior::Wrap),
+            wander_enabled: true,
+            spatial_grid: true,
+            ..BoidConfig::default()
+        },
+    ]
+}
+
+#[test]
+fn test_fixed_flock_tick_never_allocates() {
+    for config in configs() {
+        let mut flock = Flock::<64>::new(200.0, 160.0, config);
+        for boid in varied_boids() {
+            flock.add_boid(boid).unwrap();
+        }
+        flock.population = Some(PopulationLimit::new(40));
+
+        let mut behaviors = BehaviorList::<5>::new();
+        for behavior in [
+            &Separation as &dyn boid_core::SteeringBehavior,
+            &Alignment,
+            &Cohesion,
+            &Seek,
+            &Flee,
+        ] {
+            behaviors.push(behavior, 1.0).ok().unwrap();
+        }
+
+        let count = allocations(|| {
+            for tick in 0..TICKS {
+                match tick % 3 {
+                    0 => flock.update_with_target(target(tick)),
+                    1 => {
+                        let _ = flock.try_update(target(tick));
+                    }
+                    _ => flock.update_with_behaviors(target(tick), &behaviors),
+                }
+            }
+        });
+        assert_eq!(count, 0, "{:?}", config);
+    }
+}
+
+#[test]
+fn test_std_flock_steady_tick_never_allocates() {
+    for config in configs() {
+        let mut flock = FlockStd::new_with_config_and_seed(200.0, 160.0, 0, config, 5);
+        flock.boids = varied_boids();
+        flock.group_configs.insert(
+            1,
+            BoidConfig {
+                max_speed: 3.0,
+                ..config
+            },
+        );
+        let targets = [Vector2D::new(50.0, 50.0), Vector2D::new(150.0, 100.0)];
+
+        // Let the scratch buffers grow to the flock's size
+        flock.update_with_targets(&targets);
+
+        let growths = flock.scratch().growths();
+        let count = allocations(|| {
+            for tick in 0..TICKS {
+                flock.update_with_targets(&targets[..tick % 3]);
+            }
+        });
+        // The neighbor lists grow, geometrically, while the flock clusters
+        let growths = flock.scratch().growths() - growths;
+        assert_eq!(count, growths as usize, "{:?}", config);
+        assert!(growths <= 3, "{:?}", config);
+    }
+}
@@ -6,42 +6,82 @@ pub struct Config {
     wifi_ssid: &'static str,
     #[default("")]
     wifi_psk: &'static str,
+    #[default(0)]
+    demo_scene_secs: u32,
+    #[default("jpeg")]
+    camera_format: &'static str,
+    #[default("qvga")]
+    camera_frame_size: &'static str,
+    #[default("")]
+    metrics_endpoint: &'static str,
+    #[default("influx")]
+    metrics_format: &'static str,
+    #[default(10)]
+    metrics_interval_secs: u32,
+    #[default("")]
+    metrics_device: &'static str,
+    #[default("")]
+    metrics_authorization: &'static str,
+    #[default(6)]
+    display_trail_length: u32,
+    #[default(true)]
+    display_dirty_rects: bool,
 }
 
 fn main() {
-    // Check if cfg.toml exists
+    // Without credentials the board opens the `boid-setup` access point at
+    // boot, where they can be entered instead
     let config_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("cfg.toml");
-
     if !config_path.exists() {
-        panic!(
-            "You need to create a `cfg.toml` file with your Wi-Fi credentials! Use `cfg.toml.example` as a template.\n\n\
-            Example:\n\
-            [wifi]\n\
-            ssid = \"YourNetworkName\"\n\
-            psk = \"YourPassword\"\n"
+        println!(
+            "cargo:warning=No cfg.toml (see cfg.toml.example); WiFi must be set up through the boid-setup access point"
         );
     }
 
-    // Validate that credentials are not placeholders
-    if CONFIG.wifi_ssid.is_empty() || CONFIG.wifi_ssid == "YourNetworkName" {
-        panic!(
-            "Please set a valid WiFi SSID in cfg.toml\n\
-            Current value: '{}'\n\
-            Edit cfg.toml and set your actual network name",
-            CONFIG.wifi_ssid
-        );
-    }
-
-    if CONFIG.wifi_psk.is_empty() || CONFIG.wifi_psk == "YourPassword" {
-        panic!(
-            "Please set a valid WiFi password in cfg.toml\n\
-            Edit cfg.toml and set your actual WiFi password"
+    // Placeholders from the example count as unset
+    let (ssid, psk) = if CONFIG.wifi_ssid == "YourNetworkName" {
+        ("", "")
+    } else if CONFIG.wifi_psk == "YourPassword" {
+        (CONFIG.wifi_ssid, "")
+    } else {
+        (CONFIG.wifi_ssid, CONFIG.wifi_psk)
+    };
+    if config_path.exists() && ssid.is_empty() {
+        println!(
+            "cargo:warning=No WiFi SSID in cfg.toml; WiFi must be set up through the boid-setup access point"
         );
     }
 
     // Set environment variables for compile time
-    println!("cargo:rustc-env=WIFI_SSID={}", CONFIG.wifi_ssid);
-    println!("cargo:rustc-env=WIFI_PASSWORD={}", CONFIG.wifi_psk);
+    println!("cargo:rustc-env=WIFI_SSID={}", ssid);
+    println!("cargo:rustc-env=WIFI_PASSWORD={}", psk);
+    println!("cargo:rustc-env=DEMO_SCENE_SECS={}", CONFIG.demo_scene_secs);
+    println!("cargo:rustc-env=CAMERA_FORMAT={}", CONFIG.camera_format);
+    println!(
+        "cargo:rustc-env=CAMERA_FRAME_SIZE={}",
+        CONFIG.camera_frame_size
+    );
+
+    println!(
+        "cargo:rustc-env=METRICS_ENDPOINT={}",
+        CONFIG.metrics_endpoint
+    );
+    println!("cargo:rustc-env=METRICS_FORMAT={}", CONFIG.metrics_format);
+    println!(
+        "cargo:rustc-env=METRICS_INTERVAL_SECS={}",
+        CONFIG.metrics_interval_secs
+    );
+    println!("cargo:rustc-env=METRICS_DEVICE={}", CONFIG.metrics_device);
+    println!(
+        "cargo:rustc-env=METRICS_AUTHORIZATION={}",
+        CONFIG.metrics_authorization
+    );
+
+    println!(
+        "cargo:rustc-env=TRAIL_LENGTH={}",
+        CONFIG.display_trail_length
+    );
+    println!("cargo:rustc-env=DIRTY_RECTS={}", CONFIG.display_dirty_rects);
 
     // Rebuild if cfg.toml changes
     println!("cargo:rerun-if-changed=cfg.toml");
@@ -0,0 +1,160 @@
+// Staged start-up
+//
+// Joining WiFi and starting the camera take seconds, and the board used to
+// show a blank panel until both were done. Now `main` brings up the display
+// and the flock first and runs the slow steps as stages on their own
+// threads. The camera and WiFi stages run side by side, since they share no
+// pins or buses. The services stage waits for both: it runs the self-test,
+// which reads the WiFi signal, and starts the HTTP server and the other
+// network inputs.
+//
+// Until every stage is done the flock plays the boot animation: it chases a
+// target circling the middle of the screen, on a tighter circle as stages
+// finish. A failed self-test is handed to the main loop, which owns the
+// display, to show the status screen.
+
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use boid_core::Vector2D;
+use boid_shared::HealthResponse;
+use log::info;
+
+/// Seconds per turn of the boot animation's target
+const ORBIT_SECS: f32 = 2.0;
+/// Orbit radius as a share of the screen's shorter side, before the first
+/// stage finishes and once all but the last have
+const ORBIT_RADIUS: (f32, f32) = (0.35, 0.1);
+
+/// A start-up step that runs on its own thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Camera,
+    Wifi,
+    /// Self-test, HTTP server, UDP and on-device detection
+    Services,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Camera, Stage::Wifi, Stage::Services];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Camera => "camera",
+            Stage::Wifi => "wifi",
+            Stage::Services => "services",
+        }
+    }
+}
+
+/// How far start-up got, shared between the stages and the main loop
+pub struct BootProgress {
+    powered_on: Instant,
+    /// One bit per finished stage, by its position in `Stage::ALL`
+    finished: AtomicU8,
+    /// A failed self-test the main loop has yet to show
+    failure: Mutex<Option<HealthResponse>>,
+}
+
+impl BootProgress {
+    pub fn new() -> Self {
+        Self {
+            powered_on: Instant::now(),
+            finished: AtomicU8::new(0),
+            failure: Mutex::new(None),
+        }
+    }
+
+    /// Time since start-up began
+    pub fn elapsed(&self) -> Duration {
+        self.powered_on.elapsed()
+    }
+
+    fn finish(&self, stage: Stage) {
+        let bit = 1 << stage as u8;
+        let finished = self.finished.fetch_or(bit, Ordering::AcqRel) | bit;
+        info!(
+            "Boot stage {} done after {} ms",
+            stage.name(),
+            self.elapsed().as_millis()
+        );
+        if finished.count_ones() as usize == Stage::ALL.len() {
+            info!("Boot finished after {} ms", self.elapsed().as_millis());
+        }
+    }
+
+    pub fn finished(&self) -> usize {
+        self.finished.load(Ordering::Acquire).count_ones() as usize
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.finished() == Stage::ALL.len()
+    }
+
+    /// Hand a failed self-test to the main loop
+    pub fn report_failure(&self, report: HealthResponse) {
+        *self.failure.lock().unwrap() = Some(report);
+    }
+
+    /// The failed self-test to show, once
+    pub fn take_failure(&self) -> Option<HealthResponse> {
+        self.failure.lock().unwrap().take()
+    }
+
+    /// Target of the boot animation on a `width` x `height` screen
+    pub fn animation_target(&self, width: f32, height: f32) -> Vector2D {
+        let (widest, tightest) = ORBIT_RADIUS;
+        let done = self.finished().min(Stage::ALL.len() - 1) as f32;
+        let share = widest + (tightest - widest) * done / (Stage::ALL.len() - 1) as f32;
+        let radius = width.min(height) * share;
+        let angle = self.elapsed().as_secs_f32() / ORBIT_SECS * TAU;
+        Vector2D::new(
+            width / 2.0 + radius * angle.cos(),
+            height / 2.0 + radius * angle.sin(),
+        )
+    }
+}
+
+impl Default for BootProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A running stage; [`wait`](Self::wait) hands its result to the stages
+/// that depend on it
+pub struct Task<T> {
+    stage: Stage,
+    handle: JoinHandle<T>,
+}
+
+impl<T> Task<T> {
+    /// Block until the stage is done
+    pub fn wait(self) -> anyhow::Result<T> {
+        self.handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("Boot stage {} panicked", self.stage.name()))
+    }
+}
+
+/// Run `stage` on a thread of its own with `stack_size` bytes of stack
+pub fn spawn<T: Send + 'static>(
+    stage: Stage,
+    progress: &Arc<BootProgress>,
+    stack_size: usize,
+    run: impl FnOnce() -> T + Send + 'static,
+) -> anyhow::Result<Task<T>> {
+    let progress = progress.clone();
+    let handle = thread::Builder::new()
+        .name(format!("boot-{}", stage.name()))
+        .stack_size(stack_size)
+        .spawn(move || {
+            let result = run();
+            progress.finish(stage);
+            result
+        })?;
+    Ok(Task { stage, handle })
+}
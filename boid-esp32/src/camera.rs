@@ -22,16 +22,143 @@
 // - HREF: GPIO47
 // - PCLK: GPIO13
 
+//
+// PWDN is not wired on this board, so the sensor is powered down in software:
+// the OV2640 standby bit (COM2, sensor register bank) stops the pixel array
+// and output while keeping the register configuration.
+
+use std::time::{Duration, Instant};
+
 use esp32cam::Camera;
 use esp_idf_svc::sys::camera::{
+    esp_camera_sensor_get,
+    framesize_t,
+    framesize_t_FRAMESIZE_QQVGA,
     framesize_t_FRAMESIZE_QVGA,
+    framesize_t_FRAMESIZE_VGA,
+    jpg2rgb565,
+    jpg_scale_t_JPG_SCALE_2X,
+    pixformat_t,
+    pixformat_t_PIXFORMAT_GRAYSCALE,
     pixformat_t_PIXFORMAT_JPEG,
+    pixformat_t_PIXFORMAT_RGB565,
 };
 use esp_idf_hal::gpio::*;
 use esp_idf_hal::peripheral::Peripheral;
 
+/// What the sensor delivers per frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// Compressed by the sensor; the only format `/stream` can serve
+    #[default]
+    Jpeg,
+    /// Two bytes per pixel, high byte first
+    Rgb565,
+    /// One byte per pixel
+    Grayscale,
+}
+
+impl PixelFormat {
+    /// `"jpeg"`, `"rgb565"` or `"grayscale"`, as in cfg.toml
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "jpeg" => Some(Self::Jpeg),
+            "rgb565" => Some(Self::Rgb565),
+            "grayscale" => Some(Self::Grayscale),
+            _ => None,
+        }
+    }
+
+    /// Bytes per pixel of a raw format; `None` for JPEG
+    pub fn bytes_per_pixel(self) -> Option<usize> {
+        match self {
+            Self::Jpeg => None,
+            Self::Rgb565 => Some(2),
+            Self::Grayscale => Some(1),
+        }
+    }
+
+    fn to_driver(self) -> pixformat_t {
+        match self {
+            Self::Jpeg => pixformat_t_PIXFORMAT_JPEG,
+            Self::Rgb565 => pixformat_t_PIXFORMAT_RGB565,
+            Self::Grayscale => pixformat_t_PIXFORMAT_GRAYSCALE,
+        }
+    }
+}
+
+/// Resolution of captured frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameSize {
+    /// 160x120
+    Qqvga,
+    /// 320x240
+    #[default]
+    Qvga,
+    /// 640x480; raw frames this large only fit in PSRAM
+    Vga,
+}
+
+impl FrameSize {
+    /// `"qqvga"`, `"qvga"` or `"vga"`, as in cfg.toml
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "qqvga" => Some(Self::Qqvga),
+            "qvga" => Some(Self::Qvga),
+            "vga" => Some(Self::Vga),
+            _ => None,
+        }
+    }
+
+    /// Width and height in pixels
+    pub fn dimensions(self) -> (u32, u32) {
+        match self {
+            Self::Qqvga => (160, 120),
+            Self::Qvga => (320, 240),
+            Self::Vga => (640, 480),
+        }
+    }
+
+    fn to_driver(self) -> framesize_t {
+        match self {
+            Self::Qqvga => framesize_t_FRAMESIZE_QQVGA,
+            Self::Qvga => framesize_t_FRAMESIZE_QVGA,
+            Self::Vga => framesize_t_FRAMESIZE_VGA,
+        }
+    }
+}
+
+/// Sensor output, fixed when the camera is initialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CameraSettings {
+    pub format: PixelFormat,
+    pub size: FrameSize,
+}
+
+/// A frame as the sensor delivered it, valid until the next capture
+pub struct RawFrame<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: &'a [u8],
+}
+
+/// The sensor goes back to standby after this long without a captured frame
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OV2640 COM2 as addressed by the driver's `set_reg`: bank select in bit 8
+/// (1 = sensor bank), register in the low byte
+const OV2640_COM2: i32 = 0x100 | 0x09;
+/// COM2 bit putting the sensor into standby
+const OV2640_STANDBY: i32 = 0x10;
+/// Frames dropped after waking while exposure settles
+const WAKE_DISCARD_FRAMES: usize = 3;
+
 pub struct CameraWrapper {
     camera: Camera,
+    settings: CameraSettings,
+    powered: bool,
+    last_used: Instant,
 }
 
 impl CameraWrapper {
@@ -50,8 +177,13 @@ impl CameraWrapper {
         pclk: impl Peripheral<P = Gpio13> + 'static,
         vsync: impl Peripheral<P = Gpio38> + 'static,
         href: impl Peripheral<P = Gpio47> + 'static,
+        settings: CameraSettings,
     ) -> Result<Self, esp32cam::CameraError> {
-        log::info!("Initializing camera for XIAO ESP32S3 Sense");
+        log::info!(
+            "Initializing camera for XIAO ESP32S3 Sense ({:?}, {:?})",
+            settings.format,
+            settings.size
+        );
 
         let camera = Camera::new(
             xclk,   // GPIO10
@@ -68,20 +200,139 @@ impl CameraWrapper {
             pclk,   // GPIO13
             vsync,  // GPIO38
             href,   // GPIO47
-            pixformat_t_PIXFORMAT_JPEG,
-            framesize_t_FRAMESIZE_QVGA,  // 320x240
+            settings.format.to_driver(),
+            settings.size.to_driver(),
         )?;
 
         log::info!("Camera initialized successfully");
-        Ok(Self { camera })
+        let mut wrapper = Self {
+            camera,
+            settings,
+            powered: true,
+            last_used: Instant::now(),
+        };
+
+        // Stay in standby until a client asks for the stream
+        if let Err(e) = wrapper.power_down() {
+            log::warn!("Camera standby failed, sensor stays on: {:?}", e);
+        }
+        Ok(wrapper)
     }
 
-    /// Capture a JPEG frame from the camera
-    /// Returns the frame buffer as a byte slice
-    pub fn capture_jpeg(&mut self) -> Result<&[u8], esp32cam::CameraError> {
+    pub fn settings(&self) -> CameraSettings {
+        self.settings
+    }
+
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Wake the sensor from standby; does nothing if it is already awake
+    pub fn power_up(&mut self) -> anyhow::Result<()> {
+        self.last_used = Instant::now();
+        if self.powered {
+            return Ok(());
+        }
+
+        self.set_standby(false)?;
+        for _ in 0..WAKE_DISCARD_FRAMES {
+            let _ = self.camera.get_framebuffer();
+        }
+        self.powered = true;
+        log::info!("Camera powered up");
+        Ok(())
+    }
+
+    /// Put the sensor into standby
+    pub fn power_down(&mut self) -> anyhow::Result<()> {
+        if !self.powered {
+            return Ok(());
+        }
+
+        self.set_standby(true)?;
+        self.powered = false;
+        log::info!("Camera powered down");
+        Ok(())
+    }
+
+    /// Power down if no frame was captured for `timeout`.
+    /// Returns whether the camera was powered down.
+    pub fn power_down_if_idle(&mut self, timeout: Duration) -> bool {
+        if !self.powered || self.last_used.elapsed() < timeout {
+            return false;
+        }
+        match self.power_down() {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Camera standby failed: {:?}", e);
+                false
+            }
+        }
+    }
+
+    fn set_standby(&mut self, standby: bool) -> anyhow::Result<()> {
+        // SAFETY: the driver stays initialized for as long as `self.camera`
+        // lives, and the sensor handle it returns is owned by the driver
+        let sensor = unsafe { esp_camera_sensor_get() };
+        let set_reg = unsafe { sensor.as_ref() }
+            .and_then(|sensor| sensor.set_reg)
+            .ok_or_else(|| anyhow::anyhow!("Camera sensor unavailable"))?;
+
+        let value = if standby { OV2640_STANDBY } else { 0 };
+        if unsafe { set_reg(sensor, OV2640_COM2, OV2640_STANDBY, value) } != 0 {
+            anyhow::bail!("Failed to write OV2640 COM2");
+        }
+        Ok(())
+    }
+
+    /// Capture a frame in whatever format the camera was set up for
+    pub fn capture_raw(&mut self) -> Result<RawFrame<'_>, esp32cam::CameraError> {
+        self.last_used = Instant::now();
         // Capture two frames, discard first for freshness (common practice)
         self.camera.get_framebuffer()?;
-        let fb = self.camera.get_framebuffer()?;
-        Ok(fb)
+        let data = self.camera.get_framebuffer()?;
+        let (width, height) = self.settings.size.dimensions();
+        Ok(RawFrame {
+            width,
+            height,
+            format: self.settings.format,
+            data,
+        })
+    }
+
+    /// Capture a JPEG frame from the camera
+    /// Returns the frame buffer as a byte slice
+    pub fn capture_jpeg(&mut self) -> anyhow::Result<&[u8]> {
+        if self.settings.format != PixelFormat::Jpeg {
+            anyhow::bail!("Camera captures {:?}, not JPEG", self.settings.format);
+        }
+        let frame = self
+            .capture_raw()
+            .map_err(|e| anyhow::anyhow!("Capture failed: {:?}", e))?;
+        Ok(frame.data)
+    }
+
+    /// Capture a JPEG frame and decode it at half resolution into `rgb565`,
+    /// two bytes per pixel, high byte first. Decoding while scaling skips
+    /// most of the JPEG work. Returns the decoded width and height.
+    pub fn capture_rgb565_half(&mut self, rgb565: &mut Vec<u8>) -> anyhow::Result<(u32, u32)> {
+        let (width, height) = self.settings.size.dimensions();
+        let (width, height) = (width / 2, height / 2);
+        rgb565.resize(width as usize * height as usize * 2, 0);
+        let jpeg = self.capture_jpeg()?;
+        // SAFETY: `rgb565` has room for the frame at half scale, and the
+        // frame buffer stays valid until the next capture
+        let decoded = unsafe {
+            jpg2rgb565(
+                jpeg.as_ptr(),
+                jpeg.len(),
+                rgb565.as_mut_ptr(),
+                jpg_scale_t_JPG_SCALE_2X,
+            )
+        };
+        if !decoded {
+            anyhow::bail!("Failed to decode JPEG frame");
+        }
+        Ok((width, height))
     }
 }
@@ -0,0 +1,169 @@
+// On-device hand tracking from the ESP32's own camera
+//
+// Without a client, the firmware can find the hand itself: a background
+// thread takes camera frames as RGB565, converts them to BGR and runs the
+// same skin-color detector the clients use. A camera set to RGB565 hands
+// its frames over as they are; JPEG frames, which `/stream` needs, are
+// decoded at half resolution. The index fingertip becomes the flock target
+// through `http_server::set_target`, so it gets the same lens correction
+// and smoothing as a posted position.
+//
+// The MJPEG stream keeps working alongside. While no hand is in view the
+// detector leaves the target alone, so a client can still steer the flock;
+// it only clears a target it set itself. To the target arbiter it is one
+// more client, named `camera`.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use boid_hand_detector::HandDetector;
+use boid_shared::{ErrorCode, Position};
+use log::{info, warn};
+
+use crate::camera::{CameraWrapper, PixelFormat};
+use crate::http_server;
+use crate::types::SimulationState;
+
+// Time between detections (~10 per second), leaving the CPU to the flock
+const DETECT_INTERVAL: Duration = Duration::from_millis(100);
+// Client id of the detector's targets, e.g. to give it a priority
+const CLIENT_ID: &str = "camera";
+// Decoding and blob grouping need more than the default thread stack
+const DETECT_STACK_SIZE: usize = 16 * 1024;
+// Detector thresholds for a 160x120 frame; the detector's defaults are
+// tuned for 320x240, four times the pixels and twice the distances
+const MIN_SKIN_PIXELS: usize = 500;
+const GROUPING_THRESHOLD: usize = 15;
+const REFERENCE_WIDTH: u32 = 160;
+
+/// Spawn the detection loop; it keeps the camera awake while it runs
+pub fn spawn_detector(
+    camera: Arc<Mutex<Option<CameraWrapper>>>,
+    sim_state: Arc<Mutex<SimulationState>>,
+) -> anyhow::Result<()> {
+    let settings = match camera.lock().unwrap().as_ref() {
+        Some(cam) => cam.settings(),
+        None => anyhow::bail!("[{}] No camera", ErrorCode::CameraInitFailed),
+    };
+    let (width, _) = settings.size.dimensions();
+    let width = match settings.format {
+        PixelFormat::Jpeg => width / 2,
+        PixelFormat::Rgb565 => width,
+        PixelFormat::Grayscale => anyhow::bail!("Skin detection needs a color camera format"),
+    };
+
+    // Scale the thresholds with the frame the detector sees
+    let scale = width as f32 / REFERENCE_WIDTH as f32;
+    let detector = HandDetector::new()
+        .with_min_skin_pixels((MIN_SKIN_PIXELS as f32 * scale * scale) as usize)
+        .with_grouping_threshold((GROUPING_THRESHOLD as f32 * scale) as usize);
+    thread::Builder::new()
+        .stack_size(DETECT_STACK_SIZE)
+        .spawn(move || run(detector, &camera, &sim_state))?;
+    info!(
+        "On-device hand detection started on {}px wide frames",
+        width
+    );
+    Ok(())
+}
+
+fn run(
+    detector: HandDetector,
+    camera: &Mutex<Option<CameraWrapper>>,
+    sim_state: &Mutex<SimulationState>,
+) {
+    let mut rgb565 = Vec::new();
+    let mut bgr = Vec::new();
+    let mut tracking = false;
+
+    loop {
+        thread::sleep(DETECT_INTERVAL);
+
+        let captured = match camera.lock().unwrap().as_mut() {
+            Some(cam) => cam
+                .power_up()
+                .and_then(|()| capture_bgr(cam, &mut rgb565, &mut bgr)),
+            None => return,
+        };
+        let (width, height) = match captured {
+            Ok(size) => size,
+            Err(e) => {
+                warn!(
+                    "[{}] Detection frame failed: {:?}",
+                    ErrorCode::CameraCaptureFailed,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let hand = detector.process_bgr_image(width as usize, height as usize, &bgr);
+        if hand.is_none() && !tracking {
+            continue;
+        }
+        tracking = hand.is_some();
+
+        // Back to full-frame coordinates, as clients post them
+        let mut state = sim_state.lock().unwrap();
+        let scale = state.frame_size.dimensions().0 as f32 / width as f32;
+        let position =
+            hand.map(|hand| Position::new(hand.index_tip.x * scale, hand.index_tip.y * scale));
+        // While another client holds the flock the hand is ignored
+        let _ = http_server::set_target(&mut state, CLIENT_ID, position);
+    }
+}
+
+/// Capture a frame into `bgr`, decoding JPEG through `rgb565`; returns the
+/// size of the converted frame
+fn capture_bgr(
+    cam: &mut CameraWrapper,
+    rgb565: &mut Vec<u8>,
+    bgr: &mut Vec<u8>,
+) -> anyhow::Result<(u32, u32)> {
+    match cam.settings().format {
+        PixelFormat::Rgb565 => {
+            let frame = cam
+                .capture_raw()
+                .map_err(|e| anyhow::anyhow!("Capture failed: {:?}", e))?;
+            rgb565_to_bgr(frame.data, bgr);
+            Ok((frame.width, frame.height))
+        }
+        PixelFormat::Jpeg => {
+            let size = cam.capture_rgb565_half(rgb565)?;
+            rgb565_to_bgr(rgb565, bgr);
+            Ok(size)
+        }
+        PixelFormat::Grayscale => anyhow::bail!("Skin detection needs a color camera format"),
+    }
+}
+
+/// Expand big-endian RGB565 pixels into BGR bytes, replacing `bgr`
+fn rgb565_to_bgr(rgb565: &[u8], bgr: &mut Vec<u8>) {
+    bgr.clear();
+    for pixel in rgb565.chunks_exact(2) {
+        let value = u16::from_be_bytes([pixel[0], pixel[1]]);
+        let r = ((value >> 11) & 0x1f) as u8;
+        let g = ((value >> 5) & 0x3f) as u8;
+        let b = (value & 0x1f) as u8;
+        // Repeat the high bits into the low ones, so full intensity is 255
+        bgr.extend_from_slice(&[
+            (b << 3) | (b >> 2),
+            (g << 2) | (g >> 4),
+            (r << 3) | (r >> 2),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb565_to_bgr() {
+        let mut bgr = Vec::new();
+        // White, pure red, pure green, pure blue
+        rgb565_to_bgr(&[0xff, 0xff, 0xf8, 0x00, 0x07, 0xe0, 0x00, 0x1f], &mut bgr);
+        assert_eq!(bgr, [255, 255, 255, 0, 0, 255, 0, 255, 0, 255, 0, 0]);
+    }
+}
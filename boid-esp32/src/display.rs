@@ -1,5 +1,9 @@
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+use boid_shared::dirty_tiles::{DirtyTiles, Span};
 use display_interface_spi::SPIInterface;
-use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
 use esp_idf_hal::{
     delay::FreeRtos,
     gpio::{Output, PinDriver},
@@ -7,6 +11,12 @@ use esp_idf_hal::{
 };
 use mipidsi::{models::ST7789, Builder};
 
+/// What the flock is drawn over
+pub const BACKGROUND: Rgb565 = Rgb565::BLACK;
+
+// Sending frames takes little stack
+const SENDER_STACK_SIZE: usize = 4 * 1024;
+
 pub type Display<'a> = mipidsi::Display<
     SPIInterface<SpiDeviceDriver<'a, &'a mut esp_idf_hal::spi::SpiDriver<'a>>, PinDriver<'a, esp_idf_hal::gpio::AnyOutputPin, Output>, PinDriver<'a, esp_idf_hal::gpio::AnyOutputPin, Output>>,
     ST7789,
@@ -22,7 +32,7 @@ impl<'a> DisplayWrapper<'a> {
         spi: SpiDeviceDriver<'a, &'a mut esp_idf_hal::spi::SpiDriver<'a>>,
         dc: PinDriver<'a, esp_idf_hal::gpio::AnyOutputPin, Output>,
         mut rst: PinDriver<'a, esp_idf_hal::gpio::AnyOutputPin, Output>,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         // Reset the display
         rst.set_low().ok();
         FreeRtos::delay_ms(10);
@@ -38,9 +48,9 @@ impl<'a> DisplayWrapper<'a> {
             .display_size(240, 240)
             .invert_colors(mipidsi::options::ColorInversion::Inverted)
             .init(&mut FreeRtos)
-            .unwrap();
+            .map_err(|e| anyhow::anyhow!("Display init failed: {:?}", e))?;
 
-        Self { display }
+        Ok(Self { display })
     }
 
     pub fn clear(&mut self, color: Rgb565) -> Result<(), mipidsi::Error> {
@@ -58,6 +68,19 @@ impl<'a> DrawTarget for DisplayWrapper<'a> {
     {
         self.display.draw_iter(pixels)
     }
+
+    // Forwarded so an area is sent through one address window rather than
+    // pixel by pixel
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display.fill_solid(area, color)
+    }
 }
 
 impl<'a> OriginDimensions for DisplayWrapper<'a> {
@@ -65,3 +88,308 @@ impl<'a> OriginDimensions for DisplayWrapper<'a> {
         self.display.size()
     }
 }
+
+/// Where frames are drawn, and how they get to the display
+pub enum Screen {
+    /// Cleared and drawn on the display directly
+    Direct(DisplayWrapper<'static>),
+    /// Drawn into a frame buffer, whose changed tiles are sent at the end
+    /// of the frame
+    Buffered {
+        frame: FrameBuffer,
+        display: DisplayWrapper<'static>,
+    },
+    /// As `Buffered`, but a thread of its own sends the changed tiles while
+    /// the next frame is simulated and drawn
+    Pipelined {
+        frame: FrameBuffer,
+        sender: FrameSender,
+    },
+}
+
+impl Screen {
+    /// The fastest way to draw that memory allows; with `dirty_rects` off,
+    /// always `Direct`
+    pub fn new(display: DisplayWrapper<'static>, dirty_rects: bool) -> Self {
+        if !dirty_rects {
+            return Self::Direct(display);
+        }
+        let size = display.size();
+        let Some(frame) = FrameBuffer::new(size, BACKGROUND) else {
+            log::warn!("No memory for a frame buffer, redrawing the whole display");
+            return Self::Direct(display);
+        };
+        match FrameSender::spawn(display) {
+            Ok(sender) => Self::Pipelined { frame, sender },
+            Err(display) => {
+                log::warn!("No memory for a second frame buffer, sending frames in the main loop");
+                Self::Buffered { frame, display }
+            }
+        }
+    }
+
+    /// Clear the display, or just the frame buffer when there is one
+    pub fn begin_frame(&mut self) {
+        match self {
+            Self::Direct(display) => {
+                display.clear(BACKGROUND).ok();
+            }
+            Self::Buffered { frame, .. } | Self::Pipelined { frame, .. } => frame.begin_frame(),
+        }
+    }
+
+    /// Send what changed in the frame buffer
+    pub fn end_frame(&mut self) {
+        match self {
+            Self::Direct(_) => {}
+            Self::Buffered { frame, display } => {
+                frame.flush(display).ok();
+            }
+            Self::Pipelined { frame, sender } => sender.send(frame),
+        }
+    }
+
+    /// Pixels sent since the last call; `None` without a frame buffer
+    pub fn take_sent_pixels(&mut self) -> Option<u64> {
+        match self {
+            Self::Direct(_) => None,
+            Self::Buffered { frame, .. } | Self::Pipelined { frame, .. } => {
+                Some(frame.take_sent_pixels())
+            }
+        }
+    }
+}
+
+impl DrawTarget for Screen {
+    type Color = Rgb565;
+    type Error = mipidsi::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        match self {
+            Self::Direct(display) => display.draw_iter(pixels),
+            Self::Buffered { frame, .. } | Self::Pipelined { frame, .. } => {
+                frame.draw_iter(pixels).map_err(|never| match never {})
+            }
+        }
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match self {
+            Self::Direct(display) => display.fill_solid(area, color),
+            Self::Buffered { frame, .. } | Self::Pipelined { frame, .. } => frame
+                .fill_solid(area, color)
+                .map_err(|never| match never {}),
+        }
+    }
+}
+
+impl OriginDimensions for Screen {
+    fn size(&self) -> Size {
+        match self {
+            Self::Direct(display) => display.size(),
+            Self::Buffered { frame, .. } | Self::Pipelined { frame, .. } => frame.size(),
+        }
+    }
+}
+
+/// The picture in RAM, of which only the tiles that changed since the last
+/// frame are sent to the display
+pub struct FrameBuffer {
+    pixels: Vec<Rgb565>,
+    size: Size,
+    background: Rgb565,
+    tiles: DirtyTiles,
+    /// Pixels sent since [`take_sent_pixels`](Self::take_sent_pixels)
+    sent_pixels: u64,
+}
+
+impl FrameBuffer {
+    /// `None` when the heap can't spare a frame (115 KB at 240x240)
+    pub fn new(size: Size, background: Rgb565) -> Option<Self> {
+        let tiles = DirtyTiles::new(size.width, size.height)?;
+        Some(Self {
+            pixels: allocate_pixels(size, background)?,
+            size,
+            background,
+            tiles,
+            sent_pixels: 0,
+        })
+    }
+
+    /// Erase what the last frame drew, in RAM only
+    pub fn begin_frame(&mut self) {
+        let width = self.size.width as usize;
+        for span in self.tiles.drawn_spans() {
+            for y in span.y..span.y + span.height {
+                let start = y as usize * width + span.x as usize;
+                self.pixels[start..start + span.width as usize].fill(self.background);
+            }
+        }
+        self.tiles.next_frame();
+    }
+
+    /// Send the tiles drawn in this frame or the last
+    pub fn flush(&mut self, display: &mut DisplayWrapper) -> Result<(), mipidsi::Error> {
+        let width = self.size.width;
+        for span in self.tiles.spans() {
+            send_span(display, &self.pixels, width, span)?;
+            self.sent_pixels += u64::from(span.width * span.height);
+        }
+        Ok(())
+    }
+
+    /// Copy the tiles [`flush`](Self::flush) would send into `transfer`,
+    /// which has the same size
+    fn stage(&mut self, transfer: &mut Transfer) {
+        let width = self.size.width as usize;
+        transfer.spans.clear();
+        for span in self.tiles.spans() {
+            for y in span.y..span.y + span.height {
+                let start = y as usize * width + span.x as usize;
+                let row = start..start + span.width as usize;
+                transfer.pixels[row.clone()].copy_from_slice(&self.pixels[row]);
+            }
+            transfer.spans.push(span);
+            self.sent_pixels += u64::from(span.width * span.height);
+        }
+    }
+
+    pub fn take_sent_pixels(&mut self) -> u64 {
+        std::mem::take(&mut self.sent_pixels)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = (self.size.width as i32, self.size.height as i32);
+        for Pixel(point, color) in pixels {
+            if (0..width).contains(&point.x) && (0..height).contains(&point.y) {
+                self.pixels[(point.y * width + point.x) as usize] = color;
+                self.tiles.mark(point.x, point.y);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+/// A frame's changed tiles on their way to the display
+struct Transfer {
+    pixels: Vec<Rgb565>,
+    width: u32,
+    spans: Vec<Span>,
+}
+
+/// Owns the display on a thread of its own. The changed tiles of frame N
+/// are copied into a second buffer and sent from there, over DMA, while the
+/// main loop goes on to frame N+1; it only waits when frame N is still
+/// going out by the time N+1 is ready.
+pub struct FrameSender {
+    /// The second buffer, while it is not being sent
+    idle: Option<Transfer>,
+    to_send: SyncSender<Transfer>,
+    sent: Receiver<Transfer>,
+}
+
+impl FrameSender {
+    /// Hands `display` back when the second buffer or the thread can't be
+    /// had
+    fn spawn(display: DisplayWrapper<'static>) -> Result<Self, DisplayWrapper<'static>> {
+        let size = display.size();
+        let Some(pixels) = allocate_pixels(size, BACKGROUND) else {
+            return Err(display);
+        };
+        let (to_send, to_send_rx) = mpsc::sync_channel::<Transfer>(1);
+        let (sent_tx, sent) = mpsc::channel();
+        // The display follows once the thread is running, so a failed spawn
+        // doesn't take it along
+        let (hand_over, handed_over) = mpsc::sync_channel::<DisplayWrapper<'static>>(1);
+        let spawned = thread::Builder::new()
+            .stack_size(SENDER_STACK_SIZE)
+            .spawn(move || {
+                let Ok(mut display) = handed_over.recv() else {
+                    return;
+                };
+                for transfer in to_send_rx {
+                    for &span in &transfer.spans {
+                        send_span(&mut display, &transfer.pixels, transfer.width, span).ok();
+                    }
+                    if sent_tx.send(transfer).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            log::warn!("Display thread failed to start: {:?}", e);
+            return Err(display);
+        }
+        hand_over.send(display).ok();
+        Ok(Self {
+            idle: Some(Transfer {
+                pixels,
+                width: size.width,
+                spans: Vec::new(),
+            }),
+            to_send,
+            sent,
+        })
+    }
+
+    /// Queue what changed in `frame`, first waiting for the previous frame
+    /// to finish sending
+    fn send(&mut self, frame: &mut FrameBuffer) {
+        let transfer = match self.idle.take() {
+            Some(transfer) => Some(transfer),
+            None => self.sent.recv().ok(),
+        };
+        // Without a transfer the thread is gone and nothing can be sent
+        let Some(mut transfer) = transfer else {
+            return;
+        };
+        frame.stage(&mut transfer);
+        if let Err(mpsc::SendError(transfer)) = self.to_send.send(transfer) {
+            self.idle = Some(transfer);
+        }
+    }
+}
+
+fn allocate_pixels(size: Size, background: Rgb565) -> Option<Vec<Rgb565>> {
+    let len = (size.width * size.height) as usize;
+    let mut pixels = Vec::new();
+    pixels.try_reserve_exact(len).ok()?;
+    pixels.resize(len, background);
+    Some(pixels)
+}
+
+/// Send `span` of a whole frame `width` pixels wide through one address
+/// window
+fn send_span(
+    display: &mut DisplayWrapper,
+    pixels: &[Rgb565],
+    width: u32,
+    span: Span,
+) -> Result<(), mipidsi::Error> {
+    let area = Rectangle::new(
+        Point::new(span.x as i32, span.y as i32),
+        Size::new(span.width, span.height),
+    );
+    let rows = (span.y..span.y + span.height).flat_map(|y| {
+        let start = (y * width + span.x) as usize;
+        pixels[start..start + span.width as usize].iter().copied()
+    });
+    display.fill_contiguous(&area, rows)
+}
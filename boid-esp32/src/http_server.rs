@@ -1,19 +1,38 @@
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Write};
 use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use boid_core::Vector2D;
-use boid_shared::{SettingsUpdate, StatusResponse, TargetPositionUpdate};
-use log::{error, info};
+use boid_core::{
+    ChangeSource, ConfigChange, ConfigParameter, ConfigTransition, FlockProfile, ParameterScales,
+    Vector2D,
+};
+use boid_shared::arbitration::TargetHeld;
+use boid_shared::mirror;
+use boid_shared::websocket::{self, Opcode};
+use boid_shared::{
+    encoding, error_code_table, limits, ArbitrationConfig, BatchCommand, BatchResponse,
+    BatchResult, BoidCountResponse, BoidCountUpdate, BoidSettings, ColorScheme, ColorSchemeUpdate,
+    ControlMessage, DepthUpdate, ErrorBody, ErrorCode, InvalidSetting, ParameterScalesUpdate,
+    Position, PresetUpdate, RenderStyle, SpellRequest, WindUpdate,
+};
+use boid_shared::{
+    ConfigChangeEntry, ConfigHistoryResponse, Finger, HealthResponse, LensCalibration,
+    SettingAdjustment, SettingsUpdate, SettingsUpdateResponse, Smoothing, StatusResponse,
+    StreamPowerResponse, TargetPositionUpdate,
+};
+use log::{error, info, warn};
 
-use crate::camera::CameraWrapper;
-use crate::types::SimulationState;
+use crate::camera::{self, CameraWrapper, PixelFormat};
+use crate::types::{SimulationState, SpellState};
 
 /// Start the HTTP server on port 80
 pub fn start_server(
-    camera: Arc<Mutex<CameraWrapper>>,
+    camera: Arc<Mutex<Option<CameraWrapper>>>,
     sim_state: Arc<Mutex<SimulationState>>,
+    health: HealthResponse,
 ) -> anyhow::Result<()> {
     let listener = TcpListener::bind("0.0.0.0:80")?;
     listener.set_nonblocking(false)?;
@@ -28,7 +47,7 @@ pub fn start_server(
 
                 // Handle each connection in the same thread (single-threaded server)
                 // For ESP32, we don't want to spawn too many threads
-                if let Err(e) = handle_client(stream, camera_clone, sim_state_clone) {
+                if let Err(e) = handle_client(stream, camera_clone, sim_state_clone, &health) {
                     error!("Error handling client: {:?}", e);
                 }
             }
@@ -43,8 +62,9 @@ pub fn start_server(
 
 fn handle_client(
     mut stream: TcpStream,
-    camera: Arc<Mutex<CameraWrapper>>,
+    camera: Arc<Mutex<Option<CameraWrapper>>>,
     sim_state: Arc<Mutex<SimulationState>>,
+    health: &HealthResponse,
 ) -> anyhow::Result<()> {
     stream.set_read_timeout(Some(Duration::from_secs(5)))?;
     stream.set_write_timeout(Some(Duration::from_secs(5)))?;
@@ -64,20 +84,129 @@ fn handle_client(
             ("GET", "/stream") => {
                 handle_mjpeg_stream(stream, camera)?;
             }
+            ("POST", "/api/stream/start") => {
+                let response = handle_stream_power(&camera, true);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/stream/stop") => {
+                let response = handle_stream_power(&camera, false);
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/ws") => {
+                handle_websocket_upgrade(stream, &request, sim_state)?;
+            }
             ("POST", "/api/position") => {
-                let response = handle_position_update(request.body, &sim_state);
+                let client = client_id(&request, &stream);
+                let response = handle_position_update(request.body, &client, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/depth") => {
+                let response = handle_depth_update(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/scales") => {
+                let response = handle_scales_update(request.body, &sim_state);
                 write_response(&mut stream, &response)?;
             }
             ("POST", "/api/settings") => {
                 let response = handle_settings_update(request.body, &sim_state);
                 write_response(&mut stream, &response)?;
             }
+            ("POST", "/api/preset") => {
+                let response = handle_preset(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/boids") => {
+                let response = handle_boid_count(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/wind") => {
+                let response = handle_wind(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/batch") => {
+                let client = client_id(&request, &stream);
+                let response = handle_batch(request.body, &client, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/colors") => {
+                let response = handle_color_scheme(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/style") => {
+                let response = handle_style_get(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/style") => {
+                let response = handle_style_update(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/settings/reset") => {
+                let response = handle_settings_reset(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/settings/undo") => {
+                let response = handle_settings_undo(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/settings/history") => {
+                let response = negotiate(&request, handle_settings_history(&sim_state));
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/calibration") => {
+                let response = handle_calibration_get(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/calibration") => {
+                let response = handle_calibration_update(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/smoothing") => {
+                let response = handle_smoothing_get(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/smoothing") => {
+                let response = handle_smoothing_update(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/arbitration") => {
+                let response = handle_arbitration_get(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/arbitration") => {
+                let response = handle_arbitration_update(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/spell") => {
+                let response = handle_spell(request.body, &sim_state);
+                write_response(&mut stream, &response)?;
+            }
+            ("POST", "/api/spell/clear") => {
+                let response = handle_spell_clear(&sim_state);
+                write_response(&mut stream, &response)?;
+            }
             ("GET", "/api/status") => {
-                let response = handle_status(&sim_state);
+                let response = negotiate(&request, handle_status(&sim_state));
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/snapshot") => {
+                let response = negotiate(&request, handle_snapshot(&sim_state));
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/flock") => {
+                let response = negotiate(&request, handle_flock(&request, &sim_state));
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/errors") => {
+                let response = negotiate(&request, handle_error_codes());
+                write_response(&mut stream, &response)?;
+            }
+            ("GET", "/api/health") => {
+                let response = negotiate(&request, handle_health(health));
                 write_response(&mut stream, &response)?;
             }
             _ => {
-                let response = Response::error(404, r#"{"error":"Not found"}"#);
+                let response = Response::error(ErrorCode::EndpointNotFound);
                 write_response(&mut stream, &response)?;
             }
         }
@@ -88,8 +217,31 @@ fn handle_client(
 
 fn handle_mjpeg_stream(
     mut stream: TcpStream,
-    camera: Arc<Mutex<CameraWrapper>>,
+    camera: Arc<Mutex<Option<CameraWrapper>>>,
 ) -> anyhow::Result<()> {
+    // Opening the stream arms the camera; it powers down again once frames
+    // stop being pulled for `camera::IDLE_TIMEOUT`
+    let woken = match camera.lock().unwrap().as_mut() {
+        Some(cam) if cam.settings().format != PixelFormat::Jpeg => {
+            let response = Response::error(ErrorCode::CameraFormatUnsupported);
+            return write_response(&mut stream, &response);
+        }
+        Some(cam) => cam.power_up(),
+        None => {
+            let response = Response::error(ErrorCode::CameraInitFailed);
+            return write_response(&mut stream, &response);
+        }
+    };
+    if let Err(e) = woken {
+        error!(
+            "[{}] Camera wake error: {:?}",
+            ErrorCode::CameraPowerFailed,
+            e
+        );
+        let response = Response::error(ErrorCode::CameraPowerFailed);
+        return write_response(&mut stream, &response);
+    }
+
     // Send MJPEG header
     let header = b"HTTP/1.1 200 OK\r\n\
                     Content-Type: multipart/x-mixed-replace; boundary=BOUNDARY\r\n\
@@ -102,10 +254,17 @@ fn handle_mjpeg_stream(
     loop {
         let jpeg_data = {
             let mut cam = camera.lock().unwrap();
+            let Some(cam) = cam.as_mut() else {
+                break;
+            };
             match cam.capture_jpeg() {
                 Ok(data) => data.to_vec(),
                 Err(e) => {
-                    error!("Camera capture error: {:?}", e);
+                    error!(
+                        "[{}] Camera capture error: {:?}",
+                        ErrorCode::CameraCaptureFailed,
+                        e
+                    );
                     break;
                 }
             }
@@ -144,69 +303,975 @@ fn handle_mjpeg_stream(
     Ok(())
 }
 
+/// Wake the camera ahead of streaming, or put it back into standby
+fn handle_stream_power(camera: &Arc<Mutex<Option<CameraWrapper>>>, power: bool) -> Response {
+    let mut camera = camera.lock().unwrap();
+    let Some(cam) = camera.as_mut() else {
+        return Response::error(ErrorCode::CameraInitFailed);
+    };
+
+    let result = if power {
+        cam.power_up()
+    } else {
+        cam.power_down()
+    };
+    if let Err(e) = result {
+        error!(
+            "[{}] Camera power error: {:?}",
+            ErrorCode::CameraPowerFailed,
+            e
+        );
+        return Response::error(ErrorCode::CameraPowerFailed);
+    }
+
+    let response = StreamPowerResponse {
+        powered: cam.is_powered(),
+        idle_timeout_secs: camera::IDLE_TIMEOUT.as_secs(),
+    };
+    match serde_json::to_string(&response) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+/// Table of every error code with its description, for UIs and docs
+fn handle_error_codes() -> Response {
+    match serde_json::to_string(&error_code_table()) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
 fn handle_position_update(
     body: &[u8],
+    client: &str,
     sim_state: &Arc<Mutex<SimulationState>>,
 ) -> Response {
     match serde_json::from_slice::<TargetPositionUpdate>(body) {
-        Ok(update) => {
+        Ok(update) => match set_target(&mut sim_state.lock().unwrap(), client, update.position) {
+            Ok(()) => Response::ok(r#"{"status":"ok"}"#),
+            Err(held) => Response::error_body(ErrorCode::TargetHeld, target_held_body(&held)),
+        },
+        Err(_) => Response::error(ErrorCode::TargetParseFailed),
+    }
+}
+
+/// Who sent a request, for target arbitration: the `X-Client-Id` header if
+/// given, the peer's address otherwise, so the same device is one client
+/// over HTTP, the WebSocket and UDP
+fn client_id(request: &HttpRequest, stream: &TcpStream) -> String {
+    match request.header("X-Client-Id").filter(|id| !id.is_empty()) {
+        Some(id) => id.chars().take(limits::CLIENT_ID_MAX_CHARS).collect(),
+        None => stream
+            .peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|_| "unknown".to_string()),
+    }
+}
+
+fn target_held_body(held: &TargetHeld) -> String {
+    let message = format!("{} is controlling the flock.", held.controller);
+    ErrorBody::with_message(ErrorCode::TargetHeld, message).to_json()
+}
+
+/// Make a position from the camera stream of `client` the flock target,
+/// unless the arbiter gives the flock to another client
+pub(crate) fn set_target(
+    state: &mut SimulationState,
+    client: &str,
+    position: Option<Position>,
+) -> Result<(), TargetHeld> {
+    // Undo the lens distortion before the position becomes a target
+    let calibration = state.calibration;
+    let (width, height) = state.frame_size.dimensions();
+    let position = position.map(|p| calibration.undistort(p, width as f32, height as f32));
+    let elapsed = state.started.elapsed();
+    if state
+        .arbiter
+        .submit(client, position, elapsed.as_millis() as u64)?
+    {
+        // Don't glide from the previous client's target to the new one
+        state.smoother.reset();
+        state.target_path.clear();
+    }
+    // Merged targets go to the flock as posted, so the smoother and path
+    // only ever follow one client
+    if state.arbiter.is_merging() {
+        return Ok(());
+    }
+
+    let position = position.map(|p| state.smoother.smooth_point(Finger::Index.tip(), p));
+    if position.is_none() {
+        state.smoother.reset();
+    }
+    state.target_position = position.map(|p| Vector2D::new(p.x, p.y));
+    match state.target_position {
+        Some(target) => state.target_path.push(elapsed.as_secs_f64(), target),
+        None => state.target_path.clear(),
+    }
+    Ok(())
+}
+
+fn handle_arbitration_get(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let state = sim_state.lock().unwrap();
+    match serde_json::to_string(state.arbiter.config()) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn handle_arbitration_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<ArbitrationConfig>(body) {
+        Ok(config) => {
+            info!("Target arbitration set to {:?}", config.policy);
             let mut state = sim_state.lock().unwrap();
-            state.target_position = update.position.map(|p| Vector2D::new(p.x, p.y));
+            // The smoothed single target starts afresh from the next update
+            if config.policy != state.arbiter.config().policy {
+                state.target_position = None;
+                state.target_path.clear();
+                state.smoother.reset();
+            }
+            state.arbiter.set_config(config);
+            Response::ok(r#"{"status":"ok"}"#)
+        }
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
+    }
+}
+
+/// Store the hand depth from a stereo client; `false` if it is not finite
+fn set_depth(state: &mut SimulationState, depth: Option<f32>) -> bool {
+    if depth.is_some_and(|depth| !depth.is_finite()) {
+        return false;
+    }
+    state.hand_depth = depth.map(|depth| depth.clamp(0.0, 1.0));
+    true
+}
+
+fn handle_depth_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<DepthUpdate>(body) {
+        Ok(update) if set_depth(&mut sim_state.lock().unwrap(), update.depth) => {
+            Response::ok(r#"{"status":"ok"}"#)
+        }
+        Ok(_) => Response::error(ErrorCode::InvalidSetting),
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
+    }
+}
+
+/// Store the multipliers a client's input mappings put on the config;
+/// `false` if one names no parameter or is not finite
+fn set_scales(state: &mut SimulationState, update: &ParameterScalesUpdate) -> bool {
+    let (min, max) = limits::PARAMETER_SCALE;
+    let mut scales = ParameterScales::IDENTITY;
+    for (name, &factor) in &update.scales {
+        match ConfigParameter::parse(name) {
+            Some(parameter) if factor.is_finite() => {
+                scales.scale(parameter, factor.clamp(min, max));
+            }
+            _ => return false,
+        }
+    }
+    state.scales = scales;
+    true
+}
+
+fn handle_scales_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<ParameterScalesUpdate>(body) {
+        Ok(update) if set_scales(&mut sim_state.lock().unwrap(), &update) => {
+            Response::ok(r#"{"status":"ok"}"#)
+        }
+        Ok(_) => Response::error(ErrorCode::InvalidMapping),
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
+    }
+}
+
+fn handle_calibration_get(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let calibration = sim_state.lock().unwrap().calibration;
+    match serde_json::to_string(&calibration) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn handle_calibration_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<LensCalibration>(body) {
+        Ok(calibration) if calibration.is_valid() => {
+            sim_state.lock().unwrap().calibration = calibration;
+            info!(
+                "Lens calibration set to k1={} k2={}",
+                calibration.k1, calibration.k2
+            );
             Response::ok(r#"{"status":"ok"}"#)
         }
-        Err(_) => Response::error(400, r#"{"error":"Invalid JSON"}"#),
+        Ok(_) => Response::error(ErrorCode::InvalidCalibration),
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
     }
 }
 
+fn handle_smoothing_get(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let smoothing = sim_state.lock().unwrap().smoother.smoothing();
+    match serde_json::to_string(&smoothing) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn handle_smoothing_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<Smoothing>(body) {
+        Ok(smoothing) if smoothing.is_valid() => {
+            sim_state.lock().unwrap().smoother.set_smoothing(smoothing);
+            info!("Target smoothing set to {:?}", smoothing);
+            Response::ok(r#"{"status":"ok"}"#)
+        }
+        Ok(_) => Response::error(ErrorCode::InvalidSetting),
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
+    }
+}
+
+fn handle_spell(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let request = match serde_json::from_slice::<SpellRequest>(body) {
+        Ok(request) => request,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    let text = request.text.trim();
+    if text.is_empty() || text.chars().count() > limits::SPELL_TEXT_MAX_CHARS {
+        return Response::error(ErrorCode::InvalidText);
+    }
+
+    info!("Spelling \"{}\" (hold {}s)", text, request.hold_secs);
+    let until = (request.hold_secs > 0)
+        .then(|| Instant::now() + Duration::from_secs(request.hold_secs as u64));
+    let mut state = sim_state.lock().unwrap();
+    state.spell = Some(SpellState {
+        text: text.to_string(),
+        until,
+    });
+    state.spell_changed = true;
+    Response::ok(r#"{"status":"ok"}"#)
+}
+
+fn handle_spell_clear(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let mut state = sim_state.lock().unwrap();
+    state.spell = None;
+    state.spell_changed = true;
+    Response::ok(r#"{"status":"ok"}"#)
+}
+
 fn handle_settings_update(
     body: &[u8],
     sim_state: &Arc<Mutex<SimulationState>>,
 ) -> Response {
-    match serde_json::from_slice::<SettingsUpdate>(body) {
-        Ok(update) => {
-            let mut state = sim_state.lock().unwrap();
-            state.config.separation_weight = update.settings.separation_weight;
-            state.config.alignment_weight = update.settings.alignment_weight;
-            state.config.cohesion_weight = update.settings.cohesion_weight;
-            state.config.max_speed = update.settings.max_speed;
-            state.config.max_force = update.settings.max_force;
+    let update = match serde_json::from_slice::<SettingsUpdate>(body) {
+        Ok(update) => update,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+
+    match apply_settings(update, sim_state) {
+        Ok(response) => match serde_json::to_string(&response) {
+            Ok(json) => Response::json(&json),
+            Err(_) => Response::error(ErrorCode::SerializationFailed),
+        },
+        Err(invalid) => Response::invalid_setting(invalid),
+    }
+}
+
+/// Resize the flock; the main loop spawns or removes boids on its next tick
+fn handle_boid_count(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let update = match serde_json::from_slice::<BoidCountUpdate>(body) {
+        Ok(update) => update,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    let boid_count = clamp_boid_count(update.count);
+    sim_state.lock().unwrap().persisted.boid_count = boid_count;
+
+    let response = BoidCountResponse {
+        boid_count,
+        capacity: crate::NUM_BOIDS,
+    };
+    match serde_json::to_string(&response) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn clamp_boid_count(requested: u32) -> usize {
+    let boid_count = (requested as usize).clamp(1, crate::NUM_BOIDS);
+    if boid_count != requested as usize {
+        warn!("Clamped boid_count from {} to {}", requested, boid_count);
+    }
+    boid_count
+}
+
+/// Set the wind; the main loop applies it on its next tick
+fn handle_wind(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let mut wind = match serde_json::from_slice::<WindUpdate>(body) {
+        Ok(wind) => wind,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    if let Err(invalid) = wind.clamp_to_limits() {
+        return Response::invalid_setting(invalid);
+    }
+    info!("Wind set to {:?}", wind);
+    sim_state.lock().unwrap().wind = Some(wind);
+    Response::ok(r#"{"status":"ok"}"#)
+}
+
+/// Switch boid colors; kept across reboots like the settings
+fn handle_color_scheme(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let update = match serde_json::from_slice::<ColorSchemeUpdate>(body) {
+        Ok(update) => update,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    info!("Color scheme set to {}", update.scheme.as_str());
+    sim_state.lock().unwrap().persisted.style.palette = update.scheme;
+    Response::ok(r#"{"status":"ok"}"#)
+}
+
+fn handle_style_get(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let style = sim_state.lock().unwrap().persisted.style;
+    match serde_json::to_string(&style) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+/// Set boid size, palette and shape at once; kept across reboots like the
+/// settings
+fn handle_style_update(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    match serde_json::from_slice::<RenderStyle>(body) {
+        Ok(style) if style.is_valid() => {
+            info!("Render style set to {:?}", style);
+            sim_state.lock().unwrap().persisted.style = style;
             Response::ok(r#"{"status":"ok"}"#)
         }
-        Err(_) => Response::error(400, r#"{"error":"Invalid JSON"}"#),
+        Ok(_) => Response::error(ErrorCode::InvalidSetting),
+        Err(_) => Response::error(ErrorCode::InvalidRequestBody),
     }
 }
 
-fn handle_status(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+fn invalid_setting_body(invalid: InvalidSetting) -> String {
+    invalid_setting_error(invalid).to_json()
+}
+
+fn invalid_setting_error(invalid: InvalidSetting) -> ErrorBody {
+    ErrorBody::with_message(
+        ErrorCode::InvalidSetting,
+        format!("Invalid value for {}", invalid.field),
+    )
+}
+
+/// Validate settings from a client and apply them to the simulation
+fn apply_settings(
+    update: SettingsUpdate,
+    sim_state: &Arc<Mutex<SimulationState>>,
+) -> Result<SettingsUpdateResponse, InvalidSetting> {
+    let (settings, boid_count, adjusted) = validate_settings_update(update)?;
+    set_settings(&mut sim_state.lock().unwrap(), settings, boid_count);
+    Ok(SettingsUpdateResponse {
+        status: "ok".to_string(),
+        adjusted,
+    })
+}
+
+/// Settings and flock size from a client, clamped, with what was adjusted
+fn validate_settings_update(
+    update: SettingsUpdate,
+) -> Result<(BoidSettings, Option<usize>, Vec<SettingAdjustment>), InvalidSetting> {
+    let SettingsUpdate {
+        mut settings,
+        boid_count,
+    } = update;
+    let mut adjusted = validate_settings(&mut settings)?;
+    let boid_count = boid_count.map(|requested| {
+        let applied = (requested as usize).clamp(1, crate::NUM_BOIDS);
+        if applied != requested as usize {
+            log::warn!("Clamped boid_count from {} to {}", requested, applied);
+            adjusted.push(SettingAdjustment {
+                field: "boid_count".to_string(),
+                requested: requested as f32,
+                applied: applied as f32,
+            });
+        }
+        applied
+    });
+    Ok((settings, boid_count, adjusted))
+}
+
+fn set_settings(state: &mut SimulationState, settings: BoidSettings, boid_count: Option<usize>) {
+    record_change(state, "settings");
+    // Explicit settings take over from a preset crossfade
+    state.transition = None;
+    warn_ignored(&BoidSettings::from(&state.config), &settings);
+    state.config = settings.apply_to(state.config);
+    state.persisted.settings = settings;
+    if let Some(boid_count) = boid_count {
+        state.persisted.boid_count = boid_count;
+    }
+}
+
+/// Warn about changed settings that the flock profile compiled in has no
+/// use for; they are kept, so a build with another profile picks them up
+fn warn_ignored(before: &BoidSettings, after: &BoidSettings) {
+    let check = |field: &str, old: f32, new: f32| {
+        if old != new && crate::Profile::ignores(field) {
+            warn!("{} has no effect with this build's flock profile", field);
+        }
+    };
+    check(
+        "separation_weight",
+        before.separation_weight,
+        after.separation_weight,
+    );
+    check(
+        "alignment_weight",
+        before.alignment_weight,
+        after.alignment_weight,
+    );
+    check(
+        "cohesion_weight",
+        before.cohesion_weight,
+        after.cohesion_weight,
+    );
+    check("seek_weight", before.seek_weight, after.seek_weight);
+    if let (Some(old), Some(new)) = (before.seek_radius, after.seek_radius) {
+        check("seek_radius", old, new);
+    }
+}
+
+/// Never trust client values: out-of-range settings are clamped, non-finite ones rejected
+fn validate_settings(
+    settings: &mut BoidSettings,
+) -> Result<Vec<SettingAdjustment>, InvalidSetting> {
+    let mut adjusted = Vec::new();
+    settings.clamp_to_limits(|field, requested, applied| {
+        log::warn!("Clamped {} from {} to {}", field, requested, applied);
+        adjusted.push(SettingAdjustment {
+            field: field.to_string(),
+            requested,
+            applied,
+        });
+    })?;
+    Ok(adjusted)
+}
+
+fn record_change(state: &mut SimulationState, field: &'static str) {
+    let previous = state.config;
+    state.history.record(ConfigChange {
+        field,
+        source: ChangeSource::Api,
+        timestamp_ms: uptime_ms(),
+        previous,
+    });
+}
+
+fn handle_preset(body: &[u8], sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let PresetUpdate {
+        mut settings,
+        duration_ticks,
+    } = match serde_json::from_slice::<PresetUpdate>(body) {
+        Ok(update) => update,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    let adjusted = match validate_settings(&mut settings) {
+        Ok(adjusted) => adjusted,
+        Err(invalid) => return Response::invalid_setting(invalid),
+    };
+
+    start_preset(&mut sim_state.lock().unwrap(), settings, duration_ticks);
+
+    let response = SettingsUpdateResponse {
+        status: "ok".to_string(),
+        adjusted,
+    };
+    match serde_json::to_string(&response) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn start_preset(state: &mut SimulationState, settings: BoidSettings, duration_ticks: u32) {
+    record_change(state, "preset");
+    let preset = settings.apply_to(state.config);
+    state.persisted.settings = settings;
+    let ticks = duration_ticks.min(limits::PRESET_TICKS_MAX);
+    if ticks == 0 {
+        state.config = preset;
+        state.transition = None;
+    } else {
+        state.transition = Some(ConfigTransition::new(state.config, preset, ticks));
+    }
+}
+
+/// A batch command that passed validation
+enum Prepared {
+    Position(Option<Position>),
+    Settings(BoidSettings, Option<usize>),
+    Preset(BoidSettings, u32),
+    Boids(usize),
+    Wind(WindUpdate),
+    Colors(ColorScheme),
+}
+
+/// Validate one batch command without touching the simulation
+fn prepare(command: BatchCommand) -> Result<(Prepared, Vec<SettingAdjustment>), ErrorBody> {
+    Ok(match command {
+        BatchCommand::Position(update) => (Prepared::Position(update.position), Vec::new()),
+        BatchCommand::Settings(update) => {
+            let (settings, boid_count, adjusted) =
+                validate_settings_update(update).map_err(invalid_setting_error)?;
+            (Prepared::Settings(settings, boid_count), adjusted)
+        }
+        BatchCommand::Preset(PresetUpdate {
+            mut settings,
+            duration_ticks,
+        }) => {
+            let adjusted = validate_settings(&mut settings).map_err(invalid_setting_error)?;
+            (Prepared::Preset(settings, duration_ticks), adjusted)
+        }
+        BatchCommand::Boids(update) => {
+            (Prepared::Boids(clamp_boid_count(update.count)), Vec::new())
+        }
+        BatchCommand::Wind(mut wind) => {
+            wind.clamp_to_limits().map_err(invalid_setting_error)?;
+            (Prepared::Wind(wind), Vec::new())
+        }
+        BatchCommand::Colors(update) => (Prepared::Colors(update.scheme), Vec::new()),
+    })
+}
+
+/// Run one validated command; the batch checked beforehand that `client`
+/// may set the target
+fn apply(state: &mut SimulationState, client: &str, command: Prepared) {
+    match command {
+        Prepared::Position(position) => {
+            if let Err(held) = set_target(state, client, position) {
+                warn!("Batch target refused: {} holds the flock", held.controller);
+            }
+        }
+        Prepared::Settings(settings, boid_count) => set_settings(state, settings, boid_count),
+        Prepared::Preset(settings, duration_ticks) => start_preset(state, settings, duration_ticks),
+        Prepared::Boids(boid_count) => state.persisted.boid_count = boid_count,
+        Prepared::Wind(wind) => state.wind = Some(wind),
+        Prepared::Colors(scheme) => state.persisted.style.palette = scheme,
+    }
+}
+
+/// Apply several commands in order under one lock, so the main loop picks
+/// them all up in the same tick and never shows the states in between. One
+/// invalid command rejects the whole batch.
+fn handle_batch(body: &[u8], client: &str, sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let commands = match serde_json::from_slice::<Vec<BatchCommand>>(body) {
+        Ok(commands) => commands,
+        Err(_) => return Response::error(ErrorCode::InvalidRequestBody),
+    };
+    if commands.is_empty() || commands.len() > limits::BATCH_COMMANDS_MAX {
+        return Response::error(ErrorCode::InvalidBatch);
+    }
+
+    let mut prepared = Vec::with_capacity(commands.len());
+    let mut results = Vec::with_capacity(commands.len());
+    for command in commands {
+        match prepare(command) {
+            Ok((command, adjusted)) => {
+                prepared.push(command);
+                results.push(BatchResult {
+                    error: None,
+                    adjusted,
+                });
+            }
+            Err(error) => results.push(BatchResult {
+                error: Some(error),
+                adjusted: Vec::new(),
+            }),
+        }
+    }
+
+    let applied = prepared.len() == results.len();
+    if applied {
+        let mut state = sim_state.lock().unwrap();
+        // A target the arbiter would refuse rejects the whole batch
+        if prepared
+            .iter()
+            .any(|command| matches!(command, Prepared::Position(_)))
+        {
+            let now_ms = state.started.elapsed().as_millis() as u64;
+            if let Err(held) = state.arbiter.check(client, now_ms) {
+                return Response::error_body(ErrorCode::TargetHeld, target_held_body(&held));
+            }
+        }
+        for command in prepared {
+            apply(&mut state, client, command);
+        }
+        info!("Applied a batch of {} commands", results.len());
+    }
+    let response = BatchResponse {
+        applied,
+        results,
+        error: (!applied).then(|| ErrorBody::new(ErrorCode::InvalidBatch)),
+    };
+    match serde_json::to_string(&response) {
+        Ok(json) if applied => Response::json(&json),
+        Ok(json) => Response::error_body(ErrorCode::InvalidBatch, json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn handle_settings_undo(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let mut state = sim_state.lock().unwrap();
+    match state.history.undo() {
+        Some(change) => {
+            state.transition = None;
+            state.config = change.previous;
+            state.persisted.settings = BoidSettings::from(&change.previous);
+            info!(
+                "Undid {} change from {}",
+                change.field,
+                change.source.as_str()
+            );
+            Response::ok(r#"{"status":"ok"}"#)
+        }
+        None => Response::error(ErrorCode::NothingToUndo),
+    }
+}
+
+/// Back to the settings and flock size the firmware boots with, erasing
+/// the saved ones
+fn handle_settings_reset(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let mut state = sim_state.lock().unwrap();
+    record_change(&mut state, "reset");
+    state.transition = None;
+    state.config = crate::default_config();
+    state.persisted = crate::default_persisted();
+    state.forget_persisted = true;
+    info!("Settings reset to defaults");
+    Response::ok(r#"{"status":"ok"}"#)
+}
+
+fn handle_settings_history(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
     let state = sim_state.lock().unwrap();
-    let status = StatusResponse {
-        boid_count: 20, // NUM_BOIDS from main
-        fps: 30,
-        target_active: state.target_position.is_some(),
+    let history = ConfigHistoryResponse {
+        changes: state
+            .history
+            .iter()
+            .map(|change| ConfigChangeEntry {
+                field: change.field.to_string(),
+                source: change.source.as_str().to_string(),
+                timestamp_ms: change.timestamp_ms,
+            })
+            .collect(),
     };
 
+    match serde_json::to_string(&history) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+/// Milliseconds since boot
+fn uptime_ms() -> u64 {
+    // SAFETY: esp_timer_get_time has no preconditions
+    (unsafe { esp_idf_svc::sys::esp_timer_get_time() } / 1000) as u64
+}
+
+pub fn status_response(state: &SimulationState) -> StatusResponse {
+    let now_ms = state.started.elapsed().as_millis() as u64;
+    let controllers: Vec<String> = state
+        .arbiter
+        .controllers(now_ms)
+        .map(str::to_owned)
+        .collect();
+    StatusResponse {
+        boid_count: state.boid_count,
+        fps: state.fps,
+        target_active: match state.arbiter.is_merging() {
+            true => !controllers.is_empty(),
+            false => state.target_position.is_some(),
+        },
+        controllers,
+    }
+}
+
+fn handle_status(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let status = status_response(&sim_state.lock().unwrap());
+
     match serde_json::to_string(&status) {
         Ok(json) => Response::json(&json),
-        Err(_) => Response::error(500, r#"{"error":"Serialization failed"}"#),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+fn handle_snapshot(sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let state = sim_state.lock().unwrap();
+    match state.snapshot {
+        Some(ref json) => Response::json(json),
+        None => Response::error(ErrorCode::SnapshotUnavailable),
+    }
+}
+
+/// Every boid of the last frame, as compact JSON or in the binary layout
+/// of `boid_shared::mirror` when the client accepts it
+fn handle_flock(request: &HttpRequest, sim_state: &Arc<Mutex<SimulationState>>) -> Response {
+    let binary = request
+        .header("Accept")
+        .is_some_and(|accept| accept.contains(mirror::BINARY_CONTENT_TYPE));
+    let state = sim_state.lock().unwrap();
+    if binary {
+        return Response::binary(state.flock_frame.to_bytes());
+    }
+    match serde_json::to_string(&state.flock_frame) {
+        Ok(json) => Response::json(&json),
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
     }
 }
 
-fn write_response(stream: &mut TcpStream, response: &Response) -> anyhow::Result<()> {
+fn handle_health(health: &HealthResponse) -> Response {
+    match serde_json::to_string(health) {
+        // Report failures with 503 so simple probes don't need to parse the body
+        Ok(json) if health.is_healthy() => Response::json(&json),
+        Ok(json) => Response {
+            status: 503,
+            ..Response::json(&json)
+        },
+        Err(_) => Response::error(ErrorCode::SerializationFailed),
+    }
+}
+
+// Only one control channel at a time; each holds a thread and its stack
+static WEBSOCKET_ACTIVE: AtomicBool = AtomicBool::new(false);
+const WEBSOCKET_STACK_SIZE: usize = 8 * 1024;
+// How often StatusResponse is pushed over the control channel
+const STATUS_PUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Upgrade to a WebSocket control channel and serve it on its own thread,
+/// so the single-threaded HTTP server keeps answering other requests
+fn handle_websocket_upgrade(
+    mut stream: TcpStream,
+    request: &HttpRequest,
+    sim_state: Arc<Mutex<SimulationState>>,
+) -> anyhow::Result<()> {
+    let key = match (
+        request.header("Upgrade"),
+        request.header("Sec-WebSocket-Key"),
+    ) {
+        (Some(upgrade), Some(key)) if upgrade.eq_ignore_ascii_case("websocket") => key,
+        _ => {
+            let response = Response::error(ErrorCode::WebSocketExpected);
+            return write_response(&mut stream, &response);
+        }
+    };
+    if WEBSOCKET_ACTIVE.swap(true, Ordering::AcqRel) {
+        let response = Response::error(ErrorCode::ControlChannelBusy);
+        return write_response(&mut stream, &response);
+    }
+
+    let handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    );
+    if let Err(e) = stream.write_all(handshake.as_bytes()) {
+        WEBSOCKET_ACTIVE.store(false, Ordering::Release);
+        return Err(e.into());
+    }
+
+    let client = client_id(request, &stream);
+    info!("WebSocket control channel opened by {}", client);
+    let spawned = thread::Builder::new()
+        .stack_size(WEBSOCKET_STACK_SIZE)
+        .spawn(move || {
+            if let Err(e) = run_websocket(stream, &client, &sim_state) {
+                warn!("WebSocket control channel failed: {:?}", e);
+            }
+            WEBSOCKET_ACTIVE.store(false, Ordering::Release);
+            info!("WebSocket control channel closed");
+        });
+    if let Err(e) = spawned {
+        WEBSOCKET_ACTIVE.store(false, Ordering::Release);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn run_websocket(
+    mut stream: TcpStream,
+    client: &str,
+    sim_state: &Arc<Mutex<SimulationState>>,
+) -> anyhow::Result<()> {
+    // Reads time out so status pushes keep flowing while the client is quiet
+    stream.set_read_timeout(Some(STATUS_PUSH_INTERVAL))?;
+
+    let mut pending = Vec::new();
+    let mut chunk = [0u8; 512];
+    let mut last_status = Instant::now();
+
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => pending.extend_from_slice(&chunk[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        loop {
+            let frame = match websocket::decode_frame(&pending) {
+                Ok(Some((frame, used))) => {
+                    pending.drain(..used);
+                    frame
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    stream.write_all(&websocket::encode_close(
+                        websocket::CLOSE_PROTOCOL_ERROR,
+                        None,
+                    ))?;
+                    anyhow::bail!("Invalid frame: {:?}", e);
+                }
+            };
+
+            match frame.opcode {
+                Opcode::Text if frame.fin => {
+                    if let Some(reply) = handle_control_message(&frame.payload, client, sim_state) {
+                        stream.write_all(&websocket::encode_frame(
+                            Opcode::Text,
+                            reply.as_bytes(),
+                            None,
+                        ))?;
+                    }
+                }
+                Opcode::Ping => {
+                    stream.write_all(&websocket::encode_frame(
+                        Opcode::Pong,
+                        &frame.payload,
+                        None,
+                    ))?;
+                }
+                Opcode::Pong => {}
+                Opcode::Close => {
+                    stream.write_all(&websocket::encode_close(websocket::CLOSE_NORMAL, None))?;
+                    return Ok(());
+                }
+                // Binary and fragmented messages are not part of the protocol
+                _ => {
+                    stream.write_all(&websocket::encode_close(
+                        websocket::CLOSE_UNSUPPORTED_DATA,
+                        None,
+                    ))?;
+                    anyhow::bail!("Unsupported frame: {:?}", frame.opcode);
+                }
+            }
+        }
+
+        if last_status.elapsed() >= STATUS_PUSH_INTERVAL {
+            let status = status_response(&sim_state.lock().unwrap());
+            let json = serde_json::to_string(&status)?;
+            stream.write_all(&websocket::encode_frame(
+                Opcode::Text,
+                json.as_bytes(),
+                None,
+            ))?;
+            last_status = Instant::now();
+        }
+    }
+}
+
+/// Apply one control channel message; returns the reply to send, if any.
+/// Position updates are not acknowledged to keep the stream lean; only
+/// refused ones are answered.
+fn handle_control_message(
+    payload: &[u8],
+    client: &str,
+    sim_state: &Arc<Mutex<SimulationState>>,
+) -> Option<String> {
+    match serde_json::from_slice::<ControlMessage>(payload) {
+        Ok(ControlMessage::Position(update)) => {
+            set_target(&mut sim_state.lock().unwrap(), client, update.position)
+                .err()
+                .map(|held| target_held_body(&held))
+        }
+        Ok(ControlMessage::Settings(update)) => match apply_settings(update, sim_state) {
+            Ok(response) => serde_json::to_string(&response).ok(),
+            Err(invalid) => Some(invalid_setting_body(invalid)),
+        },
+        Ok(ControlMessage::Depth(update)) => {
+            if set_depth(&mut sim_state.lock().unwrap(), update.depth) {
+                None
+            } else {
+                Some(ErrorBody::new(ErrorCode::InvalidSetting).to_json())
+            }
+        }
+        Ok(ControlMessage::Scales(update)) => {
+            if set_scales(&mut sim_state.lock().unwrap(), &update) {
+                None
+            } else {
+                Some(ErrorBody::new(ErrorCode::InvalidMapping).to_json())
+            }
+        }
+        Err(_) => Some(ErrorBody::new(ErrorCode::InvalidControlMessage).to_json()),
+    }
+}
+
+// Bodies smaller than this are sent uncompressed; gzip overhead isn't worth it
+const GZIP_MIN_BYTES: usize = 256;
+
+/// Apply conditional-request and content-encoding negotiation to a
+/// successful GET response
+fn negotiate(request: &HttpRequest, mut response: Response) -> Response {
+    if response.status != 200 {
+        return response;
+    }
+
+    // Polling clients that already have this payload get an empty 304
+    let etag = encoding::etag(&response.body);
+    if let Some(if_none_match) = request.header("If-None-Match") {
+        if encoding::etag_matches(if_none_match, &etag) {
+            return Response {
+                status: 304,
+                body: Vec::new(),
+                etag: Some(etag),
+                ..response
+            };
+        }
+    }
+    response.etag = Some(etag);
+
+    let accepts_gzip = request
+        .header("Accept-Encoding")
+        .is_some_and(encoding::accepts_gzip);
+    if accepts_gzip && response.body.len() >= GZIP_MIN_BYTES {
+        response.body = encoding::gzip(&response.body);
+        response.content_encoding = Some("gzip");
+    }
+
+    response
+}
+
+pub(crate) fn write_response(stream: &mut TcpStream, response: &Response) -> anyhow::Result<()> {
     let status_text = match response.status {
         200 => "OK",
+        304 => "Not Modified",
         400 => "Bad Request",
         404 => "Not Found",
+        409 => "Conflict",
         500 => "Internal Server Error",
+        503 => "Service Unavailable",
         _ => "Unknown",
     };
 
-    let header = format!(
+    let mut header = format!(
         "HTTP/1.1 {} {}\r\n\
          Content-Type: {}\r\n\
          Content-Length: {}\r\n\
-         Access-Control-Allow-Origin: *\r\n\
-         \r\n",
+         Access-Control-Allow-Origin: *\r\n",
         response.status, status_text, response.content_type, response.body.len()
     );
+    if let Some(ref etag) = response.etag {
+        header.push_str(&format!("ETag: {}\r\nVary: Accept-Encoding\r\n", etag));
+    }
+    if let Some(content_encoding) = response.content_encoding {
+        header.push_str(&format!("Content-Encoding: {}\r\n", content_encoding));
+    }
+    header.push_str("\r\n");
 
     stream.write_all(header.as_bytes())?;
     stream.write_all(&response.body)?;
@@ -215,10 +1280,12 @@ fn write_response(stream: &mut TcpStream, response: &Response) -> anyhow::Result
     Ok(())
 }
 
-struct Response {
+pub(crate) struct Response {
     status: u16,
     body: Vec<u8>,
     content_type: &'static str,
+    etag: Option<String>,
+    content_encoding: Option<&'static str>,
 }
 
 impl Response {
@@ -227,6 +1294,8 @@ impl Response {
             status: 200,
             body: body.as_bytes().to_vec(),
             content_type: "application/json",
+            etag: None,
+            content_encoding: None,
         }
     }
 
@@ -235,26 +1304,60 @@ impl Response {
             status: 200,
             body: body.as_bytes().to_vec(),
             content_type: "application/json",
+            etag: None,
+            content_encoding: None,
         }
     }
 
-    fn error(status: u16, message: &str) -> Self {
+    fn binary(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            body,
+            content_type: mirror::BINARY_CONTENT_TYPE,
+            etag: None,
+            content_encoding: None,
+        }
+    }
+
+    pub(crate) fn html(status: u16, body: &str) -> Self {
         Self {
             status,
-            body: message.as_bytes().to_vec(),
+            body: body.as_bytes().to_vec(),
+            content_type: "text/html; charset=utf-8",
+            etag: None,
+            content_encoding: None,
+        }
+    }
+
+    /// Error with the code's status and an `ErrorBody` describing it
+    fn error(code: ErrorCode) -> Self {
+        Self::error_body(code, ErrorBody::new(code).to_json())
+    }
+
+    fn invalid_setting(invalid: InvalidSetting) -> Self {
+        Self::error_body(ErrorCode::InvalidSetting, invalid_setting_body(invalid))
+    }
+
+    fn error_body(code: ErrorCode, body: String) -> Self {
+        Self {
+            status: code.http_status(),
+            body: body.into_bytes(),
             content_type: "application/json",
+            etag: None,
+            content_encoding: None,
         }
     }
 }
 
-struct HttpRequest<'a> {
-    method: &'a str,
-    path: &'a str,
-    body: &'a [u8],
+pub(crate) struct HttpRequest<'a> {
+    pub(crate) method: &'a str,
+    pub(crate) path: &'a str,
+    headers: &'a str,
+    pub(crate) body: &'a [u8],
 }
 
 impl<'a> HttpRequest<'a> {
-    fn parse(data: &'a [u8]) -> Option<Self> {
+    pub(crate) fn parse(data: &'a [u8]) -> Option<Self> {
         let request_str = std::str::from_utf8(data).ok()?;
         let mut lines = request_str.lines();
         let request_line = lines.next()?;
@@ -263,15 +1366,53 @@ impl<'a> HttpRequest<'a> {
         let method = parts.next()?;
         let path = parts.next()?;
 
-        // Find body (after \r\n\r\n)
-        let body_start = data
-            .windows(4)
-            .position(|w| w == b"\r\n\r\n")
-            .map(|pos| pos + 4)
-            .unwrap_or(data.len());
+        // Header lines sit between the request line and the blank line; a
+        // truncated read without one is all headers
+        let blank_line = data.windows(4).position(|w| w == b"\r\n\r\n");
+        let headers_end = blank_line.unwrap_or(data.len()).max(request_line.len());
 
+        // Find body (after \r\n\r\n)
+        let body_start = blank_line.map_or(data.len(), |pos| pos + 4);
         let body = &data[body_start..];
 
-        Some(HttpRequest { method, path, body })
+        let headers = request_str.get(request_line.len()..headers_end)?;
+
+        Some(HttpRequest {
+            method,
+            path,
+            headers,
+            body,
+        })
+    }
+
+    /// Value of the first header named `name` (case-insensitive)
+    fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then_some(value.trim())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_without_blank_line() {
+        let request = HttpRequest::parse(
+            b"GET /api/status HTTP/1.1\r\nAccept-Encoding: gzip\r\nIf-None-Match: \"abc\"",
+        )
+        .unwrap();
+        assert_eq!((request.method, request.path), ("GET", "/api/status"));
+        assert_eq!(request.header("Accept-Encoding"), Some("gzip"));
+        assert_eq!(request.header("If-None-Match"), Some("\"abc\""));
+        assert!(request.body.is_empty());
+
+        let request = HttpRequest::parse(b"POST /api/depth HTTP/1.1\r\nX: 1\r\n\r\n{}").unwrap();
+        assert_eq!(request.header("x"), Some("1"));
+        assert_eq!(request.body, b"{}");
     }
 }
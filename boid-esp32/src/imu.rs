@@ -0,0 +1,90 @@
+// Orientation input for handheld builds
+//
+// Reads an MPU6050-compatible accelerometer over I2C and publishes the
+// in-plane component of gravity. The main loop turns this into a global
+// force on the flock, so tilting the device makes the boids pour toward the
+// low edge of the screen.
+//
+// Wiring (XIAO ESP32S3 Sense, free header pins):
+// - SDA: GPIO1 (D0)
+// - SCL: GPIO2 (D1)
+//
+// The sensor's X/Y axes are assumed to line up with the display's X/Y axes.
+// Flip the signs in `AXIS_SIGN` if the IMU is mounted rotated.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use boid_core::Vector2D;
+use esp_idf_hal::{
+    delay::BLOCK,
+    gpio::{InputPin, OutputPin},
+    i2c::{I2c, I2cConfig, I2cDriver},
+    peripheral::Peripheral,
+    prelude::*,
+};
+use log::{info, warn};
+
+const MPU6050_ADDR: u8 = 0x68;
+const REG_PWR_MGMT_1: u8 = 0x6B;
+const REG_ACCEL_XOUT_H: u8 = 0x3B;
+
+// Default ±2g range
+const LSB_PER_G: f32 = 16384.0;
+
+const AXIS_SIGN: (f32, f32) = (1.0, 1.0);
+
+// Readings below this tilt (in g) are treated as lying flat
+const DEAD_ZONE_G: f32 = 0.05;
+
+const POLL_INTERVAL_MS: u64 = 20;
+
+pub struct Imu<'d> {
+    i2c: I2cDriver<'d>,
+}
+
+impl<'d> Imu<'d> {
+    pub fn new(
+        i2c: impl Peripheral<P = impl I2c> + 'd,
+        sda: impl Peripheral<P = impl InputPin + OutputPin> + 'd,
+        scl: impl Peripheral<P = impl InputPin + OutputPin> + 'd,
+    ) -> anyhow::Result<Self> {
+        let config = I2cConfig::new().baudrate(400.kHz().into());
+        let mut i2c = I2cDriver::new(i2c, sda, scl, &config)?;
+
+        // Wake the sensor from sleep mode
+        i2c.write(MPU6050_ADDR, &[REG_PWR_MGMT_1, 0x00], BLOCK)?;
+
+        info!("IMU initialized");
+        Ok(Self { i2c })
+    }
+
+    /// Gravity projected onto the screen plane, in g (each axis -1..1)
+    pub fn read_tilt(&mut self) -> anyhow::Result<Vector2D> {
+        let mut raw = [0u8; 4];
+        self.i2c
+            .write_read(MPU6050_ADDR, &[REG_ACCEL_XOUT_H], &mut raw, BLOCK)?;
+
+        let ax = i16::from_be_bytes([raw[0], raw[1]]) as f32 / LSB_PER_G;
+        let ay = i16::from_be_bytes([raw[2], raw[3]]) as f32 / LSB_PER_G;
+
+        let tilt = Vector2D::new(ax * AXIS_SIGN.0, ay * AXIS_SIGN.1);
+        if tilt.magnitude() < DEAD_ZONE_G {
+            Ok(Vector2D::zero())
+        } else {
+            Ok(tilt)
+        }
+    }
+}
+
+/// Spawn the polling task; the latest tilt is written to `tilt`
+pub fn spawn_reader(mut imu: Imu<'static>, tilt: Arc<Mutex<Vector2D>>) {
+    thread::spawn(move || loop {
+        match imu.read_tilt() {
+            Ok(reading) => *tilt.lock().unwrap() = reading,
+            Err(e) => warn!("IMU read failed: {:?}", e),
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    });
+}
@@ -1,18 +1,31 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration as StdDuration;
+use std::time::{Duration as StdDuration, Instant};
 
-use boid_core::{Boid, BoidConfig, Flock, Vector2D};
-use boid_shared::Position;
+use boid_core::step::Stimulus;
+use boid_core::{
+    Boid, BoidConfig, BoundaryBehavior, ConfigHistory, ConfigTransition, Flock, FlockProfile, Gust,
+    ParameterScales, StepInput, TargetInterpolator, Vector2D,
+};
+use boid_shared::arbitration::MAX_MERGED_CLIENTS;
+use boid_shared::colors::{self, Glyph, Rgb, Shape};
+use boid_shared::demo::{DemoPlayer, DemoSchedule, Palette};
+use boid_shared::mirror::{BoidState, FlockFrame};
+use boid_shared::trails::{Trail, TrailLength, TRAIL_CAPACITY};
+use boid_shared::{
+    stereo, BoidSettings, ErrorCode, GustSettings, LandmarkSmoother, LensCalibration, Position,
+    RenderStyle, TargetArbiter,
+};
 use embedded_graphics::{
     pixelcolor::Rgb565,
     prelude::*,
-    primitives::{Circle, PrimitiveStyle, Triangle},
+    primitives::{Circle, Polyline, PrimitiveStyle, Triangle},
 };
 use esp_idf_hal::{
     gpio::PinDriver,
+    modem::Modem,
     peripherals::Peripherals,
-    spi::{SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
+    spi::{Dma, SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
 };
 use esp_idf_svc::{
     eventloop::EspSystemEventLoop,
@@ -22,17 +35,31 @@ use esp_idf_svc::{
 };
 use log::info;
 
+mod boot;
 mod camera;
+#[cfg(feature = "on-device-detection")]
+mod detect;
 mod display;
 mod http_server;
+#[cfg(feature = "imu")]
+mod imu;
+mod metrics;
+mod persist;
+mod provisioning;
 mod rng;
+mod self_test;
 mod types;
+#[cfg(feature = "udp")]
+mod udp;
 mod wifi_config;
 
-use camera::CameraWrapper;
-use display::DisplayWrapper;
+use boot::{BootProgress, Stage, Task};
+use camera::{CameraSettings, CameraWrapper, FrameSize, PixelFormat};
+use display::{DisplayWrapper, Screen};
+use persist::{SettingsStore, StoredSettings};
 use rng::SimpleRng;
-use types::SimulationState;
+use types::{SimulationState, SpellState};
+use wifi_config::WifiCredentials;
 
 // Display configuration for common LCD screens
 const DISPLAY_WIDTH: u32 = 240;
@@ -42,6 +69,54 @@ const DISPLAY_HEIGHT: u32 = 240;
 const NUM_BOIDS: usize = 20;
 const BOID_SIZE: u32 = 3;
 
+// Flock behaviors and tuning compiled in, picked with the `low-cpu` or
+// `pretty` feature
+#[cfg(all(feature = "low-cpu", feature = "pretty"))]
+compile_error!("Enable at most one of the `low-cpu` and `pretty` features");
+#[cfg(feature = "low-cpu")]
+pub(crate) type Profile = boid_core::profile::LowCpu;
+#[cfg(all(feature = "pretty", not(feature = "low-cpu")))]
+pub(crate) type Profile = boid_core::profile::Pretty;
+#[cfg(not(any(feature = "low-cpu", feature = "pretty")))]
+pub(crate) type Profile = boid_core::profile::Standard;
+
+// Frames between flock snapshots served by /api/snapshot (~1 second)
+const SNAPSHOT_INTERVAL_FRAMES: u32 = 30;
+// Frames between camera idle checks (~1 second)
+const CAMERA_IDLE_CHECK_FRAMES: u32 = 30;
+// Default reach of the hand target on the 240x240 display
+const SEEK_RADIUS: f32 = 100.0;
+// Frames per second the main loop aims for
+const TARGET_FPS: u32 = 30;
+const FRAME_PERIOD: StdDuration = StdDuration::from_millis(1000 / TARGET_FPS as u64);
+// Every frame sleeps at least this long, so the network threads get to run;
+// the rest of the period is the budget for simulating and drawing
+const MIN_FRAME_SLEEP: StdDuration = StdDuration::from_millis(10);
+// Trail points drawn behind each boid at most, from cfg.toml; 0 turns
+// trails off
+const TRAIL_LENGTH: &str = env!("TRAIL_LENGTH");
+// A trail stops at a step longer than this, as when a boid wraps around
+const MAX_TRAIL_STEP: f32 = 40.0;
+// Draw into a frame buffer and send only the tiles that changed, from
+// cfg.toml; "false" clears and redraws the whole panel every frame
+const DIRTY_RECTS: &str = env!("DIRTY_RECTS");
+// Largest SPI transfer the DMA can take at once; longer writes are split
+const SPI_DMA_BUFFER_SIZE: usize = 4096;
+// Seconds per built-in demo scene, from cfg.toml; 0 keeps demo mode off
+const DEMO_SCENE_SECS: &str = env!("DEMO_SCENE_SECS");
+// Camera pixel format and frame size, from cfg.toml
+const CAMERA_FORMAT: &str = env!("CAMERA_FORMAT");
+const CAMERA_FRAME_SIZE: &str = env!("CAMERA_FRAME_SIZE");
+
+// Camera init needs little stack; joining WiFi and serving HTTP need more
+// than the default
+const CAMERA_STAGE_STACK_SIZE: usize = 8 * 1024;
+const NETWORK_STAGE_STACK_SIZE: usize = 16 * 1024;
+
+// Strength of the tilt-driven gravity force per g of tilt
+#[cfg(feature = "imu")]
+const TILT_GRAVITY_STRENGTH: f32 = 0.1;
+
 fn main() -> anyhow::Result<()> {
     // Initialize ESP-IDF services
     esp_idf_svc::sys::link_patches();
@@ -49,82 +124,155 @@ fn main() -> anyhow::Result<()> {
 
     info!("Starting boid simulation on ESP32-S3 with camera streaming!");
 
+    let progress = Arc::new(BootProgress::new());
     let peripherals = Peripherals::take()?;
     let sys_loop = EspSystemEventLoop::take()?;
     let nvs = EspDefaultNvsPartition::take()?;
 
-    // Initialize WiFi
-    let mut wifi = BlockingWifi::wrap(
-        EspWifi::new(peripherals.modem, sys_loop.clone(), Some(nvs))?,
-        sys_loop,
-    )?;
-
-    connect_wifi(&mut wifi)?;
-
-    let ip_info = wifi.wifi().sta_netif().get_ip_info()?;
-    info!("WiFi connected!");
-    info!("IP Address: {}", ip_info.ip);
-    info!("Connect client to: http://{}", ip_info.ip);
-
-    // Initialize camera
-    let camera = Arc::new(Mutex::new(CameraWrapper::new(
-        peripherals.pins.gpio10, // XCLK
-        peripherals.pins.gpio40, // SIOD
-        peripherals.pins.gpio39, // SIOC
-        peripherals.pins.gpio48, // Y9
-        peripherals.pins.gpio11, // Y8
-        peripherals.pins.gpio12, // Y7
-        peripherals.pins.gpio14, // Y6
-        peripherals.pins.gpio16, // Y5
-        peripherals.pins.gpio18, // Y4
-        peripherals.pins.gpio17, // Y3
-        peripherals.pins.gpio15, // Y2
-        peripherals.pins.gpio13, // PCLK
-        peripherals.pins.gpio38, // VSYNC
-        peripherals.pins.gpio47, // HREF
-    )?));
-
-    // Initialize SPI for display
+    // The display and the flock come up first, so the board shows something
+    // moving within moments; WiFi and the camera follow in the background
+    // (see boot.rs)
     let spi = SpiDeviceDriver::new_single(
         peripherals.spi2,
         peripherals.pins.gpio8,  // SCLK
         peripherals.pins.gpio9,  // MOSI
         Option::<esp_idf_hal::gpio::Gpio0>::None, // MISO (not used)
         Some(peripherals.pins.gpio7), // CS
-        &SpiDriverConfig::new(),
+        &SpiDriverConfig::new().dma(Dma::Auto(SPI_DMA_BUFFER_SIZE)),
         &SpiConfig::new().baudrate(40.MHz().into()),
     )?;
 
     let dc = PinDriver::output(peripherals.pins.gpio4)?;
     let rst = PinDriver::output(peripherals.pins.gpio5)?;
 
-    let mut display = DisplayWrapper::new(spi, dc, rst);
-    display.clear(Rgb565::BLACK).ok();
-    info!("Display initialized!");
+    let mut display = DisplayWrapper::new(spi, dc, rst)
+        .map_err(|e| log::error!("{:?}", e))
+        .ok();
+    let display_ok = display
+        .as_mut()
+        .is_some_and(|d| d.clear(Rgb565::BLACK).is_ok());
+    if display_ok {
+        info!("Display initialized!");
+    }
+    let mut screen = display.map(|display| Screen::new(display, DIRTY_RECTS != "false"));
+
+    // Orientation input for handheld builds
+    #[cfg(feature = "imu")]
+    let tilt = {
+        let tilt = Arc::new(Mutex::new(Vector2D::zero()));
+        match imu::Imu::new(
+            peripherals.i2c0,
+            peripherals.pins.gpio1, // SDA
+            peripherals.pins.gpio2, // SCL
+        ) {
+            Ok(sensor) => imu::spawn_reader(sensor, tilt.clone()),
+            Err(e) => log::error!("IMU init failed: {:?}", e),
+        }
+        tilt
+    };
+
+    // Settings a client set before the last power cycle
+    let mut settings_store = SettingsStore::open(nvs.clone())
+        .map_err(|e| log::error!("Settings storage unavailable: {:?}", e))
+        .ok();
+    let persisted = match settings_store.as_mut() {
+        Some(store) => store.load(default_persisted(), NUM_BOIDS),
+        None => default_persisted(),
+    };
 
     // Initialize shared simulation state
+    let camera_settings = camera_settings();
     let sim_state = Arc::new(Mutex::new(SimulationState {
         target_position: None,
-        config: BoidConfig {
-            max_speed: 2.0,
-            max_force: 0.05,
-            separation_distance: 15.0,
-            alignment_distance: 25.0,
-            cohesion_distance: 25.0,
-            separation_weight: 1.5,
-            alignment_weight: 1.0,
-            cohesion_weight: 1.0,
-        },
+        target_path: TargetInterpolator::new(),
+        started: Instant::now(),
+        arbiter: TargetArbiter::default(),
+        config: persisted.settings.apply_to(default_config()),
+        transition: None,
+        history: ConfigHistory::new(),
+        snapshot: None,
+        flock_frame: FlockFrame::default(),
+        frame_size: camera_settings.size,
+        calibration: LensCalibration::default(),
+        smoother: LandmarkSmoother::disabled(),
+        spell: None,
+        spell_changed: false,
+        wind: None,
+        hand_depth: None,
+        scales: ParameterScales::IDENTITY,
+        persisted: persisted.clone(),
+        forget_persisted: false,
+        boid_count: 0,
+        fps: 0,
     }));
 
-    // Spawn HTTP server thread
-    let camera_clone = camera.clone();
-    let sim_state_clone = sim_state.clone();
-    thread::spawn(move || {
-        if let Err(e) = http_server::start_server(camera_clone, sim_state_clone) {
-            log::error!("HTTP server error: {:?}", e);
-        }
-    });
+    // Camera and WiFi come up side by side (a camera failure is reported by
+    // the self-test instead of aborting)
+    let camera_task = boot::spawn(
+        Stage::Camera,
+        &progress,
+        CAMERA_STAGE_STACK_SIZE,
+        move || {
+            CameraWrapper::new(
+                peripherals.pins.gpio10, // XCLK
+                peripherals.pins.gpio40, // SIOD
+                peripherals.pins.gpio39, // SIOC
+                peripherals.pins.gpio48, // Y9
+                peripherals.pins.gpio11, // Y8
+                peripherals.pins.gpio12, // Y7
+                peripherals.pins.gpio14, // Y6
+                peripherals.pins.gpio16, // Y5
+                peripherals.pins.gpio18, // Y4
+                peripherals.pins.gpio17, // Y3
+                peripherals.pins.gpio15, // Y2
+                peripherals.pins.gpio13, // PCLK
+                peripherals.pins.gpio38, // VSYNC
+                peripherals.pins.gpio47, // HREF
+                camera_settings,
+            )
+            .map_err(|e| {
+                log::error!(
+                    "[{}] Camera init failed: {:?}",
+                    ErrorCode::CameraInitFailed,
+                    e
+                )
+            })
+            .ok()
+        },
+    )?;
+    let modem = peripherals.modem;
+    let wifi_nvs = nvs.clone();
+    let wifi_task = boot::spawn(
+        Stage::Wifi,
+        &progress,
+        NETWORK_STAGE_STACK_SIZE,
+        move || join_network(modem, sys_loop, wifi_nvs),
+    )?;
+
+    // Everything that needs the camera or the network
+    let camera = Arc::new(Mutex::new(None));
+    let services_camera = camera.clone();
+    let services_state = sim_state.clone();
+    let services_progress = progress.clone();
+    boot::spawn(
+        Stage::Services,
+        &progress,
+        NETWORK_STAGE_STACK_SIZE,
+        move || {
+            let started = start_services(
+                camera_task,
+                wifi_task,
+                services_camera,
+                services_state,
+                &services_progress,
+                display_ok,
+                nvs,
+            );
+            if let Err(e) = started {
+                log::error!("Network services failed to start: {:?}", e);
+            }
+        },
+    )?;
 
     // Initialize the boid simulation
     let config = {
@@ -132,67 +280,352 @@ fn main() -> anyhow::Result<()> {
         state.config.clone()
     };
 
-    let mut flock = Flock::<NUM_BOIDS>::new(DISPLAY_WIDTH as f32, DISPLAY_HEIGHT as f32, config);
+    let mut flock =
+        Flock::<NUM_BOIDS, Profile>::new(DISPLAY_WIDTH as f32, DISPLAY_HEIGHT as f32, config);
 
     // Initialize boids with pseudo-random positions
     let mut rng = SimpleRng::new(12345);
-    for _ in 0..NUM_BOIDS {
-        let x = rng.next_f32() * DISPLAY_WIDTH as f32;
-        let y = rng.next_f32() * DISPLAY_HEIGHT as f32;
-        let vx = (rng.next_f32() - 0.5) * 4.0;
-        let vy = (rng.next_f32() - 0.5) * 4.0;
-
-        let boid = Boid::new(Vector2D::new(x, y), Vector2D::new(vx, vy));
-        let _ = flock.add_boid(boid);
-    }
+    resize_flock(&mut flock, persisted.boid_count, &mut rng);
+
+    // Kiosk rotation; clients still take over the target while they send one
+    let mut demo = match DEMO_SCENE_SECS.parse::<u32>().unwrap_or(0) {
+        0 => None,
+        secs => {
+            info!("Demo mode: {}s per scene", secs);
+            DemoPlayer::new(DemoSchedule::builtin(secs * TARGET_FPS)).ok()
+        }
+    };
 
-    info!("Boids initialized, starting simulation loop...");
+    info!(
+        "Boids initialized after {} ms, starting simulation loop...",
+        progress.elapsed().as_millis()
+    );
 
     // Main simulation loop
+    let mut frame: u32 = 0;
+    let mut fps_frames: u32 = 0;
+    let mut fps_since = Instant::now();
+    let mut style = persisted.style;
+    // Reused every frame, so following targets does not allocate
+    let mut targets: Vec<Vector2D> = Vec::with_capacity(MAX_MERGED_CLIENTS);
+    // One trail per boid, drawn as long as frames stay within budget
+    let mut trails: Vec<Trail> = Vec::new();
+    let mut trail_length = TrailLength::new(TRAIL_LENGTH.parse().unwrap_or(0));
+    let frame_budget = FRAME_PERIOD - MIN_FRAME_SLEEP;
     loop {
+        let frame_start = Instant::now();
         // Update configuration and target from shared state
         {
-            let state = sim_state.lock().unwrap();
+            let mut state = sim_state.lock().unwrap();
+            if let Some(demo) = demo.as_mut() {
+                advance_demo(demo, &mut state);
+            }
+            if let Some(mut transition) = state.transition {
+                state.config = transition.step();
+                state.transition = (!transition.is_finished()).then_some(transition);
+            }
             flock.config = state.config.clone();
+            state.scales.apply(&mut flock.config);
+
+            if let Some(store) = settings_store.as_mut() {
+                if state.forget_persisted {
+                    store.forget(&state.persisted);
+                }
+                store.track(&state.persisted);
+            }
+            state.forget_persisted = false;
+            resize_flock(&mut flock, state.persisted.boid_count, &mut rng);
+            style = state.persisted.style;
+
+            // A stereo client reaching toward the cameras widens the hand's reach
+            if let Some(depth) = state.hand_depth {
+                flock.config.seek_radius =
+                    stereo::seek_radius_for_depth(flock.config.seek_radius, depth);
+            }
+
+            let expired = state
+                .spell
+                .as_ref()
+                .and_then(|spell| spell.until)
+                .is_some_and(|until| Instant::now() >= until);
+            if expired {
+                state.spell = None;
+                state.spell_changed = true;
+            }
+            if state.spell_changed {
+                state.spell_changed = false;
+                match state.spell.as_ref() {
+                    Some(spell) => {
+                        flock.spell(&spell.text);
+                    }
+                    None => flock.release_formation(),
+                }
+            }
+
+            if let Some(wind) = state.wind.take() {
+                flock.set_global_force(Vector2D::new(wind.force_x, wind.force_y));
+                flock.set_gust(gust_of(wind.gust));
+            }
 
-            // Clear display
-            display.clear(Rgb565::BLACK).ok();
+            // Clear the display, or just the frame buffer when there is one
+            if let Some(screen) = screen.as_mut() {
+                screen.begin_frame();
+            }
 
-            // Update boid positions with optional target
-            if let Some(target) = state.target_position {
-                flock.update_with_target(Some(target));
+            // Update boid positions with optional target, moving smoothly
+            // between the updates clients send. Merged clients' targets are
+            // followed as posted.
+            let elapsed = state.started.elapsed();
+            let now = elapsed.as_secs_f64();
+            targets.clear();
+            if state.arbiter.is_merging() {
+                let merged = state.arbiter.targets(elapsed.as_millis() as u64);
+                targets.extend(merged.map(|p| Vector2D::new(p.x, p.y)));
             } else {
-                flock.update();
+                targets.extend(state.target_path.sample(now));
+            }
+            // Clients can't reach the flock before start-up is done
+            if targets.is_empty() && !progress.is_done() {
+                targets.push(progress.animation_target(flock.width, flock.height));
+            }
+            if targets.is_empty() {
+                let demo_target = demo
+                    .as_ref()
+                    .and_then(|demo| demo.target(flock.width, flock.height));
+                targets.extend(demo_target.map(|target| Vector2D::new(target.x, target.y)));
+            }
+            // Tilting the device pulls the flock toward the low edge
+            #[cfg(feature = "imu")]
+            let stimuli = [Stimulus::Force(
+                *tilt.lock().unwrap() * TILT_GRAVITY_STRENGTH,
+            )];
+            #[cfg(not(feature = "imu"))]
+            let stimuli: [Stimulus; 0] = [];
+            let output = flock.step(
+                StepInput::new()
+                    .with_targets(&targets)
+                    .with_stimuli(&stimuli),
+            );
+            if !output.events.is_empty() {
+                log::warn!(
+                    "Flock at {}/{} boids: {:?}",
+                    output.stats.boids,
+                    flock.max_population(),
+                    output.events
+                );
+            }
+
+            if frame % SNAPSHOT_INTERVAL_FRAMES == 0 {
+                // Serialized into the previous snapshot's buffer, so the
+                // heap does not fragment over days of uptime
+                let mut buffer = state
+                    .snapshot
+                    .take()
+                    .map(String::into_bytes)
+                    .unwrap_or_default();
+                buffer.clear();
+                state.snapshot = serde_json::to_writer(&mut buffer, &flock)
+                    .ok()
+                    .and_then(|()| String::from_utf8(buffer).ok());
+            }
+
+            // Every frame for clients mirroring the flock, into the same
+            // buffer
+            let mirrored = &mut state.flock_frame;
+            mirrored.frame = frame;
+            (mirrored.width, mirrored.height) = (flock.width, flock.height);
+            let boids = flock.boids.iter().map(|boid| BoidState {
+                x: boid.position.x,
+                y: boid.position.y,
+                vx: boid.velocity.x,
+                vy: boid.velocity.y,
+                group: boid.group,
+            });
+            mirrored.boids.clear();
+            mirrored.boids.extend(boids);
+
+            // Measured rather than assumed, so clients can tell a slow
+            // device from a slow network
+            state.boid_count = flock.boids.len();
+            fps_frames += 1;
+            if fps_since.elapsed() >= StdDuration::from_secs(1) {
+                if let Some(sent) = screen.as_mut().and_then(Screen::take_sent_pixels) {
+                    let panel = u64::from(DISPLAY_WIDTH * DISPLAY_HEIGHT * fps_frames.max(1));
+                    log::debug!(
+                        "{} fps, {}% of the panel sent",
+                        fps_frames,
+                        sent * 100 / panel
+                    );
+                }
+                state.fps = fps_frames;
+                fps_frames = 0;
+                fps_since = Instant::now();
             }
         }
 
-        // Draw each boid
-        for boid in flock.boids.iter() {
-            draw_boid(&mut display, boid);
+        // A failed self-test pauses the flock while its checks are shown
+        if let Some(report) = progress.take_failure() {
+            if let Some(screen) = screen.as_mut() {
+                self_test::show_status_screen(screen, &report);
+                screen.end_frame();
+                thread::sleep(StdDuration::from_millis(self_test::FAILURE_SCREEN_MS));
+            }
         }
 
-        // Target ~30 FPS
-        thread::sleep(StdDuration::from_millis(33));
+        // Put the camera back into standby once clients stop pulling frames.
+        // `try_lock` because an active stream holds the lock while capturing.
+        if frame % CAMERA_IDLE_CHECK_FRAMES == 0 {
+            if let Ok(mut cam) = camera.try_lock() {
+                if let Some(cam) = cam.as_mut() {
+                    cam.power_down_if_idle(camera::IDLE_TIMEOUT);
+                }
+            }
+        }
+        frame = frame.wrapping_add(1);
+
+        // Into the frame buffer when there is one, then only what changed
+        // goes out to the display
+        if let Some(screen) = screen.as_mut() {
+            trails.resize(flock.boids.len(), Trail::new());
+            for (boid, trail) in flock.boids.iter().zip(trails.iter_mut()) {
+                trail.push(Position::new(boid.position.x, boid.position.y));
+            }
+            let scene = Scene {
+                boids: &flock.boids,
+                max_speed: flock.config.max_speed,
+                trails: &trails,
+                trail_length: trail_length.get(),
+                style: &style,
+                palette: demo.as_ref().map(DemoPlayer::palette),
+            };
+            scene.draw(screen);
+            screen.end_frame();
+        }
+
+        // Target ~30 FPS, drawing shorter trails when a frame takes too long
+        let work = frame_start.elapsed();
+        trail_length.record(
+            work.as_secs_f32() * 1000.0,
+            frame_budget.as_secs_f32() * 1000.0,
+        );
+        thread::sleep(FRAME_PERIOD.saturating_sub(work).max(MIN_FRAME_SLEEP));
     }
 }
 
-fn connect_wifi(wifi: &mut BlockingWifi<EspWifi<'static>>) -> anyhow::Result<()> {
-    use wifi_config::{PASSWORD, SSID};
+/// Join the first reachable network, or let the user pick one on the
+/// setup page, which restarts the board
+fn join_network(
+    modem: Modem,
+    sys_loop: EspSystemEventLoop,
+    nvs: EspDefaultNvsPartition,
+) -> anyhow::Result<BlockingWifi<EspWifi<'static>>> {
+    let mut wifi = BlockingWifi::wrap(
+        EspWifi::new(modem, sys_loop.clone(), Some(nvs.clone()))?,
+        sys_loop,
+    )?;
+
+    let joined = WifiCredentials::candidates(&nvs)
+        .into_iter()
+        .any(|credentials| {
+            connect_wifi(&mut wifi, &credentials)
+                .map_err(|e| log::warn!("Joining '{}' failed: {:?}", credentials.ssid, e))
+                .is_ok()
+        });
+    if !joined {
+        match provisioning::run(&mut wifi, nvs)? {}
+    }
+
+    let ip_info = wifi.wifi().sta_netif().get_ip_info()?;
+    info!("WiFi connected!");
+    info!("IP Address: {}", ip_info.ip);
+    info!("Connect client to: http://{}", ip_info.ip);
+    Ok(wifi)
+}
 
+/// The services stage: once the camera is up, hand it over and start
+/// on-device detection; once WiFi is up too, run the self-test and start
+/// the network inputs
+fn start_services(
+    camera_task: Task<Option<CameraWrapper>>,
+    wifi_task: Task<anyhow::Result<BlockingWifi<EspWifi<'static>>>>,
+    camera: Arc<Mutex<Option<CameraWrapper>>>,
+    sim_state: Arc<Mutex<SimulationState>>,
+    progress: &BootProgress,
+    display_ok: bool,
+    nvs: EspDefaultNvsPartition,
+) -> anyhow::Result<()> {
+    let ready = camera_task.wait()?;
+    let camera_ok = ready.is_some();
+    *camera.lock().unwrap() = ready;
+
+    // Optional hand tracking on the device itself, for setups without a client
+    #[cfg(feature = "on-device-detection")]
+    if let Err(e) = detect::spawn_detector(camera.clone(), sim_state.clone()) {
+        log::error!("On-device hand detection failed to start: {:?}", e);
+    }
+
+    let wifi = wifi_task.wait()??;
+
+    // Run the boot-time self-test and show a status screen if anything failed
+    let health = self_test::run(display_ok, camera_ok, nvs);
+    if !health.is_healthy() {
+        progress.report_failure(health.clone());
+    }
+
+    // Optional low-latency target input; HTTP keeps working alongside it
+    #[cfg(feature = "udp")]
+    if let Err(e) = udp::spawn_listener(sim_state.clone()) {
+        log::error!("UDP target channel failed to start: {:?}", e);
+    }
+
+    // Optional push of the board's numbers to a central collector
+    if let Err(e) = metrics::spawn_pusher(sim_state.clone()) {
+        log::error!("Metrics push failed to start: {:?}", e);
+    }
+
+    thread::Builder::new()
+        .stack_size(NETWORK_STAGE_STACK_SIZE)
+        .spawn(move || {
+            // The connection stays up for as long as the server runs
+            let _wifi = wifi;
+            if let Err(e) = http_server::start_server(camera, sim_state, health) {
+                log::error!("HTTP server error: {:?}", e);
+            }
+        })?;
+    Ok(())
+}
+
+fn connect_wifi(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    credentials: &WifiCredentials,
+) -> anyhow::Result<()> {
     let wifi_configuration = Configuration::Client(ClientConfiguration {
-        ssid: SSID.try_into().unwrap(),
-        password: PASSWORD.try_into().unwrap(),
+        ssid: credentials
+            .ssid
+            .as_str()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("SSID too long"))?,
+        password: credentials
+            .password
+            .as_str()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("password too long"))?,
         ..Default::default()
     });
 
     wifi.set_configuration(&wifi_configuration)?;
 
-    info!("Starting WiFi...");
-    wifi.start()?;
-    info!("WiFi started");
+    if !wifi.is_started()? {
+        info!("Starting WiFi...");
+        wifi.start()?;
+        info!("WiFi started");
+    }
 
-    info!("Connecting to WiFi...");
-    wifi.connect()?;
+    info!("Connecting to '{}'...", credentials.ssid);
+    if let Err(e) = wifi.connect() {
+        let _ = wifi.disconnect();
+        return Err(e.into());
+    }
     info!("WiFi connected");
 
     info!("Waiting for DHCP lease...");
@@ -202,9 +635,206 @@ fn connect_wifi(wifi: &mut BlockingWifi<EspWifi<'static>>) -> anyhow::Result<()>
     Ok(())
 }
 
-fn draw_boid(display: &mut DisplayWrapper, boid: &Boid) {
+/// Configuration the firmware boots with when no settings are saved: the
+/// flock profile's, adapted to the display
+fn default_config() -> BoidConfig {
+    BoidConfig {
+        // Only boids near the hand respond, so a pinch scoops part of the flock
+        seek_radius: SEEK_RADIUS,
+        // Boids leaving the small display come back on the other side
+        boundary: Some(BoundaryBehavior::Wrap),
+        ..Profile::config()
+    }
+}
+
+/// Wind gust as the flock takes it
+fn gust_of(settings: GustSettings) -> Gust {
+    match settings {
+        GustSettings::Calm => Gust::Calm,
+        GustSettings::Sine {
+            amplitude_x,
+            amplitude_y,
+            period,
+        } => Gust::Sine {
+            amplitude: Vector2D::new(amplitude_x, amplitude_y),
+            period,
+        },
+        GustSettings::Noise {
+            amplitude_x,
+            amplitude_y,
+            period,
+            seed,
+        } => Gust::Noise {
+            amplitude: Vector2D::new(amplitude_x, amplitude_y),
+            period,
+            seed,
+        },
+    }
+}
+
+/// Camera settings from cfg.toml; unknown names fall back to JPEG QVGA
+fn camera_settings() -> CameraSettings {
+    let format = PixelFormat::parse(CAMERA_FORMAT).unwrap_or_else(|| {
+        log::warn!("Unknown camera format '{}', using JPEG", CAMERA_FORMAT);
+        PixelFormat::default()
+    });
+    let size = FrameSize::parse(CAMERA_FRAME_SIZE).unwrap_or_else(|| {
+        log::warn!(
+            "Unknown camera frame size '{}', using QVGA",
+            CAMERA_FRAME_SIZE
+        );
+        FrameSize::default()
+    });
+    CameraSettings { format, size }
+}
+
+/// Settings and flock size of `default_config`
+fn default_persisted() -> StoredSettings {
+    StoredSettings {
+        settings: BoidSettings::from(&default_config()),
+        boid_count: NUM_BOIDS,
+        style: Default::default(),
+    }
+}
+
+/// Drop the newest boids or spawn new ones at random until there are `count`
+fn resize_flock(flock: &mut Flock<NUM_BOIDS, Profile>, count: usize, rng: &mut SimpleRng) {
+    flock.set_boid_count(count, || {
+        let x = rng.next_f32() * DISPLAY_WIDTH as f32;
+        let y = rng.next_f32() * DISPLAY_HEIGHT as f32;
+        let vx = (rng.next_f32() - 0.5) * 4.0;
+        let vy = (rng.next_f32() - 0.5) * 4.0;
+
+        Boid::new(Vector2D::new(x, y), Vector2D::new(vx, vy))
+    });
+}
+
+/// Move the demo one frame on, starting each scene's crossfade and text
+fn advance_demo(demo: &mut DemoPlayer, state: &mut SimulationState) {
+    let ticks = demo.transition_ticks();
+    let was_spelling = demo.scene().spell.is_some();
+    let Some(scene) = demo.advance() else {
+        return;
+    };
+    // Demo scenes stay out of the undo history
+    if let Some(ref settings) = scene.settings {
+        let preset = settings.apply_to(state.config);
+        state.transition = Some(ConfigTransition::new(state.config, preset, ticks.max(1)));
+    }
+    match scene.spell {
+        Some(ref text) => {
+            state.spell = Some(SpellState {
+                text: text.clone(),
+                until: None,
+            });
+            state.spell_changed = true;
+        }
+        None if was_spelling => {
+            state.spell = None;
+            state.spell_changed = true;
+        }
+        None => {}
+    }
+}
+
+fn boid_color(palette: &Palette, boid: &Boid, max_speed: f32) -> Rgb565 {
+    nearest_rgb565(palette.rgb(boid.velocity.magnitude() / max_speed))
+}
+
+fn nearest_rgb565(color: Rgb) -> Rgb565 {
+    let (r, g, b) = colors::rgb565(color);
+    Rgb565::new(r, g, b)
+}
+
+/// What one frame puts on the display
+struct Scene<'a> {
+    boids: &'a [Boid],
+    max_speed: f32,
+    trails: &'a [Trail],
+    trail_length: usize,
+    style: &'a RenderStyle,
+    palette: Option<Palette>,
+}
+
+impl Scene<'_> {
+    /// Each boid over its trail, shaped and sized by the render style: in
+    /// a fixed palette when one is chosen, otherwise in the demo theme's
+    /// colors while it runs
+    fn draw(&self, screen: &mut Screen) {
+        for (boid, trail) in self.boids.iter().zip(self.trails) {
+            let speed = boid.velocity.magnitude() / self.max_speed;
+            let glyph = self.style.glyph(boid.group, speed);
+            if let Some(color) = self.style.color(boid.group) {
+                let color = nearest_rgb565(color);
+                draw_trail(screen, trail, self.trail_length, color);
+                draw_glyph(screen, boid, glyph, color);
+                continue;
+            }
+            let color = self.palette.map_or(Rgb565::GREEN, |palette| {
+                boid_color(&palette, boid, self.max_speed)
+            });
+            draw_trail(screen, trail, self.trail_length, color);
+            draw_boid(screen, boid, glyph, color);
+        }
+    }
+}
+
+/// `glyph`'s shape turned to the boid's heading, three `BOID_SIZE`s long at
+/// scale 1
+fn draw_glyph(display: &mut Screen, boid: &Boid, glyph: Glyph, color: Rgb565) {
+    let (cos_a, sin_a) = if boid.velocity.magnitude() > 0.1 {
+        let angle = libm::atan2f(boid.velocity.y, boid.velocity.x);
+        (libm::cosf(angle), libm::sinf(angle))
+    } else {
+        (1.0, 0.0)
+    };
+    let size = 3.0 * BOID_SIZE as f32 * glyph.scale;
+    let corner = |&(x, y): &(f32, f32)| {
+        Point::new(
+            (boid.position.x + (x * cos_a - y * sin_a) * size) as i32,
+            (boid.position.y + (x * sin_a + y * cos_a) * size) as i32,
+        )
+    };
+
+    // Filled as a fan of triangles from the first corner
+    let corners = glyph.shape.corners();
+    let first = corner(&corners[0]);
+    let style = PrimitiveStyle::with_fill(color);
+    for pair in corners[1..].windows(2) {
+        Triangle::new(first, corner(&pair[0]), corner(&pair[1]))
+            .into_styled(style)
+            .draw(display)
+            .ok();
+    }
+}
+
+/// Up to `length` points of the boid's recent path, at half brightness
+fn draw_trail(display: &mut Screen, trail: &Trail, length: usize, color: Rgb565) {
+    let mut path = [Position::new(0.0, 0.0); TRAIL_CAPACITY];
+    let count = trail.path_into(length, MAX_TRAIL_STEP, &mut path);
+    if count < 2 {
+        return;
+    }
+    let mut points = [Point::zero(); TRAIL_CAPACITY];
+    for (point, position) in points.iter_mut().zip(&path[..count]) {
+        *point = Point::new(position.x as i32, position.y as i32);
+    }
+    let dimmed = Rgb565::new(color.r() / 2, color.g() / 2, color.b() / 2);
+    Polyline::new(&points[..count])
+        .into_styled(PrimitiveStyle::with_stroke(dimmed, 1))
+        .draw(display)
+        .ok();
+}
+
+/// The usual triangle scaled by `glyph.scale`, or a dot while the boid
+/// stands still; other shapes are drawn by `draw_glyph`
+fn draw_boid(display: &mut Screen, boid: &Boid, glyph: Glyph, color: Rgb565) {
+    if glyph.shape != Shape::Triangle {
+        return draw_glyph(display, boid, glyph, color);
+    }
     let x = boid.position.x as i32;
     let y = boid.position.y as i32;
+    let size = BOID_SIZE as f32 * glyph.scale;
 
     // Calculate boid direction for triangle orientation
     let vel_mag = boid.velocity.magnitude();
@@ -212,7 +842,6 @@ fn draw_boid(display: &mut DisplayWrapper, boid: &Boid) {
         let angle = libm::atan2f(boid.velocity.y, boid.velocity.x);
 
         // Draw a triangle pointing in the direction of movement
-        let size = BOID_SIZE as f32;
         let cos_a = libm::cosf(angle);
         let sin_a = libm::sinf(angle);
 
@@ -231,16 +860,16 @@ fn draw_boid(display: &mut DisplayWrapper, boid: &Boid) {
             Point::new(p2_x, p2_y),
             Point::new(p3_x, p3_y),
         )
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN));
+        .into_styled(PrimitiveStyle::with_fill(color));
 
         triangle.draw(display).ok();
     } else {
         // If not moving, just draw a circle
         let circle = Circle::new(
-            Point::new(x - BOID_SIZE as i32, y - BOID_SIZE as i32),
-            BOID_SIZE * 2,
+            Point::new(x - size as i32, y - size as i32),
+            (size * 2.0) as u32,
         )
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN));
+        .into_styled(PrimitiveStyle::with_fill(color));
         circle.draw(display).ok();
     }
 }
@@ -0,0 +1,139 @@
+// Metrics push to a central collector
+//
+// With `[metrics] endpoint` set in cfg.toml, a thread POSTs the frame rate,
+// flock size, target activity and free heap every `interval_secs` (see
+// `boid_shared::metrics`), so an installation's boards can be watched on
+// one dashboard. A collector that is down only costs a warning per push;
+// the flock never waits for it.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use boid_shared::metrics::{self, DeviceMetrics, Endpoint, MetricsFormat, DEFAULT_INTERVAL_SECS};
+use esp_idf_svc::sys;
+use log::{info, warn};
+
+use crate::http_server;
+use crate::types::SimulationState;
+
+const METRICS_ENDPOINT: &str = env!("METRICS_ENDPOINT");
+const METRICS_FORMAT: &str = env!("METRICS_FORMAT");
+const METRICS_INTERVAL_SECS: &str = env!("METRICS_INTERVAL_SECS");
+const METRICS_DEVICE: &str = env!("METRICS_DEVICE");
+const METRICS_AUTHORIZATION: &str = env!("METRICS_AUTHORIZATION");
+
+const PUSH_STACK_SIZE: usize = 6 * 1024;
+// Connecting, sending and reading the answer each give up after this long
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+// Enough of the answer for its status line
+const RESPONSE_BUFFER_LEN: usize = 64;
+
+/// Start pushing if cfg.toml names an endpoint
+pub fn spawn_pusher(sim_state: Arc<Mutex<SimulationState>>) -> anyhow::Result<()> {
+    if METRICS_ENDPOINT.is_empty() {
+        return Ok(());
+    }
+    let device = match METRICS_DEVICE {
+        "" => default_device(),
+        name => name.to_string(),
+    };
+    let endpoint = Endpoint::parse(METRICS_ENDPOINT, &device).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Metrics endpoint must be an http:// URL: {}",
+            METRICS_ENDPOINT
+        )
+    })?;
+    let format = MetricsFormat::parse(METRICS_FORMAT).unwrap_or_else(|| {
+        warn!("Unknown metrics format '{}', using influx", METRICS_FORMAT);
+        MetricsFormat::Influx
+    });
+    let interval = match METRICS_INTERVAL_SECS.parse::<u32>() {
+        Ok(secs) if secs > 0 => secs,
+        _ => DEFAULT_INTERVAL_SECS,
+    };
+    let authorization = (!METRICS_AUTHORIZATION.is_empty()).then_some(METRICS_AUTHORIZATION);
+    info!(
+        "Pushing {} metrics as '{}' to {}:{} every {}s",
+        format.as_str(),
+        device,
+        endpoint.host,
+        endpoint.port,
+        interval
+    );
+
+    thread::Builder::new()
+        .stack_size(PUSH_STACK_SIZE)
+        .spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval as u64));
+            let reading = read_metrics(&device, &sim_state);
+            let request = endpoint.post(format, &format.encode(&reading), authorization);
+            match push(&endpoint, &request) {
+                Ok(status) if (200..300).contains(&status) => {}
+                Ok(status) => warn!("Metrics push refused with HTTP {}", status),
+                Err(e) => warn!("Metrics push failed: {:?}", e),
+            }
+        })?;
+    Ok(())
+}
+
+fn read_metrics(device: &str, sim_state: &Mutex<SimulationState>) -> DeviceMetrics {
+    let status = http_server::status_response(&sim_state.lock().unwrap());
+    // SAFETY: read-only queries of the heap allocator and the boot timer
+    let (free_heap_bytes, min_free_heap_bytes, uptime_us) = unsafe {
+        (
+            sys::esp_get_free_heap_size(),
+            sys::esp_get_minimum_free_heap_size(),
+            sys::esp_timer_get_time(),
+        )
+    };
+    DeviceMetrics {
+        device: device.to_string(),
+        uptime_secs: (uptime_us / 1_000_000) as u64,
+        fps: status.fps,
+        boid_count: status.boid_count,
+        target_active: status.target_active,
+        controllers: status.controllers.len(),
+        free_heap_bytes,
+        min_free_heap_bytes,
+    }
+}
+
+/// Send `request` and return the status code of the answer
+fn push(endpoint: &Endpoint, request: &str) -> anyhow::Result<u16> {
+    let address = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("{} did not resolve", endpoint.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, PUSH_TIMEOUT)?;
+    stream.set_read_timeout(Some(PUSH_TIMEOUT))?;
+    stream.set_write_timeout(Some(PUSH_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = [0u8; RESPONSE_BUFFER_LEN];
+    let mut len = 0;
+    while len < response.len() {
+        match stream.read(&mut response[len..])? {
+            0 => break,
+            read => len += read,
+        }
+        if response[..len].contains(&b'\n') {
+            break;
+        }
+    }
+    metrics::response_status(&response[..len])
+        .ok_or_else(|| anyhow::anyhow!("malformed response from {}", endpoint.host))
+}
+
+/// `boid-` and the last three bytes of the WiFi MAC address, which stay
+/// the same across reflashes
+fn default_device() -> String {
+    let mut mac = [0u8; 6];
+    // SAFETY: `mac` has room for the six bytes written
+    unsafe {
+        sys::esp_read_mac(mac.as_mut_ptr(), sys::esp_mac_type_t_ESP_MAC_WIFI_STA);
+    }
+    format!("boid-{:02x}{:02x}{:02x}", mac[3], mac[4], mac[5])
+}
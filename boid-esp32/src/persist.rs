@@ -0,0 +1,137 @@
+// Settings persistence in NVS
+//
+// The settings and flock size a client set last are restored at boot. They
+// are written once they have stopped changing for `SAVE_DELAY`, so dragging
+// a slider does not wear out the flash. `POST /api/settings/reset` erases
+// them.
+
+use std::time::{Duration, Instant};
+
+use boid_shared::{BoidSettings, RenderStyle};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+const NVS_NAMESPACE: &str = "boid";
+const SETTINGS_KEY: &str = "settings";
+
+/// Quiet period before changed settings are written
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// What survives a power cycle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredSettings {
+    pub settings: BoidSettings,
+    pub boid_count: usize,
+    /// Missing from settings saved by older firmware, which kept only the
+    /// palette, as `color_scheme`; see `parse_stored`
+    #[serde(default)]
+    pub style: RenderStyle,
+}
+
+pub struct SettingsStore {
+    nvs: EspNvs<NvsDefault>,
+    /// Last value known to be in NVS
+    saved: Option<StoredSettings>,
+    /// Changed value waiting for its quiet period, and when it last changed
+    pending: Option<(StoredSettings, Instant)>,
+}
+
+impl SettingsStore {
+    pub fn open(partition: EspDefaultNvsPartition) -> anyhow::Result<Self> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NVS_NAMESPACE, true)?,
+            saved: None,
+            pending: None,
+        })
+    }
+
+    /// The saved settings, or `defaults` if there are none or they cannot
+    /// be used. Saved values are clamped to the current limits and to
+    /// `max_boids`, since the firmware may have changed since they were
+    /// written.
+    pub fn load(&mut self, defaults: StoredSettings, max_boids: usize) -> StoredSettings {
+        let mut buffer = [0u8; 512];
+        let stored = match self.nvs.get_str(SETTINGS_KEY, &mut buffer) {
+            Ok(Some(json)) => parse_stored(json)
+                .map_err(|e| warn!("Ignoring saved settings: {}", e))
+                .ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Reading saved settings failed: {:?}", e);
+                None
+            }
+        };
+        let loaded = stored.and_then(|mut stored| {
+            stored.settings.clamp_to_limits(|_, _, _| {}).ok()?;
+            if !stored.style.is_valid() {
+                stored.style.size = RenderStyle::default().size;
+            }
+            stored.boid_count = stored.boid_count.clamp(1, max_boids);
+            Some(stored)
+        });
+        if let Some(ref loaded) = loaded {
+            info!("Restored saved settings: {:?}", loaded);
+        }
+        // Defaults are not written until something changes
+        let current = loaded.unwrap_or(defaults);
+        self.saved = Some(current.clone());
+        current
+    }
+
+    /// Call once per frame with the current settings; writes them once
+    /// they have stayed the same for `SAVE_DELAY`
+    pub fn track(&mut self, current: &StoredSettings) {
+        if self.saved.as_ref() == Some(current) {
+            self.pending = None;
+            return;
+        }
+        let quiet = self
+            .pending
+            .as_ref()
+            .filter(|(pending, _)| pending == current)
+            .map(|(_, since)| since.elapsed());
+        match quiet {
+            Some(quiet) if quiet >= SAVE_DELAY => self.save(current),
+            Some(_) => {}
+            None => self.pending = Some((current.clone(), Instant::now())),
+        }
+    }
+
+    fn save(&mut self, current: &StoredSettings) {
+        self.pending = None;
+        let result = serde_json::to_string(current)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(self.nvs.set_str(SETTINGS_KEY, &json)?));
+        match result {
+            Ok(()) => {
+                info!("Saved settings");
+                self.saved = Some(current.clone());
+            }
+            // Retried after the next quiet period
+            Err(e) => warn!("Saving settings failed: {:?}", e),
+        }
+    }
+
+    /// Erase the saved settings. `current`, the defaults, counts as saved
+    /// so it is not written straight back.
+    pub fn forget(&mut self, current: &StoredSettings) {
+        if let Err(e) = self.nvs.remove(SETTINGS_KEY) {
+            warn!("Erasing saved settings failed: {:?}", e);
+        }
+        self.saved = Some(current.clone());
+        self.pending = None;
+    }
+}
+
+/// Saved settings, moving a `color_scheme` saved before render styles into
+/// the style's palette
+fn parse_stored(json: &str) -> serde_json::Result<StoredSettings> {
+    let mut value: serde_json::Value = serde_json::from_str(json)?;
+    if let Some(fields) = value.as_object_mut() {
+        if let Some(scheme) = fields.remove("color_scheme") {
+            fields.insert("style".into(), serde_json::json!({ "palette": scheme }));
+        }
+    }
+    serde_json::from_value(value)
+}
@@ -0,0 +1,153 @@
+// Access-point fallback for entering WiFi credentials at runtime
+//
+// When no stored or compiled-in network can be joined, the board opens the
+// `boid-setup` access point and serves a one-field-per-line form on port 80.
+// Submitting it stores the credentials in NVS and restarts the board, which
+// then joins the new network.
+
+use std::convert::Infallible;
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use esp_idf_svc::{
+    nvs::EspDefaultNvsPartition,
+    wifi::{AccessPointConfiguration, AuthMethod, BlockingWifi, Configuration, EspWifi},
+};
+use log::{error, info};
+
+use crate::http_server::{write_response, HttpRequest, Response};
+use crate::wifi_config::WifiCredentials;
+
+/// Name of the setup network; it is open, so anyone nearby can configure
+/// the board until it restarts
+pub const SETUP_SSID: &str = "boid-setup";
+
+const SETUP_PAGE: &str = "<!DOCTYPE html>\
+<html><head><meta name=\"viewport\" content=\"width=device-width\"><title>Boid setup</title></head>\
+<body><h1>Boid WiFi setup</h1>\
+<form method=\"post\" action=\"/wifi\">\
+<p><label>Network <input name=\"ssid\" maxlength=\"32\" required></label></p>\
+<p><label>Password <input name=\"password\" type=\"password\" maxlength=\"64\"></label></p>\
+<p><button>Save and restart</button></p>\
+</form></body></html>";
+
+/// Serve the setup page from the `boid-setup` access point until
+/// credentials are submitted, then restart
+pub fn run(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    nvs: EspDefaultNvsPartition,
+) -> anyhow::Result<Infallible> {
+    let _ = wifi.stop();
+    wifi.set_configuration(&Configuration::AccessPoint(AccessPointConfiguration {
+        ssid: SETUP_SSID.try_into().unwrap(),
+        auth_method: AuthMethod::None,
+        channel: 1,
+        ..Default::default()
+    }))?;
+    wifi.start()?;
+    wifi.wait_netif_up()?;
+
+    let ip_info = wifi.wifi().ap_netif().get_ip_info()?;
+    info!(
+        "No WiFi network reachable; join '{}' and open http://{} to set one",
+        SETUP_SSID, ip_info.ip
+    );
+
+    let listener = TcpListener::bind("0.0.0.0:80")?;
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => match handle_client(stream, &nvs) {
+                Ok(true) => {
+                    info!("WiFi credentials saved, restarting");
+                    // Let the response reach the browser first
+                    std::thread::sleep(Duration::from_millis(500));
+                    esp_idf_svc::hal::reset::restart();
+                }
+                Ok(false) => {}
+                Err(e) => error!("Error handling setup client: {:?}", e),
+            },
+            Err(e) => error!("Connection error: {:?}", e),
+        }
+    }
+    anyhow::bail!("setup server stopped accepting connections")
+}
+
+/// Answer one request; true once credentials have been stored
+fn handle_client(mut stream: TcpStream, nvs: &EspDefaultNvsPartition) -> anyhow::Result<bool> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut buffer = [0u8; 1024];
+    let bytes_read = stream.read(&mut buffer)?;
+    let Some(request) = HttpRequest::parse(&buffer[..bytes_read]) else {
+        return Ok(false);
+    };
+
+    let (response, saved) = match (request.method, request.path) {
+        ("POST", "/wifi") => {
+            let body = std::str::from_utf8(request.body).unwrap_or_default();
+            let credentials = WifiCredentials {
+                ssid: form_field(body, "ssid").unwrap_or_default(),
+                password: form_field(body, "password").unwrap_or_default(),
+            };
+            if !credentials.is_valid() {
+                let page = "<p>The network name must be 1-32 bytes and the password \
+                            empty or 8-64 bytes. <a href=\"/\">Back</a></p>";
+                (Response::html(400, page), false)
+            } else {
+                credentials.save(nvs)?;
+                let page = format!(
+                    "<p>Saved. Restarting to join '{}'…</p>",
+                    html_escape(&credentials.ssid)
+                );
+                (Response::html(200, &page), true)
+            }
+        }
+        // Any other path shows the form, so captive-portal probes land on it
+        _ => (Response::html(200, SETUP_PAGE), false),
+    };
+    write_response(&mut stream, &response)?;
+    Ok(saved)
+}
+
+/// Decoded value of `name` in an `application/x-www-form-urlencoded` body
+fn form_field(body: &str, name: &str) -> Option<String> {
+    body.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| percent_decode(value))
+    })
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,97 @@
+// Boot-time self-test
+//
+// Runs once after the peripherals are brought up. The results are cached and
+// served by `GET /api/health`, and a failing check is shown on the display so
+// the board never just sits there with a blank panel.
+
+use boid_shared::HealthResponse;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_svc::sys;
+use log::{info, warn};
+
+use crate::display::Screen;
+
+const NVS_NAMESPACE: &str = "boid";
+
+/// How long the failure screen pauses the flock
+pub const FAILURE_SCREEN_MS: u64 = 3000;
+
+/// Probe the remaining hardware and combine it with the results of
+/// display and camera initialization
+pub fn run(display_ok: bool, camera_ok: bool, nvs: EspDefaultNvsPartition) -> HealthResponse {
+    let report = HealthResponse {
+        display_ok,
+        camera_ok,
+        wifi_rssi: wifi_rssi(),
+        psram_present: psram_present(),
+        nvs_ok: nvs_readable(nvs),
+    };
+
+    if report.is_healthy() {
+        info!("Self-test passed: {:?}", report);
+    } else {
+        warn!("Self-test failed: {:?}", report);
+    }
+
+    report
+}
+
+fn wifi_rssi() -> Option<i8> {
+    let mut ap_info = sys::wifi_ap_record_t::default();
+    // SAFETY: ap_info is a valid, writable record; WiFi is started before the self-test runs
+    let result = unsafe { sys::esp_wifi_sta_get_ap_info(&mut ap_info) };
+    (result == sys::ESP_OK).then_some(ap_info.rssi)
+}
+
+fn psram_present() -> bool {
+    // SAFETY: read-only query of the heap allocator
+    unsafe { sys::heap_caps_get_total_size(sys::MALLOC_CAP_SPIRAM) > 0 }
+}
+
+fn nvs_readable(nvs: EspDefaultNvsPartition) -> bool {
+    match EspNvs::<NvsDefault>::new(nvs, NVS_NAMESPACE, true) {
+        Ok(store) => store.contains("selftest").is_ok(),
+        Err(e) => {
+            warn!("NVS open failed: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Draw a per-check PASS/FAIL summary
+pub fn show_status_screen(display: &mut Screen, report: &HealthResponse) {
+    display.clear(Rgb565::BLACK).ok();
+
+    let title_style = MonoTextStyle::new(&FONT_6X10, Rgb565::YELLOW);
+    Text::new("SELF-TEST FAILED", Point::new(10, 20), title_style)
+        .draw(display)
+        .ok();
+
+    let rssi_ok = report.wifi_rssi.is_some();
+    let checks = [
+        ("DISPLAY", report.display_ok),
+        ("CAMERA", report.camera_ok),
+        ("WIFI", rssi_ok),
+        ("PSRAM", report.psram_present),
+        ("NVS", report.nvs_ok),
+    ];
+
+    for (row, (name, ok)) in checks.iter().enumerate() {
+        let (label, color) = if *ok {
+            ("PASS", Rgb565::GREEN)
+        } else {
+            ("FAIL", Rgb565::RED)
+        };
+        let line = format!("{:<8} {}", name, label);
+        let y = 45 + row as i32 * 15;
+        Text::new(&line, Point::new(10, y), MonoTextStyle::new(&FONT_6X10, color))
+            .draw(display)
+            .ok();
+    }
+}
@@ -1,7 +1,66 @@
-use boid_core::{BoidConfig, Vector2D};
+use std::time::Instant;
+
+use boid_core::{
+    BoidConfig, ConfigHistory, ConfigTransition, ParameterScales, TargetInterpolator, Vector2D,
+};
+use boid_shared::mirror::FlockFrame;
+use boid_shared::{LandmarkSmoother, LensCalibration, TargetArbiter, WindUpdate};
+
+use crate::camera::FrameSize;
+use crate::persist::StoredSettings;
+
+/// Number of configuration changes kept for undo
+pub const CONFIG_HISTORY_SIZE: usize = 16;
+
+/// Text the flock is spelling out, set through `/api/spell`
+pub struct SpellState {
+    pub text: String,
+    /// When the flock disperses again; `None` holds until cleared
+    pub until: Option<Instant>,
+}
 
 /// Shared state for boid simulation
 pub struct SimulationState {
     pub target_position: Option<Vector2D>,
+    /// Recent target positions, so the flock follows a smooth path between
+    /// updates instead of jumping at every one
+    pub target_path: TargetInterpolator,
+    /// When the state was created; target samples are timed from here
+    pub started: Instant,
+    /// Which client's targets the flock follows when several send them
+    pub arbiter: TargetArbiter,
     pub config: BoidConfig,
+    /// Preset crossfade advanced by the main loop, one step per frame
+    pub transition: Option<ConfigTransition>,
+    pub history: ConfigHistory<CONFIG_HISTORY_SIZE>,
+    /// Latest JSON snapshot of the flock, refreshed by the main loop
+    pub snapshot: Option<String>,
+    /// Every boid as of the last frame, served by `/api/flock`
+    pub flock_frame: FlockFrame,
+    /// Size of the camera frames posted positions refer to
+    pub frame_size: FrameSize,
+    /// Lens correction applied to positions posted from camera frames
+    pub calibration: LensCalibration,
+    /// Temporal smoothing of posted positions; off until configured
+    pub smoother: LandmarkSmoother,
+    /// Hand depth from a stereo client (0 near, 1 far), scaling the seek
+    /// radius; `None` leaves it as configured
+    pub hand_depth: Option<f32>,
+    /// Multipliers from a client's input mappings, applied on top of
+    /// `config` each frame and never saved
+    pub scales: ParameterScales,
+    pub spell: Option<SpellState>,
+    /// Set whenever `spell` changes, so the main loop re-forms the flock
+    pub spell_changed: bool,
+    /// Wind from `/api/wind` the main loop has yet to apply
+    pub wind: Option<WindUpdate>,
+    /// Settings and flock size a client set last, kept across reboots. The
+    /// main loop resizes the flock to `boid_count`.
+    pub persisted: StoredSettings,
+    /// Set by `/api/settings/reset`, so the main loop erases the saved copy
+    pub forget_persisted: bool,
+    /// Boids in the flock, as of the last frame
+    pub boid_count: usize,
+    /// Frames simulated over the last second, reported by `/api/status`
+    pub fps: u32,
 }
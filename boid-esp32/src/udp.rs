@@ -0,0 +1,72 @@
+// Low-latency target input over UDP
+//
+// Listens for datagrams holding one postcard-encoded `TargetPositionUpdate`
+// (see `boid_shared::udp`). There is no acknowledgement: when several
+// datagrams queue up while the thread sleeps, only the newest is applied and
+// the rest are dropped. Settings and status stay on HTTP. Senders are told
+// apart by address for target arbitration, like HTTP clients without a
+// token.
+
+use std::io::ErrorKind;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use boid_shared::udp::{self, DEFAULT_PORT};
+use boid_shared::TargetPositionUpdate;
+use log::{info, warn};
+
+use crate::http_server;
+use crate::types::SimulationState;
+
+// Room for a datagram that is too long, so it can be told apart and dropped
+const RECV_BUFFER_LEN: usize = 32;
+
+/// Bind the target port and spawn the receive loop
+pub fn spawn_listener(sim_state: Arc<Mutex<SimulationState>>) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(("0.0.0.0", DEFAULT_PORT))?;
+    info!("UDP target channel listening on port {}", DEFAULT_PORT);
+
+    thread::spawn(move || loop {
+        match receive_latest(&socket) {
+            Ok(Some((sender, update))) => {
+                let client = sender.to_string();
+                // Refused targets are dropped like malformed ones
+                let _ = http_server::set_target(
+                    &mut sim_state.lock().unwrap(),
+                    &client,
+                    update.position,
+                );
+            }
+            Ok(None) => {}
+            Err(e) => warn!("UDP receive failed: {:?}", e),
+        }
+    });
+    Ok(())
+}
+
+/// Block for one datagram, then drain whatever else is already queued
+///
+/// Returns the newest valid update and its sender, or `None` if all of them
+/// were malformed.
+fn receive_latest(socket: &UdpSocket) -> std::io::Result<Option<(IpAddr, TargetPositionUpdate)>> {
+    let mut buf = [0u8; RECV_BUFFER_LEN];
+
+    socket.set_nonblocking(false)?;
+    let (len, sender) = socket.recv_from(&mut buf)?;
+    let mut latest = udp::decode_target(&buf[..len]).map(|update| (sender.ip(), update));
+
+    socket.set_nonblocking(true)?;
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((len, sender)) => {
+                if let Some(update) = udp::decode_target(&buf[..len]) {
+                    latest = Some((sender.ip(), update));
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(latest)
+}
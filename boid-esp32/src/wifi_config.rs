@@ -1,5 +1,76 @@
 // WiFi configuration
-// Set your credentials in cfg.toml (copy from cfg.toml.example)
-// These values are loaded at compile time by build.rs
+//
+// Credentials entered on the setup page (see `provisioning.rs`) are stored
+// in NVS and tried first. The ones in cfg.toml (copy from cfg.toml.example),
+// loaded at compile time by build.rs, are tried next and may be left empty.
+
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
 pub const SSID: &str = env!("WIFI_SSID");
 pub const PASSWORD: &str = env!("WIFI_PASSWORD");
+
+const NVS_NAMESPACE: &str = "boid";
+const CREDENTIALS_KEY: &str = "wifi";
+
+/// SSID and password lengths the ESP-IDF station configuration accepts;
+/// an empty password joins an open network
+pub const MAX_SSID_LEN: usize = 32;
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_PASSWORD_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WifiCredentials {
+    pub ssid: String,
+    pub password: String,
+}
+
+impl WifiCredentials {
+    /// Credentials to try, in order: the stored ones, then cfg.toml's if
+    /// set and different
+    pub fn candidates(nvs: &EspDefaultNvsPartition) -> Vec<Self> {
+        let mut candidates: Vec<Self> = Self::stored(nvs).into_iter().collect();
+        let compiled = Self {
+            ssid: SSID.to_string(),
+            password: PASSWORD.to_string(),
+        };
+        if !compiled.ssid.is_empty() && !candidates.contains(&compiled) {
+            candidates.push(compiled);
+        }
+        candidates
+    }
+
+    fn stored(nvs: &EspDefaultNvsPartition) -> Option<Self> {
+        let nvs = EspNvs::new(nvs.clone(), NVS_NAMESPACE, true)
+            .map_err(|e| warn!("WiFi credential storage unavailable: {:?}", e))
+            .ok()?;
+        let mut buffer = [0u8; 192];
+        match nvs.get_str(CREDENTIALS_KEY, &mut buffer) {
+            Ok(Some(json)) => serde_json::from_str(json)
+                .map_err(|e| warn!("Ignoring stored WiFi credentials: {}", e))
+                .ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Reading stored WiFi credentials failed: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Store the credentials for the next boot. The password is kept in
+    /// plain text, like ESP-IDF's own WiFi configuration.
+    pub fn save(&self, nvs: &EspDefaultNvsPartition) -> anyhow::Result<()> {
+        let mut storage = EspNvs::new(nvs.clone(), NVS_NAMESPACE, true)?;
+        storage.set_str(CREDENTIALS_KEY, &serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the station configuration can hold these credentials
+    pub fn is_valid(&self) -> bool {
+        !self.ssid.is_empty()
+            && self.ssid.len() <= MAX_SSID_LEN
+            && (self.password.is_empty()
+                || (MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&self.password.len()))
+    }
+}
@@ -0,0 +1,173 @@
+//! Intermediate results of a detection, for diagnosing missed hands
+//!
+//! When recording is on, a detector keeps the last frame's skin mask, the
+//! blobs it considered and the fingertips it chose, and with background
+//! subtraction on, the motion mask. [`FrameArtifacts::render`] draws them as
+//! RGBA buffers of the frame's size, to show next to the camera image or
+//! save as pictures.
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use crate::{BlobRejection, BlobShape};
+use boid_shared::{HandLandmarks, Position};
+
+/// Color of blobs that passed the shape filters but were not the largest
+const CANDIDATE_COLOR: [u8; 4] = [255, 220, 0, 255];
+const CHOSEN_COLOR: [u8; 4] = [0, 230, 60, 255];
+const REJECTED_COLOR: [u8; 4] = [230, 40, 40, 255];
+const THUMB_COLOR: [u8; 4] = [40, 140, 255, 255];
+const INDEX_COLOR: [u8; 4] = [255, 40, 220, 255];
+/// Gray of skin pixels under the blobs in the composite
+const COMPOSITE_SKIN: u8 = 110;
+/// Half the length of a fingertip cross's arms, in pixels
+const TIP_RADIUS: i64 = 4;
+
+/// A buffer [`FrameArtifacts::render`] can draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactLayer {
+    /// Skin pixels white, the rest black
+    SkinMask,
+    /// Pixels differing from the background white; all black without
+    /// background subtraction
+    MotionMask,
+    /// Blob bounding boxes and fingertips on a transparent background
+    Candidates,
+    /// The candidates over a dimmed skin mask
+    Composite,
+}
+
+impl ArtifactLayer {
+    pub const ALL: [ArtifactLayer; 4] = [
+        Self::SkinMask,
+        Self::MotionMask,
+        Self::Candidates,
+        Self::Composite,
+    ];
+
+    /// `"skin_mask"`, `"motion_mask"`, `"candidates"` or `"composite"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SkinMask => "skin_mask",
+            Self::MotionMask => "motion_mask",
+            Self::Candidates => "candidates",
+            Self::Composite => "composite",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layer| layer.name() == name)
+    }
+}
+
+/// A blob the detector considered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub shape: BlobShape,
+    /// `None` for blobs that passed the shape filters
+    pub rejection: Option<BlobRejection>,
+}
+
+/// What a detector saw in one frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameArtifacts {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major over the whole frame; pixels outside the searched region
+    /// are not skin
+    pub skin_mask: Vec<bool>,
+    /// Like `skin_mask`, for pixels differing from the background; empty
+    /// without background subtraction. Blobs are made of pixels in both.
+    pub motion_mask: Vec<bool>,
+    /// Blobs large enough to be considered, in frame coordinates
+    pub candidates: Vec<Candidate>,
+    /// Index into `candidates` of the blob the fingertips came from
+    pub chosen: Option<usize>,
+    pub hand: Option<HandLandmarks>,
+}
+
+impl FrameArtifacts {
+    /// An empty record for a `width` x `height` frame
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            skin_mask: vec![false; width * height],
+            ..Self::default()
+        }
+    }
+
+    /// `layer` as `width * height * 4` RGBA bytes
+    pub fn render(&self, layer: ArtifactLayer) -> Vec<u8> {
+        let mut rgba = vec![0u8; self.width * self.height * 4];
+        if layer == ArtifactLayer::MotionMask {
+            for (pixel, &moving) in rgba.chunks_exact_mut(4).zip(self.motion_mask.iter()) {
+                let gray = if moving { 255 } else { 0 };
+                pixel.copy_from_slice(&[gray, gray, gray, 255]);
+            }
+            // Opaque black where nothing was recorded
+            for pixel in rgba.chunks_exact_mut(4).skip(self.motion_mask.len()) {
+                pixel[3] = 255;
+            }
+            return rgba;
+        }
+        if layer != ArtifactLayer::Candidates {
+            let skin = match layer {
+                ArtifactLayer::SkinMask => 255,
+                _ => COMPOSITE_SKIN,
+            };
+            for (pixel, &is_skin) in rgba.chunks_exact_mut(4).zip(self.skin_mask.iter()) {
+                let gray = if is_skin { skin } else { 0 };
+                pixel.copy_from_slice(&[gray, gray, gray, 255]);
+            }
+        }
+        if layer == ArtifactLayer::SkinMask {
+            return rgba;
+        }
+
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            let color = match (candidate.rejection, self.chosen == Some(i)) {
+                (_, true) => CHOSEN_COLOR,
+                (Some(_), false) => REJECTED_COLOR,
+                (None, false) => CANDIDATE_COLOR,
+            };
+            self.draw_box(&mut rgba, &candidate.shape, color);
+        }
+        if let Some(hand) = &self.hand {
+            self.draw_cross(&mut rgba, hand.thumb_tip, THUMB_COLOR);
+            self.draw_cross(&mut rgba, hand.index_tip, INDEX_COLOR);
+        }
+        rgba
+    }
+
+    fn draw_box(&self, rgba: &mut [u8], shape: &BlobShape, color: [u8; 4]) {
+        let (min, max) = (shape.min, shape.max);
+        for x in min.x..=max.x {
+            self.put(rgba, x as i64, min.y as i64, color);
+            self.put(rgba, x as i64, max.y as i64, color);
+        }
+        for y in min.y..=max.y {
+            self.put(rgba, min.x as i64, y as i64, color);
+            self.put(rgba, max.x as i64, y as i64, color);
+        }
+    }
+
+    fn draw_cross(&self, rgba: &mut [u8], at: Position, color: [u8; 4]) {
+        let (x, y) = (at.x as i64, at.y as i64);
+        for d in -TIP_RADIUS..=TIP_RADIUS {
+            self.put(rgba, x + d, y, color);
+            self.put(rgba, x, y + d, color);
+        }
+    }
+
+    fn put(&self, rgba: &mut [u8], x: i64, y: i64, color: [u8; 4]) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let offset = (y as usize * self.width + x as usize) * 4;
+        rgba[offset..offset + 4].copy_from_slice(&color);
+    }
+}
@@ -0,0 +1,157 @@
+//! Background model for scenes with skin-toned surfaces
+//!
+//! Skin color alone cannot tell a hand from a wooden desk or a beige wall.
+//! With background subtraction on, the detector keeps a running average of
+//! the empty scene, one color per cell of the frame, and looks for skin
+//! only where the frame differs from it. The first frame is taken as the
+//! background, so it should show the scene without the hand;
+//! [`HandDetector::reset_background`](crate::HandDetector::reset_background)
+//! recaptures it.
+//!
+//! Cells that match the background are blended into it, so it follows slow
+//! changes such as daylight. Moving cells are left out, so a hand held
+//! still is not learned away. When most of the frame differs at once, the
+//! scene changed (the camera moved or a light was switched on) and the
+//! background is recaptured.
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use crate::Rgb;
+
+/// Default summed difference of the three channels, 0-765, above which a
+/// cell counts as moving
+pub const DEFAULT_MOTION_THRESHOLD: u16 = 60;
+/// A cell moves this share of the way to a matching frame per frame
+const LEARN_SHIFT: i16 = 4;
+/// Share of a full frame that, moving at once, means the scene changed
+const SCENE_CHANGE_FRACTION: f32 = 0.5;
+
+/// Running average of the scene, per cell of a `cols` x `rows` grid over
+/// the whole frame
+#[derive(Debug, Clone)]
+pub(crate) struct Background {
+    cols: usize,
+    rows: usize,
+    /// `None` for cells no search has covered yet
+    cells: Vec<Option<[u8; 3]>>,
+}
+
+impl Background {
+    pub(crate) fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![None; cols * rows],
+        }
+    }
+
+    pub(crate) fn fits(&self, cols: usize, rows: usize) -> bool {
+        (self.cols, self.rows) == (cols, rows)
+    }
+
+    /// Which of the `cols` x `rows` cells at grid cell `origin` differ from
+    /// the background by more than `threshold`, learning the rest. Cells
+    /// seen for the first time are taken as background. `whole_frame` lets
+    /// a frame that differs almost everywhere replace the background.
+    pub(crate) fn subtract(
+        &mut self,
+        origin: (usize, usize),
+        (cols, rows): (usize, usize),
+        threshold: u16,
+        whole_frame: bool,
+        cell: impl Fn(usize) -> Rgb,
+    ) -> Vec<bool> {
+        let index = |idx: usize| (origin.1 + idx / cols) * self.cols + origin.0 + idx % cols;
+        let mut motion: Vec<bool> = (0..cols * rows)
+            .map(|idx| {
+                let rgb = cell(idx);
+                self.cells[index(idx)].is_some_and(|known| difference(known, rgb) > threshold)
+            })
+            .collect();
+
+        let moving = motion.iter().filter(|&&moving| moving).count();
+        let changed = whole_frame && moving as f32 > (cols * rows) as f32 * SCENE_CHANGE_FRACTION;
+        if changed {
+            motion.fill(false);
+        }
+        for (idx, _) in motion.iter().enumerate().filter(|&(_, &moving)| !moving) {
+            let rgb = cell(idx);
+            let current = [rgb.r, rgb.g, rgb.b];
+            let known = &mut self.cells[index(idx)];
+            *known = Some(match *known {
+                Some(known) if !changed => learn(known, current),
+                _ => current,
+            });
+        }
+        motion
+    }
+}
+
+fn difference(known: [u8; 3], rgb: Rgb) -> u16 {
+    known
+        .iter()
+        .zip([rgb.r, rgb.g, rgb.b])
+        .map(|(&a, b)| a.abs_diff(b) as u16)
+        .sum()
+}
+
+/// One step of the running average, at least one level toward `current`
+fn learn(known: [u8; 3], current: [u8; 3]) -> [u8; 3] {
+    let mut next = known;
+    for (channel, &target) in next.iter_mut().zip(&current) {
+        let diff = target as i16 - *channel as i16;
+        let step = match diff >> LEARN_SHIFT {
+            0 => diff.signum(),
+            step => step,
+        };
+        *channel = (*channel as i16 + step) as u8;
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moving_cells_and_learning() {
+        let mut background = Background::new(2, 1);
+        let gray = |level: u8| move |_| Rgb::new(level, level, level);
+        // The first frame is the background
+        assert_eq!(
+            background.subtract((0, 0), (2, 1), 60, false, gray(100)),
+            [false, false]
+        );
+
+        let hand = |idx| match idx {
+            0 => Rgb::new(180, 150, 120),
+            _ => Rgb::new(102, 102, 102),
+        };
+        assert_eq!(
+            background.subtract((0, 0), (2, 1), 60, false, hand),
+            [true, false]
+        );
+        // The hand's cell is kept, the other one follows the light
+        assert_eq!(background.cells, [Some([100; 3]), Some([101; 3])]);
+
+        // Everything moving at once recaptures the whole frame
+        assert_eq!(
+            background.subtract((0, 0), (2, 1), 60, true, gray(200)),
+            [false, false]
+        );
+        assert_eq!(background.cells, [Some([200; 3]); 2]);
+    }
+
+    #[test]
+    fn test_learning_reaches_the_frame() {
+        let mut known = [0, 255, 100];
+        for _ in 0..200 {
+            known = learn(known, [255, 0, 100]);
+        }
+        assert_eq!(known, [255, 0, 100]);
+    }
+}
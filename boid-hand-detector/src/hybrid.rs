@@ -0,0 +1,148 @@
+//! Hybrid detection: an accurate but slow detector, such as MediaPipe,
+//! finds the hand a few times a second, and the cheap skin tracker follows
+//! it at full frame rate inside the region around it
+//!
+//! Both sides implement [`HandDetection`], so any backend can propose
+//! regions. Between proposals, the tracker's fingertip picks are shifted by
+//! how far they were off the proposer's at the last proposal.
+
+use crate::{HandDetector, Roi};
+use boid_shared::{HandLandmarks, Position};
+
+/// Frames between proposals by default: 5Hz at 30fps
+pub const DEFAULT_PROPOSAL_INTERVAL: u32 = 6;
+
+/// Default padding in pixels around the fingertips when cropping to the
+/// hand; the palm below them gets three times as much
+pub const DEFAULT_MARGIN: usize = 60;
+
+pub use boid_shared::detection::{Frame, HandDetectorBackend, PixelFormat};
+
+/// A detector that can also search part of a frame, as the hybrid
+/// detector's tracker and proposer do
+pub trait HandDetection: HandDetectorBackend {
+    /// Landmarks of a hand inside `roi`, in frame pixels. Backends that
+    /// cannot crop search the whole frame.
+    fn detect_in(&mut self, frame: &Frame<'_>, roi: Roi) -> Option<HandLandmarks> {
+        let _ = roi;
+        self.detect(frame)
+    }
+}
+
+impl HandDetectorBackend for HandDetector {
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        self.detect_in(frame, Roi::new(0, 0, frame.width, frame.height))
+    }
+}
+
+impl HandDetection for HandDetector {
+    fn detect_in(&mut self, frame: &Frame<'_>, roi: Roi) -> Option<HandLandmarks> {
+        match frame.format {
+            PixelFormat::Rgba => self.track_rgba_region(frame.width, frame.height, frame.data, roi),
+            PixelFormat::Bgr => self.track_bgr_region(frame.width, frame.height, frame.data, roi),
+        }
+    }
+}
+
+/// Runs `proposer` every few frames and a [`HandDetector`] inside the
+/// proposed region on every frame
+pub struct HybridDetector<P> {
+    proposer: P,
+    tracker: HandDetector,
+    interval: u32,
+    margin: usize,
+    frames_until_proposal: u32,
+    roi: Option<Roi>,
+    /// Proposer minus tracker position of thumb and index tip at the last
+    /// proposal
+    correction: [(f32, f32); 2],
+}
+
+impl<P: HandDetection> HybridDetector<P> {
+    pub fn new(proposer: P, tracker: HandDetector) -> Self {
+        Self {
+            proposer,
+            tracker,
+            interval: DEFAULT_PROPOSAL_INTERVAL,
+            margin: DEFAULT_MARGIN,
+            frames_until_proposal: 0,
+            roi: None,
+            correction: [(0.0, 0.0); 2],
+        }
+    }
+
+    /// Ask the proposer every `frames` frames (at least 1)
+    pub fn with_proposal_interval(mut self, frames: u32) -> Self {
+        self.interval = frames.max(1);
+        self
+    }
+
+    /// Padding around the fingertips of the tracked region
+    pub fn with_margin(mut self, pixels: usize) -> Self {
+        self.margin = pixels;
+        self
+    }
+
+    /// Region the tracker searches; `None` until the proposer finds a
+    /// hand, and again after it loses it
+    pub fn region(&self) -> Option<Roi> {
+        self.roi
+    }
+
+    pub fn proposer_mut(&mut self) -> &mut P {
+        &mut self.proposer
+    }
+
+    /// Crop around the fingertips, with room for the palm below them
+    fn region_around(&self, hand: &HandLandmarks, frame: &Frame<'_>) -> Roi {
+        let tips = [hand.thumb_tip, hand.index_tip];
+        let min_x = tips.iter().map(|p| p.x).fold(f32::MAX, f32::min).max(0.0) as usize;
+        let max_x = tips.iter().map(|p| p.x).fold(0.0, f32::max) as usize;
+        let min_y = tips.iter().map(|p| p.y).fold(f32::MAX, f32::min).max(0.0) as usize;
+        let max_y = tips.iter().map(|p| p.y).fold(0.0, f32::max) as usize;
+        let x = min_x.saturating_sub(self.margin);
+        let y = min_y.saturating_sub(self.margin);
+        Roi::new(x, y, max_x + self.margin - x, max_y + 3 * self.margin - y)
+            .clamp(frame.width, frame.height)
+    }
+}
+
+impl<P: HandDetection> HandDetection for HybridDetector<P> {}
+
+impl<P: HandDetection> HandDetectorBackend for HybridDetector<P> {
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        if self.frames_until_proposal == 0 {
+            self.frames_until_proposal = self.interval - 1;
+            let Some(proposal) = self.proposer.detect(frame) else {
+                self.roi = None;
+                return None;
+            };
+            let roi = self.region_around(&proposal, frame);
+            self.roi = Some(roi);
+            // Learn how far the tracker's picks are off for this hand pose
+            self.correction = match self.tracker.detect_in(frame, roi) {
+                Some(tracked) => [
+                    offset(tracked.thumb_tip, proposal.thumb_tip),
+                    offset(tracked.index_tip, proposal.index_tip),
+                ],
+                None => [(0.0, 0.0); 2],
+            };
+            return Some(proposal);
+        }
+        self.frames_until_proposal -= 1;
+
+        let tracked = self.tracker.detect_in(frame, self.roi?)?;
+        let [thumb, index] = self.correction;
+        let fused = HandLandmarks::new(
+            Position::new(tracked.thumb_tip.x + thumb.0, tracked.thumb_tip.y + thumb.1),
+            Position::new(tracked.index_tip.x + index.0, tracked.index_tip.y + index.1),
+        );
+        // Follow the hand until the next proposal
+        self.roi = Some(self.region_around(&fused, frame));
+        Some(fused)
+    }
+}
+
+fn offset(from: Position, to: Position) -> (f32, f32) {
+    (to.x - from.x, to.y - from.y)
+}
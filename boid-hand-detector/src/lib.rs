@@ -16,7 +16,18 @@ use std::vec::Vec;
 extern crate alloc;
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
+
+pub mod artifacts;
+pub mod background;
+pub mod hybrid;
+#[cfg(feature = "std")]
+pub mod watchdog;
+
+use artifacts::Candidate;
+pub use artifacts::{ArtifactLayer, FrameArtifacts};
+use background::Background;
+pub use background::DEFAULT_MOTION_THRESHOLD;
 
 /// RGB color value
 #[derive(Debug, Clone, Copy)]
@@ -73,16 +84,100 @@ impl Rgb {
 
     /// Check if this color is likely skin tone
     pub fn is_skin_color(&self) -> bool {
+        self.is_skin_color_within(SKIN_V_MIN, SKIN_V_MAX)
+    }
+
+    /// [`is_skin_color`](Self::is_skin_color) with the value (brightness)
+    /// range `v_min..=v_max` instead of the default 25-95
+    pub fn is_skin_color_within(&self, v_min: f32, v_max: f32) -> bool {
         let hsv = self.to_hsv();
 
         // Skin color in HSV space
         // Hue: 0-50 (reddish/orange/yellow tones to accommodate different skin tones)
         // Saturation: 15-90 (allow for lighter skin tones with lower saturation)
-        // Value: 25-95 (avoid very dark or very bright pixels)
-        hsv.h <= 50.0 && hsv.s >= 15.0 && hsv.s <= 90.0 && hsv.v >= 25.0 && hsv.v <= 95.0
+        // Value: avoid very dark or very bright pixels for the lighting
+        hsv.h <= 50.0 && hsv.s >= 15.0 && hsv.s <= 90.0 && hsv.v >= v_min && hsv.v <= v_max
+    }
+
+    /// HSV value (0-100) without the rest of the conversion
+    fn value(&self) -> f32 {
+        self.r.max(self.g).max(self.b) as f32 / 255.0 * 100.0
     }
 }
 
+/// Skin value range for a frame of the reference brightness
+const SKIN_V_MIN: f32 = 25.0;
+const SKIN_V_MAX: f32 = 95.0;
+/// Median frame brightness the default skin range was tuned for
+const REFERENCE_BRIGHTNESS: f32 = 50.0;
+/// Threshold shift per unit of brightness away from the reference
+const EXPOSURE_GAIN: f32 = 0.5;
+/// Largest shift in either direction, so a frame filled by a white wall or
+/// a dark sleeve cannot push skin out of range
+const MAX_EXPOSURE_SHIFT: f32 = 20.0;
+/// Fraction of each frame's brightness blended into the tracked brightness;
+/// lighting changes slowly, a hand entering the frame should not
+const EXPOSURE_RATE: f32 = 0.1;
+
+/// Skin value range adapted to the brightness of recent frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposureState {
+    /// Median brightness (HSV value, 0-100) of the last frame
+    pub frame_brightness: f32,
+    /// Brightness the thresholds follow, smoothed over frames
+    pub brightness: f32,
+    /// Skin value range in effect
+    pub v_min: f32,
+    pub v_max: f32,
+}
+
+impl ExposureState {
+    /// The default range, as used before the first frame or with
+    /// adaptation disabled
+    pub fn neutral() -> Self {
+        Self::at(REFERENCE_BRIGHTNESS, REFERENCE_BRIGHTNESS)
+    }
+
+    fn at(frame_brightness: f32, brightness: f32) -> Self {
+        let shift = ((brightness - REFERENCE_BRIGHTNESS) * EXPOSURE_GAIN)
+            .clamp(-MAX_EXPOSURE_SHIFT, MAX_EXPOSURE_SHIFT);
+        Self {
+            frame_brightness,
+            brightness,
+            v_min: SKIN_V_MIN + shift,
+            v_max: (SKIN_V_MAX + shift).min(100.0),
+        }
+    }
+
+    /// How far the range has moved from the default, in value percent
+    pub fn shift(&self) -> f32 {
+        self.v_min - SKIN_V_MIN
+    }
+}
+
+impl Default for ExposureState {
+    fn default() -> Self {
+        Self::neutral()
+    }
+}
+
+/// Median HSV value (0-100) over `pixels` pixels
+fn median_brightness(pixels: usize, pixel: &impl Fn(usize) -> Rgb) -> f32 {
+    let mut histogram = [0u32; 101];
+    for idx in 0..pixels {
+        histogram[pixel(idx).value() as usize] += 1;
+    }
+    let half = (pixels as u32).div_ceil(2);
+    let mut seen = 0;
+    for (value, &count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= half {
+            return value as f32;
+        }
+    }
+    REFERENCE_BRIGHTNESS
+}
+
 /// A 2D point in image coordinates
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
@@ -102,10 +197,142 @@ impl Point {
     }
 }
 
+/// Rectangle of a frame, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Roi {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Roi {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The part of this rectangle inside a `width` x `height` frame
+    pub fn clamp(&self, width: usize, height: usize) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Self::new(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Why a candidate skin blob was rejected by the shape filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobRejection {
+    /// Bounding box is too elongated (e.g. a forearm)
+    AspectRatio,
+    /// Blob is too solid (e.g. a face) or too ragged (e.g. noise)
+    Solidity,
+    /// Blob covers too little or too much of the frame
+    AreaFraction,
+}
+
+/// Shape statistics for a connected skin-colored region
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlobShape {
+    pub pixel_count: usize,
+    /// Top-left corner of the bounding box
+    pub min: Point,
+    /// Bottom-right corner of the bounding box (inclusive)
+    pub max: Point,
+    /// Short side of the bounding box divided by the long side (0-1)
+    pub aspect_ratio: f32,
+    /// Pixel area divided by convex hull area (0-1)
+    pub solidity: f32,
+    /// Pixel area divided by frame area (0-1)
+    pub area_fraction: f32,
+}
+
+/// A candidate blob that did not pass the shape filters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RejectedBlob {
+    pub shape: BlobShape,
+    pub reason: BlobRejection,
+}
+
+/// Diagnostic output collected while processing a single frame
+#[derive(Debug, Clone, Default)]
+pub struct DetectionDebug {
+    pub rejected: Vec<RejectedBlob>,
+    /// Skin value range used for the frame
+    pub exposure: ExposureState,
+}
+
+/// Default padding in pixels around the hand's blob when following it
+/// from frame to frame
+pub const DEFAULT_TRACKING_MARGIN: usize = 40;
+
+/// Frame width the default thresholds are tuned for, and a working width
+/// that keeps them as they are
+pub const DEFAULT_WORKING_WIDTH: usize = 320;
+
+/// What a detector carries from one frame to the next in the `track_*`
+/// methods
+#[derive(Default)]
+struct FrameState {
+    /// `None` until the first frame with adaptation on
+    exposure: Option<ExposureState>,
+    /// The last frame's, while recording
+    artifacts: Option<FrameArtifacts>,
+    /// Where the next frame is searched first, around the last hand
+    tracked: Option<Roi>,
+    /// `None` until the first frame with subtraction on
+    background: Option<Background>,
+}
+
+impl FrameState {
+    /// Track the brightness of a frame of `pixels` pixels; the first frame
+    /// is taken as is, later ones are blended in
+    fn adapt_exposure(&mut self, pixels: usize, pixel: &impl Fn(usize) -> Rgb) -> ExposureState {
+        let frame = median_brightness(pixels, pixel);
+        let brightness = match self.exposure {
+            Some(state) => state.brightness + (frame - state.brightness) * EXPOSURE_RATE,
+            None => frame,
+        };
+        let state = ExposureState::at(frame, brightness);
+        self.exposure = Some(state);
+        state
+    }
+}
+
 /// Hand detector using skin color detection
+///
+/// The `process_*` methods look at each frame on its own, with the fixed
+/// skin range. The `track_*` methods carry what they learn from frame to
+/// frame: the exposure range, the tracked region, the background and the
+/// artifacts, for whichever of those are turned on.
 pub struct HandDetector {
     min_skin_pixels: usize,
     grouping_threshold: usize,
+    min_aspect_ratio: f32,
+    min_solidity: f32,
+    max_solidity: f32,
+    min_area_fraction: f32,
+    max_area_fraction: f32,
+    adapt_exposure: bool,
+    record_artifacts: bool,
+    /// Side of the pixel blocks averaged into one before detection
+    downscale: usize,
+    /// Frames wider than this are averaged down toward it, and thresholds
+    /// are taken to be in its pixels
+    working_width: Option<usize>,
+    track_roi: bool,
+    tracking_margin: usize,
+    subtract_background: bool,
+    motion_threshold: u16,
+    state: FrameState,
 }
 
 impl HandDetector {
@@ -113,6 +340,20 @@ impl HandDetector {
         Self {
             min_skin_pixels: 2000,
             grouping_threshold: 30,
+            min_aspect_ratio: 0.25,
+            min_solidity: 0.5,
+            max_solidity: 0.95,
+            min_area_fraction: 0.01,
+            max_area_fraction: 0.6,
+            adapt_exposure: false,
+            record_artifacts: false,
+            downscale: 1,
+            working_width: None,
+            track_roi: false,
+            tracking_margin: DEFAULT_TRACKING_MARGIN,
+            subtract_background: false,
+            motion_threshold: DEFAULT_MOTION_THRESHOLD,
+            state: FrameState::default(),
         }
     }
 
@@ -126,6 +367,174 @@ impl HandDetector {
         self
     }
 
+    /// Reject blobs whose bounding box short/long side ratio is below `ratio`
+    pub fn with_min_aspect_ratio(mut self, ratio: f32) -> Self {
+        self.min_aspect_ratio = ratio;
+        self
+    }
+
+    /// Accept only blobs whose solidity (area / convex hull area) lies in `min..=max`
+    pub fn with_solidity_range(mut self, min: f32, max: f32) -> Self {
+        self.min_solidity = min;
+        self.max_solidity = max;
+        self
+    }
+
+    /// Accept only blobs covering `min..=max` of the frame area
+    pub fn with_area_fraction_range(mut self, min: f32, max: f32) -> Self {
+        self.min_area_fraction = min;
+        self.max_area_fraction = max;
+        self
+    }
+
+    /// Follow the overall frame brightness with the skin value range in
+    /// the `track_*` methods, so detection survives lighting changes. Off
+    /// by default, which keeps the fixed range.
+    pub fn with_exposure_adaptation(mut self, enabled: bool) -> Self {
+        self.adapt_exposure = enabled;
+        self
+    }
+
+    /// Keep each `track_*` frame's skin mask, candidate blobs and
+    /// fingertips for [`artifacts`](Self::artifacts); off by default, as it
+    /// copies the mask every frame
+    pub fn with_artifacts(mut self, enabled: bool) -> Self {
+        self.set_artifacts(enabled);
+        self
+    }
+
+    /// Start or stop recording artifacts; stopping drops the last ones
+    pub fn set_artifacts(&mut self, enabled: bool) {
+        self.record_artifacts = enabled;
+        if !enabled {
+            self.state.artifacts = None;
+        }
+    }
+
+    /// What the last frame's detection saw, while recording
+    pub fn artifacts(&self) -> Option<&FrameArtifacts> {
+        self.state.artifacts.as_ref()
+    }
+
+    /// Average `factor` x `factor` pixel blocks into one before looking for
+    /// skin; 1, the default, keeps full resolution. Pixel counts and
+    /// distances are scaled to match, and results stay in frame coordinates.
+    pub fn with_downscale(mut self, factor: usize) -> Self {
+        self.set_downscale(factor);
+        self
+    }
+
+    pub fn set_downscale(&mut self, factor: usize) {
+        self.downscale = factor.max(1);
+    }
+
+    /// Treat every frame as if it were `width` pixels wide, whatever size
+    /// it comes in: wider frames are averaged down by the largest whole
+    /// factor that keeps them at least `width` wide, on top of
+    /// [`with_downscale`](Self::with_downscale). The pixel counts and
+    /// distances set on the detector are then in pixels of a `width` wide
+    /// frame, so one setup suits any camera. Narrower frames are taken as
+    /// they are. `None`, the default, takes thresholds in frame pixels.
+    pub fn with_working_width(mut self, width: Option<usize>) -> Self {
+        self.set_working_width(width);
+        self
+    }
+
+    pub fn set_working_width(&mut self, width: Option<usize>) {
+        self.working_width = width.filter(|&width| width > 0);
+    }
+
+    /// Block side averaged into one cell and frame pixels per threshold
+    /// pixel, for frames `width` pixels wide
+    fn resolution(&self, width: usize) -> (usize, f32) {
+        match self.working_width {
+            Some(working) if width > working => (
+                self.downscale * (width / working),
+                width as f32 / working as f32,
+            ),
+            _ => (self.downscale, 1.0),
+        }
+    }
+
+    /// Search each frame around the hand found in the previous one first,
+    /// and the whole frame only when it is not there (off by default). This
+    /// applies to the `track_*_image` methods; the `_region` ones search
+    /// where they are told.
+    pub fn with_roi_tracking(mut self, enabled: bool) -> Self {
+        self.set_roi_tracking(enabled);
+        self
+    }
+
+    /// Start or stop tracking; stopping forgets the tracked region
+    pub fn set_roi_tracking(&mut self, enabled: bool) {
+        self.track_roi = enabled;
+        if !enabled {
+            self.state.tracked = None;
+        }
+    }
+
+    /// Padding around the hand's blob of the tracked region
+    pub fn with_tracking_margin(mut self, pixels: usize) -> Self {
+        self.tracking_margin = pixels;
+        self
+    }
+
+    /// Look for skin only where the frame differs from a learned
+    /// background in the `track_*` methods, so skin-toned walls and
+    /// furniture are ignored; see [`background`]. Off by default. The first
+    /// frame after turning it on is taken as the background and finds no
+    /// hand.
+    pub fn with_background_subtraction(mut self, enabled: bool) -> Self {
+        self.set_background_subtraction(enabled);
+        self
+    }
+
+    /// Start or stop subtracting; stopping forgets the background
+    pub fn set_background_subtraction(&mut self, enabled: bool) {
+        self.subtract_background = enabled;
+        if !enabled {
+            self.state.background = None;
+        }
+    }
+
+    /// Summed RGB difference (0-765) from the background above which a
+    /// cell counts as foreground
+    pub fn with_motion_threshold(mut self, threshold: u16) -> Self {
+        self.motion_threshold = threshold;
+        self
+    }
+
+    /// Take the next frame as the empty scene, e.g. after moving the
+    /// camera; the hand should be out of view
+    pub fn reset_background(&mut self) {
+        self.state.background = None;
+    }
+
+    /// Region the next frame is searched in first; `None` until a hand is
+    /// found while tracking, and again after it is lost
+    pub fn tracked_region(&self) -> Option<Roi> {
+        self.state.tracked
+    }
+
+    /// The skin value range in effect, for debugging
+    pub fn exposure(&self) -> ExposureState {
+        self.state.exposure.unwrap_or_default()
+    }
+
+    /// Forget the tracked brightness; the next frame is taken as is, e.g.
+    /// after switching cameras
+    pub fn reset_exposure(&mut self) {
+        self.state.exposure = None;
+    }
+
+    /// Run `detect` with the state carried between frames
+    fn with_state<T>(&mut self, detect: impl FnOnce(&Self, &mut FrameState) -> T) -> T {
+        let mut state = core::mem::take(&mut self.state);
+        let result = detect(self, &mut state);
+        self.state = state;
+        result
+    }
+
     /// Process an image and detect hand landmarks
     /// Image data is expected to be in RGBA format (4 bytes per pixel)
     pub fn process_rgba_image(
@@ -133,126 +542,473 @@ impl HandDetector {
         width: usize,
         height: usize,
         data: &[u8],
+    ) -> Option<HandLandmarks> {
+        self.process_rgba_image_with_debug(width, height, data, &mut DetectionDebug::default())
+    }
+
+    /// Same as [`process_rgba_image`](Self::process_rgba_image), additionally
+    /// recording rejected blob candidates into `debug`
+    pub fn process_rgba_image_with_debug(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        debug: &mut DetectionDebug,
     ) -> Option<HandLandmarks> {
         if data.len() < width * height * 4 {
             return None;
         }
+        self.process_frame(None, width, height, rgba_pixel(data), debug)
+    }
 
-        // Find all skin-colored pixels
-        let mut skin_pixels = Vec::new();
+    /// Same as [`process_rgba_image`](Self::process_rgba_image), carrying
+    /// the exposure range, tracked region, background and artifacts over
+    /// to the next frame
+    pub fn track_rgba_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+    ) -> Option<HandLandmarks> {
+        self.track_rgba_image_with_debug(width, height, data, &mut DetectionDebug::default())
+    }
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = (y * width + x) * 4;
-                let rgb = Rgb::new(data[idx], data[idx + 1], data[idx + 2]);
+    pub fn track_rgba_image_with_debug(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 4 {
+            return None;
+        }
+        self.with_state(|detector, state| {
+            detector.process_frame(Some(state), width, height, rgba_pixel(data), debug)
+        })
+    }
 
-                if rgb.is_skin_color() {
-                    skin_pixels.push(Point::new(x, y));
-                }
-            }
+    /// Look for a hand only inside `roi`, e.g. around where a slower,
+    /// more accurate detector last saw one. Landmarks are in frame
+    /// coordinates, and the area filters stay relative to the whole frame.
+    pub fn process_rgba_region(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+    ) -> Option<HandLandmarks> {
+        self.process_rgba_region_with_debug(
+            width,
+            height,
+            data,
+            roi,
+            &mut DetectionDebug::default(),
+        )
+    }
+
+    pub fn process_rgba_region_with_debug(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 4 {
+            return None;
         }
+        self.process_region(None, (width, height), roi, true, rgba_pixel(data), debug)
+            .map(|(_, hand)| hand)
+    }
 
-        if skin_pixels.len() < self.min_skin_pixels {
+    /// [`process_rgba_region`](Self::process_rgba_region) carrying state
+    /// over like [`track_rgba_image`](Self::track_rgba_image)
+    pub fn track_rgba_region(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 4 {
             return None;
         }
+        let debug = &mut DetectionDebug::default();
+        self.with_state(|detector, state| {
+            detector.process_region(
+                Some(state),
+                (width, height),
+                roi,
+                true,
+                rgba_pixel(data),
+                debug,
+            )
+        })
+        .map(|(_, hand)| hand)
+    }
 
-        // Find bounding box of skin region
-        let min_x = skin_pixels.iter().map(|p| p.x).min()?;
-        let max_x = skin_pixels.iter().map(|p| p.x).max()?;
-        let min_y = skin_pixels.iter().map(|p| p.y).min()?;
-        let max_y = skin_pixels.iter().map(|p| p.y).max()?;
+    /// Process BGR image data (OpenCV format)
+    pub fn process_bgr_image(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+    ) -> Option<HandLandmarks> {
+        self.process_bgr_image_with_debug(width, height, data, &mut DetectionDebug::default())
+    }
 
-        if max_x <= min_x || max_y <= min_y {
+    /// Same as [`process_bgr_image`](Self::process_bgr_image), additionally
+    /// recording rejected blob candidates into `debug`
+    pub fn process_bgr_image_with_debug(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 3 {
             return None;
         }
+        self.process_frame(None, width, height, bgr_pixel(data), debug)
+    }
 
-        // Find fingertip candidates in the top third of the hand region
-        let top_threshold = min_y + (max_y - min_y) / 3;
-        let mut top_points: Vec<Point> = skin_pixels
-            .iter()
-            .filter(|p| p.y < top_threshold)
-            .copied()
-            .collect();
+    /// BGR counterpart of [`track_rgba_image`](Self::track_rgba_image)
+    pub fn track_bgr_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+    ) -> Option<HandLandmarks> {
+        self.track_bgr_image_with_debug(width, height, data, &mut DetectionDebug::default())
+    }
 
-        if top_points.len() < 2 {
+    pub fn track_bgr_image_with_debug(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 3 {
             return None;
         }
+        self.with_state(|detector, state| {
+            detector.process_frame(Some(state), width, height, bgr_pixel(data), debug)
+        })
+    }
 
-        // Sort by y-coordinate (topmost first)
-        top_points.sort_by_key(|p| p.y);
+    /// BGR counterpart of [`process_rgba_region`](Self::process_rgba_region)
+    pub fn process_bgr_region(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+    ) -> Option<HandLandmarks> {
+        self.process_bgr_region_with_debug(width, height, data, roi, &mut DetectionDebug::default())
+    }
 
-        // Group nearby points and find cluster centroids
-        let mut finger_candidates: Vec<Point> = Vec::new();
+    pub fn process_bgr_region_with_debug(
+        &self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 3 {
+            return None;
+        }
+        self.process_region(None, (width, height), roi, true, bgr_pixel(data), debug)
+            .map(|(_, hand)| hand)
+    }
 
-        for point in top_points.iter().take(100) {
-            let mut found_group = false;
+    /// BGR counterpart of [`track_rgba_region`](Self::track_rgba_region)
+    pub fn track_bgr_region(
+        &mut self,
+        width: usize,
+        height: usize,
+        data: &[u8],
+        roi: Roi,
+    ) -> Option<HandLandmarks> {
+        if data.len() < width * height * 3 {
+            return None;
+        }
+        let debug = &mut DetectionDebug::default();
+        self.with_state(|detector, state| {
+            detector.process_region(
+                Some(state),
+                (width, height),
+                roi,
+                true,
+                bgr_pixel(data),
+                debug,
+            )
+        })
+        .map(|(_, hand)| hand)
+    }
 
-            for candidate in finger_candidates.iter_mut() {
-                if point.distance_to(candidate) < self.grouping_threshold as f32 {
-                    // Average the positions
-                    candidate.x = (candidate.x + point.x) / 2;
-                    candidate.y = (candidate.y + point.y) / 2;
-                    found_group = true;
-                    break;
+    /// Detect in the whole frame or, while tracking with `state`, around
+    /// the last hand first
+    fn process_frame(
+        &self,
+        mut state: Option<&mut FrameState>,
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize) -> Rgb,
+        debug: &mut DetectionDebug,
+    ) -> Option<HandLandmarks> {
+        if let Some(state) = state.as_deref_mut() {
+            if let Some(roi) = state.tracked.take() {
+                // The tracked region is mostly hand, whose brightness would
+                // drag the skin range along, so the range is kept as it is
+                if let Some((shape, hand)) =
+                    self.process_region(Some(state), (width, height), roi, false, &pixel, debug)
+                {
+                    state.tracked = Some(self.region_around(&shape, width, height));
+                    return Some(hand);
                 }
             }
+        }
+        let full = Roi::new(0, 0, width, height);
+        let (shape, hand) = self.process_region(
+            state.as_deref_mut(),
+            (width, height),
+            full,
+            true,
+            &pixel,
+            debug,
+        )?;
+        if let Some(state) = state.filter(|_| self.track_roi) {
+            state.tracked = Some(self.region_around(&shape, width, height));
+        }
+        Some(hand)
+    }
 
-            if !found_group {
-                finger_candidates.push(*point);
-            }
+    /// The hand's blob with the tracking margin around it
+    fn region_around(&self, shape: &BlobShape, width: usize, height: usize) -> Roi {
+        let margin = (self.tracking_margin as f32 * self.resolution(width).1) as usize;
+        let x = shape.min.x.saturating_sub(margin);
+        let y = shape.min.y.saturating_sub(margin);
+        Roi::new(
+            x,
+            y,
+            shape.max.x + 1 + margin - x,
+            shape.max.y + 1 + margin - y,
+        )
+        .clamp(width, height)
+    }
 
-            if finger_candidates.len() >= 5 {
-                break;
+    /// Detect inside `roi` of a `width` x `height` frame whose pixel `idx`
+    /// is read via `pixel`, and move the results back into frame
+    /// coordinates. Without `state` the fixed skin range is used and
+    /// nothing is learned or recorded; with it, `adapt` lets the frame move
+    /// the skin range. Returns the hand's blob along with its landmarks.
+    fn process_region(
+        &self,
+        mut state: Option<&mut FrameState>,
+        (width, height): (usize, usize),
+        roi: Roi,
+        adapt: bool,
+        pixel: impl Fn(usize) -> Rgb,
+        debug: &mut DetectionDebug,
+    ) -> Option<(BlobShape, HandLandmarks)> {
+        let (scale, units) = self.resolution(width);
+        // Cells line up with those of the whole frame, which the
+        // background is kept in
+        let roi = roi.clamp(width, height);
+        let (dx, dy) = (roi.x % scale, roi.y % scale);
+        let roi = Roi::new(roi.x - dx, roi.y - dy, roi.width + dx, roi.height + dy);
+        let (cols, rows) = (roi.width / scale, roi.height / scale);
+        let mut record =
+            (self.record_artifacts && state.is_some()).then(|| FrameArtifacts::new(width, height));
+        if cols == 0 || rows == 0 {
+            debug.rejected.clear();
+            if let Some(state) = state {
+                state.artifacts = record;
             }
-        }
-
-        if finger_candidates.len() < 2 {
             return None;
         }
 
-        // Sort by x-coordinate (leftmost first)
-        finger_candidates.sort_by_key(|p| p.x);
-
-        // Take leftmost two points as thumb and index
-        let thumb = finger_candidates[0];
-        let index = finger_candidates[1];
+        // Top-left frame pixel of each cell, a `scale` x `scale` block
+        let corner = |idx: usize| (roi.x + idx % cols * scale, roi.y + idx / cols * scale);
+        let cell = |idx: usize| {
+            let (x, y) = corner(idx);
+            if scale == 1 {
+                return pixel(y * width + x);
+            }
+            let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+            for row in y..y + scale {
+                for col in x..x + scale {
+                    let rgb = pixel(row * width + col);
+                    r += rgb.r as u32;
+                    g += rgb.g as u32;
+                    b += rgb.b as u32;
+                }
+            }
+            let n = (scale * scale) as u32;
+            Rgb::new((r / n) as u8, (g / n) as u8, (b / n) as u8)
+        };
+        debug.exposure = match state.as_deref_mut() {
+            Some(state) if adapt && self.adapt_exposure => state.adapt_exposure(cols * rows, &cell),
+            Some(state) => state.exposure.unwrap_or_default(),
+            None => ExposureState::neutral(),
+        };
+        let mut mask = skin_mask(cols, rows, &debug.exposure, cell);
+        let motion = match state.as_deref_mut() {
+            Some(state) if self.subtract_background => {
+                let grid = (width / scale, height / scale);
+                let background = match &mut state.background {
+                    Some(background) if background.fits(grid.0, grid.1) => background,
+                    slot => slot.insert(Background::new(grid.0, grid.1)),
+                };
+                let origin = (roi.x / scale, roi.y / scale);
+                let whole_frame = (cols, rows) == grid;
+                Some(background.subtract(
+                    origin,
+                    (cols, rows),
+                    self.motion_threshold,
+                    whole_frame,
+                    cell,
+                ))
+            }
+            _ => None,
+        };
+        // Cell masks spread over the frame's pixels
+        let fill = |target: &mut Vec<bool>, cells: &[bool]| {
+            for (idx, _) in cells.iter().enumerate().filter(|&(_, &set)| set) {
+                let (x, y) = corner(idx);
+                for row in y..y + scale {
+                    target[row * width + x..row * width + x + scale].fill(true);
+                }
+            }
+        };
+        if let Some(record) = record.as_mut() {
+            fill(&mut record.skin_mask, &mask);
+            if let Some(motion) = &motion {
+                record.motion_mask = vec![false; width * height];
+                fill(&mut record.motion_mask, motion);
+            }
+        }
+        if let Some(motion) = motion {
+            for (skin, moving) in mask.iter_mut().zip(motion) {
+                *skin &= moving;
+            }
+        }
 
-        Some(HandLandmarks::new(
-            boid_shared::Position::new(thumb.x as f32, thumb.y as f32),
-            boid_shared::Position::new(index.x as f32, index.y as f32),
-        ))
+        let frame_area = (width * height) as f32 / (scale * scale) as f32;
+        let cell_size = scale as f32 / units;
+        let found = self.find_hand(
+            (cols, rows),
+            &mask,
+            frame_area,
+            cell_size,
+            debug,
+            record.as_mut(),
+        );
+        let to_frame = |shape: &mut BlobShape| {
+            shape.pixel_count *= scale * scale;
+            shape.min = Point::new(roi.x + shape.min.x * scale, roi.y + shape.min.y * scale);
+            shape.max = Point::new(
+                roi.x + shape.max.x * scale + scale - 1,
+                roi.y + shape.max.y * scale + scale - 1,
+            );
+        };
+        debug
+            .rejected
+            .iter_mut()
+            .for_each(|rejected| to_frame(&mut rejected.shape));
+        record
+            .iter_mut()
+            .flat_map(|record| record.candidates.iter_mut())
+            .for_each(|candidate| to_frame(&mut candidate.shape));
+        let found = found.map(|(mut shape, hand)| {
+            to_frame(&mut shape);
+            // Fingertips sit in the middle of their cell
+            let shift = |p: boid_shared::Position| {
+                let center = (scale - 1) as f32 / 2.0;
+                boid_shared::Position::new(
+                    roi.x as f32 + p.x * scale as f32 + center,
+                    roi.y as f32 + p.y * scale as f32 + center,
+                )
+            };
+            let hand = HandLandmarks::new(shift(hand.thumb_tip), shift(hand.index_tip));
+            (shape, hand)
+        });
+        if let (Some(state), Some(mut record)) = (state, record) {
+            record.hand = found.as_ref().map(|(_, hand)| hand.clone());
+            state.artifacts = Some(record);
+        }
+        found
     }
 
-    /// Process BGR image data (OpenCV format)
-    pub fn process_bgr_image(
+    /// Pick the largest skin blob of a `width` x `height` cell mask that
+    /// passes the shape filters and extract fingertips from it. Pixel
+    /// counts and distances shrink with `cell_size`, the side of a cell in
+    /// the pixels they are given in.
+    fn find_hand(
         &self,
-        width: usize,
-        height: usize,
-        data: &[u8],
-    ) -> Option<HandLandmarks> {
-        if data.len() < width * height * 3 {
+        (width, height): (usize, usize),
+        mask: &[bool],
+        frame_area: f32,
+        cell_size: f32,
+        debug: &mut DetectionDebug,
+        mut record: Option<&mut FrameArtifacts>,
+    ) -> Option<(BlobShape, HandLandmarks)> {
+        debug.rejected.clear();
+
+        let cell_area = cell_size * cell_size;
+        let min_skin_pixels = ((self.min_skin_pixels as f32 / cell_area) as usize).max(1);
+        let skin_count = mask.iter().filter(|&&skin| skin).count();
+        if skin_count < min_skin_pixels {
             return None;
         }
 
-        // Find all skin-colored pixels
-        let mut skin_pixels = Vec::new();
+        let mut best: Option<(Vec<Point>, BlobShape)> = None;
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = (y * width + x) * 3;
-                // BGR format: B, G, R
-                let rgb = Rgb::new(data[idx + 2], data[idx + 1], data[idx]);
+        // Blobs smaller than min_skin_pixels are treated as noise, not candidates
+        for blob in find_blobs(width, height, mask) {
+            if blob.len() < min_skin_pixels {
+                continue;
+            }
 
-                if rgb.is_skin_color() {
-                    skin_pixels.push(Point::new(x, y));
+            let shape = blob_shape(&blob, frame_area);
+            let rejection = self.check_shape(&shape);
+            let best_so_far =
+                rejection.is_none() && best.as_ref().is_none_or(|(b, _)| blob.len() > b.len());
+            if let Some(record) = record.as_deref_mut() {
+                if best_so_far {
+                    record.chosen = Some(record.candidates.len());
                 }
+                record.candidates.push(Candidate { shape, rejection });
+            }
+            if let Some(reason) = rejection {
+                debug.rejected.push(RejectedBlob { shape, reason });
+            } else if best_so_far {
+                best = Some((blob, shape));
             }
         }
 
-        if skin_pixels.len() < self.min_skin_pixels {
-            return None;
+        let (blob, shape) = best?;
+        Some((shape, self.find_fingertips(&blob, cell_size)?))
+    }
+
+    fn check_shape(&self, shape: &BlobShape) -> Option<BlobRejection> {
+        if shape.aspect_ratio < self.min_aspect_ratio {
+            Some(BlobRejection::AspectRatio)
+        } else if shape.solidity < self.min_solidity || shape.solidity > self.max_solidity {
+            Some(BlobRejection::Solidity)
+        } else if shape.area_fraction < self.min_area_fraction
+            || shape.area_fraction > self.max_area_fraction
+        {
+            Some(BlobRejection::AreaFraction)
+        } else {
+            None
         }
+    }
 
+    fn find_fingertips(&self, skin_pixels: &[Point], cell_size: f32) -> Option<HandLandmarks> {
         // Find bounding box of skin region
         let min_x = skin_pixels.iter().map(|p| p.x).min()?;
         let max_x = skin_pixels.iter().map(|p| p.x).max()?;
@@ -280,12 +1036,14 @@ impl HandDetector {
 
         // Group nearby points and find cluster centroids
         let mut finger_candidates: Vec<Point> = Vec::new();
+        let grouping_threshold = self.grouping_threshold as f32 / cell_size;
+        let tips = (100.0 / (cell_size * cell_size)) as usize;
 
-        for point in top_points.iter().take(100) {
+        for point in top_points.iter().take(tips.max(2)) {
             let mut found_group = false;
 
             for candidate in finger_candidates.iter_mut() {
-                if point.distance_to(candidate) < self.grouping_threshold as f32 {
+                if point.distance_to(candidate) < grouping_threshold {
                     // Average the positions
                     candidate.x = (candidate.x + point.x) / 2;
                     candidate.y = (candidate.y + point.y) / 2;
@@ -321,6 +1079,173 @@ impl HandDetector {
     }
 }
 
+/// Pixel `idx` of RGBA bytes
+fn rgba_pixel(data: &[u8]) -> impl Fn(usize) -> Rgb + '_ {
+    move |idx| {
+        let offset = idx * 4;
+        Rgb::new(data[offset], data[offset + 1], data[offset + 2])
+    }
+}
+
+/// Pixel `idx` of BGR bytes, OpenCV's layout
+fn bgr_pixel(data: &[u8]) -> impl Fn(usize) -> Rgb + '_ {
+    move |idx| {
+        let offset = idx * 3;
+        Rgb::new(data[offset + 2], data[offset + 1], data[offset])
+    }
+}
+
+/// Build a row-major skin mask, reading the color of pixel `idx` via `pixel`
+fn skin_mask(
+    width: usize,
+    height: usize,
+    exposure: &ExposureState,
+    pixel: impl Fn(usize) -> Rgb,
+) -> Vec<bool> {
+    (0..width * height)
+        .map(|idx| pixel(idx).is_skin_color_within(exposure.v_min, exposure.v_max))
+        .collect()
+}
+
+/// Split a mask into 4-connected blobs of pixel coordinates
+fn find_blobs(width: usize, height: usize, mask: &[bool]) -> Vec<Vec<Point>> {
+    let mut visited = vec![false; width * height];
+    let mut blobs = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..width * height {
+        if !mask[start] || visited[start] {
+            continue;
+        }
+
+        let mut blob = Vec::new();
+        visited[start] = true;
+        stack.push(start);
+
+        while let Some(idx) = stack.pop() {
+            let (x, y) = (idx % width, idx / width);
+            blob.push(Point::new(x, y));
+
+            let mut visit = |n: usize| {
+                if mask[n] && !visited[n] {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            };
+
+            if x > 0 {
+                visit(idx - 1);
+            }
+            if x + 1 < width {
+                visit(idx + 1);
+            }
+            if y > 0 {
+                visit(idx - width);
+            }
+            if y + 1 < height {
+                visit(idx + width);
+            }
+        }
+
+        blobs.push(blob);
+    }
+
+    blobs
+}
+
+fn blob_shape(blob: &[Point], frame_area: f32) -> BlobShape {
+    let min_x = blob.iter().map(|p| p.x).min().unwrap_or(0);
+    let max_x = blob.iter().map(|p| p.x).max().unwrap_or(0);
+    let min_y = blob.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_y = blob.iter().map(|p| p.y).max().unwrap_or(0);
+
+    let w = (max_x - min_x + 1) as f32;
+    let h = (max_y - min_y + 1) as f32;
+
+    // The hull of a pixel set only depends on the outer corners of each row's extremes
+    let rows = max_y - min_y + 1;
+    let mut row_extents = vec![(usize::MAX, 0usize); rows];
+    for p in blob {
+        let extent = &mut row_extents[p.y - min_y];
+        extent.0 = extent.0.min(p.x);
+        extent.1 = extent.1.max(p.x);
+    }
+
+    let mut corners = Vec::with_capacity(rows * 4);
+    for (row, &(left, right)) in row_extents.iter().enumerate() {
+        if left == usize::MAX {
+            continue;
+        }
+        let y = (min_y + row) as f32;
+        let (left, right) = (left as f32, right as f32 + 1.0);
+        corners.extend_from_slice(&[(left, y), (right, y), (left, y + 1.0), (right, y + 1.0)]);
+    }
+
+    let hull_area = convex_hull_area(&mut corners);
+    let pixel_area = blob.len() as f32;
+    let solidity = if hull_area > 0.0 {
+        (pixel_area / hull_area).min(1.0)
+    } else {
+        1.0
+    };
+
+    BlobShape {
+        pixel_count: blob.len(),
+        min: Point::new(min_x, min_y),
+        max: Point::new(max_x, max_y),
+        aspect_ratio: w.min(h) / w.max(h),
+        solidity,
+        area_fraction: pixel_area / frame_area,
+    }
+}
+
+/// Area of the convex hull of `points` (Andrew's monotone chain + shoelace)
+fn convex_hull_area(points: &mut [(f32, f32)]) -> f32 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    points.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(core::cmp::Ordering::Equal)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(core::cmp::Ordering::Equal))
+    });
+
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(points.len() * 2);
+
+    // Lower hull
+    for &p in points.iter() {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+
+    // Upper hull
+    let lower_len = hull.len() + 1;
+    for &p in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    hull.pop();
+
+    let mut area = 0.0;
+    for i in 0..hull.len() {
+        let (x1, y1) = hull[i];
+        let (x2, y2) = hull[(i + 1) % hull.len()];
+        area += x1 * y2 - x2 * y1;
+    }
+
+    area.abs() / 2.0
+}
+
 impl Default for HandDetector {
     fn default() -> Self {
         Self::new()
@@ -441,4 +1366,456 @@ mod tests {
             "Thumb should be to the left of index finger"
         );
     }
+
+    /// Paint a skin-colored rectangle into an RGBA buffer
+    fn fill_skin(
+        data: &mut [u8],
+        width: usize,
+        xs: core::ops::Range<usize>,
+        ys: core::ops::Range<usize>,
+    ) {
+        for y in ys {
+            for x in xs.clone() {
+                let idx = (y * width + x) * 4;
+                data[idx] = 180;
+                data[idx + 1] = 150;
+                data[idx + 2] = 120;
+                data[idx + 3] = 255;
+            }
+        }
+    }
+
+    fn white_image(width: usize, height: usize) -> Vec<u8> {
+        vec![255u8; width * height * 4]
+    }
+
+    #[test]
+    fn test_elongated_blob_rejected_by_aspect_ratio() {
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+
+        // Forearm-like strip across the frame
+        let mut data = white_image(200, 200);
+        fill_skin(&mut data, 200, 0..200, 90..110);
+
+        let mut debug = DetectionDebug::default();
+        let result = detector.process_rgba_image_with_debug(200, 200, &data, &mut debug);
+
+        assert!(result.is_none());
+        assert_eq!(debug.rejected.len(), 1);
+        assert_eq!(debug.rejected[0].reason, BlobRejection::AspectRatio);
+    }
+
+    #[test]
+    fn test_solid_blob_rejected_by_solidity() {
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+
+        // Face-like solid block with no finger gaps
+        let mut data = white_image(200, 200);
+        fill_skin(&mut data, 200, 50..130, 40..140);
+
+        let mut debug = DetectionDebug::default();
+        let result = detector.process_rgba_image_with_debug(200, 200, &data, &mut debug);
+
+        assert!(result.is_none());
+        assert_eq!(debug.rejected.len(), 1);
+        assert_eq!(debug.rejected[0].reason, BlobRejection::Solidity);
+        assert!(debug.rejected[0].shape.solidity > 0.99);
+    }
+
+    #[test]
+    fn test_hand_blob_preferred_over_rejected_blobs() {
+        let detector = HandDetector::new().with_min_skin_pixels(300);
+
+        let mut data = white_image(300, 200);
+
+        // Face-like solid block on the left
+        fill_skin(&mut data, 300, 10..90, 20..120);
+
+        // Hand on the right: palm plus two fingers
+        fill_skin(&mut data, 300, 180..260, 80..180);
+        fill_skin(&mut data, 300, 190..200, 40..80);
+        fill_skin(&mut data, 300, 240..250, 40..80);
+
+        let mut debug = DetectionDebug::default();
+        let landmarks = detector
+            .process_rgba_image_with_debug(300, 200, &data, &mut debug)
+            .expect("hand blob should pass the shape filters");
+
+        assert_eq!(debug.rejected.len(), 1);
+        assert!(landmarks.thumb_tip.x >= 180.0);
+        assert!(landmarks.index_tip.x >= 180.0);
+    }
+
+    #[test]
+    fn test_shape_filters_are_tunable() {
+        let detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_min_aspect_ratio(0.0)
+            .with_solidity_range(0.0, 1.0)
+            .with_area_fraction_range(0.0, 1.0);
+
+        let mut data = white_image(200, 200);
+        fill_skin(&mut data, 200, 0..200, 90..110);
+
+        let mut debug = DetectionDebug::default();
+        detector.process_rgba_image_with_debug(200, 200, &data, &mut debug);
+
+        assert!(debug.rejected.is_empty());
+    }
+
+    /// Palm with two raised fingers, in `skin` on `background`
+    fn hand_image(background: [u8; 3], skin: [u8; 3]) -> Vec<u8> {
+        let mut data = vec![255u8; 200 * 200 * 4];
+        for (idx, pixel) in data.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (idx % 200, idx / 200);
+            let hand = (60..140).contains(&x) && (80..180).contains(&y)
+                || ((70..80).contains(&x) || (120..130).contains(&x)) && (40..80).contains(&y);
+            pixel[..3].copy_from_slice(if hand { &skin } else { &background });
+        }
+        data
+    }
+
+    #[test]
+    fn test_skin_range_follows_exposure() {
+        // A dim room: the hand is darker than the fixed range allows
+        let dim = hand_image([20, 22, 25], [51, 42, 34]);
+        let mut fixed = HandDetector::new().with_min_skin_pixels(500);
+        assert!(fixed.track_rgba_image(200, 200, &dim).is_none());
+        assert_eq!(fixed.exposure(), ExposureState::neutral());
+
+        let mut detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_exposure_adaptation(true);
+        let mut debug = DetectionDebug::default();
+        assert!(detector
+            .track_rgba_image_with_debug(200, 200, &dim, &mut debug)
+            .is_some());
+        assert!(debug.exposure.shift() < 0.0);
+        assert_eq!(debug.exposure, detector.exposure());
+        // Single frames keep the fixed range
+        assert!(detector.process_rgba_image(200, 200, &dim).is_none());
+
+        // The lights come on: the range moves up gradually, not at once
+        let bright = hand_image([200, 205, 210], [180, 150, 120]);
+        detector.track_rgba_image(200, 200, &bright);
+        let exposure = detector.exposure();
+        assert!(exposure.frame_brightness > 70.0);
+        assert!(exposure.brightness < 30.0);
+
+        detector.reset_exposure();
+        detector.track_rgba_image(200, 200, &bright);
+        assert_eq!(detector.exposure().brightness, exposure.frame_brightness);
+    }
+
+    #[test]
+    fn test_region_limits_the_search() {
+        let data = hand_image([255, 255, 255], [180, 150, 120]);
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+        let full = detector.process_rgba_image(200, 200, &data).unwrap();
+
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+        let around = detector
+            .process_rgba_region(200, 200, &data, Roi::new(30, 20, 150, 500))
+            .unwrap();
+        assert_eq!(around, full);
+
+        let mut debug = DetectionDebug::default();
+        let beside = Roi::new(150, 0, 50, 200);
+        assert!(detector
+            .process_rgba_region_with_debug(200, 200, &data, beside, &mut debug)
+            .is_none());
+        assert_eq!(
+            Roi::new(150, 190, 100, 100).clamp(200, 200),
+            Roi::new(150, 190, 50, 10)
+        );
+    }
+
+    #[test]
+    fn test_downscale_finds_the_same_hand() {
+        let data = hand_image([255, 255, 255], [180, 150, 120]);
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+        let full = detector.process_rgba_image(200, 200, &data).unwrap();
+
+        let detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_downscale(4);
+        let mut debug = DetectionDebug::default();
+        let coarse = detector
+            .process_rgba_image_with_debug(200, 200, &data, &mut debug)
+            .unwrap();
+        for (a, b) in [
+            (full.thumb_tip, coarse.thumb_tip),
+            (full.index_tip, coarse.index_tip),
+        ] {
+            assert!((a.x - b.x).abs() <= 4.0 && (a.y - b.y).abs() <= 4.0);
+        }
+        assert!(debug.rejected.is_empty());
+    }
+
+    #[test]
+    fn test_working_width_handles_any_resolution() {
+        let data = hand_image([255, 255, 255], [180, 150, 120]);
+        let detector = HandDetector::new().with_min_skin_pixels(500);
+        let small = detector.process_rgba_image(200, 200, &data).unwrap();
+        // Narrower frames than the working width are taken as they are
+        let detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_working_width(Some(320));
+        assert_eq!(
+            detector.process_rgba_image(200, 200, &data),
+            Some(small.clone())
+        );
+
+        // The same scene at four times the resolution, with the thresholds
+        // still meant for 200 pixels across
+        let large: Vec<u8> = (0..800 * 800)
+            .flat_map(|idx| {
+                let (x, y) = (idx % 800 / 4, idx / 800 / 4);
+                data[(y * 200 + x) * 4..][..4].to_vec()
+            })
+            .collect();
+        let detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_working_width(Some(200));
+        let hand = detector.process_rgba_image(800, 800, &large).unwrap();
+        let (small, hand) = (
+            small.normalized(200.0, 200.0),
+            hand.normalized(800.0, 800.0),
+        );
+        for (a, b) in [
+            (small.thumb_tip, hand.thumb_tip),
+            (small.index_tip, hand.index_tip),
+        ] {
+            assert!(a.distance_to(&b) < 0.02, "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_background_subtraction_ignores_skin_toned_scenery() {
+        // A wooden door behind the left half of the hand
+        let wood = [210, 140, 90];
+        let door = |mut data: Vec<u8>| {
+            for (idx, pixel) in data.chunks_exact_mut(4).enumerate() {
+                if idx % 200 < 100 && pixel[..3] == [255, 255, 255] {
+                    pixel[..3].copy_from_slice(&wood);
+                }
+            }
+            data
+        };
+        let empty = door(white_image(200, 200));
+        let scene = door(hand_image([255, 255, 255], [180, 150, 120]));
+        let expected = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .process_rgba_image(200, 200, &hand_image([255, 255, 255], [180, 150, 120]))
+            .unwrap();
+
+        // The door and the hand make one blob, whose top is the door's
+        let plain = HandDetector::new().with_min_skin_pixels(500);
+        assert_ne!(
+            plain.process_rgba_image(200, 200, &scene),
+            Some(expected.clone())
+        );
+
+        let mut detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_background_subtraction(true)
+            .with_artifacts(true);
+        assert_eq!(detector.track_rgba_image(200, 200, &empty), None);
+        assert_eq!(detector.track_rgba_image(200, 200, &scene), Some(expected));
+        let artifacts = detector.artifacts().unwrap();
+        assert!(artifacts.motion_mask[100 * 200 + 100]);
+        assert!(!artifacts.motion_mask[190 * 200 + 20]);
+        assert!(artifacts.skin_mask[190 * 200 + 20]);
+
+        // Recapturing with the hand in view makes it part of the scene
+        detector.reset_background();
+        assert_eq!(detector.track_rgba_image(200, 200, &scene), None);
+        assert_eq!(detector.track_rgba_image(200, 200, &scene), None);
+    }
+
+    #[test]
+    fn test_roi_tracking_follows_the_hand() {
+        let data = hand_image([255, 255, 255], [180, 150, 120]);
+        let mut detector = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .with_roi_tracking(true)
+            .with_tracking_margin(5);
+        let first = detector.track_rgba_image(200, 200, &data).unwrap();
+        // The hand spans 60..140 x 40..180
+        assert_eq!(detector.tracked_region(), Some(Roi::new(55, 35, 90, 150)));
+
+        let second = detector.track_rgba_image(200, 200, &data).unwrap();
+        assert_eq!(second, first);
+
+        // Skin outside the region is only looked at once the hand is gone
+        let mut moved = white_image(200, 200);
+        fill_skin(&mut moved, 200, 0..50, 0..50);
+        assert!(detector.track_rgba_image(200, 200, &moved).is_none());
+        assert_eq!(detector.tracked_region(), None);
+
+        detector.track_rgba_image(200, 200, &data).unwrap();
+        detector.set_roi_tracking(false);
+        assert_eq!(detector.tracked_region(), None);
+    }
+
+    #[test]
+    fn test_artifacts_record_what_the_detector_saw() {
+        let mut data = hand_image([255, 255, 255], [180, 150, 120]);
+        // A forearm-like strip the shape filters reject
+        fill_skin(&mut data, 200, 0..200, 190..200);
+        let mut detector = HandDetector::new().with_min_skin_pixels(500);
+        let hand = detector.track_rgba_image(200, 200, &data);
+        assert!(detector.artifacts().is_none());
+
+        detector.set_artifacts(true);
+        let roi = Roi::new(0, 30, 200, 170);
+        assert_eq!(detector.track_rgba_region(200, 200, &data, roi), hand);
+        let artifacts = detector.artifacts().unwrap();
+        assert_eq!(artifacts.hand, hand);
+        assert!(artifacts.skin_mask[100 * 200 + 100]);
+        assert!(!artifacts.skin_mask[10 * 200 + 100]);
+
+        // The hand is chosen, the strip rejected, both in frame coordinates
+        assert_eq!(artifacts.candidates.len(), 2);
+        let chosen = artifacts.candidates[artifacts.chosen.unwrap()];
+        assert_eq!(chosen.rejection, None);
+        assert_eq!(chosen.shape.min, Point::new(60, 40));
+        assert!(artifacts
+            .candidates
+            .iter()
+            .any(|c| c.rejection == Some(BlobRejection::AspectRatio)));
+
+        let composite = artifacts.render(ArtifactLayer::Composite);
+        assert_eq!(composite.len(), 200 * 200 * 4);
+        let at = |x: usize, y: usize| &composite[(y * 200 + x) * 4..(y * 200 + x) * 4 + 4];
+        assert_eq!(at(60, 100), [0, 230, 60, 255]);
+        assert_eq!(at(100, 100), [110, 110, 110, 255]);
+        assert_eq!(at(5, 5), [0, 0, 0, 255]);
+        let candidates = artifacts.render(ArtifactLayer::Candidates);
+        assert_eq!(candidates[(5 * 200 + 5) * 4 + 3], 0);
+
+        detector.set_artifacts(false);
+        assert!(detector.artifacts().is_none());
+    }
+
+    /// Stands in for MediaPipe: reports a fixed hand, or none
+    struct Scripted {
+        hand: Option<HandLandmarks>,
+        calls: usize,
+    }
+
+    impl hybrid::HandDetection for Scripted {}
+
+    impl hybrid::HandDetectorBackend for Scripted {
+        fn detect(&mut self, _frame: &hybrid::Frame<'_>) -> Option<HandLandmarks> {
+            self.calls += 1;
+            self.hand.clone()
+        }
+    }
+
+    #[test]
+    fn test_hybrid_tracks_between_proposals() {
+        use hybrid::{Frame, HandDetectorBackend, HybridDetector, PixelFormat};
+
+        let data = hand_image([255, 255, 255], [180, 150, 120]);
+        let frame = Frame {
+            width: 200,
+            height: 200,
+            data: &data,
+            format: PixelFormat::Rgba,
+        };
+        let tracked = HandDetector::new()
+            .with_min_skin_pixels(500)
+            .detect(&frame)
+            .unwrap();
+        // The accurate detector puts the tips a little lower than the tracker
+        let accurate = HandLandmarks::new(
+            boid_shared::Position::new(tracked.thumb_tip.x, tracked.thumb_tip.y + 4.0),
+            boid_shared::Position::new(tracked.index_tip.x + 1.0, tracked.index_tip.y + 4.0),
+        );
+
+        let proposer = Scripted {
+            hand: Some(accurate.clone()),
+            calls: 0,
+        };
+        let tracker = HandDetector::new().with_min_skin_pixels(500);
+        let mut hybrid = HybridDetector::new(proposer, tracker).with_proposal_interval(5);
+        for _ in 0..10 {
+            assert_eq!(hybrid.detect(&frame), Some(accurate.clone()));
+        }
+        assert_eq!(hybrid.proposer_mut().calls, 2);
+        assert!(hybrid.region().is_some_and(|roi| roi.width < 200));
+
+        // Once the proposer loses the hand, so does the tracker
+        hybrid.proposer_mut().hand = None;
+        for _ in 0..5 {
+            assert_eq!(hybrid.detect(&frame), None);
+        }
+        assert_eq!(hybrid.region(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_watchdog_abandons_a_hung_backend() {
+        use hybrid::{Frame, HandDetection, HandDetectorBackend, PixelFormat};
+        use std::sync::atomic::{AtomicU8, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use watchdog::{DetectError, Watchdog};
+
+        const ANSWER: u8 = 0;
+        const STALL: u8 = 1;
+        const PANIC: u8 = 2;
+
+        /// Stands in for a graph that can wedge or crash
+        struct Flaky(Arc<AtomicU8>);
+
+        impl HandDetection for Flaky {}
+
+        impl HandDetectorBackend for Flaky {
+            fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+                match self.0.load(Ordering::SeqCst) {
+                    STALL => std::thread::sleep(Duration::from_millis(300)),
+                    PANIC => panic!("graph crashed"),
+                    _ => {}
+                }
+                let tip = boid_shared::Position::new(frame.width as f32, frame.data[0] as f32);
+                Some(HandLandmarks::new(tip, tip))
+            }
+        }
+
+        let mode = Arc::new(AtomicU8::new(ANSWER));
+        let shared = Arc::clone(&mode);
+        let mut watchdog = Watchdog::new(move || Flaky(Arc::clone(&shared)))
+            .with_timeout(Duration::from_millis(50));
+        let data = [7u8; 16];
+        let frame = Frame {
+            width: 2,
+            height: 2,
+            data: &data,
+            format: PixelFormat::Rgba,
+        };
+        let hand = watchdog.try_detect(&frame).unwrap().unwrap();
+        assert_eq!((hand.thumb_tip.x, hand.thumb_tip.y), (2.0, 7.0));
+
+        mode.store(STALL, Ordering::SeqCst);
+        assert_eq!(
+            watchdog.try_detect(&frame),
+            Err(DetectError::TimedOut(Duration::from_millis(50)))
+        );
+        // The next frame goes to a fresh backend
+        mode.store(ANSWER, Ordering::SeqCst);
+        assert!(watchdog.detect(&frame).is_some());
+        assert_eq!(watchdog.builds(), 2);
+
+        mode.store(PANIC, Ordering::SeqCst);
+        assert_eq!(watchdog.try_detect(&frame), Err(DetectError::Crashed));
+        mode.store(ANSWER, Ordering::SeqCst);
+        assert!(watchdog.try_detect(&frame).unwrap().is_some());
+        assert_eq!(watchdog.builds(), 3);
+
+        watchdog.reset();
+        assert!(watchdog.try_detect(&frame).unwrap().is_some());
+        assert_eq!(watchdog.builds(), 4);
+    }
 }
@@ -0,0 +1,195 @@
+//! Timeouts for detection backends that can hang, such as a MediaPipe graph
+//! behind FFI
+//!
+//! [`Watchdog`] builds the backend on a thread of its own and hands it one
+//! frame at a time. A frame that takes longer than the timeout comes back
+//! as [`DetectError::TimedOut`] and the caller moves on. Threads cannot be
+//! killed safely, so a wedged backend is abandoned, not stopped: its thread
+//! exits once the call returns, if it ever does. The next detection builds
+//! a fresh backend.
+
+use crate::hybrid::{Frame, HandDetection, HandDetectorBackend, PixelFormat};
+use crate::Roi;
+use boid_shared::HandLandmarks;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::vec::Vec;
+
+/// Default time a backend gets per frame; a stalled graph is noticed
+/// within a few frames at 30fps
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Why a guarded detection gave no answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectError {
+    /// The backend did not answer within the timeout and was abandoned
+    TimedOut(Duration),
+    /// The backend panicked, or failed to start
+    Crashed,
+}
+
+impl fmt::Display for DetectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TimedOut(timeout) => {
+                write!(f, "hand detection took longer than {:?}", timeout)
+            }
+            Self::Crashed => write!(f, "hand detection backend crashed"),
+        }
+    }
+}
+
+impl std::error::Error for DetectError {}
+
+/// A frame copied for the backend thread; the pixel buffer travels back
+/// with the answer and is reused for the next frame
+struct Job {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+    format: PixelFormat,
+    roi: Option<Roi>,
+}
+
+struct Worker {
+    jobs: SyncSender<Job>,
+    answers: Receiver<(Vec<u8>, Option<HandLandmarks>)>,
+}
+
+type Factory<D> = Arc<dyn Fn() -> D + Send + Sync>;
+
+/// Runs a [`HandDetection`] backend on a dedicated thread with a timeout
+pub struct Watchdog<D> {
+    make: Factory<D>,
+    timeout: Duration,
+    worker: Option<Worker>,
+    spare: Vec<u8>,
+    builds: u32,
+}
+
+impl<D: HandDetection + 'static> Watchdog<D> {
+    /// Guard backends built by `make`. The backend is built on its worker
+    /// thread, so it need not be `Send`, and again after every timeout,
+    /// crash or `reset`.
+    pub fn new(make: impl Fn() -> D + Send + Sync + 'static) -> Self {
+        Self {
+            make: Arc::new(make),
+            timeout: DEFAULT_TIMEOUT,
+            worker: None,
+            spare: Vec::new(),
+            builds: 0,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Number of backends started so far; more than one means earlier
+    /// ones timed out, crashed or were reset
+    pub fn builds(&self) -> u32 {
+        self.builds
+    }
+
+    /// Landmarks of the hand in `frame`, or why there is no answer
+    pub fn try_detect(&mut self, frame: &Frame<'_>) -> Result<Option<HandLandmarks>, DetectError> {
+        self.run(frame, None)
+    }
+
+    /// Like [`try_detect`](Self::try_detect), searching inside `roi` only
+    pub fn try_detect_in(
+        &mut self,
+        frame: &Frame<'_>,
+        roi: Roi,
+    ) -> Result<Option<HandLandmarks>, DetectError> {
+        self.run(frame, Some(roi))
+    }
+
+    /// Drop the backend; the next detection builds a new one. Use it to
+    /// recover a backend that answers but has gone bad.
+    pub fn reset(&mut self) {
+        self.worker = None;
+    }
+
+    fn run(
+        &mut self,
+        frame: &Frame<'_>,
+        roi: Option<Roi>,
+    ) -> Result<Option<HandLandmarks>, DetectError> {
+        let timeout = self.timeout;
+        let worker = match self.worker.take() {
+            Some(worker) => worker,
+            None => self.spawn()?,
+        };
+
+        let mut data = core::mem::take(&mut self.spare);
+        data.clear();
+        data.extend_from_slice(frame.data);
+        let job = Job {
+            width: frame.width,
+            height: frame.height,
+            data,
+            format: frame.format,
+            roi,
+        };
+        worker.jobs.send(job).map_err(|_| DetectError::Crashed)?;
+
+        match worker.answers.recv_timeout(timeout) {
+            Ok((data, landmarks)) => {
+                self.spare = data;
+                self.worker = Some(worker);
+                Ok(landmarks)
+            }
+            // Dropping `worker` abandons the backend
+            Err(RecvTimeoutError::Timeout) => Err(DetectError::TimedOut(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(DetectError::Crashed),
+        }
+    }
+
+    fn spawn(&mut self) -> Result<Worker, DetectError> {
+        let (jobs, job_queue) = mpsc::sync_channel::<Job>(1);
+        let (answer, answers) = mpsc::sync_channel(1);
+        let make = Arc::clone(&self.make);
+        thread::Builder::new()
+            .name("hand-detection".into())
+            .spawn(move || {
+                let mut backend = make();
+                // Ends when the watchdog drops the worker
+                for job in job_queue {
+                    let frame = Frame {
+                        width: job.width,
+                        height: job.height,
+                        data: &job.data,
+                        format: job.format,
+                    };
+                    let landmarks = match job.roi {
+                        Some(roi) => backend.detect_in(&frame, roi),
+                        None => backend.detect(&frame),
+                    };
+                    if answer.send((job.data, landmarks)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|_| DetectError::Crashed)?;
+        self.builds += 1;
+        Ok(Worker { jobs, answers })
+    }
+}
+
+impl<D: HandDetection + 'static> HandDetectorBackend for Watchdog<D> {
+    /// No hand when the backend times out or crashes
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        self.try_detect(frame).ok().flatten()
+    }
+}
+
+impl<D: HandDetection + 'static> HandDetection for Watchdog<D> {
+    fn detect_in(&mut self, frame: &Frame<'_>, roi: Roi) -> Option<HandLandmarks> {
+        self.try_detect_in(frame, roi).ok().flatten()
+    }
+}
@@ -0,0 +1,145 @@
+//! Mouse and keyboard handling, matching the web demo
+//!
+//! Holding the left button steers the flock (attract, repel or nothing,
+//! cycled with `M`), a click that does not drag spawns a boid and a right
+//! click removes the nearest one. The web demo's control panel maps to keys.
+
+use boid_core::{PointerMode, TargetMode, Vector2D};
+use winit::event::VirtualKeyCode;
+
+/// A press that moves less than this before release is a click
+const CLICK_SLOP: f32 = 4.0;
+/// How far from the cursor a right click still removes a boid
+pub const REMOVE_DISTANCE: f32 = 30.0;
+/// Max speed change per `Up`/`Down` press
+const SPEED_STEP: f32 = 0.5;
+
+/// What a key press asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    ToggleWander,
+    CyclePointerMode,
+    /// Split into this many sub-flocks
+    SubFlocks(u8),
+    /// Change the max speed by this much
+    AdjustSpeed(f32),
+    TogglePause,
+    Quit,
+}
+
+impl Command {
+    pub fn from_key(key: VirtualKeyCode) -> Option<Self> {
+        use VirtualKeyCode::*;
+        Some(match key {
+            W => Self::ToggleWander,
+            M => Self::CyclePointerMode,
+            Key1 => Self::SubFlocks(1),
+            Key2 => Self::SubFlocks(2),
+            Key3 => Self::SubFlocks(3),
+            Key4 => Self::SubFlocks(4),
+            Up => Self::AdjustSpeed(SPEED_STEP),
+            Down => Self::AdjustSpeed(-SPEED_STEP),
+            Space => Self::TogglePause,
+            Escape | Q => Self::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// Pointer state, in simulation coordinates
+#[derive(Debug, Clone, Default)]
+pub struct Pointer {
+    pub position: Option<Vector2D>,
+    pub mode: PointerMode,
+    pressed_at: Option<Vector2D>,
+    /// Set once the pointer strays from where it was pressed
+    dragged: bool,
+}
+
+impl Pointer {
+    pub fn moved(&mut self, position: Vector2D) {
+        self.position = Some(position);
+        if let Some(pressed_at) = self.pressed_at {
+            self.dragged |= pressed_at.distance(&position) >= CLICK_SLOP;
+        }
+    }
+
+    /// The pointer left the window; like the web demo, this ends a press
+    pub fn left(&mut self) {
+        self.position = None;
+        self.pressed_at = None;
+    }
+
+    pub fn pressed(&mut self) {
+        self.pressed_at = self.position;
+        self.dragged = false;
+    }
+
+    /// Returns where to spawn a boid if the press was a click
+    pub fn released(&mut self) -> Option<Vector2D> {
+        let click = self.pressed_at.take().filter(|_| !self.dragged);
+        click.and(self.position)
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    /// Target for this tick and the target mode it needs, if the pointer is
+    /// steering the flock
+    pub fn steering(&self) -> Option<(Vector2D, TargetMode)> {
+        if !self.is_pressed() {
+            return None;
+        }
+        let position = self.position?;
+        match self.mode {
+            PointerMode::Attract => Some((position, TargetMode::Seek)),
+            PointerMode::Repel => Some((position, TargetMode::Flee)),
+            PointerMode::None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_spawns_but_drag_steers() {
+        let mut pointer = Pointer::default();
+        pointer.moved(Vector2D::new(100.0, 100.0));
+        pointer.pressed();
+        assert_eq!(
+            pointer.steering(),
+            Some((Vector2D::new(100.0, 100.0), TargetMode::Seek))
+        );
+        pointer.moved(Vector2D::new(101.0, 101.0));
+        assert_eq!(pointer.released(), Some(Vector2D::new(101.0, 101.0)));
+        assert_eq!(pointer.steering(), None);
+
+        pointer.pressed();
+        pointer.moved(Vector2D::new(150.0, 101.0));
+        pointer.moved(Vector2D::new(102.0, 101.0));
+        assert_eq!(pointer.released(), None);
+    }
+
+    #[test]
+    fn test_pointer_modes() {
+        let mut pointer = Pointer::default();
+        pointer.moved(Vector2D::new(10.0, 10.0));
+        pointer.pressed();
+
+        pointer.mode = pointer.mode.next();
+        assert_eq!(pointer.steering().unwrap().1, TargetMode::Flee);
+        pointer.mode = pointer.mode.next();
+        assert_eq!(pointer.steering(), None);
+        assert_eq!(pointer.mode.next(), PointerMode::Attract);
+
+        pointer.left();
+        assert!(!pointer.is_pressed());
+        assert_eq!(
+            Command::from_key(VirtualKeyCode::Key3),
+            Some(Command::SubFlocks(3))
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Webcam hand tracking as a control source
+//!
+//! Runs boid-client's OpenCV hand tracker on a background thread. While the
+//! thumb and index finger are pinched, the point between them is the
+//! flock's target, as in the web demo.
+
+use anyhow::{Context, Result};
+use boid_client::hand_tracker::HandTracker;
+use boid_core::Vector2D;
+use boid_shared::PinchStateMachine;
+use opencv::{
+    core::Mat,
+    prelude::*,
+    videoio::{VideoCapture, VideoCaptureAPIs},
+};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Latest pinch position from a camera, in simulation coordinates
+pub struct HandSource {
+    target: Arc<Mutex<Option<Vector2D>>>,
+}
+
+impl HandSource {
+    /// Track hands on camera device `camera`, mapping its frames onto a
+    /// `width` x `height` simulation
+    pub fn spawn(camera: i32, width: f32, height: f32) -> Result<Self> {
+        let mut capture = VideoCapture::new(camera, VideoCaptureAPIs::CAP_ANY as i32)?;
+        if !capture.is_opened()? {
+            anyhow::bail!("Failed to open camera device {}", camera);
+        }
+        let mut tracker = HandTracker::new()?;
+        let target = Arc::new(Mutex::new(None));
+
+        let shared = target.clone();
+        thread::Builder::new()
+            .name("hand-tracker".to_string())
+            .spawn(move || {
+                let mut pinch = PinchStateMachine::default();
+                let mut frame = Mat::default();
+                loop {
+                    if let Err(e) = capture.read(&mut frame) {
+                        log::warn!("Camera read failed, hand tracking stopped: {}", e);
+                        break;
+                    }
+                    if frame.empty() {
+                        continue;
+                    }
+                    let hand = match tracker.process_frame(&frame) {
+                        Ok(hand) => hand,
+                        Err(e) => {
+                            log::debug!("Hand tracking failed on a frame: {}", e);
+                            None
+                        }
+                    };
+                    let pinched = pinch.update(hand.as_ref().map(|hand| hand.pinch_distance()));
+                    let (cols, rows) = (frame.cols() as f32, frame.rows() as f32);
+                    *shared.lock().unwrap() = hand.filter(|_| pinched).map(|hand| {
+                        let x = (hand.thumb_tip.x + hand.index_tip.x) / 2.0;
+                        let y = (hand.thumb_tip.y + hand.index_tip.y) / 2.0;
+                        // Mirrored, so moving the hand right moves the target right
+                        Vector2D::new((1.0 - x / cols) * width, y / rows * height)
+                    });
+                }
+            })
+            .context("Failed to start the hand tracking thread")?;
+
+        Ok(Self { target })
+    }
+
+    pub fn target(&self) -> Option<Vector2D> {
+        *self.target.lock().unwrap()
+    }
+}
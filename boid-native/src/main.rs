@@ -0,0 +1,634 @@
+mod controls;
+#[cfg(feature = "hand-tracking")]
+mod hands;
+
+use anyhow::{Context, Result};
+use boid_client::scene::{self, Scene, SceneWatcher};
+use boid_core::record::{self, Frame};
+use boid_core::{
+    ConfigModifiers, ConfigTransition, FlockStd, Marker, PerchConfig, StepInput, TimelineConfig,
+    Vector2D,
+};
+use boid_shared::colors::{ColorScheme, Glyph};
+use boid_shared::demo::{Palette, Theme};
+use clap::Parser;
+use pixels::{Pixels, SurfaceTexture};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyboardInput, MouseButton, WindowEvent},
+    event_loop::EventLoop,
+    window::WindowBuilder,
+};
+
+use controls::{Command, Pointer};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Desktop boid simulation", long_about = None)]
+struct Args {
+    /// Simulation width in pixels
+    #[arg(long, default_value_t = 800)]
+    width: u32,
+
+    /// Simulation height in pixels
+    #[arg(long, default_value_t = 600)]
+    height: u32,
+
+    /// Number of boids
+    #[arg(short, long, default_value_t = 100)]
+    boids: usize,
+
+    /// Seed for a reproducible flock
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Render this many frames without opening a window, then exit
+    #[arg(long)]
+    headless: Option<u32>,
+
+    /// With --headless, write the last frame to this file (PPM)
+    #[arg(long, requires = "headless")]
+    screenshot: Option<PathBuf>,
+
+    /// Boid colors: theme, okabe_ito or high_contrast. The last two are
+    /// color-blind safe and tell sub-flocks apart by shape too.
+    #[arg(long, default_value = "theme", value_parser = parse_color_scheme)]
+    color_scheme: ColorScheme,
+
+    /// Mark notable moments (targets, clusters splitting and merging, spawn
+    /// bursts) and write them to this CSV file on exit, for annotating
+    /// recordings
+    #[arg(long)]
+    timeline: Option<PathBuf>,
+
+    /// Let boids land on the bottom edge now and then and rest a few
+    /// seconds before taking off again
+    #[arg(long)]
+    perch: bool,
+
+    /// Apply settings from a scene file, and again every time it is saved
+    #[arg(long)]
+    scene: Option<PathBuf>,
+
+    /// Camera device to track hands on; a pinch steers the flock
+    #[cfg(feature = "hand-tracking")]
+    #[arg(long)]
+    camera: Option<i32>,
+}
+
+struct App {
+    flock: FlockStd,
+    pointer: Pointer,
+    palette: Palette,
+    /// Color per sub-flock while the flock is split
+    group_palettes: Vec<Palette>,
+    color_scheme: ColorScheme,
+    paused: bool,
+    /// Target of the last tick, drawn as a ring
+    target: Option<Vector2D>,
+    /// Scene file being watched for edits
+    scene: Option<SceneWatcher>,
+    /// Crossfade to the scene's settings, one step per tick
+    transition: Option<ConfigTransition>,
+    #[cfg(feature = "hand-tracking")]
+    hands: Option<hands::HandSource>,
+}
+
+impl App {
+    fn new(args: &Args) -> Result<Self> {
+        let (width, height) = (args.width as f32, args.height as f32);
+        let mut flock = match args.seed {
+            Some(seed) => FlockStd::new_with_seed(width, height, args.boids, seed),
+            None => FlockStd::new(width, height, args.boids),
+        };
+        if args.timeline.is_some() {
+            flock.set_timeline(Some(TimelineConfig::default()));
+        }
+        if args.perch {
+            flock.set_perching(Some(PerchConfig::default()));
+        }
+
+        #[cfg(feature = "hand-tracking")]
+        let hands = match args.camera {
+            Some(camera) => {
+                let source = hands::HandSource::spawn(camera, width, height)
+                    .context("Failed to start hand tracking")?;
+                log::info!("Tracking hands on camera {}", camera);
+                Some(source)
+            }
+            None => None,
+        };
+
+        let mut app = Self {
+            flock,
+            pointer: Pointer::default(),
+            palette: Theme::default().palette(),
+            group_palettes: Vec::new(),
+            color_scheme: args.color_scheme,
+            paused: false,
+            target: None,
+            scene: None,
+            transition: None,
+            #[cfg(feature = "hand-tracking")]
+            hands,
+        };
+
+        // A broken scene file is reported, not fatal: fixing and saving it
+        // applies it without restarting
+        if let Some(ref path) = args.scene {
+            match scene::load_scene(path) {
+                Ok(scene) => app.apply_scene(scene),
+                Err(e) => log::warn!("{}: {}", path.display(), e),
+            }
+            let watcher = SceneWatcher::new(path)
+                .with_context(|| format!("Failed to watch {}", path.display()))?;
+            log::info!("Watching {} for changes", path.display());
+            app.scene = Some(watcher);
+        }
+        Ok(app)
+    }
+
+    /// Crossfade to the scene's settings over its `duration_ticks`
+    fn apply_scene(&mut self, scene: Scene) {
+        let to = scene.preset.settings.apply_to(self.flock.config);
+        self.transition = Some(ConfigTransition::new(
+            self.flock.config,
+            to,
+            scene.preset.duration_ticks,
+        ));
+        log::info!(
+            "Scene applied over {} ticks: {:?}",
+            scene.preset.duration_ticks,
+            scene.preset.settings
+        );
+        if !scene.mappings.mappings().is_empty() {
+            log::warn!("Input mappings are only driven by boid-client; ignoring them");
+        }
+    }
+
+    fn poll_scene(&mut self) {
+        let Some(ref mut watcher) = self.scene else {
+            return;
+        };
+        match watcher.poll() {
+            Some(Ok(scene)) => {
+                log::info!("{} changed, applying", watcher.path().display());
+                self.apply_scene(scene);
+            }
+            Some(Err(e)) => log::warn!("{}: {}", watcher.path().display(), e),
+            None => {}
+        }
+    }
+
+    fn hand_target(&self) -> Option<Vector2D> {
+        #[cfg(feature = "hand-tracking")]
+        if let Some(ref hands) = self.hands {
+            return hands.target();
+        }
+        None
+    }
+
+    fn tick(&mut self) {
+        self.poll_scene();
+        if self.paused {
+            return;
+        }
+        if let Some(mut transition) = self.transition {
+            self.flock.config = transition.step();
+            self.transition = (!transition.is_finished()).then_some(transition);
+        }
+        // Hands always attract; the pointer mode applies otherwise
+        self.flock.modifiers = ConfigModifiers::IDENTITY;
+        self.target = match self.hand_target() {
+            Some(target) => Some(target),
+            None => self.pointer.steering().map(|(target, mode)| {
+                self.flock.modifiers.target_mode = Some(mode);
+                target
+            }),
+        };
+        self.flock
+            .step(StepInput::new().with_targets(self.target.as_slice()));
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        frame.clear(record::BACKGROUND);
+        for boid in &self.flock.boids {
+            let max_speed = self.flock.config_for_group(boid.group).max_speed;
+            let speed = boid.velocity.magnitude() / max_speed.max(f32::EPSILON);
+            if self.flock.perch_state(boid.id).is_perched() {
+                let color = match self.color_scheme.color(boid.group) {
+                    Some(color) => color,
+                    None => self
+                        .group_palettes
+                        .get(boid.group as usize)
+                        .unwrap_or(&self.palette)
+                        .rgb(0.0),
+                };
+                let glyph = self.color_scheme.glyph(boid.group, 0.0).perched();
+                draw_glyph(frame, boid.position, UPRIGHT, glyph, color);
+                continue;
+            }
+            if let Some(color) = self.color_scheme.color(boid.group) {
+                draw_glyph(
+                    frame,
+                    boid.position,
+                    boid.velocity,
+                    self.color_scheme.glyph(boid.group, speed),
+                    color,
+                );
+                continue;
+            }
+            let color = if boid.puppet {
+                record::PUPPET
+            } else {
+                let palette = self.group_palettes.get(boid.group as usize);
+                palette.unwrap_or(&self.palette).rgb(speed)
+            };
+            frame.boid(boid, color);
+        }
+        if let Some(target) = self.target {
+            frame.target(target);
+        }
+    }
+
+    /// Returns false when the app should quit
+    fn run_command(&mut self, command: Command) -> bool {
+        match command {
+            Command::ToggleWander => {
+                self.flock.config.wander_enabled = !self.flock.config.wander_enabled;
+                log::info!("Wander: {}", self.flock.config.wander_enabled);
+            }
+            Command::CyclePointerMode => {
+                self.pointer.mode = self.pointer.mode.next();
+                log::info!("Pointer: {:?}", self.pointer.mode);
+            }
+            Command::SubFlocks(count) => self.split_sub_flocks(count),
+            Command::AdjustSpeed(delta) => {
+                let config = &mut self.flock.config;
+                config.max_speed = (config.max_speed + delta).clamp(1.0, 10.0);
+                log::info!("Max speed: {:.1}", config.max_speed);
+            }
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Quit => return false,
+        }
+        true
+    }
+
+    /// Deal the boids round-robin into `count` sub-flocks, each with its own
+    /// hue; `1` merges them back
+    fn split_sub_flocks(&mut self, count: u8) {
+        let count = count.max(1);
+        for (i, boid) in self.flock.boids.iter_mut().enumerate() {
+            boid.group = (i % count as usize) as u8;
+        }
+        self.group_palettes = if count > 1 {
+            let span = self.palette.hue_span / count as f32;
+            (0..count)
+                .map(|group| Palette::new(group as f32 * 360.0 / count as f32, span))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        log::info!("Sub-flocks: {}", count);
+    }
+
+    fn spawn(&mut self, position: Vector2D) {
+        let angle = rand::random::<f32>() * std::f32::consts::TAU;
+        let velocity = Vector2D::new(angle.cos(), angle.sin());
+        self.flock.spawn(position, velocity);
+    }
+}
+
+/// Heading perched boids are drawn with
+const UPRIGHT: Vector2D = Vector2D { x: 0.0, y: -1.0 };
+
+/// `glyph`'s shape around `position`, turned to `heading`
+fn draw_glyph(
+    frame: &mut Frame,
+    position: Vector2D,
+    heading: Vector2D,
+    glyph: Glyph,
+    color: record::Rgb,
+) {
+    let heading = if heading.magnitude() > 0.0 {
+        heading.normalize()
+    } else {
+        Vector2D::new(1.0, 0.0)
+    };
+    let side = Vector2D::new(-heading.y, heading.x);
+    let size = record::BOID_SIZE * glyph.scale;
+    let outline = glyph.shape.corners();
+    let mut corners = [Vector2D::zero(); 8];
+    for (corner, &(x, y)) in corners.iter_mut().zip(outline) {
+        *corner = position + heading * (x * size) + side * (y * size);
+    }
+    frame.fill_polygon(&corners[..outline.len()], color);
+}
+
+fn parse_color_scheme(name: &str) -> Result<ColorScheme, String> {
+    ColorScheme::parse(name)
+        .ok_or_else(|| format!("expected theme, okabe_ito or high_contrast, got {}", name))
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let args = Args::parse();
+    if args.width == 0 || args.height == 0 {
+        anyhow::bail!("--width and --height must be positive");
+    }
+    let app = App::new(&args)?;
+
+    match args.headless {
+        Some(frames) => run_headless(app, &args, frames),
+        None => run_window(app, &args),
+    }
+}
+
+/// Simulate and render `frames` frames off screen, reporting the frame time
+fn run_headless(mut app: App, args: &Args, frames: u32) -> Result<()> {
+    let (width, height) = (args.width as usize, args.height as usize);
+    let mut buffer = vec![0; width * height * 4];
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        app.tick();
+        app.draw(&mut Frame::new(&mut buffer, width, height));
+    }
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+    log::info!(
+        "Rendered {} frames of {} boids in {:.1}ms ({:.3}ms per frame)",
+        frames,
+        app.flock.boids.len(),
+        elapsed_ms,
+        elapsed_ms / frames.max(1) as f64
+    );
+
+    if let Some(ref path) = args.screenshot {
+        write_ppm(path, &buffer, width, height)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        log::info!("Last frame written to {}", path.display());
+    }
+    if let Some(ref path) = args.timeline {
+        write_timeline(path, &app.flock)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Save an RGBA frame as a binary PPM, which needs no image library
+fn write_ppm(path: &Path, rgba: &[u8], width: usize, height: usize) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for pixel in rgba.chunks_exact(4) {
+        out.write_all(&pixel[..3])?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Save the flock's timeline as CSV, one marker per row: the tick, the
+/// marker's name, the target position and the cluster or boid count, each
+/// left empty where it does not apply
+fn write_timeline(path: &Path, flock: &FlockStd) -> Result<()> {
+    let Some(timeline) = flock.timeline() else {
+        return Ok(());
+    };
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "tick,event,x,y,count")?;
+    for entry in timeline.entries() {
+        let (position, count) = match entry.marker {
+            Marker::TargetAcquired { position } => (Some(position), None),
+            Marker::ClusterSplit { clusters } | Marker::ClusterMerge { clusters } => {
+                (None, Some(clusters))
+            }
+            Marker::SpawnBurst { count } | Marker::DespawnBurst { count } => (None, Some(count)),
+            Marker::TargetLost | Marker::PresetApplied { .. } => (None, None),
+        };
+        let (x, y) = position.map_or((String::new(), String::new()), |p| {
+            (format!("{:.1}", p.x), format!("{:.1}", p.y))
+        });
+        let count = count.map(|count| count.to_string()).unwrap_or_default();
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            entry.tick,
+            entry.marker.name(),
+            x,
+            y,
+            count
+        )?;
+    }
+    out.flush()?;
+    log::info!(
+        "{} timeline markers written to {}",
+        timeline.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn run_window(mut app: App, args: &Args) -> Result<()> {
+    let event_loop = EventLoop::new();
+    let size = LogicalSize::new(args.width as f64, args.height as f64);
+    let window = WindowBuilder::new()
+        .with_title("Boids")
+        .with_inner_size(size)
+        .with_min_inner_size(size)
+        .build(&event_loop)
+        .context("Failed to open a window")?;
+
+    // The frame keeps the simulation size and is scaled to the window
+    let mut pixels = {
+        let surface = window.inner_size();
+        let texture = SurfaceTexture::new(surface.width, surface.height, &window);
+        Pixels::new(args.width, args.height, texture)?
+    };
+    let (width, height) = (args.width as usize, args.height as usize);
+    let timeline = args.timeline.clone();
+
+    log::info!(
+        "Drag to steer, click to spawn, right-click to remove. \
+         W wander, M pointer mode, 1-4 sub-flocks, Up/Down speed, Space pause, Esc quit"
+    );
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::CloseRequested => control_flow.set_exit(),
+            WindowEvent::Resized(size) => {
+                if let Err(e) = pixels.resize_surface(size.width, size.height) {
+                    log::error!("Failed to resize the surface: {}", e);
+                    control_flow.set_exit();
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, y) = pixels
+                    .window_pos_to_pixel((position.x as f32, position.y as f32))
+                    .unwrap_or_else(|outside| pixels.clamp_pixel_pos(outside));
+                app.pointer.moved(Vector2D::new(x as f32, y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => app.pointer.left(),
+            WindowEvent::MouseInput { state, button, .. } => match (button, state) {
+                (MouseButton::Left, ElementState::Pressed) => app.pointer.pressed(),
+                (MouseButton::Left, ElementState::Released) => {
+                    if let Some(position) = app.pointer.released() {
+                        app.spawn(position);
+                    }
+                }
+                (MouseButton::Right, ElementState::Pressed) => {
+                    if let Some(position) = app.pointer.position {
+                        app.flock
+                            .remove_nearest(position, controls::REMOVE_DISTANCE);
+                    }
+                }
+                _ => {}
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(command) = Command::from_key(key) {
+                    if !app.run_command(command) {
+                        control_flow.set_exit();
+                    }
+                }
+            }
+            _ => {}
+        },
+        Event::MainEventsCleared => window.request_redraw(),
+        // Presenting waits for vsync, which paces the simulation
+        Event::RedrawRequested(_) => {
+            app.tick();
+            app.draw(&mut Frame::new(pixels.frame_mut(), width, height));
+            if let Err(e) = pixels.render() {
+                log::error!("Failed to render: {}", e);
+                control_flow.set_exit();
+            }
+        }
+        // Closing, Esc and failures all end here
+        Event::LoopDestroyed => {
+            if let Some(ref path) = timeline {
+                if let Err(e) = write_timeline(path, &app.flock) {
+                    log::error!("Failed to write {}: {}", path.display(), e);
+                }
+            }
+        }
+        _ => {}
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boid_core::{BoidConfig, TargetMode};
+    use boid_shared::colors::OKABE_ITO;
+
+    fn test_args() -> Args {
+        Args::parse_from(["boid-native", "--boids", "30", "--seed", "1"])
+    }
+
+    #[test]
+    fn test_pointer_steers_and_sub_flocks_split() {
+        let mut app = App::new(&test_args()).unwrap();
+        app.pointer.moved(Vector2D::new(400.0, 300.0));
+        app.pointer.pressed();
+        app.tick();
+        assert_eq!(app.target, Some(Vector2D::new(400.0, 300.0)));
+
+        assert!(app.run_command(Command::CyclePointerMode));
+        app.tick();
+        assert_eq!(app.flock.effective_config().target_mode, TargetMode::Flee);
+
+        app.run_command(Command::SubFlocks(3));
+        assert_eq!(app.flock.boids[4].group, 1);
+        assert_eq!(app.group_palettes.len(), 3);
+        assert!(!app.run_command(Command::Quit));
+        assert_eq!(app.flock.config.max_speed, BoidConfig::default().max_speed);
+    }
+
+    #[test]
+    fn test_color_scheme_paints_palette() {
+        let args = Args::parse_from(["boid-native", "--seed", "1", "--color-scheme", "okabe_ito"]);
+        let mut app = App::new(&args).unwrap();
+        app.run_command(Command::SubFlocks(2));
+        let mut pixels = vec![0; 800 * 600 * 4];
+        app.draw(&mut Frame::new(&mut pixels, 800, 600));
+        for color in &OKABE_ITO[..2] {
+            assert!(pixels.chunks_exact(4).any(|pixel| pixel[..3] == color[..]));
+        }
+        assert!(Args::try_parse_from(["boid-native", "--color-scheme", "rainbow"]).is_err());
+    }
+
+    #[test]
+    fn test_headless_screenshot() {
+        let path = std::env::temp_dir().join("boid-native-test.ppm");
+        let args = Args::parse_from([
+            "boid-native",
+            "--width",
+            "64",
+            "--height",
+            "48",
+            "--seed",
+            "3",
+            "--headless",
+            "5",
+            "--screenshot",
+            path.to_str().unwrap(),
+        ]);
+        run_headless(App::new(&args).unwrap(), &args, 5).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let header = b"P6\n64 48\n255\n";
+        assert!(bytes.starts_with(header));
+        assert_eq!(bytes.len(), header.len() + 64 * 48 * 3);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_headless_timeline() {
+        let path = std::env::temp_dir().join("boid-native-timeline-test.csv");
+        let args = Args::parse_from([
+            "boid-native",
+            "--seed",
+            "3",
+            "--headless",
+            "40",
+            "--timeline",
+            path.to_str().unwrap(),
+        ]);
+        let mut app = App::new(&args).unwrap();
+        app.tick();
+        app.pointer.moved(Vector2D::new(400.0, 300.0));
+        app.pointer.pressed();
+        app.tick();
+        for _ in 0..6 {
+            app.spawn(Vector2D::new(100.0, 100.0));
+        }
+        run_headless(app, &args, 40).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<_> = csv
+            .lines()
+            .filter(|row| !row.contains(",cluster_"))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "tick,event,x,y,count",
+                "1,target_acquired,400.0,300.0,",
+                "2,spawn_burst,,,6",
+            ]
+        );
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,300 @@
+//! Deciding which client steers the flock
+//!
+//! Several clients can post targets to the same ESP32: a phone, the desktop
+//! client, the on-device detector. Applied as they arrive, the flock jumps
+//! between their targets at every update. A [`TargetArbiter`] is told which
+//! client sent each target and decides, by the [`ArbitrationPolicy`], which
+//! targets the flock follows. Clients are named by a token they send or,
+//! without one, by their address.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Position;
+
+/// How long a controlling client keeps the flock after its last update
+pub const DEFAULT_LEASE_MS: u32 = 2000;
+/// Most clients whose targets are merged at once
+pub const MAX_MERGED_CLIENTS: usize = 4;
+
+/// Who gets the flock when several clients send targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArbitrationPolicy {
+    /// The client that set the target keeps the flock while it keeps
+    /// sending; another one can take over once it has been quiet for the
+    /// lease
+    #[default]
+    Lease,
+    /// Like `Lease`, but a client of higher priority takes over at once
+    Priority,
+    /// The flock follows every client that sent a target within the lease,
+    /// each boid the nearest one
+    Merge,
+}
+
+/// Arbitration settings, as served at `/api/arbitration`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbitrationConfig {
+    #[serde(default)]
+    pub policy: ArbitrationPolicy,
+    #[serde(default = "default_lease_ms")]
+    pub lease_ms: u32,
+    /// Priority per client id under `Priority`; unlisted clients have 0
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub priorities: BTreeMap<String, i32>,
+}
+
+fn default_lease_ms() -> u32 {
+    DEFAULT_LEASE_MS
+}
+
+impl Default for ArbitrationConfig {
+    fn default() -> Self {
+        Self {
+            policy: ArbitrationPolicy::default(),
+            lease_ms: DEFAULT_LEASE_MS,
+            priorities: BTreeMap::new(),
+        }
+    }
+}
+
+impl ArbitrationConfig {
+    pub fn priority(&self, client: &str) -> i32 {
+        self.priorities.get(client).copied().unwrap_or(0)
+    }
+}
+
+/// A target was refused because another client holds the flock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetHeld {
+    pub controller: String,
+}
+
+#[derive(Debug, Clone)]
+struct ClientTarget {
+    id: String,
+    position: Position,
+    updated_ms: u64,
+}
+
+/// Applies an [`ArbitrationConfig`] to the targets clients send
+///
+/// Times are milliseconds on any clock that does not go backwards.
+#[derive(Debug, Clone, Default)]
+pub struct TargetArbiter {
+    config: ArbitrationConfig,
+    /// Newest first; at most one unless merging
+    clients: Vec<ClientTarget>,
+}
+
+impl TargetArbiter {
+    pub fn new(config: ArbitrationConfig) -> Self {
+        Self {
+            config,
+            clients: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ArbitrationConfig {
+        &self.config
+    }
+
+    /// Switch policy; the newest target stays, and its client keeps the
+    /// flock
+    pub fn set_config(&mut self, config: ArbitrationConfig) {
+        if config.policy != ArbitrationPolicy::Merge {
+            self.clients.truncate(1);
+        }
+        self.config = config;
+    }
+
+    /// Whether targets are merged, so the flock follows all of
+    /// [`targets`](Self::targets)
+    pub fn is_merging(&self) -> bool {
+        self.config.policy == ArbitrationPolicy::Merge
+    }
+
+    /// Whether a target from `client` would be taken now
+    pub fn check(&self, client: &str, now_ms: u64) -> Result<(), TargetHeld> {
+        let refuse = |holder: &ClientTarget| {
+            Err(TargetHeld {
+                controller: holder.id.clone(),
+            })
+        };
+        if self.is_merging() {
+            let others: Vec<&ClientTarget> = self
+                .live(now_ms)
+                .filter(|other| other.id != client)
+                .collect();
+            return match others.first() {
+                Some(newest) if others.len() >= MAX_MERGED_CLIENTS => refuse(newest),
+                _ => Ok(()),
+            };
+        }
+        match self.clients.first() {
+            Some(holder) if holder.id != client => {
+                let expired =
+                    now_ms.saturating_sub(holder.updated_ms) >= self.config.lease_ms as u64;
+                let outranked = self.config.policy == ArbitrationPolicy::Priority
+                    && self.config.priority(client) > self.config.priority(&holder.id);
+                if expired || outranked {
+                    Ok(())
+                } else {
+                    refuse(holder)
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Take a target from `client`; `None` withdraws it. Returns whether
+    /// `client` took the flock over from another client, so a caller
+    /// smoothing the target can start afresh.
+    pub fn submit(
+        &mut self,
+        client: &str,
+        position: Option<Position>,
+        now_ms: u64,
+    ) -> Result<bool, TargetHeld> {
+        let merging = self.is_merging();
+        // Anyone may withdraw their own merged target
+        if position.is_some() || !merging {
+            self.check(client, now_ms)?;
+        }
+        let handover = !merging
+            && self
+                .clients
+                .first()
+                .is_some_and(|holder| holder.id != client);
+        if merging {
+            let lease = self.config.lease_ms as u64;
+            self.clients.retain(|other| {
+                other.id != client && now_ms.saturating_sub(other.updated_ms) < lease
+            });
+        } else {
+            self.clients.clear();
+        }
+        if let Some(position) = position {
+            self.clients.insert(
+                0,
+                ClientTarget {
+                    id: client.to_string(),
+                    position,
+                    updated_ms: now_ms,
+                },
+            );
+        }
+        Ok(handover)
+    }
+
+    /// Clients whose targets the flock follows, newest first. Unless
+    /// merging, the one client holding the flock, even once its lease ran
+    /// out and until another one takes over.
+    pub fn controllers(&self, now_ms: u64) -> impl Iterator<Item = &str> {
+        self.live(now_ms).map(|client| client.id.as_str())
+    }
+
+    /// The targets of [`controllers`](Self::controllers)
+    pub fn targets(&self, now_ms: u64) -> impl Iterator<Item = Position> + '_ {
+        self.live(now_ms).map(|client| client.position)
+    }
+
+    fn live(&self, now_ms: u64) -> impl Iterator<Item = &ClientTarget> {
+        let lease = self.config.lease_ms as u64;
+        let merging = self.is_merging();
+        self.clients
+            .iter()
+            .filter(move |client| !merging || now_ms.saturating_sub(client.updated_ms) < lease)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arbiter(policy: ArbitrationPolicy) -> TargetArbiter {
+        TargetArbiter::new(ArbitrationConfig {
+            policy,
+            lease_ms: 1000,
+            priorities: [("camera".to_string(), 1)].into_iter().collect(),
+        })
+    }
+
+    #[test]
+    fn test_lease_keeps_the_flock_with_one_client() {
+        let mut arbiter = arbiter(ArbitrationPolicy::Lease);
+        let (a, b) = (Position::new(10.0, 10.0), Position::new(90.0, 90.0));
+        assert_eq!(arbiter.submit("phone", Some(a), 0), Ok(false));
+        assert_eq!(
+            arbiter.submit("laptop", Some(b), 500),
+            Err(TargetHeld {
+                controller: "phone".to_string()
+            })
+        );
+        // Withdrawing someone else's target is refused too
+        assert!(arbiter.submit("laptop", None, 600).is_err());
+        assert_eq!(arbiter.targets(600).collect::<Vec<_>>(), [a]);
+
+        // The holder keeps the flock after going quiet, until someone else
+        // takes over
+        assert_eq!(arbiter.controllers(5000).collect::<Vec<_>>(), ["phone"]);
+        assert_eq!(arbiter.submit("laptop", Some(b), 5000), Ok(true));
+        assert_eq!(arbiter.controllers(5000).collect::<Vec<_>>(), ["laptop"]);
+
+        // Withdrawing frees the flock at once
+        assert_eq!(arbiter.submit("laptop", None, 5100), Ok(false));
+        assert_eq!(arbiter.submit("phone", Some(a), 5200), Ok(false));
+    }
+
+    #[test]
+    fn test_priority_takes_over_at_once() {
+        let mut arbiter = arbiter(ArbitrationPolicy::Priority);
+        let target = Position::new(1.0, 2.0);
+        arbiter.submit("phone", Some(target), 0).unwrap();
+        assert_eq!(arbiter.submit("camera", Some(target), 10), Ok(true));
+        assert!(arbiter.submit("phone", Some(target), 20).is_err());
+        assert_eq!(arbiter.controllers(20).collect::<Vec<_>>(), ["camera"]);
+    }
+
+    #[test]
+    fn test_merge_follows_every_live_client() {
+        let mut arbiter = arbiter(ArbitrationPolicy::Merge);
+        let (a, b) = (Position::new(10.0, 10.0), Position::new(90.0, 90.0));
+        arbiter.submit("phone", Some(a), 0).unwrap();
+        arbiter.submit("laptop", Some(b), 100).unwrap();
+        assert_eq!(
+            arbiter.controllers(200).collect::<Vec<_>>(),
+            ["laptop", "phone"]
+        );
+        assert_eq!(arbiter.targets(200).collect::<Vec<_>>(), [b, a]);
+
+        // Quiet clients drop out
+        assert_eq!(arbiter.controllers(1050).collect::<Vec<_>>(), ["laptop"]);
+        arbiter.submit("laptop", None, 1060).unwrap();
+        assert_eq!(arbiter.targets(1060).count(), 0);
+
+        for (i, client) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            arbiter.submit(client, Some(a), 2000 + i as u64).unwrap();
+        }
+        assert!(arbiter.submit("e", Some(a), 2010).is_err());
+
+        // Leaving merge keeps the newest client in control
+        arbiter.set_config(ArbitrationConfig::default());
+        assert_eq!(arbiter.controllers(2010).collect::<Vec<_>>(), ["d"]);
+    }
+
+    #[test]
+    fn test_config_json() {
+        let config: ArbitrationConfig =
+            serde_json::from_str(r#"{"policy":"priority","priorities":{"camera":2}}"#).unwrap();
+        assert_eq!(config.lease_ms, DEFAULT_LEASE_MS);
+        assert_eq!(config.priority("camera"), 2);
+        assert_eq!(config.priority("phone"), 0);
+        assert_eq!(
+            serde_json::to_string(&ArbitrationConfig::default()).unwrap(),
+            r#"{"policy":"lease","lease_ms":2000}"#
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Boid colors that stay legible to color-blind viewers
+//!
+//! The demo themes shade boids by hue alone, from slow to fast, and sub-flocks
+//! differ only in hue too. A [`ColorScheme`] other than
+//! [`ColorScheme::Theme`] replaces those hues with a fixed palette whose
+//! colors stay apart under the common kinds of color blindness, and repeats
+//! what the color says in the boid's outline: each group gets a [`Shape`] of
+//! its own and faster boids are drawn larger. Displays with fewer colors,
+//! such as the ESP32's RGB565 panels, map the palette with [`rgb565`].
+
+use serde::{Deserialize, Serialize};
+
+pub type Rgb = [u8; 3];
+
+/// Okabe and Ito's color-blind safe palette, without its black, which would
+/// vanish on the dark background
+pub const OKABE_ITO: [Rgb; 7] = [
+    [230, 159, 0],   // orange
+    [86, 180, 233],  // sky blue
+    [0, 158, 115],   // bluish green
+    [240, 228, 66],  // yellow
+    [0, 114, 178],   // blue
+    [213, 94, 0],    // vermillion
+    [204, 121, 167], // reddish purple
+];
+
+/// Fully saturated colors at full brightness, for projectors and bright
+/// rooms; they differ in lightness as well as hue
+pub const HIGH_CONTRAST: [Rgb; 4] = [[255, 255, 255], [255, 255, 0], [0, 255, 255], [255, 0, 255]];
+
+/// Size of a boid at rest and at full speed under the fixed palettes,
+/// relative to the renderer's usual size
+pub const MIN_SCALE: f32 = 0.75;
+pub const MAX_SCALE: f32 = 1.5;
+
+/// Size of a boid perched on the ground, relative to its glyph in flight
+pub const PERCHED_SCALE: f32 = 0.7;
+
+/// How boids are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorScheme {
+    /// The theme's hues, shaded by speed
+    #[default]
+    Theme,
+    /// [`OKABE_ITO`] by group, with shape and size encoding
+    OkabeIto,
+    /// [`HIGH_CONTRAST`] by group, with shape and size encoding
+    HighContrast,
+}
+
+impl ColorScheme {
+    /// `"theme"`, `"okabe_ito"` or `"high_contrast"`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "theme" => Some(Self::Theme),
+            "okabe_ito" => Some(Self::OkabeIto),
+            "high_contrast" => Some(Self::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Theme => "theme",
+            Self::OkabeIto => "okabe_ito",
+            Self::HighContrast => "high_contrast",
+        }
+    }
+
+    /// The fixed palette; `None` for [`ColorScheme::Theme`], whose colors
+    /// come from the theme
+    pub fn colors(&self) -> Option<&'static [Rgb]> {
+        match self {
+            Self::Theme => None,
+            Self::OkabeIto => Some(&OKABE_ITO),
+            Self::HighContrast => Some(&HIGH_CONTRAST),
+        }
+    }
+
+    /// Color of a boid in sub-flock `group`, cycling through the palette
+    pub fn color(&self, group: u8) -> Option<Rgb> {
+        self.colors()
+            .map(|colors| colors[group as usize % colors.len()])
+    }
+
+    /// Outline of a boid in sub-flock `group` moving at `speed_fraction` of
+    /// the maximum speed; the usual triangle under [`ColorScheme::Theme`]
+    pub fn glyph(&self, group: u8, speed_fraction: f32) -> Glyph {
+        match self {
+            Self::Theme => Glyph::PLAIN,
+            Self::OkabeIto | Self::HighContrast => Glyph {
+                shape: Shape::ALL[group as usize % Shape::ALL.len()],
+                scale: MIN_SCALE + (MAX_SCALE - MIN_SCALE) * speed_fraction.clamp(0.0, 1.0),
+            },
+        }
+    }
+}
+
+/// Geometry a renderer draws a boid's outline from
+///
+/// Palettes pick one per group; otherwise the viewer's
+/// [`BoidShape`](crate::style::BoidShape) picks one for every boid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Triangle,
+    Square,
+    Diamond,
+    Circle,
+    /// Only drawn by [`BoidShape::Arrow`](crate::style::BoidShape::Arrow)
+    Arrow,
+}
+
+impl Shape {
+    /// In the order groups are given them
+    pub const ALL: [Shape; 4] = [
+        Shape::Triangle,
+        Shape::Square,
+        Shape::Diamond,
+        Shape::Circle,
+    ];
+
+    /// Corners for a boid of length 1 at the origin, heading along +x, in
+    /// drawing order. Every corner can be seen from the first, so renderers
+    /// that can only fill triangles fan out from it. Circles are octagons.
+    pub fn corners(&self) -> &'static [(f32, f32)] {
+        const C: f32 = 0.353_553_4; // 0.5 * cos(45°)
+        match self {
+            Shape::Triangle => &[(1.0, 0.0), (-0.5, 0.5), (-0.5, -0.5)],
+            Shape::Square => &[(0.4, 0.4), (-0.4, 0.4), (-0.4, -0.4), (0.4, -0.4)],
+            Shape::Diamond => &[(0.8, 0.0), (0.0, 0.45), (-0.6, 0.0), (0.0, -0.45)],
+            Shape::Circle => &[
+                (0.5, 0.0),
+                (C, C),
+                (0.0, 0.5),
+                (-C, C),
+                (-0.5, 0.0),
+                (-C, -C),
+                (0.0, -0.5),
+                (C, -C),
+            ],
+            Shape::Arrow => &[(1.0, 0.0), (-0.5, 0.5), (-0.2, 0.0), (-0.5, -0.5)],
+        }
+    }
+}
+
+/// Shape and size to draw a boid with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub shape: Shape,
+    /// Multiplies the renderer's boid size
+    pub scale: f32,
+}
+
+impl Glyph {
+    pub const PLAIN: Glyph = Glyph {
+        shape: Shape::Triangle,
+        scale: 1.0,
+    };
+
+    /// The same outline for a boid resting on the ground, wings folded;
+    /// renderers draw it pointing straight up
+    pub fn perched(self) -> Glyph {
+        Glyph {
+            scale: self.scale * PERCHED_SCALE,
+            ..self
+        }
+    }
+}
+
+/// The nearest RGB565 color as 5-, 6- and 5-bit channels; truncating the
+/// low bits instead can land a whole level off
+pub fn rgb565(color: Rgb) -> (u8, u8, u8) {
+    let scale = |channel: u8, max: u16| ((channel as u16 * max + 127) / 255) as u8;
+    (
+        scale(color[0], 31),
+        scale(color[1], 63),
+        scale(color[2], 31),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemes_encode_groups_and_speed_without_hue() {
+        for scheme in [
+            ColorScheme::Theme,
+            ColorScheme::OkabeIto,
+            ColorScheme::HighContrast,
+        ] {
+            assert_eq!(ColorScheme::parse(scheme.as_str()), Some(scheme));
+        }
+        assert_eq!(ColorScheme::parse("rainbow"), None);
+        assert_eq!(ColorScheme::Theme.color(0), None);
+        assert_eq!(ColorScheme::Theme.glyph(3, 1.0), Glyph::PLAIN);
+
+        let scheme = ColorScheme::OkabeIto;
+        assert_eq!(scheme.color(1), Some([86, 180, 233]));
+        assert_eq!(scheme.color(8), scheme.color(1));
+        // Neighboring groups differ in shape as well as color
+        for group in 0..3 {
+            assert_ne!(
+                scheme.glyph(group, 0.5).shape,
+                scheme.glyph(group + 1, 0.5).shape
+            );
+        }
+        assert_eq!(scheme.glyph(0, 0.0).scale, MIN_SCALE);
+        assert_eq!(scheme.glyph(0, 2.0).scale, MAX_SCALE);
+        assert!(scheme.glyph(0, 0.5).scale > MIN_SCALE);
+
+        assert_eq!(rgb565([255, 255, 255]), (31, 63, 31));
+        assert_eq!(rgb565([0, 0, 0]), (0, 0, 0));
+        // Truncating would give (30, 57, 8)
+        assert_eq!(rgb565([240, 228, 66]), (29, 56, 8));
+    }
+}
@@ -0,0 +1,479 @@
+//! Demo rotation for unattended displays
+//!
+//! A [`DemoSchedule`] is a list of scenes, each shown for a number of ticks.
+//! A scene can crossfade to new settings, spell text, move a scripted target
+//! around and switch the color theme. [`DemoPlayer`] loops through the
+//! scenes; the frontend applies what it reports each tick.
+
+use crate::{limits, BoidSettings, ErrorCode, Position};
+use serde::{Deserialize, Serialize};
+
+/// Crossfade into each scene, in ticks, unless the schedule says otherwise
+pub const DEFAULT_TRANSITION_TICKS: u32 = 60;
+
+/// Color scheme for drawing boids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Cyan to green, the frontend's usual colors
+    #[default]
+    Ocean,
+    Forest,
+    Ember,
+    Dusk,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Ocean => Palette::new(180.0, 60.0),
+            Theme::Forest => Palette::new(80.0, 50.0),
+            Theme::Ember => Palette::new(0.0, 45.0),
+            Theme::Dusk => Palette::new(250.0, 70.0),
+        }
+    }
+
+    pub fn depth_style(self) -> DepthStyle {
+        match self {
+            Theme::Ocean => DepthStyle {
+                shadow_offset: (5.0, 8.0),
+                shadow_blur: 4.0,
+                shadow_opacity: 0.5,
+                reflection_opacity: 0.45,
+            },
+            Theme::Forest => DepthStyle {
+                shadow_offset: (4.0, 6.0),
+                shadow_blur: 3.0,
+                shadow_opacity: 0.6,
+                reflection_opacity: 0.35,
+            },
+            Theme::Ember => DepthStyle {
+                shadow_offset: (2.0, 10.0),
+                shadow_blur: 6.0,
+                shadow_opacity: 0.65,
+                reflection_opacity: 0.3,
+            },
+            Theme::Dusk => DepthStyle {
+                shadow_offset: (8.0, 6.0),
+                shadow_blur: 8.0,
+                shadow_opacity: 0.4,
+                reflection_opacity: 0.5,
+            },
+        }
+    }
+}
+
+/// How shadows and reflections are drawn under a theme's boids
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthStyle {
+    /// Shadow offset from each boid in pixels, down and right as if lit
+    /// from the upper left
+    pub shadow_offset: (f32, f32),
+    /// Blur radius of shadows in pixels
+    pub shadow_blur: f32,
+    /// 0-1
+    pub shadow_opacity: f32,
+    /// Opacity of a reflection right at the horizon; reflections fade out
+    /// further from it
+    pub reflection_opacity: f32,
+}
+
+impl DepthStyle {
+    /// `t` of the way from `self` to `other`
+    pub fn lerp(&self, other: &DepthStyle, t: f32) -> DepthStyle {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        DepthStyle {
+            shadow_offset: (
+                mix(self.shadow_offset.0, other.shadow_offset.0),
+                mix(self.shadow_offset.1, other.shadow_offset.1),
+            ),
+            shadow_blur: mix(self.shadow_blur, other.shadow_blur),
+            shadow_opacity: mix(self.shadow_opacity, other.shadow_opacity),
+            reflection_opacity: mix(self.reflection_opacity, other.reflection_opacity),
+        }
+    }
+}
+
+/// Boid hues in degrees: `base_hue` at rest, up to `base_hue + hue_span`
+/// at full speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub base_hue: f32,
+    pub hue_span: f32,
+}
+
+impl Palette {
+    pub fn new(base_hue: f32, hue_span: f32) -> Self {
+        Self { base_hue, hue_span }
+    }
+
+    /// Hue for a boid moving at `speed_fraction` of the maximum speed
+    pub fn hue(&self, speed_fraction: f32) -> f32 {
+        (self.base_hue + speed_fraction.clamp(0.0, 1.0) * self.hue_span).rem_euclid(360.0)
+    }
+
+    /// The hue at 70% saturation and 60% lightness, as 8-bit RGB
+    pub fn rgb(&self, speed_fraction: f32) -> [u8; 3] {
+        let (saturation, lightness) = (0.7, 0.6);
+        let chroma = (1.0 - (2.0 * lightness - 1.0_f32).abs()) * saturation;
+        let sector = self.hue(speed_fraction) / 60.0;
+        let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = lightness - chroma / 2.0;
+        [r, g, b].map(|channel| ((channel + m) * 255.0).round() as u8)
+    }
+
+    /// `t` of the way from `self` to `other`, going the short way around the
+    /// color wheel
+    pub fn lerp(&self, other: &Palette, t: f32) -> Palette {
+        let t = t.clamp(0.0, 1.0);
+        let mut delta = (other.base_hue - self.base_hue).rem_euclid(360.0);
+        if delta > 180.0 {
+            delta -= 360.0;
+        }
+        Palette {
+            base_hue: (self.base_hue + delta * t).rem_euclid(360.0),
+            hue_span: self.hue_span + (other.hue_span - self.hue_span) * t,
+        }
+    }
+}
+
+/// Shape a scripted target traces around the middle of the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetPath {
+    Orbit,
+    FigureEight,
+    /// Side to side through the center
+    Sweep,
+}
+
+/// A target that moves on its own, standing in for a hand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedTarget {
+    pub path: TargetPath,
+    /// Ticks per lap
+    pub period_ticks: u32,
+}
+
+impl ScriptedTarget {
+    /// Where the target is `tick` ticks into its scene on a `width` x
+    /// `height` canvas. The path spans 70% of each dimension.
+    pub fn position(&self, tick: u32, width: f32, height: f32) -> Position {
+        let period = self.period_ticks.max(1);
+        let phase = (tick % period) as f32 / period as f32 * core::f32::consts::TAU;
+        let (x, y) = match self.path {
+            TargetPath::Orbit => (libm::cosf(phase), libm::sinf(phase)),
+            TargetPath::FigureEight => (libm::sinf(phase), libm::sinf(2.0 * phase)),
+            TargetPath::Sweep => (libm::sinf(phase), 0.0),
+        };
+        Position::new(
+            width / 2.0 + x * width * 0.35,
+            height / 2.0 + y * height * 0.35,
+        )
+    }
+}
+
+/// One step of the rotation. Anything left out carries over from the
+/// previous scene, except `spell` and `target`, which only last one scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoScene {
+    pub duration_ticks: u32,
+    #[serde(default)]
+    pub settings: Option<BoidSettings>,
+    /// Text for the flock to spell while the scene lasts
+    #[serde(default)]
+    pub spell: Option<String>,
+    #[serde(default)]
+    pub target: Option<ScriptedTarget>,
+    #[serde(default)]
+    pub theme: Option<Theme>,
+}
+
+/// Scenes shown in a loop, e.g.
+///
+/// ```json
+/// {"transition_ticks": 60, "scenes": [
+///   {"duration_ticks": 600, "theme": "ember",
+///    "target": {"path": "orbit", "period_ticks": 300}},
+///   {"duration_ticks": 300, "spell": "HELLO"}
+/// ]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoSchedule {
+    pub scenes: Vec<DemoScene>,
+    /// Settings and theme crossfade into each scene over this many ticks,
+    /// capped at `limits::PRESET_TICKS_MAX`
+    #[serde(default = "default_transition_ticks")]
+    pub transition_ticks: u32,
+}
+
+fn default_transition_ticks() -> u32 {
+    DEFAULT_TRANSITION_TICKS
+}
+
+impl DemoSchedule {
+    /// The built-in rotation, holding each scene for `scene_ticks`: a calm
+    /// orbit, a tight swarm on a figure eight, the word "BOIDS" and a fast
+    /// scatter
+    pub fn builtin(scene_ticks: u32) -> Self {
+        let scene = |settings, target, theme| DemoScene {
+            duration_ticks: scene_ticks,
+            settings: Some(settings),
+            spell: None,
+            target,
+            theme: Some(theme),
+        };
+        let defaults = BoidSettings::default();
+        let lap = (scene_ticks / 2).max(1);
+        Self {
+            scenes: vec![
+                scene(
+                    defaults.clone(),
+                    Some(ScriptedTarget {
+                        path: TargetPath::Orbit,
+                        period_ticks: lap,
+                    }),
+                    Theme::Ocean,
+                ),
+                scene(
+                    BoidSettings {
+                        cohesion_weight: 2.5,
+                        separation_weight: 1.0,
+                        max_speed: 3.0,
+                        ..defaults.clone()
+                    },
+                    Some(ScriptedTarget {
+                        path: TargetPath::FigureEight,
+                        period_ticks: lap,
+                    }),
+                    Theme::Ember,
+                ),
+                DemoScene {
+                    spell: Some("BOIDS".to_string()),
+                    ..scene(defaults.clone(), None, Theme::Dusk)
+                },
+                scene(
+                    BoidSettings {
+                        separation_weight: 3.0,
+                        cohesion_weight: 0.5,
+                        max_speed: 4.0,
+                        ..defaults
+                    },
+                    Some(ScriptedTarget {
+                        path: TargetPath::Sweep,
+                        period_ticks: lap,
+                    }),
+                    Theme::Forest,
+                ),
+            ],
+            transition_ticks: DEFAULT_TRANSITION_TICKS,
+        }
+    }
+
+    /// Reject schedules that cannot play and clamp scene settings into
+    /// their ranges
+    pub fn validate(&mut self) -> Result<(), ErrorCode> {
+        if self.scenes.is_empty() {
+            return Err(ErrorCode::InvalidDemoSchedule);
+        }
+        for scene in self.scenes.iter_mut() {
+            let lapless = scene.target.is_some_and(|target| target.period_ticks == 0);
+            if scene.duration_ticks == 0 || lapless {
+                return Err(ErrorCode::InvalidDemoSchedule);
+            }
+            if let Some(settings) = scene.settings.as_mut() {
+                settings
+                    .clamp_to_limits(|_, _, _| {})
+                    .map_err(|_| ErrorCode::InvalidSetting)?;
+            }
+        }
+        self.transition_ticks = self.transition_ticks.min(limits::PRESET_TICKS_MAX);
+        Ok(())
+    }
+}
+
+/// Steps through a schedule, one call to [`advance`](Self::advance) per tick
+#[derive(Debug, Clone)]
+pub struct DemoPlayer {
+    schedule: DemoSchedule,
+    scene: usize,
+    /// Ticks since the current scene started
+    tick: u32,
+    started: bool,
+    theme: Theme,
+    /// Palette when the current scene started, faded out over the transition
+    previous_palette: Palette,
+    previous_depth_style: DepthStyle,
+}
+
+impl DemoPlayer {
+    pub fn new(mut schedule: DemoSchedule) -> Result<Self, ErrorCode> {
+        schedule.validate()?;
+        let palette = Theme::default().palette();
+        Ok(Self {
+            schedule,
+            scene: 0,
+            tick: 0,
+            started: false,
+            theme: Theme::default(),
+            previous_palette: palette,
+            previous_depth_style: Theme::default().depth_style(),
+        })
+    }
+
+    /// Move one tick forward. Returns the scene starting on this tick, so the
+    /// caller can crossfade its settings and start or stop spelling; the
+    /// first call starts the first scene.
+    pub fn advance(&mut self) -> Option<&DemoScene> {
+        if !self.started {
+            self.started = true;
+            self.enter(0);
+            return Some(self.scene());
+        }
+        self.tick = self.tick.saturating_add(1);
+        if self.tick < self.scene().duration_ticks {
+            return None;
+        }
+        self.enter((self.scene + 1) % self.schedule.scenes.len());
+        Some(self.scene())
+    }
+
+    fn enter(&mut self, index: usize) {
+        self.previous_palette = self.palette();
+        self.previous_depth_style = self.depth_style();
+        self.scene = index;
+        self.tick = 0;
+        if let Some(theme) = self.scene().theme {
+            self.theme = theme;
+        }
+    }
+
+    pub fn scene(&self) -> &DemoScene {
+        &self.schedule.scenes[self.scene]
+    }
+
+    pub fn scene_index(&self) -> usize {
+        self.scene
+    }
+
+    pub fn transition_ticks(&self) -> u32 {
+        self.schedule.transition_ticks
+    }
+
+    /// The scripted target this tick, if the scene has one
+    pub fn target(&self, width: f32, height: f32) -> Option<Position> {
+        self.scene()
+            .target
+            .map(|target| target.position(self.tick, width, height))
+    }
+
+    /// Colors this tick, partway between the themes while a scene fades in
+    pub fn palette(&self) -> Palette {
+        let to = self.theme.palette();
+        match self.schedule.transition_ticks {
+            0 => to,
+            ticks => self
+                .previous_palette
+                .lerp(&to, self.tick as f32 / ticks as f32),
+        }
+    }
+
+    /// Shadow and reflection style this tick, blended like the palette
+    pub fn depth_style(&self) -> DepthStyle {
+        let to = self.theme.depth_style();
+        match self.schedule.transition_ticks {
+            0 => to,
+            ticks => self
+                .previous_depth_style
+                .lerp(&to, self.tick as f32 / ticks as f32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_loops_through_scenes() {
+        let mut player = DemoPlayer::new(DemoSchedule::builtin(10)).unwrap();
+        assert_eq!(
+            player.advance().map(|scene| scene.theme),
+            Some(Some(Theme::Ocean))
+        );
+        for _ in 0..9 {
+            assert!(player.advance().is_none());
+        }
+        assert!(player.target(100.0, 100.0).is_some());
+
+        let second = player.advance().unwrap().clone();
+        assert_eq!(second.theme, Some(Theme::Ember));
+        assert_eq!(player.scene_index(), 1);
+        // The palette starts where the last scene left off
+        assert_eq!(player.palette(), Theme::Ocean.palette());
+        assert_eq!(player.depth_style(), Theme::Ocean.depth_style());
+        for _ in 0..5 {
+            player.advance();
+        }
+        let fading = Theme::Ocean.depth_style().lerp(
+            &Theme::Ember.depth_style(),
+            5.0 / DEFAULT_TRANSITION_TICKS as f32,
+        );
+        assert_eq!(player.depth_style(), fading);
+
+        for _ in 0..5 {
+            player.advance();
+        }
+        assert_eq!(player.scene().spell.as_deref(), Some("BOIDS"));
+        for _ in 0..19 {
+            player.advance();
+        }
+        assert!(player.advance().is_some());
+        assert_eq!(player.scene_index(), 0);
+    }
+
+    #[test]
+    fn test_schedule_validation_and_json() {
+        let mut schedule: DemoSchedule = serde_json::from_str(
+            r#"{"scenes": [{"duration_ticks": 30, "theme": "dusk",
+                "target": {"path": "figure_eight", "period_ticks": 20}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(schedule.transition_ticks, DEFAULT_TRANSITION_TICKS);
+        assert!(schedule.validate().is_ok());
+
+        schedule.scenes[0].duration_ticks = 0;
+        assert_eq!(schedule.validate(), Err(ErrorCode::InvalidDemoSchedule));
+        let empty = DemoSchedule {
+            scenes: Vec::new(),
+            transition_ticks: 0,
+        };
+        assert_eq!(
+            DemoPlayer::new(empty).err(),
+            Some(ErrorCode::InvalidDemoSchedule)
+        );
+        assert!(serde_json::from_str::<DemoSchedule>(r#"{"scenes": [{"duration": 5}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_palette_blends_the_short_way() {
+        let red = Palette::new(350.0, 0.0);
+        let orange = Palette::new(30.0, 40.0);
+        let halfway = red.lerp(&orange, 0.5);
+        assert!((halfway.base_hue - 10.0).abs() < 1e-3);
+        assert_eq!(halfway.hue_span, 20.0);
+        assert_eq!(Palette::new(120.0, 0.0).rgb(0.0), [82, 224, 82]);
+    }
+}
@@ -0,0 +1,69 @@
+//! The interface every hand detector implements
+//!
+//! The skin detector in `boid-hand-detector`, the OpenCV tracker in
+//! `boid-client` and the backends behind the hybrid detector all take a
+//! [`Frame`] and return the hand's fingertips, so a client can pick one at
+//! run time and wrap any of them in the hybrid detector or the watchdog.
+
+use crate::HandLandmarks;
+
+/// Byte layout of a frame's pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba,
+    /// OpenCV's default
+    Bgr,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba => 4,
+            PixelFormat::Bgr => 3,
+        }
+    }
+}
+
+/// A camera frame as handed to a detector: rows top to bottom, without
+/// padding
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub data: &'a [u8],
+    pub format: PixelFormat,
+}
+
+impl<'a> Frame<'a> {
+    /// `None` if `data` is too short for a `width` x `height` frame
+    pub fn new(width: usize, height: usize, data: &'a [u8], format: PixelFormat) -> Option<Self> {
+        let needed = width
+            .checked_mul(height)?
+            .checked_mul(format.bytes_per_pixel())?;
+        (data.len() >= needed).then_some(Self {
+            width,
+            height,
+            data,
+            format,
+        })
+    }
+}
+
+/// A hand detector
+pub trait HandDetectorBackend {
+    /// Landmarks of the hand in `frame`, in frame pixels
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks>;
+}
+
+impl<D: HandDetectorBackend + ?Sized> HandDetectorBackend for &mut D {
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        (**self).detect(frame)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: HandDetectorBackend + ?Sized> HandDetectorBackend for std::boxed::Box<D> {
+    fn detect(&mut self, frame: &Frame<'_>) -> Option<HandLandmarks> {
+        (**self).detect(frame)
+    }
+}
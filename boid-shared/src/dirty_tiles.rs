@@ -0,0 +1,191 @@
+//! Which parts of a display changed since it was last sent
+//!
+//! Clearing the ESP32's 240x240 panel and drawing every boid again each
+//! frame sends the whole screen over SPI and shows it blank in between,
+//! which flickers. Drawing into RAM instead and sending only what changed
+//! needs to know what changed. [`DirtyTiles`] splits the display into
+//! [`TILE_SIZE`] squares and remembers which ones were drawn this frame and
+//! the last: the first hold the new picture, the second still show the old
+//! one and need erasing. [`DirtyTiles::spans`] merges them into runs along
+//! each row of tiles, one SPI window each.
+
+/// Side of a tile in pixels
+pub const TILE_SIZE: u32 = 16;
+/// Tiles per side at most, so a display can be up to 512x512 pixels
+pub const MAX_TILES: u32 = 32;
+
+/// A rectangle of pixels to send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tiles drawn in this frame and the last, one bit per tile in each row
+#[derive(Debug, Clone)]
+pub struct DirtyTiles {
+    width: u32,
+    height: u32,
+    drawn: [u32; MAX_TILES as usize],
+    stale: [u32; MAX_TILES as usize],
+}
+
+impl DirtyTiles {
+    /// `None` for a display larger than [`MAX_TILES`] tiles a side
+    pub fn new(width: u32, height: u32) -> Option<Self> {
+        if width.div_ceil(TILE_SIZE) > MAX_TILES || height.div_ceil(TILE_SIZE) > MAX_TILES {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            drawn: [0; MAX_TILES as usize],
+            stale: [0; MAX_TILES as usize],
+        })
+    }
+
+    /// Note a pixel drawn this frame; pixels off the display are ignored
+    pub fn mark(&mut self, x: i32, y: i32) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        self.drawn[(y as u32 / TILE_SIZE) as usize] |= 1 << (x as u32 / TILE_SIZE);
+    }
+
+    /// Have the next [`spans`](Self::spans) cover the whole display, after
+    /// something else drew on it
+    pub fn invalidate(&mut self) {
+        let all = u32::MAX
+            .checked_shr(MAX_TILES - self.columns())
+            .unwrap_or(0);
+        let rows = self.rows();
+        self.stale[..rows as usize].fill(all);
+    }
+
+    /// Start a new frame: what was drawn becomes what needs erasing
+    pub fn next_frame(&mut self) {
+        self.stale = self.drawn;
+        self.drawn = [0; MAX_TILES as usize];
+    }
+
+    /// Tiles drawn this frame, merged along rows
+    pub fn drawn_spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.spans_of(false)
+    }
+
+    /// Tiles to send: drawn this frame or the last
+    pub fn spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.spans_of(true)
+    }
+
+    fn columns(&self) -> u32 {
+        self.width.div_ceil(TILE_SIZE)
+    }
+
+    fn rows(&self) -> u32 {
+        self.height.div_ceil(TILE_SIZE)
+    }
+
+    fn spans_of(&self, with_stale: bool) -> impl Iterator<Item = Span> + '_ {
+        (0..self.rows()).flat_map(move |row| {
+            let y = row * TILE_SIZE;
+            let height = TILE_SIZE.min(self.height - y);
+            let row = row as usize;
+            let mut bits = self.drawn[row] | if with_stale { self.stale[row] } else { 0 };
+            core::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let first = bits.trailing_zeros();
+                let count = (bits >> first).trailing_ones();
+                bits &= !((u32::MAX >> (MAX_TILES - count)) << first);
+                let x = first * TILE_SIZE;
+                Some(Span {
+                    x,
+                    y,
+                    width: (count * TILE_SIZE).min(self.width - x),
+                    height,
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(tiles: &DirtyTiles) -> std::vec::Vec<Span> {
+        tiles.spans().collect()
+    }
+
+    #[test]
+    fn test_drawn_tiles_merge_along_rows() {
+        let mut tiles = DirtyTiles::new(240, 240).unwrap();
+        assert!(spans(&tiles).is_empty());
+        tiles.mark(5, 5);
+        tiles.mark(20, 10);
+        tiles.mark(100, 10);
+        tiles.mark(239, 239);
+        tiles.mark(-1, 0);
+        tiles.mark(240, 0);
+        let expected = [
+            Span {
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 16,
+            },
+            Span {
+                x: 96,
+                y: 0,
+                width: 16,
+                height: 16,
+            },
+            Span {
+                x: 224,
+                y: 224,
+                width: 16,
+                height: 16,
+            },
+        ];
+        assert_eq!(spans(&tiles), expected);
+        assert!(tiles.drawn_spans().eq(expected));
+    }
+
+    #[test]
+    fn test_last_frame_is_sent_once_more_to_erase_it() {
+        let mut tiles = DirtyTiles::new(240, 240).unwrap();
+        tiles.mark(5, 5);
+        tiles.next_frame();
+        tiles.mark(40, 5);
+        // Both the boid's old and new tile, of which only the new is drawn
+        assert_eq!(spans(&tiles).len(), 2);
+        assert_eq!(tiles.drawn_spans().count(), 1);
+        tiles.next_frame();
+        tiles.next_frame();
+        assert!(spans(&tiles).is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_covers_the_display() {
+        // Tiles at the right and bottom edges are cut to the display
+        let mut tiles = DirtyTiles::new(100, 40).unwrap();
+        tiles.invalidate();
+        let covered: u32 = spans(&tiles)
+            .iter()
+            .map(|span| {
+                assert_eq!((span.x, span.width), (0, 100));
+                span.width * span.height
+            })
+            .sum();
+        assert_eq!(covered, 100 * 40);
+
+        let mut widest = DirtyTiles::new(512, 512).unwrap();
+        widest.invalidate();
+        assert_eq!(spans(&widest).len(), 32);
+        assert!(DirtyTiles::new(513, 16).is_none());
+    }
+}
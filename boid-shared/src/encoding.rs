@@ -0,0 +1,266 @@
+//! HTTP response encoding helpers for bandwidth-constrained servers
+//!
+//! A small gzip encoder (LZ77 + fixed Huffman codes, no allocation beyond the
+//! output buffer) and ETag helpers so polling clients can skip unchanged
+//! payloads.
+
+/// Fixed window used for back-references (the DEFLATE maximum)
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 12;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// LSB-first bit writer as required by DEFLATE
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> Self {
+        Self {
+            out,
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.buffer |= value << self.bits;
+        self.bits += count;
+        while self.bits >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// Huffman codes are defined MSB-first, so they are bit-reversed on write
+    fn write_code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+/// Write a literal/length symbol using the fixed Huffman table (RFC 1951 3.2.6)
+fn write_literal_length(writer: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let length_index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap_or(0);
+    write_literal_length(writer, 257 + length_index as u16);
+    writer.write_bits(
+        (length - LENGTH_BASE[length_index] as usize) as u32,
+        LENGTH_EXTRA[length_index] as u32,
+    );
+
+    let dist_index = DIST_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap_or(0);
+    writer.write_code(dist_index as u32, 5);
+    writer.write_bits(
+        (distance - DIST_BASE[dist_index] as usize) as u32,
+        DIST_EXTRA[dist_index] as u32,
+    );
+}
+
+fn hash3(data: &[u8]) -> usize {
+    let value = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compress `data` into a single fixed-Huffman DEFLATE block
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new(Vec::with_capacity(data.len() / 2 + 16));
+
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    // Most recent position + 1 for each hash bucket (0 = empty)
+    let mut head = vec![0usize; 1 << HASH_BITS];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+
+        if pos + MIN_MATCH <= data.len() {
+            let bucket = hash3(&data[pos..]);
+            if let Some(candidate) = head[bucket].checked_sub(1) {
+                let distance = pos - candidate;
+                if distance <= WINDOW_SIZE {
+                    let max_len = MAX_MATCH.min(data.len() - pos);
+                    let len = (0..max_len)
+                        .take_while(|&i| data[candidate + i] == data[pos + i])
+                        .count();
+                    if len >= MIN_MATCH {
+                        best_len = len;
+                        best_dist = distance;
+                    }
+                }
+            }
+            head[bucket] = pos + 1;
+        }
+
+        if best_len > 0 {
+            write_match(&mut writer, best_len, best_dist);
+            // Index the skipped positions so later data can reference them
+            for p in pos + 1..(pos + best_len).min(data.len().saturating_sub(MIN_MATCH - 1)) {
+                head[hash3(&data[p..])] = p + 1;
+            }
+            pos += best_len;
+        } else {
+            write_literal_length(&mut writer, data[pos] as u16);
+            pos += 1;
+        }
+    }
+
+    // End of block
+    write_literal_length(&mut writer, 256);
+    writer.finish()
+}
+
+/// CRC-32 (IEEE) as used by the gzip trailer
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Wrap a DEFLATE stream of `data` in a gzip container (RFC 1952)
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Magic, CM = deflate, no flags, no mtime, no extra flags, OS = unknown
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Whether an `Accept-Encoding` header value allows gzip
+pub fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|entry| {
+        let mut parts = entry.split(';');
+        let coding = parts.next().unwrap_or_default().trim();
+        let refused = parts.any(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        (coding.eq_ignore_ascii_case("gzip") || coding == "*") && !refused
+    })
+}
+
+/// Strong ETag (quoted FNV-1a hash) for a response body
+pub fn etag(body: &[u8]) -> String {
+    let hash = body.iter().fold(0x811c_9dc5u32, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    });
+    format!("\"{:08x}\"", hash)
+}
+
+/// Whether an `If-None-Match` header value matches `etag`
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(data)
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_gzip_roundtrip() {
+        let json = br#"{"boids":[{"x":1.0,"y":2.0},{"x":1.0,"y":2.0},{"x":1.5,"y":2.5}]}"#;
+        assert_eq!(gunzip(&gzip(json)), json);
+        assert_eq!(gunzip(&gzip(b"")), b"");
+    }
+
+    #[test]
+    fn test_gzip_compresses_repetitive_data() {
+        let data = br#"{"x":120.0,"y":80.0},"#.repeat(200);
+        let compressed = gzip(&data);
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(gunzip(&compressed), data);
+    }
+
+    #[test]
+    fn test_crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip("gzip, deflate, br"));
+        assert!(accepts_gzip("br;q=1.0, GZIP;q=0.5"));
+        assert!(!accepts_gzip("gzip;q=0, deflate"));
+        assert!(!accepts_gzip("identity"));
+    }
+
+    #[test]
+    fn test_etag_matching() {
+        let tag = etag(br#"{"boid_count":20}"#);
+        assert_eq!(tag, etag(br#"{"boid_count":20}"#));
+        assert_ne!(tag, etag(br#"{"boid_count":21}"#));
+
+        assert!(etag_matches(&tag, &tag));
+        assert!(etag_matches(&format!("\"other\", W/{}", tag), &tag));
+        assert!(etag_matches("*", &tag));
+        assert!(!etag_matches("\"other\"", &tag));
+    }
+}
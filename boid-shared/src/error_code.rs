@@ -0,0 +1,245 @@
+//! Stable error codes shared by the firmware, the browser frontend and the
+//! client
+//!
+//! Every error a user can run into carries one of these identifiers
+//! (`E-<AREA>-<NN>`), so UIs, logs and support docs can refer to the same
+//! thing. Codes are never renumbered or reused; new ones are appended.
+
+use core::fmt;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $variant:ident = $code:literal, $status:literal, $description:literal;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($(#[$meta])* $variant,)*
+        }
+
+        impl ErrorCode {
+            /// Every code, in table order
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            /// The stable identifier, e.g. `"E-CAM-01"`
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                }
+            }
+
+            /// One-sentence explanation for users
+            pub fn description(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $description,)*
+                }
+            }
+
+            /// HTTP status the ESP32 answers with
+            pub fn http_status(self) -> u16 {
+                match self {
+                    $(ErrorCode::$variant => $status,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// The camera failed to initialize at boot
+    CameraInitFailed = "E-CAM-01", 503, "The camera failed to initialize, so streaming is unavailable.";
+    CameraCaptureFailed = "E-CAM-02", 500, "The camera did not deliver a frame.";
+    /// The sensor could not be woken or put into standby
+    CameraPowerFailed = "E-CAM-03", 500, "The camera could not be powered up or put into standby.";
+    /// `/stream` on a camera set to capture raw frames
+    CameraFormatUnsupported = "E-CAM-04", 409, "The camera captures raw frames, so it cannot stream JPEG.";
+
+    EndpointNotFound = "E-NET-01", 404, "The requested endpoint does not exist.";
+    InvalidRequestBody = "E-NET-02", 400, "The request body is not valid JSON for this endpoint.";
+    TargetParseFailed = "E-NET-03", 400, "The target position could not be parsed.";
+    WebSocketExpected = "E-NET-04", 400, "The endpoint expects a WebSocket upgrade request.";
+    ControlChannelBusy = "E-NET-05", 409, "Another client is already connected to the control channel.";
+    InvalidControlMessage = "E-NET-06", 400, "A control channel message could not be parsed.";
+    /// Reported by clients when the device does not answer
+    ServerUnreachable = "E-NET-07", 503, "The boid server could not be reached.";
+    /// A batch with no commands or too many, or with an invalid command
+    InvalidBatch = "E-NET-08", 400, "The batch is empty, too long, or has a command that was rejected.";
+    /// A target from a client while another one holds the flock
+    TargetHeld = "E-NET-09", 409, "Another client is controlling the flock.";
+
+    /// A setting is NaN or infinite; out-of-range values are clamped instead
+    InvalidSetting = "E-CFG-01", 400, "A setting is not a finite number.";
+    InvalidCalibration = "E-CFG-02", 400, "Lens calibration coefficients must be finite numbers.";
+    NothingToUndo = "E-CFG-03", 409, "There is no configuration change to undo.";
+    InvalidPreset = "E-CFG-04", 400, "The preset must be a JSON object of known config fields.";
+    InvalidDemoSchedule = "E-CFG-05", 400, "The demo schedule is empty, or a scene has no duration.";
+    /// Reported by clients for a missing or unreadable device profile
+    InvalidProfile = "E-CFG-06", 400, "The client profile does not exist or could not be parsed.";
+    /// An input mapping with an unknown input or parameter, or numbers out
+    /// of range
+    InvalidMapping = "E-CFG-07", 400, "An input mapping names an unknown input or parameter, or has an invalid range.";
+
+    SnapshotUnavailable = "E-SIM-01", 503, "No flock snapshot has been taken yet.";
+    InvalidSnapshot = "E-SIM-02", 400, "The flock snapshot could not be parsed.";
+    UnknownBoid = "E-SIM-03", 404, "No boid has the given id.";
+    UnknownGroup = "E-SIM-04", 404, "No boid group has the given name.";
+    InvalidText = "E-SIM-05", 400, "The text to spell is empty or too long.";
+    /// Shape points for a formation that are not whole, finite x, y pairs
+    InvalidShape = "E-SIM-06", 400, "Shape points must be finite x, y pairs.";
+
+    /// An unrecognized name for a render style, LOD focus or handedness
+    UnknownOption = "E-UI-01", 400, "The option name is not one of the accepted values.";
+    InvalidLandmarks = "E-UI-02", 400, "Hand landmark data has the wrong number of values.";
+    InvalidSpriteSheet = "E-UI-03", 400, "Sprite sheet dimensions must be positive.";
+    /// A DOM element or browser API the frontend needs is missing
+    ElementNotFound = "E-UI-04", 500, "A required page element or browser feature is missing.";
+    /// A bitmap to spell whose pixels do not fill whole rows
+    InvalidBitmap = "E-UI-05", 400, "Bitmap pixels must fill whole rows of the given width.";
+    /// Exclusion rectangles that are not whole, finite quadruples
+    InvalidExclusionRects = "E-UI-06", 400, "Exclusion rectangles must be finite x, y, width, height values.";
+
+    SerializationFailed = "E-SYS-01", 500, "The response could not be serialized.";
+}
+
+impl ErrorCode {
+    /// Look up a code by its identifier, ignoring case
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|known| known.as_str().eq_ignore_ascii_case(code))
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CodeVisitor;
+
+        impl Visitor<'_> for CodeVisitor {
+            type Value = ErrorCode;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an error code such as \"E-CAM-01\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<ErrorCode, E> {
+                ErrorCode::parse(value)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_str(CodeVisitor)
+    }
+}
+
+/// JSON body of every error response: `{"code": "E-NET-03", "error": "..."}`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    /// The code's description, or a more specific message
+    pub error: String,
+}
+
+#[cfg(feature = "std")]
+impl ErrorBody {
+    pub fn new(code: ErrorCode) -> Self {
+        Self::with_message(code, code.description())
+    }
+
+    pub fn with_message(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            error: message.into(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// One row of the code table, as served to UIs
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrorCodeInfo {
+    pub code: ErrorCode,
+    pub description: String,
+    pub http_status: u16,
+}
+
+#[cfg(feature = "std")]
+impl From<ErrorCode> for ErrorCodeInfo {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            code,
+            description: code.description().to_string(),
+            http_status: code.http_status(),
+        }
+    }
+}
+
+/// The whole code table
+#[cfg(feature = "std")]
+pub fn error_code_table() -> Vec<ErrorCodeInfo> {
+    ErrorCode::ALL
+        .iter()
+        .copied()
+        .map(ErrorCodeInfo::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique_and_well_formed() {
+        for (i, code) in ErrorCode::ALL.iter().enumerate() {
+            let id = code.as_str();
+            let parts: Vec<&str> = id.split('-').collect();
+            assert_eq!(parts.len(), 3, "{}", id);
+            assert_eq!(parts[0], "E");
+            assert!(parts[1].chars().all(|c| c.is_ascii_uppercase()), "{}", id);
+            assert_eq!(parts[2].len(), 2, "{}", id);
+            assert!(!code.description().is_empty());
+            assert!(ErrorCode::ALL[..i].iter().all(|other| other.as_str() != id));
+            assert_eq!(ErrorCode::parse(id), Some(*code));
+        }
+        assert_eq!(
+            ErrorCode::parse("e-cam-01"),
+            Some(ErrorCode::CameraInitFailed)
+        );
+        assert_eq!(ErrorCode::parse("E-CAM-99"), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_error_body_json() {
+        let body = ErrorBody::new(ErrorCode::TargetParseFailed);
+        assert_eq!(
+            body.to_json(),
+            r#"{"code":"E-NET-03","error":"The target position could not be parsed."}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ErrorBody>(&body.to_json()).unwrap(),
+            body
+        );
+        assert!(serde_json::from_str::<ErrorCode>(r#""E-XYZ-01""#).is_err());
+
+        let table = error_code_table();
+        assert_eq!(table.len(), ErrorCode::ALL.len());
+        assert_eq!(table[0].http_status, 503);
+    }
+}
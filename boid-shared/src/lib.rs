@@ -1,7 +1,43 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use boid_core::BoidConfig;
 use serde::{Deserialize, Serialize};
 
+pub mod colors;
+pub mod detection;
+pub mod dirty_tiles;
+pub mod error_code;
+pub mod stereo;
+pub mod style;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod trails;
+pub mod udp;
+
+#[cfg(feature = "std")]
+pub mod arbitration;
+#[cfg(feature = "std")]
+pub mod demo;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod mirror;
+#[cfg(feature = "std")]
+pub mod transport;
+#[cfg(feature = "std")]
+pub mod websocket;
+
+#[cfg(feature = "std")]
+pub use arbitration::{ArbitrationConfig, ArbitrationPolicy, TargetArbiter};
+pub use colors::ColorScheme;
+pub use detection::{Frame, HandDetectorBackend, PixelFormat};
+pub use error_code::ErrorCode;
+#[cfg(feature = "std")]
+pub use error_code::{error_code_table, ErrorBody, ErrorCodeInfo};
+pub use style::RenderStyle;
+
 /// Represents a 2D position in screen coordinates
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct Position {
@@ -23,10 +59,14 @@ impl Position {
 }
 
 /// Hand landmark data from tracking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct HandLandmarks {
     pub thumb_tip: Position,
     pub index_tip: Position,
+    /// Distance from the cameras from 0 (near) to 1 (far), when a stereo
+    /// pair measured it; see [`stereo`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<f32>,
 }
 
 impl HandLandmarks {
@@ -34,6 +74,33 @@ impl HandLandmarks {
         Self {
             thumb_tip,
             index_tip,
+            depth: None,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: Option<f32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Positions divided by the frame size, so they run from 0 to 1 across
+    /// the frame whatever its resolution
+    pub fn normalized(&self, width: f32, height: f32) -> Self {
+        self.scaled(1.0 / width, 1.0 / height)
+    }
+
+    /// Normalized positions stretched over a `width` x `height` space, such
+    /// as the canvas the video is drawn on
+    pub fn denormalized(&self, width: f32, height: f32) -> Self {
+        self.scaled(width, height)
+    }
+
+    fn scaled(&self, x: f32, y: f32) -> Self {
+        let scale = |p: Position| Position::new(p.x * x, p.y * y);
+        Self {
+            thumb_tip: scale(self.thumb_tip),
+            index_tip: scale(self.index_tip),
+            depth: self.depth,
         }
     }
 
@@ -43,6 +110,607 @@ impl HandLandmarks {
     }
 }
 
+/// Which hand a set of landmarks belongs to, from the user's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+impl Handedness {
+    /// Accepts `"left"`/`"right"` in any case, so MediaPipe's
+    /// `"Left"`/`"Right"` category names work as-is
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("left") {
+            Some(Self::Left)
+        } else if name.eq_ignore_ascii_case("right") {
+            Some(Self::Right)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
+}
+
+/// Landmarks of one hand when several are tracked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedHand {
+    pub handedness: Handedness,
+    pub landmarks: HandLandmarks,
+}
+
+/// Every hand found in one frame, at most one per handedness
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiHandLandmarks {
+    pub hands: Vec<TrackedHand>,
+}
+
+#[cfg(feature = "std")]
+impl MultiHandLandmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hand, replacing an earlier one with the same handedness
+    pub fn insert(&mut self, hand: TrackedHand) {
+        self.hands.retain(|h| h.handedness != hand.handedness);
+        self.hands.push(hand);
+    }
+
+    pub fn get(&self, handedness: Handedness) -> Option<&HandLandmarks> {
+        self.hands
+            .iter()
+            .find(|h| h.handedness == handedness)
+            .map(|h| &h.landmarks)
+    }
+
+    /// The hand single-hand consumers should follow: the right one if
+    /// present, otherwise whichever was found
+    pub fn primary(&self) -> Option<&HandLandmarks> {
+        self.get(Handedness::Right)
+            .or_else(|| self.hands.first().map(|h| &h.landmarks))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hands.is_empty()
+    }
+}
+
+/// Number of points in a MediaPipe hand
+pub const HAND_LANDMARK_COUNT: usize = 21;
+
+/// Visibility below which a landmark is treated as unknown
+const MIN_VISIBILITY: f32 = 0.5;
+
+/// A finger of the MediaPipe hand model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finger {
+    Thumb,
+    Index,
+    Middle,
+    Ring,
+    Pinky,
+}
+
+impl Finger {
+    pub const ALL: [Finger; 5] = [
+        Finger::Thumb,
+        Finger::Index,
+        Finger::Middle,
+        Finger::Ring,
+        Finger::Pinky,
+    ];
+
+    /// Index of the joint nearest the palm (CMC for the thumb, MCP otherwise)
+    pub fn base(self) -> usize {
+        1 + 4 * self as usize
+    }
+
+    /// Index of the middle joint (MCP for the thumb, PIP otherwise)
+    pub fn middle_joint(self) -> usize {
+        self.base() + 1
+    }
+
+    pub fn tip(self) -> usize {
+        self.base() + 3
+    }
+}
+
+/// Hand pose recognized from a full landmark set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandGesture {
+    /// All four fingers curled
+    Fist,
+    /// All four fingers extended
+    OpenPalm,
+    /// Only the index finger extended
+    Pointing,
+}
+
+impl HandGesture {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fist => "fist",
+            Self::OpenPalm => "open_palm",
+            Self::Pointing => "pointing",
+        }
+    }
+}
+
+/// All 21 landmarks of a hand, in MediaPipe order: wrist, then four joints
+/// per finger from the palm outwards (thumb, index, middle, ring, pinky)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FullHandLandmarks {
+    pub points: [Position; HAND_LANDMARK_COUNT],
+    /// Per-point confidence in [0, 1], if the tracker reports it
+    #[serde(default)]
+    pub visibility: Option<[f32; HAND_LANDMARK_COUNT]>,
+}
+
+impl FullHandLandmarks {
+    pub const WRIST: usize = 0;
+
+    pub fn new(points: [Position; HAND_LANDMARK_COUNT]) -> Self {
+        Self {
+            points,
+            visibility: None,
+        }
+    }
+
+    /// Build from interleaved `x, y` coordinates (42 values)
+    pub fn from_xy(coords: &[f32]) -> Option<Self> {
+        if coords.len() != 2 * HAND_LANDMARK_COUNT {
+            return None;
+        }
+        let mut points = [Position::new(0.0, 0.0); HAND_LANDMARK_COUNT];
+        for (point, xy) in points.iter_mut().zip(coords.chunks_exact(2)) {
+            *point = Position::new(xy[0], xy[1]);
+        }
+        Some(Self::new(points))
+    }
+
+    pub fn with_visibility(mut self, visibility: [f32; HAND_LANDMARK_COUNT]) -> Self {
+        self.visibility = Some(visibility);
+        self
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.visibility
+            .is_none_or(|visibility| visibility[index] >= MIN_VISIBILITY)
+    }
+
+    /// The thumb and index tips used for pinch control
+    pub fn to_hand_landmarks(&self) -> HandLandmarks {
+        HandLandmarks::new(
+            self.points[Finger::Thumb.tip()],
+            self.points[Finger::Index.tip()],
+        )
+    }
+
+    /// Mean of the wrist and the four finger knuckles
+    pub fn palm_center(&self) -> Position {
+        let palm = [Self::WRIST, 5, 9, 13, 17];
+        let (x, y) = palm.iter().fold((0.0, 0.0), |(x, y), &i| {
+            (x + self.points[i].x, y + self.points[i].y)
+        });
+        Position::new(x / palm.len() as f32, y / palm.len() as f32)
+    }
+
+    /// A finger counts as extended when its tip is farther from the wrist
+    /// than its middle joint. `None` if any of those points is not visible.
+    pub fn is_extended(&self, finger: Finger) -> Option<bool> {
+        let (joint, tip) = (finger.middle_joint(), finger.tip());
+        if ![Self::WRIST, joint, tip]
+            .iter()
+            .all(|&i| self.is_visible(i))
+        {
+            return None;
+        }
+        let wrist = &self.points[Self::WRIST];
+        Some(wrist.distance_to(&self.points[tip]) > wrist.distance_to(&self.points[joint]))
+    }
+
+    /// Gesture formed by the four fingers; the thumb is ignored since it
+    /// stays ambiguous in most poses
+    pub fn gesture(&self) -> Option<HandGesture> {
+        let mut extended = [false; 4];
+        for (state, finger) in extended.iter_mut().zip(&Finger::ALL[1..]) {
+            *state = self.is_extended(*finger)?;
+        }
+        match extended {
+            [false, false, false, false] => Some(HandGesture::Fist),
+            [true, true, true, true] => Some(HandGesture::OpenPalm),
+            [true, false, false, false] => Some(HandGesture::Pointing),
+            _ => None,
+        }
+    }
+}
+
+/// Radial lens distortion coefficients for a camera
+///
+/// Uses the radial terms of the Brown-Conrady model: a point at normalized
+/// radius `r` from the frame center is imaged at `r * (1 + k1*r^2 + k2*r^4)`.
+/// Radii are normalized by half the frame diagonal, so the coefficients do
+/// not depend on the resolution the camera streams at. Negative `k1` is
+/// barrel distortion, typical of wide-angle modules. All zeros (the default)
+/// leaves positions untouched.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LensCalibration {
+    pub k1: f32,
+    pub k2: f32,
+}
+
+impl LensCalibration {
+    /// Fixed-point iterations used to invert the distortion model
+    const UNDISTORT_ITERATIONS: usize = 8;
+
+    pub fn new(k1: f32, k2: f32) -> Self {
+        Self { k1, k2 }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.k1 == 0.0 && self.k2 == 0.0
+    }
+
+    /// Whether both coefficients are finite numbers
+    pub fn is_valid(&self) -> bool {
+        self.k1.is_finite() && self.k2.is_finite()
+    }
+
+    fn scale(&self, radius_sq: f32) -> f32 {
+        1.0 + self.k1 * radius_sq + self.k2 * radius_sq * radius_sq
+    }
+
+    /// Where the camera images a point that is really at `position`, in a
+    /// `width` x `height` frame
+    pub fn distort(&self, position: Position, width: f32, height: f32) -> Position {
+        let (x, y, half_diagonal) = normalize(position, width, height);
+        let scale = self.scale(x * x + y * y);
+        denormalize(x * scale, y * scale, width, height, half_diagonal)
+    }
+
+    /// Where a point imaged at `position` in a `width` x `height` frame
+    /// really is
+    ///
+    /// The model has no closed-form inverse, so this iterates
+    /// `undistorted = distorted / scale(undistorted)`. If the coefficients
+    /// fold the frame over itself the input is returned unchanged.
+    pub fn undistort(&self, position: Position, width: f32, height: f32) -> Position {
+        if self.is_identity() || !self.is_valid() {
+            return position;
+        }
+
+        let (x, y, half_diagonal) = normalize(position, width, height);
+        let (mut ux, mut uy) = (x, y);
+        for _ in 0..Self::UNDISTORT_ITERATIONS {
+            let scale = self.scale(ux * ux + uy * uy);
+            if scale <= 0.0 || !scale.is_finite() {
+                return position;
+            }
+            ux = x / scale;
+            uy = y / scale;
+        }
+        denormalize(ux, uy, width, height, half_diagonal)
+    }
+
+    /// Undistort both fingertips of detected landmarks
+    pub fn undistort_landmarks(
+        &self,
+        landmarks: &HandLandmarks,
+        width: f32,
+        height: f32,
+    ) -> HandLandmarks {
+        HandLandmarks::new(
+            self.undistort(landmarks.thumb_tip, width, height),
+            self.undistort(landmarks.index_tip, width, height),
+        )
+        .with_depth(landmarks.depth)
+    }
+}
+
+/// Frame coordinates to center-relative units of half the frame diagonal
+fn normalize(position: Position, width: f32, height: f32) -> (f32, f32, f32) {
+    let half_diagonal = libm::sqrtf(width * width + height * height) / 2.0;
+    if half_diagonal <= 0.0 {
+        return (0.0, 0.0, 1.0);
+    }
+    (
+        (position.x - width / 2.0) / half_diagonal,
+        (position.y - height / 2.0) / half_diagonal,
+        half_diagonal,
+    )
+}
+
+fn denormalize(x: f32, y: f32, width: f32, height: f32, half_diagonal: f32) -> Position {
+    Position::new(
+        x * half_diagonal + width / 2.0,
+        y * half_diagonal + height / 2.0,
+    )
+}
+
+/// Finger distance below which a pinch starts, in pixels
+pub const PINCH_ENTER_THRESHOLD: f32 = 50.0;
+/// Finger distance above which a pinch ends, in pixels
+pub const PINCH_EXIT_THRESHOLD: f32 = 70.0;
+/// Consecutive frames required before the pinch state flips
+pub const PINCH_HOLD_FRAMES: u32 = 3;
+
+/// Pinch detector with hysteresis and debouncing
+///
+/// A pinch starts once the finger distance stays below `enter_threshold` for
+/// `min_hold_frames` consecutive frames, and ends once it stays above
+/// `exit_threshold` (or the hand is lost) for the same number of frames.
+/// Keeping `exit_threshold > enter_threshold` stops readings that jitter
+/// around a single threshold from toggling the target on and off.
+#[derive(Debug, Clone)]
+pub struct PinchStateMachine {
+    pub enter_threshold: f32,
+    pub exit_threshold: f32,
+    pub min_hold_frames: u32,
+    pinched: bool,
+    pending_frames: u32,
+}
+
+impl PinchStateMachine {
+    pub fn new(enter_threshold: f32, exit_threshold: f32, min_hold_frames: u32) -> Self {
+        Self {
+            enter_threshold,
+            exit_threshold,
+            min_hold_frames,
+            pinched: false,
+            pending_frames: 0,
+        }
+    }
+
+    /// Feed the pinch distance of the current frame (None if no hand is
+    /// visible) and return whether the gesture is considered a pinch
+    pub fn update(&mut self, distance: Option<f32>) -> bool {
+        let wants_change = match distance {
+            Some(d) if self.pinched => d > self.exit_threshold,
+            Some(d) => d < self.enter_threshold,
+            None => self.pinched,
+        };
+
+        if wants_change {
+            self.pending_frames += 1;
+            if self.pending_frames >= self.min_hold_frames {
+                self.pinched = !self.pinched;
+                self.pending_frames = 0;
+            }
+        } else {
+            self.pending_frames = 0;
+        }
+
+        self.pinched
+    }
+
+    pub fn is_pinched(&self) -> bool {
+        self.pinched
+    }
+
+    /// Forget the current gesture, e.g. when tracking is restarted
+    pub fn reset(&mut self) {
+        self.pinched = false;
+        self.pending_frames = 0;
+    }
+}
+
+impl Default for PinchStateMachine {
+    fn default() -> Self {
+        Self::new(
+            PINCH_ENTER_THRESHOLD,
+            PINCH_EXIT_THRESHOLD,
+            PINCH_HOLD_FRAMES,
+        )
+    }
+}
+
+/// How [`LandmarkSmoother`] filters each coordinate
+///
+/// In JSON: `{"mode":"exponential","alpha":0.5}` or
+/// `{"mode":"kalman","process_noise":0.5,"measurement_noise":8.0}`. The
+/// tagged format needs an allocator, so serde support requires `std`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(tag = "mode", rename_all = "snake_case"))]
+pub enum Smoothing {
+    /// Exponential moving average: `smoothed += alpha * (raw - smoothed)`.
+    /// `alpha` is in (0, 1]; lower is smoother but lags more, 1 disables it.
+    Exponential { alpha: f32 },
+    /// Kalman filter for a point that drifts randomly between frames.
+    /// A large `measurement_noise` relative to `process_noise` smooths more.
+    Kalman {
+        process_noise: f32,
+        measurement_noise: f32,
+    },
+}
+
+impl Smoothing {
+    /// Whether the parameters are in range: `alpha` in (0, 1], noises
+    /// finite with a positive measurement noise
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Smoothing::Exponential { alpha } => alpha > 0.0 && alpha <= 1.0,
+            Smoothing::Kalman {
+                process_noise,
+                measurement_noise,
+            } => {
+                process_noise.is_finite()
+                    && process_noise >= 0.0
+                    && measurement_noise.is_finite()
+                    && measurement_noise > 0.0
+            }
+        }
+    }
+}
+
+/// Filter state of one coordinate
+#[derive(Debug, Clone, Copy)]
+struct AxisFilter {
+    value: f32,
+    /// Kalman estimate variance; unused by the exponential filter
+    variance: f32,
+}
+
+impl AxisFilter {
+    fn new(value: f32, smoothing: Smoothing) -> Self {
+        let variance = match smoothing {
+            Smoothing::Exponential { .. } => 0.0,
+            Smoothing::Kalman {
+                measurement_noise, ..
+            } => measurement_noise,
+        };
+        Self { value, variance }
+    }
+
+    fn update(&mut self, raw: f32, smoothing: Smoothing) -> f32 {
+        match smoothing {
+            Smoothing::Exponential { alpha } => self.value += alpha * (raw - self.value),
+            Smoothing::Kalman {
+                process_noise,
+                measurement_noise,
+            } => {
+                self.variance += process_noise;
+                let gain = self.variance / (self.variance + measurement_noise);
+                self.value += gain * (raw - self.value);
+                self.variance *= 1.0 - gain;
+            }
+        }
+        self.value
+    }
+}
+
+/// Temporal smoothing for hand landmarks from any detector
+///
+/// Each of the 21 landmark slots is filtered independently; `HandLandmarks`
+/// use the thumb and index tip slots, so one smoother serves either kind of
+/// input. The first reading after [`reset`](Self::reset) passes through
+/// unchanged. Reset when the hand is lost, so a hand reappearing elsewhere
+/// does not glide in from where the last one left.
+#[derive(Debug, Clone)]
+pub struct LandmarkSmoother {
+    smoothing: Smoothing,
+    points: [Option<(AxisFilter, AxisFilter)>; HAND_LANDMARK_COUNT],
+}
+
+impl LandmarkSmoother {
+    /// Exponential moving average with weight `alpha` for new readings,
+    /// clamped to (0, 1]
+    pub fn new(alpha: f32) -> Self {
+        let alpha = if alpha.is_finite() {
+            alpha.clamp(f32::EPSILON, 1.0)
+        } else {
+            1.0
+        };
+        Self::with_smoothing(Smoothing::Exponential { alpha })
+    }
+
+    pub fn kalman(process_noise: f32, measurement_noise: f32) -> Self {
+        Self::with_smoothing(Smoothing::Kalman {
+            process_noise: process_noise.max(0.0),
+            measurement_noise: measurement_noise.max(f32::EPSILON),
+        })
+    }
+
+    /// Passes readings through unchanged
+    pub fn disabled() -> Self {
+        Self::new(1.0)
+    }
+
+    pub fn with_smoothing(smoothing: Smoothing) -> Self {
+        Self {
+            smoothing,
+            points: [None; HAND_LANDMARK_COUNT],
+        }
+    }
+
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    /// Switch filters; starts over from the next reading
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.smoothing = smoothing;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.points = [None; HAND_LANDMARK_COUNT];
+    }
+
+    /// Smooth one landmark, `landmark` being its MediaPipe index
+    pub fn smooth_point(&mut self, landmark: usize, position: Position) -> Position {
+        let smoothing = self.smoothing;
+        let Some(slot) = self.points.get_mut(landmark) else {
+            return position;
+        };
+        match slot {
+            Some((x, y)) => Position::new(
+                x.update(position.x, smoothing),
+                y.update(position.y, smoothing),
+            ),
+            None => {
+                *slot = Some((
+                    AxisFilter::new(position.x, smoothing),
+                    AxisFilter::new(position.y, smoothing),
+                ));
+                position
+            }
+        }
+    }
+
+    pub fn smooth(&mut self, landmarks: &HandLandmarks) -> HandLandmarks {
+        HandLandmarks::new(
+            self.smooth_point(Finger::Thumb.tip(), landmarks.thumb_tip),
+            self.smooth_point(Finger::Index.tip(), landmarks.index_tip),
+        )
+        .with_depth(landmarks.depth)
+    }
+
+    pub fn smooth_full(&mut self, landmarks: &FullHandLandmarks) -> FullHandLandmarks {
+        let mut smoothed = landmarks.clone();
+        for (i, point) in smoothed.points.iter_mut().enumerate() {
+            *point = self.smooth_point(i, *point);
+        }
+        smoothed
+    }
+}
+
+impl Default for LandmarkSmoother {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+/// Hand depth from a stereo camera pair, posted to `/api/depth`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DepthUpdate {
+    /// 0 (near) to 1 (far); `None` when the hand is lost
+    pub depth: Option<f32>,
+}
+
+/// Multipliers a client's input mappings put on the flock's parameters,
+/// posted to `/api/scales`, e.g. `{"scales":{"separation_weight":2.5}}`
+///
+/// Keys are `BoidConfig` field names; parameters left out stay as set. The
+/// device applies them on top of its settings without saving them, so an
+/// empty map returns the flock to its settings exactly.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ParameterScalesUpdate {
+    pub scales: std::collections::BTreeMap<String, f32>,
+}
+
 /// Update message sent from client to ESP32 to control boid target position
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TargetPositionUpdate {
@@ -51,7 +719,7 @@ pub struct TargetPositionUpdate {
 }
 
 /// Boid simulation configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BoidSettings {
     pub separation_weight: f32,
     pub alignment_weight: f32,
@@ -59,6 +727,11 @@ pub struct BoidSettings {
     pub max_speed: f32,
     pub max_force: f32,
     pub seek_weight: f32,
+    /// Radius around the target within which boids seek it (0 = unlimited).
+    /// Left out, the receiver keeps its own, which differs between the
+    /// firmware and the other frontends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seek_radius: Option<f32>,
 }
 
 impl Default for BoidSettings {
@@ -70,22 +743,397 @@ impl Default for BoidSettings {
             max_speed: 2.0,
             max_force: 0.05,
             seek_weight: 8.0,
+            seek_radius: None,
         }
     }
 }
 
+/// Inclusive `(min, max)` ranges accepted for each `BoidSettings` field
+pub mod limits {
+    pub const SEPARATION_WEIGHT: (f32, f32) = (0.0, 10.0);
+    pub const ALIGNMENT_WEIGHT: (f32, f32) = (0.0, 10.0);
+    pub const COHESION_WEIGHT: (f32, f32) = (0.0, 10.0);
+    pub const MAX_SPEED: (f32, f32) = (0.1, 20.0);
+    pub const MAX_FORCE: (f32, f32) = (0.001, 2.0);
+    pub const SEEK_WEIGHT: (f32, f32) = (0.0, 50.0);
+    pub const SEEK_RADIUS: (f32, f32) = (0.0, 2000.0);
+    /// Multiplier a `ParameterScalesUpdate` may put on one parameter
+    pub const PARAMETER_SCALE: (f32, f32) = (0.0, 10.0);
+    /// Longest preset crossfade, in ticks (10 s at 30 FPS)
+    pub const PRESET_TICKS_MAX: u32 = 300;
+    /// Longest text the ESP32 spells; its small flock gets unreadable
+    /// beyond a few letters anyway
+    pub const SPELL_TEXT_MAX_CHARS: usize = 12;
+    /// Strongest wind or gust on each axis, in velocity change per tick
+    pub const WIND_FORCE_MAX: f32 = 1.0;
+    /// Most commands in one `/api/batch` request
+    pub const BATCH_COMMANDS_MAX: usize = 16;
+    /// Longest client id kept for target arbitration; longer ones are cut
+    pub const CLIENT_ID_MAX_CHARS: usize = 32;
+    /// Boid size relative to the renderer's usual size, see
+    /// [`RenderStyle`](crate::RenderStyle)
+    pub const BOID_SIZE: (f32, f32) = (0.25, 4.0);
+}
+
+/// A settings field that cannot be clamped because it is NaN or infinite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidSetting {
+    pub field: &'static str,
+}
+
+impl BoidSettings {
+    /// Clamp every field into its range from [`limits`].
+    ///
+    /// `on_adjust(field, requested, applied)` is called for each field that
+    /// had to be changed. Non-finite values are rejected outright and leave
+    /// the settings untouched.
+    pub fn clamp_to_limits(
+        &mut self,
+        mut on_adjust: impl FnMut(&'static str, f32, f32),
+    ) -> Result<(), InvalidSetting> {
+        let mut fields = [
+            (
+                "separation_weight",
+                &mut self.separation_weight,
+                limits::SEPARATION_WEIGHT,
+            ),
+            (
+                "alignment_weight",
+                &mut self.alignment_weight,
+                limits::ALIGNMENT_WEIGHT,
+            ),
+            (
+                "cohesion_weight",
+                &mut self.cohesion_weight,
+                limits::COHESION_WEIGHT,
+            ),
+            ("max_speed", &mut self.max_speed, limits::MAX_SPEED),
+            ("max_force", &mut self.max_force, limits::MAX_FORCE),
+            ("seek_weight", &mut self.seek_weight, limits::SEEK_WEIGHT),
+        ];
+        let mut seek_radius = self
+            .seek_radius
+            .as_mut()
+            .map(|radius| ("seek_radius", radius, limits::SEEK_RADIUS));
+
+        if let Some((field, _, _)) = fields
+            .iter()
+            .chain(&seek_radius)
+            .find(|(_, value, _)| !value.is_finite())
+        {
+            return Err(InvalidSetting { field });
+        }
+
+        for (field, value, (min, max)) in fields.iter_mut().chain(&mut seek_radius) {
+            let clamped = value.clamp(*min, *max);
+            if clamped != **value {
+                on_adjust(field, **value, clamped);
+                **value = clamped;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `config` with the fields clients can set replaced by these
+    pub fn apply_to(&self, config: BoidConfig) -> BoidConfig {
+        BoidConfig {
+            separation_weight: self.separation_weight,
+            alignment_weight: self.alignment_weight,
+            cohesion_weight: self.cohesion_weight,
+            max_speed: self.max_speed,
+            max_force: self.max_force,
+            seek_weight: self.seek_weight,
+            seek_radius: self.seek_radius.unwrap_or(config.seek_radius),
+            ..config
+        }
+    }
+}
+
+/// The fields of a config clients can set, the inverse of
+/// [`BoidSettings::apply_to`]
+impl From<&BoidConfig> for BoidSettings {
+    fn from(config: &BoidConfig) -> Self {
+        Self {
+            separation_weight: config.separation_weight,
+            alignment_weight: config.alignment_weight,
+            cohesion_weight: config.cohesion_weight,
+            max_speed: config.max_speed,
+            max_force: config.max_force,
+            seek_weight: config.seek_weight,
+            seek_radius: Some(config.seek_radius),
+        }
+    }
+}
+
+/// A settings field that was clamped into range by the server
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingAdjustment {
+    pub field: String,
+    pub requested: f32,
+    pub applied: f32,
+}
+
+/// Response to a settings update, listing any fields the server adjusted
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsUpdateResponse {
+    pub status: String,
+    pub adjusted: Vec<SettingAdjustment>,
+}
+
+/// One entry of the server's configuration change log
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigChangeEntry {
+    pub field: String,
+    /// "setter" or "api"
+    pub source: String,
+    pub timestamp_ms: u64,
+}
+
+/// Configuration change log, oldest first
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigHistoryResponse {
+    pub changes: Vec<ConfigChangeEntry>,
+}
+
 /// Settings update message sent from client to ESP32
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsUpdate {
     pub settings: BoidSettings,
+    /// Flock size; servers clamp it to the boids they can hold and keep
+    /// their current size when it is omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boid_count: Option<u32>,
+}
+
+/// Flock size posted to `/api/boids`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BoidCountUpdate {
+    pub count: u32,
+}
+
+/// Reply to `/api/boids`: the size the flock is resized to, after clamping
+/// to between 1 and `capacity`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BoidCountResponse {
+    pub boid_count: usize,
+    pub capacity: usize,
+}
+
+/// Wind posted to `/api/wind`: a steady push on every boid plus gusts
+///
+/// In JSON: `{"force_x":0.05,"force_y":0.0,"gust":{"kind":"sine",
+/// "amplitude_x":0.1,"amplitude_y":0.0,"period":4.0}}`; `gust` defaults to
+/// calm. Needs `std`, like [`Smoothing`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct WindUpdate {
+    pub force_x: f32,
+    pub force_y: f32,
+    #[cfg_attr(feature = "std", serde(default))]
+    pub gust: GustSettings,
+}
+
+/// Time-varying part of a [`WindUpdate`]; periods are in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum GustSettings {
+    #[default]
+    Calm,
+    /// Swells and turns back every `period` seconds
+    Sine {
+        amplitude_x: f32,
+        amplitude_y: f32,
+        period: f32,
+    },
+    /// Changes course about every `period` seconds, repeatably for a `seed`
+    Noise {
+        amplitude_x: f32,
+        amplitude_y: f32,
+        period: f32,
+        #[cfg_attr(feature = "std", serde(default))]
+        seed: u32,
+    },
+}
+
+impl WindUpdate {
+    /// Clamp forces and amplitudes to `limits::WIND_FORCE_MAX`. Non-finite
+    /// values and periods that are not positive are rejected.
+    pub fn clamp_to_limits(&mut self) -> Result<(), InvalidSetting> {
+        let clamp = |value: &mut f32, field: &'static str| {
+            if !value.is_finite() {
+                return Err(InvalidSetting { field });
+            }
+            *value = value.clamp(-limits::WIND_FORCE_MAX, limits::WIND_FORCE_MAX);
+            Ok(())
+        };
+        clamp(&mut self.force_x, "force_x")?;
+        clamp(&mut self.force_y, "force_y")?;
+        match &mut self.gust {
+            GustSettings::Calm => Ok(()),
+            GustSettings::Sine {
+                amplitude_x,
+                amplitude_y,
+                period,
+            }
+            | GustSettings::Noise {
+                amplitude_x,
+                amplitude_y,
+                period,
+                ..
+            } => {
+                clamp(amplitude_x, "gust.amplitude_x")?;
+                clamp(amplitude_y, "gust.amplitude_y")?;
+                if period.is_finite() && *period > 0.0 {
+                    Ok(())
+                } else {
+                    Err(InvalidSetting {
+                        field: "gust.period",
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Color scheme sent from client to ESP32, see [`colors`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColorSchemeUpdate {
+    pub scheme: ColorScheme,
+}
+
+/// Preset sent from client to ESP32, crossfaded in over `duration_ticks`
+/// simulation ticks instead of applied at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetUpdate {
+    pub settings: BoidSettings,
+    /// `0` applies the preset immediately; longer values are capped at
+    /// `limits::PRESET_TICKS_MAX`
+    #[serde(default)]
+    pub duration_ticks: u32,
+}
+
+/// Message streamed from client to ESP32 over the `/ws` control channel
+///
+/// Tagged by `type`, e.g. `{"type":"position","position":{"x":1.0,"y":2.0}}`
+/// or `{"type":"settings","settings":{...}}`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    Position(TargetPositionUpdate),
+    Settings(SettingsUpdate),
+    Depth(DepthUpdate),
+    Scales(ParameterScalesUpdate),
+}
+
+/// One command of a `/api/batch` request, tagged by `type` like
+/// [`ControlMessage`], e.g. `{"type":"boids","count":12}`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchCommand {
+    Position(TargetPositionUpdate),
+    Settings(SettingsUpdate),
+    Preset(PresetUpdate),
+    Boids(BoidCountUpdate),
+    Wind(WindUpdate),
+    Colors(ColorSchemeUpdate),
+}
+
+/// Reply to `/api/batch`. Either every command is applied, in order and
+/// within one simulation tick, or none is; a rejected batch also carries
+/// an [`ErrorBody`] at the top level.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchResponse {
+    pub applied: bool,
+    /// One per command, in request order
+    pub results: Vec<BatchResult>,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+/// Outcome of one batch command
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BatchResult {
+    /// Why this command was rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+    /// Values the server clamped into range
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adjusted: Vec<SettingAdjustment>,
 }
 
 /// Status response from ESP32
+#[cfg(feature = "std")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusResponse {
     pub boid_count: usize,
     pub fps: u32,
     pub target_active: bool,
+    /// Clients whose targets the flock follows, newest first; see
+    /// [`arbitration`]. Empty from firmware without arbitration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub controllers: Vec<String>,
+}
+
+#[cfg(feature = "std")]
+impl StatusResponse {
+    /// Whether clients other than `client` steer the flock, so a UI can
+    /// tell its user someone else is in control
+    pub fn controlled_by_other(&self, client: &str) -> bool {
+        !self.controllers.is_empty() && !self.controllers.iter().any(|id| id == client)
+    }
+}
+
+/// Text for the ESP32's flock to spell out, posted to `/api/spell`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpellRequest {
+    /// At most `limits::SPELL_TEXT_MAX_CHARS` characters
+    pub text: String,
+    /// Seconds to hold the text before the flock disperses; `0` holds it
+    /// until `/api/spell/clear`
+    #[serde(default)]
+    pub hold_secs: u32,
+}
+
+/// Camera power state returned by the ESP32's `/api/stream/start` and
+/// `/api/stream/stop`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamPowerResponse {
+    pub powered: bool,
+    /// Seconds without a streamed frame before the camera powers down again
+    pub idle_timeout_secs: u64,
+}
+
+/// Cached boot-time self-test results served by the ESP32 health endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HealthResponse {
+    pub display_ok: bool,
+    pub camera_ok: bool,
+    /// Signal strength of the connected access point in dBm (None if unavailable)
+    pub wifi_rssi: Option<i8>,
+    pub psram_present: bool,
+    pub nvs_ok: bool,
+}
+
+impl HealthResponse {
+    /// Whether every hardware check passed
+    pub fn is_healthy(&self) -> bool {
+        self.display_ok
+            && self.camera_ok
+            && self.wifi_rssi.is_some()
+            && self.psram_present
+            && self.nvs_ok
+    }
 }
 
 #[cfg(test)]
@@ -99,9 +1147,448 @@ mod tests {
         assert_eq!(p1.distance_to(&p2), 5.0);
     }
 
+    #[test]
+    fn test_frame_needs_every_pixel() {
+        let data = [0u8; 12];
+        let frame = Frame::new(2, 2, &data, PixelFormat::Bgr).unwrap();
+        assert_eq!((frame.width, frame.height), (2, 2));
+        assert!(Frame::new(2, 2, &data, PixelFormat::Rgba).is_none());
+        assert!(Frame::new(usize::MAX, 2, &data, PixelFormat::Bgr).is_none());
+    }
+
     #[test]
     fn test_pinch_distance() {
         let landmarks = HandLandmarks::new(Position::new(0.0, 0.0), Position::new(30.0, 40.0));
         assert_eq!(landmarks.pinch_distance(), 50.0);
     }
+
+    #[test]
+    fn test_normalized_landmarks_map_between_resolutions() {
+        let landmarks = HandLandmarks::new(Position::new(160.0, 60.0), Position::new(320.0, 240.0))
+            .with_depth(Some(0.5));
+        let normalized = landmarks.normalized(640.0, 480.0);
+        assert_eq!(normalized.thumb_tip, Position::new(0.25, 0.125));
+        assert_eq!(normalized.index_tip, Position::new(0.5, 0.5));
+        assert_eq!(normalized.depth, Some(0.5));
+
+        let canvas = normalized.denormalized(800.0, 600.0);
+        assert_eq!(canvas.thumb_tip, Position::new(200.0, 75.0));
+        assert_eq!(canvas.index_tip, Position::new(400.0, 300.0));
+    }
+
+    /// Hand pointing up from a wrist at (0, 100)
+    fn full_hand(extended: [bool; 5]) -> FullHandLandmarks {
+        test_util::full_hand(Position::new(0.0, 100.0), extended)
+    }
+
+    #[test]
+    fn test_full_hand_gestures() {
+        assert_eq!(full_hand([false; 5]).gesture(), Some(HandGesture::Fist));
+        // The thumb does not matter
+        assert_eq!(
+            full_hand([true, false, false, false, false]).gesture(),
+            Some(HandGesture::Fist)
+        );
+        assert_eq!(full_hand([true; 5]).gesture(), Some(HandGesture::OpenPalm));
+        assert_eq!(
+            full_hand([false, true, false, false, false]).gesture(),
+            Some(HandGesture::Pointing)
+        );
+        assert_eq!(full_hand([false, true, true, false, false]).gesture(), None);
+
+        let hand = full_hand([true; 5]);
+        let tips = hand.to_hand_landmarks();
+        assert_eq!(tips.thumb_tip, hand.points[4]);
+        assert_eq!(tips.index_tip, hand.points[8]);
+        assert_eq!(hand.palm_center(), Position::new(4.0, 84.0));
+    }
+
+    #[test]
+    fn test_full_hand_visibility_and_parsing() {
+        let mut visibility = [1.0; HAND_LANDMARK_COUNT];
+        visibility[Finger::Pinky.tip()] = 0.1;
+        let hand = full_hand([true; 5]).with_visibility(visibility);
+        assert_eq!(hand.is_extended(Finger::Index), Some(true));
+        assert_eq!(hand.is_extended(Finger::Pinky), None);
+        assert_eq!(hand.gesture(), None);
+
+        let coords: Vec<f32> = (0..42).map(|i| i as f32).collect();
+        let parsed = FullHandLandmarks::from_xy(&coords).unwrap();
+        assert_eq!(parsed.points[20], Position::new(40.0, 41.0));
+        assert!(FullHandLandmarks::from_xy(&coords[..40]).is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_multi_hand_landmarks() {
+        let hand = |handedness, x| TrackedHand {
+            handedness,
+            landmarks: HandLandmarks::new(Position::new(x, 0.0), Position::new(x, 10.0)),
+        };
+        let mut hands = MultiHandLandmarks::new();
+        assert!(hands.primary().is_none());
+
+        hands.insert(hand(Handedness::Left, 1.0));
+        assert_eq!(hands.primary().unwrap().thumb_tip.x, 1.0);
+        hands.insert(hand(Handedness::Right, 2.0));
+        hands.insert(hand(Handedness::Left, 3.0));
+        assert_eq!(hands.hands.len(), 2);
+        assert_eq!(hands.primary().unwrap().thumb_tip.x, 2.0);
+        assert_eq!(hands.get(Handedness::Left).unwrap().thumb_tip.x, 3.0);
+
+        assert_eq!(Handedness::parse("Left"), Some(Handedness::Left));
+        assert_eq!(Handedness::parse("both"), None);
+        let json = serde_json::to_string(&hands.hands[0]).unwrap();
+        assert!(json.starts_with(r#"{"handedness":"right""#));
+    }
+
+    #[test]
+    fn test_health_requires_all_checks() {
+        let mut health = HealthResponse {
+            display_ok: true,
+            camera_ok: true,
+            wifi_rssi: Some(-60),
+            psram_present: true,
+            nvs_ok: true,
+        };
+        assert!(health.is_healthy());
+
+        health.camera_ok = false;
+        assert!(!health.is_healthy());
+    }
+
+    #[test]
+    fn test_pinch_requires_hold_frames() {
+        let mut pinch = PinchStateMachine::new(50.0, 70.0, 3);
+        assert!(!pinch.update(Some(40.0)));
+        assert!(!pinch.update(Some(40.0)));
+        assert!(pinch.update(Some(40.0)));
+    }
+
+    #[test]
+    fn test_pinch_ignores_jitter_around_threshold() {
+        let mut pinch = PinchStateMachine::new(50.0, 70.0, 2);
+        pinch.update(Some(40.0));
+        assert!(pinch.update(Some(40.0)));
+
+        // Readings between the thresholds, or single spikes above, keep the pinch
+        for distance in [55.0, 75.0, 60.0, 49.0, 80.0, 65.0] {
+            assert!(pinch.update(Some(distance)));
+        }
+
+        assert!(pinch.update(Some(80.0)));
+        assert!(!pinch.update(Some(80.0)));
+    }
+
+    #[test]
+    fn test_pinch_released_when_hand_lost() {
+        let mut pinch = PinchStateMachine::new(50.0, 70.0, 1);
+        assert!(pinch.update(Some(10.0)));
+        assert!(!pinch.update(None));
+    }
+
+    #[test]
+    fn test_smoother_damps_jitter() {
+        let mut smoother = LandmarkSmoother::new(0.5);
+        let first = HandLandmarks::new(Position::new(0.0, 0.0), Position::new(100.0, 100.0));
+        assert_eq!(smoother.smooth(&first), first);
+
+        let jumped = HandLandmarks::new(Position::new(10.0, 0.0), Position::new(100.0, 120.0));
+        let smoothed = smoother.smooth(&jumped);
+        assert_eq!(smoothed.thumb_tip, Position::new(5.0, 0.0));
+        assert_eq!(smoothed.index_tip, Position::new(100.0, 110.0));
+
+        // After a reset the next reading is taken as is
+        smoother.reset();
+        assert_eq!(smoother.smooth(&jumped), jumped);
+
+        // The Kalman filter converges on a steady reading
+        let mut kalman = LandmarkSmoother::kalman(0.01, 4.0);
+        kalman.smooth_point(0, Position::new(0.0, 0.0));
+        let mut point = Position::new(0.0, 0.0);
+        for _ in 0..200 {
+            point = kalman.smooth_point(0, Position::new(50.0, -50.0));
+        }
+        assert!((point.x - 50.0).abs() < 1.0 && (point.y + 50.0).abs() < 1.0);
+        let step = kalman.smooth_point(0, Position::new(60.0, -50.0));
+        assert!(step.x > 50.0 && step.x < 55.0);
+
+        // Disabled passes readings through
+        let mut full = FullHandLandmarks::new([Position::new(1.0, 1.0); HAND_LANDMARK_COUNT]);
+        let mut off = LandmarkSmoother::disabled();
+        off.smooth_full(&full);
+        full.points[FullHandLandmarks::WRIST] = Position::new(9.0, 9.0);
+        assert_eq!(off.smooth_full(&full), full);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_smoothing_json() {
+        let json = r#"{"mode":"kalman","process_noise":0.5,"measurement_noise":8.0}"#;
+        let parsed: Smoothing = serde_json::from_str(json).unwrap();
+        assert!(parsed.is_valid());
+        assert_eq!(parsed, LandmarkSmoother::kalman(0.5, 8.0).smoothing());
+
+        let parsed: Smoothing =
+            serde_json::from_str(r#"{"mode":"exponential","alpha":0.0}"#).unwrap();
+        assert!(!parsed.is_valid());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wind_update_json() {
+        let json = r#"{"force_x":3.0,"force_y":0.0,"gust":{"kind":"noise","amplitude_x":0.1,"amplitude_y":0.2,"period":4.0}}"#;
+        let mut wind: WindUpdate = serde_json::from_str(json).unwrap();
+        wind.clamp_to_limits().unwrap();
+        assert_eq!(wind.force_x, limits::WIND_FORCE_MAX);
+        assert_eq!(
+            wind.gust,
+            GustSettings::Noise {
+                amplitude_x: 0.1,
+                amplitude_y: 0.2,
+                period: 4.0,
+                seed: 0
+            }
+        );
+
+        let mut calm: WindUpdate =
+            serde_json::from_str(r#"{"force_x":0.0,"force_y":0.1}"#).unwrap();
+        assert_eq!(calm.gust, GustSettings::Calm);
+        calm.gust = GustSettings::Sine {
+            amplitude_x: 0.1,
+            amplitude_y: 0.0,
+            period: 0.0,
+        };
+        assert_eq!(
+            calm.clamp_to_limits(),
+            Err(InvalidSetting {
+                field: "gust.period"
+            })
+        );
+    }
+
+    #[test]
+    fn test_settings_within_limits_untouched() {
+        let mut settings = BoidSettings::default();
+        let mut adjusted = 0;
+        settings.clamp_to_limits(|_, _, _| adjusted += 1).unwrap();
+        assert_eq!(adjusted, 0);
+    }
+
+    #[test]
+    fn test_settings_clamped_and_reported() {
+        let mut settings = BoidSettings {
+            max_speed: 1e9,
+            cohesion_weight: -3.0,
+            seek_radius: Some(5000.0),
+            ..BoidSettings::default()
+        };
+
+        let mut adjusted = Vec::new();
+        settings
+            .clamp_to_limits(|field, requested, applied| adjusted.push((field, requested, applied)))
+            .unwrap();
+
+        assert_eq!(settings.max_speed, limits::MAX_SPEED.1);
+        assert_eq!(settings.cohesion_weight, limits::COHESION_WEIGHT.0);
+        assert_eq!(settings.seek_radius, Some(limits::SEEK_RADIUS.1));
+        assert_eq!(
+            adjusted,
+            vec![
+                ("cohesion_weight", -3.0, limits::COHESION_WEIGHT.0),
+                ("max_speed", 1e9, limits::MAX_SPEED.1),
+                ("seek_radius", 5000.0, limits::SEEK_RADIUS.1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_finite_settings_rejected() {
+        let mut settings = BoidSettings {
+            max_force: f32::NAN,
+            max_speed: 1e9,
+            ..BoidSettings::default()
+        };
+
+        let result = settings.clamp_to_limits(|_, _, _| {});
+        assert_eq!(result, Err(InvalidSetting { field: "max_force" }));
+        // Nothing is applied when the update is rejected
+        assert_eq!(settings.max_speed, 1e9);
+    }
+
+    #[test]
+    fn test_settings_apply_to_config_and_back() {
+        let settings = BoidSettings {
+            separation_weight: 2.0,
+            max_speed: 3.5,
+            seek_radius: Some(80.0),
+            ..BoidSettings::default()
+        };
+        let base = BoidConfig {
+            flee_radius: 50.0,
+            seek_radius: 100.0,
+            ..BoidConfig::default()
+        };
+
+        let config = settings.apply_to(base);
+        assert_eq!(config.max_speed, 3.5);
+        assert_eq!(config.seek_radius, 80.0);
+        // Fields clients cannot set stay as they were
+        assert_eq!(config.flee_radius, 50.0);
+        assert_eq!(BoidSettings::from(&config), settings);
+
+        // Without a seek radius the receiver keeps its own
+        let config = BoidSettings::default().apply_to(base);
+        assert_eq!(config.seek_radius, 100.0);
+    }
+
+    #[test]
+    fn test_lens_calibration_identity() {
+        let calibration = LensCalibration::default();
+        assert!(calibration.is_identity());
+        let position = Position::new(12.5, 200.0);
+        assert_eq!(calibration.undistort(position, 320.0, 240.0), position);
+    }
+
+    #[test]
+    fn test_lens_calibration_center_is_fixed() {
+        let calibration = LensCalibration::new(-0.3, 0.1);
+        let center = Position::new(160.0, 120.0);
+        assert_eq!(calibration.undistort(center, 320.0, 240.0), center);
+    }
+
+    #[test]
+    fn test_lens_calibration_barrel_correction_moves_edges_outward() {
+        let calibration = LensCalibration::new(-0.2, 0.0);
+        let imaged = Position::new(300.0, 120.0);
+        let corrected = calibration.undistort(imaged, 320.0, 240.0);
+        assert!(corrected.x > imaged.x);
+        assert!((corrected.y - 120.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_lens_calibration_roundtrip() {
+        let calibration = LensCalibration::new(-0.15, 0.05);
+        for &(x, y) in &[(10.0, 10.0), (300.0, 40.0), (200.0, 230.0), (160.0, 5.0)] {
+            let real = Position::new(x, y);
+            let imaged = calibration.distort(real, 320.0, 240.0);
+            let restored = calibration.undistort(imaged, 320.0, 240.0);
+            assert!(
+                restored.distance_to(&real) < 0.01,
+                "{:?} -> {:?}",
+                real,
+                restored
+            );
+        }
+    }
+
+    #[test]
+    fn test_lens_calibration_rejects_degenerate_coefficients() {
+        let position = Position::new(310.0, 230.0);
+        let folded = LensCalibration::new(-5.0, 0.0);
+        assert_eq!(folded.undistort(position, 320.0, 240.0), position);
+
+        let nan = LensCalibration::new(f32::NAN, 0.0);
+        assert!(!nan.is_valid());
+        assert_eq!(nan.undistort(position, 320.0, 240.0), position);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lens_calibration_json_defaults() {
+        let calibration: LensCalibration = serde_json::from_str(r#"{"k1":-0.2}"#).unwrap();
+        assert_eq!(calibration, LensCalibration::new(-0.2, 0.0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_control_message_tagging() {
+        let message = ControlMessage::Position(TargetPositionUpdate {
+            position: Some(Position::new(1.0, 2.0)),
+        });
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"type":"position","position":{"x":1.0,"y":2.0}}"#);
+
+        let json = serde_json::to_string(&ControlMessage::Settings(SettingsUpdate {
+            settings: BoidSettings::default(),
+            boid_count: None,
+        }))
+        .unwrap();
+        match serde_json::from_str::<ControlMessage>(&json).unwrap() {
+            ControlMessage::Settings(update) => assert_eq!(update.settings.max_speed, 2.0),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let scales = ParameterScalesUpdate {
+            scales: [("max_speed".to_string(), 1.5)].into_iter().collect(),
+        };
+        let json = serde_json::to_string(&ControlMessage::Scales(scales)).unwrap();
+        assert_eq!(json, r#"{"type":"scales","scales":{"max_speed":1.5}}"#);
+
+        assert!(serde_json::from_str::<ControlMessage>(r#"{"type":"reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_batch_commands_and_response() {
+        let json = r#"[
+            {"type":"preset","settings":{"separation_weight":2.0,"alignment_weight":1.0,
+             "cohesion_weight":1.0,"max_speed":3.0,"max_force":0.05,"seek_weight":8.0}},
+            {"type":"position","position":{"x":10.0,"y":20.0}},
+            {"type":"boids","count":12},
+            {"type":"wind","force_x":0.1,"force_y":0.0},
+            {"type":"colors","scheme":"high_contrast"}
+        ]"#;
+        let commands: Vec<BatchCommand> = serde_json::from_str(json).unwrap();
+        assert_eq!(commands.len(), 5);
+        assert!(matches!(
+            &commands[0],
+            BatchCommand::Preset(PresetUpdate {
+                duration_ticks: 0,
+                ..
+            })
+        ));
+        assert!(matches!(
+            commands[4],
+            BatchCommand::Colors(ColorSchemeUpdate {
+                scheme: ColorScheme::HighContrast
+            })
+        ));
+
+        // A rejected batch reads as an error body too
+        let response = BatchResponse {
+            applied: false,
+            results: vec![
+                BatchResult::default(),
+                BatchResult {
+                    error: Some(ErrorBody::new(ErrorCode::InvalidSetting)),
+                    adjusted: Vec::new(),
+                },
+            ],
+            error: Some(ErrorBody::new(ErrorCode::InvalidBatch)),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        let error: ErrorBody = serde_json::from_str(&json).unwrap();
+        assert_eq!(error.code, ErrorCode::InvalidBatch);
+        assert_eq!(
+            serde_json::from_str::<BatchResponse>(&json).unwrap(),
+            response
+        );
+        let applied = r#"{"applied":true,"results":[{}]}"#;
+        let response: BatchResponse = serde_json::from_str(applied).unwrap();
+        assert_eq!(response.error, None);
+        assert_eq!(serde_json::to_string(&response).unwrap(), applied);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_status_controllers() {
+        let old_firmware = r#"{"boid_count":20,"fps":30,"target_active":true}"#;
+        let mut status: StatusResponse = serde_json::from_str(old_firmware).unwrap();
+        assert!(!status.controlled_by_other("phone"));
+        assert_eq!(serde_json::to_string(&status).unwrap(), old_firmware);
+
+        status.controllers = vec!["laptop".to_string()];
+        assert!(status.controlled_by_other("phone"));
+        assert!(!status.controlled_by_other("laptop"));
+    }
 }
@@ -0,0 +1,243 @@
+//! Metrics an ESP32 pushes to a central collector
+//!
+//! An installation can run many boards. Each can POST its numbers to one
+//! endpoint every few seconds, in InfluxDB line protocol (`/api/v2/write`,
+//! or `/write` on 1.x) or in the Prometheus text format a Pushgateway
+//! accepts (`/metrics/job/<job>/instance/<device>`). Prometheus
+//! remote-write needs protobuf and snappy, which the firmware does without;
+//! have Prometheus scrape the Pushgateway instead.
+//!
+//! Plain `http://` only, with a minimal HTTP/1.1 request the firmware
+//! writes to a socket itself.
+
+use std::fmt::Write;
+use std::string::String;
+
+/// Replaced with the device name in an endpoint URL
+pub const DEVICE_PLACEHOLDER: &str = "{device}";
+
+/// Push interval used when none is configured
+pub const DEFAULT_INTERVAL_SECS: u32 = 10;
+
+/// One reading of a device
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceMetrics {
+    /// Tags every series, so boards can be told apart
+    pub device: String,
+    pub uptime_secs: u64,
+    pub fps: u32,
+    pub boid_count: usize,
+    /// Whether the flock follows a target
+    pub target_active: bool,
+    /// Clients whose targets the flock follows
+    pub controllers: usize,
+    pub free_heap_bytes: u32,
+    /// Lowest free heap since boot
+    pub min_free_heap_bytes: u32,
+}
+
+/// Body format of a push
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// InfluxDB line protocol, one line per push
+    Influx,
+    /// Prometheus text exposition, for a Pushgateway
+    Prometheus,
+}
+
+impl MetricsFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "influx" => Some(Self::Influx),
+            "prometheus" => Some(Self::Prometheus),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Influx => "influx",
+            Self::Prometheus => "prometheus",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Influx => "text/plain; charset=utf-8",
+            Self::Prometheus => "text/plain; version=0.0.4",
+        }
+    }
+
+    /// `metrics` as a request body. Points carry no timestamp, since boards
+    /// have no wall clock; the collector stamps them on arrival.
+    pub fn encode(&self, metrics: &DeviceMetrics) -> String {
+        let fields = [
+            ("fps", metrics.fps as u64),
+            ("boids", metrics.boid_count as u64),
+            ("target_active", metrics.target_active as u64),
+            ("controllers", metrics.controllers as u64),
+            ("free_heap_bytes", metrics.free_heap_bytes as u64),
+            ("min_free_heap_bytes", metrics.min_free_heap_bytes as u64),
+            ("uptime_seconds", metrics.uptime_secs),
+        ];
+        let mut body = String::new();
+        match self {
+            Self::Influx => {
+                body.push_str("boid,device=");
+                for c in metrics.device.chars() {
+                    if matches!(c, ',' | '=' | ' ' | '\\') {
+                        body.push('\\');
+                    }
+                    body.push(c);
+                }
+                for (i, (name, value)) in fields.into_iter().enumerate() {
+                    let separator = if i == 0 { ' ' } else { ',' };
+                    let _ = write!(body, "{}{}={}i", separator, name, value);
+                }
+                body.push('\n');
+            }
+            // The Pushgateway takes the device from the URL's grouping key
+            Self::Prometheus => {
+                for (name, value) in fields {
+                    let _ = writeln!(body, "# TYPE boid_{} gauge", name);
+                    let _ = writeln!(body, "boid_{} {}", name, value);
+                }
+            }
+        }
+        body
+    }
+}
+
+/// Where pushes go, split from an `http://host[:port]/path` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    /// Path and query, starting with `/`
+    pub path: String,
+}
+
+impl Endpoint {
+    /// Parse `url`, putting `device` wherever it says
+    /// [`DEVICE_PLACEHOLDER`]. `None` for anything but a plain HTTP URL.
+    pub fn parse(url: &str, device: &str) -> Option<Self> {
+        let url = url.replace(DEVICE_PLACEHOLDER, device);
+        let rest = url.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// A complete POST of `body`; `authorization` is sent as is (e.g.
+    /// `"Token <api token>"` for InfluxDB 2)
+    pub fn post(&self, format: MetricsFormat, body: &str, authorization: Option<&str>) -> String {
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path,
+            self.host,
+            self.port,
+            format.content_type(),
+            body.len()
+        );
+        if let Some(authorization) = authorization {
+            let _ = write!(request, "Authorization: {}\r\n", authorization);
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        request
+    }
+}
+
+/// Status code of an HTTP response's first line
+pub fn response_status(response: &[u8]) -> Option<u16> {
+    let line = response.split(|&b| b == b'\n').next()?;
+    let line = core::str::from_utf8(line).ok()?;
+    let mut parts = line.split_whitespace();
+    parts.next()?.strip_prefix("HTTP/")?;
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading() -> DeviceMetrics {
+        DeviceMetrics {
+            device: "hall 2,left".to_string(),
+            uptime_secs: 3600,
+            fps: 29,
+            boid_count: 20,
+            target_active: true,
+            controllers: 1,
+            free_heap_bytes: 120_000,
+            min_free_heap_bytes: 98_000,
+        }
+    }
+
+    #[test]
+    fn test_influx_line() {
+        assert_eq!(
+            MetricsFormat::Influx.encode(&reading()),
+            "boid,device=hall\\ 2\\,left fps=29i,boids=20i,target_active=1i,controllers=1i,\
+             free_heap_bytes=120000i,min_free_heap_bytes=98000i,uptime_seconds=3600i\n"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_text() {
+        let body = MetricsFormat::Prometheus.encode(&reading());
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0], "# TYPE boid_fps gauge");
+        assert_eq!(lines[1], "boid_fps 29");
+        assert!(lines.contains(&"boid_target_active 1"));
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_endpoint_parse_and_post() {
+        let endpoint = Endpoint::parse(
+            "http://gateway:9091/metrics/job/boid/instance/{device}",
+            "boid-a1b2c3",
+        )
+        .unwrap();
+        assert_eq!(endpoint.host, "gateway");
+        assert_eq!(endpoint.port, 9091);
+        assert_eq!(endpoint.path, "/metrics/job/boid/instance/boid-a1b2c3");
+
+        let bare = Endpoint::parse("http://10.0.0.5", "x").unwrap();
+        assert_eq!((bare.port, bare.path.as_str()), (80, "/"));
+        assert!(Endpoint::parse("https://influx:8086/api/v2/write", "x").is_none());
+        assert!(Endpoint::parse("http://:8086/write", "x").is_none());
+        assert!(Endpoint::parse("http://influx:port/write", "x").is_none());
+
+        let request = bare.post(MetricsFormat::Influx, "boid fps=1i\n", Some("Token t"));
+        assert!(request.starts_with("POST / HTTP/1.1\r\nHost: 10.0.0.5:80\r\n"));
+        assert!(request.contains("Content-Length: 12\r\nConnection: close\r\n"));
+        assert!(request.ends_with("Authorization: Token t\r\n\r\nboid fps=1i\n"));
+    }
+
+    #[test]
+    fn test_response_status() {
+        assert_eq!(
+            response_status(b"HTTP/1.1 204 No Content\r\n\r\n"),
+            Some(204)
+        );
+        assert_eq!(response_status(b"HTTP/1.0 400 Bad Request\r\n"), Some(400));
+        assert_eq!(response_status(b"garbage"), None);
+        assert_eq!(response_status(b""), None);
+    }
+}
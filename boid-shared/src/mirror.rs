@@ -0,0 +1,170 @@
+//! The device's flock as `GET /api/flock` sends it, for mirroring
+//!
+//! A browser can draw what the ESP32 simulates by polling for every boid's
+//! position and velocity. Two encodings carry the same [`FlockFrame`]:
+//! compact JSON, with each boid as a `[x, y, vx, vy, group]` array, and a
+//! fixed little-endian layout for `Accept: application/octet-stream`:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 4 | frame number (`u32`) |
+//! | 4 + 4 | width, height (`f32`) |
+//! | 2 | boid count (`u16`) |
+//! | 17 per boid | x, y, vx, vy (`f32`), group (`u8`) |
+//!
+//! The binary form is 17 bytes a boid against about 40 as JSON, and reads
+//! with a `DataView` without parsing.
+
+use serde::{Deserialize, Serialize};
+
+/// Content type of the binary encoding
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
+const HEADER_LEN: usize = 14;
+const BOID_LEN: usize = 17;
+
+/// One boid of a [`FlockFrame`]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(from = "(f32, f32, f32, f32, u8)", into = "(f32, f32, f32, f32, u8)")]
+pub struct BoidState {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub group: u8,
+}
+
+impl From<(f32, f32, f32, f32, u8)> for BoidState {
+    fn from((x, y, vx, vy, group): (f32, f32, f32, f32, u8)) -> Self {
+        Self {
+            x,
+            y,
+            vx,
+            vy,
+            group,
+        }
+    }
+}
+
+impl From<BoidState> for (f32, f32, f32, f32, u8) {
+    fn from(boid: BoidState) -> Self {
+        (boid.x, boid.y, boid.vx, boid.vy, boid.group)
+    }
+}
+
+/// Every boid after one simulation frame
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FlockFrame {
+    /// Counts up with every simulated frame, so a poller can tell a new
+    /// frame from one it already drew; wraps around
+    pub frame: u32,
+    pub width: f32,
+    pub height: f32,
+    pub boids: Vec<BoidState>,
+}
+
+impl FlockFrame {
+    /// The binary encoding; boids past `u16::MAX` are left out
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count = self.boids.len().min(u16::MAX as usize);
+        let mut bytes = Vec::with_capacity(HEADER_LEN + count * BOID_LEN);
+        bytes.extend_from_slice(&self.frame.to_le_bytes());
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&(count as u16).to_le_bytes());
+        for boid in &self.boids[..count] {
+            for value in [boid.x, boid.y, boid.vx, boid.vy] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.push(boid.group);
+        }
+        bytes
+    }
+
+    /// Decode [`to_bytes`](Self::to_bytes); `None` if `bytes` is cut short
+    /// or has trailing bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let header = bytes.get(..HEADER_LEN)?;
+        let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let count = u16::from_le_bytes([header[12], header[13]]) as usize;
+        let body = &bytes[HEADER_LEN..];
+        if body.len() != count * BOID_LEN {
+            return None;
+        }
+        let boids = body
+            .chunks_exact(BOID_LEN)
+            .map(|chunk| {
+                let f32_at = |at: usize| f32::from_le_bytes(chunk[at..at + 4].try_into().unwrap());
+                BoidState {
+                    x: f32_at(0),
+                    y: f32_at(4),
+                    vx: f32_at(8),
+                    vy: f32_at(12),
+                    group: chunk[16],
+                }
+            })
+            .collect();
+        Some(Self {
+            frame: u32_at(0),
+            width: f32::from_bits(u32_at(4)),
+            height: f32::from_bits(u32_at(8)),
+            boids,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> FlockFrame {
+        FlockFrame {
+            frame: 7,
+            width: 240.0,
+            height: 240.0,
+            boids: vec![
+                BoidState {
+                    x: 10.5,
+                    y: 20.0,
+                    vx: -1.25,
+                    vy: 0.5,
+                    group: 0,
+                },
+                BoidState {
+                    x: 200.0,
+                    y: 3.0,
+                    vx: 0.0,
+                    vy: 2.0,
+                    group: 2,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_flock_frame_compact_json() {
+        let json = serde_json::to_string(&frame()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"frame":7,"width":240.0,"height":240.0,"boids":[[10.5,20.0,-1.25,0.5,0],[200.0,3.0,0.0,2.0,2]]}"#
+        );
+        let decoded: FlockFrame = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, frame());
+    }
+
+    #[test]
+    fn test_flock_frame_binary_round_trip() {
+        let bytes = frame().to_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + 2 * BOID_LEN);
+        assert_eq!(&bytes[..4], &[7, 0, 0, 0]);
+        assert_eq!(FlockFrame::from_bytes(&bytes), Some(frame()));
+
+        assert!(FlockFrame::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(FlockFrame::from_bytes(&bytes[..10]).is_none());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(FlockFrame::from_bytes(&longer).is_none());
+        let empty = FlockFrame::default().to_bytes();
+        assert_eq!(FlockFrame::from_bytes(&empty), Some(FlockFrame::default()));
+    }
+}
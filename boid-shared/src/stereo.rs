@@ -0,0 +1,90 @@
+//! Rough hand depth from two cameras
+//!
+//! With two cameras side by side, the same hand appears at different
+//! horizontal positions in each frame; the closer the hand, the larger the
+//! difference (disparity). Instead of a full stereo calibration, the user
+//! holds the hand at a near and a far pose once, and depth is interpolated
+//! between the two recorded disparities. That gives a normalized depth that
+//! works even when the two cameras differ in resolution or field of view.
+
+use crate::HandLandmarks;
+use serde::{Deserialize, Serialize};
+
+/// Disparities recorded at the near and far calibration poses
+///
+/// Disparities are in fractions of the frame width, see [`disparity`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct StereoCalibration {
+    pub near_disparity: f32,
+    pub far_disparity: f32,
+}
+
+impl StereoCalibration {
+    /// Poses closer together than this cannot be told apart
+    pub const MIN_SPREAD: f32 = 0.01;
+
+    pub fn new(near_disparity: f32, far_disparity: f32) -> Self {
+        Self {
+            near_disparity,
+            far_disparity,
+        }
+    }
+
+    /// Both disparities are finite and far enough apart to interpolate
+    pub fn is_valid(&self) -> bool {
+        self.near_disparity.is_finite()
+            && self.far_disparity.is_finite()
+            && (self.near_disparity - self.far_disparity).abs() >= Self::MIN_SPREAD
+    }
+
+    /// Depth from 0 (near pose) to 1 (far pose), clamped; `None` if the
+    /// calibration is invalid
+    pub fn depth(&self, disparity: f32) -> Option<f32> {
+        if !self.is_valid() || !disparity.is_finite() {
+            return None;
+        }
+        let t = (disparity - self.near_disparity) / (self.far_disparity - self.near_disparity);
+        Some(t.clamp(0.0, 1.0))
+    }
+}
+
+/// Horizontal offset of the index tip between the left and right camera,
+/// in fractions of each frame's width
+pub fn disparity(
+    left: &HandLandmarks,
+    left_width: f32,
+    right: &HandLandmarks,
+    right_width: f32,
+) -> f32 {
+    left.index_tip.x / left_width - right.index_tip.x / right_width
+}
+
+/// Seek radius for a hand at `depth`: 1.5x `base` when near, down to 0.5x
+/// when far, so reaching toward the cameras pulls in more of the flock.
+/// A radius of 0 (every boid responds) stays 0.
+pub fn seek_radius_for_depth(base: f32, depth: f32) -> f32 {
+    base * (1.5 - depth.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_depth_interpolates_between_poses() {
+        let calibration = StereoCalibration::new(0.4, 0.1);
+        assert!(calibration.is_valid());
+        assert_eq!(calibration.depth(0.4), Some(0.0));
+        assert_eq!(calibration.depth(0.25), Some(0.5));
+        assert_eq!(calibration.depth(0.0), Some(1.0));
+        assert_eq!(StereoCalibration::new(0.2, 0.2).depth(0.2), None);
+
+        let left = HandLandmarks::new(Position::new(0.0, 0.0), Position::new(320.0, 100.0));
+        let right = HandLandmarks::new(Position::new(0.0, 0.0), Position::new(60.0, 90.0));
+        assert!((disparity(&left, 640.0, &right, 240.0) - 0.25).abs() < 1e-6);
+
+        assert_eq!(seek_radius_for_depth(100.0, 0.0), 150.0);
+        assert_eq!(seek_radius_for_depth(100.0, 1.0), 50.0);
+    }
+}
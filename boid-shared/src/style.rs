@@ -0,0 +1,162 @@
+//! How boids look, chosen once and honored by every renderer
+//!
+//! A [`RenderStyle`] gathers what a viewer can change about the boids
+//! without touching the simulation: their size, their [`ColorScheme`] and
+//! their outline. The browser and the ESP32 display both draw each boid from
+//! [`RenderStyle::glyph`] and [`RenderStyle::color`], so a style set on
+//! either looks the same on both, within what the display can show.
+
+use serde::{Deserialize, Serialize};
+
+use crate::colors::{Glyph, Rgb, Shape};
+use crate::{limits, ColorScheme};
+
+/// The outline a viewer picks for every boid, under palettes that do not
+/// pick one per group. Each is drawn as a [`Shape`] at some scale; not every
+/// `Shape` can be picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoidShape {
+    /// Pointing along the heading
+    #[default]
+    Triangle,
+    /// A small round dot, for large flocks
+    Dot,
+    /// A notched arrowhead, whose heading reads at a glance
+    Arrow,
+}
+
+impl BoidShape {
+    /// `"triangle"`, `"dot"` or `"arrow"`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "triangle" => Some(Self::Triangle),
+            "dot" => Some(Self::Dot),
+            "arrow" => Some(Self::Arrow),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Triangle => "triangle",
+            Self::Dot => "dot",
+            Self::Arrow => "arrow",
+        }
+    }
+
+    fn glyph(&self) -> Glyph {
+        match self {
+            Self::Triangle => Glyph::PLAIN,
+            Self::Dot => Glyph {
+                shape: Shape::Circle,
+                scale: 0.5,
+            },
+            Self::Arrow => Glyph {
+                shape: Shape::Arrow,
+                scale: 1.0,
+            },
+        }
+    }
+}
+
+/// Size, palette and shape of the boids
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RenderStyle {
+    /// Multiplies the renderer's usual boid size, within
+    /// `limits::BOID_SIZE`
+    #[serde(default = "default_size")]
+    pub size: f32,
+    #[serde(default)]
+    pub palette: ColorScheme,
+    /// Ignored under palettes that give each group its own outline
+    #[serde(default)]
+    pub shape: BoidShape,
+}
+
+fn default_size() -> f32 {
+    1.0
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        Self {
+            size: default_size(),
+            palette: ColorScheme::default(),
+            shape: BoidShape::default(),
+        }
+    }
+}
+
+impl RenderStyle {
+    /// Whether `size` is within `limits::BOID_SIZE`
+    pub fn is_valid(&self) -> bool {
+        let (min, max) = limits::BOID_SIZE;
+        self.size >= min && self.size <= max
+    }
+
+    /// Color of a boid in sub-flock `group`; `None` under
+    /// [`ColorScheme::Theme`], where the renderer shades by speed
+    pub fn color(&self, group: u8) -> Option<Rgb> {
+        self.palette.color(group)
+    }
+
+    /// Outline and size of a boid in sub-flock `group` moving at
+    /// `speed_fraction` of the maximum speed
+    pub fn glyph(&self, group: u8, speed_fraction: f32) -> Glyph {
+        let glyph = match self.palette {
+            ColorScheme::Theme => self.shape.glyph(),
+            // The outline repeats what the color says; keep it
+            _ => self.palette.glyph(group, speed_fraction),
+        };
+        Glyph {
+            scale: glyph.scale * self.size,
+            ..glyph
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_scales_and_shapes_glyphs() {
+        assert_eq!(RenderStyle::default().glyph(2, 1.0), Glyph::PLAIN);
+        for shape in [BoidShape::Triangle, BoidShape::Dot, BoidShape::Arrow] {
+            assert_eq!(BoidShape::parse(shape.as_str()), Some(shape));
+        }
+        assert_eq!(BoidShape::parse("hexagon"), None);
+
+        let arrows = RenderStyle {
+            size: 2.0,
+            shape: BoidShape::Arrow,
+            ..RenderStyle::default()
+        };
+        let glyph = arrows.glyph(0, 0.5);
+        assert_eq!((glyph.shape, glyph.scale), (Shape::Arrow, 2.0));
+        assert_eq!(arrows.color(0), None);
+
+        // A fixed palette keeps its per-group outlines, at the chosen size
+        let palette = RenderStyle {
+            palette: ColorScheme::OkabeIto,
+            ..arrows
+        };
+        let fixed = ColorScheme::OkabeIto.glyph(1, 0.5);
+        assert_eq!(palette.glyph(1, 0.5).shape, fixed.shape);
+        assert_eq!(palette.glyph(1, 0.5).scale, fixed.scale * 2.0);
+        assert_eq!(palette.color(1), ColorScheme::OkabeIto.color(1));
+    }
+
+    #[test]
+    fn test_style_size_is_limited() {
+        assert!(RenderStyle::default().is_valid());
+        for size in [0.0, 10.0, f32::NAN] {
+            let style = RenderStyle {
+                size,
+                ..RenderStyle::default()
+            };
+            assert!(!style.is_valid());
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Fixtures for tests here and in the crates that depend on this one
+//!
+//! Those enable it with the `test-util` feature, in their dev-dependencies.
+
+use crate::{Finger, FullHandLandmarks, Position, HAND_LANDMARK_COUNT};
+
+/// Hand pointing up from `wrist`, fingers 10 apart and joints 20 apart;
+/// curled fingers fold their last two joints back toward the palm
+pub fn full_hand(wrist: Position, extended: [bool; 5]) -> FullHandLandmarks {
+    let mut points = [wrist; HAND_LANDMARK_COUNT];
+    for (finger, &out) in Finger::ALL.iter().zip(&extended) {
+        let x = wrist.x + *finger as usize as f32 * 10.0 - 20.0;
+        for joint in 0..4 {
+            let reach = if out || joint < 2 {
+                20.0 * (joint + 1) as f32
+            } else {
+                20.0 * (3 - joint) as f32
+            };
+            points[finger.base() + joint] = Position::new(x, wrist.y - reach);
+        }
+    }
+    FullHandLandmarks::new(points)
+}
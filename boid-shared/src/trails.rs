@@ -0,0 +1,211 @@
+//! Motion trails for small displays
+//!
+//! Twenty boids on a 240x240 panel that is cleared every frame look
+//! sparse. A short trail behind each boid shows where it came from without
+//! adding boids. Each boid keeps its last few positions in a fixed [`Trail`]
+//! ring, so nothing is allocated per frame, and the renderer draws them as
+//! a polyline.
+//!
+//! A trail is cut where it bends more sharply than [`MAX_TURN_COS`] allows
+//! or jumps farther than a step can go: a boid that wrapped around an edge
+//! or turned on the spot would otherwise draw a spike. How many points are
+//! drawn follows the frame budget through [`TrailLength`].
+
+use crate::Position;
+
+/// Positions a trail remembers
+pub const TRAIL_CAPACITY: usize = 12;
+
+/// Cosine of the sharpest turn a trail follows, a right angle
+pub const MAX_TURN_COS: f32 = 0.0;
+
+/// Frames between changes of the drawn length, so it does not flicker
+const ADJUST_FRAMES: u32 = 15;
+
+/// Frames below this share of the budget may draw longer trails
+const HEADROOM: f32 = 0.75;
+
+/// The last [`TRAIL_CAPACITY`] positions of one boid
+#[derive(Debug, Clone, Copy)]
+pub struct Trail {
+    points: [Position; TRAIL_CAPACITY],
+    /// Index of the newest point
+    newest: usize,
+    len: usize,
+}
+
+impl Trail {
+    pub const fn new() -> Self {
+        Self {
+            points: [Position { x: 0.0, y: 0.0 }; TRAIL_CAPACITY],
+            newest: 0,
+            len: 0,
+        }
+    }
+
+    /// Remember `point`, forgetting the oldest one when full
+    pub fn push(&mut self, point: Position) {
+        self.newest = (self.newest + 1) % TRAIL_CAPACITY;
+        self.points[self.newest] = point;
+        self.len = (self.len + 1).min(TRAIL_CAPACITY);
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Newest first
+    pub fn iter(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.len)
+            .map(move |age| self.points[(self.newest + TRAIL_CAPACITY - age) % TRAIL_CAPACITY])
+    }
+
+    /// Write up to `length` points into `out`, newest first, stopping at a
+    /// turn sharper than [`MAX_TURN_COS`] or a step longer than `max_step`.
+    /// Returns how many were written; fewer than two draw nothing.
+    pub fn path_into(&self, length: usize, max_step: f32, out: &mut [Position]) -> usize {
+        let length = length.min(out.len());
+        let mut count: usize = 0;
+        let mut heading: Option<(f32, f32)> = None;
+        for point in self.iter().take(length) {
+            if let Some(previous) = count.checked_sub(1).map(|i| out[i]) {
+                let (dx, dy) = (previous.x - point.x, previous.y - point.y);
+                let step = libm::sqrtf(dx * dx + dy * dy);
+                if step > max_step {
+                    break;
+                }
+                if step > f32::EPSILON {
+                    let direction = (dx / step, dy / step);
+                    if let Some((hx, hy)) = heading {
+                        if direction.0 * hx + direction.1 * hy < MAX_TURN_COS {
+                            break;
+                        }
+                    }
+                    heading = Some(direction);
+                }
+            }
+            out[count] = point;
+            count += 1;
+        }
+        count
+    }
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How many trail points to draw, shortened when frames run over their
+/// budget and lengthened again when there is time to spare
+#[derive(Debug, Clone, Copy)]
+pub struct TrailLength {
+    length: usize,
+    max: usize,
+    countdown: u32,
+}
+
+impl TrailLength {
+    /// Start at `max` points, at most [`TRAIL_CAPACITY`]; 0 turns trails off
+    pub fn new(max: usize) -> Self {
+        let max = max.min(TRAIL_CAPACITY);
+        Self {
+            length: max,
+            max,
+            countdown: ADJUST_FRAMES,
+        }
+    }
+
+    pub fn get(&self) -> usize {
+        self.length
+    }
+
+    /// Account for a frame whose work took `frame_ms` of `budget_ms`
+    pub fn record(&mut self, frame_ms: f32, budget_ms: f32) {
+        self.countdown = self.countdown.saturating_sub(1);
+        if self.countdown > 0 {
+            return;
+        }
+        if frame_ms > budget_ms && self.length > 0 {
+            self.length -= 1;
+        } else if frame_ms < budget_ms * HEADROOM && self.length < self.max {
+            self.length += 1;
+        } else {
+            return;
+        }
+        self.countdown = ADJUST_FRAMES;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(trail: &Trail, length: usize) -> usize {
+        let mut out = [Position::new(0.0, 0.0); TRAIL_CAPACITY];
+        trail.path_into(length, 20.0, &mut out)
+    }
+
+    #[test]
+    fn test_trail_keeps_the_newest_points() {
+        let mut trail = Trail::new();
+        for x in 0..20 {
+            trail.push(Position::new(x as f32, 0.0));
+        }
+        assert_eq!(trail.len(), TRAIL_CAPACITY);
+        assert!(trail.iter().map(|p| p.x).take(3).eq([19.0, 18.0, 17.0]));
+        assert_eq!(path(&trail, 5), 5);
+        assert_eq!(path(&trail, 99), TRAIL_CAPACITY);
+        trail.clear();
+        assert_eq!(path(&trail, 5), 0);
+    }
+
+    #[test]
+    fn test_trail_is_cut_at_wraps_and_sharp_turns() {
+        let mut wrapped = Trail::new();
+        for x in [230.0, 234.0, 238.0, 2.0, 6.0] {
+            wrapped.push(Position::new(x, 50.0));
+        }
+        assert_eq!(path(&wrapped, 5), 2);
+
+        // Newest first: a right angle is followed, a reversal is not
+        let mut turning = Trail::new();
+        for (x, y) in [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)] {
+            turning.push(Position::new(x, y));
+        }
+        assert_eq!(path(&turning, 4), 4);
+        let mut reversing = Trail::new();
+        for x in [0.0, 5.0, 10.0, 5.0] {
+            reversing.push(Position::new(x, 0.0));
+        }
+        assert_eq!(path(&reversing, 4), 2);
+    }
+
+    #[test]
+    fn test_trail_length_follows_the_budget() {
+        let mut length = TrailLength::new(6);
+        for _ in 0..ADJUST_FRAMES * 3 {
+            length.record(30.0, 20.0);
+        }
+        assert_eq!(length.get(), 3);
+        // Inside the budget but without headroom: no change
+        for _ in 0..ADJUST_FRAMES * 2 {
+            length.record(18.0, 20.0);
+        }
+        assert_eq!(length.get(), 3);
+        for _ in 0..ADJUST_FRAMES * 10 {
+            length.record(5.0, 20.0);
+        }
+        assert_eq!(length.get(), 6);
+        assert_eq!(TrailLength::new(99).get(), TRAIL_CAPACITY);
+    }
+}
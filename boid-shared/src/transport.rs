@@ -0,0 +1,724 @@
+//! Ways of talking to a boid device, behind one [`Transport`] trait
+//!
+//! Frontends send targets and settings and read status through the trait,
+//! so a new protocol is one more implementation here instead of a change to
+//! every frontend. Implementations:
+//!
+//! - [`WebSocketTransport`]: one persistent `/ws` connection; the device
+//!   pushes statuses back
+//! - [`UdpTransport`]: target positions as single datagrams (see
+//!   [`crate::udp`]); nothing else
+//! - [`SerialTransport`]: newline-delimited JSON over any byte stream, e.g.
+//!   a USB serial port
+//!
+//! HTTP, one request per message, is `boid_client::http::HttpTransport`,
+//! on reqwest.
+//!
+//! Transports that cannot carry a message answer
+//! [`TransportError::Unsupported`], so callers can send it another way.
+
+use crate::websocket::{self, Opcode};
+use crate::{
+    udp, ControlMessage, DepthUpdate, ErrorCode, ParameterScalesUpdate, SettingsUpdate,
+    StatusResponse, TargetPositionUpdate,
+};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long requests, and waits for a pushed status, may take
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Events kept for [`Transport::next_event`] before the oldest are dropped
+pub const MAX_QUEUED_EVENTS: usize = 64;
+
+/// Why a message did not get through
+#[derive(Debug)]
+pub enum TransportError {
+    /// The device could not be reached, or the connection broke
+    Io(io::Error),
+    /// The device answered with an error status
+    Rejected {
+        status: u16,
+        /// From the `ErrorBody`, unless the firmware predates error codes
+        code: Option<ErrorCode>,
+        message: String,
+    },
+    /// The device answered with something this end cannot read
+    Protocol(String),
+    /// The device closed the connection
+    Closed,
+    /// This transport cannot carry the named message
+    Unsupported(&'static str),
+}
+
+impl TransportError {
+    /// Error code to report to users
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Rejected {
+                code: Some(code), ..
+            } => *code,
+            Self::Protocol(_) => ErrorCode::InvalidControlMessage,
+            Self::Unsupported(_) => ErrorCode::EndpointNotFound,
+            _ => ErrorCode::ServerUnreachable,
+        }
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Rejected {
+                code: Some(code),
+                message,
+                ..
+            } => write!(f, "[{}] {}", code, message),
+            Self::Rejected { status, .. } => write!(f, "HTTP {}", status),
+            Self::Protocol(message) => write!(f, "{}", message),
+            Self::Closed => write!(f, "connection closed by the device"),
+            Self::Unsupported(what) => write!(f, "this transport cannot send {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TransportError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Protocol(e.to_string())
+    }
+}
+
+/// Something the device sent on its own
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    Status(StatusResponse),
+}
+
+/// A connection to a boid device
+pub trait Transport {
+    /// Short name for logs, e.g. `"websocket"`
+    fn name(&self) -> &'static str;
+
+    fn send_target(&mut self, update: &TargetPositionUpdate) -> Result<(), TransportError>;
+
+    fn send_settings(&mut self, update: &SettingsUpdate) -> Result<(), TransportError>;
+
+    fn send_depth(&mut self, _update: &DepthUpdate) -> Result<(), TransportError> {
+        Err(TransportError::Unsupported("depth updates"))
+    }
+
+    fn send_scales(&mut self, _update: &ParameterScalesUpdate) -> Result<(), TransportError> {
+        Err(TransportError::Unsupported("parameter scales"))
+    }
+
+    /// Send whichever kind of update `message` holds
+    fn send(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        match message {
+            ControlMessage::Position(update) => self.send_target(update),
+            ControlMessage::Settings(update) => self.send_settings(update),
+            ControlMessage::Depth(update) => self.send_depth(update),
+            ControlMessage::Scales(update) => self.send_scales(update),
+        }
+    }
+
+    /// The device's current status
+    fn fetch_status(&mut self) -> Result<StatusResponse, TransportError>;
+
+    /// Start queueing what the device pushes, for [`next_event`](Self::next_event)
+    fn subscribe_events(&mut self) -> Result<(), TransportError> {
+        Err(TransportError::Unsupported("events"))
+    }
+
+    /// The oldest queued event, without blocking
+    fn next_event(&mut self) -> Result<Option<ServerEvent>, TransportError> {
+        Ok(None)
+    }
+}
+
+/// `host[:port]` part of an `http://` URL
+pub fn host_from_url(url: &str) -> &str {
+    let rest = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("ws://"))
+        .unwrap_or(url);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+/// Host and port of a `host[:port]`, with IPv6 addresses in brackets as in
+/// URLs, e.g. `[fe80::1]:8080`; the host comes back without them
+fn split_host(host_port: &str) -> (&str, Option<&str>) {
+    if let Some((host, rest)) = host_port
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+    {
+        return (host, rest.strip_prefix(':'));
+    }
+    match host_port.rsplit_once(':') {
+        // More colons than one are a bare IPv6 address
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (host_port, None),
+    }
+}
+
+/// `host:port` of an `http://` URL, port 80 unless it names one
+fn address_of(url: &str) -> String {
+    match split_host(host_from_url(url)) {
+        (host, Some(port)) if host.contains(':') => format!("[{}]:{}", host, port),
+        (host, Some(port)) => format!("{}:{}", host, port),
+        (host, None) if host.contains(':') => format!("[{}]:80", host),
+        (host, None) => format!("{}:80", host),
+    }
+}
+
+fn connect(address: &str, timeout: Duration) -> Result<TcpStream, TransportError> {
+    let mut last_error =
+        io::Error::new(ErrorKind::NotFound, format!("{} did not resolve", address));
+    for addr in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error.into())
+}
+
+/// Statuses a device pushed, for transports that receive them
+#[derive(Debug, Default)]
+struct Inbox {
+    latest: Option<StatusResponse>,
+    events: VecDeque<ServerEvent>,
+    subscribed: bool,
+}
+
+impl Inbox {
+    fn status(&mut self, status: StatusResponse) {
+        if self.subscribed {
+            if self.events.len() == MAX_QUEUED_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(ServerEvent::Status(status.clone()));
+        }
+        self.latest = Some(status);
+    }
+}
+
+/// Wait up to `timeout` for `poll` to have produced a status
+fn await_status<T>(
+    transport: &mut T,
+    timeout: Duration,
+    poll: impl Fn(&mut T) -> Result<&Inbox, TransportError>,
+) -> Result<StatusResponse, TransportError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(ref status) = poll(transport)?.latest {
+            return Ok(status.clone());
+        }
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(ErrorKind::TimedOut, "no status received").into());
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Persistent WebSocket connection to the ESP32's `/ws` endpoint
+///
+/// Updates go out as masked text frames on one connection instead of a new
+/// HTTP request each. The device pushes a `StatusResponse` about once a
+/// second, which [`fetch_status`](Transport::fetch_status) returns the
+/// newest of.
+pub struct WebSocketTransport {
+    stream: TcpStream,
+    pending: Vec<u8>,
+    mask_state: u64,
+    inbox: Inbox,
+    timeout: Duration,
+}
+
+impl WebSocketTransport {
+    /// Connect to `server_url` (e.g. `http://192.168.1.100`) and perform the
+    /// WebSocket handshake
+    pub fn connect(server_url: &str) -> Result<Self, TransportError> {
+        let address = address_of(server_url);
+        let timeout = DEFAULT_TIMEOUT * 2;
+        let mut stream = connect(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+
+        let mut mask_state = seed();
+        let mut nonce = [0u8; 16];
+        for chunk in nonce.chunks_mut(8) {
+            chunk.copy_from_slice(&next_random(&mut mask_state).to_le_bytes());
+        }
+        let key = websocket::base64_encode(&nonce);
+
+        let request = format!(
+            "GET /ws HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n",
+            host_from_url(server_url),
+            key
+        );
+        stream.write_all(request.as_bytes())?;
+
+        // Read the response head; anything after it is already frame data
+        let mut response = Vec::new();
+        let mut chunk = [0u8; 256];
+        let head_end = loop {
+            let n = stream.read(&mut chunk)?;
+            if n == 0 {
+                return Err(TransportError::Closed);
+            }
+            response.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = response.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&response[..head_end]);
+        if !head.starts_with("HTTP/1.1 101") {
+            return Err(TransportError::Protocol(format!(
+                "server refused WebSocket upgrade: {}",
+                head.lines().next().unwrap_or_default()
+            )));
+        }
+        let accept = head.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("Sec-WebSocket-Accept")
+                .then_some(value.trim())
+        });
+        if accept != Some(websocket::accept_key(&key).as_str()) {
+            return Err(TransportError::Protocol(
+                "invalid Sec-WebSocket-Accept in handshake response".to_string(),
+            ));
+        }
+
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            pending: response[head_end..].to_vec(),
+            mask_state,
+            inbox: Inbox::default(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    fn send_message(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        let json = serde_json::to_vec(message)?;
+        let mask = (next_random(&mut self.mask_state) as u32).to_le_bytes();
+        self.write_frame(&websocket::encode_frame(Opcode::Text, &json, Some(mask)))
+    }
+
+    /// Handle the frames received so far
+    fn poll(&mut self) -> Result<&Inbox, TransportError> {
+        let mut chunk = [0u8; 512];
+        let mut closed = false;
+        let mut received = false;
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(n) => self.pending.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        loop {
+            let frame = match websocket::decode_frame(&self.pending) {
+                Ok(Some((frame, used))) => {
+                    self.pending.drain(..used);
+                    frame
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    return Err(TransportError::Protocol(format!(
+                        "invalid frame from server: {:?}",
+                        e
+                    )))
+                }
+            };
+
+            match frame.opcode {
+                // Anything else is a reply to a settings update or an error
+                Opcode::Text => {
+                    if let Ok(status) = serde_json::from_slice(&frame.payload) {
+                        self.inbox.status(status);
+                        received = true;
+                    }
+                }
+                Opcode::Ping => {
+                    let mask = (next_random(&mut self.mask_state) as u32).to_le_bytes();
+                    let pong = websocket::encode_frame(Opcode::Pong, &frame.payload, Some(mask));
+                    self.write_frame(&pong)?;
+                }
+                Opcode::Close => return Err(TransportError::Closed),
+                _ => {}
+            }
+        }
+
+        // Hand out what arrived before the connection dropped; the next
+        // poll reports the closure
+        if closed && !received {
+            return Err(TransportError::Closed);
+        }
+        Ok(&self.inbox)
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        // The socket is non-blocking for polling; block while writing
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(frame);
+        self.stream.set_nonblocking(true)?;
+        Ok(result?)
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn send_target(&mut self, update: &TargetPositionUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Position(update.clone()))
+    }
+
+    fn send_settings(&mut self, update: &SettingsUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Settings(update.clone()))
+    }
+
+    fn send_depth(&mut self, update: &DepthUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Depth(*update))
+    }
+
+    fn send_scales(&mut self, update: &ParameterScalesUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Scales(update.clone()))
+    }
+
+    fn send(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        self.send_message(message)
+    }
+
+    /// The newest status pushed so far, waiting for the first one
+    fn fetch_status(&mut self) -> Result<StatusResponse, TransportError> {
+        let timeout = self.timeout;
+        await_status(self, timeout, Self::poll)
+    }
+
+    fn subscribe_events(&mut self) -> Result<(), TransportError> {
+        self.inbox.subscribed = true;
+        Ok(())
+    }
+
+    fn next_event(&mut self) -> Result<Option<ServerEvent>, TransportError> {
+        self.poll()?;
+        Ok(self.inbox.events.pop_front())
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        let mask = (next_random(&mut self.mask_state) as u32).to_le_bytes();
+        let _ = self.write_frame(&websocket::encode_close(
+            websocket::CLOSE_NORMAL,
+            Some(mask),
+        ));
+    }
+}
+
+fn seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    (nanos ^ (std::process::id() as u64).rotate_left(32)) | 1
+}
+
+/// xorshift64; masking only needs keys a proxy cannot predict in advance
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Target positions as single UDP datagrams
+///
+/// Fire-and-forget: nothing is acknowledged, so a lost datagram is simply
+/// superseded by the next one. The ESP32 must be built with its `udp`
+/// feature. Everything but targets is unsupported.
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    /// Aim at the host of `server_url` (e.g. `http://192.168.1.100`) on `port`
+    ///
+    /// Any port in the URL belongs to the HTTP server and is ignored.
+    pub fn connect(server_url: &str, port: u16) -> Result<Self, TransportError> {
+        let (host, _) = split_host(host_from_url(server_url));
+        let target = (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, format!("{} did not resolve", host))
+        })?;
+
+        let socket = if target.is_ipv6() {
+            UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?
+        } else {
+            UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?
+        };
+        socket.connect(target)?;
+        Ok(Self { socket })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn name(&self) -> &'static str {
+        "udp"
+    }
+
+    fn send_target(&mut self, update: &TargetPositionUpdate) -> Result<(), TransportError> {
+        let mut buf = [0u8; udp::MAX_DATAGRAM_LEN];
+        let datagram = udp::encode_target(update, &mut buf)
+            .map_err(|e| TransportError::Protocol(e.to_string()))?;
+        self.socket.send(datagram)?;
+        Ok(())
+    }
+
+    fn send_settings(&mut self, _update: &SettingsUpdate) -> Result<(), TransportError> {
+        Err(TransportError::Unsupported("settings"))
+    }
+
+    fn fetch_status(&mut self) -> Result<StatusResponse, TransportError> {
+        Err(TransportError::Unsupported("status requests"))
+    }
+}
+
+/// Newline-delimited JSON over a byte stream, such as a serial port
+///
+/// Each update goes out as one `ControlMessage` per line, the format of the
+/// WebSocket channel. Lines coming back that hold a `StatusResponse` are
+/// statuses; anything else is ignored. `port` should have a read timeout or
+/// be non-blocking; reads that time out count as nothing received yet.
+pub struct SerialTransport<S> {
+    port: S,
+    pending: Vec<u8>,
+    inbox: Inbox,
+    timeout: Duration,
+}
+
+impl<S: Read + Write> SerialTransport<S> {
+    pub fn new(port: S) -> Self {
+        Self {
+            port,
+            pending: Vec::new(),
+            inbox: Inbox::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.port
+    }
+
+    fn send_message(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.port.write_all(&line)?;
+        self.port.flush()?;
+        Ok(())
+    }
+
+    /// Handle the lines received so far
+    fn poll(&mut self) -> Result<&Inbox, TransportError> {
+        let mut chunk = [0u8; 256];
+        loop {
+            match self.port.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => self.pending.extend_from_slice(&chunk[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            if let Ok(status) = serde_json::from_slice(&line) {
+                self.inbox.status(status);
+            }
+        }
+        Ok(&self.inbox)
+    }
+}
+
+impl<S: Read + Write> Transport for SerialTransport<S> {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn send_target(&mut self, update: &TargetPositionUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Position(update.clone()))
+    }
+
+    fn send_settings(&mut self, update: &SettingsUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Settings(update.clone()))
+    }
+
+    fn send_depth(&mut self, update: &DepthUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Depth(*update))
+    }
+
+    fn send_scales(&mut self, update: &ParameterScalesUpdate) -> Result<(), TransportError> {
+        self.send_message(&ControlMessage::Scales(update.clone()))
+    }
+
+    fn send(&mut self, message: &ControlMessage) -> Result<(), TransportError> {
+        self.send_message(message)
+    }
+
+    /// The newest status received so far, waiting for the first one
+    fn fetch_status(&mut self) -> Result<StatusResponse, TransportError> {
+        let timeout = self.timeout;
+        await_status(self, timeout, Self::poll)
+    }
+
+    fn subscribe_events(&mut self) -> Result<(), TransportError> {
+        self.inbox.subscribed = true;
+        Ok(())
+    }
+
+    fn next_event(&mut self) -> Result<Option<ServerEvent>, TransportError> {
+        self.poll()?;
+        Ok(self.inbox.events.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_split_host() {
+        assert_eq!(split_host("esp32.local"), ("esp32.local", None));
+        assert_eq!(split_host("10.0.0.2:8080"), ("10.0.0.2", Some("8080")));
+        assert_eq!(split_host("[fe80::1]:8080"), ("fe80::1", Some("8080")));
+        assert_eq!(split_host("[::1]"), ("::1", None));
+        assert_eq!(split_host("::1"), ("::1", None));
+        assert_eq!(address_of("http://[::1]/"), "[::1]:80");
+        assert_eq!(address_of("http://esp32.local:81"), "esp32.local:81");
+    }
+
+    #[test]
+    fn test_udp_transport_connects_over_ipv6() {
+        let Ok(receiver) = UdpSocket::bind("[::1]:0") else {
+            // No IPv6 loopback on this machine
+            return;
+        };
+        let port = receiver.local_addr().unwrap().port();
+
+        let mut transport = UdpTransport::connect("http://[::1]:8080/", port).unwrap();
+        transport
+            .send_target(&TargetPositionUpdate { position: None })
+            .unwrap();
+
+        let mut buf = [0u8; 32];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(udp::decode_target(&buf[..len]).unwrap().position, None);
+    }
+
+    #[test]
+    fn test_udp_transport_sends_targets_only() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+
+        let mut transport = UdpTransport::connect("http://127.0.0.1:8080/", port).unwrap();
+        let update = TargetPositionUpdate {
+            position: Some(Position::new(10.0, 20.0)),
+        };
+        transport.send_target(&update).unwrap();
+
+        let mut buf = [0u8; 32];
+        let len = receiver.recv(&mut buf).unwrap();
+        let received = udp::decode_target(&buf[..len]).unwrap();
+        assert_eq!(received.position, update.position);
+
+        assert!(matches!(
+            transport.fetch_status(),
+            Err(TransportError::Unsupported(_))
+        ));
+    }
+
+    /// Canned input and captured output of a serial port
+    struct Loopback {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_serial_transport_frames_lines() {
+        let input = concat!(
+            "boot log noise\n",
+            r#"{"boid_count":5,"fps":25,"target_active":false}"#,
+            "\n",
+            r#"{"boid_count":6,"fps":24,"target_active":true}"#,
+            "\n"
+        );
+        let mut transport = SerialTransport::new(Loopback {
+            input: Cursor::new(input.as_bytes().to_vec()),
+            output: Vec::new(),
+        });
+        transport.subscribe_events().unwrap();
+
+        transport
+            .send(&ControlMessage::Depth(DepthUpdate { depth: Some(0.5) }))
+            .unwrap();
+        let ServerEvent::Status(first) = transport.next_event().unwrap().unwrap();
+        assert_eq!(first.boid_count, 5);
+        assert_eq!(transport.fetch_status().unwrap().boid_count, 6);
+        assert!(transport.next_event().unwrap().is_some());
+        assert!(transport.next_event().unwrap().is_none());
+
+        let output = transport.into_inner().output;
+        assert_eq!(output, b"{\"type\":\"depth\",\"depth\":0.5}\n");
+    }
+}
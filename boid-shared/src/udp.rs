@@ -0,0 +1,61 @@
+//! Wire format of the UDP target channel
+//!
+//! On a LAN, sending the hand position as a single UDP datagram skips the
+//! TCP and HTTP overhead of `POST /api/position`. Each datagram holds one
+//! postcard-encoded `TargetPositionUpdate`; there is no acknowledgement, and
+//! receivers apply only the newest datagram they have. Settings and status
+//! stay on HTTP.
+
+use crate::TargetPositionUpdate;
+
+/// Port the ESP32 listens on unless configured otherwise
+pub const DEFAULT_PORT: u16 = 4210;
+
+/// Largest encoded update: an option tag and two `f32`s
+pub const MAX_DATAGRAM_LEN: usize = 9;
+
+/// Encode an update into `buf`, returning the bytes to send
+///
+/// `buf` must hold at least `MAX_DATAGRAM_LEN` bytes.
+pub fn encode_target<'a>(
+    update: &TargetPositionUpdate,
+    buf: &'a mut [u8],
+) -> Result<&'a [u8], postcard::Error> {
+    postcard::to_slice(update, buf).map(|bytes| &*bytes)
+}
+
+/// Decode one datagram; `None` if it is malformed or has trailing bytes
+pub fn decode_target(datagram: &[u8]) -> Option<TargetPositionUpdate> {
+    match postcard::take_from_bytes(datagram) {
+        Ok((update, [])) => Some(update),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_target_datagram_round_trip() {
+        let mut buf = [0u8; MAX_DATAGRAM_LEN];
+        let update = TargetPositionUpdate {
+            position: Some(Position::new(320.5, -12.0)),
+        };
+        let bytes = encode_target(&update, &mut buf).unwrap();
+        assert_eq!(bytes.len(), MAX_DATAGRAM_LEN);
+        let decoded = decode_target(bytes).unwrap();
+        assert_eq!(decoded.position, update.position);
+
+        let cleared = encode_target(&TargetPositionUpdate { position: None }, &mut buf)
+            .unwrap()
+            .to_vec();
+        assert_eq!(cleared, [0]);
+        assert!(decode_target(&cleared).unwrap().position.is_none());
+
+        assert!(decode_target(&[]).is_none());
+        assert!(decode_target(&[1, 0, 0]).is_none());
+        assert!(decode_target(&[0, 0]).is_none());
+    }
+}
@@ -0,0 +1,340 @@
+//! Minimal WebSocket (RFC 6455) support for persistent control connections
+//!
+//! Covers what the boid control channel needs: the opening handshake key,
+//! single-frame text/binary messages, ping/pong and close. Fragmented
+//! messages and extensions are not supported. Frames sent by clients must
+//! be masked; frames sent by servers must not be.
+
+/// Magic value appended to the client key when computing the accept key
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest payload accepted when decoding; control messages are tiny
+pub const MAX_PAYLOAD: usize = 16 * 1024;
+
+/// Close status code for a normal shutdown
+pub const CLOSE_NORMAL: u16 = 1000;
+/// Close status code for a frame that breaks the protocol
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// Close status code for a message type the endpoint does not accept
+pub const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0x0 => Some(Self::Continuation),
+            0x1 => Some(Self::Text),
+            0x2 => Some(Self::Binary),
+            0x8 => Some(Self::Close),
+            0x9 => Some(Self::Ping),
+            0xA => Some(Self::Pong),
+            _ => None,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    /// Unmasked payload
+    pub payload: Vec<u8>,
+}
+
+/// A frame that cannot be decoded; the connection should be closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    UnknownOpcode(u8),
+    PayloadTooLarge(u64),
+}
+
+/// `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`
+pub fn accept_key(client_key: &str) -> String {
+    let mut input = Vec::with_capacity(client_key.len() + HANDSHAKE_GUID.len());
+    input.extend_from_slice(client_key.trim().as_bytes());
+    input.extend_from_slice(HANDSHAKE_GUID.as_bytes());
+    base64_encode(&sha1(&input))
+}
+
+/// Encode a single, final frame. Clients must pass a `mask` key.
+pub fn encode_frame(opcode: Opcode, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 14);
+    out.push(0x80 | opcode.bits());
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => out.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            out.push(mask_bit | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(mask_bit | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match mask {
+        Some(key) => {
+            out.extend_from_slice(&key);
+            out.extend(payload.iter().enumerate().map(|(i, &b)| b ^ key[i % 4]));
+        }
+        None => out.extend_from_slice(payload),
+    }
+    out
+}
+
+/// Close frame carrying a status code
+pub fn encode_close(code: u16, mask: Option<[u8; 4]>) -> Vec<u8> {
+    encode_frame(Opcode::Close, &code.to_be_bytes(), mask)
+}
+
+/// Decode the frame at the start of `data`
+///
+/// Returns the frame and the number of bytes it used, or `Ok(None)` if more
+/// data is needed.
+pub fn decode_frame(data: &[u8]) -> Result<Option<(Frame, usize)>, FrameError> {
+    if data.len() < 2 {
+        return Ok(None);
+    }
+
+    let fin = data[0] & 0x80 != 0;
+    let opcode =
+        Opcode::from_bits(data[0] & 0x0F).ok_or(FrameError::UnknownOpcode(data[0] & 0x0F))?;
+    let masked = data[1] & 0x80 != 0;
+
+    let (len, mut pos) = match data[1] & 0x7F {
+        126 => {
+            let Some(bytes) = data.get(2..4) else {
+                return Ok(None);
+            };
+            (u16::from_be_bytes([bytes[0], bytes[1]]) as u64, 4)
+        }
+        127 => {
+            let Some(bytes) = data.get(2..10) else {
+                return Ok(None);
+            };
+            let mut be = [0u8; 8];
+            be.copy_from_slice(bytes);
+            (u64::from_be_bytes(be), 10)
+        }
+        len => (len as u64, 2),
+    };
+    if len > MAX_PAYLOAD as u64 {
+        return Err(FrameError::PayloadTooLarge(len));
+    }
+    let len = len as usize;
+
+    let mask = if masked {
+        let Some(bytes) = data.get(pos..pos + 4) else {
+            return Ok(None);
+        };
+        pos += 4;
+        Some([bytes[0], bytes[1], bytes[2], bytes[3]])
+    } else {
+        None
+    };
+
+    let Some(payload) = data.get(pos..pos + len) else {
+        return Ok(None);
+    };
+    let payload = match mask {
+        Some(key) => payload
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| b ^ key[i % 4])
+            .collect(),
+        None => payload.to_vec(),
+    };
+
+    Ok(Some((
+        Frame {
+            fin,
+            opcode,
+            payload,
+        },
+        pos + len,
+    )))
+}
+
+/// SHA-1 digest, needed only for the handshake accept key
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, value) in digest.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key_rfc_example() {
+        // Example handshake from RFC 6455 section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_base64_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_frame_roundtrip_masked_and_unmasked() {
+        let payload = br#"{"type":"position","position":{"x":1.0,"y":2.0}}"#;
+        for mask in [None, Some([0x37, 0xfa, 0x21, 0x3d])] {
+            let encoded = encode_frame(Opcode::Text, payload, mask);
+            let (frame, used) = decode_frame(&encoded).unwrap().unwrap();
+            assert_eq!(used, encoded.len());
+            assert!(frame.fin);
+            assert_eq!(frame.opcode, Opcode::Text);
+            assert_eq!(frame.payload, payload);
+        }
+    }
+
+    #[test]
+    fn test_decode_rfc_masked_hello() {
+        // Masked "Hello" from RFC 6455 section 5.7
+        let data = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let (frame, used) = decode_frame(&data).unwrap().unwrap();
+        assert_eq!(used, data.len());
+        assert_eq!(frame.payload, b"Hello");
+    }
+
+    #[test]
+    fn test_decode_needs_more_data() {
+        let encoded = encode_frame(Opcode::Binary, &[7u8; 300], Some([1, 2, 3, 4]));
+        assert_eq!(encoded[1] & 0x7F, 126);
+        for end in [0, 1, 3, 7, encoded.len() - 1] {
+            assert_eq!(decode_frame(&encoded[..end]), Ok(None));
+        }
+
+        // Two frames back to back decode one at a time
+        let mut stream = encode_frame(Opcode::Ping, b"hi", None);
+        stream.extend(encode_close(CLOSE_NORMAL, None));
+        let (first, used) = decode_frame(&stream).unwrap().unwrap();
+        assert_eq!(first.opcode, Opcode::Ping);
+        let (second, _) = decode_frame(&stream[used..]).unwrap().unwrap();
+        assert_eq!(second.opcode, Opcode::Close);
+        assert_eq!(second.payload, CLOSE_NORMAL.to_be_bytes());
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_frames() {
+        assert_eq!(
+            decode_frame(&[0x83, 0x00]),
+            Err(FrameError::UnknownOpcode(3))
+        );
+
+        let mut huge = vec![0x82, 0x7F];
+        huge.extend_from_slice(&(1u64 << 32).to_be_bytes());
+        assert_eq!(
+            decode_frame(&huge),
+            Err(FrameError::PayloadTooLarge(1 << 32))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Minimal page driven entirely from Rust
+//!
+//! Creates its own canvas, builds a `BoidSimulation` on it and runs the
+//! animation loop with `requestAnimationFrame`, without any of the JavaScript
+//! glue in `www/index.js`. Clicking and dragging makes the flock seek the
+//! pointer.
+//!
+//! Build with:
+//! ```text
+//! cargo build -p boid-wasm --example minimal_page --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir www/examples/pkg \
+//!     target/wasm32-unknown-unknown/debug/examples/minimal_page.wasm
+//! ```
+//! and load `pkg/minimal_page.js` from a page with an empty `<body>`.
+
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<(), wasm_bindgen::JsValue> {
+    use boid_wasm::BoidSimulation;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 600.0;
+
+    let window = web_sys::window().ok_or("no global window")?;
+    let document = window.document().ok_or("no document")?;
+
+    let canvas = document.create_element("canvas")?;
+    canvas.set_id("boid-canvas");
+    document.body().ok_or("no body")?.append_child(&canvas)?;
+
+    let simulation = Rc::new(RefCell::new(BoidSimulation::new(
+        "boid-canvas",
+        WIDTH,
+        HEIGHT,
+        100,
+    )?));
+
+    // Pointer handlers feed the same API the JavaScript frontend uses
+    let sim = simulation.clone();
+    let on_down = Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |e: web_sys::MouseEvent| {
+        sim.borrow_mut()
+            .handle_pointer_down(e.offset_x() as f64, e.offset_y() as f64);
+    });
+    canvas.add_event_listener_with_callback("mousedown", on_down.as_ref().unchecked_ref())?;
+    on_down.forget();
+
+    let sim = simulation.clone();
+    let on_move = Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |e: web_sys::MouseEvent| {
+        sim.borrow_mut()
+            .handle_pointer_move(e.offset_x() as f64, e.offset_y() as f64);
+    });
+    canvas.add_event_listener_with_callback("mousemove", on_move.as_ref().unchecked_ref())?;
+    on_move.forget();
+
+    let sim = simulation.clone();
+    let on_up = Closure::<dyn FnMut()>::new(move || sim.borrow_mut().handle_pointer_up());
+    canvas.add_event_listener_with_callback("mouseup", on_up.as_ref().unchecked_ref())?;
+    on_up.forget();
+
+    // The frame callback has to reschedule itself, so it keeps a handle to its own closure
+    let frame = Rc::new(RefCell::new(None::<Closure<dyn FnMut()>>));
+    let next_frame = frame.clone();
+    *frame.borrow_mut() = Some(Closure::new(move || {
+        let mut sim = simulation.borrow_mut();
+        sim.update();
+        sim.render().ok();
+
+        if let Some(window) = web_sys::window() {
+            if let Some(callback) = next_frame.borrow().as_ref() {
+                window
+                    .request_animation_frame(callback.as_ref().unchecked_ref())
+                    .ok();
+            }
+        }
+    }));
+
+    let callback = frame.borrow();
+    window.request_animation_frame(callback.as_ref().unwrap().as_ref().unchecked_ref())?;
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    println!("This example runs in the browser; build it for wasm32-unknown-unknown.");
+}
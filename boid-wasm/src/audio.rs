@@ -0,0 +1,177 @@
+//! Spatialized sound for installations
+//!
+//! The largest clusters each get a soft sine voice. A cluster's centroid
+//! pans its voice between the speakers and fades it toward the canvas
+//! edges, and the cluster's mean speed raises its pitch. Parameters glide to
+//! their new values, so voices swell and fade as clusters split and merge
+//! instead of clicking.
+
+use boid_core::{Boid, Vector2D};
+use wasm_bindgen::JsValue;
+use web_sys::{AudioContext, GainNode, OscillatorNode, OscillatorType, StereoPannerNode};
+
+/// Most clusters voiced at once; smaller clusters stay silent
+const MAX_VOICES: usize = 6;
+/// Clusters smaller than this are stragglers, not worth a voice
+const MIN_CLUSTER_SIZE: usize = 3;
+/// Pitch of the largest cluster at rest
+const BASE_FREQUENCY: f32 = 220.0;
+/// Semitones above the base per voice: a pentatonic scale never clashes
+const SCALE: [f32; MAX_VOICES] = [0.0, 2.0, 4.0, 7.0, 9.0, 12.0];
+/// Loudness of a voice holding the whole flock, before the master volume
+const VOICE_GAIN: f32 = 0.3;
+/// Seconds for parameters to cover most of the way to a new value
+const GLIDE_SECS: f64 = 0.25;
+
+/// What one cluster should sound like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Voice {
+    /// -1 (left) to 1 (right)
+    pub pan: f32,
+    pub gain: f32,
+    pub frequency: f32,
+}
+
+/// Voices for `clusters` (indices into `boids`, largest first)
+pub fn voices(
+    boids: &[Boid],
+    clusters: &[Vec<usize>],
+    width: f32,
+    height: f32,
+    max_speed: f32,
+) -> Vec<Voice> {
+    let center = Vector2D::new(width / 2.0, height / 2.0);
+    let half_diagonal = center.magnitude().max(f32::EPSILON);
+
+    clusters
+        .iter()
+        .filter(|cluster| cluster.len() >= MIN_CLUSTER_SIZE)
+        .take(MAX_VOICES)
+        .zip(SCALE)
+        .map(|(cluster, semitones)| {
+            let size = cluster.len() as f32;
+            let (sum, speed) = cluster
+                .iter()
+                .map(|&i| &boids[i])
+                .fold((Vector2D::zero(), 0.0), |(sum, speed), boid| {
+                    (sum + boid.position, speed + boid.velocity.magnitude())
+                });
+            let centroid = sum / size;
+            let speed = if max_speed > 0.0 {
+                (speed / size / max_speed).min(1.0)
+            } else {
+                0.0
+            };
+
+            let pan = (centroid.x / width.max(f32::EPSILON) * 2.0 - 1.0).clamp(-1.0, 1.0);
+            let attenuation = 1.0 - 0.6 * (centroid.distance(&center) / half_diagonal).min(1.0);
+            let share = (size / boids.len() as f32).sqrt();
+            Voice {
+                pan,
+                gain: VOICE_GAIN * share * attenuation,
+                frequency: BASE_FREQUENCY * (semitones / 12.0).exp2() * (1.0 + 0.5 * speed),
+            }
+        })
+        .collect()
+}
+
+struct VoiceNodes {
+    oscillator: OscillatorNode,
+    gain: GainNode,
+    panner: StereoPannerNode,
+}
+
+/// Web Audio graph: one oscillator, gain and panner per voice, summed into
+/// a master gain
+pub struct SoundField {
+    context: AudioContext,
+    master: GainNode,
+    voices: Vec<VoiceNodes>,
+}
+
+impl SoundField {
+    /// Browsers only allow audio to start from a user gesture, so call this
+    /// from an input handler
+    pub fn new(volume: f32) -> Result<Self, JsValue> {
+        let context = AudioContext::new()?;
+        let master = context.create_gain()?;
+        master.gain().set_value(volume);
+        master.connect_with_audio_node(&context.destination())?;
+        let _ = context.resume()?;
+        Ok(Self {
+            context,
+            master,
+            voices: Vec::new(),
+        })
+    }
+
+    pub fn set_volume(&self, volume: f32) -> Result<(), JsValue> {
+        self.master
+            .gain()
+            .set_target_at_time(volume, self.context.current_time(), GLIDE_SECS)?;
+        Ok(())
+    }
+
+    /// Glide toward `voices`; voices no longer needed fade out but keep
+    /// running, ready for the next cluster
+    pub fn play(&mut self, voices: &[Voice]) -> Result<(), JsValue> {
+        while self.voices.len() < voices.len() {
+            let nodes = self.create_voice()?;
+            self.voices.push(nodes);
+        }
+
+        let now = self.context.current_time();
+        for (index, nodes) in self.voices.iter().enumerate() {
+            match voices.get(index) {
+                Some(voice) => {
+                    nodes
+                        .gain
+                        .gain()
+                        .set_target_at_time(voice.gain, now, GLIDE_SECS)?;
+                    nodes
+                        .panner
+                        .pan()
+                        .set_target_at_time(voice.pan, now, GLIDE_SECS)?;
+                    nodes.oscillator.frequency().set_target_at_time(
+                        voice.frequency,
+                        now,
+                        GLIDE_SECS,
+                    )?;
+                }
+                None => {
+                    nodes.gain.gain().set_target_at_time(0.0, now, GLIDE_SECS)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn create_voice(&self) -> Result<VoiceNodes, JsValue> {
+        let oscillator = self.context.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Sine);
+        let gain = self.context.create_gain()?;
+        // Start silent and fade in
+        gain.gain().set_value(0.0);
+        let panner = self.context.create_stereo_panner()?;
+
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&panner)?;
+        panner.connect_with_audio_node(&self.master)?;
+        oscillator.start()?;
+
+        Ok(VoiceNodes {
+            oscillator,
+            gain,
+            panner,
+        })
+    }
+
+    /// Stop every voice and release the audio device
+    pub fn close(self) -> Result<(), JsValue> {
+        for nodes in &self.voices {
+            nodes.oscillator.stop()?;
+        }
+        let _ = self.context.close()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Which hand tracker drives the hands, with automatic fallback
+//!
+//! Three trackers can feed hands in, best first: MediaPipe Hands and
+//! OpenCV.js run in JavaScript and pass their landmarks on, and the
+//! built-in skin detector runs on the frames given to
+//! `process_video_frame`. The page loads the JavaScript libraries itself,
+//! and a missing or broken script used to leave hand control dead without
+//! a word. The manager picks the first tracker that is available and keeps
+//! up with the camera, and the page asks it which one to run.
+//!
+//! The skin detector needs no script, so it is always there to fall back
+//! to. A tracker that takes longer than [`FRAME_BUDGET_MS`] per frame on
+//! average is passed over for the next one, unless it was forced.
+
+/// Frame time above which a tracker falls behind a 30 fps camera
+pub const FRAME_BUDGET_MS: f64 = 1000.0 / 30.0;
+/// Frames timed before a tracker can be judged too slow
+const MIN_COST_SAMPLES: u32 = 10;
+/// Weight of each new frame time in the running average
+const COST_SMOOTHING: f64 = 0.1;
+
+/// A hand tracker, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandBackend {
+    /// MediaPipe Hands, in JavaScript
+    MediaPipe,
+    /// Contours found with OpenCV.js, in JavaScript
+    OpenCv,
+    /// The built-in skin detector
+    Skin,
+}
+
+impl HandBackend {
+    /// In the order they are tried
+    pub const CHAIN: [HandBackend; 3] = [
+        HandBackend::MediaPipe,
+        HandBackend::OpenCv,
+        HandBackend::Skin,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::CHAIN
+            .into_iter()
+            .find(|backend| backend.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MediaPipe => "mediapipe",
+            Self::OpenCv => "opencv",
+            Self::Skin => "skin",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BackendState {
+    available: bool,
+    /// Running average of the frame time in milliseconds
+    cost_ms: Option<f64>,
+    samples: u32,
+}
+
+/// Availability and frame cost of every tracker
+#[derive(Debug, Clone)]
+pub struct BackendManager {
+    states: [BackendState; 3],
+    forced: Option<HandBackend>,
+}
+
+impl BackendManager {
+    /// Only the skin detector is available until the page reports more
+    pub fn new() -> Self {
+        let mut states = [BackendState::default(); 3];
+        states[HandBackend::Skin.index()].available = true;
+        Self {
+            states,
+            forced: None,
+        }
+    }
+
+    /// Mark a tracker as loaded, or as gone after it failed. The skin
+    /// detector stays available whatever is reported.
+    pub fn set_available(&mut self, backend: HandBackend, available: bool) {
+        let state = &mut self.states[backend.index()];
+        state.available = available || backend == HandBackend::Skin;
+        // A reloaded tracker gets a fresh chance to keep up
+        state.cost_ms = None;
+        state.samples = 0;
+    }
+
+    pub fn is_available(&self, backend: HandBackend) -> bool {
+        self.states[backend.index()].available
+    }
+
+    /// Time one frame of `backend`
+    pub fn record_cost(&mut self, backend: HandBackend, ms: f64) {
+        let state = &mut self.states[backend.index()];
+        state.cost_ms = Some(match state.cost_ms {
+            Some(cost) => cost + (ms - cost) * COST_SMOOTHING,
+            None => ms,
+        });
+        state.samples = state.samples.saturating_add(1);
+    }
+
+    /// Average frame time in milliseconds, once a frame was timed
+    pub fn cost_ms(&self, backend: HandBackend) -> Option<f64> {
+        self.states[backend.index()].cost_ms
+    }
+
+    pub fn is_too_slow(&self, backend: HandBackend) -> bool {
+        let state = &self.states[backend.index()];
+        state.samples >= MIN_COST_SAMPLES && state.cost_ms.is_some_and(|ms| ms > FRAME_BUDGET_MS)
+    }
+
+    /// Run `backend` whatever its cost while it is available; `None`
+    /// returns to picking automatically
+    pub fn force(&mut self, backend: Option<HandBackend>) {
+        self.forced = backend;
+    }
+
+    pub fn forced(&self) -> Option<HandBackend> {
+        self.forced
+    }
+
+    /// The tracker to run: the forced one while it is available, else the
+    /// first available one that keeps up, else the skin detector
+    pub fn active(&self) -> HandBackend {
+        if let Some(forced) = self.forced.filter(|&forced| self.is_available(forced)) {
+            return forced;
+        }
+        HandBackend::CHAIN
+            .into_iter()
+            .find(|&backend| self.is_available(backend) && !self.is_too_slow(backend))
+            .unwrap_or(HandBackend::Skin)
+    }
+}
+
+impl Default for BackendManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
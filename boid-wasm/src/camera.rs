@@ -0,0 +1,121 @@
+//! Viewport onto the simulation area
+//!
+//! The camera shows the world (where boids live) on the canvas, looking at
+//! `center` magnified by `zoom`. At rest it frames the whole area; while
+//! following, it glides after the flock, a named group or a single boid,
+//! and can zoom to keep the followed boids in view.
+
+use boid_core::Vector2D;
+
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 8.0;
+
+/// Share of the way to the target the camera still has to go after each
+/// frame by default; 0 snaps, values near 1 lag far behind
+pub const DEFAULT_DAMPING: f32 = 0.9;
+
+// World units kept clear around the followed boids when zooming to fit
+const FIT_PADDING: f32 = 80.0;
+
+/// What the camera follows
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Follow {
+    /// The view stays where it was put
+    #[default]
+    Off,
+    /// Centroid of all boids
+    Flock,
+    /// Centroid of a group made with `select_*`
+    Group(String),
+    /// A boid, by id
+    Boid(u32),
+}
+
+impl Follow {
+    /// `"off"`, `"flock"`, `"group:<name>"` or `"boid:<id>"`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            None if name == "off" => Some(Self::Off),
+            None if name == "flock" => Some(Self::Flock),
+            Some(("group", group)) if !group.is_empty() => Some(Self::Group(group.to_string())),
+            Some(("boid", id)) => id.parse().ok().map(Self::Boid),
+            _ => None,
+        }
+    }
+
+    pub fn to_name(&self) -> String {
+        match self {
+            Self::Off => "off".to_string(),
+            Self::Flock => "flock".to_string(),
+            Self::Group(group) => format!("group:{}", group),
+            Self::Boid(id) => format!("boid:{}", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    /// World point in the middle of the canvas
+    pub center: Vector2D,
+    pub zoom: f32,
+    pub follow: Follow,
+    pub damping: f32,
+    /// Zoom so the followed boids fill the canvas
+    pub zoom_to_fit: bool,
+}
+
+impl Camera {
+    /// Framing the whole `width` x `height` area
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            center: Vector2D::new(width / 2.0, height / 2.0),
+            zoom: 1.0,
+            follow: Follow::Off,
+            damping: DEFAULT_DAMPING,
+            zoom_to_fit: false,
+        }
+    }
+
+    pub fn set_view(&mut self, center: Vector2D, zoom: f32) {
+        self.center = center;
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Move a step towards the centroid of `points`, and towards the zoom
+    /// that fits them into a `viewport` sized canvas. No points, no move.
+    pub fn track(&mut self, points: impl IntoIterator<Item = Vector2D>, viewport: Vector2D) {
+        let mut count = 0;
+        let mut sum = Vector2D::zero();
+        let mut min = Vector2D::new(f32::MAX, f32::MAX);
+        let mut max = Vector2D::new(f32::MIN, f32::MIN);
+        for point in points {
+            count += 1;
+            sum += point;
+            min = Vector2D::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vector2D::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        if count == 0 {
+            return;
+        }
+
+        let target = sum / count as f32;
+        self.center = target + (self.center - target) * self.damping;
+        if self.zoom_to_fit {
+            let extent = max - min + Vector2D::new(2.0 * FIT_PADDING, 2.0 * FIT_PADDING);
+            let fit = (viewport.x / extent.x)
+                .min(viewport.y / extent.y)
+                .clamp(MIN_ZOOM, MAX_ZOOM);
+            self.zoom = fit + (self.zoom - fit) * self.damping;
+        }
+    }
+
+    /// Canvas pixel showing world point `point`
+    pub fn world_to_screen(&self, point: Vector2D, viewport: Vector2D) -> Vector2D {
+        (point - self.center) * self.zoom + viewport / 2.0
+    }
+
+    /// World point under canvas pixel `point`
+    pub fn screen_to_world(&self, point: Vector2D, viewport: Vector2D) -> Vector2D {
+        (point - viewport / 2.0) / self.zoom + self.center
+    }
+}
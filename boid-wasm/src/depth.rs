@@ -0,0 +1,46 @@
+//! Cheap depth cues drawn beneath the boids
+//!
+//! Shadows are soft, offset dark copies of each boid; reflections mirror
+//! the boids above a horizon line into the "water" below it, fading with
+//! distance from the horizon. How each looks comes from the color theme's
+//! `DepthStyle`.
+
+/// Default horizon, as a fraction of the canvas height from the top
+pub const DEFAULT_HORIZON: f64 = 0.75;
+
+/// Depth cue drawn beneath the boids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthEffect {
+    #[default]
+    None,
+    Shadow,
+    Reflection,
+}
+
+impl DepthEffect {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "shadow" => Some(Self::Shadow),
+            "reflection" => Some(Self::Reflection),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Shadow => "shadow",
+            Self::Reflection => "reflection",
+        }
+    }
+}
+
+/// Opacity of a boid's reflection `depth` pixels above a horizon `reach`
+/// pixels below the top of the canvas: full at the horizon, gone at the top
+pub fn reflection_alpha(opacity: f32, depth: f64, reach: f64) -> f64 {
+    if reach <= 0.0 || depth < 0.0 {
+        return 0.0;
+    }
+    opacity as f64 * (1.0 - depth / reach).max(0.0)
+}
@@ -0,0 +1,175 @@
+//! Named selections of boids for group-targeted commands
+//!
+//! Boids are tracked by their stable id, so groups survive other boids being
+//! added or removed. A boid belongs to at most one group; selecting it into a
+//! new group takes it out of the old one.
+
+use boid_core::{Boid, TargetOverride, Vector2D};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Default)]
+pub struct BoidGroup {
+    pub members: BTreeSet<u32>,
+    /// Target applied to every member
+    pub target: TargetOverride,
+    /// Render hue (degrees) overriding the speed-based color
+    pub hue: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BoidGroups {
+    groups: BTreeMap<String, BoidGroup>,
+    membership: HashMap<u32, String>,
+}
+
+impl BoidGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move `ids` into the group `name`, creating it if needed.
+    /// Returns the resulting group size.
+    pub fn assign(&mut self, name: &str, ids: impl IntoIterator<Item = u32>) -> usize {
+        for id in ids {
+            self.remove_member(id);
+            self.membership.insert(id, name.to_string());
+            self.groups
+                .entry(name.to_string())
+                .or_default()
+                .members
+                .insert(id);
+        }
+        self.get(name).map_or(0, |group| group.members.len())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BoidGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut BoidGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// Group the boid with `id` currently belongs to
+    pub fn group_of(&self, id: u32) -> Option<&BoidGroup> {
+        self.membership
+            .get(&id)
+            .and_then(|name| self.groups.get(name))
+    }
+
+    pub fn is_member(&self, name: &str, id: u32) -> bool {
+        self.membership.get(&id).is_some_and(|group| group == name)
+    }
+
+    /// Take a boid out of its group, dropping the group once it is empty
+    pub fn remove_member(&mut self, id: u32) {
+        let Some(name) = self.membership.remove(&id) else {
+            return;
+        };
+        if let Some(group) = self.groups.get_mut(&name) {
+            group.members.remove(&id);
+            if group.members.is_empty() {
+                self.groups.remove(&name);
+            }
+        }
+    }
+
+    /// Take every boid `keep` rejects out of its group, dropping the groups
+    /// left empty
+    pub fn retain_members(&mut self, mut keep: impl FnMut(u32) -> bool) {
+        let gone: Vec<u32> = self
+            .membership
+            .keys()
+            .copied()
+            .filter(|&id| !keep(id))
+            .collect();
+        for id in gone {
+            self.remove_member(id);
+        }
+    }
+
+    /// Delete a group and return its former members
+    pub fn dissolve(&mut self, name: &str) -> Vec<u32> {
+        let Some(group) = self.groups.remove(name) else {
+            return Vec::new();
+        };
+        for id in &group.members {
+            self.membership.remove(id);
+        }
+        group.members.into_iter().collect()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.groups.keys().map(String::as_str)
+    }
+}
+
+/// Ids of the boids inside the rectangle spanned by two corners
+pub fn select_rect(boids: &[Boid], corner_a: Vector2D, corner_b: Vector2D) -> Vec<u32> {
+    let (min_x, max_x) = (corner_a.x.min(corner_b.x), corner_a.x.max(corner_b.x));
+    let (min_y, max_y) = (corner_a.y.min(corner_b.y), corner_a.y.max(corner_b.y));
+    boids
+        .iter()
+        .filter(|boid| {
+            (min_x..=max_x).contains(&boid.position.x) && (min_y..=max_y).contains(&boid.position.y)
+        })
+        .map(|boid| boid.id)
+        .collect()
+}
+
+/// Ids of the boids connected to the boid nearest `point` by chains of
+/// neighbors closer than `link_distance`
+pub fn select_cluster(boids: &[Boid], point: Vector2D, link_distance: f32) -> Vec<u32> {
+    let Some(start) = boids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            a.position
+                .distance(&point)
+                .total_cmp(&b.position.distance(&point))
+        })
+        .map(|(index, _)| index)
+    else {
+        return Vec::new();
+    };
+
+    let mut visited = vec![false; boids.len()];
+    let mut cluster: Vec<u32> = flood(boids, start, link_distance, &mut visited)
+        .into_iter()
+        .map(|index| boids[index].id)
+        .collect();
+    cluster.sort_unstable();
+    cluster
+}
+
+/// Split the flock into clusters, each a chain of neighbors closer than
+/// `link_distance`, as indices into `boids`; largest cluster first
+pub fn clusters(boids: &[Boid], link_distance: f32) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; boids.len()];
+    let mut clusters = Vec::new();
+    for start in 0..boids.len() {
+        if !visited[start] {
+            clusters.push(flood(boids, start, link_distance, &mut visited));
+        }
+    }
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.len()));
+    clusters
+}
+
+/// Indices of the unvisited boids reachable from `start`, marking them
+fn flood(boids: &[Boid], start: usize, link_distance: f32, visited: &mut [bool]) -> Vec<usize> {
+    visited[start] = true;
+    let mut queue = vec![start];
+    let mut cluster = Vec::new();
+
+    while let Some(index) = queue.pop() {
+        cluster.push(index);
+        for (other, boid) in boids.iter().enumerate() {
+            if !visited[other] && boid.position.distance(&boids[index].position) < link_distance {
+                visited[other] = true;
+                queue.push(other);
+            }
+        }
+    }
+    cluster
+}
@@ -0,0 +1,150 @@
+//! Per-hand finger tracking for one or two hands
+//!
+//! Each hand keeps its own pinch state machine, so the two hands pinch and
+//! release independently. Single-hand input (`update_finger_positions`,
+//! the built-in detector) drives the right hand. Readings pass through the
+//! hand's landmark smoother before they are stored.
+
+use boid_core::Vector2D;
+use boid_shared::{
+    Finger, FullHandLandmarks, HandGesture, Handedness, LandmarkSmoother, PinchStateMachine,
+    Position, Smoothing,
+};
+
+/// Finger tips and pinch state of one tracked hand
+#[derive(Debug, Clone)]
+pub struct HandInput {
+    pub thumb: Option<Vector2D>,
+    pub index: Option<Vector2D>,
+    /// All 21 points in canvas coordinates, when the tracker provides them
+    pub landmarks: Option<FullHandLandmarks>,
+    pub pinch: PinchStateMachine,
+    pub smoother: LandmarkSmoother,
+}
+
+impl HandInput {
+    pub fn new(pinch: PinchStateMachine) -> Self {
+        Self {
+            thumb: None,
+            index: None,
+            landmarks: None,
+            pinch,
+            smoother: LandmarkSmoother::disabled(),
+        }
+    }
+
+    /// Track the finger tips only; drops any earlier full landmark set
+    pub fn set(&mut self, thumb: Vector2D, index: Vector2D) {
+        let thumb = self
+            .smoother
+            .smooth_point(Finger::Thumb.tip(), to_position(thumb));
+        let index = self
+            .smoother
+            .smooth_point(Finger::Index.tip(), to_position(index));
+        self.thumb = Some(to_vector(thumb));
+        self.index = Some(to_vector(index));
+        self.landmarks = None;
+    }
+
+    /// Track all landmarks, taking the finger tips from them
+    pub fn set_landmarks(&mut self, landmarks: FullHandLandmarks) {
+        let landmarks = self.smoother.smooth_full(&landmarks);
+        let tips = landmarks.to_hand_landmarks();
+        self.thumb = Some(to_vector(tips.thumb_tip));
+        self.index = Some(to_vector(tips.index_tip));
+        self.landmarks = Some(landmarks);
+    }
+
+    pub fn clear(&mut self) {
+        self.thumb = None;
+        self.index = None;
+        self.landmarks = None;
+        self.smoother.reset();
+    }
+
+    /// Recognized gesture and the point it acts on: the index tip when
+    /// pointing, the palm center otherwise
+    pub fn gesture(&self) -> Option<(HandGesture, Vector2D)> {
+        let landmarks = self.landmarks.as_ref()?;
+        let gesture = landmarks.gesture()?;
+        let anchor = match gesture {
+            HandGesture::Pointing => self.index?,
+            HandGesture::Fist | HandGesture::OpenPalm => to_vector(landmarks.palm_center()),
+        };
+        Some((gesture, anchor))
+    }
+
+    /// Both finger tips, if the hand is currently tracked
+    pub fn fingers(&self) -> Option<(Vector2D, Vector2D)> {
+        Some((self.thumb?, self.index?))
+    }
+
+    pub fn finger_distance(&self) -> Option<f32> {
+        self.fingers().map(|(thumb, index)| thumb.distance(&index))
+    }
+
+    pub fn midpoint(&self) -> Option<Vector2D> {
+        self.fingers().map(|(thumb, index)| {
+            Vector2D::new((thumb.x + index.x) / 2.0, (thumb.y + index.y) / 2.0)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hands {
+    right: HandInput,
+    left: HandInput,
+}
+
+impl Hands {
+    pub fn new(pinch: PinchStateMachine) -> Self {
+        Self {
+            right: HandInput::new(pinch.clone()),
+            left: HandInput::new(pinch),
+        }
+    }
+
+    pub fn get(&self, handedness: Handedness) -> &HandInput {
+        match handedness {
+            Handedness::Right => &self.right,
+            Handedness::Left => &self.left,
+        }
+    }
+
+    pub fn get_mut(&mut self, handedness: Handedness) -> &mut HandInput {
+        match handedness {
+            Handedness::Right => &mut self.right,
+            Handedness::Left => &mut self.left,
+        }
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.right.smoother.set_smoothing(smoothing);
+        self.left.smoother.set_smoothing(smoothing);
+    }
+
+    /// Both hands, right first
+    pub fn iter(&self) -> impl Iterator<Item = (Handedness, &HandInput)> {
+        [
+            (Handedness::Right, &self.right),
+            (Handedness::Left, &self.left),
+        ]
+        .into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handedness, &mut HandInput)> {
+        [
+            (Handedness::Right, &mut self.right),
+            (Handedness::Left, &mut self.left),
+        ]
+        .into_iter()
+    }
+}
+
+fn to_vector(position: Position) -> Vector2D {
+    Vector2D::new(position.x, position.y)
+}
+
+fn to_position(vector: Vector2D) -> Position {
+    Position::new(vector.x, vector.y)
+}
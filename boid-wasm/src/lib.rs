@@ -1,8 +1,51 @@
-use boid_core::{Boid, FlockStd, Vector2D};
-use boid_hand_detector::HandDetector;
+mod audio;
+mod backends;
+mod camera;
+mod depth;
+mod groups;
+mod hands;
+mod lod;
+mod metrics;
+mod mirror;
+mod music;
+mod sprites;
+
+use audio::SoundField;
+use backends::{BackendManager, HandBackend};
+use boid_core::mapping::{InputMapping, InputSignal, InputSignals, MappingTable};
+use boid_core::step::{StepEvent, Stimulus};
+use boid_core::{
+    formation,
+    obstacles::{ExclusionRects, ObstacleMap, Rect},
+    particles::ParticleEmitter,
+    Boid, BoidConfig, BoundaryBehavior, ChangeSource, ConfigChange, ConfigHistory, ConfigModifiers,
+    EcologyConfig, FlockEvent, FlockStd, Gust, Marker, NeighborRadius, PerchConfig, PointerMode,
+    PopulationLimit, Raster, Region, StepInput, TargetInterpolator, TargetMode, TargetOverride,
+    TickTiming, TimelineConfig, TimelineEntry, Vector2D,
+};
+use boid_hand_detector::{ArtifactLayer, HandDetector, DEFAULT_WORKING_WIDTH};
+use boid_shared::demo::{DemoPlayer, DemoSchedule, DepthStyle, Palette, Theme};
+use boid_shared::mirror::FlockFrame;
+use boid_shared::style::{self, BoidShape};
+use boid_shared::{
+    ColorScheme, ErrorCode, FullHandLandmarks, HandGesture, HandLandmarks, Handedness,
+    LandmarkSmoother, MultiHandLandmarks, PinchStateMachine, Position,
+};
+use camera::{Camera, Follow};
+use depth::{DepthEffect, DEFAULT_HORIZON};
+use groups::BoidGroups;
+use hands::Hands;
+use lod::LodFocus;
+use metrics::{MetricsCollector, SessionMetrics, UNNAMED_PRESET};
+use mirror::RemoteFlock;
+use music::{Curve, Mapping, MusicDriver};
+use sprites::{SpriteAtlas, SpriteMode};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlVideoElement, ImageData};
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, HtmlVideoElement, ImageData,
+};
 
 #[wasm_bindgen]
 extern "C" {
@@ -19,21 +62,113 @@ pub struct BoidSimulation {
     flock: FlockStd,
     canvas: HtmlCanvasElement,
     context: CanvasRenderingContext2d,
+    /// Canvas size in CSS pixels, the unit of every coordinate the
+    /// simulation takes and gives, whatever the screen's density
+    size: Vector2D,
+    /// Device pixels per CSS pixel in the canvas's backing store
+    pixel_ratio: f64,
     pointer_position: Option<Vector2D>,
     pointer_pressed: bool,
-    thumb_position: Option<Vector2D>,
-    index_position: Option<Vector2D>,
+    pointer_mode: PointerMode,
+    hands: Hands,
     video_element: Option<HtmlVideoElement>,
     wander_enabled: bool,
-    baseline_separation_weight: f32,
-    baseline_max_speed: f32,
     hand_detector: HandDetector,
+    /// Landmarks of the last processed video frame, from 0 to 1 across it
+    detected_hand: Option<HandLandmarks>,
+    /// Which hand tracker the page should run
+    hand_backends: BackendManager,
+    /// Input signals that scale config parameters, see `set_input_mappings`
+    input_mappings: MappingTable,
+    /// Signals of the last update
+    input_signals: InputSignals,
+    /// Midpoint of the lead hand at the last update, and when, for its speed
+    last_hand: Option<(Vector2D, f64)>,
+    config_history: ConfigHistory<CONFIG_HISTORY_SIZE>,
+    groups: BoidGroups,
+    highlighted_group: Option<String>,
+    /// Render hue (degrees) per sub-flock, see `Boid::group`
+    sub_flock_hues: BTreeMap<u8, f64>,
+    performance: Option<web_sys::Performance>,
+    last_tick_timing: TickTiming,
+    sprite_mode: SpriteMode,
+    sprite_atlas: Option<SpriteAtlas>,
+    /// Animation phase in [0, 1) per boid id, advanced while drawing sprites
+    animation_phases: HashMap<u32, f32>,
+    /// Boids drawn in full detail per frame; `None` draws every boid in full
+    lod_budget: Option<usize>,
+    lod_focus: LodFocus,
+    depth_effect: DepthEffect,
+    /// Height of the reflection horizon as a fraction of the canvas height
+    horizon: f64,
+    camera: Camera,
+    /// Rectangles from `set_exclusion_rects`, in canvas CSS pixels
+    exclusion_rects: Vec<Rect>,
+    /// Size, palette and shape of boids drawn as shapes
+    boid_style: style::RenderStyle,
+    /// Kiosk rotation through scenes; user input still takes over the target
+    demo: Option<DemoPlayer>,
+    /// Config to crossfade back to when the demo stops
+    config_before_demo: Option<BoidConfig>,
+    /// Cluster voices; `None` while sound is off
+    sound: Option<SoundField>,
+    sound_volume: f32,
+    /// Ticks until the clusters are analyzed and voiced again
+    sound_countdown: u32,
+    /// Spawn events for an external particle system
+    particles: ParticleEmitter,
+    /// Receives the events after every update; `None` emits nothing
+    particle_callback: Option<js_sys::Function>,
+    /// Receives the flock's events after every update, once set
+    event_callback: Option<js_sys::Function>,
+    /// Drives the flock from analyser data while `audio_reactive`
+    music: MusicDriver,
+    audio_reactive: bool,
+    /// Usage counts for the host page; `None` until it opts in
+    metrics: Option<MetricsCollector>,
+    /// Smooths a single hand or pointer target between input updates;
+    /// `None` while off
+    target_path: Option<TargetInterpolator>,
+    /// Detector artifact shown in a corner, and the offscreen canvas it is
+    /// drawn into after each processed frame
+    detector_pip: Option<(ArtifactLayer, HtmlCanvasElement)>,
+    /// Frames of a device's flock drawn instead of simulating; `None`
+    /// simulates locally
+    mirror: Option<RemoteFlock>,
 }
 
-// Pinch detection threshold in pixels
-const PINCH_THRESHOLD: f32 = 50.0;
-// Maximum distance for scaling parameters (in pixels)
-const MAX_FINGER_DISTANCE: f32 = 300.0;
+// Number of configuration changes kept for undo
+const CONFIG_HISTORY_SIZE: usize = 32;
+
+// Ticks between cluster analyses for sound; voices glide in between
+const SOUND_UPDATE_TICKS: u32 = 6;
+
+// Cohesion multiplier while a fist gathers the flock
+const GATHER_COHESION: f32 = 3.0;
+// Separation multiplier while an open palm scatters the flock
+const SCATTER_SEPARATION: f32 = 4.0;
+// Speed multiplier while an open palm scatters the flock
+const SCATTER_SPEED: f32 = 2.0;
+// Updates per built-in demo scene (~10 s at 60 FPS)
+const DEMO_SCENE_TICKS: u32 = 600;
+// Sprite animation cycles per frame when standing still
+const ANIMATION_BASE_RATE: f32 = 0.03;
+// Extra animation cycles per frame per unit of speed
+const ANIMATION_SPEED_RATE: f32 = 0.02;
+// Width of the detector picture-in-picture as a share of the canvas width
+const DETECTOR_PIP_SCALE: f64 = 0.25;
+// Gap between the picture-in-picture and the canvas corner (CSS pixels)
+const DETECTOR_PIP_MARGIN: f64 = 10.0;
+// Edge push of the "steer-away" boundary, in units of max force
+const STEER_AWAY_WEIGHT: f32 = 2.0;
+// Pipeline entry of the mask loaded with `load_mask`
+const MASK_BEHAVIOR: &str = "obstacles";
+// Pipeline entry of the rectangles set with `set_exclusion_rects`
+const EXCLUSION_BEHAVIOR: &str = "exclusions";
+// Device pixel ratios `set_pixel_ratio` accepts; 4x already exceeds any
+// phone, and a larger backing store would only cost fill rate
+const MIN_PIXEL_RATIO: f64 = 0.25;
+const MAX_PIXEL_RATIO: f64 = 4.0;
 
 #[wasm_bindgen]
 impl BoidSimulation {
@@ -43,103 +178,437 @@ impl BoidSimulation {
         width: f64,
         height: f64,
         boid_count: usize,
+    ) -> Result<BoidSimulation, JsValue> {
+        Self::create(canvas_id, width, height, boid_count, None)
+    }
+
+    /// Like the constructor, but with a seeded RNG so the initial boids and
+    /// wander are reproducible (e.g. for recorded demos)
+    pub fn new_with_seed(
+        canvas_id: &str,
+        width: f64,
+        height: f64,
+        boid_count: usize,
+        seed: u32,
+    ) -> Result<BoidSimulation, JsValue> {
+        Self::create(canvas_id, width, height, boid_count, Some(seed))
+    }
+
+    fn create(
+        canvas_id: &str,
+        width: f64,
+        height: f64,
+        boid_count: usize,
+        seed: Option<u32>,
     ) -> Result<BoidSimulation, JsValue> {
         console_log!("Initializing boid simulation with {} boids", boid_count);
 
-        let window = web_sys::window().ok_or("no global window")?;
-        let document = window.document().ok_or("no document")?;
+        let window = web_sys::window()
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no global window"))?;
+        let document = window
+            .document()
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no document"))?;
         let canvas = document
             .get_element_by_id(canvas_id)
-            .ok_or("canvas not found")?
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "canvas not found"))?
             .dyn_into::<HtmlCanvasElement>()?;
 
-        canvas.set_width(width as u32);
-        canvas.set_height(height as u32);
+        let size = Vector2D::new(width as f32, height as f32);
+        fit_canvas(&canvas, size, 1.0)?;
 
         let context = canvas
             .get_context("2d")?
-            .ok_or("no 2d context")?
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no 2d context"))?
             .dyn_into::<CanvasRenderingContext2d>()?;
 
-        let flock = FlockStd::new(width as f32, height as f32, boid_count);
-
-        // Store baseline values for dynamic adjustment
-        let baseline_separation_weight = flock.config.separation_weight;
-        let baseline_max_speed = flock.config.max_speed;
+        // Grid-based neighbor lookups keep large flocks at interactive frame rates
+        let config = BoidConfig {
+            spatial_grid: true,
+            ..BoidConfig::default()
+        };
+        let (width, height) = (width as f32, height as f32);
+        let flock = match seed {
+            Some(seed) => {
+                FlockStd::new_with_config_and_seed(width, height, boid_count, config, seed as u64)
+            }
+            None => FlockStd::new_with_config(width, height, boid_count, config),
+        };
 
         Ok(BoidSimulation {
             flock,
             canvas,
             context,
+            size,
+            pixel_ratio: 1.0,
             pointer_position: None,
             pointer_pressed: false,
-            thumb_position: None,
-            index_position: None,
+            pointer_mode: PointerMode::default(),
+            hands: Hands::new(PinchStateMachine::default()),
             video_element: None,
             wander_enabled: false,
-            baseline_separation_weight,
-            baseline_max_speed,
-            hand_detector: HandDetector::new(),
+            // Video frames come in whatever size the camera picked
+            hand_detector: HandDetector::new()
+                .with_working_width(Some(DEFAULT_WORKING_WIDTH))
+                .with_exposure_adaptation(true),
+            detected_hand: None,
+            hand_backends: BackendManager::new(),
+            input_mappings: MappingTable::hands(),
+            input_signals: InputSignals::default(),
+            last_hand: None,
+            config_history: ConfigHistory::new(),
+            groups: BoidGroups::new(),
+            highlighted_group: None,
+            sub_flock_hues: BTreeMap::new(),
+            performance: window.performance(),
+            last_tick_timing: TickTiming::default(),
+            sprite_mode: SpriteMode::default(),
+            sprite_atlas: None,
+            animation_phases: HashMap::new(),
+            lod_budget: None,
+            lod_focus: LodFocus::default(),
+            depth_effect: DepthEffect::default(),
+            horizon: DEFAULT_HORIZON,
+            camera: Camera::new(width, height),
+            exclusion_rects: Vec::new(),
+            boid_style: style::RenderStyle::default(),
+            demo: None,
+            config_before_demo: None,
+            sound: None,
+            sound_volume: 0.5,
+            sound_countdown: 0,
+            particles: ParticleEmitter::new(),
+            particle_callback: None,
+            event_callback: None,
+            music: MusicDriver::default(),
+            audio_reactive: false,
+            metrics: None,
+            target_path: None,
+            detector_pip: None,
+            mirror: None,
         })
     }
 
     pub fn update(&mut self) {
-        let target;
-
-        // Check if hand tracking is active
-        if let (Some(thumb), Some(index)) = (self.thumb_position, self.index_position) {
-            // Calculate distance between thumb and index finger
-            let dx = index.x - thumb.x;
-            let dy = index.y - thumb.y;
-            let distance = (dx * dx + dy * dy).sqrt();
-
-            if distance < PINCH_THRESHOLD {
-                // Fingers are pinched - follow the midpoint between fingers
-                let midpoint = Vector2D::new((thumb.x + index.x) / 2.0, (thumb.y + index.y) / 2.0);
-                target = Some(midpoint);
-                console_log!("Pinch detected! Distance: {:.1}px", distance);
+        if self.mirror.is_some() {
+            self.update_mirror();
+            return;
+        }
+        self.advance_demo();
+
+        // One target per pinching hand; each boid follows the nearest
+        let mut targets = [Vector2D::zero(); 2];
+        let mut target_count = 0;
+        let mut open_distance = None;
+        // Midpoint and finger distance of the first hand seen
+        let mut lead_hand = None;
+        let mut gathering = false;
+        let mut scattering = false;
+        let mut tracking = false;
+        let mut pinches_started = 0;
+        // Hands are tracked over the canvas; targets live in the world
+        let (camera, viewport) = (&self.camera, self.viewport());
+        let to_world = |point| camera.screen_to_world(point, viewport);
+
+        for (handedness, hand) in self.hands.iter_mut() {
+            let distance = hand.finger_distance();
+            let was_pinched = hand.pinch.is_pinched();
+            let pinched = hand.pinch.update(distance);
+            pinches_started += (pinched && !was_pinched) as u32;
+            let (Some(midpoint), Some(distance)) = (hand.midpoint(), distance) else {
+                continue;
+            };
+            tracking = true;
+            lead_hand.get_or_insert((midpoint, distance));
+
+            match hand.gesture() {
+                // A fist also reads as a pinch; gather the flock at the palm instead
+                Some((HandGesture::Fist, palm)) => {
+                    targets[target_count] = to_world(palm);
+                    target_count += 1;
+                    gathering = true;
+                }
+                _ if pinched => {
+                    // Fingers are pinched - follow the midpoint between fingers
+                    targets[target_count] = to_world(midpoint);
+                    target_count += 1;
+                    console_log!(
+                        "Pinch detected ({} hand)! Distance: {:.1}px",
+                        handedness.as_str(),
+                        distance
+                    );
+                }
+                Some((HandGesture::Pointing, tip)) => {
+                    targets[target_count] = to_world(tip);
+                    target_count += 1;
+                }
+                Some((HandGesture::OpenPalm, _)) => scattering = true,
+                None => {
+                    if open_distance.is_none() {
+                        open_distance = Some(distance);
+                    }
+                }
+            }
+        }
+
+        let now_ms = self.now_ms();
+        let mut signals = self.read_input_signals(lead_hand, now_ms);
+        if tracking {
+            if gathering || scattering {
+                // Gestures override finger-distance modulation
+                self.flock.modifiers = ConfigModifiers {
+                    cohesion_weight: if gathering { GATHER_COHESION } else { 1.0 },
+                    separation_weight: if scattering { SCATTER_SEPARATION } else { 1.0 },
+                    max_speed: if scattering { SCATTER_SPEED } else { 1.0 },
+                    ..ConfigModifiers::IDENTITY
+                };
             } else {
-                // Fingers are open - adjust separation and speed based on distance
-                target = None;
-
-                // Normalize distance (0.0 to 1.0) based on MAX_FINGER_DISTANCE
-                let normalized_distance = (distance / MAX_FINGER_DISTANCE).min(1.0);
-
-                // Scale separation weight: larger distance = more separation
-                // Range: baseline to baseline * 3
-                self.flock.config.separation_weight =
-                    self.baseline_separation_weight * (1.0 + normalized_distance * 2.0);
-
-                // Scale max speed: larger distance = faster movement
-                // Range: baseline to baseline * 2.5
-                self.flock.config.max_speed =
-                    self.baseline_max_speed * (1.0 + normalized_distance * 1.5);
-
-                console_log!(
-                    "Open fingers - Distance: {:.1}px, Separation: {:.2}, Speed: {:.2}",
-                    distance,
-                    self.flock.config.separation_weight,
-                    self.flock.config.max_speed
-                );
+                // With two open hands the right one wins
+                self.flock.modifiers = ConfigModifiers::IDENTITY;
+                signals.finger_distance = open_distance;
             }
         } else {
-            // No hand detected - restore baseline values and check for mouse/touch pointer
-            self.flock.config.separation_weight = self.baseline_separation_weight;
-            self.flock.config.max_speed = self.baseline_max_speed;
+            // No hand detected - drop gesture modulation and check for mouse/touch pointer
+            self.flock.modifiers = ConfigModifiers::IDENTITY;
+
+            if let (true, Some(pointer)) = (self.pointer_pressed, self.pointer_position) {
+                if self.pointer_mode == PointerMode::Repel {
+                    self.flock.modifiers.target_mode = Some(TargetMode::Flee);
+                }
+                if self.pointer_mode != PointerMode::None {
+                    targets[0] = pointer;
+                    target_count = 1;
+                }
+            } else if let Some(target) = self
+                .demo
+                .as_ref()
+                .and_then(|demo| demo.target(self.flock.width, self.flock.height))
+            {
+                targets[0] = Vector2D::new(target.x, target.y);
+                target_count = 1;
+            }
+        }
 
-            target = if self.pointer_pressed {
-                self.pointer_position
+        // Mapped input modulates on top of the user-set config, which stays
+        // untouched
+        self.input_mappings
+            .update(&signals, &mut self.flock.modifiers.scales);
+        if let Some(distance) = signals.finger_distance {
+            let effective = self.flock.effective_config();
+            console_log!(
+                "Open fingers - Distance: {:.1}px, Separation: {:.2}, Speed: {:.2}",
+                distance,
+                effective.separation_weight,
+                effective.max_speed
+            );
+        }
+        self.input_signals = signals;
+
+        // Music plays on top of the hands for this tick only, so the hands'
+        // modifiers never compound with it
+        let hand_modifiers = self.flock.modifiers;
+        let mut stimuli = [Stimulus::Force(Vector2D::zero())];
+        let mut stimulus_count = 0;
+        if self.audio_reactive {
+            self.music.modulate(&mut self.flock.modifiers);
+            if let Some(impulse) =
+                self.music
+                    .impulse(&self.flock.boids, self.flock.width, self.flock.height)
+            {
+                stimuli[0] = impulse;
+                stimulus_count = 1;
+            }
+        }
+
+        let now_secs = now_ms / 1000.0;
+        if let Some(path) = self.target_path.as_mut() {
+            if target_count == 1 {
+                // Hands update at the camera's rate; only a moved target is
+                // a new sample
+                if path.newest() != Some(targets[0]) {
+                    path.push(now_secs, targets[0]);
+                }
+                targets[0] = path.sample(now_secs).unwrap_or(targets[0]);
             } else {
-                None
-            };
+                path.clear();
+            }
+        }
+
+        self.place_exclusions();
+        let performance = &self.performance;
+        let targets = &targets[..target_count];
+        let mut clock = || {
+            performance
+                .as_ref()
+                .map_or_else(js_sys::Date::now, |p| p.now())
+        };
+        let output = self.flock.step(
+            StepInput::new()
+                .with_targets(targets)
+                .with_stimuli(&stimuli[..stimulus_count])
+                .with_clock(&mut clock),
+        );
+        self.flock.modifiers = hand_modifiers;
+        self.last_tick_timing = output.debug.unwrap_or_default();
+        // Over a lowered cap, or in ecological mode, the step removes boids
+        if output
+            .events
+            .iter()
+            .any(|event| matches!(event, StepEvent::Trimmed(_) | StepEvent::Died(_)))
+        {
+            self.forget_removed_boids();
+        }
+        self.follow_with_camera();
+
+        if self.sprite_mode == SpriteMode::Sprite {
+            self.advance_animations();
+        }
+        self.update_sound();
+        self.emit_particles();
+        self.emit_events();
+
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.frame();
+            metrics.interactions(pinches_started);
+        }
+    }
+
+    /// Draw a device's flock, fed in with `apply_remote_flock`, instead of
+    /// simulating. Hands, the pointer and the demo no longer move the boids.
+    /// Turning it off simulates on from where the mirrored boids are.
+    pub fn set_mirror_mode(&mut self, enabled: bool) {
+        self.mirror = enabled.then(RemoteFlock::new);
+    }
+
+    pub fn is_mirror_mode(&self) -> bool {
+        self.mirror.is_some()
+    }
+
+    /// Take a frame of `GET /api/flock` in its binary encoding. True if it
+    /// is a new frame; false for a repeat, or while mirror mode is off.
+    pub fn apply_remote_flock(&mut self, bytes: &[u8]) -> Result<bool, JsValue> {
+        let frame = FlockFrame::from_bytes(bytes).ok_or_else(|| {
+            coded_error(
+                ErrorCode::InvalidSnapshot,
+                &format!("{} bytes are not a flock frame", bytes.len()),
+            )
+        })?;
+        Ok(self.receive_remote_flock(frame))
+    }
+
+    /// `apply_remote_flock` for the JSON encoding
+    pub fn apply_remote_flock_json(&mut self, json: &str) -> Result<bool, JsValue> {
+        let frame = serde_json::from_str(json)
+            .map_err(|e| coded_error(ErrorCode::InvalidSnapshot, &e.to_string()))?;
+        Ok(self.receive_remote_flock(frame))
+    }
+
+    /// Number of the device frame last received; `undefined` before the first
+    pub fn get_remote_frame(&self) -> Option<u32> {
+        self.mirror.as_ref()?.latest_frame()
+    }
+
+    /// Replace the table that maps hand and music input onto parameters
+    /// with a JSON array of `{ input, parameter, input_min, input_max, low,
+    /// high, exponent, smoothing }`; see `boid_core::mapping`. `"[]"`
+    /// turns mapping off.
+    pub fn set_input_mappings(&mut self, json: &str) -> Result<(), JsValue> {
+        let mappings: Vec<InputMapping> = serde_json::from_str(json)
+            .map_err(|e| coded_error(ErrorCode::InvalidMapping, &e.to_string()))?;
+        self.input_mappings = MappingTable::new(mappings)
+            .map_err(|e| coded_error(ErrorCode::InvalidMapping, &e.to_string()))?;
+        Ok(())
+    }
+
+    /// The input mappings as a JSON array
+    pub fn get_input_mappings(&self) -> Result<String, JsValue> {
+        serde_json::to_string(self.input_mappings.mappings())
+            .map_err(|e| coded_error(ErrorCode::SerializationFailed, &e.to_string()))
+    }
+
+    /// Back to the built-in mappings: open fingers spread the flock out and
+    /// speed it up
+    pub fn reset_input_mappings(&mut self) {
+        self.input_mappings = MappingTable::hands();
+    }
+
+    /// The input signals of the last `update`, by name; signals that were
+    /// not measured are left out
+    pub fn get_input_signals(&self) -> js_sys::Object {
+        let signals = js_sys::Object::new();
+        for input in InputSignal::ALL {
+            if let Some(value) = self.input_signals.get(input) {
+                let _ = js_sys::Reflect::set(&signals, &input.as_str().into(), &value.into());
+            }
+        }
+        signals
+    }
+
+    /// Move a single hand or pointer target along a smooth curve between
+    /// input updates instead of jumping at each one. It trails the input by
+    /// about one update interval. Two hands are never smoothed.
+    pub fn set_target_interpolation(&mut self, enabled: bool) {
+        self.target_path = enabled.then(TargetInterpolator::new);
+    }
+
+    pub fn is_target_interpolation_enabled(&self) -> bool {
+        self.target_path.is_some()
+    }
+
+    /// Count session length, frame rate, interactions and presets for
+    /// `get_session_metrics`. Nothing leaves the page. Turning it off
+    /// discards the counts; turning it on starts a new session.
+    pub fn set_metrics_enabled(&mut self, enabled: bool) {
+        self.metrics = enabled.then(|| MetricsCollector::new(self.now_ms()));
+    }
+
+    pub fn is_metrics_enabled(&self) -> bool {
+        self.metrics.is_some()
+    }
+
+    /// `{ session_secs, frames, average_fps, interactions, presets }`, with
+    /// `presets` an array of `{ name, count }`; `undefined` unless enabled
+    pub fn get_session_metrics(&self) -> Option<js_sys::Object> {
+        let metrics = self.metrics.as_ref()?.snapshot(self.now_ms());
+        Some(session_metrics_to_js(&metrics))
+    }
+
+    /// Phase breakdown of the last `update` as
+    /// `{ neighbor_search_ms, forces_ms, integration_ms, total_ms }`
+    pub fn get_tick_timing(&self) -> js_sys::Object {
+        let timing = self.last_tick_timing;
+        let entry = js_sys::Object::new();
+        for (key, value) in [
+            ("neighbor_search_ms", timing.neighbor_search_ms),
+            ("forces_ms", timing.forces_ms),
+            ("integration_ms", timing.integration_ms),
+            ("total_ms", timing.total_ms()),
+        ] {
+            let _ = js_sys::Reflect::set(&entry, &key.into(), &value.into());
         }
+        entry
+    }
 
-        self.flock.update_with_target(target);
+    /// Reuse of the per-tick buffers as `{ capacity_bytes, growths }`.
+    /// `growths` stops increasing once the flock has reached a steady size.
+    pub fn get_scratch_stats(&self) -> js_sys::Object {
+        let scratch = self.flock.scratch();
+        let entry = js_sys::Object::new();
+        for (key, value) in [
+            ("capacity_bytes", scratch.capacity_bytes() as f64),
+            ("growths", scratch.growths() as f64),
+        ] {
+            let _ = js_sys::Reflect::set(&entry, &key.into(), &value.into());
+        }
+        entry
     }
 
     pub fn render(&self) -> Result<(), JsValue> {
-        let width = self.canvas.width() as f64;
-        let height = self.canvas.height() as f64;
+        let width = self.size.x as f64;
+        let height = self.size.y as f64;
+        // Draw in CSS pixels; the backing store may be denser
+        let ratio = self.pixel_ratio;
+        self.context
+            .set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)?;
 
         // Draw video as background if available
         if let Some(ref video) = self.video_element {
@@ -160,40 +629,323 @@ impl BoidSimulation {
             self.context.fill_rect(0.0, 0.0, width, height);
         }
 
-        // Draw finger landmarks if available
-        if let (Some(thumb), Some(index)) = (self.thumb_position, self.index_position) {
-            self.draw_finger_landmarks(thumb, index)?;
+        // Draw finger landmarks of every tracked hand
+        for (_, hand) in self.hands.iter() {
+            if let Some((thumb, index)) = hand.fingers() {
+                self.draw_finger_landmarks(thumb, index)?;
+            }
         }
 
-        // Draw each boid
-        for boid in &self.flock.boids {
-            self.draw_boid(boid)?;
+        // Everything below is drawn in world coordinates
+        let viewport = self.viewport();
+        let origin = self.camera.world_to_screen(Vector2D::zero(), viewport);
+        let zoom = self.camera.zoom as f64;
+        self.context.save();
+        self.context.translate(origin.x as f64, origin.y as f64)?;
+        self.context.scale(zoom, zoom)?;
+
+        // Draw each boid, falling back to triangles until a sprite sheet is ready
+        let atlas = match (self.sprite_mode, &self.sprite_atlas) {
+            (SpriteMode::Sprite, Some(atlas)) if atlas.is_ready() => Some(atlas),
+            _ => None,
+        };
+        let detailed = self.lod_budget.map(|budget| {
+            lod::detail_mask(
+                &self.flock.boids,
+                self.lod_focus_point(),
+                self.flock.config.max_speed,
+                budget,
+            )
+        });
+        let draw_detailed = |include: &dyn Fn(&Boid) -> Option<f64>| -> Result<(), JsValue> {
+            for (index, boid) in self.flock.boids.iter().enumerate() {
+                if detailed.as_ref().is_some_and(|mask| !mask[index]) {
+                    continue;
+                }
+                let Some(alpha) = include(boid) else {
+                    continue;
+                };
+                self.context.set_global_alpha(alpha);
+                match atlas {
+                    Some(atlas) => self.draw_sprite(boid, atlas)?,
+                    None => self.draw_boid(boid)?,
+                }
+            }
+            self.context.set_global_alpha(1.0);
+            Ok(())
+        };
+
+        let style = self.depth_style();
+        match self.depth_effect {
+            DepthEffect::None => draw_detailed(&|_| Some(1.0))?,
+            DepthEffect::Shadow => {
+                // Canvas shadows ignore the transform, so every boid casts
+                // its shadow the same way whatever its heading
+                self.context.save();
+                self.context
+                    .set_shadow_color(&format!("rgba(0, 0, 0, {})", style.shadow_opacity));
+                self.context.set_shadow_blur(style.shadow_blur as f64);
+                self.context
+                    .set_shadow_offset_x(style.shadow_offset.0 as f64);
+                self.context
+                    .set_shadow_offset_y(style.shadow_offset.1 as f64);
+                draw_detailed(&|_| Some(1.0))?;
+                self.context.restore();
+            }
+            DepthEffect::Reflection => {
+                let horizon = self.horizon * height;
+                // Mirror the boids above the horizon into the water below it
+                self.context.save();
+                self.context.begin_path();
+                self.context.rect(0.0, horizon, width, height - horizon);
+                self.context.clip();
+                self.context.translate(0.0, 2.0 * horizon)?;
+                self.context.scale(1.0, -1.0)?;
+                draw_detailed(&|boid| {
+                    let depth = horizon - boid.position.y as f64;
+                    let alpha = depth::reflection_alpha(style.reflection_opacity, depth, horizon);
+                    (alpha > 0.0).then_some(alpha)
+                })?;
+                self.context.restore();
+
+                self.context.begin_path();
+                self.context.move_to(0.0, horizon);
+                self.context.line_to(width, horizon);
+                self.context
+                    .set_stroke_style_str("rgba(255, 255, 255, 0.15)");
+                self.context.set_line_width(1.0);
+                self.context.stroke();
+
+                draw_detailed(&|_| Some(1.0))?;
+            }
+        }
+
+        // Everything outside the detail budget goes out as one batched path
+        if let Some(mask) = detailed {
+            self.context.begin_path();
+            for (boid, _) in self.flock.boids.iter().zip(mask).filter(|(_, full)| !full) {
+                self.context.rect(
+                    (boid.position.x as f64).floor(),
+                    (boid.position.y as f64).floor(),
+                    1.0,
+                    1.0,
+                );
+            }
+            self.context.set_fill_style_str("rgba(200, 240, 255, 0.8)");
+            self.context.fill();
+        }
+        self.context.restore();
+
+        // What the detector saw, in the bottom right corner
+        if let Some((_, pip)) = &self.detector_pip {
+            if pip.width() > 0 {
+                let pip_width = width * DETECTOR_PIP_SCALE;
+                let pip_height = pip_width * pip.height() as f64 / pip.width() as f64;
+                self.context
+                    .draw_image_with_html_canvas_element_and_dw_and_dh(
+                        pip,
+                        width - pip_width - DETECTOR_PIP_MARGIN,
+                        height - pip_height - DETECTOR_PIP_MARGIN,
+                        pip_width,
+                        pip_height,
+                    )?;
+            }
         }
 
         Ok(())
     }
 
+    /// Boid colors: the demo's current theme, cyan to green otherwise
+    fn palette(&self) -> Palette {
+        self.demo
+            .as_ref()
+            .map_or_else(|| Theme::default().palette(), DemoPlayer::palette)
+    }
+
+    /// Shadow and reflection look: the demo's current theme, or the default
+    fn depth_style(&self) -> DepthStyle {
+        self.demo
+            .as_ref()
+            .map_or_else(|| Theme::default().depth_style(), DemoPlayer::depth_style)
+    }
+
+    /// Signals of the lead hand and the music for the mapping table. Finger
+    /// distance is left to `update`, since gestures take it over.
+    fn read_input_signals(
+        &mut self,
+        lead_hand: Option<(Vector2D, f32)>,
+        now_ms: f64,
+    ) -> InputSignals {
+        let audio_level = self.audio_reactive.then(|| {
+            let (bass, treble) = self.music.levels();
+            (bass + treble) / 2.0
+        });
+        let last_hand = std::mem::replace(
+            &mut self.last_hand,
+            lead_hand.map(|(midpoint, _)| (midpoint, now_ms)),
+        );
+        let Some((midpoint, distance)) = lead_hand else {
+            return InputSignals {
+                audio_level,
+                ..InputSignals::default()
+            };
+        };
+        let hand_speed = last_hand.and_then(|(last, at)| {
+            let secs = ((now_ms - at) / 1000.0) as f32;
+            (secs > 0.0).then(|| (midpoint - last).magnitude() / secs)
+        });
+        // Finger distance is read later, and only from an open hand
+        InputSignals {
+            finger_distance: None,
+            audio_level,
+            ..InputSignals::of_hand(midpoint, distance, self.size.y, hand_speed)
+        }
+    }
+
+    /// Milliseconds on the page's clock
+    fn now_ms(&self) -> f64 {
+        self.performance
+            .as_ref()
+            .map_or_else(js_sys::Date::now, |p| p.now())
+    }
+
+    fn viewport(&self) -> Vector2D {
+        self.size
+    }
+
+    /// Exclusion rectangles are laid out on the canvas, but boids steer in
+    /// the world, which the camera may have moved or zoomed since
+    fn place_exclusions(&mut self) {
+        let Some(weight) = self.flock.behaviors.weight(EXCLUSION_BEHAVIOR) else {
+            return;
+        };
+        let zoom = self.camera.zoom;
+        let rects = ExclusionRects::new(self.exclusion_rects.iter().map(|rect| {
+            let corner = self.to_world(rect.x as f64, rect.y as f64);
+            Rect::new(corner.x, corner.y, rect.width / zoom, rect.height / zoom)
+        }));
+        self.flock.behaviors.add(EXCLUSION_BEHAVIOR, rects, weight);
+    }
+
+    fn to_world(&self, x: f64, y: f64) -> Vector2D {
+        self.camera
+            .screen_to_world(Vector2D::new(x as f32, y as f32), self.viewport())
+    }
+
+    fn receive_remote_flock(&mut self, frame: FlockFrame) -> bool {
+        let now = self.now_ms();
+        self.mirror
+            .as_mut()
+            .is_some_and(|mirror| mirror.receive(frame, now))
+    }
+
+    /// Put the boids where the device's are; they stand still until the
+    /// first frame arrives
+    fn update_mirror(&mut self) {
+        let Some(mirror) = &self.mirror else {
+            return;
+        };
+        let mirrored = mirror.sample(self.now_ms(), self.flock.width, self.flock.height);
+        if mirror.latest_frame().is_some() {
+            if self.flock.boids.len() != mirrored.len() {
+                self.flock.set_boid_count(mirrored.len());
+                self.forget_removed_boids();
+            }
+            for (boid, remote) in self.flock.boids.iter_mut().zip(mirrored) {
+                boid.position = remote.position;
+                boid.velocity = remote.velocity;
+                boid.acceleration = Vector2D::zero();
+                boid.group = remote.group;
+            }
+        }
+        self.follow_with_camera();
+
+        if self.sprite_mode == SpriteMode::Sprite {
+            self.advance_animations();
+        }
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.frame();
+        }
+    }
+
+    fn follow_with_camera(&mut self) {
+        let viewport = self.viewport();
+        let follow = std::mem::take(&mut self.camera.follow);
+        let groups = &self.groups;
+        let points = self
+            .flock
+            .boids
+            .iter()
+            .filter(|boid| match &follow {
+                Follow::Off => false,
+                Follow::Flock => true,
+                Follow::Group(name) => groups.is_member(name, boid.id),
+                Follow::Boid(id) => boid.id == *id,
+            })
+            .map(|boid| boid.position);
+        self.camera.track(points, viewport);
+        self.camera.follow = follow;
+    }
+
+    fn lod_focus_point(&self) -> Vector2D {
+        let center = Vector2D::new(self.flock.width / 2.0, self.flock.height / 2.0);
+        match self.lod_focus {
+            LodFocus::Center => center,
+            LodFocus::Cursor => self.pointer_position.unwrap_or(center),
+        }
+    }
+
     fn draw_boid(&self, boid: &Boid) -> Result<(), JsValue> {
-        let size = 8.0;
-        let angle = (boid.velocity.y as f64).atan2(boid.velocity.x as f64);
+        let speed = boid.velocity.magnitude();
+        let max_speed = self.flock.config_for_group(boid.group).max_speed;
+        let normalized_speed = ((speed / max_speed).min(1.0)) as f64;
+        let perched = self.flock.perch_state(boid.id).is_perched();
+        let (glyph, angle) = if perched {
+            // Sits upright, wings folded
+            let glyph = self.boid_style.glyph(boid.group, 0.0).perched();
+            (glyph, -std::f64::consts::FRAC_PI_2)
+        } else {
+            let glyph = self.boid_style.glyph(boid.group, normalized_speed as f32);
+            (
+                glyph,
+                (boid.velocity.y as f64).atan2(boid.velocity.x as f64),
+            )
+        };
+        let size = 8.0 * glyph.scale as f64;
 
         self.context.save();
         self.context
             .translate(boid.position.x as f64, boid.position.y as f64)?;
         self.context.rotate(angle)?;
 
-        // Draw a triangle pointing in the direction of movement
+        // Draw the boid's shape pointing in the direction of movement
         self.context.begin_path();
-        self.context.move_to(size, 0.0);
-        self.context.line_to(-size / 2.0, size / 2.0);
-        self.context.line_to(-size / 2.0, -size / 2.0);
+        for (i, &(x, y)) in glyph.shape.corners().iter().enumerate() {
+            let (x, y) = (x as f64 * size, y as f64 * size);
+            if i == 0 {
+                self.context.move_to(x, y);
+            } else {
+                self.context.line_to(x, y);
+            }
+        }
         self.context.close_path();
 
-        // Fill with gradient color based on velocity
-        let speed = boid.velocity.magnitude();
-        let normalized_speed = ((speed / self.flock.config.max_speed).min(1.0)) as f64;
-        let hue = 180.0 + normalized_speed * 60.0; // Cyan to green
-        let color = format!("hsl({}, 70%, 60%)", hue);
+        // A fixed palette colors by sub-flock; themes shade by velocity
+        let color = if let Some([r, g, b]) = self.boid_style.color(boid.group) {
+            format!("rgb({}, {}, {})", r, g, b)
+        } else {
+            let group = self.groups.group_of(boid.id);
+            let hue = if let Some(hue) = group.and_then(|g| g.hue) {
+                hue
+            } else if let Some(&hue) = self.sub_flock_hues.get(&boid.group) {
+                hue
+            } else if boid.puppet {
+                30.0 // Orange for externally driven boids
+            } else {
+                self.palette().hue(normalized_speed as f32) as f64
+            };
+            format!("hsl({}, 70%, 60%)", hue)
+        };
 
         self.context.set_fill_style_str(&color);
         self.context.fill();
@@ -204,401 +956,3442 @@ impl BoidSimulation {
         self.context.set_line_width(1.0);
         self.context.stroke();
 
+        self.draw_group_highlight(boid, size * 1.5)?;
+
         self.context.restore();
 
         Ok(())
     }
 
-    pub fn resize(&mut self, width: f64, height: f64) {
-        self.canvas.set_width(width as u32);
-        self.canvas.set_height(height as u32);
-        self.flock.resize(width as f32, height as f32);
-        console_log!("Resized to {}x{}", width, height);
-    }
+    fn draw_sprite(&self, boid: &Boid, atlas: &SpriteAtlas) -> Result<(), JsValue> {
+        let angle = (boid.velocity.y as f64).atan2(boid.velocity.x as f64);
+        let phase = self.animation_phases.get(&boid.id).copied().unwrap_or(0.0);
+        let (source_x, source_y) = atlas.frame_origin(phase, angle);
+        let (width, height) = (atlas.frame_width, atlas.frame_height);
 
-    pub fn boid_count(&self) -> usize {
-        self.flock.boids.len()
+        self.context.save();
+        self.context
+            .translate(boid.position.x as f64, boid.position.y as f64)?;
+        // Single-row sheets face right and are rotated into the heading
+        if atlas.headings <= 1 {
+            self.context.rotate(angle)?;
+        }
+
+        self.context
+            .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                &atlas.image,
+                source_x,
+                source_y,
+                width,
+                height,
+                -width / 2.0,
+                -height / 2.0,
+                width,
+                height,
+            )?;
+
+        self.draw_group_highlight(boid, width.max(height) * 0.75)?;
+
+        self.context.restore();
+
+        Ok(())
     }
 
-    pub fn set_separation_weight(&mut self, weight: f64) {
-        self.flock.config.separation_weight = weight as f32;
-        self.baseline_separation_weight = weight as f32;
+    /// Ring around members of the highlighted group, drawn at the origin of
+    /// the current (boid-local) transform
+    fn draw_group_highlight(&self, boid: &Boid, radius: f64) -> Result<(), JsValue> {
+        let Some(ref name) = self.highlighted_group else {
+            return Ok(());
+        };
+        if self.groups.is_member(name, boid.id) {
+            self.context.begin_path();
+            self.context
+                .arc(0.0, 0.0, radius, 0.0, 2.0 * std::f64::consts::PI)?;
+            self.context.set_stroke_style_str("rgba(255, 255, 0, 0.8)");
+            self.context.set_line_width(1.0);
+            self.context.stroke();
+        }
+        Ok(())
     }
 
-    pub fn set_alignment_weight(&mut self, weight: f64) {
-        self.flock.config.alignment_weight = weight as f32;
+    /// Load a sprite sheet for the `"sprite"` render style. Columns are
+    /// animation frames and rows are `headings` evenly spaced directions,
+    /// starting at facing right and turning clockwise; with one row the
+    /// frames are rotated instead. Boids are drawn as triangles until the
+    /// image has loaded.
+    pub fn load_sprite_sheet(
+        &mut self,
+        url: &str,
+        frame_width: f64,
+        frame_height: f64,
+        frames: u32,
+        headings: u32,
+    ) -> Result<(), JsValue> {
+        if frame_width <= 0.0 || frame_height <= 0.0 || frames == 0 || headings == 0 {
+            return Err(coded_error(
+                ErrorCode::InvalidSpriteSheet,
+                "sprite sheet dimensions must be positive",
+            ));
+        }
+
+        let image = HtmlImageElement::new()?;
+        image.set_src(url);
+        self.sprite_atlas = Some(SpriteAtlas {
+            image,
+            frame_width,
+            frame_height,
+            frames,
+            headings,
+        });
+        console_log!("Loading sprite sheet {}", url);
+        Ok(())
     }
 
-    pub fn set_cohesion_weight(&mut self, weight: f64) {
-        self.flock.config.cohesion_weight = weight as f32;
+    pub fn clear_sprite_sheet(&mut self) {
+        self.sprite_atlas = None;
     }
 
-    pub fn set_max_speed(&mut self, speed: f64) {
-        self.flock.config.max_speed = speed as f32;
-        self.baseline_max_speed = speed as f32;
+    /// Whether a sprite sheet is loaded and ready to draw
+    pub fn is_sprite_sheet_ready(&self) -> bool {
+        self.sprite_atlas
+            .as_ref()
+            .is_some_and(SpriteAtlas::is_ready)
     }
 
-    pub fn set_max_force(&mut self, force: f64) {
-        self.flock.config.max_force = force as f32;
+    /// Select how boids are drawn: `"triangle"` draws shapes in the boid
+    /// style, `"sprite"` frames from the sprite sheet
+    pub fn set_render_style(&mut self, style: &str) -> Result<(), JsValue> {
+        self.sprite_mode = SpriteMode::parse(style)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown render style"))?;
+        Ok(())
     }
 
-    pub fn set_seek_weight(&mut self, weight: f64) {
-        self.flock.config.seek_weight = weight as f32;
+    pub fn get_render_style(&self) -> String {
+        self.sprite_mode.as_str().to_string()
     }
 
-    pub fn set_wander_radius(&mut self, radius: f64) {
-        self.flock.config.wander_radius = radius as f32;
+    /// Select boid colors: `"theme"` (the default) shades by speed in the
+    /// current theme's hues; `"okabe_ito"` and `"high_contrast"` color by
+    /// sub-flock from a color-blind safe palette, give each sub-flock its
+    /// own shape and draw faster boids larger. Group and sub-flock hues
+    /// only apply under `"theme"`; sprites are unaffected.
+    pub fn set_color_scheme(&mut self, scheme: &str) -> Result<(), JsValue> {
+        self.boid_style.palette = ColorScheme::parse(scheme)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown color scheme"))?;
+        Ok(())
     }
 
-    pub fn set_wander_enabled(&mut self, enabled: bool) {
-        self.wander_enabled = enabled;
-        self.flock.config.wander_enabled = enabled;
-        console_log!(
-            "Wander behavior {}",
-            if enabled { "enabled" } else { "disabled" }
-        );
+    pub fn get_color_scheme(&self) -> String {
+        self.boid_style.palette.as_str().to_string()
     }
 
-    pub fn get_wander_enabled(&self) -> bool {
-        self.wander_enabled
+    /// Scale boids drawn as shapes, 1 being the usual size; accepted
+    /// between 0.25 and 4
+    pub fn set_boid_size(&mut self, size: f32) -> Result<(), JsValue> {
+        let style = style::RenderStyle {
+            size,
+            ..self.boid_style
+        };
+        if !style.is_valid() {
+            return Err(coded_error(
+                ErrorCode::InvalidSetting,
+                "boid size out of range",
+            ));
+        }
+        self.boid_style = style;
+        Ok(())
     }
 
-    pub fn handle_pointer_down(&mut self, x: f64, y: f64) {
-        self.pointer_position = Some(Vector2D::new(x as f32, y as f32));
-        self.pointer_pressed = true;
-        console_log!("Pointer down at ({}, {})", x, y);
+    pub fn get_boid_size(&self) -> f32 {
+        self.boid_style.size
     }
 
-    pub fn handle_pointer_move(&mut self, x: f64, y: f64) {
-        self.pointer_position = Some(Vector2D::new(x as f32, y as f32));
+    /// Outline of boids drawn as shapes: `"triangle"` (the default),
+    /// `"dot"` or `"arrow"`. Under `"okabe_ito"` and `"high_contrast"`
+    /// each sub-flock keeps its own outline instead.
+    pub fn set_boid_shape(&mut self, shape: &str) -> Result<(), JsValue> {
+        self.boid_style.shape = BoidShape::parse(shape)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown boid shape"))?;
+        Ok(())
     }
 
-    pub fn handle_pointer_up(&mut self) {
-        self.pointer_pressed = false;
-        console_log!("Pointer released");
+    pub fn get_boid_shape(&self) -> String {
+        self.boid_style.shape.as_str().to_string()
     }
 
-    pub fn get_average_position(&self) -> Option<Vec<f64>> {
-        if self.flock.boids.is_empty() {
-            return None;
+    /// Set size, palette and shape at once from the JSON the ESP32's
+    /// `/api/style` takes, e.g. `{"size":1.5,"palette":"theme","shape":"dot"}`;
+    /// fields left out take their defaults
+    pub fn set_boid_style(&mut self, json: &str) -> Result<(), JsValue> {
+        let style: style::RenderStyle = serde_json::from_str(json)
+            .map_err(|e| coded_error(ErrorCode::InvalidRequestBody, &e.to_string()))?;
+        if !style.is_valid() {
+            return Err(coded_error(
+                ErrorCode::InvalidSetting,
+                "boid size out of range",
+            ));
         }
+        self.boid_style = style;
+        Ok(())
+    }
 
-        let mut sum_x = 0.0_f32;
-        let mut sum_y = 0.0_f32;
-        for boid in &self.flock.boids {
-            sum_x += boid.position.x;
-            sum_y += boid.position.y;
-        }
+    pub fn get_boid_style(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.boid_style)
+            .map_err(|e| coded_error(ErrorCode::SerializationFailed, &e.to_string()))
+    }
 
-        let len = self.flock.boids.len() as f32;
-        Some(vec![(sum_x / len) as f64, (sum_y / len) as f64])
+    /// Limit how many boids are drawn in full per frame; the rest are drawn
+    /// as single pixels. `None` (the default) draws every boid in full.
+    pub fn set_lod_budget(&mut self, budget: Option<u32>) {
+        self.lod_budget = budget.map(|budget| budget as usize);
     }
 
-    pub fn all_boids_within_bounds(&self, width: f64, height: f64) -> bool {
-        self.flock.boids.iter().all(|boid| {
-            boid.position.x >= 0.0
-                && boid.position.x <= width as f32
-                && boid.position.y >= 0.0
-                && boid.position.y <= height as f32
-        })
+    pub fn get_lod_budget(&self) -> Option<u32> {
+        self.lod_budget.map(|budget| budget as u32)
     }
 
-    pub fn set_video_element(&mut self, video_id: &str) -> Result<(), JsValue> {
-        let window = web_sys::window().ok_or("no global window")?;
-        let document = window.document().ok_or("no document")?;
-        let video = document
-            .get_element_by_id(video_id)
-            .ok_or("video element not found")?
-            .dyn_into::<HtmlVideoElement>()?;
-        self.video_element = Some(video);
-        console_log!("Video element set");
+    /// Select where the detail budget is spent: `"center"` or `"cursor"`
+    pub fn set_lod_focus(&mut self, focus: &str) -> Result<(), JsValue> {
+        self.lod_focus = LodFocus::parse(focus)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown LOD focus"))?;
         Ok(())
     }
 
-    pub fn update_finger_positions(
-        &mut self,
-        thumb_x: f64,
-        thumb_y: f64,
-        index_x: f64,
-        index_y: f64,
-    ) {
-        let canvas_width = self.canvas.width() as f32;
-        // Mirror the x-coordinates to match the flipped video
-        self.thumb_position = Some(Vector2D::new(canvas_width - thumb_x as f32, thumb_y as f32));
-        self.index_position = Some(Vector2D::new(canvas_width - index_x as f32, index_y as f32));
+    pub fn get_lod_focus(&self) -> String {
+        self.lod_focus.as_str().to_string()
     }
 
-    pub fn clear_finger_positions(&mut self) {
-        self.thumb_position = None;
-        self.index_position = None;
+    /// Select a depth cue drawn beneath the boids: `"none"`, `"shadow"` or
+    /// `"reflection"`
+    pub fn set_depth_effect(&mut self, effect: &str) -> Result<(), JsValue> {
+        self.depth_effect = DepthEffect::parse(effect)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown depth effect"))?;
+        Ok(())
     }
 
-    pub fn get_finger_distance(&self) -> Option<f64> {
-        if let (Some(thumb), Some(index)) = (self.thumb_position, self.index_position) {
-            let dx = index.x - thumb.x;
-            let dy = index.y - thumb.y;
-            Some(((dx * dx + dy * dy) as f64).sqrt())
-        } else {
-            None
-        }
+    pub fn get_depth_effect(&self) -> String {
+        self.depth_effect.as_str().to_string()
     }
 
-    pub fn is_pinched(&self) -> bool {
-        if let Some(distance) = self.get_finger_distance() {
-            distance < PINCH_THRESHOLD as f64
-        } else {
-            false
-        }
+    /// Place the reflection horizon, as a fraction of the canvas height
+    /// from the top
+    pub fn set_horizon(&mut self, fraction: f64) {
+        self.horizon = fraction.clamp(0.0, 1.0);
     }
 
-    pub fn get_current_separation_weight(&self) -> f64 {
-        self.flock.config.separation_weight as f64
+    pub fn get_horizon(&self) -> f64 {
+        self.horizon
     }
 
-    pub fn get_current_max_speed(&self) -> f64 {
-        self.flock.config.max_speed as f64
+    /// Camera target: `"off"`, `"flock"`, `"group:<name>"` for a group made
+    /// with `select_*`, or `"boid:<id>"`. The camera glides to it every
+    /// update and holds still while it has no boids.
+    pub fn set_camera_follow(&mut self, target: &str) -> Result<(), JsValue> {
+        self.camera.follow = Follow::parse(target)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown camera target"))?;
+        Ok(())
     }
 
-    fn draw_finger_landmarks(&self, thumb: Vector2D, index: Vector2D) -> Result<(), JsValue> {
-        // Draw line between thumb and index
-        self.context.begin_path();
-        self.context.move_to(thumb.x as f64, thumb.y as f64);
-        self.context.line_to(index.x as f64, index.y as f64);
+    pub fn get_camera_follow(&self) -> String {
+        self.camera.follow.to_name()
+    }
+
+    /// Share of the way to its target the camera still has to go after each
+    /// update: 0 snaps to it, values close to 1 trail far behind
+    pub fn set_camera_damping(&mut self, damping: f64) {
+        self.camera.damping = (damping as f32).clamp(0.0, 0.99);
+    }
+
+    /// While following, zoom so the followed boids fill the canvas
+    pub fn set_camera_zoom_to_fit(&mut self, enabled: bool) {
+        self.camera.zoom_to_fit = enabled;
+    }
+
+    /// Look at world point `(x, y)` with magnification `zoom`; stops
+    /// following
+    pub fn set_camera_view(&mut self, x: f64, y: f64, zoom: f64) {
+        self.camera.follow = Follow::Off;
+        self.camera
+            .set_view(Vector2D::new(x as f32, y as f32), zoom as f32);
+    }
+
+    /// Frame the whole area again and stop following
+    pub fn reset_camera(&mut self) {
+        self.camera.follow = Follow::Off;
+        let center = Vector2D::new(self.flock.width / 2.0, self.flock.height / 2.0);
+        self.camera.set_view(center, 1.0);
+    }
+
+    /// `[x, y, zoom]`: the world point in the middle of the canvas and the
+    /// magnification
+    pub fn get_camera(&self) -> Vec<f64> {
+        vec![
+            self.camera.center.x as f64,
+            self.camera.center.y as f64,
+            self.camera.zoom as f64,
+        ]
+    }
+
+    /// World point `[x, y]` under canvas pixel `(x, y)`, for placing boids
+    /// where the user clicked
+    pub fn screen_to_world(&self, x: f64, y: f64) -> Vec<f64> {
+        let point = self.to_world(x, y);
+        vec![point.x as f64, point.y as f64]
+    }
+
+    /// Give each of the largest clusters a voice, panned and faded by where
+    /// it is and pitched up as it speeds up. Browsers only start audio from
+    /// a user gesture, so enable it from an input handler.
+    pub fn set_sound_enabled(&mut self, enabled: bool) -> Result<(), JsValue> {
+        match (enabled, self.sound.take()) {
+            (true, Some(sound)) => self.sound = Some(sound),
+            (true, None) => {
+                let sound = SoundField::new(self.sound_volume).map_err(|_| {
+                    coded_error(ErrorCode::ElementNotFound, "Web Audio is unavailable")
+                })?;
+                self.sound = Some(sound);
+                self.sound_countdown = 0;
+            }
+            (false, Some(sound)) => sound.close()?,
+            (false, None) => {}
+        }
+        Ok(())
+    }
+
+    pub fn is_sound_enabled(&self) -> bool {
+        self.sound.is_some()
+    }
+
+    /// Master volume, clamped to 0-1
+    pub fn set_sound_volume(&mut self, volume: f64) -> Result<(), JsValue> {
+        self.sound_volume = (volume as f32).clamp(0.0, 1.0);
+        if let Some(ref sound) = self.sound {
+            sound.set_volume(self.sound_volume)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_sound_volume(&self) -> f64 {
+        self.sound_volume as f64
+    }
+
+    /// Call `callback` after every update with the particles boids emit,
+    /// as a `Float32Array` of `[x, y, vx, vy, intensity]` per particle.
+    /// Boids emit when their velocity changes by at least `threshold` times
+    /// `max_force` in one tick, e.g. on sharp turns and bounces, with
+    /// intensity rising to 1 at a full `max_force` change. Pass `undefined`
+    /// to stop.
+    pub fn set_particle_callback(&mut self, callback: Option<js_sys::Function>, threshold: f64) {
+        self.particles.reset();
+        self.particles.threshold = threshold.clamp(0.0, 1.0) as f32;
+        self.particle_callback = callback;
+    }
+
+    /// Call `callback` after every update that had events with an array of
+    /// them, e.g. to play sounds. Each is an object with a `type` of
+    /// `"spawned"`, `"removed"`, `"hit_boundary"`, `"region_entered"`,
+    /// `"region_left"`, `"target_acquired"` or `"target_lost"`, plus `id`
+    /// for boid events, `region` for region events and `x`, `y` where a
+    /// position applies. Pass `undefined` to stop.
+    pub fn set_event_callback(&mut self, callback: Option<js_sys::Function>) {
+        if callback.is_some() {
+            self.flock.watch_events();
+        }
+        self.flock.drain_events();
+        self.event_callback = callback;
+    }
+
+    /// Report the flock's centroid entering and leaving a rectangle of the
+    /// world to the event callback; returns the region's number
+    pub fn add_event_region(&mut self, x: f64, y: f64, width: f64, height: f64) -> usize {
+        let corner = Vector2D::new(x as f32, y as f32);
+        let size = Vector2D::new(width as f32, height as f32);
+        self.flock
+            .add_event_region(Region::new(corner, corner + size))
+    }
+
+    pub fn clear_event_regions(&mut self) {
+        self.flock.clear_event_regions();
+    }
+
+    /// Let music drive the flock: bass raises cohesion, treble raises
+    /// speed and beats push the boids apart. Feed it with `feed_audio`.
+    pub fn set_audio_reactive(&mut self, enabled: bool) {
+        self.music.reset();
+        self.audio_reactive = enabled;
+    }
+
+    pub fn is_audio_reactive(&self) -> bool {
+        self.audio_reactive
+    }
+
+    /// Take one frame of an `AnalyserNode`'s `getFloatFrequencyData`,
+    /// with the audio context's `sampleRate`; ignored unless audio-reactive
+    pub fn feed_audio(&mut self, bins: &[f32], sample_rate: f64) {
+        if self.audio_reactive {
+            self.music.feed(bins, sample_rate as f32);
+        }
+    }
+
+    /// Smoothed `[bass, treble]` energy, each 0 to 1, e.g. for a level meter
+    pub fn get_audio_levels(&self) -> Vec<f32> {
+        let (bass, treble) = self.music.levels();
+        vec![bass, treble]
+    }
+
+    /// Shape how the music drives `parameter`: `"cohesion"` (by bass) and
+    /// `"max_speed"` (by treble) are multipliers on the config, `"impulse"`
+    /// (by beat strength) is the radius of a beat's push as a fraction of
+    /// the shorter side. Energy `e` from 0 to 1 maps to
+    /// `low + (high - low) * e^exponent`.
+    pub fn set_audio_mapping(
+        &mut self,
+        parameter: &str,
+        low: f64,
+        high: f64,
+        exponent: f64,
+    ) -> Result<(), JsValue> {
+        let mapping = Mapping::parse(parameter)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown audio mapping"))?;
+        *self.music.curve_mut(mapping) = Curve::new(low as f32, high as f32, exponent as f32);
+        Ok(())
+    }
+
+    /// Resize the canvas and the simulation area, in CSS pixels
+    pub fn resize(&mut self, width: f64, height: f64) -> Result<(), JsValue> {
+        self.size = Vector2D::new(width as f32, height as f32);
+        fit_canvas(&self.canvas, self.size, self.pixel_ratio)?;
+        self.flock.resize(width as f32, height as f32);
+        if self.camera.follow == Follow::Off {
+            self.camera.center = Vector2D::new(width as f32 / 2.0, height as f32 / 2.0);
+        }
+        console_log!("Resized to {}x{}", width, height);
+        Ok(())
+    }
+
+    /// Render at `ratio` device pixels per CSS pixel, e.g.
+    /// `window.devicePixelRatio`, so boids stay sharp on dense screens.
+    /// The canvas keeps its CSS size; boid sizes, speeds, pointer and hand
+    /// coordinates and pinch thresholds all stay in CSS pixels, so they
+    /// look the same on every screen. Ratios are clamped to 0.25-4.
+    pub fn set_pixel_ratio(&mut self, ratio: f64) -> Result<(), JsValue> {
+        if !ratio.is_finite() {
+            return Err(coded_error(
+                ErrorCode::InvalidSetting,
+                "pixel ratio must be finite",
+            ));
+        }
+        let ratio = ratio.clamp(MIN_PIXEL_RATIO, MAX_PIXEL_RATIO);
+        self.pixel_ratio = ratio;
+        fit_canvas(&self.canvas, self.size, ratio)
+    }
+
+    pub fn get_pixel_ratio(&self) -> f64 {
+        self.pixel_ratio
+    }
+
+    pub fn boid_count(&self) -> usize {
+        self.flock.boids.len()
+    }
+
+    /// Grow the flock with random boids or remove the newest ones until it
+    /// has `count`, at most the population cap; returns the new count
+    pub fn set_boid_count(&mut self, count: usize) -> usize {
+        self.flock.set_boid_count(count);
+        self.forget_removed_boids();
+        self.flock.boids.len()
+    }
+
+    /// Add an externally driven boid at the canvas center and return its id.
+    /// Puppet boids are ignored by force integration but still influence
+    /// their neighbors; move them with `set_boid_state`.
+    pub fn add_puppet_boid(&mut self) -> u32 {
+        let center = Vector2D::new(self.flock.width / 2.0, self.flock.height / 2.0);
+        self.flock
+            .add_boid(Boid::new_puppet(center, Vector2D::zero()))
+    }
+
+    /// Overwrite position and velocity of the boid with the given id
+    pub fn set_boid_state(
+        &mut self,
+        id: u32,
+        x: f64,
+        y: f64,
+        vx: f64,
+        vy: f64,
+    ) -> Result<(), JsValue> {
+        let boid = self
+            .flock
+            .get_mut(id)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownBoid, "unknown boid id"))?;
+        boid.position = Vector2D::new(x as f32, y as f32);
+        boid.velocity = Vector2D::new(vx as f32, vy as f32);
+        Ok(())
+    }
+
+    /// Spawn a boid at (x, y) with the given velocity and return its id, or
+    /// `undefined` if the population cap refuses it
+    pub fn spawn_boid(&mut self, x: f64, y: f64, vx: f64, vy: f64) -> Option<u32> {
+        // At the cap, the oldest boids make room
+        let id = self.flock.spawn(
+            Vector2D::new(x as f32, y as f32),
+            Vector2D::new(vx as f32, vy as f32),
+        );
+        self.forget_removed_boids();
+        id
+    }
+
+    /// Remove the boid nearest (x, y) if it is within `max_distance`, and
+    /// return its id
+    pub fn remove_nearest_boid(&mut self, x: f64, y: f64, max_distance: f64) -> Option<u32> {
+        let boid = self
+            .flock
+            .remove_nearest(Vector2D::new(x as f32, y as f32), max_distance as f32)?;
+        self.forget_removed_boids();
+        Some(boid.id)
+    }
+
+    /// Cap the number of boids; `undefined` removes the cap. At the cap,
+    /// `spawn_boid` replaces the oldest boid if `recycle_oldest` is set and
+    /// fails otherwise. Boids over a lowered cap are removed, oldest first,
+    /// on the next update.
+    pub fn set_max_population(&mut self, max: Option<usize>, recycle_oldest: bool) {
+        self.flock.population = max.map(|max| PopulationLimit {
+            max,
+            recycle_oldest,
+        });
+    }
+
+    pub fn get_max_population(&self) -> Option<usize> {
+        self.flock.max_population()
+    }
+
+    /// Respawn removed boids at random positions while fewer than `min`
+    /// remain; `0` turns respawning off
+    pub fn set_min_population(&mut self, min: usize) {
+        self.flock.min_population = min;
+    }
+
+    /// Let boids age, breed and die with the default rates, for ambient
+    /// displays whose flock should change over hours
+    pub fn set_ecology_enabled(&mut self, enabled: bool) {
+        self.flock.set_ecology(enabled.then(EcologyConfig::default));
+    }
+
+    pub fn is_ecology_enabled(&self) -> bool {
+        self.flock.ecology().is_some()
+    }
+
+    /// `{ population, births, deaths_of_age, deaths_of_exhaustion,
+    /// mean_age, mean_energy }`, ages in seconds; `undefined` unless the
+    /// ecology is on
+    pub fn get_population_stats(&self) -> Option<js_sys::Object> {
+        let stats = self.flock.population_stats()?;
+        let entry = js_sys::Object::new();
+        for (key, value) in [
+            ("population", stats.population as f64),
+            ("births", stats.births as f64),
+            ("deaths_of_age", stats.deaths_of_age as f64),
+            ("deaths_of_exhaustion", stats.deaths_of_exhaustion as f64),
+            ("mean_age", stats.mean_age as f64),
+            ("mean_energy", stats.mean_energy as f64),
+        ] {
+            let _ = js_sys::Reflect::set(&entry, &key.into(), &value.into());
+        }
+        Some(entry)
+    }
+
+    /// Keep a timeline of notable moments (targets, presets, clusters
+    /// splitting and merging, spawn bursts) from the next update on, for
+    /// annotating replays; turning it off discards it
+    pub fn set_timeline_enabled(&mut self, enabled: bool) {
+        if enabled != self.is_timeline_enabled() {
+            self.flock
+                .set_timeline(enabled.then(TimelineConfig::default));
+        }
+    }
+
+    pub fn is_timeline_enabled(&self) -> bool {
+        self.flock.timeline().is_some()
+    }
+
+    /// Markers of the updates `from..to`, counted from when the timeline
+    /// was turned on; either bound may be left out. Each is `{ tick, type,
+    /// x?, y?, count?, crossfade? }`, with `count` the clusters after a
+    /// split or merge, or the boids of a burst.
+    pub fn get_timeline(&self, from: Option<f64>, to: Option<f64>) -> js_sys::Array {
+        let Some(timeline) = self.flock.timeline() else {
+            return js_sys::Array::new();
+        };
+        let from = from.map_or(0, |tick| tick.max(0.0) as u64);
+        let to = to.map_or(u64::MAX, |tick| tick.max(0.0) as u64);
+        timeline
+            .between(from, to)
+            .map(timeline_entry_to_js)
+            .collect()
+    }
+
+    /// Let boids land on the bottom edge now and then and rest a few
+    /// seconds before taking off again; perched boids are drawn upright
+    /// and smaller
+    pub fn set_perching_enabled(&mut self, enabled: bool) {
+        self.flock.set_perching(enabled.then(PerchConfig::default));
+    }
+
+    pub fn is_perching_enabled(&self) -> bool {
+        self.flock.perching().is_some()
+    }
+
+    /// How many boids are resting on the ground
+    pub fn get_perched_count(&self) -> usize {
+        self.flock
+            .boids
+            .iter()
+            .filter(|boid| self.flock.perch_state(boid.id).is_perched())
+            .count()
+    }
+
+    /// Serialize the whole flock (boids, config, bounds) to JSON
+    pub fn export_state(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.flock)
+            .map_err(|e| coded_error(ErrorCode::SerializationFailed, &e.to_string()))
+    }
+
+    /// Replace the flock with a snapshot from `export_state` or from the
+    /// ESP32 `/api/snapshot` endpoint. Positions and velocities are restored
+    /// exactly; groups and sprite animation state are reset.
+    pub fn import_state(&mut self, json: &str) -> Result<(), JsValue> {
+        let flock: FlockStd = serde_json::from_str(json)
+            .map_err(|e| coded_error(ErrorCode::InvalidSnapshot, &e.to_string()))?;
+        self.flock = flock;
+        self.wander_enabled = self.flock.config.wander_enabled;
+        self.groups = BoidGroups::new();
+        self.highlighted_group = None;
+        self.animation_phases.clear();
+        console_log!("Imported {} boids", self.flock.boids.len());
+        Ok(())
+    }
+
+    /// Stable ids of all boids, in render order
+    pub fn get_boid_ids(&self) -> Vec<u32> {
+        self.flock.boids.iter().map(|boid| boid.id).collect()
+    }
+
+    /// Neighbors of the last update under the `"separation"`,
+    /// `"alignment"` or `"cohesion"` radius, for drawing the links between
+    /// boids: a flat `[a, b, a, b, ...]` list of boid ids, each pair once
+    pub fn get_neighbor_edges(&self, radius: &str) -> Result<Vec<u32>, JsValue> {
+        let radius = parse_neighbor_radius(radius)?;
+        Ok(self
+            .flock
+            .neighbor_graph(radius)
+            .edges()
+            .flat_map(|(a, b)| [a, b])
+            .collect())
+    }
+
+    /// Ids of the boids `id` counted as neighbors under `radius` in the
+    /// last update
+    pub fn get_neighbors_of(&self, id: u32, radius: &str) -> Result<Vec<u32>, JsValue> {
+        Ok(self.flock.neighbors_of(id, parse_neighbor_radius(radius)?))
+    }
+
+    /// Select the boids inside the rectangle spanned by two corners into
+    /// `group`. Returns the new group size.
+    pub fn select_rect(&mut self, group: &str, x0: f64, y0: f64, x1: f64, y1: f64) -> usize {
+        let ids = groups::select_rect(
+            &self.flock.boids,
+            Vector2D::new(x0 as f32, y0 as f32),
+            Vector2D::new(x1 as f32, y1 as f32),
+        );
+        self.assign_group(group, ids)
+    }
+
+    /// Select boids by id into `group`; unknown ids are ignored
+    pub fn select_ids(&mut self, group: &str, ids: Vec<u32>) -> usize {
+        let ids: Vec<u32> = ids
+            .into_iter()
+            .filter(|&id| self.flock.index_of(id).is_some())
+            .collect();
+        self.assign_group(group, ids)
+    }
+
+    /// Select the cluster around the boid nearest (x, y) into `group`.
+    /// Boids closer than the cohesion distance count as connected.
+    pub fn select_cluster(&mut self, group: &str, x: f64, y: f64) -> usize {
+        let ids = groups::select_cluster(
+            &self.flock.boids,
+            Vector2D::new(x as f32, y as f32),
+            self.flock.config.cohesion_distance,
+        );
+        self.assign_group(group, ids)
+    }
+
+    pub fn get_group_members(&self, group: &str) -> Vec<u32> {
+        self.groups
+            .get(group)
+            .map(|g| g.members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get_group_names(&self) -> js_sys::Array {
+        self.groups.names().map(JsValue::from).collect()
+    }
+
+    /// Dissolve a group; its members go back to following the flock target
+    pub fn ungroup(&mut self, group: &str) {
+        for id in self.groups.dissolve(group) {
+            if let Some(boid) = self.flock.get_mut(id) {
+                boid.target_override = TargetOverride::Inherit;
+            }
+        }
+        if self.highlighted_group.as_deref() == Some(group) {
+            self.highlighted_group = None;
+        }
+    }
+
+    /// Make the group seek (x, y) regardless of the pointer or hand target
+    pub fn group_set_target(&mut self, group: &str, x: f64, y: f64) -> Result<(), JsValue> {
+        let target = Vector2D::new(x as f32, y as f32);
+        self.set_group_target(group, TargetOverride::Target(target))
+    }
+
+    /// Make the group ignore all targets and flock freely
+    pub fn group_freefloat(&mut self, group: &str) -> Result<(), JsValue> {
+        self.set_group_target(group, TargetOverride::FreeFloat)
+    }
+
+    /// Let the group follow the flock-wide target again
+    pub fn group_follow_flock(&mut self, group: &str) -> Result<(), JsValue> {
+        self.set_group_target(group, TargetOverride::Inherit)
+    }
+
+    /// Render the group with a fixed hue (degrees), or the default colors for `None`
+    pub fn group_set_tint(&mut self, group: &str, hue: Option<f64>) -> Result<(), JsValue> {
+        let group = self
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownGroup, "unknown group"))?;
+        group.hue = hue;
+        Ok(())
+    }
+
+    /// Remove every boid in the group from the simulation.
+    /// Returns the number of boids removed.
+    pub fn group_remove(&mut self, group: &str) -> Result<usize, JsValue> {
+        if self.groups.get(group).is_none() {
+            return Err(coded_error(ErrorCode::UnknownGroup, "unknown group"));
+        }
+        let ids = self.groups.dissolve(group);
+        for &id in &ids {
+            self.flock.remove_boid(id);
+        }
+        self.forget_removed_boids();
+        console_log!("Removed {} boids in group {}", ids.len(), group);
+        Ok(ids.len())
+    }
+
+    /// Deal the boids round-robin into `count` sub-flocks that only align
+    /// and cohere among themselves, each tinted with its own evenly spaced
+    /// hue. `1` merges them back into one flock.
+    pub fn split_sub_flocks(&mut self, count: u8) {
+        let count = count.max(1);
+        for (i, boid) in self.flock.boids.iter_mut().enumerate() {
+            boid.group = (i % count as usize) as u8;
+        }
+        self.sub_flock_hues.clear();
+        if count > 1 {
+            for group in 0..count {
+                let hue = group as f64 * 360.0 / count as f64;
+                self.sub_flock_hues.insert(group, hue);
+            }
+        }
+    }
+
+    /// Move the boid with `id` into sub-flock `group`; false if there is none
+    pub fn set_sub_flock(&mut self, id: u32, group: u8) -> bool {
+        self.flock.set_group(id, group)
+    }
+
+    pub fn get_sub_flock(&self, id: u32) -> Option<u8> {
+        let index = self.flock.index_of(id)?;
+        Some(self.flock.boids[index].group)
+    }
+
+    /// Give sub-flock `group` its own parameters: the fields in the JSON
+    /// object `overrides` replace those of the flock-wide config
+    pub fn set_sub_flock_config(&mut self, group: u8, overrides: &str) -> Result<(), JsValue> {
+        let config = merge_config(&self.flock.config, overrides)?;
+        self.flock.set_group_config(group, config);
+        Ok(())
+    }
+
+    /// Let sub-flock `group` follow the flock-wide config again
+    pub fn clear_sub_flock_config(&mut self, group: u8) {
+        self.flock.clear_group_config(group);
+    }
+
+    /// Render sub-flock `group` with a fixed hue (degrees), or the default
+    /// colors for `None`. Named group tints take precedence.
+    pub fn set_sub_flock_hue(&mut self, group: u8, hue: Option<f64>) {
+        match hue {
+            Some(hue) => self.sub_flock_hues.insert(group, hue),
+            None => self.sub_flock_hues.remove(&group),
+        };
+    }
+
+    /// Color hint for sub-flock `group`, for matching UI swatches
+    pub fn get_sub_flock_hue(&self, group: u8) -> Option<f64> {
+        self.sub_flock_hues.get(&group).copied()
+    }
+
+    /// Assemble the flock into `text` (letters, digits and `!?.,-:'`),
+    /// centered on the canvas, until `clear_spell`. Returns the number of
+    /// letter pixels the boids are spread over.
+    pub fn spell(&mut self, text: &str) -> usize {
+        self.flock.spell(text)
+    }
+
+    /// Assemble the flock into a bitmap: `pixels` row by row, `width` per
+    /// row, nonzero bytes set
+    pub fn spell_bitmap(&mut self, width: usize, pixels: Vec<u8>) -> Result<usize, JsValue> {
+        if width == 0 || !pixels.len().is_multiple_of(width) {
+            return Err(coded_error(
+                ErrorCode::InvalidBitmap,
+                "pixels do not fill whole rows",
+            ));
+        }
+        let raster = Raster::Bitmap {
+            width,
+            pixels: &pixels,
+        };
+        let slots: Vec<Vector2D> =
+            formation::fit_centered(raster, self.flock.width, self.flock.height).collect();
+        self.flock.form(&slots);
+        Ok(slots.len())
+    }
+
+    /// Hold the flock in a shape given as canvas points, flat
+    /// `[x0, y0, x1, y1, ...]`, e.g. sampled from text drawn to an
+    /// offscreen canvas. Each boid gets its own point, with the least total
+    /// travel, and boids spawned or removed later are fitted in. Until
+    /// `disperse`, `clear_spell` or another formation.
+    pub fn form_shape(&mut self, points: Vec<f32>) -> Result<(), JsValue> {
+        if !points.len().is_multiple_of(2) || points.iter().any(|v| !v.is_finite()) {
+            return Err(coded_error(
+                ErrorCode::InvalidShape,
+                "points must be finite x, y pairs",
+            ));
+        }
+        let points: Vec<Vector2D> = points
+            .chunks_exact(2)
+            .map(|pair| Vector2D::new(pair[0], pair[1]))
+            .collect();
+        self.flock.form_shape(&points);
+        Ok(())
+    }
+
+    /// Break up the shape, scattering the boids outward from its middle
+    pub fn disperse(&mut self) {
+        self.flock.disperse();
+        self.restore_group_targets();
+    }
+
+    /// Flow the flock around the black parts of a mask, e.g. a logo
+    /// silhouette drawn to a canvas. White and transparent pixels are open.
+    /// The mask is stretched over the canvas; `weight` scales the push away
+    /// from black regions. Replaces any mask loaded before.
+    pub fn load_mask(&mut self, image_data: &ImageData, weight: f64) -> Result<(), JsValue> {
+        let map = ObstacleMap::from_rgba(
+            &image_data.data(),
+            image_data.width() as usize,
+            image_data.height() as usize,
+        )
+        .map_err(|e| coded_error(ErrorCode::InvalidBitmap, &e.to_string()))?;
+        self.flock.behaviors.add(MASK_BEHAVIOR, map, weight as f32);
+        Ok(())
+    }
+
+    pub fn clear_mask(&mut self) {
+        self.flock.behaviors.remove(MASK_BEHAVIOR);
+    }
+
+    /// Flow the flock around parts of a page laid over the canvas, e.g. the
+    /// headlines and buttons of a site with the flock behind its content.
+    /// `rects` holds `x, y, width, height` for each rectangle, in CSS pixels
+    /// from the canvas's top left corner; `weight` scales the push away
+    /// from them. The rectangles stay where they are on the canvas when the
+    /// camera moves. Replaces the rectangles set before; `exclusions.js`
+    /// measures elements and sets them again whenever the page lays out.
+    pub fn set_exclusion_rects(&mut self, rects: Vec<f32>, weight: f64) -> Result<(), JsValue> {
+        if !rects.len().is_multiple_of(4)
+            || rects.iter().any(|v| !v.is_finite())
+            || !weight.is_finite()
+        {
+            return Err(coded_error(
+                ErrorCode::InvalidExclusionRects,
+                "rects must be finite x, y, width, height values",
+            ));
+        }
+        self.exclusion_rects = rects
+            .chunks_exact(4)
+            .map(|r| Rect::new(r[0], r[1], r[2], r[3]))
+            .collect();
+        // Holds the weight until the rectangles are placed in the world
+        self.flock
+            .behaviors
+            .add(EXCLUSION_BEHAVIOR, ExclusionRects::new([]), weight as f32);
+        self.place_exclusions();
+        Ok(())
+    }
+
+    pub fn clear_exclusion_rects(&mut self) {
+        self.exclusion_rects.clear();
+        self.flock.behaviors.remove(EXCLUSION_BEHAVIOR);
+    }
+
+    /// Cycle through scenes of settings, spelled text, scripted targets and
+    /// color themes until `stop_demo_mode`, e.g. on an unattended kiosk.
+    /// `schedule` is a JSON `DemoSchedule`
+    /// (`{"transition_ticks": 60, "scenes": [{"duration_ticks": 600, "theme": "ember",
+    /// "target": {"path": "orbit", "period_ticks": 300}}]}`); `None` plays the
+    /// built-in rotation. Hands and the pointer still take over the target.
+    pub fn start_demo_mode(&mut self, schedule: Option<String>) -> Result<(), JsValue> {
+        let schedule = match schedule {
+            Some(json) => serde_json::from_str::<DemoSchedule>(&json)
+                .map_err(|e| coded_error(ErrorCode::InvalidDemoSchedule, &e.to_string()))?,
+            None => DemoSchedule::builtin(DEMO_SCENE_TICKS),
+        };
+        let demo =
+            DemoPlayer::new(schedule).map_err(|code| coded_error(code, code.description()))?;
+        if self.demo.is_none() {
+            self.config_before_demo = Some(self.flock.config);
+        }
+        self.demo = Some(demo);
+        console_log!("Demo mode started");
+        Ok(())
+    }
+
+    /// Stop the demo, fading back to the settings from before it started
+    pub fn stop_demo_mode(&mut self) {
+        let Some(demo) = self.demo.take() else {
+            return;
+        };
+        if demo.scene().spell.is_some() {
+            self.clear_spell();
+        }
+        if let Some(config) = self.config_before_demo.take() {
+            self.flock
+                .apply_preset_over(config, demo.transition_ticks());
+        }
+        console_log!("Demo mode stopped");
+    }
+
+    pub fn is_demo_mode_active(&self) -> bool {
+        self.demo.is_some()
+    }
+
+    /// Index of the scene on screen, while the demo runs
+    pub fn get_demo_scene(&self) -> Option<usize> {
+        self.demo.as_ref().map(DemoPlayer::scene_index)
+    }
+
+    /// Let the flock fly freely again after `spell`
+    pub fn clear_spell(&mut self) {
+        self.flock.release_formation();
+        self.restore_group_targets();
+    }
+
+    /// Grouped boids go back to their group's target
+    fn restore_group_targets(&mut self) {
+        for boid in self.flock.boids.iter_mut() {
+            if let Some(group) = self.groups.group_of(boid.id) {
+                boid.target_override = group.target;
+            }
+        }
+    }
+
+    fn emit_particles(&mut self) {
+        let Some(ref callback) = self.particle_callback else {
+            return;
+        };
+        self.particles.full_scale = self.flock.effective_config().max_force;
+        self.particles.observe(&self.flock.boids);
+        if self.particles.is_empty() {
+            return;
+        }
+        let data: Vec<f32> = self
+            .particles
+            .drain()
+            .flat_map(|p| {
+                [
+                    p.position.x,
+                    p.position.y,
+                    p.velocity.x,
+                    p.velocity.y,
+                    p.intensity,
+                ]
+            })
+            .collect();
+        let array = js_sys::Float32Array::from(data.as_slice());
+        if let Err(e) = callback.call1(&JsValue::NULL, &array) {
+            console_log!("Particle callback failed: {:?}", e);
+        }
+    }
+
+    fn emit_events(&mut self) {
+        let Some(ref callback) = self.event_callback else {
+            // Unset callbacks leave the flock watched; keep its queue empty
+            self.flock.drain_events();
+            return;
+        };
+        let events: js_sys::Array = self.flock.drain_events().map(|e| event_to_js(&e)).collect();
+        if events.length() == 0 {
+            return;
+        }
+        if let Err(e) = callback.call1(&JsValue::NULL, &events) {
+            console_log!("Event callback failed: {:?}", e);
+        }
+    }
+
+    fn update_sound(&mut self) {
+        let Some(ref mut sound) = self.sound else {
+            return;
+        };
+        if self.sound_countdown > 0 {
+            self.sound_countdown -= 1;
+            return;
+        }
+        self.sound_countdown = SOUND_UPDATE_TICKS - 1;
+
+        let config = self.flock.effective_config();
+        let clusters = groups::clusters(&self.flock.boids, config.cohesion_distance);
+        let voices = audio::voices(
+            &self.flock.boids,
+            &clusters,
+            self.flock.width,
+            self.flock.height,
+            config.max_speed,
+        );
+        if let Err(e) = sound.play(&voices) {
+            console_log!("Sound update failed: {:?}", e);
+        }
+    }
+
+    fn advance_demo(&mut self) {
+        let Some(demo) = self.demo.as_mut() else {
+            return;
+        };
+        let ticks = demo.transition_ticks();
+        let was_spelling = demo.scene().spell.is_some();
+        let Some(scene) = demo.advance().cloned() else {
+            return;
+        };
+        // Demo scenes stay out of the undo history
+        if let Some(ref settings) = scene.settings {
+            let preset = settings.apply_to(self.flock.config);
+            self.flock.apply_preset_over(preset, ticks);
+        }
+        match scene.spell {
+            Some(ref text) => {
+                self.flock.spell(text);
+            }
+            None if was_spelling => self.clear_spell(),
+            None => {}
+        }
+    }
+
+    /// Outline the members of `group` when rendering; `None` clears it
+    pub fn set_highlighted_group(&mut self, group: Option<String>) {
+        self.highlighted_group = group;
+    }
+
+    pub fn set_separation_weight(&mut self, weight: f64) {
+        self.record_config_change("separation_weight");
+        self.flock.config.separation_weight = weight as f32;
+    }
+
+    pub fn set_alignment_weight(&mut self, weight: f64) {
+        self.record_config_change("alignment_weight");
+        self.flock.config.alignment_weight = weight as f32;
+    }
+
+    pub fn set_cohesion_weight(&mut self, weight: f64) {
+        self.record_config_change("cohesion_weight");
+        self.flock.config.cohesion_weight = weight as f32;
+    }
+
+    pub fn set_max_speed(&mut self, speed: f64) {
+        self.record_config_change("max_speed");
+        self.flock.config.max_speed = speed as f32;
+    }
+
+    pub fn set_max_force(&mut self, force: f64) {
+        self.record_config_change("max_force");
+        self.flock.config.max_force = force as f32;
+    }
+
+    pub fn set_seek_weight(&mut self, weight: f64) {
+        self.record_config_change("seek_weight");
+        self.flock.config.seek_weight = weight as f32;
+    }
+
+    /// Limit the target's pull to boids within `radius`; `0` means all boids
+    pub fn set_seek_radius(&mut self, radius: f64) {
+        self.record_config_change("seek_radius");
+        self.flock.config.seek_radius = radius.max(0.0) as f32;
+    }
+
+    pub fn set_wander_radius(&mut self, radius: f64) {
+        self.record_config_change("wander_radius");
+        self.flock.config.wander_radius = radius as f32;
+    }
+
+    pub fn set_wander_enabled(&mut self, enabled: bool) {
+        self.record_config_change("wander_enabled");
+        self.wander_enabled = enabled;
+        self.flock.config.wander_enabled = enabled;
+        console_log!(
+            "Wander behavior {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+
+    /// Crossfade to a preset over the next `ticks` updates (`0` switches
+    /// immediately). `preset_json` is an object of `BoidConfig` fields, e.g.
+    /// `{"cohesion_weight": 2.0, "max_speed": 4.0}`; fields it leaves out
+    /// keep their current values. Any setter call cancels the crossfade.
+    pub fn apply_preset_over(&mut self, preset_json: &str, ticks: u32) -> Result<(), JsValue> {
+        self.apply_named_preset(UNNAMED_PRESET, preset_json, ticks)
+    }
+
+    /// `apply_preset_over`, counting the preset under `name` in the
+    /// session metrics
+    pub fn apply_named_preset(
+        &mut self,
+        name: &str,
+        preset_json: &str,
+        ticks: u32,
+    ) -> Result<(), JsValue> {
+        let preset = merge_config(&self.flock.config, preset_json)?;
+        self.record_config_change("preset");
+        self.flock.apply_preset_over(preset, ticks);
+        self.wander_enabled = preset.wander_enabled;
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.preset(name);
+        }
+        Ok(())
+    }
+
+    /// Whether a preset crossfade is still in progress
+    pub fn is_preset_transition_active(&self) -> bool {
+        self.flock.transition.is_some()
+    }
+
+    /// Revert the most recent configuration change.
+    /// Returns false if there is nothing to undo.
+    pub fn undo_last_config_change(&mut self) -> bool {
+        let Some(change) = self.config_history.undo() else {
+            return false;
+        };
+
+        self.flock.transition = None;
+        self.flock.config = change.previous;
+        self.wander_enabled = change.previous.wander_enabled;
+        console_log!("Undid change to {}", change.field);
+        true
+    }
+
+    /// Recorded configuration changes, oldest first, as
+    /// `{ field, source, timestamp }` objects
+    pub fn get_config_change_log(&self) -> js_sys::Array {
+        self.config_history
+            .iter()
+            .map(|change| {
+                let entry = js_sys::Object::new();
+                let _ = js_sys::Reflect::set(&entry, &"field".into(), &change.field.into());
+                let _ =
+                    js_sys::Reflect::set(&entry, &"source".into(), &change.source.as_str().into());
+                let _ = js_sys::Reflect::set(
+                    &entry,
+                    &"timestamp".into(),
+                    &(change.timestamp_ms as f64).into(),
+                );
+                JsValue::from(entry)
+            })
+            .collect()
+    }
+
+    pub fn get_wander_enabled(&self) -> bool {
+        self.wander_enabled
+    }
+
+    /// Switch the target between steering-limited seek and an orbital gravity well
+    pub fn set_gravity_well_enabled(&mut self, enabled: bool) {
+        self.record_config_change("target_mode");
+        self.flock.config.target_mode = if enabled {
+            TargetMode::GravityWell
+        } else {
+            TargetMode::Seek
+        };
+        console_log!(
+            "Gravity well mode {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+
+    pub fn get_gravity_well_enabled(&self) -> bool {
+        self.flock.config.target_mode == TargetMode::GravityWell
+    }
+
+    /// Toggle grid-based neighbor lookups (disable to compare against brute force)
+    pub fn set_spatial_grid_enabled(&mut self, enabled: bool) {
+        self.flock.config.spatial_grid = enabled;
+    }
+
+    pub fn get_spatial_grid_enabled(&self) -> bool {
+        self.flock.config.spatial_grid
+    }
+
+    /// Choose what boids do at the canvas edges: `"wrap"`, `"bounce"`
+    /// (default), `"steer-away"` or `"none"`. Steering away starts
+    /// `margin` pixels from an edge.
+    pub fn set_boundary_behavior(&mut self, behavior: &str, margin: f64) -> Result<(), JsValue> {
+        let boundary = match behavior {
+            "wrap" => BoundaryBehavior::Wrap,
+            "bounce" => BoundaryBehavior::Bounce,
+            "steer-away" => BoundaryBehavior::SteerAway {
+                margin: margin.max(1.0) as f32,
+                weight: STEER_AWAY_WEIGHT,
+            },
+            "none" => BoundaryBehavior::Unbounded,
+            _ => {
+                return Err(coded_error(
+                    ErrorCode::UnknownOption,
+                    "unknown boundary behavior",
+                ))
+            }
+        };
+        self.record_config_change("boundary");
+        self.flock.config.boundary = Some(boundary);
+        Ok(())
+    }
+
+    pub fn get_boundary_behavior(&self) -> String {
+        match self.flock.boundary() {
+            BoundaryBehavior::Wrap => "wrap",
+            BoundaryBehavior::Bounce => "bounce",
+            BoundaryBehavior::SteerAway { .. } => "steer-away",
+            BoundaryBehavior::Unbounded => "none",
+        }
+        .to_string()
+    }
+
+    pub fn set_gravity_strength(&mut self, strength: f64) {
+        self.record_config_change("gravity_strength");
+        self.flock.config.gravity_strength = strength as f32;
+    }
+
+    pub fn set_gravity_damping(&mut self, damping: f64) {
+        self.record_config_change("gravity_damping");
+        self.flock.config.gravity_damping = damping as f32;
+    }
+
+    /// Steady wind pushing every boid, as velocity change per update
+    pub fn set_wind(&mut self, x: f64, y: f64) {
+        self.flock
+            .set_global_force(Vector2D::new(x as f32, y as f32));
+    }
+
+    /// Gusts on top of the wind, up to `amplitude` on each axis: `"calm"`,
+    /// `"sine"` (swelling and turning back every `period` seconds) or
+    /// `"noise"` (changing course about every `period` seconds)
+    pub fn set_gust(
+        &mut self,
+        kind: &str,
+        amplitude_x: f64,
+        amplitude_y: f64,
+        period: f64,
+    ) -> Result<(), JsValue> {
+        let amplitude = Vector2D::new(amplitude_x as f32, amplitude_y as f32);
+        let period = period as f32;
+        let gust = match kind {
+            "calm" => Gust::Calm,
+            "sine" => Gust::Sine { amplitude, period },
+            "noise" => Gust::Noise {
+                amplitude,
+                period,
+                seed: 0,
+            },
+            _ => return Err(coded_error(ErrorCode::UnknownOption, "unknown gust")),
+        };
+        self.flock.set_gust(gust);
+        Ok(())
+    }
+
+    /// Wind blowing right now, gusts included, as `[x, y]`
+    pub fn get_wind(&self) -> Vec<f64> {
+        let wind = self.flock.wind();
+        vec![wind.x as f64, wind.y as f64]
+    }
+
+    /// Choose what a pressed pointer does: `"attract"` (default), `"repel"`
+    /// or `"none"`
+    pub fn set_pointer_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.pointer_mode = PointerMode::parse(mode)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown pointer mode"))?;
+        Ok(())
+    }
+
+    pub fn get_pointer_mode(&self) -> String {
+        self.pointer_mode.as_str().to_string()
+    }
+
+    /// Pointer events take canvas pixels and steer towards the world point
+    /// under them
+    pub fn handle_pointer_down(&mut self, x: f64, y: f64) {
+        self.pointer_position = Some(self.to_world(x, y));
+        self.pointer_pressed = true;
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.interactions(1);
+        }
+        console_log!("Pointer down at ({}, {})", x, y);
+    }
+
+    pub fn handle_pointer_move(&mut self, x: f64, y: f64) {
+        self.pointer_position = Some(self.to_world(x, y));
+    }
+
+    pub fn handle_pointer_up(&mut self) {
+        self.pointer_pressed = false;
+        console_log!("Pointer released");
+    }
+
+    pub fn get_average_position(&self) -> Option<Vec<f64>> {
+        if self.flock.boids.is_empty() {
+            return None;
+        }
+
+        let mut sum_x = 0.0_f32;
+        let mut sum_y = 0.0_f32;
+        for boid in &self.flock.boids {
+            sum_x += boid.position.x;
+            sum_y += boid.position.y;
+        }
+
+        let len = self.flock.boids.len() as f32;
+        Some(vec![(sum_x / len) as f64, (sum_y / len) as f64])
+    }
+
+    pub fn all_boids_within_bounds(&self, width: f64, height: f64) -> bool {
+        self.flock.boids.iter().all(|boid| {
+            boid.position.x >= 0.0
+                && boid.position.x <= width as f32
+                && boid.position.y >= 0.0
+                && boid.position.y <= height as f32
+        })
+    }
+
+    pub fn set_video_element(&mut self, video_id: &str) -> Result<(), JsValue> {
+        let window = web_sys::window()
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no global window"))?;
+        let document = window
+            .document()
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no document"))?;
+        let video = document
+            .get_element_by_id(video_id)
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "video element not found"))?
+            .dyn_into::<HtmlVideoElement>()?;
+        self.video_element = Some(video);
+        console_log!("Video element set");
+        Ok(())
+    }
+
+    pub fn update_finger_positions(
+        &mut self,
+        thumb_x: f64,
+        thumb_y: f64,
+        index_x: f64,
+        index_y: f64,
+    ) {
+        self.set_hand(Handedness::Right, thumb_x, thumb_y, index_x, index_y);
+    }
+
+    pub fn clear_finger_positions(&mut self) {
+        self.hands.get_mut(Handedness::Right).clear();
+    }
+
+    /// Finger tips of one of two tracked hands (`"left"` or `"right"`, any
+    /// case, as reported by MediaPipe). Each pinching hand herds the boids
+    /// nearest to it. Single-hand input drives the right hand.
+    pub fn update_hand_positions(
+        &mut self,
+        handedness: &str,
+        thumb_x: f64,
+        thumb_y: f64,
+        index_x: f64,
+        index_y: f64,
+    ) -> Result<(), JsValue> {
+        let handedness = Handedness::parse(handedness)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown handedness"))?;
+        self.set_hand(handedness, thumb_x, thumb_y, index_x, index_y);
+        Ok(())
+    }
+
+    pub fn clear_hand_positions(&mut self, handedness: &str) -> Result<(), JsValue> {
+        let handedness = Handedness::parse(handedness)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown handedness"))?;
+        self.hands.get_mut(handedness).clear();
+        Ok(())
+    }
+
+    /// Every hand found in one frame, as the JSON of a
+    /// `boid_shared::MultiHandLandmarks`. Hands missing from it are cleared,
+    /// so one call per frame replaces `update_hand_positions` and
+    /// `clear_hand_positions`.
+    pub fn update_hands(&mut self, json: &str) -> Result<(), JsValue> {
+        let frame: MultiHandLandmarks = serde_json::from_str(json)
+            .map_err(|e| coded_error(ErrorCode::InvalidLandmarks, &e.to_string()))?;
+        for handedness in [Handedness::Left, Handedness::Right] {
+            match frame.get(handedness) {
+                Some(hand) => self.set_hand(
+                    handedness,
+                    hand.thumb_tip.x as f64,
+                    hand.thumb_tip.y as f64,
+                    hand.index_tip.x as f64,
+                    hand.index_tip.y as f64,
+                ),
+                None => self.hands.get_mut(handedness).clear(),
+            }
+        }
+        Ok(())
+    }
+
+    /// All 21 MediaPipe landmarks of one hand as interleaved `x, y` video
+    /// coordinates (42 values), with optional per-point visibility. Besides
+    /// pinching, the full set enables gestures: a fist gathers the nearby
+    /// boids at the palm, pointing leads them with the index tip, and an
+    /// open palm scatters the flock.
+    pub fn update_full_hand_landmarks(
+        &mut self,
+        handedness: &str,
+        coords: Vec<f32>,
+        visibility: Option<Vec<f32>>,
+    ) -> Result<(), JsValue> {
+        let handedness = Handedness::parse(handedness)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown handedness"))?;
+        let mut landmarks = FullHandLandmarks::from_xy(&coords).ok_or_else(|| {
+            coded_error(
+                ErrorCode::InvalidLandmarks,
+                "expected 42 landmark coordinates",
+            )
+        })?;
+        if let Some(visibility) = visibility {
+            let visibility = visibility.try_into().map_err(|_| {
+                coded_error(ErrorCode::InvalidLandmarks, "expected 21 visibility values")
+            })?;
+            landmarks = landmarks.with_visibility(visibility);
+        }
+
+        // Mirror the x-coordinates to match the flipped video
+        let canvas_width = self.size.x;
+        for point in landmarks.points.iter_mut() {
+            *point = Position::new(canvas_width - point.x, point.y);
+        }
+        self.hands.get_mut(handedness).set_landmarks(landmarks);
+        Ok(())
+    }
+
+    /// `"fist"`, `"open_palm"` or `"pointing"` for a hand tracked with full
+    /// landmarks, if it currently forms one of them
+    pub fn get_hand_gesture(&self, handedness: &str) -> Result<Option<String>, JsValue> {
+        let handedness = Handedness::parse(handedness)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown handedness"))?;
+        Ok(self
+            .hands
+            .get(handedness)
+            .gesture()
+            .map(|(gesture, _)| gesture.as_str().to_string()))
+    }
+
+    /// Smooth hand readings with an exponential moving average; `alpha` is
+    /// the weight of each new reading, from 1 (off, the default) down
+    /// towards 0 (smoothest, but laggiest). 0.3-0.5 tames the built-in
+    /// detector's jitter.
+    pub fn set_landmark_smoothing(&mut self, alpha: f64) {
+        let smoothing = LandmarkSmoother::new(alpha as f32).smoothing();
+        self.hands.set_smoothing(smoothing);
+    }
+
+    /// Smooth hand readings with a Kalman filter instead; raising
+    /// `measurement_noise` relative to `process_noise` smooths more
+    pub fn set_landmark_kalman(&mut self, process_noise: f64, measurement_noise: f64) {
+        let smoothing =
+            LandmarkSmoother::kalman(process_noise as f32, measurement_noise as f32).smoothing();
+        self.hands.set_smoothing(smoothing);
+    }
+
+    pub fn get_finger_distance(&self) -> Option<f64> {
+        self.hands
+            .get(Handedness::Right)
+            .finger_distance()
+            .map(|distance| distance as f64)
+    }
+
+    /// Whether the given hand (`"left"` or `"right"`) is pinching
+    pub fn is_hand_pinched(&self, handedness: &str) -> Result<bool, JsValue> {
+        let handedness = Handedness::parse(handedness)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown handedness"))?;
+        Ok(self.hands.get(handedness).pinch.is_pinched())
+    }
+
+    pub fn is_pinched(&self) -> bool {
+        self.hands.get(Handedness::Right).pinch.is_pinched()
+    }
+
+    /// Separation weight in effect, including gesture modulation
+    pub fn get_current_separation_weight(&self) -> f64 {
+        self.flock.effective_config().separation_weight as f64
+    }
+
+    /// Max speed in effect, including gesture modulation
+    pub fn get_current_max_speed(&self) -> f64 {
+        self.flock.effective_config().max_speed as f64
+    }
+
+    /// Remember the user-set configuration before a setter changes it.
+    /// Gesture modulation lives in `flock.modifiers`, so `flock.config` is
+    /// always the persisted baseline.
+    fn record_config_change(&mut self, field: &'static str) {
+        // A manual change takes over from a preset crossfade, keeping the
+        // values it has reached so far
+        self.flock.transition = None;
+        self.config_history.record(ConfigChange {
+            field,
+            source: ChangeSource::Setter,
+            timestamp_ms: js_sys::Date::now() as u64,
+            previous: self.flock.config,
+        });
+    }
+
+    /// Forget the group membership and animation phase of boids no longer
+    /// in the flock, whichever way they left it
+    fn forget_removed_boids(&mut self) {
+        let live: HashSet<u32> = self.flock.boids.iter().map(|boid| boid.id).collect();
+        self.groups.retain_members(|id| live.contains(&id));
+        self.animation_phases.retain(|id, _| live.contains(id));
+    }
+
+    /// Step each boid's sprite animation; faster boids flap faster
+    fn advance_animations(&mut self) {
+        for boid in &self.flock.boids {
+            // Spread initial phases so the flock does not flap in unison
+            let phase = self
+                .animation_phases
+                .entry(boid.id)
+                .or_insert_with(|| (boid.id as f32 * 0.618_034).fract());
+            let rate = ANIMATION_BASE_RATE + boid.velocity.magnitude() * ANIMATION_SPEED_RATE;
+            *phase = (*phase + rate).fract();
+        }
+    }
+
+    /// Move boids into a group and give them the group's target override
+    fn assign_group(&mut self, name: &str, ids: Vec<u32>) -> usize {
+        // Boids taken from another group keep that group's override otherwise
+        let target = match self.groups.get(name) {
+            Some(group) => group.target,
+            None => TargetOverride::Inherit,
+        };
+        for &id in &ids {
+            if let Some(boid) = self.flock.get_mut(id) {
+                boid.target_override = target;
+            }
+        }
+        self.groups.assign(name, ids)
+    }
+
+    fn set_group_target(&mut self, name: &str, target: TargetOverride) -> Result<(), JsValue> {
+        let group = self
+            .groups
+            .get_mut(name)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownGroup, "unknown group"))?;
+        group.target = target;
+        for &id in &group.members {
+            if let Some(boid) = self.flock.get_mut(id) {
+                boid.target_override = target;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_hand(
+        &mut self,
+        handedness: Handedness,
+        thumb_x: f64,
+        thumb_y: f64,
+        index_x: f64,
+        index_y: f64,
+    ) {
+        let canvas_width = self.size.x;
+        // Mirror the x-coordinates to match the flipped video
+        self.hands.get_mut(handedness).set(
+            Vector2D::new(canvas_width - thumb_x as f32, thumb_y as f32),
+            Vector2D::new(canvas_width - index_x as f32, index_y as f32),
+        );
+    }
+
+    fn draw_finger_landmarks(&self, thumb: Vector2D, index: Vector2D) -> Result<(), JsValue> {
+        // Draw line between thumb and index
+        self.context.begin_path();
+        self.context.move_to(thumb.x as f64, thumb.y as f64);
+        self.context.line_to(index.x as f64, index.y as f64);
         self.context.set_stroke_style_str("rgba(0, 255, 0, 0.8)");
         self.context.set_line_width(3.0);
         self.context.stroke();
 
-        // Draw thumb circle
-        self.context.begin_path();
-        self.context.arc(
-            thumb.x as f64,
-            thumb.y as f64,
-            8.0,
-            0.0,
-            2.0 * std::f64::consts::PI,
-        )?;
-        self.context.set_fill_style_str("rgba(255, 0, 0, 0.8)");
-        self.context.fill();
+        // Draw thumb circle
+        self.context.begin_path();
+        self.context.arc(
+            thumb.x as f64,
+            thumb.y as f64,
+            8.0,
+            0.0,
+            2.0 * std::f64::consts::PI,
+        )?;
+        self.context.set_fill_style_str("rgba(255, 0, 0, 0.8)");
+        self.context.fill();
+
+        // Draw index finger circle
+        self.context.begin_path();
+        self.context.arc(
+            index.x as f64,
+            index.y as f64,
+            8.0,
+            0.0,
+            2.0 * std::f64::consts::PI,
+        )?;
+        self.context.set_fill_style_str("rgba(0, 0, 255, 0.8)");
+        self.context.fill();
+
+        Ok(())
+    }
+
+    /// Skin brightness range the built-in detector is using, as
+    /// `{ frame_brightness, brightness, v_min, v_max }` (HSV value, 0-100).
+    /// The range follows the room's lighting from frame to frame.
+    pub fn get_detector_exposure(&self) -> js_sys::Object {
+        let exposure = self.hand_detector.exposure();
+        let entry = js_sys::Object::new();
+        for (key, value) in [
+            ("frame_brightness", exposure.frame_brightness),
+            ("brightness", exposure.brightness),
+            ("v_min", exposure.v_min),
+            ("v_max", exposure.v_max),
+        ] {
+            let _ = js_sys::Reflect::set(&entry, &key.into(), &(value as f64).into());
+        }
+        entry
+    }
+
+    /// Have the built-in detector average `factor` x `factor` pixel blocks
+    /// before looking for skin: 2 does a quarter of the work on each video
+    /// frame, at the cost of fingertip precision. 1 restores the working
+    /// resolution.
+    pub fn set_detector_downscale(&mut self, factor: u32) {
+        self.hand_detector.set_downscale(factor as usize);
+    }
+
+    /// Width the built-in detector brings video frames down to before
+    /// looking for skin, 320 by default, so a 1080p camera costs about as
+    /// much as a small one. `undefined` detects at the frame's own
+    /// resolution, with thresholds meant for 320 pixels wide frames.
+    pub fn set_detector_working_width(&mut self, width: Option<u32>) {
+        self.hand_detector
+            .set_working_width(width.map(|width| width as usize));
+    }
+
+    /// `{ thumb_x, thumb_y, index_x, index_y }` of the hand in the last
+    /// video frame, from 0 to 1 across the frame whatever its resolution,
+    /// unmirrored; `undefined` without a hand. `normalized_to_canvas` maps
+    /// them onto the canvas.
+    pub fn get_detected_hand(&self) -> Option<js_sys::Object> {
+        let hand = self.detected_hand.as_ref()?;
+        let entry = js_sys::Object::new();
+        for (key, value) in [
+            ("thumb_x", hand.thumb_tip.x),
+            ("thumb_y", hand.thumb_tip.y),
+            ("index_x", hand.index_tip.x),
+            ("index_y", hand.index_tip.y),
+        ] {
+            let _ = js_sys::Reflect::set(&entry, &key.into(), &(value as f64).into());
+        }
+        Some(entry)
+    }
+
+    /// Canvas pixel `[x, y]` of a normalized video position, mirrored like
+    /// the video the canvas shows, for drawing over the hand
+    pub fn normalized_to_canvas(&self, x: f64, y: f64) -> Vec<f64> {
+        vec![(1.0 - x) * self.size.x as f64, y * self.size.y as f64]
+    }
+
+    /// Search each video frame around the hand found in the previous one
+    /// first, and the whole frame only once it is lost
+    pub fn set_detector_roi_tracking(&mut self, enabled: bool) {
+        self.hand_detector.set_roi_tracking(enabled);
+    }
+
+    /// Have the built-in detector learn the empty scene and look for skin
+    /// only where the video differs from it, for skin-toned walls and
+    /// furniture. The first frame after turning it on is taken as the
+    /// scene, so keep the hand out of view.
+    pub fn set_detector_background_subtraction(&mut self, enabled: bool) {
+        self.hand_detector.set_background_subtraction(enabled);
+    }
+
+    /// Take the next video frame as the empty scene, e.g. after moving the
+    /// camera
+    pub fn reset_detector_background(&mut self) {
+        self.hand_detector.reset_background();
+    }
+
+    /// Record the skin detector's intermediate results and show one of
+    /// them in a corner of the canvas: `"skin_mask"`, `"motion_mask"` (with
+    /// background subtraction), `"candidates"` (blob boxes: green chosen,
+    /// yellow passed, red rejected; fingertip crosses) or `"composite"`
+    /// (both). `None` stops recording.
+    pub fn set_detector_artifacts(&mut self, layer: Option<String>) -> Result<(), JsValue> {
+        let Some(layer) = layer else {
+            self.hand_detector.set_artifacts(false);
+            self.detector_pip = None;
+            return Ok(());
+        };
+        let layer = ArtifactLayer::parse(&layer)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown detector artifact"))?;
+        let pip = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no document"))?
+            .create_element("canvas")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        pip.set_width(0);
+        self.hand_detector.set_artifacts(true);
+        self.detector_pip = Some((layer, pip));
+        Ok(())
+    }
+
+    /// The last processed frame's `layer` at the frame's size, for the host
+    /// to draw or save; `undefined` unless recording
+    pub fn get_detector_artifacts(&self, layer: &str) -> Result<Option<ImageData>, JsValue> {
+        let layer = ArtifactLayer::parse(layer)
+            .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown detector artifact"))?;
+        self.hand_detector
+            .artifacts()
+            .map(|artifacts| {
+                let rgba = artifacts.render(layer);
+                ImageData::new_with_u8_clamped_array(Clamped(&rgba), artifacts.width as u32)
+            })
+            .transpose()
+    }
+
+    /// Redraw the picture-in-picture from the last frame's artifacts
+    fn update_detector_pip(&self) -> Result<(), JsValue> {
+        let (Some((layer, pip)), Some(artifacts)) =
+            (&self.detector_pip, self.hand_detector.artifacts())
+        else {
+            return Ok(());
+        };
+        if artifacts.width == 0 || artifacts.height == 0 {
+            return Ok(());
+        }
+        pip.set_width(artifacts.width as u32);
+        pip.set_height(artifacts.height as u32);
+        let context = pip
+            .get_context("2d")?
+            .ok_or_else(|| coded_error(ErrorCode::ElementNotFound, "no 2d context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        let rgba = artifacts.render(*layer);
+        let image = ImageData::new_with_u8_clamped_array(Clamped(&rgba), artifacts.width as u32)?;
+        context.put_image_data(&image, 0.0, 0.0)
+    }
+
+    /// Look for MediaPipe Hands (a global `Hands`) and OpenCV.js (a global
+    /// `cv` with its runtime loaded) and fall back past the missing ones.
+    /// Returns the tracker to run, as `get_hand_backend` does.
+    pub fn detect_hand_backends(&mut self) -> String {
+        let global = js_sys::global();
+        let has = |target: &JsValue, key: &str| {
+            js_sys::Reflect::get(target, &key.into()).is_ok_and(|value| !value.is_undefined())
+        };
+        let opencv = js_sys::Reflect::get(&global, &"cv".into()).unwrap_or(JsValue::UNDEFINED);
+        let found = [
+            (HandBackend::MediaPipe, has(&global, "Hands")),
+            (
+                HandBackend::OpenCv,
+                !opencv.is_undefined() && has(&opencv, "Mat"),
+            ),
+        ];
+        self.change_hand_backends(|backends| {
+            for (backend, available) in found {
+                backends.set_available(backend, available);
+            }
+        });
+        self.get_hand_backend()
+    }
+
+    /// Report that a tracker (`"mediapipe"`, `"opencv"` or `"skin"`) has
+    /// loaded, or failed and should be passed over. The skin detector is
+    /// always available.
+    pub fn set_hand_backend_available(
+        &mut self,
+        name: &str,
+        available: bool,
+    ) -> Result<(), JsValue> {
+        let backend = parse_hand_backend(name)?;
+        self.change_hand_backends(|backends| backends.set_available(backend, available));
+        Ok(())
+    }
+
+    /// Time one frame of a JavaScript tracker, in milliseconds. Trackers
+    /// slower than a 30 fps camera on average are passed over for the next
+    /// one. Frames given to `process_video_frame` are timed already.
+    pub fn report_hand_backend_cost(&mut self, name: &str, ms: f64) -> Result<(), JsValue> {
+        let backend = parse_hand_backend(name)?;
+        self.change_hand_backends(|backends| backends.record_cost(backend, ms));
+        Ok(())
+    }
+
+    /// The tracker the page should run this frame: `"mediapipe"`,
+    /// `"opencv"` or `"skin"` (call `process_video_frame`)
+    pub fn get_hand_backend(&self) -> String {
+        self.hand_backends.active().as_str().to_string()
+    }
+
+    /// Run the given tracker while it is available, however slow it is;
+    /// `null` picks automatically again
+    pub fn force_hand_backend(&mut self, name: Option<String>) -> Result<(), JsValue> {
+        let backend = name.as_deref().map(parse_hand_backend).transpose()?;
+        self.change_hand_backends(|backends| backends.force(backend));
+        Ok(())
+    }
+
+    /// Every tracker in fallback order, as `{ name, available, cost_ms,
+    /// too_slow, forced, active }`; `cost_ms` is `undefined` until a frame
+    /// was timed
+    pub fn get_hand_backends(&self) -> js_sys::Array {
+        let backends = &self.hand_backends;
+        let active = backends.active();
+        HandBackend::CHAIN
+            .into_iter()
+            .map(|backend| {
+                let entry = js_sys::Object::new();
+                let cost = backends
+                    .cost_ms(backend)
+                    .map_or(JsValue::UNDEFINED, JsValue::from);
+                for (key, value) in [
+                    ("name", JsValue::from(backend.as_str())),
+                    ("available", backends.is_available(backend).into()),
+                    ("cost_ms", cost),
+                    ("too_slow", backends.is_too_slow(backend).into()),
+                    ("forced", (backends.forced() == Some(backend)).into()),
+                    ("active", (active == backend).into()),
+                ] {
+                    let _ = js_sys::Reflect::set(&entry, &key.into(), &value);
+                }
+                JsValue::from(entry)
+            })
+            .collect()
+    }
+
+    /// Apply `change` and tell the console when it switches trackers
+    fn change_hand_backends(&mut self, change: impl FnOnce(&mut BackendManager)) {
+        let before = self.hand_backends.active();
+        change(&mut self.hand_backends);
+        let after = self.hand_backends.active();
+        if after != before {
+            console_log!(
+                "Hand tracking switched from {} to {}",
+                before.as_str(),
+                after.as_str()
+            );
+        }
+    }
+
+    /// Process a video frame for hand detection using shared hand detector
+    /// Takes ImageData from a canvas and detects hand landmarks. The frame
+    /// may have any size: it need not match the canvas, and is taken to be
+    /// stretched over it.
+    pub fn process_video_frame(&mut self, image_data: &ImageData) -> Result<bool, JsValue> {
+        let width = image_data.width() as usize;
+        let height = image_data.height() as usize;
+        let data = image_data.data();
+
+        let started_ms = self.now_ms();
+        let detection = self.hand_detector.track_rgba_image(width, height, &data);
+        let cost_ms = self.now_ms() - started_ms;
+        self.change_hand_backends(|backends| backends.record_cost(HandBackend::Skin, cost_ms));
+        self.update_detector_pip()?;
+        self.detected_hand = detection.map(|hand| hand.normalized(width as f32, height as f32));
+        match self.detected_hand.clone() {
+            Some(landmarks) => {
+                // Pinch thresholds are in canvas pixels whatever the
+                // camera's resolution
+                let canvas = landmarks.denormalized(self.size.x, self.size.y);
+                let (thumb, index) = (canvas.thumb_tip, canvas.index_tip);
+                self.set_hand(
+                    Handedness::Right,
+                    thumb.x as f64,
+                    thumb.y as f64,
+                    index.x as f64,
+                    index.y as f64,
+                );
+                Ok(true) // Hand detected
+            }
+            None => {
+                self.clear_finger_positions();
+                Ok(false) // No hand detected
+            }
+        }
+    }
+}
+
+/// Human-readable description of an error code such as `"E-SIM-04"`, for
+/// showing the `code` of an exception thrown by `BoidSimulation`
+#[wasm_bindgen]
+pub fn describe_error_code(code: &str) -> Option<String> {
+    ErrorCode::parse(code).map(|code| code.description().to_string())
+}
+
+/// JS `Error` tagged with a stable code: the message reads
+/// `"[E-SIM-04] unknown group"` and `error.code` holds `"E-SIM-04"`
+/// `{ type, id?, region?, x?, y? }` for `set_event_callback`
+fn event_to_js(event: &FlockEvent) -> JsValue {
+    let (kind, id, region, position) = match *event {
+        FlockEvent::Spawned { id, position } => ("spawned", Some(id), None, Some(position)),
+        FlockEvent::Removed { id } => ("removed", Some(id), None, None),
+        FlockEvent::HitBoundary { id, position } => {
+            ("hit_boundary", Some(id), None, Some(position))
+        }
+        FlockEvent::RegionEntered { region, centroid } => {
+            ("region_entered", None, Some(region), Some(centroid))
+        }
+        FlockEvent::RegionLeft { region, centroid } => {
+            ("region_left", None, Some(region), Some(centroid))
+        }
+        FlockEvent::TargetAcquired { position } => ("target_acquired", None, None, Some(position)),
+        FlockEvent::TargetLost => ("target_lost", None, None, None),
+    };
+    let entry = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&entry, &"type".into(), &kind.into());
+    if let Some(id) = id {
+        let _ = js_sys::Reflect::set(&entry, &"id".into(), &id.into());
+    }
+    if let Some(region) = region {
+        let _ = js_sys::Reflect::set(&entry, &"region".into(), &(region as u32).into());
+    }
+    if let Some(position) = position {
+        let _ = js_sys::Reflect::set(&entry, &"x".into(), &position.x.into());
+        let _ = js_sys::Reflect::set(&entry, &"y".into(), &position.y.into());
+    }
+    entry.into()
+}
+
+/// `{ tick, type, x?, y?, count?, crossfade? }` for `get_timeline`
+fn timeline_entry_to_js(entry: &TimelineEntry) -> JsValue {
+    let (position, count, crossfade) = match entry.marker {
+        Marker::TargetAcquired { position } => (Some(position), None, None),
+        Marker::TargetLost => (None, None, None),
+        Marker::PresetApplied { crossfade } => (None, None, Some(crossfade)),
+        Marker::ClusterSplit { clusters } | Marker::ClusterMerge { clusters } => {
+            (None, Some(clusters), None)
+        }
+        Marker::SpawnBurst { count } | Marker::DespawnBurst { count } => (None, Some(count), None),
+    };
+    let object = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&object, &"tick".into(), &(entry.tick as f64).into());
+    let _ = js_sys::Reflect::set(&object, &"type".into(), &entry.marker.name().into());
+    if let Some(position) = position {
+        let _ = js_sys::Reflect::set(&object, &"x".into(), &position.x.into());
+        let _ = js_sys::Reflect::set(&object, &"y".into(), &position.y.into());
+    }
+    if let Some(count) = count {
+        let _ = js_sys::Reflect::set(&object, &"count".into(), &(count as u32).into());
+    }
+    if let Some(crossfade) = crossfade {
+        let _ = js_sys::Reflect::set(&object, &"crossfade".into(), &crossfade.into());
+    }
+    object.into()
+}
+
+fn session_metrics_to_js(metrics: &SessionMetrics) -> js_sys::Object {
+    let entry = js_sys::Object::new();
+    for (key, value) in [
+        ("session_secs", metrics.session_secs),
+        ("frames", metrics.frames as f64),
+        ("average_fps", metrics.average_fps),
+        ("interactions", metrics.interactions as f64),
+    ] {
+        let _ = js_sys::Reflect::set(&entry, &key.into(), &value.into());
+    }
+    let presets: js_sys::Array = metrics
+        .presets
+        .iter()
+        .map(|used| {
+            let preset = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&preset, &"name".into(), &used.name.as_str().into());
+            let _ = js_sys::Reflect::set(&preset, &"count".into(), &used.count.into());
+            preset
+        })
+        .collect();
+    let _ = js_sys::Reflect::set(&entry, &"presets".into(), &presets);
+    entry
+}
+
+/// Give `canvas` a backing store of `ratio` device pixels per CSS pixel
+/// while showing it at `size` CSS pixels
+fn fit_canvas(canvas: &HtmlCanvasElement, size: Vector2D, ratio: f64) -> Result<(), JsValue> {
+    canvas.set_width((size.x as f64 * ratio).round() as u32);
+    canvas.set_height((size.y as f64 * ratio).round() as u32);
+    let style = canvas.style();
+    style.set_property("width", &format!("{}px", size.x))?;
+    style.set_property("height", &format!("{}px", size.y))?;
+    Ok(())
+}
+
+fn coded_error(code: ErrorCode, message: &str) -> JsValue {
+    let error = js_sys::Error::new(&format!("[{}] {}", code, message));
+    let _ = js_sys::Reflect::set(&error, &"code".into(), &code.as_str().into());
+    error.into()
+}
+
+fn parse_neighbor_radius(name: &str) -> Result<NeighborRadius, JsValue> {
+    NeighborRadius::parse(name)
+        .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown neighbor radius"))
+}
+
+fn parse_hand_backend(name: &str) -> Result<HandBackend, JsValue> {
+    HandBackend::parse(name)
+        .ok_or_else(|| coded_error(ErrorCode::UnknownOption, "unknown hand backend"))
+}
+
+/// `base` with the fields present in the JSON object `overrides` replaced
+fn merge_config(base: &BoidConfig, overrides: &str) -> Result<BoidConfig, JsValue> {
+    let to_js = |e: serde_json::Error| coded_error(ErrorCode::InvalidPreset, &e.to_string());
+    let mut merged = serde_json::to_value(base).map_err(to_js)?;
+    let overrides: serde_json::Value = serde_json::from_str(overrides).map_err(to_js)?;
+    let (Some(fields), Some(overrides)) = (merged.as_object_mut(), overrides.as_object()) else {
+        return Err(coded_error(
+            ErrorCode::InvalidPreset,
+            "preset must be a JSON object",
+        ));
+    };
+    for (key, value) in overrides {
+        // Catch typos instead of silently ignoring them
+        if !fields.contains_key(key) {
+            return Err(coded_error(
+                ErrorCode::InvalidPreset,
+                &format!("unknown config field: {}", key),
+            ));
+        }
+        fields.insert(key.clone(), value.clone());
+    }
+    serde_json::from_value(merged).map_err(to_js)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boid_shared::{test_util, PINCH_HOLD_FRAMES};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn create_test_canvas() -> Result<HtmlCanvasElement, JsValue> {
+        let window = web_sys::window().ok_or("no window")?;
+        let document = window.document().ok_or("no document")?;
+        let canvas = document
+            .create_element("canvas")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        canvas.set_id("test-canvas");
+        canvas.set_width(800);
+        canvas.set_height(600);
+        document.body().unwrap().append_child(&canvas)?;
+        Ok(canvas)
+    }
+
+    fn create_test_simulation() -> Result<BoidSimulation, JsValue> {
+        create_test_canvas()?;
+        BoidSimulation::new("test-canvas", 800.0, 600.0, 10)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_simulation_creation() {
+        let simulation = create_test_simulation();
+        assert!(simulation.is_ok());
+        let sim = simulation.unwrap();
+        assert_eq!(sim.boid_count(), 10);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pointer_down_sets_state() {
+        let mut sim = create_test_simulation().unwrap();
+
+        // Initially, pointer should not be pressed
+        assert!(!sim.pointer_pressed);
+        assert!(sim.pointer_position.is_none());
+
+        // After pointer down, state should be updated
+        sim.handle_pointer_down(100.0, 200.0);
+        assert!(sim.pointer_pressed);
+        assert!(sim.pointer_position.is_some());
+
+        let pos = sim.pointer_position.unwrap();
+        assert_eq!(pos.x, 100.0);
+        assert_eq!(pos.y, 200.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pointer_move_updates_position() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.handle_pointer_down(100.0, 100.0);
+        sim.handle_pointer_move(200.0, 300.0);
+
+        assert!(sim.pointer_pressed);
+        let pos = sim.pointer_position.unwrap();
+        assert_eq!(pos.x, 200.0);
+        assert_eq!(pos.y, 300.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pointer_up_clears_pressed_state() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.handle_pointer_down(100.0, 100.0);
+        assert!(sim.pointer_pressed);
+
+        sim.handle_pointer_up();
+        assert!(!sim.pointer_pressed);
+        // Position should still be set, just not pressed
+        assert!(sim.pointer_position.is_some());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pointer_mode_repels() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_pointer_mode(), "attract");
+        assert!(sim.set_pointer_mode("push").is_err());
+
+        sim.set_pointer_mode("repel").unwrap();
+        sim.handle_pointer_down(400.0, 300.0);
+        sim.update();
+        assert_eq!(sim.flock.modifiers.target_mode, Some(TargetMode::Flee));
+        assert_eq!(sim.flock.config.target_mode, TargetMode::Seek);
+
+        sim.handle_pointer_up();
+        sim.update();
+        assert!(sim.flock.modifiers.is_identity());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_update_without_target() {
+        let mut sim = create_test_simulation().unwrap();
+
+        // Get initial positions
+        let initial_count = sim.boid_count();
+
+        // Update without pointer pressed
+        sim.update();
+
+        // Boids should still exist and move
+        assert_eq!(sim.boid_count(), initial_count);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_update_with_target() {
+        let mut sim = create_test_simulation().unwrap();
+
+        // Set a target by pressing pointer
+        sim.handle_pointer_down(400.0, 300.0);
+
+        // Update should apply seek behavior towards target
+        sim.update();
+
+        // Boid count should remain the same
+        assert_eq!(sim.boid_count(), 10);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_configuration_setters() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.set_separation_weight(2.5);
+        assert_eq!(sim.flock.config.separation_weight, 2.5);
+
+        sim.set_alignment_weight(1.8);
+        assert_eq!(sim.flock.config.alignment_weight, 1.8);
+
+        sim.set_cohesion_weight(1.2);
+        assert_eq!(sim.flock.config.cohesion_weight, 1.2);
+
+        sim.set_max_speed(6.0);
+        assert_eq!(sim.flock.config.max_speed, 6.0);
+
+        sim.set_max_force(0.2);
+        assert_eq!(sim.flock.config.max_force, 0.2);
+
+        sim.set_seek_weight(10.0);
+        assert_eq!(sim.flock.config.seek_weight, 10.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_seek_radius_setter() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.set_seek_radius(120.0);
+        assert_eq!(sim.flock.config.seek_radius, 120.0);
+
+        // A negative radius means no limit, like 0
+        sim.set_seek_radius(-5.0);
+        assert_eq!(sim.flock.config.seek_radius, 0.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resize() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.resize(1024.0, 768.0).unwrap();
+
+        assert_eq!(sim.canvas.width(), 1024);
+        assert_eq!(sim.canvas.height(), 768);
+        assert_eq!(sim.flock.width, 1024.0);
+        assert_eq!(sim.flock.height, 768.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_ecology_reports_population_stats() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim.get_population_stats().is_none());
+        sim.set_ecology_enabled(true);
+        assert!(sim.is_ecology_enabled());
+        for _ in 0..5 {
+            sim.update();
+        }
+        let stats = sim.get_population_stats().unwrap();
+        let get = |key: &str| js_sys::Reflect::get(&stats, &key.into()).unwrap();
+        assert_eq!(
+            get("population").as_f64(),
+            Some(sim.flock.boids.len() as f64)
+        );
+        assert!(get("mean_age").as_f64().unwrap() > 0.0);
+        sim.set_ecology_enabled(false);
+        assert!(sim.get_population_stats().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_timeline_marks_presets_and_spawns() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_timeline(None, None).length(), 0);
+        sim.set_timeline_enabled(true);
+        sim.update();
+        sim.apply_preset_over("{}", 10).unwrap();
+        sim.update();
+        for _ in 0..10 {
+            sim.spawn_boid(400.0, 300.0, 0.0, 0.0);
+        }
+        for _ in 0..40 {
+            sim.update();
+        }
+
+        let kinds: Vec<(f64, String)> = sim
+            .get_timeline(Some(1.0), None)
+            .iter()
+            .map(|entry| {
+                let get = |key: &str| js_sys::Reflect::get(&entry, &key.into()).unwrap();
+                (
+                    get("tick").as_f64().unwrap(),
+                    get("type").as_string().unwrap(),
+                )
+            })
+            .filter(|(_, kind)| !kind.starts_with("cluster"))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (1.0, "preset_applied".to_string()),
+                (2.0, "spawn_burst".to_string())
+            ]
+        );
+        assert_eq!(sim.get_timeline(Some(0.0), Some(1.0)).length(), 0);
+        sim.set_timeline_enabled(false);
+        assert!(!sim.is_timeline_enabled());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_detector_artifacts_picture_in_picture() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim.set_detector_artifacts(Some("motion".into())).is_err());
+        assert!(sim.get_detector_artifacts("skin_mask").unwrap().is_none());
+
+        sim.set_detector_artifacts(Some("composite".into()))
+            .unwrap();
+        let frame =
+            ImageData::new_with_u8_clamped_array(Clamped(&[255u8; 64 * 48 * 4]), 64).unwrap();
+        sim.process_video_frame(&frame).unwrap();
+        let mask = sim.get_detector_artifacts("skin_mask").unwrap().unwrap();
+        assert_eq!((mask.width(), mask.height()), (64, 48));
+        assert_eq!(sim.detector_pip.as_ref().unwrap().1.width(), 64);
+        sim.render().unwrap();
+
+        sim.set_detector_artifacts(None).unwrap();
+        assert!(sim.get_detector_artifacts("skin_mask").unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_video_frames_of_any_size_map_onto_the_canvas() {
+        let mut sim = create_test_simulation().unwrap();
+        // A palm with two raised fingers, in a frame twice the working
+        // width and of a different shape than the canvas
+        let (width, height) = (640usize, 360usize);
+        let mut data = vec![255u8; width * height * 4];
+        for (idx, pixel) in data.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (idx % width, idx / width);
+            let hand = (240..400).contains(&x) && (140..340).contains(&y)
+                || ((260..280).contains(&x) || (340..360).contains(&x)) && (60..140).contains(&y);
+            if hand {
+                pixel[..3].copy_from_slice(&[180, 150, 120]);
+            }
+        }
+        let image = ImageData::new_with_u8_clamped_array(Clamped(&data), width as u32).unwrap();
+        assert!(sim.process_video_frame(&image).unwrap());
+
+        let hand = sim.get_detected_hand().unwrap();
+        let get = |key: &str| {
+            js_sys::Reflect::get(&hand, &key.into())
+                .unwrap()
+                .as_f64()
+                .unwrap()
+        };
+        // Fingertips at the top of the two fingers, in frame fractions
+        assert!((get("thumb_x") - 270.0 / 640.0).abs() < 0.03);
+        assert!((get("index_x") - 350.0 / 640.0).abs() < 0.03);
+        assert!(get("thumb_y") < 0.25);
+
+        let canvas = sim.normalized_to_canvas(0.25, 0.5);
+        assert_eq!(canvas, [0.75 * sim.size.x as f64, 0.5 * sim.size.y as f64]);
+
+        let blank = ImageData::new_with_u8_clamped_array(Clamped(&[255u8; 8 * 4 * 4]), 8).unwrap();
+        assert!(!sim.process_video_frame(&blank).unwrap());
+        assert!(sim.get_detected_hand().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_detector_background_subtraction() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.set_detector_background_subtraction(true);
+        sim.set_detector_artifacts(Some("motion_mask".into()))
+            .unwrap();
+        let white =
+            ImageData::new_with_u8_clamped_array(Clamped(&[255u8; 64 * 48 * 4]), 64).unwrap();
+        let mut data = [255u8; 64 * 48 * 4];
+        data[..64 * 4].fill(0);
+        let changed = ImageData::new_with_u8_clamped_array(Clamped(&data), 64).unwrap();
+
+        assert!(!sim.process_video_frame(&white).unwrap());
+        assert!(!sim.process_video_frame(&changed).unwrap());
+        let motion = sim.get_detector_artifacts("motion_mask").unwrap().unwrap();
+        // The darkened top row moved, the rest is the scene
+        assert_eq!(motion.data()[0], 255);
+        assert_eq!(motion.data()[(10 * 64) * 4], 0);
+
+        sim.reset_detector_background();
+        assert!(!sim.process_video_frame(&changed).unwrap());
+        let motion = sim.get_detector_artifacts("motion_mask").unwrap().unwrap();
+        assert_eq!(motion.data()[0], 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_detector_downscale_and_roi_tracking() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.set_detector_downscale(2);
+        sim.set_detector_roi_tracking(true);
+        sim.set_detector_artifacts(Some("skin_mask".into()))
+            .unwrap();
+        let frame =
+            ImageData::new_with_u8_clamped_array(Clamped(&[255u8; 64 * 48 * 4]), 64).unwrap();
+        assert!(!sim.process_video_frame(&frame).unwrap());
+        // Artifacts stay at the frame's size
+        let mask = sim.get_detector_artifacts("skin_mask").unwrap().unwrap();
+        assert_eq!((mask.width(), mask.height()), (64, 48));
+        assert!(sim.hand_detector.tracked_region().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_target_interpolation_is_opt_in() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(!sim.is_target_interpolation_enabled());
+        sim.set_target_interpolation(true);
+        sim.handle_pointer_down(10.0, 10.0);
+        sim.update();
+        sim.handle_pointer_move(50.0, 50.0);
+        sim.update();
+        let path = sim.target_path.as_ref().unwrap();
+        assert_eq!(path.newest(), Some(sim.to_world(50.0, 50.0)));
+
+        // Releasing the pointer drops the samples
+        sim.handle_pointer_up();
+        sim.update();
+        assert!(sim.target_path.as_ref().unwrap().is_empty());
+        sim.set_target_interpolation(false);
+        assert!(!sim.is_target_interpolation_enabled());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_session_metrics_are_opt_in() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.handle_pointer_down(10.0, 10.0);
+        assert!(sim.get_session_metrics().is_none());
+
+        sim.set_metrics_enabled(true);
+        sim.handle_pointer_down(10.0, 10.0);
+        sim.update_finger_positions(100.0, 100.0, 110.0, 100.0);
+        for _ in 0..5 {
+            sim.update();
+        }
+        sim.apply_named_preset("calm", r#"{"max_speed": 2.0}"#, 0)
+            .unwrap();
+        sim.apply_named_preset("calm", "{}", 0).unwrap();
+        sim.apply_preset_over("{}", 0).unwrap();
+
+        let metrics = sim.get_session_metrics().unwrap();
+        let get = |key: &str| js_sys::Reflect::get(&metrics, &key.into()).unwrap();
+        assert_eq!(get("frames").as_f64(), Some(5.0));
+        // The press and the pinch
+        assert_eq!(get("interactions").as_f64(), Some(2.0));
+        let presets = js_sys::Array::from(&get("presets"));
+        assert_eq!(presets.length(), 2);
+        let calm = presets.get(0);
+        assert_eq!(
+            js_sys::Reflect::get(&calm, &"count".into())
+                .unwrap()
+                .as_f64(),
+            Some(2.0)
+        );
+
+        sim.set_metrics_enabled(false);
+        assert!(sim.get_session_metrics().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pixel_ratio_keeps_css_pixels() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim.set_pixel_ratio(f64::NAN).is_err());
+
+        sim.set_pixel_ratio(2.0).unwrap();
+        assert_eq!(sim.canvas.width(), 1600);
+        assert_eq!(
+            sim.canvas.style().get_property_value("width").unwrap(),
+            "800px"
+        );
+        // The simulation and its input stay in CSS pixels
+        assert_eq!(sim.flock.width, 800.0);
+        assert_eq!(sim.screen_to_world(100.0, 50.0), vec![100.0, 50.0]);
+        assert!(sim.render().is_ok());
+
+        sim.resize(400.0, 300.0).unwrap();
+        assert_eq!(sim.canvas.height(), 600);
+        sim.set_pixel_ratio(100.0).unwrap();
+        assert_eq!(sim.get_pixel_ratio(), 4.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_get_average_position() {
+        let sim = create_test_simulation().unwrap();
+
+        let avg_pos = sim.get_average_position();
+        assert!(avg_pos.is_some());
+
+        let pos = avg_pos.unwrap();
+        assert_eq!(pos.len(), 2);
+
+        // Average position should be within canvas bounds
+        assert!(pos[0] >= 0.0 && pos[0] <= 800.0);
+        assert!(pos[1] >= 0.0 && pos[1] <= 600.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_all_boids_within_bounds() {
+        let mut sim = create_test_simulation().unwrap();
+
+        // Run several updates to let boids move
+        for _ in 0..100 {
+            sim.update();
+        }
+
+        // All boids should still be within bounds
+        assert!(sim.all_boids_within_bounds(800.0, 600.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render() {
+        let sim = create_test_simulation().unwrap();
+
+        // Render should not panic
+        let result = sim.render();
+        assert!(result.is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_gravity_well_toggle() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(!sim.get_gravity_well_enabled());
+
+        sim.set_gravity_well_enabled(true);
+        assert!(sim.get_gravity_well_enabled());
+        assert_eq!(sim.flock.config.target_mode, TargetMode::GravityWell);
+
+        sim.set_gravity_well_enabled(false);
+        assert_eq!(sim.flock.config.target_mode, TargetMode::Seek);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_gravity_well_setters() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.set_gravity_strength(150.0);
+        assert_eq!(sim.flock.config.gravity_strength, 150.0);
+
+        sim.set_gravity_damping(0.05);
+        assert_eq!(sim.flock.config.gravity_damping, 0.05);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_puppet_boid_follows_external_state() {
+        let mut sim = create_test_simulation().unwrap();
+
+        let id = sim.add_puppet_boid();
+        assert_eq!(id, 10);
+        assert_eq!(sim.boid_count(), 11);
+
+        sim.set_boid_state(id, 123.0, 456.0, 1.0, -1.0).unwrap();
+        sim.update();
+
+        let puppet = &sim.flock.boids[sim.flock.index_of(id).unwrap()];
+        assert_eq!(puppet.position, Vector2D::new(123.0, 456.0));
+        assert_eq!(puppet.velocity, Vector2D::new(1.0, -1.0));
+
+        assert!(sim.set_boid_state(99, 0.0, 0.0, 0.0, 0.0).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pinch_hysteresis() {
+        let mut sim = create_test_simulation().unwrap();
+
+        // Fingers close together must be held for several frames
+        sim.update_finger_positions(400.0, 300.0, 420.0, 300.0);
+        sim.update();
+        assert!(!sim.is_pinched());
+        for _ in 1..PINCH_HOLD_FRAMES {
+            sim.update();
+        }
+        assert!(sim.is_pinched());
+
+        // Distance between the enter and exit thresholds keeps the pinch
+        sim.update_finger_positions(400.0, 300.0, 460.0, 300.0);
+        for _ in 0..10 {
+            sim.update();
+        }
+        assert!(sim.is_pinched());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_spell_and_release() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.select_rect("birds", 0.0, 0.0, 10_000.0, 10_000.0);
+        sim.group_freefloat("birds").unwrap();
+
+        assert_eq!(sim.spell("HI"), 28);
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .all(|boid| boid.target_override.is_formation()));
+        assert!(sim.spell_bitmap(3, vec![1, 0, 1, 0]).is_err());
+        assert_eq!(sim.spell_bitmap(2, vec![1, 0, 1, 1]).unwrap(), 3);
+
+        // Released boids rejoin their group's behavior
+        sim.clear_spell();
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .all(|boid| boid.target_override == TargetOverride::FreeFloat));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_form_shape_and_disperse() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim.form_shape(vec![1.0, 2.0, 3.0]).is_err());
+        assert!(sim.form_shape(vec![1.0, f32::NAN]).is_err());
+
+        sim.form_shape(vec![100.0, 100.0, 200.0, 100.0, 150.0, 200.0])
+            .unwrap();
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .all(|boid| boid.target_override.is_formation()));
+        sim.disperse();
+        assert!(sim.flock.shape().is_none());
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .all(|boid| boid.target_override == TargetOverride::Inherit));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_landmark_smoothing_damps_jumps() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.update_finger_positions(100.0, 100.0, 200.0, 100.0);
+        sim.update_finger_positions(100.0, 100.0, 300.0, 100.0);
+        assert_eq!(sim.get_finger_distance(), Some(200.0));
+
+        sim.set_landmark_smoothing(0.5);
+        sim.update_finger_positions(100.0, 100.0, 200.0, 100.0);
+        sim.update_finger_positions(100.0, 100.0, 300.0, 100.0);
+        assert_eq!(sim.get_finger_distance(), Some(150.0));
+
+        // A hand that reappears is not pulled towards where it was lost
+        sim.clear_finger_positions();
+        sim.update_finger_positions(100.0, 100.0, 400.0, 100.0);
+        assert_eq!(sim.get_finger_distance(), Some(300.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_two_hands_herd_two_sub_flocks() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.flock.boids.clear();
+        let left = sim
+            .flock
+            .add_boid(Boid::new(Vector2D::new(200.0, 300.0), Vector2D::zero()));
+        let right = sim
+            .flock
+            .add_boid(Boid::new(Vector2D::new(600.0, 300.0), Vector2D::zero()));
+
+        // Input x is mirrored: 700 lands at canvas x = 100
+        sim.update_hand_positions("Left", 700.0, 300.0, 710.0, 300.0)
+            .unwrap();
+        sim.update_hand_positions("Right", 100.0, 300.0, 110.0, 300.0)
+            .unwrap();
+        for _ in 0..PINCH_HOLD_FRAMES {
+            sim.update();
+        }
+        assert!(sim.is_hand_pinched("left").unwrap());
+        assert!(sim.is_hand_pinched("right").unwrap());
+
+        let velocity =
+            |sim: &BoidSimulation, id| sim.flock.boids[sim.flock.index_of(id).unwrap()].velocity;
+        assert!(velocity(&sim, left).x < 0.0);
+        assert!(velocity(&sim, right).x > 0.0);
+
+        // Losing one hand releases only its pinch
+        sim.clear_hand_positions("left").unwrap();
+        for _ in 0..PINCH_HOLD_FRAMES {
+            sim.update();
+        }
+        assert!(!sim.is_hand_pinched("left").unwrap());
+        assert!(sim.is_pinched());
+        assert!(sim
+            .update_hand_positions("middle", 0.0, 0.0, 0.0, 0.0)
+            .is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_update_hands_clears_missing_hands() {
+        let mut sim = create_test_simulation().unwrap();
+        let tips = r#"{"thumb_tip":{"x":100.0,"y":300.0},"index_tip":{"x":110.0,"y":300.0}}"#;
+        let both = format!(
+            r#"{{"hands":[{{"handedness":"left","landmarks":{0}}},{{"handedness":"right","landmarks":{0}}}]}}"#,
+            tips
+        );
+        sim.update_hands(&both).unwrap();
+        for _ in 0..PINCH_HOLD_FRAMES {
+            sim.update();
+        }
+        assert!(sim.is_hand_pinched("left").unwrap());
+        assert!(sim.is_hand_pinched("right").unwrap());
+
+        let right_only = format!(
+            r#"{{"hands":[{{"handedness":"right","landmarks":{}}}]}}"#,
+            tips
+        );
+        sim.update_hands(&right_only).unwrap();
+        for _ in 0..PINCH_HOLD_FRAMES {
+            sim.update();
+        }
+        assert!(!sim.is_hand_pinched("left").unwrap());
+        assert!(sim.is_hand_pinched("right").unwrap());
+        assert!(sim
+            .update_hands(r#"{"hands":[{"handedness":"middle"}]}"#)
+            .is_err());
+    }
+
+    /// 42 coordinates of a hand pointing up from a wrist at (400, 400)
+    fn hand_coords(extended: [bool; 5]) -> Vec<f32> {
+        test_util::full_hand(Position::new(400.0, 400.0), extended)
+            .points
+            .iter()
+            .flat_map(|point| [point.x, point.y])
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_full_hand_gestures() {
+        let mut sim = create_test_simulation().unwrap();
+
+        sim.update_full_hand_landmarks("right", hand_coords([false; 5]), None)
+            .unwrap();
+        assert_eq!(
+            sim.get_hand_gesture("right").unwrap().as_deref(),
+            Some("fist")
+        );
+        sim.update();
+        assert_eq!(sim.flock.modifiers.cohesion_weight, GATHER_COHESION);
+
+        sim.update_full_hand_landmarks("right", hand_coords([true; 5]), None)
+            .unwrap();
+        sim.update();
+        assert_eq!(
+            sim.get_hand_gesture("right").unwrap().as_deref(),
+            Some("open_palm")
+        );
+        assert_eq!(sim.flock.modifiers.separation_weight, SCATTER_SEPARATION);
+
+        // Low visibility on a fingertip makes the gesture unknown
+        let mut visibility = vec![1.0; 21];
+        visibility[20] = 0.0;
+        sim.update_full_hand_landmarks("right", hand_coords([true; 5]), Some(visibility))
+            .unwrap();
+        assert_eq!(sim.get_hand_gesture("right").unwrap(), None);
+
+        // Tip-only input carries no gesture
+        sim.update_finger_positions(100.0, 300.0, 400.0, 300.0);
+        assert_eq!(sim.get_hand_gesture("right").unwrap(), None);
+        assert!(sim
+            .update_full_hand_landmarks("right", vec![0.0; 10], None)
+            .is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_errors_carry_codes() {
+        let mut sim = create_test_simulation().unwrap();
+        let error = sim.group_remove("missing").unwrap_err();
+        let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("E-SIM-04"));
+
+        assert!(describe_error_code("e-sim-04").is_some());
+        assert!(describe_error_code("E-SIM-99").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_undo_config_change() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(!sim.undo_last_config_change());
+
+        sim.set_max_speed(6.0);
+        sim.set_cohesion_weight(3.0);
+        assert_eq!(sim.get_config_change_log().length(), 2);
+
+        assert!(sim.undo_last_config_change());
+        assert_eq!(sim.flock.config.cohesion_weight, 1.0);
+        assert_eq!(sim.flock.config.max_speed, 6.0);
+
+        assert!(sim.undo_last_config_change());
+        assert_eq!(sim.flock.config.max_speed, 2.0);
+        assert_eq!(sim.get_config_change_log().length(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_spatial_grid_enabled_by_default() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim.get_spatial_grid_enabled());
+
+        sim.set_spatial_grid_enabled(false);
+        assert!(!sim.get_spatial_grid_enabled());
+        sim.update();
+        assert_eq!(sim.boid_count(), 10);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_group_selection_and_commands() {
+        let mut sim = create_test_simulation().unwrap();
+        for (i, boid) in sim.flock.boids.iter_mut().enumerate() {
+            let x = if i < 4 { 100.0 } else { 600.0 };
+            boid.position = Vector2D::new(x + i as f32, 300.0);
+        }
 
-        // Draw index finger circle
-        self.context.begin_path();
-        self.context.arc(
-            index.x as f64,
-            index.y as f64,
-            8.0,
-            0.0,
-            2.0 * std::f64::consts::PI,
-        )?;
-        self.context.set_fill_style_str("rgba(0, 0, 255, 0.8)");
-        self.context.fill();
+        assert_eq!(sim.select_rect("left", 150.0, 250.0, 50.0, 350.0), 4);
+        assert_eq!(sim.select_ids("right", vec![4, 5, 99]), 2);
+        assert_eq!(sim.get_group_members("right"), vec![4, 5]);
+        assert_eq!(sim.get_group_names().length(), 2);
 
-        Ok(())
+        // Reselecting moves boids between groups
+        assert_eq!(sim.select_ids("right", vec![0]), 3);
+        assert_eq!(sim.get_group_members("left"), vec![1, 2, 3]);
+
+        sim.group_set_target("left", 50.0, 50.0).unwrap();
+        sim.group_freefloat("right").unwrap();
+        assert_eq!(
+            sim.flock.boids[1].target_override,
+            TargetOverride::Target(Vector2D::new(50.0, 50.0))
+        );
+        assert_eq!(
+            sim.flock.boids[4].target_override,
+            TargetOverride::FreeFloat
+        );
+        assert_eq!(sim.flock.boids[6].target_override, TargetOverride::Inherit);
+
+        sim.group_set_tint("left", Some(300.0)).unwrap();
+        sim.set_highlighted_group(Some("left".into()));
+        assert!(sim.render().is_ok());
+        assert!(sim.group_set_tint("missing", None).is_err());
+
+        sim.ungroup("left");
+        assert_eq!(sim.flock.boids[1].target_override, TargetOverride::Inherit);
+
+        assert_eq!(sim.group_remove("right").unwrap(), 3);
+        assert_eq!(sim.boid_count(), 7);
+        assert!(sim.get_group_names().length() == 0);
     }
 
-    /// Process a video frame for hand detection using shared hand detector
-    /// Takes ImageData from a canvas and detects hand landmarks
-    pub fn process_video_frame(&mut self, image_data: &ImageData) -> Result<bool, JsValue> {
-        let width = image_data.width() as usize;
-        let height = image_data.height() as usize;
-        let data = image_data.data();
+    #[wasm_bindgen_test]
+    fn test_sub_flocks() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.split_sub_flocks(3);
+        assert_eq!(sim.get_sub_flock(0), Some(0));
+        assert_eq!(sim.get_sub_flock(4), Some(1));
+        assert_eq!(sim.get_sub_flock(99), None);
+        assert_eq!(sim.get_sub_flock_hue(1), Some(120.0));
 
-        match self.hand_detector.process_rgba_image(width, height, &data) {
-            Some(landmarks) => {
-                let canvas_width = self.canvas.width() as f32;
-                // Mirror the x-coordinates to match the flipped video
-                self.thumb_position = Some(Vector2D::new(
-                    canvas_width - landmarks.thumb_tip.x,
-                    landmarks.thumb_tip.y,
-                ));
-                self.index_position = Some(Vector2D::new(
-                    canvas_width - landmarks.index_tip.x,
-                    landmarks.index_tip.y,
-                ));
-                Ok(true) // Hand detected
-            }
-            None => {
-                self.thumb_position = None;
-                self.index_position = None;
-                Ok(false) // No hand detected
-            }
+        assert!(sim.set_sub_flock(4, 2));
+        assert_eq!(sim.get_sub_flock(4), Some(2));
+
+        sim.set_sub_flock_config(2, r#"{"max_speed": 0.5}"#)
+            .unwrap();
+        assert_eq!(sim.flock.config_for_group(2).max_speed, 0.5);
+        assert!(sim.set_sub_flock_config(2, r#"{"max_sped": 0.5}"#).is_err());
+        sim.update();
+        assert!(sim.render().is_ok());
+
+        sim.clear_sub_flock_config(2);
+        sim.split_sub_flocks(1);
+        assert_eq!(sim.get_sub_flock(4), Some(0));
+        assert_eq!(sim.get_sub_flock_hue(0), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_cluster_voices() {
+        let mut boids = Vec::new();
+        // Five slow boids on the left, three fast ones on the right, one straggler
+        for i in 0..5 {
+            let position = Vector2D::new(100.0 + i as f32 * 5.0, 300.0);
+            boids.push(Boid::new(position, Vector2D::new(0.5, 0.0)));
         }
+        for i in 0..3 {
+            let position = Vector2D::new(700.0 + i as f32 * 5.0, 300.0);
+            boids.push(Boid::new(position, Vector2D::new(4.0, 0.0)));
+        }
+        boids.push(Boid::new(Vector2D::new(400.0, 50.0), Vector2D::zero()));
+
+        let clusters = groups::clusters(&boids, 25.0);
+        assert_eq!(
+            clusters.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![5, 3, 1]
+        );
+
+        let voices = audio::voices(&boids, &clusters, 800.0, 600.0, 4.0);
+        assert_eq!(voices.len(), 2);
+        assert!(voices[0].pan < -0.5);
+        assert!(voices[1].pan > 0.5);
+        assert!(voices[0].gain > voices[1].gain);
+        assert!(voices[1].frequency > voices[0].frequency);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use wasm_bindgen_test::*;
+    #[wasm_bindgen_test]
+    fn test_sound_volume_without_audio() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(!sim.is_sound_enabled());
+        sim.set_sound_volume(1.5).unwrap();
+        assert_eq!(sim.get_sound_volume(), 1.0);
+        sim.set_sound_enabled(false).unwrap();
+        sim.update();
+        assert!(!sim.is_sound_enabled());
+    }
 
-    wasm_bindgen_test_configure!(run_in_browser);
+    #[wasm_bindgen_test]
+    fn test_select_cluster() {
+        let mut sim = create_test_simulation().unwrap();
+        for (i, boid) in sim.flock.boids.iter_mut().enumerate() {
+            // Two chains of boids 10px apart, far from each other
+            let x = if i < 6 {
+                100.0 + i as f32 * 10.0
+            } else {
+                600.0 + i as f32 * 10.0
+            };
+            boid.position = Vector2D::new(x, 300.0);
+        }
 
-    fn create_test_canvas() -> Result<HtmlCanvasElement, JsValue> {
-        let window = web_sys::window().ok_or("no window")?;
-        let document = window.document().ok_or("no document")?;
-        let canvas = document
-            .create_element("canvas")?
-            .dyn_into::<HtmlCanvasElement>()?;
-        canvas.set_id("test-canvas");
-        canvas.set_width(800);
-        canvas.set_height(600);
-        document.body().unwrap().append_child(&canvas)?;
-        Ok(canvas)
+        assert_eq!(sim.select_cluster("chain", 95.0, 300.0), 6);
+        assert_eq!(sim.get_group_members("chain"), vec![0, 1, 2, 3, 4, 5]);
     }
 
-    fn create_test_simulation() -> Result<BoidSimulation, JsValue> {
-        create_test_canvas()?;
-        BoidSimulation::new("test-canvas", 800.0, 600.0, 10)
+    #[wasm_bindgen_test]
+    fn test_tick_timing_after_update() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.update();
+
+        let timing = sim.get_tick_timing();
+        let total = js_sys::Reflect::get(&timing, &"total_ms".into())
+            .unwrap()
+            .as_f64()
+            .unwrap();
+        assert!(total >= 0.0);
+        assert_eq!(sim.last_tick_timing.total_ms(), total);
     }
 
     #[wasm_bindgen_test]
-    fn test_simulation_creation() {
-        let simulation = create_test_simulation();
-        assert!(simulation.is_ok());
-        let sim = simulation.unwrap();
-        assert_eq!(sim.boid_count(), 10);
+    fn test_sprite_frame_selection() {
+        use sprites::{frame_index, heading_bucket};
+
+        assert_eq!(frame_index(0.0, 4), 0);
+        assert_eq!(frame_index(0.99, 4), 3);
+        assert_eq!(frame_index(1.25, 4), 1);
+
+        let quarter = std::f64::consts::FRAC_PI_2;
+        assert_eq!(heading_bucket(0.0, 8), 0);
+        assert_eq!(heading_bucket(quarter, 4), 1);
+        assert_eq!(heading_bucket(-quarter, 4), 3);
+        assert_eq!(heading_bucket(std::f64::consts::TAU - 0.01, 8), 0);
     }
 
     #[wasm_bindgen_test]
-    fn test_pointer_down_sets_state() {
+    fn test_sprite_style_falls_back_to_triangles() {
         let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_render_style(), "triangle");
+        assert!(sim.set_render_style("hexagon").is_err());
 
-        // Initially, pointer should not be pressed
-        assert!(!sim.pointer_pressed);
-        assert!(sim.pointer_position.is_none());
+        sim.set_render_style("sprite").unwrap();
+        assert!(sim.load_sprite_sheet("birds.png", 0.0, 16.0, 4, 8).is_err());
+        sim.load_sprite_sheet("birds.png", 16.0, 16.0, 4, 8)
+            .unwrap();
 
-        // After pointer down, state should be updated
-        sim.handle_pointer_down(100.0, 200.0);
-        assert!(sim.pointer_pressed);
-        assert!(sim.pointer_position.is_some());
+        // The image cannot have loaded yet, so rendering uses triangles
+        assert!(!sim.is_sprite_sheet_ready());
+        sim.update();
+        assert!(sim.render().is_ok());
+        assert_eq!(sim.animation_phases.len(), 10);
+    }
 
-        let pos = sim.pointer_position.unwrap();
-        assert_eq!(pos.x, 100.0);
-        assert_eq!(pos.y, 200.0);
+    #[wasm_bindgen_test]
+    fn test_depth_effects_render() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_depth_effect(), "none");
+        assert!(sim.set_depth_effect("fog").is_err());
+
+        sim.set_horizon(1.5);
+        assert_eq!(sim.get_horizon(), 1.0);
+        sim.set_horizon(0.6);
+        for effect in ["shadow", "reflection"] {
+            sim.set_depth_effect(effect).unwrap();
+            assert_eq!(sim.get_depth_effect(), effect);
+            sim.update();
+            assert!(sim.render().is_ok());
+        }
+
+        // Reflections fade out towards the top of the canvas
+        let near = depth::reflection_alpha(0.5, 10.0, 360.0);
+        let far = depth::reflection_alpha(0.5, 300.0, 360.0);
+        assert!(near > far && far > 0.0);
+        assert_eq!(depth::reflection_alpha(0.5, -1.0, 360.0), 0.0);
     }
 
     #[wasm_bindgen_test]
-    fn test_pointer_move_updates_position() {
+    fn test_wind_and_gusts() {
         let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_wind(), vec![0.0, 0.0]);
+        assert!(sim.set_gust("storm", 1.0, 0.0, 1.0).is_err());
 
-        sim.handle_pointer_down(100.0, 100.0);
-        sim.handle_pointer_move(200.0, 300.0);
+        sim.set_wind(0.1, 0.0);
+        sim.set_gust("sine", 0.0, 0.2, 1.0).unwrap();
+        for _ in 0..15 {
+            sim.update();
+        }
+        let wind = sim.get_wind();
+        assert!((wind[0] - 0.1).abs() < 1e-6 && (wind[1] - 0.2).abs() < 1e-3);
+    }
 
-        assert!(sim.pointer_pressed);
-        let pos = sim.pointer_position.unwrap();
-        assert_eq!(pos.x, 200.0);
-        assert_eq!(pos.y, 300.0);
+    #[wasm_bindgen_test]
+    fn test_color_schemes() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_color_scheme(), "theme");
+        assert!(sim.set_color_scheme("rainbow").is_err());
+
+        sim.flock.boids[1].group = 1;
+        for scheme in ["okabe_ito", "high_contrast", "theme"] {
+            sim.set_color_scheme(scheme).unwrap();
+            assert_eq!(sim.get_color_scheme(), scheme);
+            assert!(sim.render().is_ok());
+        }
     }
 
     #[wasm_bindgen_test]
-    fn test_pointer_up_clears_pressed_state() {
+    fn test_exclusion_rects() {
         let mut sim = create_test_simulation().unwrap();
+        assert!(sim.set_exclusion_rects(vec![0.0, 0.0, 10.0], 3.0).is_err());
+        assert!(sim
+            .set_exclusion_rects(vec![0.0, 0.0, f32::NAN, 10.0], 3.0)
+            .is_err());
 
-        sim.handle_pointer_down(100.0, 100.0);
-        assert!(sim.pointer_pressed);
+        // A headline across the middle of the canvas
+        sim.set_exclusion_rects(vec![200.0, 250.0, 400.0, 100.0], 3.0)
+            .unwrap();
+        assert_eq!(sim.flock.behaviors.weight(EXCLUSION_BEHAVIOR), Some(3.0));
+        for _ in 0..10 {
+            sim.update();
+        }
+        sim.clear_exclusion_rects();
+        assert_eq!(sim.flock.behaviors.weight(EXCLUSION_BEHAVIOR), None);
+        sim.update();
+    }
 
-        sim.handle_pointer_up();
-        assert!(!sim.pointer_pressed);
-        // Position should still be set, just not pressed
-        assert!(sim.pointer_position.is_some());
+    #[wasm_bindgen_test]
+    fn test_boid_style() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_boid_shape(), "triangle");
+        assert!(sim.set_boid_shape("hexagon").is_err());
+        assert!(sim.set_boid_size(10.0).is_err());
+        assert_eq!(sim.get_boid_size(), 1.0);
+
+        for shape in ["dot", "arrow", "triangle"] {
+            sim.set_boid_shape(shape).unwrap();
+            sim.set_boid_size(2.0).unwrap();
+            assert!(sim.render().is_ok());
+        }
+        sim.set_boid_style(r#"{"size":0.5,"palette":"okabe_ito","shape":"dot"}"#)
+            .unwrap();
+        assert_eq!(sim.get_color_scheme(), "okabe_ito");
+        assert_eq!(sim.get_boid_shape(), "dot");
+        assert_eq!(sim.get_boid_size(), 0.5);
+        assert!(sim.set_boid_style(r#"{"size":0.0}"#).is_err());
+        assert!(sim.get_boid_style().unwrap().contains(r#""shape":"dot""#));
     }
 
     #[wasm_bindgen_test]
-    fn test_update_without_target() {
+    fn test_audio_reactive_mode() {
         let mut sim = create_test_simulation().unwrap();
+        assert!(sim.set_audio_mapping("volume", 0.0, 1.0, 1.0).is_err());
+        sim.set_audio_mapping("cohesion", 1.0, 3.0, 1.0).unwrap();
 
-        // Get initial positions
-        let initial_count = sim.boid_count();
+        // 1024 bins at 48 kHz: loud bass, silent treble
+        let mut bins = vec![f32::NEG_INFINITY; 1024];
+        bins[..10].fill(-30.0);
+        sim.feed_audio(&bins, 48000.0);
+        assert_eq!(sim.get_audio_levels(), vec![0.0, 0.0]);
 
-        // Update without pointer pressed
+        sim.set_audio_reactive(true);
+        for _ in 0..10 {
+            sim.feed_audio(&bins, 48000.0);
+        }
+        let levels = sim.get_audio_levels();
+        assert!(levels[0] > 0.9 && levels[1] == 0.0);
+
+        // The music only lasts the tick
         sim.update();
+        assert!(sim.flock.modifiers.is_identity());
+    }
 
-        // Boids should still exist and move
-        assert_eq!(sim.boid_count(), initial_count);
+    #[wasm_bindgen_test]
+    fn test_camera_follows_flock() {
+        let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_camera(), vec![400.0, 300.0, 1.0]);
+        assert_eq!(sim.screen_to_world(100.0, 50.0), vec![100.0, 50.0]);
+        assert!(sim.set_camera_follow("moon").is_err());
+
+        // Herd the flock into a corner and snap onto it
+        for (index, boid) in sim.flock.boids.iter_mut().enumerate() {
+            boid.position = Vector2D::new(100.0 + index as f32, 100.0);
+        }
+        sim.set_camera_follow("flock").unwrap();
+        sim.set_camera_damping(0.0);
+        sim.set_camera_zoom_to_fit(true);
+        sim.update();
+        let [x, y, zoom] = sim.get_camera()[..] else {
+            panic!("expected x, y and zoom");
+        };
+        assert!((x - 100.0).abs() < 50.0 && (y - 100.0).abs() < 10.0);
+        assert!(zoom > 1.0);
+        assert!(sim.render().is_ok());
+
+        // The canvas middle shows the camera center
+        let middle = sim.screen_to_world(400.0, 300.0);
+        assert!((middle[0] - x).abs() < 1e-3 && (middle[1] - y).abs() < 1e-3);
+
+        let id = sim.get_boid_ids()[0];
+        sim.set_camera_follow(&format!("boid:{}", id)).unwrap();
+        assert_eq!(sim.get_camera_follow(), format!("boid:{}", id));
+        sim.set_camera_view(10.0, 20.0, 100.0);
+        assert_eq!(sim.get_camera_follow(), "off");
+        assert_eq!(sim.get_camera(), vec![10.0, 20.0, camera::MAX_ZOOM as f64]);
+        sim.reset_camera();
+        assert_eq!(sim.get_camera(), vec![400.0, 300.0, 1.0]);
     }
 
     #[wasm_bindgen_test]
-    fn test_update_with_target() {
+    fn test_hand_modulation_keeps_base_config() {
         let mut sim = create_test_simulation().unwrap();
+        sim.set_max_speed(4.0);
 
-        // Set a target by pressing pointer
-        sim.handle_pointer_down(400.0, 300.0);
+        // Wide open fingers scale the effective speed only
+        sim.update_finger_positions(100.0, 300.0, 400.0, 300.0);
+        sim.update();
+        assert_eq!(sim.get_current_max_speed(), 10.0);
+        assert_eq!(sim.flock.config.max_speed, 4.0);
 
-        // Update should apply seek behavior towards target
+        // A setter during modulation changes the base, not the modifier
+        sim.set_separation_weight(2.0);
+        assert_eq!(sim.get_current_separation_weight(), 6.0);
+
+        sim.clear_finger_positions();
         sim.update();
+        assert_eq!(sim.get_current_max_speed(), 4.0);
+        assert_eq!(sim.get_current_separation_weight(), 2.0);
+        assert!(sim.flock.modifiers.is_identity());
+    }
 
-        // Boid count should remain the same
-        assert_eq!(sim.boid_count(), 10);
+    #[wasm_bindgen_test]
+    fn test_export_import_state() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.update();
+        let json = sim.export_state().unwrap();
+
+        let mut other = create_test_simulation().unwrap();
+        other.import_state(&json).unwrap();
+        for (a, b) in sim.flock.boids.iter().zip(other.flock.boids.iter()) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.velocity, b.velocity);
+        }
+
+        assert!(other.import_state("{\"boids\": 3}").is_err());
+        assert_eq!(other.boid_count(), 10);
     }
 
     #[wasm_bindgen_test]
-    fn test_configuration_setters() {
+    fn test_seeded_simulations_match() {
+        create_test_canvas().unwrap();
+        let mut a = BoidSimulation::new_with_seed("test-canvas", 800.0, 600.0, 10, 5).unwrap();
+        let mut b = BoidSimulation::new_with_seed("test-canvas", 800.0, 600.0, 10, 5).unwrap();
+        a.set_wander_enabled(true);
+        b.set_wander_enabled(true);
+        for _ in 0..10 {
+            a.update();
+            b.update();
+        }
+        assert_eq!(a.export_state().unwrap(), b.export_state().unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_lod_detail_mask_prefers_near_and_fast_boids() {
+        let focus = Vector2D::new(100.0, 100.0);
+        let near = Boid::new(Vector2D::new(110.0, 100.0), Vector2D::zero());
+        let far = Boid::new(Vector2D::new(300.0, 100.0), Vector2D::zero());
+        let fast = Boid::new(Vector2D::new(150.0, 100.0), Vector2D::new(4.0, 0.0));
+        let slow = Boid::new(Vector2D::new(140.0, 100.0), Vector2D::zero());
+        let boids = [near, far, fast, slow];
+
+        assert_eq!(lod::detail_mask(&boids, focus, 4.0, 0), vec![false; 4]);
+        assert_eq!(
+            lod::detail_mask(&boids, focus, 4.0, 2),
+            vec![true, false, true, false]
+        );
+        assert_eq!(lod::detail_mask(&boids, focus, 4.0, 10), vec![true; 4]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_lod_settings() {
         let mut sim = create_test_simulation().unwrap();
+        assert_eq!(sim.get_lod_budget(), None);
+        assert_eq!(sim.get_lod_focus(), "center");
+        assert!(sim.set_lod_focus("corner").is_err());
 
-        sim.set_separation_weight(2.5);
-        assert_eq!(sim.flock.config.separation_weight, 2.5);
+        sim.set_lod_budget(Some(3));
+        sim.set_lod_focus("cursor").unwrap();
+        assert_eq!(sim.lod_focus_point(), Vector2D::new(400.0, 300.0));
+        sim.handle_pointer_move(20.0, 30.0);
+        assert_eq!(sim.lod_focus_point(), Vector2D::new(20.0, 30.0));
+        assert!(sim.render().is_ok());
 
-        sim.set_alignment_weight(1.8);
-        assert_eq!(sim.flock.config.alignment_weight, 1.8);
+        sim.set_lod_budget(None);
+        assert_eq!(sim.get_lod_budget(), None);
+    }
 
-        sim.set_cohesion_weight(1.2);
-        assert_eq!(sim.flock.config.cohesion_weight, 1.2);
+    #[wasm_bindgen_test]
+    fn test_preset_crossfade() {
+        let mut sim = create_test_simulation().unwrap();
+        assert!(sim
+            .apply_preset_over(r#"{"cohesion_wieght": 2.0}"#, 10)
+            .is_err());
+        assert!(sim.apply_preset_over("[1, 2]", 10).is_err());
 
-        sim.set_max_speed(6.0);
-        assert_eq!(sim.flock.config.max_speed, 6.0);
+        sim.apply_preset_over(r#"{"cohesion_weight": 3.0, "max_speed": 4.0}"#, 10)
+            .unwrap();
+        assert!(sim.is_preset_transition_active());
+        sim.update();
+        let cohesion = sim.flock.config.cohesion_weight;
+        assert!(cohesion > 1.0 && cohesion < 3.0);
+        assert_eq!(sim.flock.config.separation_weight, 1.5);
 
-        sim.set_max_force(0.2);
-        assert_eq!(sim.flock.config.max_force, 0.2);
+        // A setter cancels the crossfade where it stands
+        sim.set_separation_weight(2.0);
+        assert!(!sim.is_preset_transition_active());
+        sim.update();
+        assert_eq!(sim.flock.config.cohesion_weight, cohesion);
 
-        sim.set_seek_weight(10.0);
-        assert_eq!(sim.flock.config.seek_weight, 10.0);
+        // Undo returns to the values before the setter
+        assert!(sim.undo_last_config_change());
+        assert_eq!(sim.flock.config.separation_weight, 1.5);
     }
 
     #[wasm_bindgen_test]
-    fn test_resize() {
+    fn test_demo_mode_rotates_and_restores() {
         let mut sim = create_test_simulation().unwrap();
+        let error = sim
+            .start_demo_mode(Some(r#"{"scenes": []}"#.to_string()))
+            .unwrap_err();
+        assert_eq!(
+            js_sys::Reflect::get(&error, &"code".into()).unwrap(),
+            "E-CFG-05"
+        );
 
-        sim.resize(1024.0, 768.0);
+        let schedule = r#"{"transition_ticks": 0, "scenes": [
+            {"duration_ticks": 2, "theme": "ember",
+             "settings": {"separation_weight": 1.5, "alignment_weight": 1.0,
+                          "cohesion_weight": 3.0, "max_speed": 2.0,
+                          "max_force": 0.1, "seek_weight": 8.0},
+             "target": {"path": "orbit", "period_ticks": 10}},
+            {"duration_ticks": 2, "spell": "HI"}
+        ]}"#;
+        sim.start_demo_mode(Some(schedule.to_string())).unwrap();
+        assert!(sim.is_demo_mode_active());
+        sim.update();
+        assert_eq!(sim.get_demo_scene(), Some(0));
+        assert_eq!(sim.flock.config.cohesion_weight, 3.0);
+        assert_eq!(sim.palette(), Theme::Ember.palette());
 
-        assert_eq!(sim.canvas.width(), 1024);
-        assert_eq!(sim.canvas.height(), 768);
-        assert_eq!(sim.flock.width, 1024.0);
-        assert_eq!(sim.flock.height, 768.0);
+        sim.update();
+        sim.update();
+        assert_eq!(sim.get_demo_scene(), Some(1));
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .any(|boid| boid.target_override.is_formation()));
+
+        sim.stop_demo_mode();
+        assert!(!sim.is_demo_mode_active());
+        assert!(sim
+            .flock
+            .boids
+            .iter()
+            .all(|boid| !boid.target_override.is_formation()));
+        assert_eq!(sim.flock.config.cohesion_weight, 1.0);
     }
 
     #[wasm_bindgen_test]
-    fn test_get_average_position() {
-        let sim = create_test_simulation().unwrap();
+    fn test_spawn_and_remove_boids() {
+        let mut sim = create_test_simulation().unwrap();
+        let count = sim.boid_count();
+        let id = sim.spawn_boid(400.0, 300.0, 1.0, 0.0).unwrap();
+        assert_eq!(sim.boid_count(), count + 1);
+        sim.select_ids("picked", vec![id]);
 
-        let avg_pos = sim.get_average_position();
-        assert!(avg_pos.is_some());
+        assert_eq!(sim.remove_nearest_boid(400.0, 300.0, 5.0), Some(id));
+        assert!(sim.get_group_members("picked").is_empty());
 
-        let pos = avg_pos.unwrap();
-        assert_eq!(pos.len(), 2);
+        assert_eq!(sim.set_boid_count(25), 25);
+        sim.select_rect("all", 0.0, 0.0, 800.0, 600.0);
+        assert_eq!(sim.set_boid_count(count), count);
+        assert_eq!(sim.get_group_members("all").len(), count);
 
-        // Average position should be within canvas bounds
-        assert!(pos[0] >= 0.0 && pos[0] <= 800.0);
-        assert!(pos[1] >= 0.0 && pos[1] <= 600.0);
+        sim.set_max_population(Some(count), false);
+        assert_eq!(sim.spawn_boid(10.0, 10.0, 0.0, 0.0), None);
+        sim.set_max_population(Some(count), true);
+        assert!(sim.spawn_boid(10.0, 10.0, 0.0, 0.0).is_some());
+        assert_eq!(sim.boid_count(), count);
+
+        // Boids the cap removes leave their group too
+        assert_eq!(sim.get_group_members("all").len(), count - 1);
+        sim.set_render_style("sprite").unwrap();
+        sim.update();
+        sim.set_max_population(Some(5), false);
+        sim.update();
+        assert_eq!(sim.boid_count(), 5);
+        let ids = sim.get_boid_ids();
+        assert!(sim
+            .get_group_members("all")
+            .iter()
+            .all(|id| ids.contains(id)));
+        assert!(sim.animation_phases.keys().all(|id| ids.contains(id)));
     }
 
     #[wasm_bindgen_test]
-    fn test_all_boids_within_bounds() {
+    fn test_neighbor_edges() {
         let mut sim = create_test_simulation().unwrap();
+        sim.set_boid_count(0);
+        let a = sim.spawn_boid(400.0, 300.0, 0.0, 0.0).unwrap();
+        let b = sim.spawn_boid(405.0, 300.0, 0.0, 0.0).unwrap();
+        sim.spawn_boid(100.0, 100.0, 0.0, 0.0).unwrap();
+        sim.update();
 
-        // Run several updates to let boids move
-        for _ in 0..100 {
-            sim.update();
+        assert_eq!(sim.get_neighbor_edges("separation").unwrap(), [a, b]);
+        assert_eq!(sim.get_neighbors_of(b, "cohesion").unwrap(), [a]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_hand_backend_fallback() {
+        let mut sim = create_test_simulation().unwrap();
+        // The test page loads neither MediaPipe nor OpenCV.js
+        assert_eq!(sim.detect_hand_backends(), "skin");
+
+        sim.set_hand_backend_available("mediapipe", true).unwrap();
+        sim.set_hand_backend_available("opencv", true).unwrap();
+        assert_eq!(sim.get_hand_backend(), "mediapipe");
+        for _ in 0..20 {
+            sim.report_hand_backend_cost("mediapipe", 80.0).unwrap();
         }
+        assert_eq!(sim.get_hand_backend(), "opencv");
+        sim.set_hand_backend_available("opencv", false).unwrap();
+        assert_eq!(sim.get_hand_backend(), "skin");
 
-        // All boids should still be within bounds
-        assert!(sim.all_boids_within_bounds(800.0, 600.0));
+        sim.force_hand_backend(Some("mediapipe".to_string()))
+            .unwrap();
+        assert_eq!(sim.get_hand_backend(), "mediapipe");
+        assert_eq!(sim.get_hand_backends().length(), 3);
+        assert!(sim.force_hand_backend(Some("tfjs".to_string())).is_err());
+        sim.force_hand_backend(None).unwrap();
+        assert_eq!(sim.get_hand_backend(), "skin");
     }
 
     #[wasm_bindgen_test]
-    fn test_render() {
-        let sim = create_test_simulation().unwrap();
+    fn test_input_mappings_drive_parameters() {
+        let mut sim = create_test_simulation().unwrap();
+        let base = sim.flock.config;
+        // Built in: open fingers, 150 of 300 px, double separation
+        sim.update_finger_positions(100.0, 300.0, 250.0, 300.0);
+        sim.update();
+        assert_eq!(
+            sim.get_current_separation_weight(),
+            (base.separation_weight * 2.0) as f64
+        );
+        let signals = sim.get_input_signals();
+        let height = js_sys::Reflect::get(&signals, &"hand_height".into()).unwrap();
+        assert_eq!(height.as_f64(), Some(0.5));
 
-        // Render should not panic
-        let result = sim.render();
-        assert!(result.is_ok());
+        sim.set_input_mappings(
+            r#"[{"input": "hand_height", "parameter": "wander_radius",
+                 "input_min": 0, "input_max": 1, "low": 1, "high": 3}]"#,
+        )
+        .unwrap();
+        sim.update();
+        assert_eq!(
+            sim.get_current_separation_weight(),
+            base.separation_weight as f64
+        );
+        let wander = sim.flock.effective_config().wander_radius;
+        assert!((wander - base.wander_radius * 2.0).abs() < 1e-6);
+        assert_eq!(sim.flock.config, base);
+        assert!(sim.get_input_mappings().unwrap().contains("wander_radius"));
+
+        for invalid in [
+            r#"[{"input": "mood", "parameter": "max_speed",
+                 "input_min": 0, "input_max": 1, "low": 1, "high": 2}]"#,
+            r#"[{"input": "hand_height", "parameter": "max_speed",
+                 "input_min": 1, "input_max": 1, "low": 1, "high": 2}]"#,
+        ] {
+            let error = sim.set_input_mappings(invalid).unwrap_err();
+            let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+            assert_eq!(code.as_string().as_deref(), Some("E-CFG-07"));
+        }
+        sim.reset_input_mappings();
+        assert!(sim
+            .get_input_mappings()
+            .unwrap()
+            .contains("finger_distance"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mirror_mode_draws_the_remote_flock() {
+        use boid_shared::mirror::BoidState;
+
+        let mut sim = create_test_simulation().unwrap();
+        let boid = |x, y| BoidState {
+            x,
+            y,
+            vx: 1.0,
+            vy: 0.0,
+            group: 1,
+        };
+        let frame = FlockFrame {
+            frame: 1,
+            width: 240.0,
+            height: 240.0,
+            boids: vec![boid(60.0, 120.0), boid(120.0, 60.0)],
+        };
+        // Ignored until mirroring
+        assert!(!sim.apply_remote_flock(&frame.to_bytes()).unwrap());
+
+        sim.set_mirror_mode(true);
+        assert!(sim.apply_remote_flock(&frame.to_bytes()).unwrap());
+        assert!(!sim.apply_remote_flock(&frame.to_bytes()).unwrap());
+        assert_eq!(sim.get_remote_frame(), Some(1));
+        sim.update();
+        assert_eq!(sim.boid_count(), 2);
+        // Scaled from the device's 240 x 240 to the 800 x 600 canvas
+        let first = &sim.flock.boids[0];
+        assert!((first.position - Vector2D::new(200.0, 300.0)).magnitude() < 1e-3);
+        assert_eq!(first.group, 1);
+        // A single frame has nothing to glide from
+        sim.update();
+        assert!((sim.flock.boids[0].position - Vector2D::new(200.0, 300.0)).magnitude() < 1e-3);
+
+        let json = r#"{"frame":2,"width":240.0,"height":240.0,"boids":[[0.0,0.0,0.0,0.0,0]]}"#;
+        assert!(sim.apply_remote_flock_json(json).unwrap());
+        sim.update();
+        assert_eq!(sim.boid_count(), 1);
+        assert!(sim.apply_remote_flock(&[1, 2, 3]).is_err());
+
+        sim.set_mirror_mode(false);
+        assert_eq!(sim.get_remote_frame(), None);
+        sim.update();
+        assert_eq!(sim.boid_count(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_perched_boids_are_counted() {
+        let mut sim = create_test_simulation().unwrap();
+        sim.set_perching_enabled(true);
+        assert!(sim.is_perching_enabled());
+        // Every boid heads for the ground at once
+        sim.flock.set_perching(Some(PerchConfig {
+            perch_probability: 1000.0,
+            ..PerchConfig::default()
+        }));
+        for _ in 0..600 {
+            sim.update();
+            if sim.get_perched_count() > 0 {
+                break;
+            }
+        }
+        assert!(sim.get_perched_count() > 0);
+        sim.set_perching_enabled(false);
+        assert_eq!(sim.get_perched_count(), 0);
     }
 }
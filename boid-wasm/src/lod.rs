@@ -0,0 +1,70 @@
+//! Level of detail for large flocks
+//!
+//! With a detail budget set, only the most important boids are drawn in
+//! full; the rest become single pixels. Importance is distance to the focus
+//! point, discounted for fast boids so that motion stays visible at the
+//! edges of the focus area.
+
+use boid_core::{Boid, Vector2D};
+
+/// Point the detail budget is spent around
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LodFocus {
+    /// Center of the canvas
+    #[default]
+    Center,
+    /// Last pointer position, or the center before the pointer has moved
+    Cursor,
+}
+
+impl LodFocus {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "center" => Some(Self::Center),
+            "cursor" => Some(Self::Cursor),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Center => "center",
+            Self::Cursor => "cursor",
+        }
+    }
+}
+
+/// Ranking key; lower is more important. A boid at full speed counts as
+/// half as far away as a stationary one.
+fn importance_key(boid: &Boid, focus: Vector2D, max_speed: f32) -> f32 {
+    let normalized_speed = if max_speed > 0.0 {
+        (boid.velocity.magnitude() / max_speed).min(1.0)
+    } else {
+        0.0
+    };
+    boid.position.distance(&focus) / (1.0 + normalized_speed)
+}
+
+/// Per-boid flags marking the `budget` most important boids for full detail
+pub fn detail_mask(boids: &[Boid], focus: Vector2D, max_speed: f32, budget: usize) -> Vec<bool> {
+    if budget >= boids.len() {
+        return vec![true; boids.len()];
+    }
+
+    let mut mask = vec![false; boids.len()];
+    if budget == 0 {
+        return mask;
+    }
+
+    let mut ranked: Vec<(f32, usize)> = boids
+        .iter()
+        .enumerate()
+        .map(|(index, boid)| (importance_key(boid, focus, max_speed), index))
+        .collect();
+    // Only the split matters, so a linear-time partial sort is enough
+    ranked.select_nth_unstable_by(budget - 1, |a, b| a.0.total_cmp(&b.0));
+    for &(_, index) in &ranked[..budget] {
+        mask[index] = true;
+    }
+    mask
+}
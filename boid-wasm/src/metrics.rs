@@ -0,0 +1,82 @@
+//! Local session metrics for exhibit operators
+//!
+//! Off until the host page opts in. Everything stays in the page: the
+//! collector only counts, and the host decides whether to show the
+//! numbers or store them. No boid positions, hand data or anything else
+//! that could identify a visitor is kept.
+
+/// Presets applied without a name are counted under this one
+pub const UNNAMED_PRESET: &str = "custom";
+
+/// How a preset was used during the session
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetUse {
+    pub name: String,
+    pub count: u32,
+}
+
+/// Usage since the session started
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SessionMetrics {
+    pub session_secs: f64,
+    pub frames: u64,
+    /// Frames per second over the whole session
+    pub average_fps: f64,
+    /// Pointer presses and pinches started
+    pub interactions: u32,
+    /// In the order first used
+    pub presets: Vec<PresetUse>,
+}
+
+/// Counts what happens in a session; timestamps are milliseconds on any
+/// clock that only moves forward
+#[derive(Debug, Clone, Default)]
+pub struct MetricsCollector {
+    started_ms: f64,
+    frames: u64,
+    interactions: u32,
+    presets: Vec<PresetUse>,
+}
+
+impl MetricsCollector {
+    /// A session starting at `now_ms`
+    pub fn new(now_ms: f64) -> Self {
+        Self {
+            started_ms: now_ms,
+            ..Self::default()
+        }
+    }
+
+    pub fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn interactions(&mut self, count: u32) {
+        self.interactions += count;
+    }
+
+    pub fn preset(&mut self, name: &str) {
+        match self.presets.iter_mut().find(|used| used.name == name) {
+            Some(used) => used.count += 1,
+            None => self.presets.push(PresetUse {
+                name: name.to_string(),
+                count: 1,
+            }),
+        }
+    }
+
+    pub fn snapshot(&self, now_ms: f64) -> SessionMetrics {
+        let session_secs = ((now_ms - self.started_ms) / 1000.0).max(0.0);
+        SessionMetrics {
+            session_secs,
+            frames: self.frames,
+            average_fps: if session_secs > 0.0 {
+                self.frames as f64 / session_secs
+            } else {
+                0.0
+            },
+            interactions: self.interactions,
+            presets: self.presets.clone(),
+        }
+    }
+}
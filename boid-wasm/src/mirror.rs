@@ -0,0 +1,113 @@
+//! Mirror mode: draw the ESP32's flock instead of simulating one
+//!
+//! The page fetches `GET /api/flock` from the device (or takes the same
+//! frame off a WebSocket) and hands each answer in. Frames come in slower
+//! and less evenly than the page draws, so boids glide from the frame before
+//! the latest to the latest over one frame interval. What is drawn trails
+//! the device by about that interval, the price of never guessing ahead.
+//!
+//! A boid that wrapped around an edge between two frames is put straight
+//! down where it arrived; interpolating would sweep it across the screen.
+
+use boid_core::Vector2D;
+use boid_shared::mirror::{BoidState, FlockFrame};
+
+/// A remote boid scaled to the page's world
+#[derive(Debug, Clone, Copy)]
+pub struct MirroredBoid {
+    pub position: Vector2D,
+    pub velocity: Vector2D,
+    pub group: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Received {
+    frame: FlockFrame,
+    at_ms: f64,
+}
+
+/// The last two frames received from the device
+#[derive(Debug, Clone, Default)]
+pub struct RemoteFlock {
+    previous: Option<Received>,
+    latest: Option<Received>,
+}
+
+impl RemoteFlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take `frame`, received at `now_ms`. False for the frame already held,
+    /// as when the page polls faster than the device simulates.
+    pub fn receive(&mut self, frame: FlockFrame, now_ms: f64) -> bool {
+        if self
+            .latest
+            .as_ref()
+            .is_some_and(|latest| latest.frame.frame == frame.frame)
+        {
+            return false;
+        }
+        self.previous = self.latest.replace(Received {
+            frame,
+            at_ms: now_ms,
+        });
+        true
+    }
+
+    /// Frame number of the latest frame
+    pub fn latest_frame(&self) -> Option<u32> {
+        self.latest.as_ref().map(|latest| latest.frame.frame)
+    }
+
+    /// Every boid at `now_ms`, scaled from the device's field to
+    /// `width` x `height`. Empty until a frame arrived.
+    pub fn sample(&self, now_ms: f64, width: f32, height: f32) -> Vec<MirroredBoid> {
+        let Some(latest) = &self.latest else {
+            return Vec::new();
+        };
+        let field = &latest.frame;
+        let ratio = |to: f32, from: f32| if from > 0.0 { to / from } else { 1.0 };
+        let scale = Vector2D::new(ratio(width, field.width), ratio(height, field.height));
+        let scaled = |boid: &BoidState| MirroredBoid {
+            position: Vector2D::new(boid.x * scale.x, boid.y * scale.y),
+            velocity: Vector2D::new(boid.vx * scale.x, boid.vy * scale.y),
+            group: boid.group,
+        };
+
+        // Only frames of the same flock on the same field line up boid by boid
+        let previous = self.previous.as_ref().filter(|previous| {
+            previous.frame.boids.len() == field.boids.len()
+                && previous.frame.width == field.width
+                && previous.frame.height == field.height
+        });
+        let Some(previous) = previous else {
+            return field.boids.iter().map(scaled).collect();
+        };
+        let interval = latest.at_ms - previous.at_ms;
+        let t = if interval > 0.0 {
+            ((now_ms - latest.at_ms) / interval).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+
+        previous
+            .frame
+            .boids
+            .iter()
+            .zip(&field.boids)
+            .map(|(from, to)| {
+                let (from, to) = (scaled(from), scaled(to));
+                let jump = to.position - from.position;
+                if jump.x.abs() > width / 2.0 || jump.y.abs() > height / 2.0 {
+                    return to;
+                }
+                MirroredBoid {
+                    position: from.position + jump * t,
+                    velocity: from.velocity + (to.velocity - from.velocity) * t,
+                    group: to.group,
+                }
+            })
+            .collect()
+    }
+}
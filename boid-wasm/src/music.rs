@@ -0,0 +1,196 @@
+//! Audio-reactive flocking: the flock dances to music
+//!
+//! Each frame JS hands over a Web Audio `AnalyserNode`'s frequency data
+//! (`getFloatFrequencyData`, in decibels). The energy of the bass and
+//! treble bands is smoothed and mapped through [`Curve`]s: bass tightens or
+//! loosens the flock through cohesion, treble speeds it up. A beat, bass
+//! jumping well above its recent level, pushes the boids out from the
+//! flock's centroid for a few ticks.
+
+use boid_core::step::Stimulus;
+use boid_core::{Boid, ConfigModifiers, Vector2D};
+
+/// Frequency bands, in Hz
+const BASS: (f32, f32) = (20.0, 250.0);
+const TREBLE: (f32, f32) = (4000.0, 16000.0);
+/// Analyser levels read as silence and as full energy; the analyser's own
+/// `minDecibels` and `maxDecibels` defaults
+const MIN_DB: f32 = -100.0;
+const MAX_DB: f32 = -30.0;
+/// Share of the way a band's level moves toward a louder or quieter frame
+const ATTACK: f32 = 0.5;
+const RELEASE: f32 = 0.1;
+/// How fast the bass average behind beat detection follows the music
+const AVERAGE_RATE: f32 = 0.05;
+/// Bass this many times its average, and at least `BEAT_FLOOR`, is a beat
+const BEAT_RATIO: f32 = 1.3;
+const BEAT_FLOOR: f32 = 0.2;
+/// Ticks after a beat before the next can be heard
+const BEAT_COOLDOWN: u32 = 12;
+/// Ticks an impulse keeps pushing, fading out
+const IMPULSE_TICKS: u32 = 8;
+
+/// Maps a band's energy, 0 to 1, to `low + (high - low) * energy^exponent`.
+/// Exponents above 1 react only to loud passages, below 1 to quiet ones too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub low: f32,
+    pub high: f32,
+    pub exponent: f32,
+}
+
+impl Curve {
+    pub const fn new(low: f32, high: f32, exponent: f32) -> Self {
+        Self {
+            low,
+            high,
+            exponent,
+        }
+    }
+
+    pub fn at(&self, energy: f32) -> f32 {
+        let shaped = energy.clamp(0.0, 1.0).powf(self.exponent.max(0.0));
+        self.low + (self.high - self.low) * shaped
+    }
+}
+
+/// Parameters the music drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapping {
+    /// Bass to the cohesion multiplier
+    Cohesion,
+    /// Treble to the max speed multiplier
+    MaxSpeed,
+    /// Beat strength to the impulse radius, as a fraction of the shorter
+    /// side of the area
+    Impulse,
+}
+
+impl Mapping {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cohesion" => Some(Self::Cohesion),
+            "max_speed" => Some(Self::MaxSpeed),
+            "impulse" => Some(Self::Impulse),
+            _ => None,
+        }
+    }
+}
+
+/// Turns analyser frames into config modifiers and impulses
+pub struct MusicDriver {
+    pub cohesion: Curve,
+    pub max_speed: Curve,
+    pub impulse: Curve,
+    bass: f32,
+    treble: f32,
+    bass_average: f32,
+    cooldown: u32,
+    /// Strength of the current impulse and the ticks it has left
+    beat: f32,
+    impulse_ticks: u32,
+}
+
+impl Default for MusicDriver {
+    fn default() -> Self {
+        Self {
+            cohesion: Curve::new(0.5, 2.5, 1.5),
+            max_speed: Curve::new(0.8, 2.0, 1.0),
+            impulse: Curve::new(0.2, 0.6, 1.0),
+            bass: 0.0,
+            treble: 0.0,
+            bass_average: 0.0,
+            cooldown: 0,
+            beat: 0.0,
+            impulse_ticks: 0,
+        }
+    }
+}
+
+impl MusicDriver {
+    pub fn curve_mut(&mut self, mapping: Mapping) -> &mut Curve {
+        match mapping {
+            Mapping::Cohesion => &mut self.cohesion,
+            Mapping::MaxSpeed => &mut self.max_speed,
+            Mapping::Impulse => &mut self.impulse,
+        }
+    }
+
+    /// Forget the music heard so far; the curves stay
+    pub fn reset(&mut self) {
+        *self = Self {
+            cohesion: self.cohesion,
+            max_speed: self.max_speed,
+            impulse: self.impulse,
+            ..Self::default()
+        };
+    }
+
+    /// Smoothed bass and treble energy, 0 to 1
+    pub fn levels(&self) -> (f32, f32) {
+        (self.bass, self.treble)
+    }
+
+    /// Take one analyser frame: `bins` decibel levels from 0 Hz up to half
+    /// of `sample_rate`
+    pub fn feed(&mut self, bins: &[f32], sample_rate: f32) {
+        let bass = band_energy(bins, sample_rate, BASS);
+        let treble = band_energy(bins, sample_rate, TREBLE);
+        self.bass = follow(self.bass, bass);
+        self.treble = follow(self.treble, treble);
+
+        self.cooldown = self.cooldown.saturating_sub(1);
+        let threshold = (self.bass_average * BEAT_RATIO).max(BEAT_FLOOR);
+        if self.cooldown == 0 && bass > threshold {
+            self.beat = ((bass - self.bass_average) / self.bass_average.max(BEAT_FLOOR)).min(1.0);
+            self.impulse_ticks = IMPULSE_TICKS;
+            self.cooldown = BEAT_COOLDOWN;
+        }
+        self.bass_average += (bass - self.bass_average) * AVERAGE_RATE;
+    }
+
+    /// Scale cohesion and max speed in `modifiers` by the music
+    pub fn modulate(&self, modifiers: &mut ConfigModifiers) {
+        modifiers.cohesion_weight *= self.cohesion.at(self.bass);
+        modifiers.max_speed *= self.max_speed.at(self.treble);
+    }
+
+    /// The push of the latest beat for the coming tick, if it still lasts
+    pub fn impulse(&mut self, boids: &[Boid], width: f32, height: f32) -> Option<Stimulus> {
+        if self.impulse_ticks == 0 || boids.is_empty() {
+            return None;
+        }
+        let fade = self.impulse_ticks as f32 / IMPULSE_TICKS as f32;
+        self.impulse_ticks -= 1;
+        let centroid = boids
+            .iter()
+            .fold(Vector2D::zero(), |sum, boid| sum + boid.position)
+            / boids.len() as f32;
+        Some(Stimulus::Repel {
+            position: centroid,
+            radius: self.impulse.at(self.beat) * width.min(height) * fade,
+        })
+    }
+}
+
+/// Mean level of the bins within `band`, 0 (at `MIN_DB`) to 1 (at `MAX_DB`)
+fn band_energy(bins: &[f32], sample_rate: f32, (low, high): (f32, f32)) -> f32 {
+    if bins.is_empty() || sample_rate <= 0.0 {
+        return 0.0;
+    }
+    let hz_per_bin = sample_rate / 2.0 / bins.len() as f32;
+    let first = ((low / hz_per_bin) as usize).min(bins.len() - 1);
+    let last = ((high / hz_per_bin).ceil() as usize).clamp(first + 1, bins.len());
+    let band = &bins[first..last];
+    let sum: f32 = band
+        .iter()
+        // Silent bins come as -Infinity
+        .map(|&db| ((db - MIN_DB) / (MAX_DB - MIN_DB)).clamp(0.0, 1.0))
+        .sum();
+    sum / band.len() as f32
+}
+
+fn follow(level: f32, target: f32) -> f32 {
+    let rate = if target > level { ATTACK } else { RELEASE };
+    level + (target - level) * rate
+}
@@ -0,0 +1,78 @@
+//! Sprite-sheet rendering support
+//!
+//! A sheet is a grid of equally sized frames: columns are animation frames,
+//! rows are heading buckets spread evenly around the circle, starting at
+//! "facing right" and turning clockwise in canvas coordinates. Sheets with a
+//! single row are rotated on the canvas instead.
+
+use web_sys::HtmlImageElement;
+
+/// Whether boids are drawn as shapes or from the sprite sheet
+///
+/// Not to be confused with `boid_shared::style::RenderStyle`, the size,
+/// palette and outline of boids drawn as shapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteMode {
+    /// Shapes, in the boid style
+    #[default]
+    Triangle,
+    /// Frames from the loaded sprite sheet; triangles until it has loaded
+    Sprite,
+}
+
+impl SpriteMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "triangle" => Some(Self::Triangle),
+            "sprite" => Some(Self::Sprite),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Triangle => "triangle",
+            Self::Sprite => "sprite",
+        }
+    }
+}
+
+pub struct SpriteAtlas {
+    pub image: HtmlImageElement,
+    pub frame_width: f64,
+    pub frame_height: f64,
+    /// Animation frames per row
+    pub frames: u32,
+    /// Number of rows, one per heading bucket
+    pub headings: u32,
+}
+
+impl SpriteAtlas {
+    /// Whether the image has finished loading and can be drawn
+    pub fn is_ready(&self) -> bool {
+        self.image.complete() && self.image.natural_width() > 0
+    }
+
+    /// Source rectangle origin of the frame for `phase` and `heading`
+    pub fn frame_origin(&self, phase: f32, heading: f64) -> (f64, f64) {
+        let column = frame_index(phase, self.frames);
+        let row = heading_bucket(heading, self.headings);
+        (
+            column as f64 * self.frame_width,
+            row as f64 * self.frame_height,
+        )
+    }
+}
+
+/// Animation frame for a phase in [0, 1)
+pub fn frame_index(phase: f32, frames: u32) -> u32 {
+    let frames = frames.max(1);
+    ((phase.rem_euclid(1.0) * frames as f32) as u32).min(frames - 1)
+}
+
+/// Nearest of `buckets` evenly spaced headings to `angle` (radians)
+pub fn heading_bucket(angle: f64, buckets: u32) -> u32 {
+    let buckets = buckets.max(1);
+    let step = std::f64::consts::TAU / buckets as f64;
+    (angle.rem_euclid(std::f64::consts::TAU) / step).round() as u32 % buckets
+}
@@ -0,0 +1,61 @@
+// Keep the flock clear of page elements laid over its canvas
+//
+// On a site with the flock behind its content, boids passing under a
+// headline or a button hide it. `trackExclusions` measures the elements,
+// hands their rectangles to `set_exclusion_rects`, and measures them again
+// whenever the canvas or the elements change size, the window is resized
+// or the page scrolls, so the flock keeps flowing around them.
+//
+//     import { trackExclusions } from './exclusions.js';
+//     const stop = trackExclusions(simulation, canvas, 'h1, .hero button');
+//
+// `elements` is a CSS selector or a list of elements. `padding` keeps boids
+// that many CSS pixels further out; `weight` scales the push. The returned
+// function stops tracking and clears the rectangles.
+export function trackExclusions(simulation, canvas, elements, { weight = 3, padding = 8 } = {}) {
+    const targets = typeof elements === 'string'
+        ? Array.from(document.querySelectorAll(elements))
+        : Array.from(elements);
+
+    const measure = () => {
+        const origin = canvas.getBoundingClientRect();
+        const rects = [];
+        for (const element of targets) {
+            const box = element.getBoundingClientRect();
+            // Hidden elements have no box to avoid
+            if (box.width === 0 || box.height === 0) continue;
+            rects.push(
+                box.left - origin.left - padding,
+                box.top - origin.top - padding,
+                box.width + 2 * padding,
+                box.height + 2 * padding,
+            );
+        }
+        simulation.set_exclusion_rects(new Float32Array(rects), weight);
+    };
+
+    // Layout changes come in bursts; measure once per frame at most
+    let scheduled = false;
+    const schedule = () => {
+        if (scheduled) return;
+        scheduled = true;
+        requestAnimationFrame(() => {
+            scheduled = false;
+            measure();
+        });
+    };
+
+    const observer = new ResizeObserver(schedule);
+    observer.observe(canvas);
+    targets.forEach((element) => observer.observe(element));
+    window.addEventListener('resize', schedule);
+    window.addEventListener('scroll', schedule, { passive: true });
+    measure();
+
+    return () => {
+        observer.disconnect();
+        window.removeEventListener('resize', schedule);
+        window.removeEventListener('scroll', schedule);
+        simulation.clear_exclusion_rects();
+    };
+}
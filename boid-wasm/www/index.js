@@ -8,6 +8,12 @@ let fps = 60;
 let webcamRunning = false;
 let tempCanvas = null;
 let tempContext = null;
+// Microphone analyser feeding the audio-reactive mode
+let musicAnalyser = null;
+let musicBins = null;
+let musicStream = null;
+// How often a mirrored device is asked for its flock; the ESP32 runs at 30 fps
+const MIRROR_POLL_MS = 50;
 
 async function enableWebcam() {
     try {
@@ -77,6 +83,8 @@ async function run() {
 
         // Create simulation
         simulation = new BoidSimulation('canvas', width, height, 50);
+        // Sharp boids on dense screens; everything else stays in CSS pixels
+        simulation.set_pixel_ratio(window.devicePixelRatio || 1);
 
         // Expose simulation for testing
         window.simulation = simulation;
@@ -93,6 +101,12 @@ async function run() {
         // Start animation loop
         animate();
 
+        // `?mirror=http://<device>` draws that ESP32's flock instead
+        const mirrorUrl = new URLSearchParams(window.location.search).get('mirror');
+        if (mirrorUrl) {
+            startMirror(mirrorUrl);
+        }
+
         console.log('Boid simulation initialized successfully!');
 
         // Initialize hand tracking (non-blocking, optional feature)
@@ -111,6 +125,34 @@ async function run() {
     }
 }
 
+// Poll the device's flock and hand every frame to the simulation, which
+// glides between them. A failed poll is retried on the next tick.
+function startMirror(deviceUrl) {
+    const url = new URL('/api/flock', deviceUrl);
+    simulation.set_mirror_mode(true);
+    let polling = false;
+    setInterval(async () => {
+        if (polling) {
+            return;
+        }
+        polling = true;
+        try {
+            const response = await fetch(url, {
+                headers: { Accept: 'application/octet-stream' },
+            });
+            if (response.ok) {
+                const bytes = new Uint8Array(await response.arrayBuffer());
+                simulation.apply_remote_flock(bytes);
+            }
+        } catch (error) {
+            console.warn('Mirror poll failed:', error);
+        } finally {
+            polling = false;
+        }
+    }, MIRROR_POLL_MS);
+    console.log(`Mirroring the flock of ${url.origin}`);
+}
+
 async function initializeHandTracking() {
     try {
         // Check if webcam is available
@@ -127,7 +169,10 @@ async function initializeHandTracking() {
         if (webcamReady) {
             // Set video element in simulation
             simulation.set_video_element('webcam');
-            console.log('OpenCV hand tracking enabled!');
+            // This page ships no MediaPipe or OpenCV.js glue, so unless
+            // they are loaded the built-in skin detector tracks the hand
+            const backend = simulation.detect_hand_backends();
+            console.log(`Hand tracking enabled with the ${backend} backend`);
         } else {
             console.log('Webcam not available, hand tracking disabled');
         }
@@ -145,7 +190,9 @@ function setupControls() {
         { id: 'speed', valueId: 'speed-value', setter: (v) => simulation.set_max_speed(v) },
         { id: 'force', valueId: 'force-value', setter: (v) => simulation.set_max_force(v) },
         { id: 'seek', valueId: 'seek-value', setter: (v) => simulation.set_seek_weight(v) },
+        { id: 'seek-radius', valueId: 'seek-radius-value', setter: (v) => simulation.set_seek_radius(v) },
         { id: 'wander-radius', valueId: 'wander-radius-value', setter: (v) => simulation.set_wander_radius(v) },
+        { id: 'boid-size', valueId: 'boid-size-value', setter: (v) => simulation.set_boid_size(v) },
     ];
 
     controls.forEach(({ id, valueId, setter }) => {
@@ -159,11 +206,119 @@ function setupControls() {
         });
     });
 
+    // Set up pointer mode selector
+    const pointerMode = document.getElementById('pointer-mode');
+    pointerMode.addEventListener('change', (e) => {
+        simulation.set_pointer_mode(e.target.value);
+    });
+
+    // Color-blind safe palettes tell sub-flocks apart by shape as well
+    const colorScheme = document.getElementById('color-scheme');
+    colorScheme.addEventListener('change', (e) => {
+        simulation.set_color_scheme(e.target.value);
+    });
+
+    const boidShape = document.getElementById('boid-shape');
+    boidShape.addEventListener('change', (e) => {
+        simulation.set_boid_shape(e.target.value);
+    });
+
+    // Split the flock into independently tinted sub-flocks
+    const subFlocks = document.getElementById('sub-flocks');
+    subFlocks.addEventListener('change', (e) => {
+        simulation.split_sub_flocks(parseInt(e.target.value, 10));
+    });
+
     // Set up wander enabled checkbox
     const wanderEnabled = document.getElementById('wander-enabled');
     wanderEnabled.addEventListener('change', (e) => {
         simulation.set_wander_enabled(e.target.checked);
     });
+
+    // Let boids land on the bottom edge and rest now and then
+    const perchEnabled = document.getElementById('perch-enabled');
+    perchEnabled.addEventListener('change', (e) => {
+        simulation.set_perching_enabled(e.target.checked);
+    });
+
+    // Let the camera chase the flock, zoomed to fit it
+    const followFlock = document.getElementById('follow-flock');
+    followFlock.addEventListener('change', (e) => {
+        if (e.target.checked) {
+            simulation.set_camera_zoom_to_fit(true);
+            simulation.set_camera_follow('flock');
+        } else {
+            simulation.reset_camera();
+        }
+    });
+
+    // Set up demo mode checkbox; `?demo` in the URL starts it right away
+    const demoEnabled = document.getElementById('demo-enabled');
+    demoEnabled.addEventListener('change', (e) => {
+        if (e.target.checked) {
+            simulation.start_demo_mode();
+        } else {
+            simulation.stop_demo_mode();
+        }
+    });
+    if (new URLSearchParams(window.location.search).has('demo')) {
+        demoEnabled.checked = true;
+        simulation.start_demo_mode();
+    }
+
+    // Sound has to start from a click, which this checkbox provides
+    const soundEnabled = document.getElementById('sound-enabled');
+    soundEnabled.addEventListener('change', (e) => {
+        try {
+            simulation.set_sound_enabled(e.target.checked);
+        } catch (error) {
+            console.error(error);
+            e.target.checked = false;
+        }
+    });
+    const soundVolume = document.getElementById('sound-volume');
+    const soundVolumeValue = document.getElementById('sound-volume-value');
+    soundVolume.addEventListener('input', (e) => {
+        const volume = parseFloat(e.target.value);
+        simulation.set_sound_volume(volume);
+        soundVolumeValue.textContent = volume.toFixed(2);
+    });
+
+    const musicEnabled = document.getElementById('music-enabled');
+    musicEnabled.addEventListener('change', async (e) => {
+        if (!e.target.checked) {
+            stopMusic();
+            return;
+        }
+        try {
+            await startMusic();
+        } catch (error) {
+            console.error('Microphone unavailable:', error);
+            e.target.checked = false;
+        }
+    });
+}
+
+async function startMusic() {
+    musicStream = await navigator.mediaDevices.getUserMedia({ audio: true });
+    const context = new AudioContext();
+    musicAnalyser = context.createAnalyser();
+    musicAnalyser.fftSize = 2048;
+    context.createMediaStreamSource(musicStream).connect(musicAnalyser);
+    musicBins = new Float32Array(musicAnalyser.frequencyBinCount);
+    simulation.set_audio_reactive(true);
+}
+
+function stopMusic() {
+    simulation.set_audio_reactive(false);
+    if (musicAnalyser) {
+        musicAnalyser.context.close();
+        musicAnalyser = null;
+    }
+    if (musicStream) {
+        musicStream.getTracks().forEach((track) => track.stop());
+        musicStream = null;
+    }
 }
 
 function setupEventListeners(canvas) {
@@ -177,13 +332,37 @@ function setupEventListeners(canvas) {
     }
 
     // Mouse events for pointer tracking
+    let pressedAt = null;
     canvas.addEventListener('mousedown', (e) => {
-        if (simulation) {
+        if (simulation && e.button === 0) {
             const coords = getCanvasCoords(e);
+            pressedAt = coords;
             simulation.handle_pointer_down(coords.x, coords.y);
         }
     });
 
+    // A click that did not drag spawns a boid at the cursor
+    canvas.addEventListener('click', (e) => {
+        const coords = getCanvasCoords(e);
+        if (simulation && pressedAt &&
+            Math.hypot(coords.x - pressedAt.x, coords.y - pressedAt.y) < 4) {
+            const angle = Math.random() * 2 * Math.PI;
+            const [x, y] = simulation.screen_to_world(coords.x, coords.y);
+            simulation.spawn_boid(x, y, Math.cos(angle), Math.sin(angle));
+        }
+        pressedAt = null;
+    });
+
+    // Right-click removes the nearest boid
+    canvas.addEventListener('contextmenu', (e) => {
+        e.preventDefault();
+        if (simulation) {
+            const coords = getCanvasCoords(e);
+            const [x, y] = simulation.screen_to_world(coords.x, coords.y);
+            simulation.remove_nearest_boid(x, y, 30);
+        }
+    });
+
     canvas.addEventListener('mousemove', (e) => {
         if (simulation) {
             const coords = getCanvasCoords(e);
@@ -246,6 +425,8 @@ function setupEventListeners(canvas) {
             const width = Math.min(window.innerWidth - 80, 1160);
             const height = Math.min(600, window.innerHeight - 400);
             simulation.resize(width, height);
+            // Moving to another screen or zooming changes the ratio
+            simulation.set_pixel_ratio(window.devicePixelRatio || 1);
         }
     });
 }
@@ -257,7 +438,7 @@ function animate() {
     const deltaTime = currentTime - lastTime;
 
     // Process hand detection if webcam is running
-    if (webcamRunning && tempCanvas && tempContext) {
+    if (webcamRunning && tempCanvas && tempContext && simulation.get_hand_backend() === 'skin') {
         const video = document.getElementById('webcam');
 
         if (video.readyState === video.HAVE_ENOUGH_DATA) {
@@ -268,7 +449,7 @@ function animate() {
                 // Get ImageData from canvas
                 const imageData = tempContext.getImageData(0, 0, tempCanvas.width, tempCanvas.height);
 
-                // Process frame with OpenCV-based hand tracking in Rust
+                // Process frame with the built-in skin detector in Rust
                 const handDetected = simulation.process_video_frame(imageData);
 
                 if (handDetected) {
@@ -302,6 +483,11 @@ function animate() {
         }
     }
 
+    if (musicAnalyser) {
+        musicAnalyser.getFloatFrequencyData(musicBins);
+        simulation.feed_audio(musicBins, musicAnalyser.context.sampleRate);
+    }
+
     // Update simulation
     simulation.update();
 
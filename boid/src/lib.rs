@@ -0,0 +1,80 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Umbrella crate for the boid simulation
+//!
+//! Depend on this crate and pick the pieces with features instead of
+//! tracking the internal crates separately:
+//!
+//! - `std` (default): `FlockStd`, spatial grid, random flocks and the
+//!   std-only protocol types. Disable it for embedded builds.
+//! - `serde`: snapshot (de)serialization for boids, configs and flocks
+//! - `heuristic-detector`: skin-color hand detector (`detector`)
+//! - `wasm`: browser frontend (`wasm::BoidSimulation`)
+//!
+//! ```
+//! use boid::prelude::*;
+//!
+//! let mut flock = FlockStd::new(800.0, 600.0, 50);
+//! flock.update_with_target(Some(Vector2D::new(400.0, 300.0)));
+//! ```
+
+/// Flocking algorithm: vectors, boids, flocks and behaviors
+pub use boid_core as sim;
+
+/// Types shared between clients and servers (positions, landmarks, API messages)
+pub use boid_shared as shared;
+
+#[cfg(feature = "heuristic-detector")]
+/// Skin-color hand detector producing `HandLandmarks` from raw frames
+pub use boid_hand_detector as detector;
+
+#[cfg(feature = "wasm")]
+/// Browser frontend exposed to JavaScript through wasm-bindgen
+pub use boid_wasm as wasm;
+
+/// The types most programs need, for glob import
+pub mod prelude {
+    pub use boid_core::{
+        BehaviorList, Boid, Boid3D, BoidConfig, BoundaryBehavior, BoundsMode, ConfigModifiers,
+        Flock, Flock3D, FlockContext, PopulationLimit, SteeringBehavior, TargetMode,
+        TargetOverride, Vector2D, Vector3D,
+    };
+    #[cfg(feature = "std")]
+    pub use boid_core::{BehaviorPipeline, FlockStd, TickTiming};
+
+    pub use boid_shared::{
+        BoidSettings, FullHandLandmarks, HandGesture, HandLandmarks, Handedness, LandmarkSmoother,
+        LensCalibration, PinchStateMachine, Position, TargetPositionUpdate,
+    };
+
+    #[cfg(feature = "heuristic-detector")]
+    pub use boid_hand_detector::HandDetector;
+
+    #[cfg(feature = "wasm")]
+    pub use boid_wasm::BoidSimulation;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+
+    #[test]
+    fn test_prelude_drives_fixed_flock() {
+        let mut flock = Flock::<4>::new(100.0, 100.0, BoidConfig::default());
+        for i in 0..4 {
+            let position = Vector2D::new(20.0 * i as f32, 50.0);
+            flock
+                .add_boid(Boid::new(position, Vector2D::new(1.0, 0.0)))
+                .unwrap();
+        }
+        flock.update();
+        assert_eq!(flock.boids.len(), 4);
+    }
+
+    #[test]
+    fn test_shared_types_reachable() {
+        let landmarks = HandLandmarks::new(Position::new(0.0, 0.0), Position::new(3.0, 4.0));
+        assert_eq!(landmarks.pinch_distance(), 5.0);
+        assert!(super::shared::LensCalibration::default().is_identity());
+    }
+}